@@ -0,0 +1,89 @@
+/*
+ * The fsm crate's state machine, for C and C++. Build it with
+ * `cargo build --release -p fsm-ffi` and link against the libfsm_ffi it makes.
+ *
+ * States are ids the engine gives its own meaning to. A machine's run by the hooks in its
+ * FsmHandler, each passed the handler's user_data; any hook left null is skipped. Nothing
+ * here is thread safe, and hooks mustn't call back into the machine running them.
+ */
+#ifndef FSM_H
+#define FSM_H
+
+#include <stdbool.h>
+#include <stddef.h>
+#include <stdint.h>
+
+#ifdef __cplusplus
+extern "C" {
+#endif
+
+typedef enum FsmTransitionKind {
+    /* Stay in the current state. */
+    FSM_NONE = 0,
+    /* End the current state and resume the one beneath it. */
+    FSM_POP = 1,
+    /* Pause the current state and push `state` on top of it. */
+    FSM_PUSH = 2,
+    /* Stop the current state and run `state` in its place, leaving those beneath it paused. */
+    FSM_SWITCH = 3,
+    /* End the current state and run `states` one after another. */
+    FSM_SEQUENCE = 4,
+    /* Stop every state, exiting the state machine. */
+    FSM_QUIT = 5,
+} FsmTransitionKind;
+
+/* `kind` is one of FsmTransitionKind; any other is made as FSM_NONE, and can be found with
+ * fsm_unknown_transition. `state` is only read for a push or a switch, and `states` and `len`
+ * only for a sequence, where `states` must stay valid until the hook returning it has
+ * returned. */
+typedef struct FsmTransition {
+    uint32_t kind;
+    uint32_t state;
+    const uint32_t *states;
+    size_t len;
+} FsmTransition;
+
+typedef void (*FsmHook)(uint32_t state, void *user_data);
+
+typedef struct FsmHandler {
+    void *user_data;
+    FsmHook on_start;
+    FsmHook on_stop;
+    FsmHook on_pause;
+    FsmHook on_resume;
+    FsmTransition (*update)(uint32_t state, void *user_data);
+    /* Returns true and fills in `transition` if the message was handled. */
+    bool (*on_message)(uint32_t state, void *user_data, const void *message,
+                       FsmTransition *transition);
+} FsmHandler;
+
+typedef struct FsmMachine FsmMachine;
+
+/* Creates a machine with no states, copying the handler. Returns NULL if it's NULL. */
+FsmMachine *fsm_create(const FsmHandler *handler);
+/* Frees a machine without stopping its states; call fsm_stop first for that. */
+void fsm_destroy(FsmMachine *machine);
+/* Pushes `state` and starts it, pausing the state that was running. */
+void fsm_push(FsmMachine *machine, uint32_t state);
+/* Stops the running state and resumes the one beneath it. */
+void fsm_pop(FsmMachine *machine);
+/* Updates the running state, making the transition its update hook returns. */
+void fsm_update(FsmMachine *machine);
+/* Sends a message to the running state, returning whether it was handled. */
+bool fsm_handle_message(FsmMachine *machine, const void *message);
+/* Stops every state on the stack, from the top down. */
+void fsm_stop(FsmMachine *machine);
+/* Whether the machine still has states on its stack. */
+bool fsm_is_running(const FsmMachine *machine);
+/* Writes the running state to `state` and returns true, or returns false if there isn't one. */
+bool fsm_current_state(const FsmMachine *machine, uint32_t *state);
+/* Writes the kind of the last transition a hook returned that isn't one of FsmTransitionKind
+ * to `kind` and returns true, if there's been one since this was last called, or returns
+ * false. */
+bool fsm_unknown_transition(const FsmMachine *machine, uint32_t *kind);
+
+#ifdef __cplusplus
+}
+#endif
+
+#endif /* FSM_H */
@@ -0,0 +1,554 @@
+//! Drives the state machine from C and C++, for embedding it in a game engine that isn't
+//! written in Rust. The functions here are declared in `include/fsm.h`.
+//!
+//! States are `uint32_t` ids the engine gives its own meaning to. The handler is an
+//! [`FsmHandler`] of function pointers, one for each [`Handler`] hook, and any that are null
+//! do what the trait's default does. Every hook is passed the handler's `user_data`, which
+//! stands in for the state data, and `on_message` is passed the message as an untyped pointer.
+//!
+//! A machine is created with [`fsm_create`], which copies the handler, and must be freed with
+//! [`fsm_destroy`]. Nothing here is thread safe: a machine must only be used from one thread
+//! at a time, and its hooks mustn't call back into it.
+//!
+//! Build the library with `cargo build --release -p fsm-ffi`, which makes `libfsm_ffi` as a
+//! shared library to link against.
+#![deny(missing_docs)]
+
+use fsm::{Handler, StateMachine, StateStack, StateTransition};
+use std::cell::Cell;
+use std::ffi::c_void;
+use std::slice;
+
+/// What [`FsmTransition`] to make, as its `kind`.
+#[repr(u32)]
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum FsmTransitionKind {
+    /// Stay in the current state.
+    None = 0,
+    /// End the current state and resume the one beneath it.
+    Pop = 1,
+    /// Pause the current state and push `state` on top of it.
+    Push = 2,
+    /// Stop the current state and run `state` in its place, leaving those beneath it paused.
+    Switch = 3,
+    /// End the current state and run `states` one after another.
+    Sequence = 4,
+    /// Stop every state, exiting the state machine.
+    Quit = 5,
+}
+
+impl FsmTransitionKind {
+    /// The kind numbered `kind`, if there is one.
+    pub fn from_u32(kind: u32) -> Option<Self> {
+        [
+            FsmTransitionKind::None,
+            FsmTransitionKind::Pop,
+            FsmTransitionKind::Push,
+            FsmTransitionKind::Switch,
+            FsmTransitionKind::Sequence,
+            FsmTransitionKind::Quit,
+        ]
+        .iter()
+        .copied()
+        .find(|&known| known as u32 == kind)
+    }
+}
+
+/// A [`StateTransition`] for a hook to return. `state` is only read for a push or a switch,
+/// and `states` and `len` only for a sequence, where `states` must stay valid until the hook
+/// has returned.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FsmTransition {
+    /// What to do: one of [`FsmTransitionKind`]. Any other kind is made as
+    /// [`FsmTransitionKind::None`], and can be found with [`fsm_unknown_transition`].
+    pub kind: u32,
+    /// The state to push or switch to.
+    pub state: u32,
+    /// The states to run in sequence.
+    pub states: *const u32,
+    /// How many `states` there are.
+    pub len: usize,
+}
+
+impl FsmTransition {
+    /// No transition, for a hook to fill in.
+    fn none() -> Self {
+        FsmTransition {
+            kind: FsmTransitionKind::None as u32,
+            state: 0,
+            states: std::ptr::null(),
+            len: 0,
+        }
+    }
+
+    /// Copies the transition out, reading the sequence's states if there are any. Returns the
+    /// kind as an error if it isn't one of [`FsmTransitionKind`].
+    ///
+    /// # Safety
+    /// For a sequence, `states` must point to `len` states, or be null.
+    unsafe fn to_transition(self) -> Result<StateTransition<u32>, u32> {
+        let kind = FsmTransitionKind::from_u32(self.kind).ok_or(self.kind)?;
+        Ok(match kind {
+            FsmTransitionKind::None => StateTransition::None,
+            FsmTransitionKind::Pop => StateTransition::Pop,
+            FsmTransitionKind::Push => StateTransition::Push(self.state),
+            FsmTransitionKind::Switch => StateTransition::Switch(self.state),
+            FsmTransitionKind::Sequence if self.states.is_null() || self.len == 0 => {
+                StateTransition::Sequence(vec![])
+            }
+            FsmTransitionKind::Sequence => {
+                StateTransition::Sequence(slice::from_raw_parts(self.states, self.len).to_vec())
+            }
+            FsmTransitionKind::Quit => StateTransition::Quit,
+        })
+    }
+}
+
+/// Called when a state starts, stops, pauses or resumes.
+pub type FsmHook = Option<unsafe extern "C" fn(state: u32, user_data: *mut c_void)>;
+
+/// The handler's hooks, as function pointers. Null hooks are skipped.
+#[repr(C)]
+#[derive(Clone, Copy, Debug)]
+pub struct FsmHandler {
+    /// Passed to every hook, for the engine's own state data.
+    pub user_data: *mut c_void,
+    /// Called when a state is first pushed on the stack.
+    pub on_start: FsmHook,
+    /// Called when a state is popped from the stack.
+    pub on_stop: FsmHook,
+    /// Called when a state is pushed over this one.
+    pub on_pause: FsmHook,
+    /// Called when the state on top of this one is popped.
+    pub on_resume: FsmHook,
+    /// Called on every update of the running state, returning the transition to make.
+    pub update: Option<unsafe extern "C" fn(state: u32, user_data: *mut c_void) -> FsmTransition>,
+    /// Called with a message for the running state. Returns true and fills in `transition`
+    /// if the message was handled, or returns false to ignore it.
+    pub on_message: Option<
+        unsafe extern "C" fn(
+            state: u32,
+            user_data: *mut c_void,
+            message: *const c_void,
+            transition: *mut FsmTransition,
+        ) -> bool,
+    >,
+}
+
+/// A machine's hooks, noting the kind of any transition they return that isn't one of
+/// [`FsmTransitionKind`].
+struct Hooks<'a> {
+    handler: &'a FsmHandler,
+    unknown: &'a Cell<Option<u32>>,
+}
+
+impl Hooks<'_> {
+    /// The transition a hook returned, or none if it's of a kind there isn't.
+    ///
+    /// # Safety
+    /// As for [`FsmTransition::to_transition`].
+    unsafe fn transition(&self, transition: FsmTransition) -> StateTransition<u32> {
+        transition.to_transition().unwrap_or_else(|kind| {
+            self.unknown.set(Some(kind));
+            StateTransition::None
+        })
+    }
+}
+
+/// Calls the hooks, with the user data as the state data and messages as untyped pointers.
+impl Handler<u32, *mut c_void, *const c_void> for Hooks<'_> {
+    fn on_start(&self, state: &u32, user_data: &mut *mut c_void) {
+        if let Some(hook) = self.handler.on_start {
+            unsafe { hook(*state, *user_data) }
+        }
+    }
+
+    fn on_stop(&self, state: &u32, user_data: &mut *mut c_void) {
+        if let Some(hook) = self.handler.on_stop {
+            unsafe { hook(*state, *user_data) }
+        }
+    }
+
+    fn on_pause(&self, state: &u32, user_data: &mut *mut c_void) {
+        if let Some(hook) = self.handler.on_pause {
+            unsafe { hook(*state, *user_data) }
+        }
+    }
+
+    fn on_resume(&self, state: &u32, user_data: &mut *mut c_void) {
+        if let Some(hook) = self.handler.on_resume {
+            unsafe { hook(*state, *user_data) }
+        }
+    }
+
+    fn update(&self, state: &u32, user_data: &mut *mut c_void) -> StateTransition<u32> {
+        match self.handler.update {
+            Some(hook) => unsafe { self.transition(hook(*state, *user_data)) },
+            None => StateTransition::None,
+        }
+    }
+
+    fn on_message(
+        &self,
+        state: &u32,
+        user_data: &mut *mut c_void,
+        message: &*const c_void,
+    ) -> Option<StateTransition<u32>> {
+        let hook = self.handler.on_message?;
+        let mut transition = FsmTransition::none();
+        unsafe {
+            if hook(*state, *user_data, *message, &mut transition) {
+                Some(self.transition(transition))
+            } else {
+                None
+            }
+        }
+    }
+}
+
+/// A state machine and its stack of states, behind an opaque pointer.
+pub struct FsmMachine {
+    handler: FsmHandler,
+    stack: StateStack<u32>,
+    /// The kind of the last transition a hook returned that there isn't, till it's asked for.
+    unknown: Cell<Option<u32>>,
+}
+
+impl FsmMachine {
+    /// Runs `f` with the machine's hooks, stack and user data, which the hooks are passed.
+    fn with<'a, R>(
+        &'a mut self,
+        f: impl FnOnce(&Hooks<'a>, &mut StateStack<u32>, &mut *mut c_void) -> R,
+    ) -> R {
+        let hooks = Hooks {
+            handler: &self.handler,
+            unknown: &self.unknown,
+        };
+        let mut user_data = self.handler.user_data;
+        f(&hooks, &mut self.stack, &mut user_data)
+    }
+}
+
+/// Creates a machine with no states, which is run by `handler`'s hooks. Returns null if
+/// `handler` is null.
+///
+/// # Safety
+/// `handler` must point to a valid [`FsmHandler`], or be null.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_create(handler: *const FsmHandler) -> *mut FsmMachine {
+    match handler.as_ref() {
+        Some(handler) => Box::into_raw(Box::new(FsmMachine {
+            handler: *handler,
+            stack: StateStack::new(),
+            unknown: Cell::new(None),
+        })),
+        None => std::ptr::null_mut(),
+    }
+}
+
+/// Frees a machine, without stopping its states; call [`fsm_stop`] first for that.
+///
+/// # Safety
+/// `machine` must have come from [`fsm_create`] and not been destroyed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_destroy(machine: *mut FsmMachine) {
+    if !machine.is_null() {
+        drop(Box::from_raw(machine));
+    }
+}
+
+/// Pushes `state` and starts it, pausing the state that was running.
+///
+/// # Safety
+/// `machine` must have come from [`fsm_create`] and not been destroyed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_push(machine: *mut FsmMachine, state: u32) {
+    if let Some(machine) = machine.as_mut() {
+        machine
+            .with(|handler, stack, user_data| StateMachine::push(handler, state, stack, user_data))
+    }
+}
+
+/// Stops the running state and resumes the one beneath it.
+///
+/// # Safety
+/// `machine` must have come from [`fsm_create`] and not been destroyed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_pop(machine: *mut FsmMachine) {
+    if let Some(machine) = machine.as_mut() {
+        machine.with(StateMachine::pop)
+    }
+}
+
+/// Updates the running state, making the transition its `update` hook returns.
+///
+/// # Safety
+/// `machine` must have come from [`fsm_create`] and not been destroyed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_update(machine: *mut FsmMachine) {
+    if let Some(machine) = machine.as_mut() {
+        machine.with(StateMachine::update)
+    }
+}
+
+/// Sends `message` to the running state's `on_message` hook, returning whether it was handled.
+///
+/// # Safety
+/// `machine` must have come from [`fsm_create`] and not been destroyed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_handle_message(
+    machine: *mut FsmMachine,
+    message: *const c_void,
+) -> bool {
+    match machine.as_mut() {
+        Some(machine) => machine.with(|handler, stack, user_data| {
+            StateMachine::handle_message(handler, stack, user_data, &message)
+        }),
+        None => false,
+    }
+}
+
+/// Stops every state on the stack, from the top down.
+///
+/// # Safety
+/// `machine` must have come from [`fsm_create`] and not been destroyed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_stop(machine: *mut FsmMachine) {
+    if let Some(machine) = machine.as_mut() {
+        machine.with(StateMachine::stop)
+    }
+}
+
+/// Whether the machine still has states on its stack.
+///
+/// # Safety
+/// `machine` must have come from [`fsm_create`] and not been destroyed, or be null.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_is_running(machine: *const FsmMachine) -> bool {
+    machine
+        .as_ref()
+        .is_some_and(|machine| StateMachine::is_running(&machine.stack))
+}
+
+/// Writes the running state to `state` and returns true, or returns false if there isn't one.
+///
+/// # Safety
+/// `machine` must have come from [`fsm_create`] and not been destroyed, or be null, and
+/// `state` must be valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_current_state(machine: *const FsmMachine, state: *mut u32) -> bool {
+    match machine.as_ref().and_then(|machine| machine.stack.last()) {
+        Some(&current) => {
+            *state = current;
+            true
+        }
+        None => false,
+    }
+}
+
+/// Writes the kind of the last transition a hook returned that isn't one of
+/// [`FsmTransitionKind`] to `kind` and returns true, if there's been one since this was last
+/// called, or returns false. Transitions of kinds there aren't are made as none.
+///
+/// # Safety
+/// `machine` must have come from [`fsm_create`] and not been destroyed, or be null, and
+/// `kind` must be valid to write to.
+#[no_mangle]
+pub unsafe extern "C" fn fsm_unknown_transition(
+    machine: *const FsmMachine,
+    kind: *mut u32,
+) -> bool {
+    match machine.as_ref().and_then(|machine| machine.unknown.take()) {
+        Some(unknown) => {
+            *kind = unknown;
+            true
+        }
+        None => false,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const DIG: u32 = 1;
+    const BANK: u32 = 2;
+    const SLEEP: u32 = 3;
+    /// Asks for a transition of a kind there isn't.
+    const HAUNTED: u32 = 4;
+
+    /// What the hooks have seen, as `(hook, state)`, and how much gold's been dug.
+    #[derive(Default)]
+    struct Log {
+        calls: Vec<(&'static str, u32)>,
+        gold: u32,
+    }
+
+    unsafe fn log(user_data: *mut c_void) -> &'static mut Log {
+        &mut *(user_data as *mut Log)
+    }
+
+    unsafe extern "C" fn on_start(state: u32, user_data: *mut c_void) {
+        log(user_data).calls.push(("start", state));
+    }
+
+    unsafe extern "C" fn on_stop(state: u32, user_data: *mut c_void) {
+        log(user_data).calls.push(("stop", state));
+    }
+
+    unsafe extern "C" fn on_pause(state: u32, user_data: *mut c_void) {
+        log(user_data).calls.push(("pause", state));
+    }
+
+    unsafe extern "C" fn on_resume(state: u32, user_data: *mut c_void) {
+        log(user_data).calls.push(("resume", state));
+    }
+
+    unsafe extern "C" fn update(state: u32, user_data: *mut c_void) -> FsmTransition {
+        static SEQUENCE: [u32; 2] = [BANK, SLEEP];
+        let log = log(user_data);
+        let mut transition = FsmTransition::none();
+        match state {
+            DIG => {
+                log.gold += 1;
+                if log.gold == 2 {
+                    transition.kind = FsmTransitionKind::Sequence as u32;
+                    transition.states = SEQUENCE.as_ptr();
+                    transition.len = SEQUENCE.len();
+                }
+            }
+            BANK => transition.kind = FsmTransitionKind::Pop as u32,
+            HAUNTED => transition.kind = 42,
+            _ => {}
+        }
+        transition
+    }
+
+    unsafe extern "C" fn on_message(
+        _state: u32,
+        _user_data: *mut c_void,
+        message: *const c_void,
+        transition: *mut FsmTransition,
+    ) -> bool {
+        if *(message as *const u32) == 0 {
+            return false;
+        }
+        (*transition).kind = FsmTransitionKind::Quit as u32;
+        true
+    }
+
+    fn handler(log: &mut Log) -> FsmHandler {
+        FsmHandler {
+            user_data: log as *mut Log as *mut c_void,
+            on_start: Some(on_start),
+            on_stop: Some(on_stop),
+            on_pause: Some(on_pause),
+            on_resume: Some(on_resume),
+            update: Some(update),
+            on_message: Some(on_message),
+        }
+    }
+
+    #[test]
+    fn hooks_drive_the_machine() {
+        let mut log = Log::default();
+        let handler = handler(&mut log);
+        unsafe {
+            let machine = fsm_create(&handler);
+            fsm_push(machine, DIG);
+            fsm_update(machine);
+            fsm_update(machine);
+            let mut state = 0;
+            assert!(fsm_current_state(machine, &mut state));
+            assert_eq!(state, BANK);
+            fsm_update(machine);
+            assert!(fsm_current_state(machine, &mut state));
+            assert_eq!(state, SLEEP);
+            fsm_destroy(machine);
+        }
+        assert_eq!(log.gold, 2);
+        assert_eq!(
+            log.calls,
+            [
+                ("start", DIG),
+                ("stop", DIG),
+                ("start", SLEEP),
+                ("pause", SLEEP),
+                ("start", BANK),
+                ("stop", BANK),
+                ("resume", SLEEP),
+            ]
+        );
+    }
+
+    #[test]
+    fn unknown_kinds_are_made_as_none_and_reported() {
+        let mut log = Log::default();
+        let handler = handler(&mut log);
+        unsafe {
+            let machine = fsm_create(&handler);
+            fsm_push(machine, HAUNTED);
+            let mut kind = 0;
+            assert!(!fsm_unknown_transition(machine, &mut kind));
+            fsm_update(machine);
+            let mut state = 0;
+            assert!(fsm_current_state(machine, &mut state));
+            assert_eq!(state, HAUNTED);
+            assert!(fsm_unknown_transition(machine, &mut kind));
+            assert_eq!(kind, 42);
+            // and only reported once
+            assert!(!fsm_unknown_transition(machine, &mut kind));
+            fsm_destroy(machine);
+        }
+    }
+
+    #[test]
+    fn messages_can_be_ignored_or_handled() {
+        let mut log = Log::default();
+        let handler = handler(&mut log);
+        unsafe {
+            let machine = fsm_create(&handler);
+            fsm_push(machine, SLEEP);
+            let (ignored, quit) = (0u32, 1u32);
+            assert!(!fsm_handle_message(
+                machine,
+                &ignored as *const u32 as *const c_void
+            ));
+            assert!(fsm_is_running(machine));
+            assert!(fsm_handle_message(
+                machine,
+                &quit as *const u32 as *const c_void
+            ));
+            assert!(!fsm_is_running(machine));
+            fsm_destroy(machine);
+        }
+        assert_eq!(log.calls, [("start", SLEEP), ("stop", SLEEP)]);
+    }
+
+    #[test]
+    fn null_hooks_and_machines_are_skipped() {
+        let handler = FsmHandler {
+            user_data: std::ptr::null_mut(),
+            on_start: None,
+            on_stop: None,
+            on_pause: None,
+            on_resume: None,
+            update: None,
+            on_message: None,
+        };
+        unsafe {
+            assert!(fsm_create(std::ptr::null()).is_null());
+            fsm_update(std::ptr::null_mut());
+            assert!(!fsm_is_running(std::ptr::null()));
+
+            let machine = fsm_create(&handler);
+            fsm_push(machine, DIG);
+            fsm_update(machine);
+            assert!(!fsm_handle_message(machine, std::ptr::null()));
+            fsm_pop(machine);
+            assert!(!fsm_is_running(machine));
+            fsm_destroy(machine);
+        }
+    }
+}
@@ -0,0 +1,102 @@
+/*
+ * A miner driven through fsm.h from C, printing each hook as it's called, for
+ * tests/header.rs to check.
+ */
+#include <fsm.h>
+#include <stdio.h>
+
+enum { DIG = 1, BANK = 2, SLEEP = 3, HAUNTED = 4 };
+
+static const uint32_t TO_BANK_THEN_SLEEP[] = {BANK, SLEEP};
+
+static void on_start(uint32_t state, void *user_data) {
+    (void)user_data;
+    printf("start %u\n", state);
+}
+
+static void on_stop(uint32_t state, void *user_data) {
+    (void)user_data;
+    printf("stop %u\n", state);
+}
+
+static void on_pause(uint32_t state, void *user_data) {
+    (void)user_data;
+    printf("pause %u\n", state);
+}
+
+static void on_resume(uint32_t state, void *user_data) {
+    (void)user_data;
+    printf("resume %u\n", state);
+}
+
+static FsmTransition update(uint32_t state, void *user_data) {
+    int *gold = user_data;
+    FsmTransition transition = {FSM_NONE, 0, NULL, 0};
+    switch (state) {
+    case DIG:
+        if (++*gold == 2) {
+            transition.kind = FSM_SEQUENCE;
+            transition.states = TO_BANK_THEN_SLEEP;
+            transition.len = 2;
+        }
+        break;
+    case BANK:
+        transition.kind = FSM_SWITCH;
+        transition.state = DIG;
+        break;
+    case HAUNTED:
+        transition.kind = 42;
+        break;
+    }
+    return transition;
+}
+
+static bool on_message(uint32_t state, void *user_data, const void *message,
+                       FsmTransition *transition) {
+    (void)state;
+    (void)user_data;
+    if (*(const int *)message == 0) {
+        return false;
+    }
+    transition->kind = FSM_QUIT;
+    return true;
+}
+
+static void print_current_state(const FsmMachine *machine) {
+    uint32_t state;
+    if (fsm_current_state(machine, &state)) {
+        printf("running %u\n", state);
+    } else {
+        printf("stopped\n");
+    }
+}
+
+int main(void) {
+    int gold = 0;
+    FsmHandler handler = {&gold,  on_start, on_stop, on_pause,
+                          on_resume, update, on_message};
+    FsmMachine *machine = fsm_create(&handler);
+    uint32_t kind;
+    int ignored = 0, quit = 1;
+
+    fsm_push(machine, DIG);
+    fsm_update(machine);
+    fsm_update(machine);
+    print_current_state(machine);
+    /* the bank switches back to digging, with sleep still paused beneath */
+    fsm_update(machine);
+    print_current_state(machine);
+
+    fsm_push(machine, HAUNTED);
+    fsm_update(machine);
+    print_current_state(machine);
+    if (fsm_unknown_transition(machine, &kind)) {
+        printf("unknown kind %u\n", kind);
+    }
+
+    printf("handled %d\n", fsm_handle_message(machine, &ignored));
+    printf("handled %d\n", fsm_handle_message(machine, &quit));
+    printf("running %d\n", fsm_is_running(machine));
+    fsm_destroy(machine);
+    return 0;
+}
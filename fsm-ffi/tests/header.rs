@@ -0,0 +1,63 @@
+//! Builds a C program against `include/fsm.h`, linked with the library, and runs it, checking
+//! the header declares what the library exports and that C gets the same out of it as Rust.
+//! It takes a C compiler: `$CC`, or `cc` without it.
+#![cfg(unix)]
+
+use std::env;
+use std::path::Path;
+use std::process::Command;
+
+/// The hooks the program's miner goes through, and what it's told.
+static EXPECTED: &str = "\
+start 1
+stop 1
+start 3
+pause 3
+start 2
+running 2
+stop 2
+start 1
+running 1
+pause 1
+start 4
+running 4
+unknown kind 42
+handled 0
+stop 4
+stop 1
+stop 3
+handled 1
+running 0
+";
+
+#[test]
+fn c_drives_the_machine_through_the_header() {
+    let manifest = Path::new(env!("CARGO_MANIFEST_DIR"));
+    // the library's built alongside the tests
+    let exe = env::current_exe().unwrap();
+    let libraries = exe.parent().unwrap();
+    let program = Path::new(env!("CARGO_TARGET_TMPDIR")).join("machine");
+    let compiler = env::var("CC").unwrap_or_else(|_| "cc".to_string());
+    let status = Command::new(&compiler)
+        .args(["-std=c99", "-Wall", "-Wextra", "-Werror", "-pedantic"])
+        .arg("-I")
+        .arg(manifest.join("include"))
+        .arg(manifest.join("tests/c/machine.c"))
+        .arg("-L")
+        .arg(libraries)
+        .arg(format!("-Wl,-rpath,{}", libraries.display()))
+        .arg("-lfsm_ffi")
+        .arg("-o")
+        .arg(&program)
+        .status()
+        .unwrap_or_else(|e| panic!("couldn't run {}: {}", compiler, e));
+    assert!(status.success(), "{} couldn't build the program", compiler);
+
+    let output = Command::new(&program).output().unwrap();
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert_eq!(String::from_utf8_lossy(&output.stdout), EXPECTED);
+}
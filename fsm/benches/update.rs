@@ -0,0 +1,269 @@
+//! Benchmarks for `StateMachine::update`, run with `cargo bench -p fsm`.
+//!
+//! Every agent is a miner working through the same little script, digging,
+//! banking, drinking and sleeping, with its states either an enum matched on
+//! by one handler or boxed trait objects the handler calls through. Throughput
+//! is reported in agent updates per second, so the cost of one update can be
+//! compared across the numbers of agents, and the allocations each update
+//! makes are counted and printed before each is timed.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use fsm::{Handler, StateMachine, StateStack, StateTransition};
+use std::alloc::{GlobalAlloc, Layout, System};
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+const AGENTS: &[usize] = &[1, 1_000, 100_000];
+/// How many rounds of updates the allocations are counted over, after as
+/// many to settle.
+const COUNTED_ROUNDS: usize = 100;
+
+/// The system allocator, counting how many times it's asked for memory.
+struct Counting;
+
+static ALLOCATIONS: AtomicUsize = AtomicUsize::new(0);
+
+unsafe impl GlobalAlloc for Counting {
+    unsafe fn alloc(&self, layout: Layout) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.alloc(layout)
+    }
+
+    unsafe fn dealloc(&self, ptr: *mut u8, layout: Layout) {
+        System.dealloc(ptr, layout)
+    }
+
+    unsafe fn realloc(&self, ptr: *mut u8, layout: Layout, new_size: usize) -> *mut u8 {
+        ALLOCATIONS.fetch_add(1, Ordering::Relaxed);
+        System.realloc(ptr, layout, new_size)
+    }
+}
+
+#[global_allocator]
+static ALLOCATOR: Counting = Counting;
+
+/// What a miner's carrying and how worn out he is.
+struct Miner {
+    gold: u8,
+    thirst: u8,
+    fatigue: u8,
+}
+
+impl Miner {
+    /// Miners start part way through the script, so they don't all change
+    /// state on the same update.
+    fn staggered(agent: usize) -> Self {
+        Miner {
+            gold: (agent % 3) as u8,
+            thirst: (agent % 5) as u8,
+            fatigue: 0,
+        }
+    }
+}
+
+#[derive(Clone, Copy, Debug)]
+enum Kind {
+    Dig,
+    Drink,
+    Bank,
+    Sleep,
+}
+
+/// The script each miner follows: dig until his pockets are full, stopping
+/// for a drink when thirsty, then bank the gold, sleeping every other trip.
+fn script(kind: Kind, miner: &mut Miner) -> StateTransition<Kind> {
+    match kind {
+        Kind::Dig => {
+            miner.gold += 1;
+            miner.thirst += 1;
+            if miner.thirst >= 5 {
+                StateTransition::Push(Kind::Drink)
+            } else if miner.gold >= 3 {
+                StateTransition::Switch(Kind::Bank)
+            } else {
+                StateTransition::None
+            }
+        }
+        Kind::Drink => {
+            miner.thirst = 0;
+            StateTransition::Pop
+        }
+        Kind::Bank => {
+            miner.gold = 0;
+            miner.fatigue += 1;
+            if miner.fatigue >= 2 {
+                StateTransition::Switch(Kind::Sleep)
+            } else {
+                StateTransition::Switch(Kind::Dig)
+            }
+        }
+        Kind::Sleep => {
+            miner.fatigue -= 1;
+            if miner.fatigue == 0 {
+                StateTransition::Switch(Kind::Dig)
+            } else {
+                StateTransition::None
+            }
+        }
+    }
+}
+
+/// States as an enum, matched on.
+struct Enumerated;
+
+impl Handler<Kind, Miner> for Enumerated {
+    fn update(&self, state: &Kind, miner: &mut Miner) -> StateTransition<Kind> {
+        script(*state, miner)
+    }
+}
+
+/// A state as a trait object, boxed up on every transition into it.
+trait Behaviour {
+    fn update(&self, miner: &mut Miner) -> StateTransition<Boxed>;
+    fn clone_boxed(&self) -> Boxed;
+}
+
+struct Boxed(Box<dyn Behaviour>);
+
+impl Clone for Boxed {
+    fn clone(&self) -> Self {
+        self.0.clone_boxed()
+    }
+}
+
+/// The script's transition, with the states it goes to boxed.
+fn boxed(transition: StateTransition<Kind>) -> StateTransition<Boxed> {
+    let state = |kind| -> Boxed {
+        match kind {
+            Kind::Dig => Dig::boxed(),
+            Kind::Drink => Drink::boxed(),
+            Kind::Bank => Bank::boxed(),
+            Kind::Sleep => Sleep::boxed(),
+        }
+    };
+    match transition {
+        StateTransition::None => StateTransition::None,
+        StateTransition::Pop => StateTransition::Pop,
+        StateTransition::Push(kind) => StateTransition::Push(state(kind)),
+        StateTransition::Switch(kind) => StateTransition::Switch(state(kind)),
+        StateTransition::Sequence(kinds) => {
+            StateTransition::Sequence(kinds.into_iter().map(state).collect())
+        }
+        StateTransition::Quit => StateTransition::Quit,
+    }
+}
+
+macro_rules! behaviours {
+    ($($state:ident),*) => {
+        $(
+            /// Knowing which state it is, as boxed states have data of
+            /// their own, or boxing them wouldn't allocate.
+            struct $state {
+                kind: Kind,
+            }
+
+            impl $state {
+                fn boxed() -> Boxed {
+                    Boxed(Box::new($state { kind: Kind::$state }))
+                }
+            }
+
+            impl Behaviour for $state {
+                fn update(&self, miner: &mut Miner) -> StateTransition<Boxed> {
+                    boxed(script(self.kind, miner))
+                }
+
+                fn clone_boxed(&self) -> Boxed {
+                    $state::boxed()
+                }
+            }
+        )*
+    };
+}
+
+behaviours!(Dig, Drink, Bank, Sleep);
+
+/// Boxed states, called through.
+struct Dynamic;
+
+impl Handler<Boxed, Miner> for Dynamic {
+    fn update(&self, state: &Boxed, miner: &mut Miner) -> StateTransition<Boxed> {
+        state.0.update(miner)
+    }
+}
+
+/// `agents` miners, each started digging.
+fn town<S: Clone, H: Handler<S, Miner>>(
+    handler: &H,
+    agents: usize,
+    dig: impl Fn() -> S,
+) -> Vec<(StateStack<S>, Miner)> {
+    (0..agents)
+        .map(|agent| {
+            let mut state_stack = StateStack::new();
+            let mut miner = Miner::staggered(agent);
+            StateMachine::push(handler, dig(), &mut state_stack, &mut miner);
+            (state_stack, miner)
+        })
+        .collect()
+}
+
+/// Updates every agent once.
+fn round<S: Clone, H: Handler<S, Miner>>(handler: &H, town: &mut [(StateStack<S>, Miner)]) {
+    for (state_stack, miner) in town.iter_mut() {
+        StateMachine::update(handler, state_stack, miner);
+    }
+}
+
+/// The allocations an agent's update makes on average, once its stack has
+/// grown as deep as it goes.
+fn allocations_per_update<S: Clone, H: Handler<S, Miner>>(
+    handler: &H,
+    town: &mut [(StateStack<S>, Miner)],
+) -> f64 {
+    for _ in 0..COUNTED_ROUNDS {
+        round(handler, town);
+    }
+    let before = ALLOCATIONS.load(Ordering::Relaxed);
+    for _ in 0..COUNTED_ROUNDS {
+        round(handler, town);
+    }
+    let allocations = ALLOCATIONS.load(Ordering::Relaxed) - before;
+    allocations as f64 / (COUNTED_ROUNDS * town.len()) as f64
+}
+
+fn bench_dispatch<S: Clone, H: Handler<S, Miner>>(
+    c: &mut Criterion,
+    name: &str,
+    handler: &H,
+    dig: impl Fn() -> S + Copy,
+) {
+    let mut group = c.benchmark_group("update");
+
+    for &agents in AGENTS {
+        let mut town = town(handler, agents, dig);
+        println!(
+            "update/{}/{}: {:.3} allocations per agent update",
+            name,
+            agents,
+            allocations_per_update(handler, &mut town)
+        );
+
+        group.throughput(Throughput::Elements(agents as u64));
+        group.bench_function(BenchmarkId::new(name, agents), |b| {
+            b.iter(|| round(handler, &mut town))
+        });
+    }
+
+    group.finish();
+}
+
+fn enum_dispatch(c: &mut Criterion) {
+    bench_dispatch(c, "enum", &Enumerated, || Kind::Dig);
+}
+
+fn boxed_states(c: &mut Criterion) {
+    bench_dispatch(c, "boxed", &Dynamic, Dig::boxed);
+}
+
+criterion_group!(benches, enum_dispatch, boxed_states);
+criterion_main!(benches);
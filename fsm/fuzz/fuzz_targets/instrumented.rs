@@ -0,0 +1,270 @@
+//! A handler whose states make whatever transition they're told to next, and
+//! which checks the machine starts, stops, pauses and resumes them as it
+//! should, against a model of what the stack ought to hold.
+
+// each target drives the machine with some of this
+#![allow(dead_code)]
+
+use arbitrary::Arbitrary;
+use fsm::{Handler, StateMachine, StateStack, StateTransition};
+use std::collections::BTreeSet;
+
+/// A transition for the running state to ask for.
+#[derive(Arbitrary, Clone, Debug)]
+pub enum Step {
+    Stay,
+    Pop,
+    Push,
+    Switch,
+    /// Up to four states, none of them leaving the stack as it was.
+    Sequence(u8),
+    Quit,
+}
+
+/// A [`Step`] with the states it makes numbered, so the model can follow it.
+#[derive(Clone, Debug)]
+enum Planned {
+    Stay,
+    Pop,
+    Push(usize),
+    Switch(usize),
+    Sequence(Vec<usize>),
+    Quit,
+}
+
+/// Which states have been started, stopped and paused, with each state
+/// numbered in the order it was made, and the stack they should be on.
+#[derive(Default)]
+pub struct Lifecycle {
+    made: usize,
+    started: BTreeSet<usize>,
+    stopped: BTreeSet<usize>,
+    paused: BTreeSet<usize>,
+    /// What the stack should hold, from the bottom up.
+    model: Vec<usize>,
+    /// The transition for the running state to ask for when next asked.
+    next: Option<Planned>,
+}
+
+impl Lifecycle {
+    fn make(&mut self) -> usize {
+        self.made += 1;
+        self.made
+    }
+
+    fn plan(&mut self, step: Step) -> Planned {
+        match step {
+            Step::Stay => Planned::Stay,
+            Step::Pop => Planned::Pop,
+            Step::Push => Planned::Push(self.make()),
+            Step::Switch => Planned::Switch(self.make()),
+            Step::Sequence(n) => Planned::Sequence((0..n % 5).map(|_| self.make()).collect()),
+            Step::Quit => Planned::Quit,
+        }
+    }
+
+    /// The stack after the running state makes `planned`.
+    fn follow(&mut self, planned: Planned) {
+        let model = &mut self.model;
+        match planned {
+            Planned::Stay => (),
+            Planned::Pop => {
+                model.pop();
+            }
+            Planned::Push(state) => model.push(state),
+            // a switch replaces just the running state, leaving those beneath
+            Planned::Switch(state) => {
+                model.pop();
+                model.push(state);
+            }
+            Planned::Sequence(states) => {
+                if !states.is_empty() {
+                    model.pop();
+                    model.extend(states.into_iter().rev());
+                }
+            }
+            Planned::Quit => model.clear(),
+        }
+    }
+}
+
+/// States that make the transition planned for them, checking they're
+/// started, stopped, paused and resumed in a sensible order as they go.
+pub struct Instrumented;
+
+impl Instrumented {
+    fn running(state: &usize, data: &Lifecycle) {
+        assert_eq!(data.model.last(), Some(state), "{} ran off the top", state);
+        assert!(!data.paused.contains(state), "{} ran while paused", state);
+    }
+
+    fn transition(data: &mut Lifecycle) -> StateTransition<usize> {
+        match data.next.take().expect("asked for a transition twice") {
+            Planned::Stay => StateTransition::None,
+            Planned::Pop => StateTransition::Pop,
+            Planned::Push(state) => StateTransition::Push(state),
+            Planned::Switch(state) => StateTransition::Switch(state),
+            Planned::Sequence(states) => StateTransition::Sequence(states),
+            Planned::Quit => StateTransition::Quit,
+        }
+    }
+}
+
+impl Handler<usize, Lifecycle, bool> for Instrumented {
+    fn on_start(&self, state: &usize, data: &mut Lifecycle) {
+        assert!(*state <= data.made, "{} was never made", state);
+        assert!(data.started.insert(*state), "{} started twice", state);
+    }
+
+    fn on_stop(&self, state: &usize, data: &mut Lifecycle) {
+        assert!(data.started.contains(state), "{} stopped unstarted", state);
+        assert!(data.stopped.insert(*state), "{} stopped twice", state);
+        data.paused.remove(state);
+    }
+
+    fn on_pause(&self, state: &usize, data: &mut Lifecycle) {
+        assert!(data.started.contains(state), "{} paused unstarted", state);
+        assert!(!data.stopped.contains(state), "{} paused stopped", state);
+        assert!(data.paused.insert(*state), "{} paused twice", state);
+    }
+
+    fn on_resume(&self, state: &usize, data: &mut Lifecycle) {
+        assert!(!data.stopped.contains(state), "{} resumed stopped", state);
+        assert!(data.paused.remove(state), "{} resumed unpaused", state);
+    }
+
+    fn update(&self, state: &usize, data: &mut Lifecycle) -> StateTransition<usize> {
+        Self::running(state, data);
+        Self::transition(data)
+    }
+
+    fn on_message(
+        &self,
+        state: &usize,
+        data: &mut Lifecycle,
+        handled: &bool,
+    ) -> Option<StateTransition<usize>> {
+        Self::running(state, data);
+        if *handled {
+            Some(Self::transition(data))
+        } else {
+            None
+        }
+    }
+}
+
+/// A machine run by [`Instrumented`] states, checked after everything it does.
+pub struct Harness {
+    data: Lifecycle,
+    state_stack: StateStack<usize>,
+}
+
+impl Harness {
+    /// A machine that isn't running yet.
+    pub fn new() -> Self {
+        Harness {
+            data: Lifecycle::default(),
+            state_stack: StateStack::new(),
+        }
+    }
+
+    /// Updates the running state, which makes `step`.
+    pub fn update(&mut self, step: Step) {
+        let planned = self.data.plan(step);
+        self.data.next = Some(planned.clone());
+        StateMachine::update(&Instrumented, &mut self.state_stack, &mut self.data);
+        if !self.data.model.is_empty() {
+            self.data.follow(planned);
+        }
+        self.check();
+    }
+
+    /// Sends the running state a message, which it handles by making `step`,
+    /// or ignores if there's no step.
+    pub fn message(&mut self, step: Option<Step>) {
+        let handled = step.is_some();
+        let planned = step.map(|step| self.data.plan(step));
+        self.data.next = planned.clone();
+        let running = !self.data.model.is_empty();
+        assert_eq!(
+            StateMachine::handle_message(
+                &Instrumented,
+                &mut self.state_stack,
+                &mut self.data,
+                &handled
+            ),
+            handled && running,
+            "a message was handled wrongly"
+        );
+        if let (true, Some(planned)) = (running, planned) {
+            self.data.follow(planned);
+        }
+        self.check();
+    }
+
+    /// Pushes a new state over the running one from outside.
+    pub fn push(&mut self) {
+        let state = self.data.make();
+        StateMachine::push(&Instrumented, state, &mut self.state_stack, &mut self.data);
+        self.data.model.push(state);
+        self.check();
+    }
+
+    /// Pops the running state from outside.
+    pub fn pop(&mut self) {
+        StateMachine::pop(&Instrumented, &mut self.state_stack, &mut self.data);
+        self.data.model.pop();
+        self.check();
+    }
+
+    /// Stops every state from outside.
+    pub fn stop(&mut self) {
+        StateMachine::stop(&Instrumented, &mut self.state_stack, &mut self.data);
+        self.data.model.clear();
+        self.check();
+    }
+
+    /// Whether any states are left.
+    pub fn is_running(&self) -> bool {
+        StateMachine::is_running(&self.state_stack)
+    }
+
+    /// Checks the stack holds what the model says, and that only the state on
+    /// top runs, the rest waiting paused beneath it.
+    fn check(&self) {
+        let data = &self.data;
+        let states: Vec<usize> = self.state_stack.iter().copied().collect();
+        assert_eq!(states, data.model, "the stack isn't what it should be");
+        assert_eq!(
+            states.len(),
+            data.started.len() - data.stopped.len(),
+            "the stack doesn't hold just the states started and not stopped"
+        );
+        assert!(
+            states.iter().all(|state| !data.stopped.contains(state)),
+            "a stopped state is still on the stack"
+        );
+        if let Some((top, beneath)) = states.split_last() {
+            assert!(!data.paused.contains(top), "the running state is paused");
+            assert!(
+                beneath.iter().all(|state| data.paused.contains(state)),
+                "a state beneath the top isn't paused"
+            );
+        }
+        assert_eq!(
+            data.paused.len(),
+            states.len().saturating_sub(1),
+            "a state off the stack is still paused"
+        );
+    }
+
+    /// Stops whatever's left, checking every state started was stopped.
+    pub fn finish(mut self) {
+        self.stop();
+        assert_eq!(
+            self.data.started, self.data.stopped,
+            "a state was never stopped"
+        );
+        assert!(self.data.paused.is_empty(), "a state was left paused");
+    }
+}
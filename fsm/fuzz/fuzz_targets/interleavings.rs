@@ -0,0 +1,34 @@
+//! Pushes, pops and stops states from outside the machine, interleaved with
+//! the transitions the states ask for themselves, as agents' stacks are
+//! pushed on by the messages they're sent.
+#![no_main]
+
+mod instrumented;
+
+use arbitrary::Arbitrary;
+use instrumented::{Harness, Step};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Update(Step),
+    /// Handled by making the step, or ignored without one.
+    Message(Option<Step>),
+    Push,
+    Pop,
+    Stop,
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut harness = Harness::new();
+    for op in ops {
+        match op {
+            Op::Update(step) => harness.update(step),
+            Op::Message(step) => harness.message(step),
+            Op::Push => harness.push(),
+            Op::Pop => harness.pop(),
+            Op::Stop => harness.stop(),
+        }
+    }
+    harness.finish();
+});
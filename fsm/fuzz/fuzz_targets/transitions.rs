@@ -0,0 +1,31 @@
+//! Runs a state through the transitions it asks for from its updates and
+//! messages, until the machine exits.
+#![no_main]
+
+mod instrumented;
+
+use arbitrary::Arbitrary;
+use instrumented::{Harness, Step};
+use libfuzzer_sys::fuzz_target;
+
+#[derive(Arbitrary, Debug)]
+enum Op {
+    Update(Step),
+    /// Handled by making the step, or ignored without one.
+    Message(Option<Step>),
+}
+
+fuzz_target!(|ops: Vec<Op>| {
+    let mut harness = Harness::new();
+    harness.push();
+    for op in ops {
+        match op {
+            Op::Update(step) => harness.update(step),
+            Op::Message(step) => harness.message(step),
+        }
+        if !harness.is_running() {
+            break;
+        }
+    }
+    harness.finish();
+});
@@ -0,0 +1,651 @@
+//! A generic stack-based state machine.
+//! This state machine contains a stack of states and handles transitions between them.
+//! StateTransition happen based on the return value of the currently running state's functions.
+//! Only one state can run at once.
+//!
+//! With the `bevy` feature a [`StateStack`] is a bevy component, for agents to carry their
+//! states on, and with the `python` feature the state machine can be driven from Python, with
+//! handlers written there (see [`python`]). The `fsm-ffi` crate does the same for C and C++,
+//! with handlers given as function pointers.
+#![deny(missing_docs)]
+
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+#[cfg(feature = "python")]
+pub mod python;
+
+/// A transition from one state to the other.
+/// ## Generics
+/// - S: State data, the data that is sent to states for them to do their operations.
+pub enum StateTransition<S: Clone> {
+    /// Stay in the current state.
+    None,
+    /// End the current state and go to the previous state on the stack, if any.
+    /// If we Pop the last state, the state machine exits.
+    Pop,
+    /// Pause the current state and push a new one on top of it. The new state runs until it
+    /// pops, when the paused one resumes.
+    Push(S),
+    /// Stop the current state and run this one in its place, leaving those beneath it paused.
+    Switch(S),
+    /// End the current state and run these one after another, each taking over when the one
+    /// before it pops. They're all started now, and wait paused beneath the first. An empty
+    /// sequence does nothing, and leaves the current state running.
+    Sequence(Vec<S>),
+    /// Pop all states and exit the state machine.
+    Quit,
+}
+
+impl<S: Clone> StateTransition<S> {
+    /// Picks one of `choices` at random, each as likely as its weight is large, for states
+    /// that shouldn't always do the same thing. Picks [`StateTransition::None`] if every
+    /// weight is zero.
+    pub fn weighted<R: Rng + ?Sized>(rng: &mut R, choices: Vec<(u32, StateTransition<S>)>) -> Self {
+        let total: u32 = choices.iter().map(|&(weight, _)| weight).sum();
+        if total == 0 {
+            return StateTransition::None;
+        }
+        let mut pick = rng.gen_range(0..total);
+        for (weight, transition) in choices {
+            if pick < weight {
+                return transition;
+            }
+            pick -= weight;
+        }
+        unreachable!("the pick is always below the total weight")
+    }
+}
+
+/// Trait that states must implement.
+///
+/// ## Generics
+/// - S: State data, the data that is sent to states for them to do their operations.
+/// - M: Messages the states can be sent, if any.
+pub trait Handler<S: Clone, D, M = ()> {
+    /// Called when the state is first inserted on the stack.
+    fn on_start(&self, _state: &S, _state_data: &mut D) {}
+    /// Called when the state is popped from the stack.
+    fn on_stop(&self, _state: &S, _state_data: &mut D) {}
+    /// Called when a state is pushed over this one in the stack.
+    fn on_pause(&self, _state: &S, _state_data: &mut D) {}
+    /// Called when the state just on top of this one in the stack is popped.
+    fn on_resume(&self, _state: &S, _state_data: &mut D) {}
+    /// Executed on every frame immediately, as fast as the engine will allow.
+    /// If you need to execute logic at a predictable interval (for example, a physics engine)
+    /// it is suggested to use the state data information to determine when to run such fixed timed
+    /// logic.
+    fn update(&self, _state: &S, _state_data: &mut D) -> StateTransition<S> {
+        StateTransition::None
+    }
+    /// Called when a message arrives for the current state.
+    /// Returns the transition to make if the message was handled, or `None` to ignore it.
+    fn on_message(
+        &self,
+        _state: &S,
+        _state_data: &mut D,
+        _message: &M,
+    ) -> Option<StateTransition<S>> {
+        None
+    }
+}
+
+/// The states an agent is in, the one running on top and the paused ones beneath it.
+#[derive(Clone, Debug, Serialize, Deserialize)]
+pub struct StateStack<S: Clone> {
+    state_stack: Vec<S>,
+}
+
+impl<S: Clone> Default for StateStack<S> {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl<S: Clone> StateStack<S> {
+    /// An empty stack, for a machine that isn't running yet.
+    pub fn new() -> Self {
+        StateStack {
+            state_stack: vec![],
+        }
+    }
+
+    /// A stack holding just `initial_state`. It isn't started; the machine's already in it.
+    pub fn new_initial_state(initial_state: S) -> Self {
+        StateStack {
+            state_stack: vec![initial_state],
+        }
+    }
+
+    /// Whether there are no states left, i.e. the machine has exited.
+    pub fn is_empty(&self) -> bool {
+        self.state_stack.is_empty()
+    }
+
+    /// The running state, on top of the stack.
+    pub fn last(&self) -> Option<&S> {
+        self.state_stack.last()
+    }
+
+    /// The running state, to change in place.
+    pub fn last_mut(&mut self) -> Option<&mut S> {
+        self.state_stack.last_mut()
+    }
+
+    /// The states from the bottom of the stack up.
+    pub fn iter(&self) -> impl DoubleEndedIterator<Item = &S> {
+        self.state_stack.iter()
+    }
+
+    /// Takes the running state off the stack, without stopping it.
+    pub fn pop(&mut self) -> Option<S> {
+        self.state_stack.pop()
+    }
+
+    /// Puts `s` on top of the stack, without starting it or pausing the one beneath.
+    pub fn push(&mut self, s: S) {
+        self.state_stack.push(s);
+    }
+}
+
+/// Agents carry their states as a component, leaving the state machine itself free of bevy.
+#[cfg(feature = "bevy")]
+impl<S: Clone + Send + Sync + 'static> bevy_ecs::component::Component for StateStack<S> {
+    const STORAGE_TYPE: bevy_ecs::component::StorageType = bevy_ecs::component::StorageType::Table;
+    type Mutability = bevy_ecs::component::Mutable;
+}
+
+/// Lists the states from the bottom up, e.g. `GoHomeAndSleepTilRested > QuenchThirst`.
+impl<S: Clone + fmt::Debug> fmt::Display for StateStack<S> {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        for (i, state) in self.iter().enumerate() {
+            if i > 0 {
+                write!(f, " > ")?;
+            }
+            write!(f, "{:?}", state)?;
+        }
+        Ok(())
+    }
+}
+
+/// A state machine that holds the stack of states and performs transitions between states.
+/// It can be created using
+/// ```rust,ignore
+/// StateMachine::<()>::default()
+/// ```
+/// ## Generics
+/// - S: State data, the data that is sent to states for them to do their operations.
+pub struct StateMachine;
+
+impl StateMachine {
+    /// Returns if the state machine still has states in its stack.
+    pub fn is_running<S: Clone>(state_stack: &StateStack<S>) -> bool {
+        !state_stack.is_empty()
+    }
+
+    /// Updates the state at the top of the stack with the provided data.
+    /// If the states returns a transition, perform it.
+    pub fn update<S: Clone, D, M, H: Handler<S, D, M>>(
+        handler: &H,
+        state_stack: &mut StateStack<S>,
+        state_data: &mut D,
+    ) {
+        let trans = match state_stack.last_mut() {
+            Some(state) => handler.update(state, state_data),
+            None => StateTransition::None,
+        };
+
+        Self::transition(handler, trans, state_stack, state_data);
+    }
+
+    /// Sends a message to the state at the top of the stack, performing the transition it
+    /// returns. Returns true if the message was handled.
+    pub fn handle_message<S: Clone, D, M, H: Handler<S, D, M>>(
+        handler: &H,
+        state_stack: &mut StateStack<S>,
+        state_data: &mut D,
+        message: &M,
+    ) -> bool {
+        let trans = match state_stack.last() {
+            Some(state) => handler.on_message(state, state_data, message),
+            None => None,
+        };
+
+        match trans {
+            Some(trans) => {
+                Self::transition(handler, trans, state_stack, state_data);
+                true
+            }
+            None => false,
+        }
+    }
+
+    fn transition<S: Clone, D, M, H: Handler<S, D, M>>(
+        handler: &H,
+        request: StateTransition<S>,
+        state_stack: &mut StateStack<S>,
+        state_data: &mut D,
+    ) {
+        match request {
+            StateTransition::None => (),
+            StateTransition::Pop => Self::pop(handler, state_stack, state_data),
+            StateTransition::Push(state) => Self::push(handler, state, state_stack, state_data),
+            StateTransition::Switch(state) => Self::switch(handler, state, state_stack, state_data),
+            StateTransition::Sequence(states) => {
+                Self::sequence(handler, states, state_stack, state_data)
+            }
+            StateTransition::Quit => Self::stop(handler, state_stack, state_data),
+        }
+    }
+
+    fn switch<S: Clone, D, M, H: Handler<S, D, M>>(
+        handler: &H,
+        state: S,
+        state_stack: &mut StateStack<S>,
+        state_data: &mut D,
+    ) {
+        if let Some(state) = state_stack.pop() {
+            handler.on_stop(&state, state_data)
+        }
+
+        handler.on_start(&state, state_data);
+        state_stack.push(state);
+    }
+
+    fn sequence<S: Clone, D, M, H: Handler<S, D, M>>(
+        handler: &H,
+        states: Vec<S>,
+        state_stack: &mut StateStack<S>,
+        state_data: &mut D,
+    ) {
+        let mut states = states.into_iter().rev();
+        if let Some(last) = states.next() {
+            Self::switch(handler, last, state_stack, state_data);
+        }
+        for state in states {
+            Self::push(handler, state, state_stack, state_data);
+        }
+    }
+
+    /// Push a state on the stack and start it.
+    /// Pauses any previously active state.
+    pub fn push<S: Clone, D, M, H: Handler<S, D, M>>(
+        handler: &H,
+        state: S,
+        state_stack: &mut StateStack<S>,
+        state_data: &mut D,
+    ) {
+        if let Some(state) = state_stack.last_mut() {
+            handler.on_pause(state, state_data);
+        }
+
+        handler.on_start(&state, state_data);
+        state_stack.push(state);
+    }
+
+    /// Stops the running state and resumes the one beneath it, if any.
+    pub fn pop<S: Clone, D, M, H: Handler<S, D, M>>(
+        handler: &H,
+        state_stack: &mut StateStack<S>,
+        state_data: &mut D,
+    ) {
+        if let Some(state) = state_stack.pop() {
+            handler.on_stop(&state, state_data);
+        }
+
+        if let Some(state) = state_stack.last() {
+            handler.on_resume(state, state_data);
+        }
+    }
+
+    /// Removes all currently running states from the stack.
+    pub fn stop<S: Clone, D, M, H: Handler<S, D, M>>(
+        handler: &H,
+        state_stack: &mut StateStack<S>,
+        state_data: &mut D,
+    ) {
+        while let Some(state) = state_stack.pop() {
+            handler.on_stop(&state, state_data);
+        }
+    }
+}
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+    use rand::SeedableRng;
+    use rand_chacha::ChaCha8Rng;
+    use std::collections::{BTreeSet, VecDeque};
+
+    #[derive(Clone, Copy)]
+    enum State {
+        A,
+        B,
+    }
+
+    type StateData<'a> = (&'a mut isize, isize);
+
+    pub struct Test;
+
+    impl<'a> Handler<State, StateData<'a>> for Test {
+        fn on_start(&self, _state: &State, data: &mut StateData) {
+            *data.0 += data.1;
+        }
+
+        fn on_resume(&self, state: &State, data: &mut StateData) {
+            self.on_start(state, data);
+        }
+
+        fn update(&self, _state: &State, _data: &mut StateData) -> StateTransition<State> {
+            StateTransition::Push(State::B)
+        }
+    }
+
+    #[test]
+    fn sm_test() {
+        let mut state_stack = StateStack::new();
+        let mut state_data = (0, 10);
+        let foo = &mut (&mut state_data.0, state_data.1);
+
+        StateMachine::push(&Test, State::A, &mut state_stack, foo);
+        assert!(*foo.0 == 10);
+
+        StateMachine::update(&Test, &mut state_stack, foo);
+        assert!(*foo.0 == 20);
+
+        StateMachine::stop(&Test, &mut state_stack, foo);
+        assert!(*foo.0 == 20);
+        assert!(!StateMachine::is_running(&state_stack))
+    }
+
+    pub struct Messages;
+
+    impl Handler<State, isize, &'static str> for Messages {
+        fn on_message(
+            &self,
+            _state: &State,
+            data: &mut isize,
+            message: &&'static str,
+        ) -> Option<StateTransition<State>> {
+            match *message {
+                "push" => Some(StateTransition::Push(State::B)),
+                "count" => {
+                    *data += 1;
+                    Some(StateTransition::None)
+                }
+                _ => None,
+            }
+        }
+    }
+
+    pub struct Errands;
+
+    impl Handler<State, Vec<&'static str>> for Errands {
+        fn on_start(&self, state: &State, log: &mut Vec<&'static str>) {
+            log.push(match state {
+                State::A => "start A",
+                State::B => "start B",
+            });
+        }
+
+        fn on_resume(&self, _state: &State, log: &mut Vec<&'static str>) {
+            log.push("resume");
+        }
+
+        fn update(&self, state: &State, _log: &mut Vec<&'static str>) -> StateTransition<State> {
+            match state {
+                State::A => StateTransition::Sequence(vec![State::B, State::A]),
+                State::B => StateTransition::Pop,
+            }
+        }
+    }
+
+    #[test]
+    fn a_sequence_runs_its_states_in_order() {
+        let mut state_stack = StateStack::new_initial_state(State::A);
+        let mut log = vec![];
+
+        StateMachine::update(&Errands, &mut state_stack, &mut log);
+        assert_eq!(log, vec!["start A", "start B"]);
+        assert!(matches!(state_stack.last(), Some(State::B)));
+
+        StateMachine::update(&Errands, &mut state_stack, &mut log);
+        assert_eq!(log, vec!["start A", "start B", "resume"]);
+        assert!(matches!(state_stack.last(), Some(State::A)));
+    }
+
+    pub struct Nothing;
+
+    impl Handler<State, Vec<&'static str>> for Nothing {
+        fn on_start(&self, _state: &State, log: &mut Vec<&'static str>) {
+            log.push("start");
+        }
+
+        fn on_stop(&self, _state: &State, log: &mut Vec<&'static str>) {
+            log.push("stop");
+        }
+
+        fn update(&self, _state: &State, _log: &mut Vec<&'static str>) -> StateTransition<State> {
+            StateTransition::Sequence(vec![])
+        }
+    }
+
+    #[test]
+    fn an_empty_sequence_leaves_the_running_state() {
+        let mut state_stack = StateStack::new_initial_state(State::A);
+        let mut log = vec![];
+
+        StateMachine::update(&Nothing, &mut state_stack, &mut log);
+        assert!(log.is_empty());
+        assert_eq!(state_stack.iter().count(), 1);
+        assert!(matches!(state_stack.last(), Some(State::A)));
+    }
+
+    #[test]
+    fn messages_trigger_transitions() {
+        let mut state_stack = StateStack::new_initial_state(State::A);
+        let mut count = 0;
+
+        assert!(StateMachine::handle_message(
+            &Messages,
+            &mut state_stack,
+            &mut count,
+            &"count"
+        ));
+        assert_eq!(count, 1);
+        assert!(!StateMachine::handle_message(
+            &Messages,
+            &mut state_stack,
+            &mut count,
+            &"hello"
+        ));
+
+        assert!(StateMachine::handle_message(
+            &Messages,
+            &mut state_stack,
+            &mut count,
+            &"push"
+        ));
+        assert!(matches!(state_stack.last(), Some(State::B)));
+
+        StateMachine::stop(&Messages, &mut state_stack, &mut count);
+        assert!(!StateMachine::handle_message(
+            &Messages,
+            &mut state_stack,
+            &mut count,
+            &"count"
+        ));
+    }
+
+    /// The transition a [`Scripted`] state asks for when it's next updated.
+    #[derive(Clone, Debug)]
+    enum Step {
+        Stay,
+        Pop,
+        Push,
+        Switch,
+        Sequence(usize),
+        Quit,
+    }
+
+    fn step() -> impl Strategy<Value = Step> {
+        prop_oneof![
+            Just(Step::Stay),
+            Just(Step::Pop),
+            Just(Step::Push),
+            Just(Step::Switch),
+            (0..4usize).prop_map(Step::Sequence),
+            Just(Step::Quit),
+        ]
+    }
+
+    /// Which states have been started, stopped and paused, with each state
+    /// numbered in the order it was made.
+    #[derive(Default)]
+    struct Lifecycle {
+        script: VecDeque<Step>,
+        made: usize,
+        started: BTreeSet<usize>,
+        stopped: BTreeSet<usize>,
+        paused: BTreeSet<usize>,
+    }
+
+    impl Lifecycle {
+        fn make(&mut self) -> usize {
+            self.made += 1;
+            self.made
+        }
+    }
+
+    /// States that make the transitions in the script, checking they're
+    /// started, stopped, paused and resumed in a sensible order as they go.
+    pub struct Scripted;
+
+    impl Handler<usize, Lifecycle> for Scripted {
+        fn on_start(&self, state: &usize, data: &mut Lifecycle) {
+            assert!(data.started.insert(*state), "{} started twice", state);
+        }
+
+        fn on_stop(&self, state: &usize, data: &mut Lifecycle) {
+            assert!(data.started.contains(state), "{} stopped unstarted", state);
+            assert!(data.stopped.insert(*state), "{} stopped twice", state);
+            data.paused.remove(state);
+        }
+
+        fn on_pause(&self, state: &usize, data: &mut Lifecycle) {
+            assert!(data.paused.insert(*state), "{} paused twice", state);
+        }
+
+        fn on_resume(&self, state: &usize, data: &mut Lifecycle) {
+            assert!(data.paused.remove(state), "{} resumed unpaused", state);
+        }
+
+        fn update(&self, state: &usize, data: &mut Lifecycle) -> StateTransition<usize> {
+            assert!(
+                !data.paused.contains(state),
+                "{} updated while paused",
+                state
+            );
+            match data.script.pop_front() {
+                Some(Step::Stay) | None => StateTransition::None,
+                Some(Step::Pop) => StateTransition::Pop,
+                Some(Step::Push) => StateTransition::Push(data.make()),
+                Some(Step::Switch) => StateTransition::Switch(data.make()),
+                Some(Step::Sequence(n)) => {
+                    StateTransition::Sequence((0..n).map(|_| data.make()).collect())
+                }
+                Some(Step::Quit) => StateTransition::Quit,
+            }
+        }
+    }
+
+    proptest! {
+        #[test]
+        fn every_state_started_is_stopped_once(script in prop::collection::vec(step(), 0..40)) {
+            let mut data = Lifecycle::default();
+            let mut state_stack = StateStack::new();
+            StateMachine::push(&Scripted, data.make(), &mut state_stack, &mut data);
+            data.script = script.into();
+
+            while !data.script.is_empty() {
+                let quit = matches!(data.script.front(), Some(Step::Quit));
+                StateMachine::update(&Scripted, &mut state_stack, &mut data);
+                let states = &state_stack.state_stack;
+                // the stack holds exactly the states started and not yet stopped
+                prop_assert_eq!(states.len(), data.started.len() - data.stopped.len());
+                prop_assert!(states.iter().all(|state| !data.stopped.contains(state)));
+                // everything beneath the top waits paused, and only the top runs
+                if let Some((top, beneath)) = states.split_last() {
+                    prop_assert!(!data.paused.contains(top));
+                    prop_assert!(beneath.iter().all(|state| data.paused.contains(state)));
+                }
+                if quit {
+                    prop_assert!(!StateMachine::is_running(&state_stack));
+                }
+                if state_stack.is_empty() {
+                    break;
+                }
+            }
+
+            StateMachine::stop(&Scripted, &mut state_stack, &mut data);
+            prop_assert!(state_stack.is_empty());
+            prop_assert_eq!(&data.started, &data.stopped);
+            prop_assert!(data.paused.is_empty());
+        }
+
+        #[test]
+        fn push_pauses_the_running_state_until_the_new_one_pops(depth in 1..10usize) {
+            let mut data = Lifecycle::default();
+            let mut state_stack = StateStack::new();
+            for _ in 0..depth {
+                StateMachine::push(&Scripted, data.make(), &mut state_stack, &mut data);
+            }
+            prop_assert_eq!(data.paused.len(), depth - 1);
+
+            data.script = vec![Step::Pop; depth - 1].into();
+            for running in (1..depth).rev() {
+                StateMachine::update(&Scripted, &mut state_stack, &mut data);
+                prop_assert_eq!(state_stack.last(), Some(&running));
+                prop_assert!(!data.paused.contains(&running));
+            }
+        }
+
+        #[test]
+        fn switch_replaces_only_the_running_state(depth in 1..10usize) {
+            let mut data = Lifecycle::default();
+            let mut state_stack = StateStack::new();
+            for _ in 0..depth {
+                StateMachine::push(&Scripted, data.make(), &mut state_stack, &mut data);
+            }
+            let beneath: Vec<usize> = (1..depth).collect();
+
+            data.script = vec![Step::Switch].into();
+            StateMachine::update(&Scripted, &mut state_stack, &mut data);
+            let states = &state_stack.state_stack;
+            prop_assert_eq!(&states[..depth - 1], &beneath[..]);
+            prop_assert_eq!(states.last(), Some(&(depth + 1)));
+            prop_assert!(data.stopped.contains(&depth));
+            prop_assert!(beneath.iter().all(|state| data.paused.contains(state)));
+            prop_assert_eq!(data.stopped.len(), 1);
+        }
+
+        #[test]
+        fn a_weighted_choice_never_picks_a_weightless_transition(
+            weights in prop::collection::vec(0..4u32, 1..6),
+            seed: u64,
+        ) {
+            let mut rng = ChaCha8Rng::seed_from_u64(seed);
+            let choices = weights
+                .iter()
+                .enumerate()
+                .map(|(state, &weight)| (weight, StateTransition::Switch(state)))
+                .collect();
+            match StateTransition::weighted(&mut rng, choices) {
+                StateTransition::Switch(state) => prop_assert!(weights[state] > 0),
+                StateTransition::None => prop_assert!(weights.iter().all(|&weight| weight == 0)),
+                _ => prop_assert!(false, "picked a transition that wasn't offered"),
+            }
+        }
+    }
+}
@@ -0,0 +1,464 @@
+//! Drives the state machine from Python, for prototyping agent logic in a notebook before it's
+//! written in Rust.
+//!
+//! States, state data and messages are any Python objects. The handler is a Python object
+//! whose methods are the [`Handler`] hooks, called with the same arguments: `on_start`,
+//! `on_stop`, `on_pause` and `on_resume` take `(state, data)`, `update` takes `(state, data)`
+//! and returns a [`Transition`] or `None` to stay, and `on_message` takes
+//! `(state, data, message)` and returns a [`Transition`] or `None` to ignore the message. Any
+//! hook it doesn't have does what the trait's default does, so a handler can be a class or
+//! just a `types.SimpleNamespace` of callables.
+//!
+//! ```python
+//! import fsm
+//!
+//! class Miner:
+//!     def update(self, state, data):
+//!         data["gold"] += 1
+//!         if data["gold"] >= 3:
+//!             return fsm.Transition.push("VisitBankAndDepositGold")
+//!
+//! stack = fsm.StateStack("EnterMineAndDigForNugget")
+//! machine = fsm.StateMachine(Miner())
+//! data = {"gold": 0}
+//! while len(stack) < 2:
+//!     machine.update(stack, data)
+//! print(stack)  # 'EnterMineAndDigForNugget' > 'VisitBankAndDepositGold'
+//! ```
+//!
+//! Build the module with `maturin develop --features python` from the crate's directory; maturin
+//! builds the crate as the shared library Python loads, though Cargo.toml only asks for an rlib.
+
+use crate::{Handler, StateMachine, StateStack, StateTransition};
+use pyo3::exceptions::PyTypeError;
+use pyo3::prelude::*;
+use std::cell::RefCell;
+
+/// A transition for a Python handler to return, made with one of its static methods, e.g.
+/// `Transition.push(state)`.
+#[pyclass(name = "Transition", module = "fsm", frozen)]
+pub struct PyTransition(StateTransition<Py<PyAny>>);
+
+impl PyTransition {
+    fn to_transition(&self, py: Python<'_>) -> StateTransition<Py<PyAny>> {
+        match &self.0 {
+            StateTransition::None => StateTransition::None,
+            StateTransition::Pop => StateTransition::Pop,
+            StateTransition::Push(state) => StateTransition::Push(state.clone_ref(py)),
+            StateTransition::Switch(state) => StateTransition::Switch(state.clone_ref(py)),
+            StateTransition::Sequence(states) => {
+                StateTransition::Sequence(states.iter().map(|s| s.clone_ref(py)).collect())
+            }
+            StateTransition::Quit => StateTransition::Quit,
+        }
+    }
+}
+
+#[pymethods]
+impl PyTransition {
+    /// Stay in the current state.
+    #[staticmethod]
+    fn none() -> Self {
+        PyTransition(StateTransition::None)
+    }
+
+    /// End the current state and resume the one beneath it.
+    #[staticmethod]
+    fn pop() -> Self {
+        PyTransition(StateTransition::Pop)
+    }
+
+    /// Pause the current state and run `state` on top of it.
+    #[staticmethod]
+    fn push(state: Py<PyAny>) -> Self {
+        PyTransition(StateTransition::Push(state))
+    }
+
+    /// Stop the current state and run `state` in its place, leaving those beneath it paused.
+    #[staticmethod]
+    fn switch(state: Py<PyAny>) -> Self {
+        PyTransition(StateTransition::Switch(state))
+    }
+
+    /// End the current state and run `states` one after another.
+    #[staticmethod]
+    fn sequence(states: Vec<Py<PyAny>>) -> Self {
+        PyTransition(StateTransition::Sequence(states))
+    }
+
+    /// Stop every state, exiting the state machine.
+    #[staticmethod]
+    fn quit() -> Self {
+        PyTransition(StateTransition::Quit)
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        let repr =
+            |state: &Py<PyAny>| -> PyResult<String> { Ok(state.bind(py).repr()?.to_string()) };
+        Ok(match &self.0 {
+            StateTransition::None => "Transition.none()".to_string(),
+            StateTransition::Pop => "Transition.pop()".to_string(),
+            StateTransition::Push(state) => format!("Transition.push({})", repr(state)?),
+            StateTransition::Switch(state) => format!("Transition.switch({})", repr(state)?),
+            StateTransition::Sequence(states) => format!(
+                "Transition.sequence([{}])",
+                states
+                    .iter()
+                    .map(repr)
+                    .collect::<PyResult<Vec<_>>>()?
+                    .join(", ")
+            ),
+            StateTransition::Quit => "Transition.quit()".to_string(),
+        })
+    }
+}
+
+/// The stack of states a Python agent is in, with the running one on top.
+#[pyclass(name = "StateStack", module = "fsm")]
+pub struct PyStateStack(StateStack<Py<PyAny>>);
+
+#[pymethods]
+impl PyStateStack {
+    /// An empty stack, or one already in `initial_state` if it's given.
+    #[new]
+    #[pyo3(signature = (initial_state=None))]
+    fn new(initial_state: Option<Py<PyAny>>) -> Self {
+        PyStateStack(match initial_state {
+            Some(state) => StateStack::new_initial_state(state),
+            None => StateStack::new(),
+        })
+    }
+
+    /// The running state, or `None` once the machine has exited.
+    fn last(&self, py: Python<'_>) -> Option<Py<PyAny>> {
+        self.0.last().map(|state| state.clone_ref(py))
+    }
+
+    /// The states from the bottom of the stack up.
+    fn states(&self, py: Python<'_>) -> Vec<Py<PyAny>> {
+        self.0.iter().map(|state| state.clone_ref(py)).collect()
+    }
+
+    fn __len__(&self) -> usize {
+        self.0.iter().count()
+    }
+
+    fn __bool__(&self) -> bool {
+        !self.0.is_empty()
+    }
+
+    /// Lists the states from the bottom up, like the [`StateStack`]'s `Display`.
+    fn __str__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(self
+            .0
+            .iter()
+            .map(|state| Ok(state.bind(py).repr()?.to_string()))
+            .collect::<PyResult<Vec<_>>>()?
+            .join(" > "))
+    }
+
+    fn __repr__(&self, py: Python<'_>) -> PyResult<String> {
+        Ok(format!(
+            "StateStack([{}])",
+            self.__str__(py)?.replace(" > ", ", ")
+        ))
+    }
+}
+
+/// Calls a Python handler's hooks. The hooks can't fail, so the first exception one raises is
+/// kept to raise once the transition's done, and no more hooks are called until then.
+struct PyHandler<'py> {
+    handler: Bound<'py, PyAny>,
+    error: RefCell<Option<PyErr>>,
+}
+
+impl<'py> PyHandler<'py> {
+    fn new(handler: Bound<'py, PyAny>) -> Self {
+        PyHandler {
+            handler,
+            error: RefCell::new(None),
+        }
+    }
+
+    /// Calls the hook `name` if the handler has it, returning what it returned.
+    fn call<A>(&self, name: &str, args: A) -> Option<Bound<'py, PyAny>>
+    where
+        A: pyo3::call::PyCallArgs<'py>,
+    {
+        if self.error.borrow().is_some() {
+            return None;
+        }
+        let result = self
+            .handler
+            .getattr_opt(name)
+            .and_then(|hook| hook.map(|hook| hook.call1(args)).transpose());
+        match result {
+            Ok(returned) => returned,
+            Err(err) => {
+                self.error.replace(Some(err));
+                None
+            }
+        }
+    }
+
+    /// Reads what `update` or `on_message` returned: a [`PyTransition`], or `None`.
+    fn transition(
+        &self,
+        name: &str,
+        returned: Bound<'py, PyAny>,
+    ) -> Option<StateTransition<Py<PyAny>>> {
+        if returned.is_none() {
+            return None;
+        }
+        match returned.cast::<PyTransition>() {
+            Ok(transition) => Some(transition.get().to_transition(returned.py())),
+            Err(_) => {
+                self.error.replace(Some(PyTypeError::new_err(format!(
+                    "{} should return a Transition or None, not {}",
+                    name,
+                    returned.get_type()
+                ))));
+                None
+            }
+        }
+    }
+
+    fn finish(self) -> PyResult<()> {
+        match self.error.into_inner() {
+            Some(err) => Err(err),
+            None => Ok(()),
+        }
+    }
+}
+
+impl<'py> Handler<Py<PyAny>, Py<PyAny>, Py<PyAny>> for PyHandler<'py> {
+    fn on_start(&self, state: &Py<PyAny>, state_data: &mut Py<PyAny>) {
+        self.call("on_start", (state, &*state_data));
+    }
+
+    fn on_stop(&self, state: &Py<PyAny>, state_data: &mut Py<PyAny>) {
+        self.call("on_stop", (state, &*state_data));
+    }
+
+    fn on_pause(&self, state: &Py<PyAny>, state_data: &mut Py<PyAny>) {
+        self.call("on_pause", (state, &*state_data));
+    }
+
+    fn on_resume(&self, state: &Py<PyAny>, state_data: &mut Py<PyAny>) {
+        self.call("on_resume", (state, &*state_data));
+    }
+
+    fn update(&self, state: &Py<PyAny>, state_data: &mut Py<PyAny>) -> StateTransition<Py<PyAny>> {
+        self.call("update", (state, &*state_data))
+            .and_then(|returned| self.transition("update", returned))
+            .unwrap_or(StateTransition::None)
+    }
+
+    fn on_message(
+        &self,
+        state: &Py<PyAny>,
+        state_data: &mut Py<PyAny>,
+        message: &Py<PyAny>,
+    ) -> Option<StateTransition<Py<PyAny>>> {
+        self.call("on_message", (state, &*state_data, message))
+            .and_then(|returned| self.transition("on_message", returned))
+    }
+}
+
+/// A state machine whose states are handled in Python, by the handler it's made with.
+#[pyclass(name = "StateMachine", module = "fsm", frozen)]
+pub struct PyStateMachine {
+    handler: Py<PyAny>,
+}
+
+#[pymethods]
+impl PyStateMachine {
+    #[new]
+    fn new(handler: Py<PyAny>) -> Self {
+        PyStateMachine { handler }
+    }
+
+    /// Whether the stack still has states in it.
+    #[staticmethod]
+    fn is_running(stack: PyRef<'_, PyStateStack>) -> bool {
+        StateMachine::is_running(&stack.0)
+    }
+
+    /// Updates the running state, making the transition it returns.
+    fn update(
+        &self,
+        py: Python<'_>,
+        mut stack: PyRefMut<'_, PyStateStack>,
+        data: Py<PyAny>,
+    ) -> PyResult<()> {
+        let handler = PyHandler::new(self.handler.bind(py).clone());
+        let mut data = data;
+        StateMachine::update(&handler, &mut stack.0, &mut data);
+        handler.finish()
+    }
+
+    /// Sends `message` to the running state, returning whether it was handled.
+    fn handle_message(
+        &self,
+        py: Python<'_>,
+        mut stack: PyRefMut<'_, PyStateStack>,
+        data: Py<PyAny>,
+        message: Py<PyAny>,
+    ) -> PyResult<bool> {
+        let handler = PyHandler::new(self.handler.bind(py).clone());
+        let mut data = data;
+        let handled = StateMachine::handle_message(&handler, &mut stack.0, &mut data, &message);
+        handler.finish().map(|()| handled)
+    }
+
+    /// Pushes `state` and starts it, pausing the one that was running.
+    fn push(
+        &self,
+        py: Python<'_>,
+        mut stack: PyRefMut<'_, PyStateStack>,
+        state: Py<PyAny>,
+        data: Py<PyAny>,
+    ) -> PyResult<()> {
+        let handler = PyHandler::new(self.handler.bind(py).clone());
+        let mut data = data;
+        StateMachine::push(&handler, state, &mut stack.0, &mut data);
+        handler.finish()
+    }
+
+    /// Stops every state on the stack.
+    fn stop(
+        &self,
+        py: Python<'_>,
+        mut stack: PyRefMut<'_, PyStateStack>,
+        data: Py<PyAny>,
+    ) -> PyResult<()> {
+        let handler = PyHandler::new(self.handler.bind(py).clone());
+        let mut data = data;
+        StateMachine::stop(&handler, &mut stack.0, &mut data);
+        handler.finish()
+    }
+}
+
+/// The `fsm` Python module.
+#[pymodule]
+fn fsm(m: &Bound<'_, PyModule>) -> PyResult<()> {
+    m.add_class::<PyTransition>()?;
+    m.add_class::<PyStateStack>()?;
+    m.add_class::<PyStateMachine>()?;
+    Ok(())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use pyo3::types::PyDict;
+    use std::ffi::CString;
+
+    /// Runs `code` with the module imported as `fsm`, returning its globals.
+    fn run<'py>(py: Python<'py>, code: &str) -> PyResult<Bound<'py, PyDict>> {
+        let module = PyModule::new(py, "fsm")?;
+        fsm(&module)?;
+        let globals = PyDict::new(py);
+        globals.set_item("fsm", module)?;
+        py.run(&CString::new(code).unwrap(), Some(&globals), None)?;
+        Ok(globals)
+    }
+
+    #[test]
+    fn python_handler_pushes_and_pops() {
+        Python::initialize();
+        Python::attach(|py| {
+            let globals = run(
+                py,
+                r#"
+calls = []
+class Miner:
+    def on_start(self, state, data): calls.append(("start", state))
+    def on_stop(self, state, data): calls.append(("stop", state))
+    def on_pause(self, state, data): calls.append(("pause", state))
+    def on_resume(self, state, data): calls.append(("resume", state))
+    def update(self, state, data):
+        data["ticks"] += 1
+        if state == "dig" and data["ticks"] == 2:
+            return fsm.Transition.push("bank")
+        if state == "bank":
+            return fsm.Transition.pop()
+
+stack = fsm.StateStack("dig")
+machine = fsm.StateMachine(Miner())
+data = {"ticks": 0}
+for _ in range(3):
+    machine.update(stack, data)
+"#,
+            )
+            .unwrap();
+            let calls: Vec<(String, String)> = globals
+                .get_item("calls")
+                .unwrap()
+                .unwrap()
+                .extract()
+                .unwrap();
+            let calls: Vec<_> = calls
+                .iter()
+                .map(|(c, s)| (c.as_str(), s.as_str()))
+                .collect();
+            assert_eq!(
+                calls,
+                [
+                    ("pause", "dig"),
+                    ("start", "bank"),
+                    ("stop", "bank"),
+                    ("resume", "dig")
+                ]
+            );
+            let stack = globals.get_item("stack").unwrap().unwrap();
+            assert_eq!(stack.str().unwrap().to_string(), "'dig'");
+        });
+    }
+
+    #[test]
+    fn unhandled_messages_and_missing_hooks_are_ignored() {
+        Python::initialize();
+        Python::attach(|py| {
+            let globals = run(
+                py,
+                r#"
+from types import SimpleNamespace
+handler = SimpleNamespace(
+    on_message=lambda state, data, msg: fsm.Transition.quit() if msg == "go" else None)
+stack = fsm.StateStack("idle")
+machine = fsm.StateMachine(handler)
+machine.update(stack, None)
+ignored = machine.handle_message(stack, None, "wait")
+handled = machine.handle_message(stack, None, "go")
+running = fsm.StateMachine.is_running(stack)
+"#,
+            )
+            .unwrap();
+            let get = |name: &str| -> bool {
+                globals.get_item(name).unwrap().unwrap().extract().unwrap()
+            };
+            assert!(!get("ignored"));
+            assert!(get("handled"));
+            assert!(!get("running"));
+        });
+    }
+
+    #[test]
+    fn errors_in_hooks_are_raised() {
+        Python::initialize();
+        Python::attach(|py| {
+            let err = run(
+                py,
+                r#"
+class Broken:
+    def update(self, state, data):
+        return "not a transition"
+fsm.StateMachine(Broken()).update(fsm.StateStack("s"), None)
+"#,
+            )
+            .unwrap_err();
+            assert!(err.is_instance_of::<PyTypeError>(py));
+        });
+    }
+}
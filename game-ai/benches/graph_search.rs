@@ -0,0 +1,130 @@
+//! Benchmarks for the graph searches, run with `cargo bench -p game-ai`.
+//!
+//! Throughput is reported in graph nodes per second, so results for graphs of
+//! different sizes can be compared directly.
+
+use criterion::{criterion_group, criterion_main, BenchmarkId, Criterion, Throughput};
+use game_ai::graph::grid::{create_grid_graph, Grid};
+use game_ai::graph::path_manager::{BudgetPolicy, PathManager};
+use game_ai::graph::search::{
+    FindNodeIndex, GraphSearchAStar, GraphSearchBfs, GraphSearchDijkstra,
+};
+use game_ai::graph::{GraphEdge, SparseGraph, TimeSlicedSearch};
+use game_ai::Vector2D;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+
+const GRID_SIZES: &[usize] = &[32, 64, 128];
+const RANDOM_SIZES: &[usize] = &[1_000, 4_000, 16_000];
+const RANDOM_EDGES_PER_NODE: usize = 4;
+const AGENTS: usize = 8;
+
+/// A square grid with a wall across the middle, open only at one end, so
+/// searches have to go the long way round.
+fn walled_grid(size: usize) -> (SparseGraph, usize, usize) {
+    let mut grid = Grid::new(size, size, 1.0);
+    for x in 0..size - 1 {
+        grid.block(x, size / 2);
+    }
+    let graph = create_grid_graph(&grid);
+    (graph, grid.cell_index(0, 0), grid.cell_index(0, size - 1))
+}
+
+/// Nodes scattered over a square, each joined to a few others at random.
+/// Costs are the distance between nodes so A*'s heuristic stays admissible.
+fn random_graph(num_nodes: usize) -> (SparseGraph, usize, usize) {
+    let mut rng = StdRng::seed_from_u64(7);
+    let side = (num_nodes as f64).sqrt() * 10.0;
+    let mut graph = SparseGraph::new(false);
+
+    for _ in 0..num_nodes {
+        graph.add_node(Vector2D::new(
+            rng.gen_range(0.0..side),
+            rng.gen_range(0.0..side),
+        ));
+    }
+    for from in 0..num_nodes {
+        for _ in 0..RANDOM_EDGES_PER_NODE {
+            let to = rng.gen_range(0..num_nodes);
+            if to != from {
+                let cost = graph
+                    .node(from)
+                    .unwrap()
+                    .position
+                    .distance(graph.node(to).unwrap().position);
+                graph.add_edge(GraphEdge::new(from, to, cost));
+            }
+        }
+    }
+
+    (graph, 0, num_nodes - 1)
+}
+
+fn bench_searches(
+    c: &mut Criterion,
+    group_name: &str,
+    sizes: &[usize],
+    build: fn(usize) -> (SparseGraph, usize, usize),
+) {
+    let mut group = c.benchmark_group(group_name);
+
+    for &size in sizes {
+        let (graph, source, target) = build(size);
+        let num_nodes = graph.num_active_nodes() as u64;
+        group.throughput(Throughput::Elements(num_nodes));
+
+        group.bench_with_input(BenchmarkId::new("bfs", size), &graph, |b, graph| {
+            b.iter(|| {
+                let mut search = GraphSearchBfs::new(graph, source, target);
+                search.search(graph)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("dijkstra", size), &graph, |b, graph| {
+            b.iter(|| {
+                let mut search = GraphSearchDijkstra::new(graph, source, FindNodeIndex(target));
+                search.search(graph)
+            })
+        });
+        group.bench_with_input(BenchmarkId::new("astar", size), &graph, |b, graph| {
+            b.iter(|| {
+                let mut search = GraphSearchAStar::new(graph, source, target);
+                search.search(graph)
+            })
+        });
+
+        // every agent searches the whole graph
+        group.throughput(Throughput::Elements(num_nodes * AGENTS as u64));
+        group.bench_with_input(
+            BenchmarkId::new("time_sliced_astar", size),
+            &graph,
+            |b, graph| {
+                b.iter(|| {
+                    let mut manager = PathManager::new(100, BudgetPolicy::RoundRobin);
+                    for agent in 0..AGENTS {
+                        manager.register(
+                            agent,
+                            Box::new(GraphSearchAStar::new(graph, source, target)),
+                        );
+                    }
+                    let mut finished = 0;
+                    while finished < AGENTS {
+                        finished += manager.update_searches(graph).len();
+                    }
+                })
+            },
+        );
+    }
+
+    group.finish();
+}
+
+fn grid_searches(c: &mut Criterion) {
+    bench_searches(c, "grid", GRID_SIZES, walled_grid);
+}
+
+fn random_searches(c: &mut Criterion) {
+    bench_searches(c, "random", RANDOM_SIZES, random_graph);
+}
+
+criterion_group!(benches, grid_searches, random_searches);
+criterion_main!(benches);
@@ -0,0 +1,190 @@
+//! The weapon selection scenario from the fuzzy logic chapter: a bot scores
+//! each weapon it carries from the distance to its target and how much ammo
+//! the weapon has left, and switches to the most desirable.
+//!
+//! Run with `cargo run --example weapon_selection`.
+
+use game_ai::fuzzy::{DefuzzifyMethod, FuzzyModule, FzSet};
+use game_ai::fuzzy_rules;
+
+struct Weapon {
+    name: &'static str,
+    ammo: f64,
+    module: FuzzyModule,
+}
+
+impl Weapon {
+    fn desirability(&mut self, distance: f64) -> f64 {
+        self.module.fuzzify("DistToTarget", distance);
+        self.module.fuzzify("AmmoStatus", self.ammo);
+        self.module
+            .defuzzify("Desirability", DefuzzifyMethod::MaxAv)
+    }
+}
+
+/// The sets every weapon shares.
+struct Sets {
+    close: FzSet,
+    medium: FzSet,
+    far: FzSet,
+    undesirable: FzSet,
+    desirable: FzSet,
+    very_desirable: FzSet,
+}
+
+fn module_with_common_sets() -> (FuzzyModule, Sets) {
+    let mut module = FuzzyModule::new();
+
+    let distance = module.create_variable("DistToTarget");
+    let close = distance.add_left_shoulder_set("Target_Close", 0.0, 25.0, 150.0);
+    let medium = distance.add_triangle_set("Target_Medium", 25.0, 150.0, 300.0);
+    let far = distance.add_right_shoulder_set("Target_Far", 150.0, 300.0, 1000.0);
+
+    let desirability = module.create_variable("Desirability");
+    let undesirable = desirability.add_left_shoulder_set("Undesirable", 0.0, 25.0, 50.0);
+    let desirable = desirability.add_triangle_set("Desirable", 25.0, 50.0, 75.0);
+    let very_desirable = desirability.add_right_shoulder_set("VeryDesirable", 50.0, 75.0, 100.0);
+
+    let sets = Sets {
+        close,
+        medium,
+        far,
+        undesirable,
+        desirable,
+        very_desirable,
+    };
+    (module, sets)
+}
+
+/// Devastating at middling range, but a rocket at your feet hurts you too.
+fn rocket_launcher(ammo: f64) -> Weapon {
+    let (mut module, s) = module_with_common_sets();
+    let status = module.create_variable("AmmoStatus");
+    let low = status.add_triangle_set("Ammo_Low", 0.0, 0.0, 10.0);
+    let okay = status.add_triangle_set("Ammo_Okay", 0.0, 10.0, 30.0);
+    let loads = status.add_right_shoulder_set("Ammo_Loads", 10.0, 30.0, 100.0);
+
+    module.add_rules(fuzzy_rules! {
+        if (s.close & (loads | okay | low)) then s.undesirable;
+        if (s.medium & (loads | okay)) then s.very_desirable;
+        if (s.medium & low) then s.desirable;
+        if (s.far & loads) then s.desirable;
+        if (s.far & (okay | low)) then s.undesirable;
+    });
+
+    Weapon {
+        name: "rocket launcher",
+        ammo,
+        module,
+    }
+}
+
+/// Accurate at any range, and the only thing worth firing at distance.
+fn rail_gun(ammo: f64) -> Weapon {
+    let (mut module, s) = module_with_common_sets();
+    let status = module.create_variable("AmmoStatus");
+    let low = status.add_triangle_set("Ammo_Low", 0.0, 0.0, 10.0);
+    let okay = status.add_triangle_set("Ammo_Okay", 0.0, 10.0, 30.0);
+    let loads = status.add_right_shoulder_set("Ammo_Loads", 10.0, 30.0, 100.0);
+
+    module.add_rules(fuzzy_rules! {
+        if (s.close & (loads | okay | low)) then s.undesirable;
+        if (s.medium & (loads | okay | low)) then s.desirable;
+        if (s.far & (loads | okay | low)) then s.very_desirable;
+    });
+
+    Weapon {
+        name: "rail gun",
+        ammo,
+        module,
+    }
+}
+
+/// Spreads its shot, so it's deadly up close and useless far away.
+fn shotgun(ammo: f64) -> Weapon {
+    let (mut module, s) = module_with_common_sets();
+    let status = module.create_variable("AmmoStatus");
+    let low = status.add_triangle_set("Ammo_Low", 0.0, 0.0, 30.0);
+    let okay = status.add_triangle_set("Ammo_Okay", 0.0, 30.0, 60.0);
+    let loads = status.add_right_shoulder_set("Ammo_Loads", 30.0, 60.0, 100.0);
+
+    module.add_rules(fuzzy_rules! {
+        if (s.close & (loads | okay | low)) then s.very_desirable;
+        if (s.medium & loads) then s.very_desirable;
+        if (s.medium & okay) then s.desirable;
+        if (s.medium & low) then s.undesirable;
+        if (s.far & loads) then s.desirable;
+        if (s.far & (okay | low)) then s.undesirable;
+    });
+
+    Weapon {
+        name: "shotgun",
+        ammo,
+        module,
+    }
+}
+
+fn arsenal(ammo: f64) -> Vec<Weapon> {
+    vec![rocket_launcher(ammo), rail_gun(ammo), shotgun(ammo)]
+}
+
+/// The most desirable weapon at `distance`, and every weapon's score.
+fn choose(weapons: &mut [Weapon], distance: f64) -> (&'static str, Vec<f64>) {
+    let scores: Vec<f64> = weapons
+        .iter_mut()
+        .map(|weapon| weapon.desirability(distance))
+        .collect();
+
+    let best = (0..weapons.len())
+        .max_by(|&a, &b| scores[a].partial_cmp(&scores[b]).unwrap())
+        .unwrap();
+    (weapons[best].name, scores)
+}
+
+fn main() {
+    for &ammo in &[5.0, 20.0, 80.0] {
+        let mut weapons = arsenal(ammo);
+
+        println!("{} rounds each", ammo);
+        print!("{:>8}", "distance");
+        for weapon in &weapons {
+            print!("{:>17}", weapon.name);
+        }
+        println!("   choice");
+
+        for distance in (0..=1000).step_by(50) {
+            let (choice, scores) = choose(&mut weapons, f64::from(distance));
+            print!("{:>8}", distance);
+            for score in scores {
+                print!("{:>17.1}", score);
+            }
+            println!("   {}", choice);
+        }
+        println!();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn each_weapon_has_its_range() {
+        let mut weapons = arsenal(20.0);
+        assert_eq!(choose(&mut weapons, 10.0).0, "shotgun");
+        assert_eq!(choose(&mut weapons, 150.0).0, "rocket launcher");
+        assert_eq!(choose(&mut weapons, 700.0).0, "rail gun");
+    }
+
+    #[test]
+    fn scores_stay_within_the_desirability_range() {
+        for &ammo in &[0.0, 20.0, 100.0] {
+            let mut weapons = arsenal(ammo);
+            for distance in (0..=1000).step_by(10) {
+                for score in choose(&mut weapons, f64::from(distance)).1 {
+                    assert!((0.0..=100.0).contains(&score), "{}", score);
+                }
+            }
+        }
+    }
+}
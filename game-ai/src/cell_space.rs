@@ -0,0 +1,170 @@
+//! Cell-space partitioning: the world is divided into a grid of cells, each
+//! listing the things inside it, so finding what's near a point only means
+//! looking in the cells around it rather than at everything in the world.
+//!
+//! The partition stores keys (usually indices or ids), not the things
+//! themselves. Queries return every key in the cells overlapping an area,
+//! which may include some just outside it; callers do the exact test.
+
+use crate::vector::Vector2D;
+
+/// A grid of cells over the world, from the origin to `(width, height)`.
+/// Positions outside the world are treated as being in the nearest edge cell.
+#[derive(Clone, Debug)]
+pub struct CellSpacePartition<K> {
+    cells_x: usize,
+    cells_y: usize,
+    cell_width: f64,
+    cell_height: f64,
+    cells: Vec<Vec<K>>,
+}
+
+impl<K: Copy + Ord> CellSpacePartition<K> {
+    /// Panics if there are no cells in either direction.
+    pub fn new(width: f64, height: f64, cells_x: usize, cells_y: usize) -> Self {
+        assert!(cells_x > 0 && cells_y > 0, "a partition needs cells");
+        CellSpacePartition {
+            cells_x,
+            cells_y,
+            cell_width: width / cells_x as f64,
+            cell_height: height / cells_y as f64,
+            cells: vec![vec![]; cells_x * cells_y],
+        }
+    }
+
+    pub fn num_cells(&self) -> usize {
+        self.cells.len()
+    }
+
+    /// The coordinates of the cell containing `position`.
+    pub fn cell_at(&self, position: Vector2D) -> (usize, usize) {
+        let clamp = |value: f64, size: f64, cells: usize| {
+            ((value / size).floor().max(0.0) as usize).min(cells - 1)
+        };
+        (
+            clamp(position.x, self.cell_width, self.cells_x),
+            clamp(position.y, self.cell_height, self.cells_y),
+        )
+    }
+
+    /// The keys in the cell containing `position`.
+    pub fn cell(&self, position: Vector2D) -> &[K] {
+        let (x, y) = self.cell_at(position);
+        &self.cells[y * self.cells_x + x]
+    }
+
+    /// Adds a key for something at a point.
+    pub fn add(&mut self, key: K, position: Vector2D) {
+        let (x, y) = self.cell_at(position);
+        self.cells[y * self.cells_x + x].push(key);
+    }
+
+    /// Adds a key to every cell overlapping a box, for things that cover an
+    /// area, such as trigger regions.
+    pub fn add_area(&mut self, key: K, top_left: Vector2D, bottom_right: Vector2D) {
+        for index in self.cells_overlapping(top_left, bottom_right) {
+            self.cells[index].push(key);
+        }
+    }
+
+    /// Removes a key added at `position`, returning false if it wasn't there.
+    pub fn remove(&mut self, key: K, position: Vector2D) -> bool {
+        let (x, y) = self.cell_at(position);
+        let cell = &mut self.cells[y * self.cells_x + x];
+        match cell.iter().position(|&k| k == key) {
+            Some(index) => {
+                cell.swap_remove(index);
+                true
+            }
+            None => false,
+        }
+    }
+
+    /// Moves a key that was at `old_position`, when it has changed cell.
+    pub fn update(&mut self, key: K, old_position: Vector2D, new_position: Vector2D) {
+        if self.cell_at(old_position) != self.cell_at(new_position) {
+            self.remove(key, old_position);
+            self.add(key, new_position);
+        }
+    }
+
+    pub fn clear(&mut self) {
+        for cell in &mut self.cells {
+            cell.clear();
+        }
+    }
+
+    /// The keys in every cell overlapping a box, sorted and without
+    /// duplicates.
+    pub fn query(&self, top_left: Vector2D, bottom_right: Vector2D) -> Vec<K> {
+        let mut keys: Vec<K> = self
+            .cells_overlapping(top_left, bottom_right)
+            .flat_map(|index| self.cells[index].iter().copied())
+            .collect();
+        keys.sort_unstable();
+        keys.dedup();
+        keys
+    }
+
+    /// The keys in every cell overlapping a circle's bounding box.
+    pub fn query_radius(&self, center: Vector2D, radius: f64) -> Vec<K> {
+        let extent = Vector2D::new(radius, radius);
+        self.query(center - extent, center + extent)
+    }
+
+    fn cells_overlapping(
+        &self,
+        top_left: Vector2D,
+        bottom_right: Vector2D,
+    ) -> impl Iterator<Item = usize> {
+        let (min_x, min_y) = self.cell_at(top_left);
+        let (max_x, max_y) = self.cell_at(bottom_right);
+        let cells_x = self.cells_x;
+        (min_y..=max_y).flat_map(move |y| (min_x..=max_x).map(move |x| y * cells_x + x))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn points_and_areas_land_in_the_right_cells() {
+        let mut space = CellSpacePartition::new(100.0, 100.0, 10, 10);
+        assert_eq!(space.num_cells(), 100);
+        assert_eq!(space.cell_at(Vector2D::new(15.0, 95.0)), (1, 9));
+        assert_eq!(space.cell_at(Vector2D::new(-5.0, 150.0)), (0, 9));
+
+        space.add(1, Vector2D::new(15.0, 15.0));
+        space.add(2, Vector2D::new(85.0, 85.0));
+        space.add_area(3, Vector2D::new(5.0, 5.0), Vector2D::new(25.0, 15.0));
+
+        assert_eq!(space.cell(Vector2D::new(12.0, 18.0)), &[1, 3]);
+        assert_eq!(space.cell(Vector2D::new(21.0, 11.0)), &[3]);
+        assert_eq!(
+            space.query_radius(Vector2D::new(15.0, 15.0), 3.0),
+            vec![1, 3]
+        );
+        assert_eq!(
+            space.query(Vector2D::new(0.0, 0.0), Vector2D::new(100.0, 100.0)),
+            vec![1, 2, 3]
+        );
+    }
+
+    #[test]
+    fn moving_keys_between_cells() {
+        let mut space = CellSpacePartition::new(100.0, 100.0, 4, 4);
+        let (start, end) = (Vector2D::new(10.0, 10.0), Vector2D::new(90.0, 10.0));
+        space.add(7, start);
+
+        space.update(7, start, Vector2D::new(12.0, 12.0));
+        assert_eq!(space.cell(start), &[7]);
+
+        space.update(7, start, end);
+        assert!(space.cell(start).is_empty());
+        assert_eq!(space.cell(end), &[7]);
+
+        assert!(space.remove(7, end));
+        assert!(!space.remove(7, end));
+    }
+}
@@ -0,0 +1,136 @@
+//! Tuning constants shared by the westworld examples, loaded from a RON file
+//! with `--config=FILE` so thresholds can be tried out without recompiling.
+//!
+//! A config file looks like this, and any field left out keeps its default:
+//!
+//! ```text
+//! (
+//!     comfort_level: 5,
+//!     max_nuggets: 3,
+//!     thirst_level: 5,
+//!     tiredness_threshold: 5,
+//!     tick_interval_ms: 800,
+//! )
+//! ```
+
+use serde::{Deserialize, Serialize};
+use std::time::Duration;
+use std::{error, fmt, fs, io, path::Path};
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Config {
+    /// The amount of gold a miner must have before he feels comfortable.
+    pub comfort_level: i32,
+    /// The amount of nuggets a miner can carry.
+    pub max_nuggets: i32,
+    /// Above this value a miner is thirsty.
+    pub thirst_level: i32,
+    /// Above this value a miner is sleepy.
+    pub tiredness_threshold: i32,
+    /// Milliseconds between ticks, unless `--tick-ms=N` says otherwise.
+    pub tick_interval_ms: u64,
+}
+
+impl Config {
+    pub fn tick_interval(&self) -> Duration {
+        Duration::from_millis(self.tick_interval_ms)
+    }
+
+    /// Parses a config, refusing one whose ticks would take no time at all.
+    pub fn from_ron_str(s: &str) -> Result<Self, ConfigError> {
+        let config: Config = ron::from_str(s)?;
+        if config.tick_interval_ms == 0 {
+            return Err(ConfigError::ZeroTickInterval);
+        }
+        Ok(config)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ConfigError> {
+        Self::from_ron_str(&fs::read_to_string(path)?)
+    }
+
+    /// Loads `--config=FILE` if it's given, or the defaults if not.
+    pub fn from_args<I: IntoIterator<Item = String>>(args: I) -> Result<Self, ConfigError> {
+        match args
+            .into_iter()
+            .find_map(|arg| arg.strip_prefix("--config=").map(str::to_string))
+        {
+            Some(path) => Self::load(path),
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+impl Default for Config {
+    fn default() -> Self {
+        Config {
+            comfort_level: 5,
+            max_nuggets: 3,
+            thirst_level: 5,
+            tiredness_threshold: 5,
+            tick_interval_ms: 800,
+        }
+    }
+}
+
+/// Errors raised while reading a config file.
+#[derive(Debug)]
+pub enum ConfigError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    /// `tick_interval_ms` was 0.
+    ZeroTickInterval,
+}
+
+impl fmt::Display for ConfigError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ConfigError::Io(e) => write!(f, "config file i/o error: {}", e),
+            ConfigError::Parse(e) => write!(f, "could not parse config: {}", e),
+            ConfigError::ZeroTickInterval => write!(f, "tick_interval_ms has to be at least 1"),
+        }
+    }
+}
+
+impl error::Error for ConfigError {}
+
+impl From<io::Error> for ConfigError {
+    fn from(e: io::Error) -> Self {
+        ConfigError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for ConfigError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        ConfigError::Parse(e)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_fields_keep_their_defaults() {
+        let config = Config::from_ron_str("(max_nuggets: 10, tick_interval_ms: 50)").unwrap();
+        assert_eq!(config.max_nuggets, 10);
+        assert_eq!(config.tick_interval(), Duration::from_millis(50));
+        assert_eq!(config.thirst_level, Config::default().thirst_level);
+    }
+
+    #[test]
+    fn ticks_have_to_take_some_time() {
+        assert!(matches!(
+            Config::from_ron_str("(tick_interval_ms: 0)"),
+            Err(ConfigError::ZeroTickInterval)
+        ));
+        assert!(Config::from_ron_str("(tick_interval_ms: 1)").is_ok());
+    }
+
+    #[test]
+    fn no_config_flag_means_the_defaults() {
+        let config = Config::from_args(vec!["--fast".to_string()]).unwrap();
+        assert_eq!(config, Config::default());
+    }
+}
@@ -0,0 +1,307 @@
+//! State machines written as data rather than code, so simple characters can
+//! be put together without touching Rust. A machine works on an agent that's
+//! nothing but named numbers, its [`Stats`]: each state changes some of them
+//! every update, and moves on to another state once they say it should.
+//!
+//! A machine file is RON, or JSON if its name ends in `.json`:
+//!
+//! ```text
+//! (
+//!     initial: "Sleeping",
+//!     states: {
+//!         "Sleeping": (effects: [Add("fatigue", -1)], says: Some("ZZZZ...")),
+//!         "Mining": (effects: [Add("gold", 1), Add("fatigue", 1)]),
+//!     },
+//!     transitions: [
+//!         (from: "Sleeping", to: "Mining", when: [AtMost("fatigue", 0)]),
+//!         (from: "Mining", to: "Sleeping", when: [Above("fatigue", 5)]),
+//!     ],
+//! )
+//! ```
+//!
+//! A transition is taken once all of its guards hold after an update, the
+//! first listed winning if more than one could be. Stats an agent hasn't got
+//! count as 0.
+
+use serde::{Deserialize, Serialize};
+use std::collections::{BTreeMap, HashMap};
+use std::{error, fmt, fs, io, path::Path};
+
+/// The agent a machine runs on: its stats, by name.
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Stats(pub BTreeMap<String, i32>);
+
+impl Stats {
+    pub fn get(&self, stat: &str) -> i32 {
+        self.0.get(stat).copied().unwrap_or(0)
+    }
+
+    pub fn set(&mut self, stat: &str, value: i32) {
+        self.0.insert(stat.to_string(), value);
+    }
+}
+
+/// A change a state makes to its agent's stats each update.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Effect {
+    Add(String, i32),
+    Set(String, i32),
+    /// Moves all of the first stat onto the second.
+    Move(String, String),
+}
+
+impl Effect {
+    fn apply(&self, stats: &mut Stats) {
+        match self {
+            Effect::Add(stat, by) => stats.set(stat, stats.get(stat) + by),
+            Effect::Set(stat, value) => stats.set(stat, *value),
+            Effect::Move(from, to) => {
+                stats.set(to, stats.get(to) + stats.get(from));
+                stats.set(from, 0);
+            }
+        }
+    }
+}
+
+/// A comparison of one of the agent's stats with a number.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Guard {
+    Above(String, i32),
+    AtLeast(String, i32),
+    Below(String, i32),
+    AtMost(String, i32),
+    Equals(String, i32),
+}
+
+impl Guard {
+    pub fn holds(&self, stats: &Stats) -> bool {
+        match self {
+            Guard::Above(stat, value) => stats.get(stat) > *value,
+            Guard::AtLeast(stat, value) => stats.get(stat) >= *value,
+            Guard::Below(stat, value) => stats.get(stat) < *value,
+            Guard::AtMost(stat, value) => stats.get(stat) <= *value,
+            Guard::Equals(stat, value) => stats.get(stat) == *value,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct StateDef {
+    /// Applied in order every update.
+    pub effects: Vec<Effect>,
+    /// What the agent says every update.
+    pub says: Option<String>,
+}
+
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct TransitionDef {
+    pub from: String,
+    pub to: String,
+    #[serde(default)]
+    pub when: Vec<Guard>,
+    /// What the agent says on the way.
+    #[serde(default)]
+    pub says: Option<String>,
+}
+
+/// A machine as it's written in its file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MachineDef {
+    pub initial: String,
+    pub states: BTreeMap<String, StateDef>,
+    #[serde(default)]
+    pub transitions: Vec<TransitionDef>,
+}
+
+impl MachineDef {
+    pub fn from_ron_str(s: &str) -> Result<Self, MachineFileError> {
+        Ok(ron::from_str(s)?)
+    }
+
+    pub fn from_json_str(s: &str) -> Result<Self, MachineFileError> {
+        Ok(serde_json::from_str(s)?)
+    }
+
+    /// Loads a machine file, as JSON if it ends in `.json` and RON if not.
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, MachineFileError> {
+        let path = path.as_ref();
+        let s = fs::read_to_string(path)?;
+        if path
+            .extension()
+            .is_some_and(|extension| extension == "json")
+        {
+            Self::from_json_str(&s)
+        } else {
+            Self::from_ron_str(&s)
+        }
+    }
+}
+
+/// Errors raised while reading a machine file, or making a machine from it.
+#[derive(Debug)]
+pub enum MachineFileError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    ParseJson(serde_json::Error),
+    /// The initial state, or one a transition's from or to, that isn't one
+    /// of the states.
+    UnknownState(String),
+}
+
+impl fmt::Display for MachineFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            MachineFileError::Io(e) => write!(f, "machine file i/o error: {}", e),
+            MachineFileError::Parse(e) => write!(f, "could not parse machine: {}", e),
+            MachineFileError::ParseJson(e) => write!(f, "could not parse machine: {}", e),
+            MachineFileError::UnknownState(name) => write!(f, "no state called {:?}", name),
+        }
+    }
+}
+
+impl error::Error for MachineFileError {}
+
+impl From<io::Error> for MachineFileError {
+    fn from(e: io::Error) -> Self {
+        MachineFileError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for MachineFileError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        MachineFileError::Parse(e)
+    }
+}
+
+impl From<serde_json::Error> for MachineFileError {
+    fn from(e: serde_json::Error) -> Self {
+        MachineFileError::ParseJson(e)
+    }
+}
+
+/// A machine made from its [`MachineDef`], running on one agent.
+#[derive(Clone, Debug)]
+pub struct DataFsm {
+    names: Vec<String>,
+    states: Vec<StateDef>,
+    /// Each state's transitions out, by index, in the order they're listed.
+    transitions: Vec<Vec<(usize, TransitionDef)>>,
+    current: usize,
+}
+
+impl DataFsm {
+    /// The machine `def` describes, in its initial state.
+    pub fn new(def: MachineDef) -> Result<Self, MachineFileError> {
+        let index: HashMap<&str, usize> = def
+            .states
+            .keys()
+            .enumerate()
+            .map(|(i, name)| (name.as_str(), i))
+            .collect();
+        let find = |name: &str| {
+            index
+                .get(name)
+                .copied()
+                .ok_or_else(|| MachineFileError::UnknownState(name.to_string()))
+        };
+        let current = find(&def.initial)?;
+        let mut transitions = vec![Vec::new(); def.states.len()];
+        for transition in def.transitions.iter().cloned() {
+            let from = find(&transition.from)?;
+            let to = find(&transition.to)?;
+            transitions[from].push((to, transition));
+        }
+        Ok(DataFsm {
+            names: def.states.keys().cloned().collect(),
+            states: def.states.into_values().collect(),
+            transitions,
+            current,
+        })
+    }
+
+    /// The name of the state it's in.
+    pub fn state(&self) -> &str {
+        &self.names[self.current]
+    }
+
+    /// Runs the state it's in on `stats`, and moves on if a transition out
+    /// of it says to. Returns whatever the agent said along the way.
+    pub fn update(&mut self, stats: &mut Stats) -> Vec<&str> {
+        let state = &self.states[self.current];
+        for effect in &state.effects {
+            effect.apply(stats);
+        }
+        let mut said: Vec<&str> = state.says.as_deref().into_iter().collect();
+        let taken = self.transitions[self.current]
+            .iter()
+            .find(|(_, transition)| transition.when.iter().all(|guard| guard.holds(stats)));
+        if let Some((to, transition)) = taken {
+            said.extend(transition.says.as_deref());
+            self.current = *to;
+        }
+        said
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    const SLEEPER: &str = r#"(
+        initial: "Sleeping",
+        states: {
+            "Sleeping": (effects: [Add("fatigue", -1)], says: Some("ZZZZ...")),
+            "Mining": (effects: [Add("gold", 1), Add("fatigue", 1)]),
+        },
+        transitions: [
+            (from: "Sleeping", to: "Mining", when: [AtMost("fatigue", 0)], says: Some("Up!")),
+            (from: "Mining", to: "Sleeping", when: [Above("fatigue", 2)]),
+        ],
+    )"#;
+
+    #[test]
+    fn moves_on_once_the_guards_hold() {
+        let mut fsm = DataFsm::new(MachineDef::from_ron_str(SLEEPER).unwrap()).unwrap();
+        let mut stats = Stats::default();
+        stats.set("fatigue", 1);
+
+        assert_eq!(fsm.update(&mut stats), vec!["ZZZZ...", "Up!"]);
+        assert_eq!(fsm.state(), "Mining");
+        for _ in 0..2 {
+            fsm.update(&mut stats);
+            assert_eq!(fsm.state(), "Mining");
+        }
+        fsm.update(&mut stats);
+        assert_eq!(fsm.state(), "Sleeping");
+        assert_eq!((stats.get("gold"), stats.get("fatigue")), (3, 3));
+    }
+
+    #[test]
+    fn reads_json_as_well() {
+        let def = MachineDef::from_json_str(
+            r#"{
+                "initial": "Banking",
+                "states": { "Banking": { "effects": [{ "Move": ["gold", "bank"] }] } }
+            }"#,
+        )
+        .unwrap();
+        let mut fsm = DataFsm::new(def).unwrap();
+        let mut stats = Stats::default();
+        stats.set("gold", 4);
+        fsm.update(&mut stats);
+        assert_eq!((stats.get("gold"), stats.get("bank")), (0, 4));
+    }
+
+    #[test]
+    fn transitions_to_no_such_state_are_refused() {
+        let def = MachineDef::from_ron_str(
+            r#"(initial: "Idle", states: {"Idle": ()}, transitions: [(from: "Idle", to: "Dancing")])"#,
+        )
+        .unwrap();
+        assert!(matches!(
+            DataFsm::new(def),
+            Err(MachineFileError::UnknownState(name)) if name == "Dancing"
+        ));
+    }
+}
@@ -0,0 +1,326 @@
+//! The common model for agents and other things in the world: every entity
+//! has a unique id, a type tag, a position and a bounding radius, and can be
+//! updated and sent messages.
+
+use crate::cell_space::CellSpacePartition;
+use crate::messaging::Telegram;
+use crate::vector::Vector2D;
+use std::sync::atomic::{AtomicUsize, Ordering};
+
+/// The entity type of entities that haven't been given one.
+pub const DEFAULT_ENTITY_TYPE: i32 = -1;
+
+/// Hands out unique ids. Safe to share between threads.
+#[derive(Debug, Default)]
+pub struct IdGenerator {
+    next: AtomicUsize,
+}
+
+impl IdGenerator {
+    pub const fn new() -> Self {
+        IdGenerator {
+            next: AtomicUsize::new(0),
+        }
+    }
+
+    pub fn next_id(&self) -> usize {
+        self.next.fetch_add(1, Ordering::Relaxed)
+    }
+
+    /// Makes sure `id` will never be handed out, e.g. for entities loaded
+    /// from a save with ids already assigned.
+    pub fn reserve(&self, id: usize) {
+        self.next.fetch_max(id + 1, Ordering::Relaxed);
+    }
+
+    /// Starts again from zero, e.g. when a new game is started.
+    pub fn reset(&self) {
+        self.next.store(0, Ordering::Relaxed);
+    }
+}
+
+static ENTITY_IDS: IdGenerator = IdGenerator::new();
+
+/// The next unique entity id, shared by every [`EntityBase`] in the program.
+pub fn next_entity_id() -> usize {
+    ENTITY_IDS.next_id()
+}
+
+/// The state every entity has, for embedding in an entity's own struct.
+#[derive(Clone, Debug, PartialEq)]
+pub struct EntityBase {
+    id: usize,
+    pub entity_type: i32,
+    pub position: Vector2D,
+    pub bounding_radius: f64,
+    /// General purpose marker, e.g. for neighbours found this update.
+    pub tagged: bool,
+}
+
+impl EntityBase {
+    /// A base with the next unique id.
+    pub fn new(entity_type: i32, position: Vector2D, bounding_radius: f64) -> Self {
+        EntityBase::with_id(next_entity_id(), entity_type, position, bounding_radius)
+    }
+
+    /// A base with an id chosen by the caller, which is then never handed
+    /// out by [`next_entity_id`].
+    pub fn with_id(id: usize, entity_type: i32, position: Vector2D, bounding_radius: f64) -> Self {
+        ENTITY_IDS.reserve(id);
+        EntityBase {
+            id,
+            entity_type,
+            position,
+            bounding_radius,
+            tagged: false,
+        }
+    }
+
+    pub fn id(&self) -> usize {
+        self.id
+    }
+}
+
+impl Default for EntityBase {
+    fn default() -> Self {
+        EntityBase::new(DEFAULT_ENTITY_TYPE, Vector2D::zero(), 0.0)
+    }
+}
+
+/// An entity in the world. Implementors only need to provide access to their
+/// [`EntityBase`]; the hooks do nothing by default.
+pub trait BaseGameEntity<M = ()> {
+    fn base(&self) -> &EntityBase;
+
+    fn base_mut(&mut self) -> &mut EntityBase;
+
+    fn id(&self) -> usize {
+        self.base().id()
+    }
+
+    fn entity_type(&self) -> i32 {
+        self.base().entity_type
+    }
+
+    fn position(&self) -> Vector2D {
+        self.base().position
+    }
+
+    fn set_position(&mut self, position: Vector2D) {
+        self.base_mut().position = position;
+    }
+
+    fn bounding_radius(&self) -> f64 {
+        self.base().bounding_radius
+    }
+
+    fn is_tagged(&self) -> bool {
+        self.base().tagged
+    }
+
+    fn tag(&mut self) {
+        self.base_mut().tagged = true;
+    }
+
+    fn untag(&mut self) {
+        self.base_mut().tagged = false;
+    }
+
+    /// Advances the entity by `time_elapsed` seconds.
+    fn update(&mut self, _time_elapsed: f64) {}
+
+    /// Draws the entity, for examples that have something to draw on.
+    fn render(&self) {}
+
+    /// Returns true if the message was handled.
+    fn handle_message(&mut self, _telegram: &Telegram<M>) -> bool {
+        false
+    }
+}
+
+impl BaseGameEntity for EntityBase {
+    fn base(&self) -> &EntityBase {
+        self
+    }
+
+    fn base_mut(&mut self) -> &mut EntityBase {
+        self
+    }
+}
+
+fn is_neighbor<A, B, M, N>(entity: &A, other: &B, radius: f64) -> bool
+where
+    A: BaseGameEntity<M> + ?Sized,
+    B: BaseGameEntity<N> + ?Sized,
+{
+    let range = radius + other.bounding_radius();
+    other.id() != entity.id() && (other.position() - entity.position()).length_sq() < range * range
+}
+
+/// Tags the entities within `radius` of `entity`, counting their bounding
+/// radius, and untags the rest. `entity` itself is never tagged, so it can
+/// be a copy of one of `entities` (e.g. its [`EntityBase`]). Returns how many
+/// were tagged.
+pub fn tag_neighbors<'a, A, E, M, N, I>(entity: &A, entities: I, radius: f64) -> usize
+where
+    A: BaseGameEntity<M> + ?Sized,
+    E: BaseGameEntity<N> + ?Sized + 'a,
+    I: IntoIterator<Item = &'a mut E>,
+{
+    let mut tagged = 0;
+    for other in entities {
+        if is_neighbor(entity, other, radius) {
+            other.tag();
+            tagged += 1;
+        } else {
+            other.untag();
+        }
+    }
+    tagged
+}
+
+/// Like [`tag_neighbors`], but only measures the distance to entities in the
+/// cells near `entity`. The partition holds indices into `entities` at their
+/// positions.
+pub fn tag_neighbors_in_cells<A, E, M, N>(
+    entity: &A,
+    entities: &mut [E],
+    partition: &CellSpacePartition<usize>,
+    radius: f64,
+) -> usize
+where
+    A: BaseGameEntity<M> + ?Sized,
+    E: BaseGameEntity<N>,
+{
+    // a neighbour's centre can be out of range by up to its own radius
+    let mut largest_radius: f64 = 0.0;
+    for other in entities.iter_mut() {
+        other.untag();
+        largest_radius = largest_radius.max(other.bounding_radius());
+    }
+
+    let mut tagged = 0;
+    for index in partition.query_radius(entity.position(), radius + largest_radius) {
+        let other = &mut entities[index];
+        if is_neighbor(entity, other, radius) {
+            other.tag();
+            tagged += 1;
+        }
+    }
+    tagged
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::collections::HashSet;
+    use std::thread;
+
+    #[test]
+    fn ids_are_unique_across_threads() {
+        let generator = std::sync::Arc::new(IdGenerator::new());
+        let handles: Vec<_> = (0..4)
+            .map(|_| {
+                let generator = generator.clone();
+                thread::spawn(move || (0..1000).map(|_| generator.next_id()).collect::<Vec<_>>())
+            })
+            .collect();
+
+        let ids: HashSet<usize> = handles
+            .into_iter()
+            .flat_map(|handle| handle.join().unwrap())
+            .collect();
+        assert_eq!(ids.len(), 4000);
+        assert_eq!(generator.next_id(), 4000);
+    }
+
+    #[test]
+    fn reserved_ids_are_skipped() {
+        let generator = IdGenerator::new();
+        generator.reserve(10);
+        assert_eq!(generator.next_id(), 11);
+        generator.reserve(3);
+        assert_eq!(generator.next_id(), 12);
+        generator.reset();
+        assert_eq!(generator.next_id(), 0);
+    }
+
+    struct Cow {
+        base: EntityBase,
+        fed: bool,
+    }
+
+    impl BaseGameEntity<&'static str> for Cow {
+        fn base(&self) -> &EntityBase {
+            &self.base
+        }
+
+        fn base_mut(&mut self) -> &mut EntityBase {
+            &mut self.base
+        }
+
+        fn handle_message(&mut self, telegram: &Telegram<&'static str>) -> bool {
+            self.fed = telegram.message == "Hay";
+            self.fed
+        }
+    }
+
+    #[test]
+    fn entities_get_their_own_ids() {
+        let mut a = Cow {
+            base: EntityBase::new(1, Vector2D::zero(), 2.0),
+            fed: false,
+        };
+        let b = Cow {
+            base: EntityBase::new(1, Vector2D::zero(), 2.0),
+            fed: false,
+        };
+        assert_ne!(a.id(), b.id());
+        assert_eq!(a.entity_type(), 1);
+
+        a.tag();
+        assert!(a.is_tagged() && !b.is_tagged());
+        assert!(a.handle_message(&Telegram::new(b.id(), a.id(), "Hay", 0.0)));
+        assert!(a.fed);
+    }
+
+    fn herd(positions: &[(f64, f64)]) -> Vec<EntityBase> {
+        positions
+            .iter()
+            .map(|&(x, y)| EntityBase::new(1, Vector2D::new(x, y), 1.0))
+            .collect()
+    }
+
+    #[test]
+    fn tags_neighbors_in_range() {
+        let mut herd = herd(&[(0.0, 0.0), (3.0, 0.0), (4.5, 0.0), (10.0, 10.0)]);
+        let bessie = herd[0].clone();
+        herd[3].tag();
+
+        // the third is 4.5 away but its radius reaches inside 4
+        assert_eq!(tag_neighbors(&bessie, herd.iter_mut(), 4.0), 2);
+        let tagged: Vec<bool> = herd.iter().map(|cow| cow.tagged).collect();
+        assert_eq!(tagged, vec![false, true, true, false]);
+    }
+
+    #[test]
+    fn cell_space_tagging_matches_brute_force() {
+        let positions: Vec<(f64, f64)> = (0..200)
+            .map(|i| (f64::from(i * 37 % 100), f64::from(i * 53 % 100)))
+            .collect();
+        let mut brute = herd(&positions);
+        let mut cells = brute.clone();
+        let mut partition = CellSpacePartition::new(100.0, 100.0, 10, 10);
+        for (index, cow) in cells.iter().enumerate() {
+            partition.add(index, cow.position);
+        }
+
+        for i in 0..brute.len() {
+            let cow = brute[i].clone();
+            let expected = tag_neighbors(&cow, brute.iter_mut(), 12.0);
+            let actual = tag_neighbors_in_cells(&cow, &mut cells, &partition, 12.0);
+            assert_eq!(expected, actual);
+            assert_eq!(brute, cells);
+        }
+    }
+}
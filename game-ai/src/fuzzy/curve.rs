@@ -0,0 +1,222 @@
+//! Sampling how a module's output responds to one of its inputs, so the
+//! response can be plotted or checked against a known-good ("golden") copy.
+//!
+//! Curves are saved as two-column CSV with the variable names as the header:
+//!
+//! ```text
+//! DistToTarget,Desirability
+//! 0,12.5
+//! 25,12.5
+//! ```
+
+use super::fuzzy_module::{DefuzzifyMethod, FuzzyModule};
+use std::{error, fmt, fs, io, path::Path};
+
+/// An output variable's crisp value at evenly spaced values of an input.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Curve {
+    pub input: String,
+    pub output: String,
+    pub points: Vec<(f64, f64)>,
+}
+
+/// Errors raised while reading or writing a curve file.
+#[derive(Debug)]
+pub enum CurveError {
+    Io(io::Error),
+    Parse { line: usize, message: String },
+}
+
+impl fmt::Display for CurveError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            CurveError::Io(e) => write!(f, "curve file i/o error: {}", e),
+            CurveError::Parse { line, message } => {
+                write!(f, "could not parse curve at line {}: {}", line, message)
+            }
+        }
+    }
+}
+
+impl error::Error for CurveError {}
+
+impl From<io::Error> for CurveError {
+    fn from(e: io::Error) -> Self {
+        CurveError::Io(e)
+    }
+}
+
+impl FuzzyModule {
+    /// Fuzzifies `samples` values of `input` from `from` to `to` inclusive,
+    /// defuzzifying `output` at each. Other inputs keep whatever values they
+    /// were last given.
+    ///
+    /// Panics if either variable doesn't exist.
+    pub fn sweep(
+        &mut self,
+        input: &str,
+        output: &str,
+        method: DefuzzifyMethod,
+        (from, to): (f64, f64),
+        samples: usize,
+    ) -> Curve {
+        let step = if samples > 1 {
+            (to - from) / (samples - 1) as f64
+        } else {
+            0.0
+        };
+
+        let points = (0..samples)
+            .map(|i| {
+                let x = from + step * i as f64;
+                self.fuzzify(input, x);
+                (x, self.defuzzify(output, method))
+            })
+            .collect();
+
+        Curve {
+            input: input.to_string(),
+            output: output.to_string(),
+            points,
+        }
+    }
+}
+
+impl Curve {
+    pub fn to_csv(&self) -> String {
+        let mut csv = format!("{},{}\n", self.input, self.output);
+        for (x, y) in &self.points {
+            csv += &format!("{},{}\n", x, y);
+        }
+        csv
+    }
+
+    pub fn from_csv(s: &str) -> Result<Self, CurveError> {
+        let mut lines = s
+            .lines()
+            .enumerate()
+            .filter(|(_, line)| !line.trim().is_empty());
+
+        let (input, output) = match lines.next() {
+            Some((_, header)) => split_pair(header, 1)?,
+            None => {
+                return Err(CurveError::Parse {
+                    line: 1,
+                    message: "missing header".to_string(),
+                })
+            }
+        };
+
+        let points = lines
+            .map(|(index, line)| {
+                let (x, y) = split_pair(line, index + 1)?;
+                Ok((parse_value(x, index + 1)?, parse_value(y, index + 1)?))
+            })
+            .collect::<Result<_, CurveError>>()?;
+
+        Ok(Curve {
+            input: input.to_string(),
+            output: output.to_string(),
+            points,
+        })
+    }
+
+    pub fn save_csv<P: AsRef<Path>>(&self, path: P) -> Result<(), CurveError> {
+        fs::write(path, self.to_csv())?;
+        Ok(())
+    }
+
+    pub fn load_csv<P: AsRef<Path>>(path: P) -> Result<Self, CurveError> {
+        Self::from_csv(&fs::read_to_string(path)?)
+    }
+
+    /// The curve as whitespace separated columns, ready for
+    /// `plot "curve.dat" with lines` in gnuplot.
+    pub fn to_gnuplot(&self) -> String {
+        let mut data = format!("# {} {}\n", self.input, self.output);
+        for (x, y) in &self.points {
+            data += &format!("{} {}\n", x, y);
+        }
+        data
+    }
+
+    /// The input where the two curves differ most, and by how much, or
+    /// `None` if they weren't sampled at the same inputs.
+    pub fn max_deviation(&self, other: &Curve) -> Option<(f64, f64)> {
+        if self.points.len() != other.points.len() {
+            return None;
+        }
+
+        let mut worst = (self.points.first()?.0, 0.0);
+        for (&(x, y), &(other_x, other_y)) in self.points.iter().zip(&other.points) {
+            if x != other_x {
+                return None;
+            }
+            let deviation = (y - other_y).abs();
+            if deviation > worst.1 {
+                worst = (x, deviation);
+            }
+        }
+        Some(worst)
+    }
+}
+
+fn split_pair(line: &str, line_number: usize) -> Result<(&str, &str), CurveError> {
+    let mut fields = line.split(',').map(str::trim);
+    match (fields.next(), fields.next(), fields.next()) {
+        (Some(first), Some(second), None) => Ok((first, second)),
+        _ => Err(CurveError::Parse {
+            line: line_number,
+            message: format!("expected two columns, got {:?}", line),
+        }),
+    }
+}
+
+fn parse_value(field: &str, line_number: usize) -> Result<f64, CurveError> {
+    field.parse().map_err(|_| CurveError::Parse {
+        line: line_number,
+        message: format!("{:?} is not a number", field),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn curve() -> Curve {
+        Curve {
+            input: "Thirst".to_string(),
+            output: "Desirability".to_string(),
+            points: vec![(0.0, 12.5), (0.5, 1.0 / 3.0), (1.0, 87.5)],
+        }
+    }
+
+    #[test]
+    fn csv_round_trip_is_exact() {
+        let csv = curve().to_csv();
+        assert!(csv.starts_with("Thirst,Desirability\n0,12.5\n"));
+        assert_eq!(Curve::from_csv(&csv).unwrap(), curve());
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        match Curve::from_csv("x,y\n0,1\n1,one\n") {
+            Err(CurveError::Parse { line: 3, .. }) => (),
+            other => panic!("expected a parse error, got {:?}", other),
+        }
+        assert!(Curve::from_csv("").is_err());
+        assert!(Curve::from_csv("x,y\n0,1,2\n").is_err());
+    }
+
+    #[test]
+    fn max_deviation_needs_matching_inputs() {
+        let mut other = curve();
+        other.points[2].1 = 86.0;
+        assert_eq!(curve().max_deviation(&other), Some((1.0, 1.5)));
+
+        other.points[1].0 = 0.6;
+        assert_eq!(curve().max_deviation(&other), None);
+        other.points.pop();
+        assert_eq!(curve().max_deviation(&other), None);
+    }
+}
@@ -0,0 +1,210 @@
+use super::term::{FuzzyTerm, FzSet};
+use super::variable::FuzzyVariable;
+
+/// How an output variable's set memberships are turned back into a value.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum DefuzzifyMethod {
+    /// Average of the sets' representative values weighted by membership.
+    /// Cheap, and usually close enough.
+    MaxAv,
+    /// Centre of mass of the clipped sets, sampled at this many points.
+    Centroid(usize),
+}
+
+/// If the antecedent holds, the consequence set of an output variable
+/// holds to the same degree.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuzzyRule {
+    pub antecedent: FuzzyTerm,
+    pub consequence: FzSet,
+}
+
+impl FuzzyRule {
+    pub fn new<A: Into<FuzzyTerm>>(antecedent: A, consequence: FzSet) -> Self {
+        FuzzyRule {
+            antecedent: antecedent.into(),
+            consequence,
+        }
+    }
+}
+
+/// A set of fuzzy variables and the rules between them.
+#[derive(Clone, Debug, Default)]
+pub struct FuzzyModule {
+    variables: Vec<FuzzyVariable>,
+    rules: Vec<FuzzyRule>,
+    // degree of membership of every set, indexed by variable then set
+    doms: Vec<Vec<f64>>,
+}
+
+impl FuzzyModule {
+    pub fn new() -> Self {
+        FuzzyModule::default()
+    }
+
+    /// Creates a variable, or returns the existing one with the same name.
+    pub fn create_variable(&mut self, name: &str) -> &mut FuzzyVariable {
+        let id = match self.variable_id(name) {
+            Some(id) => id,
+            None => {
+                self.variables
+                    .push(FuzzyVariable::new(self.variables.len(), name.to_string()));
+                self.variables.len() - 1
+            }
+        };
+        &mut self.variables[id]
+    }
+
+    pub fn variable(&self, name: &str) -> Option<&FuzzyVariable> {
+        self.variable_id(name).map(|id| &self.variables[id])
+    }
+
+    pub fn variable_mut(&mut self, name: &str) -> Option<&mut FuzzyVariable> {
+        self.variable_id(name)
+            .map(move |id| &mut self.variables[id])
+    }
+
+    fn variable_id(&self, name: &str) -> Option<usize> {
+        self.variables
+            .iter()
+            .position(|variable| variable.name() == name)
+    }
+
+    pub fn add_rule<A: Into<FuzzyTerm>>(&mut self, antecedent: A, consequence: FzSet) {
+        self.rules.push(FuzzyRule::new(antecedent, consequence));
+    }
+
+    /// Adds several rules at once, e.g. those built by
+    /// [`fuzzy_rules!`](crate::fuzzy_rules).
+    pub fn add_rules<I: IntoIterator<Item = FuzzyRule>>(&mut self, rules: I) {
+        self.rules.extend(rules);
+    }
+
+    pub fn rules(&self) -> &[FuzzyRule] {
+        &self.rules
+    }
+
+    /// Sets the crisp value of an input variable.
+    ///
+    /// Panics if there's no variable called `name`.
+    pub fn fuzzify(&mut self, name: &str, value: f64) {
+        let id = self
+            .variable_id(name)
+            .unwrap_or_else(|| panic!("no fuzzy variable called {}", name));
+        self.resize_doms();
+        self.doms[id] = self.variables[id].fuzzify(value);
+    }
+
+    /// Fires every rule against the current inputs and returns the crisp
+    /// value of the output variable `name`.
+    ///
+    /// Panics if there's no variable called `name`.
+    pub fn defuzzify(&mut self, name: &str, method: DefuzzifyMethod) -> f64 {
+        let id = self
+            .variable_id(name)
+            .unwrap_or_else(|| panic!("no fuzzy variable called {}", name));
+        self.resize_doms();
+
+        for dom in &mut self.doms[id] {
+            *dom = 0.0;
+        }
+        for rule in &self.rules {
+            let FzSet { variable, set } = rule.consequence;
+            if variable == id {
+                let dom = rule.antecedent.dom(&self.doms);
+                self.doms[variable][set] = self.doms[variable][set].max(dom);
+            }
+        }
+
+        let variable = &self.variables[id];
+        match method {
+            DefuzzifyMethod::MaxAv => variable.defuzzify_max_av(&self.doms[id]),
+            DefuzzifyMethod::Centroid(samples) => {
+                variable.defuzzify_centroid(&self.doms[id], samples)
+            }
+        }
+    }
+
+    // sets may have been added since the last call
+    fn resize_doms(&mut self) {
+        self.doms.resize(self.variables.len(), vec![]);
+        for (doms, variable) in self.doms.iter_mut().zip(&self.variables) {
+            doms.resize(variable.num_sets(), 0.0);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::fuzzy::FuzzySet;
+
+    /// Thirstier miners want a drink more.
+    fn thirst_module() -> FuzzyModule {
+        let mut module = FuzzyModule::new();
+
+        let thirst = module.create_variable("Thirst");
+        let quenched = thirst.add_set("Quenched", FuzzySet::triangle(-10.0, 0.0, 10.0));
+        let parched = thirst.add_set("Parched", FuzzySet::triangle(0.0, 10.0, 20.0));
+
+        let desirability = module.create_variable("Desirability");
+        let low = desirability.add_set("Low", FuzzySet::triangle(0.0, 25.0, 50.0));
+        let high = desirability.add_set("High", FuzzySet::triangle(50.0, 75.0, 100.0));
+
+        module.add_rule(quenched, low);
+        module.add_rule(parched, high);
+        module
+    }
+
+    #[test]
+    fn max_av_weights_representative_values() {
+        let mut module = thirst_module();
+
+        module.fuzzify("Thirst", 0.0);
+        assert_eq!(
+            module.defuzzify("Desirability", DefuzzifyMethod::MaxAv),
+            25.0
+        );
+
+        module.fuzzify("Thirst", 10.0);
+        assert_eq!(
+            module.defuzzify("Desirability", DefuzzifyMethod::MaxAv),
+            75.0
+        );
+
+        // a quarter quenched, three quarters parched
+        module.fuzzify("Thirst", 7.5);
+        let value = module.defuzzify("Desirability", DefuzzifyMethod::MaxAv);
+        assert!((value - (0.25 * 25.0 + 0.75 * 75.0)).abs() < 1e-9);
+    }
+
+    #[test]
+    fn centroid_agrees_with_max_av_for_symmetric_sets() {
+        let mut module = thirst_module();
+        module.fuzzify("Thirst", 5.0);
+
+        let max_av = module.defuzzify("Desirability", DefuzzifyMethod::MaxAv);
+        let centroid = module.defuzzify("Desirability", DefuzzifyMethod::Centroid(101));
+        assert!((max_av - 50.0).abs() < 1e-9);
+        assert!((centroid - 50.0).abs() < 1e-9);
+    }
+
+    #[test]
+    fn variables_are_found_by_name() {
+        let mut module = thirst_module();
+        let thirst = module.variable("Thirst").unwrap();
+        assert_eq!(thirst.range(), (-10.0, 20.0));
+        assert_eq!(
+            thirst.set("Parched"),
+            Some(FzSet {
+                variable: 0,
+                set: 1
+            })
+        );
+        assert!(module.variable("Hunger").is_none());
+
+        // creating an existing variable returns it rather than a duplicate
+        module.create_variable("Thirst");
+        assert_eq!(module.variable("Thirst").unwrap().num_sets(), 2);
+    }
+}
@@ -0,0 +1,91 @@
+/// Builds a `Vec` of [`FuzzyRule`](crate::fuzzy::FuzzyRule)s from rules
+/// written as `if <antecedent> then <consequence>;`.
+///
+/// The antecedent is a single set, a hedged one such as `very(low)`, or a
+/// parenthesised term combining sets with `&` (AND) and `|` (OR) and the
+/// hedges [`very`](crate::fuzzy::very) and [`fairly`](crate::fuzzy::fairly). The consequence is a set of the
+/// output variable.
+///
+/// ```
+/// use game_ai::fuzzy::{very, FuzzyModule};
+/// use game_ai::fuzzy_rules;
+///
+/// let mut module = FuzzyModule::new();
+/// let distance = module.create_variable("Distance");
+/// let close = distance.add_left_shoulder_set("Close", 0.0, 25.0, 150.0);
+/// let far = distance.add_right_shoulder_set("Far", 25.0, 150.0, 1000.0);
+/// let ammo = module.create_variable("Ammo");
+/// let low = ammo.add_left_shoulder_set("Low", 0.0, 0.0, 10.0);
+/// let loads = ammo.add_right_shoulder_set("Loads", 0.0, 10.0, 100.0);
+/// let desirability = module.create_variable("Desirability");
+/// let undesirable = desirability.add_left_shoulder_set("Undesirable", 0.0, 25.0, 50.0);
+/// let desirable = desirability.add_right_shoulder_set("Desirable", 25.0, 50.0, 100.0);
+///
+/// module.add_rules(fuzzy_rules! {
+///     if (close & (loads | very(low))) then desirable;
+///     if very(far) then undesirable;
+/// });
+/// assert_eq!(module.rules().len(), 2);
+/// ```
+#[macro_export]
+macro_rules! fuzzy_rules {
+    (@rules [$($rules:expr,)*]) => {
+        vec![$($rules),*]
+    };
+    // a hedged set or term, e.g. `very(desperate)`
+    (@rules [$($rules:expr,)*] if $hedge:ident ($($term:tt)*) then $consequence:expr $(; $($rest:tt)*)?) => {
+        $crate::fuzzy_rules!(@rules [
+            $($rules,)*
+            $crate::fuzzy::FuzzyRule::new($hedge($($term)*), $consequence),
+        ] $($($rest)*)?)
+    };
+    // a single set, or a parenthesised term
+    (@rules [$($rules:expr,)*] if $antecedent:tt then $consequence:expr $(; $($rest:tt)*)?) => {
+        $crate::fuzzy_rules!(@rules [
+            $($rules,)*
+            {
+                // the term's own parentheses end up around the argument
+                #[allow(unused_parens)]
+                let antecedent = $antecedent;
+                $crate::fuzzy::FuzzyRule::new(antecedent, $consequence)
+            },
+        ] $($($rest)*)?)
+    };
+    ($($rules:tt)*) => {
+        $crate::fuzzy_rules!(@rules [] $($rules)*)
+    };
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::fuzzy::{fairly, fz_and, fz_or, very, FuzzyModule, FuzzyRule};
+
+    #[test]
+    fn rules_match_hand_built_terms() {
+        let mut module = FuzzyModule::new();
+        let distance = module.create_variable("Distance");
+        let close = distance.add_triangle_set("Close", 0.0, 0.0, 10.0);
+        let far = distance.add_triangle_set("Far", 0.0, 10.0, 10.0);
+        let mood = module.create_variable("Mood");
+        let desperate = mood.add_triangle_set("Desperate", 0.0, 0.0, 1.0);
+        let output = module.create_variable("Output");
+        let low = output.add_triangle_set("Low", 0.0, 0.0, 1.0);
+        let high = output.add_triangle_set("High", 0.0, 1.0, 1.0);
+
+        let rules = fuzzy_rules! {
+            if (close & (far | very(desperate))) then high;
+            if (fairly(far) & close & desperate) then low;
+            if very(close) then high;
+            if far then low
+        };
+        assert_eq!(
+            rules,
+            vec![
+                FuzzyRule::new(fz_and(close, fz_or(far, very(desperate))), high),
+                FuzzyRule::new(fz_and(fz_and(fairly(far), close), desperate), low),
+                FuzzyRule::new(very(close), high),
+                FuzzyRule::new(far, low),
+            ]
+        );
+    }
+}
@@ -0,0 +1,21 @@
+//! Fuzzy logic, for decisions that depend on vague notions like "quite
+//! thirsty" or "far away".
+//!
+//! A [`FuzzyModule`] holds linguistic variables, each covered by overlapping
+//! fuzzy sets, and a rule base linking sets of the input variables to sets of
+//! an output variable. Crisp inputs are fuzzified into degrees of membership,
+//! the rules are fired, and the output variable is defuzzified back into a
+//! crisp value.
+
+mod curve;
+mod fuzzy_module;
+mod macros;
+mod set;
+mod term;
+mod variable;
+
+pub use curve::{Curve, CurveError};
+pub use fuzzy_module::{DefuzzifyMethod, FuzzyModule, FuzzyRule};
+pub use set::FuzzySet;
+pub use term::{fairly, fz_and, fz_or, very, FuzzyTerm, FzSet};
+pub use variable::FuzzyVariable;
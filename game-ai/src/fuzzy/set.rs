@@ -0,0 +1,163 @@
+/// A fuzzy set over a variable's range, describing how strongly each value
+/// belongs to it.
+///
+/// Every shape is given by three points on the variable's axis: where
+/// membership starts, where it peaks and where it ends.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum FuzzySet {
+    /// Rises from zero at `left` to full membership at `peak`, then falls
+    /// back to zero at `right`.
+    Triangle { left: f64, peak: f64, right: f64 },
+    /// Full membership from `left` to `peak`, falling to zero at `right`.
+    /// Used for the lowest set of a variable, e.g. "close".
+    LeftShoulder { left: f64, peak: f64, right: f64 },
+    /// Rises from zero at `left` to full membership from `peak` to `right`.
+    /// Used for the highest set of a variable, e.g. "far".
+    RightShoulder { left: f64, peak: f64, right: f64 },
+    /// Full membership anywhere from `left` to `right` and none outside.
+    Singleton { left: f64, peak: f64, right: f64 },
+}
+
+impl FuzzySet {
+    pub fn triangle(left: f64, peak: f64, right: f64) -> Self {
+        FuzzySet::Triangle { left, peak, right }
+    }
+
+    pub fn left_shoulder(left: f64, peak: f64, right: f64) -> Self {
+        FuzzySet::LeftShoulder { left, peak, right }
+    }
+
+    pub fn right_shoulder(left: f64, peak: f64, right: f64) -> Self {
+        FuzzySet::RightShoulder { left, peak, right }
+    }
+
+    pub fn singleton(left: f64, peak: f64, right: f64) -> Self {
+        FuzzySet::Singleton { left, peak, right }
+    }
+
+    /// The degree of membership of `value`, between 0 and 1.
+    pub fn dom(&self, value: f64) -> f64 {
+        match *self {
+            FuzzySet::Triangle { left, peak, right } => {
+                if value == peak {
+                    1.0
+                } else if value > left && value < peak {
+                    rising(left, peak, value)
+                } else if value > peak && value < right {
+                    falling(peak, right, value)
+                } else {
+                    0.0
+                }
+            }
+            FuzzySet::LeftShoulder { left, peak, right } => {
+                if value >= left && value <= peak {
+                    1.0
+                } else if value > peak && value < right {
+                    falling(peak, right, value)
+                } else {
+                    0.0
+                }
+            }
+            FuzzySet::RightShoulder { left, peak, right } => {
+                if value >= peak && value <= right {
+                    1.0
+                } else if value > left && value < peak {
+                    rising(left, peak, value)
+                } else {
+                    0.0
+                }
+            }
+            FuzzySet::Singleton { left, right, .. } => {
+                if value >= left && value <= right {
+                    1.0
+                } else {
+                    0.0
+                }
+            }
+        }
+    }
+
+    /// The value that best represents the set, used by
+    /// [`DefuzzifyMethod::MaxAv`](super::DefuzzifyMethod::MaxAv): the peak,
+    /// or the middle of the plateau for shoulders.
+    pub fn representative_value(&self) -> f64 {
+        match *self {
+            FuzzySet::Triangle { peak, .. } | FuzzySet::Singleton { peak, .. } => peak,
+            FuzzySet::LeftShoulder { left, peak, .. } => (left + peak) / 2.0,
+            FuzzySet::RightShoulder { peak, right, .. } => (peak + right) / 2.0,
+        }
+    }
+
+    /// The lowest and highest values with any membership.
+    pub fn bounds(&self) -> (f64, f64) {
+        match *self {
+            FuzzySet::Triangle { left, right, .. }
+            | FuzzySet::LeftShoulder { left, right, .. }
+            | FuzzySet::RightShoulder { left, right, .. }
+            | FuzzySet::Singleton { left, right, .. } => (left, right),
+        }
+    }
+}
+
+fn rising(left: f64, peak: f64, value: f64) -> f64 {
+    (value - left) / (peak - left)
+}
+
+fn falling(peak: f64, right: f64, value: f64) -> f64 {
+    (right - value) / (right - peak)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn triangle() {
+        let set = FuzzySet::triangle(10.0, 20.0, 40.0);
+        assert_eq!(set.dom(10.0), 0.0);
+        assert_eq!(set.dom(15.0), 0.5);
+        assert_eq!(set.dom(20.0), 1.0);
+        assert_eq!(set.dom(30.0), 0.5);
+        assert_eq!(set.dom(40.0), 0.0);
+        assert_eq!(set.dom(-5.0), 0.0);
+        assert_eq!(set.representative_value(), 20.0);
+
+        // a vertical edge where the peak meets one end
+        let set = FuzzySet::triangle(0.0, 0.0, 10.0);
+        assert_eq!(set.dom(0.0), 1.0);
+        assert_eq!(set.dom(5.0), 0.5);
+    }
+
+    #[test]
+    fn left_shoulder() {
+        let set = FuzzySet::left_shoulder(0.0, 25.0, 150.0);
+        assert_eq!(set.dom(-1.0), 0.0);
+        assert_eq!(set.dom(0.0), 1.0);
+        assert_eq!(set.dom(25.0), 1.0);
+        assert_eq!(set.dom(87.5), 0.5);
+        assert_eq!(set.dom(150.0), 0.0);
+        assert_eq!(set.representative_value(), 12.5);
+    }
+
+    #[test]
+    fn right_shoulder() {
+        let set = FuzzySet::right_shoulder(150.0, 300.0, 1000.0);
+        assert_eq!(set.dom(150.0), 0.0);
+        assert_eq!(set.dom(225.0), 0.5);
+        assert_eq!(set.dom(300.0), 1.0);
+        assert_eq!(set.dom(1000.0), 1.0);
+        assert_eq!(set.dom(1000.1), 0.0);
+        assert_eq!(set.representative_value(), 650.0);
+    }
+
+    #[test]
+    fn singleton() {
+        let set = FuzzySet::singleton(4.0, 5.0, 6.0);
+        assert_eq!(set.dom(3.9), 0.0);
+        assert_eq!(set.dom(4.0), 1.0);
+        assert_eq!(set.dom(6.0), 1.0);
+        assert_eq!(set.dom(6.1), 0.0);
+        assert_eq!(set.representative_value(), 5.0);
+        assert_eq!(set.bounds(), (4.0, 6.0));
+    }
+}
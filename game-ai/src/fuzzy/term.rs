@@ -0,0 +1,152 @@
+use std::ops::{BitAnd, BitOr};
+
+/// A handle to one set of one variable in a [`FuzzyModule`](super::FuzzyModule).
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct FzSet {
+    pub(super) variable: usize,
+    pub(super) set: usize,
+}
+
+/// The antecedent of a fuzzy rule: a tree of sets joined by operators and
+/// modified by hedges.
+///
+/// Terms are usually built with `&`, `|`, [`very`] and [`fairly`] (or
+/// [`fz_and`] and [`fz_or`]) rather than by hand.
+#[derive(Clone, Debug, PartialEq)]
+pub enum FuzzyTerm {
+    /// Holds to the degree the input belongs to the set.
+    Set(FzSet),
+    /// Holds as much as the least true of its terms.
+    And(Vec<FuzzyTerm>),
+    /// Holds as much as the most true of its terms.
+    Or(Vec<FuzzyTerm>),
+    /// Sharpens a term by squaring it, so only strong membership counts.
+    Very(Box<FuzzyTerm>),
+    /// Softens a term by taking its square root.
+    Fairly(Box<FuzzyTerm>),
+}
+
+impl FuzzyTerm {
+    /// How true the term is, given the degree of membership of every set.
+    pub(super) fn dom(&self, doms: &[Vec<f64>]) -> f64 {
+        match self {
+            FuzzyTerm::Set(set) => doms[set.variable][set.set],
+            FuzzyTerm::And(terms) => terms
+                .iter()
+                .map(|term| term.dom(doms))
+                .fold(f64::INFINITY, f64::min)
+                .min(1.0),
+            FuzzyTerm::Or(terms) => terms.iter().map(|term| term.dom(doms)).fold(0.0, f64::max),
+            FuzzyTerm::Very(term) => term.dom(doms).powi(2),
+            FuzzyTerm::Fairly(term) => term.dom(doms).sqrt(),
+        }
+    }
+}
+
+impl From<FzSet> for FuzzyTerm {
+    fn from(set: FzSet) -> Self {
+        FuzzyTerm::Set(set)
+    }
+}
+
+impl<T: Into<FuzzyTerm>> BitAnd<T> for FuzzyTerm {
+    type Output = FuzzyTerm;
+
+    fn bitand(self, other: T) -> FuzzyTerm {
+        fz_and(self, other)
+    }
+}
+
+impl<T: Into<FuzzyTerm>> BitAnd<T> for FzSet {
+    type Output = FuzzyTerm;
+
+    fn bitand(self, other: T) -> FuzzyTerm {
+        fz_and(self, other)
+    }
+}
+
+impl<T: Into<FuzzyTerm>> BitOr<T> for FuzzyTerm {
+    type Output = FuzzyTerm;
+
+    fn bitor(self, other: T) -> FuzzyTerm {
+        fz_or(self, other)
+    }
+}
+
+impl<T: Into<FuzzyTerm>> BitOr<T> for FzSet {
+    type Output = FuzzyTerm;
+
+    fn bitor(self, other: T) -> FuzzyTerm {
+        fz_or(self, other)
+    }
+}
+
+/// Both terms hold. Nested ANDs are flattened into one.
+pub fn fz_and<A: Into<FuzzyTerm>, B: Into<FuzzyTerm>>(a: A, b: B) -> FuzzyTerm {
+    let mut terms = vec![];
+    for term in [a.into(), b.into()] {
+        match term {
+            FuzzyTerm::And(inner) => terms.extend(inner),
+            term => terms.push(term),
+        }
+    }
+    FuzzyTerm::And(terms)
+}
+
+/// Either term holds. Nested ORs are flattened into one.
+pub fn fz_or<A: Into<FuzzyTerm>, B: Into<FuzzyTerm>>(a: A, b: B) -> FuzzyTerm {
+    let mut terms = vec![];
+    for term in [a.into(), b.into()] {
+        match term {
+            FuzzyTerm::Or(inner) => terms.extend(inner),
+            term => terms.push(term),
+        }
+    }
+    FuzzyTerm::Or(terms)
+}
+
+pub fn very<T: Into<FuzzyTerm>>(term: T) -> FuzzyTerm {
+    FuzzyTerm::Very(Box::new(term.into()))
+}
+
+pub fn fairly<T: Into<FuzzyTerm>>(term: T) -> FuzzyTerm {
+    FuzzyTerm::Fairly(Box::new(term.into()))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn set(set: usize) -> FzSet {
+        FzSet { variable: 0, set }
+    }
+
+    #[test]
+    fn operators_and_hedges() {
+        let doms = vec![vec![0.8, 0.3, 0.5]];
+        let (close, low, desperate) = (set(0), set(1), set(2));
+
+        assert_eq!(fz_and(close, low).dom(&doms), 0.3);
+        assert_eq!(fz_or(close, low).dom(&doms), 0.8);
+        assert_eq!(very(desperate).dom(&doms), 0.25);
+        assert!((fairly(low).dom(&doms) - 0.3f64.sqrt()).abs() < 1e-12);
+
+        // IF close AND (low OR VERY(desperate))
+        let term = fz_and(close, fz_or(low, very(desperate)));
+        assert_eq!(term.dom(&doms), 0.3);
+    }
+
+    #[test]
+    fn nested_operators_are_flattened() {
+        let term = fz_and(fz_and(set(0), set(1)), set(2));
+        assert_eq!(
+            term,
+            FuzzyTerm::And(vec![set(0).into(), set(1).into(), set(2).into()])
+        );
+
+        // but not across different operators
+        let term = fz_or(fz_and(set(0), set(1)), set(2));
+        assert!(matches!(&term, FuzzyTerm::Or(terms) if terms.len() == 2));
+        assert_eq!(term.clone(), term);
+    }
+}
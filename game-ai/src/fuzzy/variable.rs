@@ -0,0 +1,141 @@
+use super::set::FuzzySet;
+use super::term::FzSet;
+
+/// A linguistic variable, such as distance to target, split into named
+/// fuzzy sets such as close, medium and far.
+#[derive(Clone, Debug, PartialEq)]
+pub struct FuzzyVariable {
+    id: usize,
+    name: String,
+    sets: Vec<(String, FuzzySet)>,
+    min_range: f64,
+    max_range: f64,
+}
+
+impl FuzzyVariable {
+    pub(super) fn new(id: usize, name: String) -> Self {
+        FuzzyVariable {
+            id,
+            name,
+            sets: vec![],
+            min_range: 0.0,
+            max_range: 0.0,
+        }
+    }
+
+    pub fn name(&self) -> &str {
+        &self.name
+    }
+
+    /// Adds a set, widening the variable's range to cover it, and returns a
+    /// handle for use in rules.
+    pub fn add_set(&mut self, name: &str, set: FuzzySet) -> FzSet {
+        let (min, max) = set.bounds();
+        if self.sets.is_empty() {
+            self.min_range = min;
+            self.max_range = max;
+        } else {
+            self.min_range = self.min_range.min(min);
+            self.max_range = self.max_range.max(max);
+        }
+
+        self.sets.push((name.to_string(), set));
+        FzSet {
+            variable: self.id,
+            set: self.sets.len() - 1,
+        }
+    }
+
+    pub fn add_triangle_set(&mut self, name: &str, left: f64, peak: f64, right: f64) -> FzSet {
+        self.add_set(name, FuzzySet::triangle(left, peak, right))
+    }
+
+    pub fn add_left_shoulder_set(&mut self, name: &str, left: f64, peak: f64, right: f64) -> FzSet {
+        self.add_set(name, FuzzySet::left_shoulder(left, peak, right))
+    }
+
+    pub fn add_right_shoulder_set(
+        &mut self,
+        name: &str,
+        left: f64,
+        peak: f64,
+        right: f64,
+    ) -> FzSet {
+        self.add_set(name, FuzzySet::right_shoulder(left, peak, right))
+    }
+
+    pub fn add_singleton_set(&mut self, name: &str, left: f64, peak: f64, right: f64) -> FzSet {
+        self.add_set(name, FuzzySet::singleton(left, peak, right))
+    }
+
+    /// The handle of the set with the given name.
+    pub fn set(&self, name: &str) -> Option<FzSet> {
+        self.sets
+            .iter()
+            .position(|(set_name, _)| set_name == name)
+            .map(|set| FzSet {
+                variable: self.id,
+                set,
+            })
+    }
+
+    pub fn sets(&self) -> impl Iterator<Item = (&str, &FuzzySet)> {
+        self.sets.iter().map(|(name, set)| (name.as_str(), set))
+    }
+
+    pub fn num_sets(&self) -> usize {
+        self.sets.len()
+    }
+
+    /// The smallest and largest values covered by the variable's sets.
+    pub fn range(&self) -> (f64, f64) {
+        (self.min_range, self.max_range)
+    }
+
+    /// The degree of membership of `value` in each set, in the order the
+    /// sets were added.
+    pub fn fuzzify(&self, value: f64) -> Vec<f64> {
+        self.sets.iter().map(|(_, set)| set.dom(value)).collect()
+    }
+
+    /// Mean of each set's representative value, weighted by its degree of
+    /// membership. Zero if no set has any membership.
+    pub fn defuzzify_max_av(&self, doms: &[f64]) -> f64 {
+        let (mut weighted, mut total) = (0.0, 0.0);
+        for ((_, set), &dom) in self.sets.iter().zip(doms) {
+            weighted += set.representative_value() * dom;
+            total += dom;
+        }
+
+        if total == 0.0 {
+            0.0
+        } else {
+            weighted / total
+        }
+    }
+
+    /// The centre of mass of the sets, each clipped at its degree of
+    /// membership, sampled at `samples` points across the range.
+    pub fn defuzzify_centroid(&self, doms: &[f64], samples: usize) -> f64 {
+        if samples < 2 {
+            return self.defuzzify_max_av(doms);
+        }
+
+        let step = (self.max_range - self.min_range) / (samples - 1) as f64;
+        let (mut moment, mut area) = (0.0, 0.0);
+        for i in 0..samples {
+            let x = self.min_range + step * i as f64;
+            for ((_, set), &dom) in self.sets.iter().zip(doms) {
+                let contribution = set.dom(x).min(dom);
+                moment += x * contribution;
+                area += contribution;
+            }
+        }
+
+        if area == 0.0 {
+            0.0
+        } else {
+            moment / area
+        }
+    }
+}
@@ -0,0 +1,282 @@
+//! 2D geometry shared by perception, steering, triggers and path smoothing:
+//! walls, the line tests made against them, and point containment tests.
+
+use crate::vector::Vector2D;
+
+/// A wall segment. Its normal points out of the side to the left of the
+/// direction from `from` to `to`.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Wall2D {
+    pub from: Vector2D,
+    pub to: Vector2D,
+}
+
+impl Wall2D {
+    pub fn new(from: Vector2D, to: Vector2D) -> Self {
+        Wall2D { from, to }
+    }
+
+    pub fn normal(&self) -> Vector2D {
+        (self.to - self.from).normalize().perp()
+    }
+
+    pub fn center(&self) -> Vector2D {
+        (self.from + self.to) / 2.0
+    }
+}
+
+/// Whether segment `a`–`b` crosses segment `c`–`d`. Segments that only touch
+/// at an end, or that are parallel, don't count as crossing.
+pub fn segments_intersect(a: Vector2D, b: Vector2D, c: Vector2D, d: Vector2D) -> bool {
+    segment_intersection(a, b, c, d).is_some()
+}
+
+/// Where segment `a`–`b` crosses segment `c`–`d`, as the distance from `a`
+/// and the point itself. Follows the same rules as [`segments_intersect`].
+pub fn segment_intersection(
+    a: Vector2D,
+    b: Vector2D,
+    c: Vector2D,
+    d: Vector2D,
+) -> Option<(f64, Vector2D)> {
+    let ab = b - a;
+    let cd = d - c;
+    let denominator = cross(ab, cd);
+    if denominator == 0.0 {
+        return None;
+    }
+
+    let ac = c - a;
+    let r = cross(ac, cd) / denominator;
+    let s = cross(ac, ab) / denominator;
+    if r > 0.0 && r < 1.0 && s > 0.0 && s < 1.0 {
+        Some((r * ab.length(), a + ab * r))
+    } else {
+        None
+    }
+}
+
+/// The point on segment `a`–`b` closest to `point`.
+pub fn closest_point_on_segment(a: Vector2D, b: Vector2D, point: Vector2D) -> Vector2D {
+    let ab = b - a;
+    let length_sq = ab.length_sq();
+    if length_sq == 0.0 {
+        return a;
+    }
+
+    let t = ((point - a).dot(ab) / length_sq).clamp(0.0, 1.0);
+    a + ab * t
+}
+
+pub fn distance_to_segment(a: Vector2D, b: Vector2D, point: Vector2D) -> f64 {
+    distance_to_segment_sq(a, b, point).sqrt()
+}
+
+pub fn distance_to_segment_sq(a: Vector2D, b: Vector2D, point: Vector2D) -> f64 {
+    point.distance_sq(closest_point_on_segment(a, b, point))
+}
+
+/// Whether `point` is strictly inside the circle.
+pub fn point_in_circle(center: Vector2D, radius: f64, point: Vector2D) -> bool {
+    point.distance_sq(center) < radius * radius
+}
+
+/// Whether `point` is inside the axis aligned rectangle, or on its edge.
+pub fn point_in_rect(top_left: Vector2D, bottom_right: Vector2D, point: Vector2D) -> bool {
+    point.x >= top_left.x
+        && point.x <= bottom_right.x
+        && point.y >= top_left.y
+        && point.y <= bottom_right.y
+}
+
+/// Whether `point` is inside the polygon with the given vertices, in either
+/// winding order. Uses the even-odd rule, so the polygon may be concave.
+/// Points exactly on an edge may be counted either way.
+pub fn point_in_polygon(vertices: &[Vector2D], point: Vector2D) -> bool {
+    let mut inside = false;
+    let mut previous = match vertices.last() {
+        Some(&last) => last,
+        None => return false,
+    };
+
+    // count the edges crossed by a ray heading off in the +x direction
+    for &vertex in vertices {
+        if (vertex.y > point.y) != (previous.y > point.y) {
+            let crossing_x =
+                vertex.x + (point.y - vertex.y) / (previous.y - vertex.y) * (previous.x - vertex.x);
+            if point.x < crossing_x {
+                inside = !inside;
+            }
+        }
+        previous = vertex;
+    }
+
+    inside
+}
+
+/// Whether the segment `from`–`to` crosses any of the walls.
+pub fn is_path_obstructed(from: Vector2D, to: Vector2D, walls: &[Wall2D]) -> bool {
+    walls
+        .iter()
+        .any(|wall| segments_intersect(from, to, wall.from, wall.to))
+}
+
+fn cross(a: Vector2D, b: Vector2D) -> f64 {
+    a.x * b.y - a.y * b.x
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use proptest::prelude::*;
+
+    fn v(x: f64, y: f64) -> Vector2D {
+        Vector2D::new(x, y)
+    }
+
+    #[test]
+    fn crossing_segments() {
+        assert!(segments_intersect(
+            v(0.0, 0.0),
+            v(2.0, 2.0),
+            v(0.0, 2.0),
+            v(2.0, 0.0)
+        ));
+        assert!(!segments_intersect(
+            v(0.0, 0.0),
+            v(1.0, 1.0),
+            v(0.0, 2.0),
+            v(2.0, 2.0)
+        ));
+        // parallel
+        assert!(!segments_intersect(
+            v(0.0, 0.0),
+            v(2.0, 0.0),
+            v(0.0, 1.0),
+            v(2.0, 1.0)
+        ));
+        // touching at an end
+        assert!(!segments_intersect(
+            v(0.0, 0.0),
+            v(1.0, 0.0),
+            v(1.0, -1.0),
+            v(1.0, 1.0)
+        ));
+    }
+
+    #[test]
+    fn walls_obstruct_paths_through_them() {
+        let walls = [Wall2D::new(v(5.0, -5.0), v(5.0, 5.0))];
+        assert!(is_path_obstructed(v(0.0, 0.0), v(10.0, 0.0), &walls));
+        assert!(!is_path_obstructed(v(0.0, 0.0), v(4.0, 0.0), &walls));
+        assert!(!is_path_obstructed(v(0.0, 10.0), v(10.0, 10.0), &walls));
+        assert_eq!(walls[0].normal(), v(-1.0, 0.0));
+        assert_eq!(walls[0].center(), v(5.0, 0.0));
+    }
+
+    #[test]
+    fn intersection_point_and_distance() {
+        let (distance, point) =
+            segment_intersection(v(0.0, 0.0), v(4.0, 0.0), v(3.0, -1.0), v(3.0, 1.0)).unwrap();
+        assert_eq!(distance, 3.0);
+        assert_eq!(point, v(3.0, 0.0));
+    }
+
+    #[test]
+    fn distances_to_segments() {
+        let (a, b) = (v(0.0, 0.0), v(10.0, 0.0));
+        assert_eq!(closest_point_on_segment(a, b, v(4.0, 3.0)), v(4.0, 0.0));
+        assert_eq!(closest_point_on_segment(a, b, v(-3.0, 4.0)), a);
+        assert_eq!(closest_point_on_segment(a, b, v(12.0, 0.0)), b);
+        assert_eq!(distance_to_segment(a, b, v(-3.0, 4.0)), 5.0);
+        assert_eq!(distance_to_segment_sq(a, b, v(5.0, -2.0)), 4.0);
+        // a segment of zero length is just a point
+        assert_eq!(distance_to_segment(a, a, v(3.0, 4.0)), 5.0);
+    }
+
+    #[test]
+    fn containment() {
+        assert!(point_in_circle(v(1.0, 1.0), 2.0, v(2.0, 2.0)));
+        assert!(!point_in_circle(v(1.0, 1.0), 2.0, v(3.0, 1.0)));
+
+        assert!(point_in_rect(v(0.0, 0.0), v(2.0, 1.0), v(2.0, 0.5)));
+        assert!(!point_in_rect(v(0.0, 0.0), v(2.0, 1.0), v(1.0, 1.5)));
+
+        // an L shape, which is concave
+        let l = [
+            v(0.0, 0.0),
+            v(2.0, 0.0),
+            v(2.0, 1.0),
+            v(1.0, 1.0),
+            v(1.0, 3.0),
+            v(0.0, 3.0),
+        ];
+        assert!(point_in_polygon(&l, v(0.5, 2.5)));
+        assert!(point_in_polygon(&l, v(1.5, 0.5)));
+        assert!(!point_in_polygon(&l, v(1.5, 2.0)));
+        assert!(!point_in_polygon(&l, v(-1.0, 0.5)));
+        assert!(!point_in_polygon(&[], v(0.0, 0.0)));
+    }
+
+    fn point() -> impl Strategy<Value = Vector2D> {
+        (-100.0..100.0, -100.0..100.0).prop_map(|(x, y)| v(x, y))
+    }
+
+    fn close(a: Vector2D, b: Vector2D) -> bool {
+        a.distance(b) < 1e-6
+    }
+
+    proptest! {
+        #[test]
+        fn intersection_is_on_both_segments(
+            a in point(),
+            b in point(),
+            c in point(),
+            d in point(),
+        ) {
+            if let Some((distance, at)) = segment_intersection(a, b, c, d) {
+                prop_assert!(distance_to_segment(a, b, at) < 1e-6);
+                prop_assert!(distance_to_segment(c, d, at) < 1e-6);
+                prop_assert!((a.distance(at) - distance).abs() < 1e-6);
+            }
+        }
+
+        #[test]
+        fn intersection_ignores_order(a in point(), b in point(), c in point(), d in point()) {
+            let forward = segment_intersection(a, b, c, d);
+            let swapped = segment_intersection(c, d, a, b);
+            prop_assert_eq!(forward.is_some(), swapped.is_some());
+            prop_assert_eq!(forward.is_some(), segment_intersection(b, a, d, c).is_some());
+            if let (Some((_, p)), Some((_, q))) = (forward, swapped) {
+                prop_assert!(close(p, q));
+            }
+        }
+
+        #[test]
+        fn segments_through_a_point_cross_there(
+            at in point(),
+            angles in (0.0..std::f64::consts::PI, 0.0..std::f64::consts::PI),
+            reaches in ((0.1..10.0, 0.1..10.0), (0.1..10.0, 0.1..10.0)),
+        ) {
+            let (u, w) = (
+                v(angles.0.cos(), angles.0.sin()),
+                v(angles.1.cos(), angles.1.sin()),
+            );
+            prop_assume!(cross(u, w).abs() > 0.01);
+            let ((r0, r1), (s0, s1)) = reaches;
+            let crossing = segment_intersection(at - u * r0, at + u * r1, at - w * s0, at + w * s1);
+            prop_assert!(crossing.is_some());
+            let (distance, p) = crossing.unwrap();
+            prop_assert!(close(p, at));
+            prop_assert!((distance - r0).abs() < 1e-6);
+        }
+
+        #[test]
+        fn closest_point_is_no_further_than_the_ends(a in point(), b in point(), p in point()) {
+            let closest = closest_point_on_segment(a, b, p);
+            prop_assert!(distance_to_segment(a, b, closest) < 1e-6);
+            prop_assert!(p.distance(closest) <= p.distance(a) + 1e-9);
+            prop_assert!(p.distance(closest) <= p.distance(b) + 1e-9);
+        }
+    }
+}
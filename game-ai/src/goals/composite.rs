@@ -0,0 +1,364 @@
+use super::goal::{Goal, GoalStatus};
+use super::replan::{ReplanPolicy, Timeout};
+use crate::messaging::Telegram;
+
+/// The subgoals of a composite goal, as a stack where the front is the
+/// subgoal currently being worked on.
+///
+/// A composite goal owns one of these and forwards to it from its own
+/// [`Goal`] methods: it pushes its plan in `activate`, calls
+/// [`CompositeGoal::process_subgoals`] from `process` and
+/// [`CompositeGoal::remove_all_subgoals`] from `terminate`.
+///
+/// When a subgoal fails, or the plan takes longer than its timeout, the
+/// [`ReplanPolicy`] decides whether the owner should replan or fail in turn.
+pub struct CompositeGoal<E, M = ()> {
+    // the front of the stack is the last element
+    subgoals: Vec<Box<dyn Goal<E, M>>>,
+    policy: ReplanPolicy,
+    failures: u32,
+    timeout: Option<Timeout>,
+}
+
+impl<E, M> CompositeGoal<E, M> {
+    pub fn new() -> Self {
+        CompositeGoal {
+            subgoals: vec![],
+            policy: ReplanPolicy::Propagate,
+            failures: 0,
+            timeout: None,
+        }
+    }
+
+    pub fn with_replan_policy(mut self, policy: ReplanPolicy) -> Self {
+        self.policy = policy;
+        self
+    }
+
+    /// Fails the plan if it's still running after `updates` calls to
+    /// [`CompositeGoal::process_subgoals`].
+    pub fn with_timeout(mut self, updates: u32) -> Self {
+        self.timeout = Some(Timeout::new(updates));
+        self
+    }
+
+    pub fn replan_policy(&self) -> ReplanPolicy {
+        self.policy
+    }
+
+    /// How many times in a row the plan has failed.
+    pub fn failures(&self) -> u32 {
+        self.failures
+    }
+
+    /// Pushes a subgoal onto the front, so it runs before the existing ones.
+    /// To plan a sequence, add the steps in reverse order.
+    ///
+    /// Pushing onto an empty stack starts a new plan, which restarts the
+    /// timeout.
+    pub fn add_subgoal(&mut self, goal: Box<dyn Goal<E, M>>) {
+        if self.subgoals.is_empty() {
+            if let Some(timeout) = &mut self.timeout {
+                timeout.restart();
+            }
+        }
+        self.subgoals.push(goal);
+    }
+
+    /// Processes the front subgoal, first removing any finished ones.
+    ///
+    /// Returns [`GoalStatus::Completed`] once every subgoal has completed.
+    /// If a subgoal fails or the plan times out, the subgoals are removed and
+    /// [`GoalStatus::Inactive`] is returned when the replan policy allows
+    /// another attempt, so the owner goes inactive and replans in its next
+    /// `activate`; otherwise [`GoalStatus::Failed`] is returned. With
+    /// [`ReplanPolicy::Propagate`] the failed subgoal is left on the stack
+    /// for the owner to inspect.
+    pub fn process_subgoals(&mut self, entity: &mut E) -> GoalStatus {
+        let expired = match &mut self.timeout {
+            Some(timeout) if !self.subgoals.is_empty() => timeout.tick(),
+            _ => false,
+        };
+
+        let status = if expired {
+            GoalStatus::Failed
+        } else {
+            self.process_front(entity)
+        };
+
+        match status {
+            GoalStatus::Failed => {
+                self.failures += 1;
+                if self.policy.should_replan(self.failures) {
+                    self.remove_all_subgoals(entity);
+                    GoalStatus::Inactive
+                } else {
+                    GoalStatus::Failed
+                }
+            }
+            GoalStatus::Completed => {
+                self.failures = 0;
+                GoalStatus::Completed
+            }
+            status => status,
+        }
+    }
+
+    fn process_front(&mut self, entity: &mut E) -> GoalStatus {
+        while let Some(front) = self.subgoals.last_mut() {
+            if front.is_complete() || front.has_failed() {
+                front.terminate(entity);
+                self.subgoals.pop();
+            } else {
+                break;
+            }
+        }
+
+        let front = match self.subgoals.last_mut() {
+            Some(front) => front,
+            None => return GoalStatus::Completed,
+        };
+
+        match front.process(entity) {
+            // carry on with the rest of the plan next update
+            GoalStatus::Completed if self.subgoals.len() > 1 => GoalStatus::Active,
+            status => status,
+        }
+    }
+
+    /// Offers a message to the front subgoal, returning whether it was
+    /// handled. A composite goal calls this from its own
+    /// [`Goal::handle_message`] and only handles the message itself if this
+    /// returns `false`.
+    pub fn forward_message(&mut self, entity: &mut E, telegram: &Telegram<M>) -> bool {
+        match self.subgoals.last_mut() {
+            Some(front) => front.handle_message(entity, telegram),
+            None => false,
+        }
+    }
+
+    /// Terminates and removes every subgoal.
+    pub fn remove_all_subgoals(&mut self, entity: &mut E) {
+        while let Some(mut goal) = self.subgoals.pop() {
+            goal.terminate(entity);
+        }
+    }
+
+    /// The subgoals, front first.
+    pub fn iter(&self) -> impl Iterator<Item = &dyn Goal<E, M>> {
+        self.subgoals.iter().rev().map(|goal| goal.as_ref())
+    }
+
+    /// The subgoal currently being worked on.
+    pub fn front(&self) -> Option<&dyn Goal<E, M>> {
+        self.subgoals.last().map(|goal| goal.as_ref())
+    }
+
+    pub fn front_mut(&mut self) -> Option<&mut (dyn Goal<E, M> + 'static)> {
+        self.subgoals.last_mut().map(|goal| goal.as_mut())
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.subgoals.is_empty()
+    }
+
+    pub fn len(&self) -> usize {
+        self.subgoals.len()
+    }
+}
+
+impl<E, M> Default for CompositeGoal<E, M> {
+    fn default() -> Self {
+        CompositeGoal::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Logs what it does, and completes (or fails) after a number of updates.
+    struct Step {
+        name: &'static str,
+        updates: u32,
+        fails: bool,
+        status: GoalStatus,
+    }
+
+    fn step(name: &'static str, updates: u32) -> Box<Step> {
+        Box::new(Step {
+            name,
+            updates,
+            fails: false,
+            status: GoalStatus::Inactive,
+        })
+    }
+
+    impl Goal<Vec<String>> for Step {
+        fn activate(&mut self, log: &mut Vec<String>) {
+            log.push(format!("activate {}", self.name));
+            self.status = GoalStatus::Active;
+        }
+
+        fn process(&mut self, log: &mut Vec<String>) -> GoalStatus {
+            self.activate_if_inactive(log);
+            self.updates -= 1;
+            if self.updates == 0 {
+                self.status = if self.fails {
+                    GoalStatus::Failed
+                } else {
+                    GoalStatus::Completed
+                };
+            }
+            self.status
+        }
+
+        fn terminate(&mut self, log: &mut Vec<String>) {
+            log.push(format!("terminate {}", self.name));
+        }
+
+        fn status(&self) -> GoalStatus {
+            self.status
+        }
+    }
+
+    #[test]
+    fn subgoals_run_front_first_until_all_complete() {
+        let mut log = vec![];
+        let mut plan = CompositeGoal::new();
+        plan.add_subgoal(step("drink", 1));
+        plan.add_subgoal(step("walk", 2));
+
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Active);
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Active);
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Completed);
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Completed);
+        assert!(plan.is_empty());
+
+        assert_eq!(
+            log,
+            vec![
+                "activate walk",
+                "terminate walk",
+                "activate drink",
+                "terminate drink"
+            ]
+        );
+    }
+
+    #[test]
+    fn failure_is_reported_and_left_for_the_owner() {
+        let mut log = vec![];
+        let mut plan = CompositeGoal::new();
+        plan.add_subgoal(step("drink", 1));
+        plan.add_subgoal(failing_step("walk"));
+
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Failed);
+        assert_eq!(plan.len(), 2);
+        assert!(plan.front().unwrap().has_failed());
+
+        plan.remove_all_subgoals(&mut log);
+        assert!(plan.is_empty());
+        assert_eq!(
+            log,
+            vec!["activate walk", "terminate walk", "terminate drink"]
+        );
+    }
+
+    /// Handles one kind of message and ignores the rest.
+    struct Listener {
+        handles: &'static str,
+    }
+
+    impl Goal<Vec<String>, &'static str> for Listener {
+        fn activate(&mut self, _log: &mut Vec<String>) {}
+
+        fn process(&mut self, _log: &mut Vec<String>) -> GoalStatus {
+            GoalStatus::Active
+        }
+
+        fn terminate(&mut self, _log: &mut Vec<String>) {}
+
+        fn status(&self) -> GoalStatus {
+            GoalStatus::Active
+        }
+
+        fn handle_message(
+            &mut self,
+            log: &mut Vec<String>,
+            telegram: &Telegram<&'static str>,
+        ) -> bool {
+            if telegram.message == self.handles {
+                log.push(format!("handled {}", telegram.message));
+                true
+            } else {
+                false
+            }
+        }
+    }
+
+    #[test]
+    fn messages_go_to_the_front_subgoal_only() {
+        let mut log = vec![];
+        let mut plan = CompositeGoal::new();
+        plan.add_subgoal(Box::new(Listener {
+            handles: "StewReady",
+        }));
+        plan.add_subgoal(Box::new(Listener {
+            handles: "HiHoneyImHome",
+        }));
+
+        let stew_ready = Telegram::new(1, 0, "StewReady", 0.0);
+        let home = Telegram::new(1, 0, "HiHoneyImHome", 0.0);
+        assert!(!plan.forward_message(&mut log, &stew_ready));
+        assert!(plan.forward_message(&mut log, &home));
+        assert_eq!(log, vec!["handled HiHoneyImHome"]);
+
+        assert!(!CompositeGoal::<Vec<String>, &str>::new().forward_message(&mut log, &home));
+    }
+
+    fn failing_step(name: &'static str) -> Box<Step> {
+        Box::new(Step {
+            fails: true,
+            ..*step(name, 1)
+        })
+    }
+
+    #[test]
+    fn failures_replan_until_retries_run_out() {
+        let mut log = vec![];
+        let mut plan = CompositeGoal::new().with_replan_policy(ReplanPolicy::Retry(1));
+
+        plan.add_subgoal(failing_step("walk"));
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Inactive);
+        assert!(plan.is_empty());
+
+        plan.add_subgoal(failing_step("walk"));
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Failed);
+        assert_eq!(plan.failures(), 2);
+
+        plan.remove_all_subgoals(&mut log);
+        plan.add_subgoal(step("walk", 1));
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Completed);
+        assert_eq!(plan.failures(), 0);
+    }
+
+    #[test]
+    fn slow_plans_time_out() {
+        let mut log = vec![];
+        let mut plan = CompositeGoal::new()
+            .with_timeout(2)
+            .with_replan_policy(ReplanPolicy::RetryForever);
+        plan.add_subgoal(step("walk", 10));
+
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Active);
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Active);
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Inactive);
+        assert_eq!(log, vec!["activate walk", "terminate walk"]);
+
+        // a new plan gets the full time again
+        plan.add_subgoal(step("walk", 2));
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Active);
+        assert_eq!(plan.process_subgoals(&mut log), GoalStatus::Completed);
+    }
+}
@@ -0,0 +1,133 @@
+//! Dumping a goal hierarchy for debugging, to see why an agent is doing what
+//! it's doing.
+
+use super::goal::Goal;
+use std::fmt::Write;
+
+/// An indented tree of a goal and its subgoals with their statuses, one goal
+/// per line and the front subgoal first:
+///
+/// ```text
+/// Think (Active)
+///   GoalGetGold (Active)
+///     DigForNugget (Inactive)
+/// ```
+pub fn describe<E, M>(goal: &dyn Goal<E, M>) -> String {
+    let mut out = String::new();
+    describe_into(&mut out, goal, 0);
+    out
+}
+
+fn describe_into<E, M>(out: &mut String, goal: &dyn Goal<E, M>, depth: usize) {
+    writeln!(
+        out,
+        "{:indent$}{} ({:?})",
+        "",
+        goal.name(),
+        goal.status(),
+        indent = depth * 2
+    )
+    .unwrap();
+    for child in goal.children() {
+        describe_into(out, child, depth + 1);
+    }
+}
+
+/// The goal hierarchy as a Graphviz DOT digraph, with edges from each goal
+/// to its subgoals.
+pub fn to_dot<E, M>(goal: &dyn Goal<E, M>) -> String {
+    let mut out = String::from("digraph goals {\n");
+    let mut next_id = 0;
+    dot_into(&mut out, goal, &mut next_id);
+    out.push_str("}\n");
+    out
+}
+
+fn dot_into<E, M>(out: &mut String, goal: &dyn Goal<E, M>, next_id: &mut usize) -> usize {
+    let id = *next_id;
+    *next_id += 1;
+    writeln!(
+        out,
+        "    g{} [label=\"{}\\n{:?}\"];",
+        id,
+        goal.name().replace('"', "\\\""),
+        goal.status()
+    )
+    .unwrap();
+
+    for child in goal.children() {
+        let child_id = dot_into(out, child, next_id);
+        writeln!(out, "    g{} -> g{};", id, child_id).unwrap();
+    }
+
+    id
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::goals::{CompositeGoal, GoalStatus};
+
+    struct Leaf;
+
+    impl Goal<()> for Leaf {
+        fn activate(&mut self, _entity: &mut ()) {}
+
+        fn process(&mut self, _entity: &mut ()) -> GoalStatus {
+            GoalStatus::Inactive
+        }
+
+        fn terminate(&mut self, _entity: &mut ()) {}
+
+        fn status(&self) -> GoalStatus {
+            GoalStatus::Inactive
+        }
+    }
+
+    struct Plan(CompositeGoal<()>);
+
+    impl Goal<()> for Plan {
+        fn activate(&mut self, _entity: &mut ()) {}
+
+        fn process(&mut self, _entity: &mut ()) -> GoalStatus {
+            GoalStatus::Active
+        }
+
+        fn terminate(&mut self, _entity: &mut ()) {}
+
+        fn status(&self) -> GoalStatus {
+            GoalStatus::Active
+        }
+
+        fn children(&self) -> Vec<&dyn Goal<()>> {
+            self.0.iter().collect()
+        }
+    }
+
+    fn plan() -> Plan {
+        let mut inner = CompositeGoal::new();
+        inner.add_subgoal(Box::new(Leaf));
+        let mut outer = CompositeGoal::new();
+        outer.add_subgoal(Box::new(Leaf));
+        outer.add_subgoal(Box::new(Plan(inner)));
+        Plan(outer)
+    }
+
+    #[test]
+    fn tree_is_indented_front_first() {
+        assert_eq!(
+            describe(&plan()),
+            "Plan (Active)\n  Plan (Active)\n    Leaf (Inactive)\n  Leaf (Inactive)\n"
+        );
+    }
+
+    #[test]
+    fn dot_links_goals_to_subgoals() {
+        let dot = to_dot(&plan());
+        assert!(dot.starts_with("digraph goals {\n"));
+        assert!(dot.contains("g0 [label=\"Plan\\nActive\"];"));
+        for edge in &["g0 -> g1;", "g1 -> g2;", "g0 -> g3;"] {
+            assert!(dot.contains(edge), "{} missing from {}", edge, dot);
+        }
+    }
+}
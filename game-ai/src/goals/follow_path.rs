@@ -0,0 +1,279 @@
+//! Goals that walk an agent along a planned path, one edge at a time.
+
+use super::composite::CompositeGoal;
+use super::goal::{Goal, GoalStatus};
+use super::replan::Timeout;
+use crate::graph::path::PathEdge;
+use crate::steering::{Behavior, Deceleration, Vehicle};
+use std::collections::VecDeque;
+
+/// How close to the end of the path counts as arrived.
+pub static ARRIVAL_DISTANCE: f64 = 0.5;
+
+// allowance on top of the time an edge should take at full speed, for
+// speeding up, slowing down and a little jostling
+static EDGE_TIME_MARGIN: f64 = 2.0;
+static EDGE_EXTRA_UPDATES: u32 = 4;
+
+/// An agent that can be walked along a path by [`GoalFollowPath`].
+pub trait PathFollower {
+    fn vehicle(&self) -> &Vehicle;
+
+    fn vehicle_mut(&mut self) -> &mut Vehicle;
+
+    /// Seconds of game time between goal updates, used to work out how many
+    /// updates an edge should take.
+    fn time_step(&self) -> f64;
+
+    /// Whether an edge can still be walked, e.g. no door has shut across it
+    /// since the path was planned.
+    fn is_passable(&self, _edge: &PathEdge) -> bool {
+        true
+    }
+}
+
+/// Steers along a single edge: seeking its end, or arriving at it if it's the
+/// last edge of the path. Fails if the edge becomes impassable or takes much
+/// longer than it should.
+pub struct GoalTraverseEdge {
+    edge: PathEdge,
+    last_edge: bool,
+    timeout: Timeout,
+    status: GoalStatus,
+}
+
+impl GoalTraverseEdge {
+    pub fn new(edge: PathEdge, last_edge: bool) -> Self {
+        GoalTraverseEdge {
+            edge,
+            last_edge,
+            timeout: Timeout::new(0),
+            status: GoalStatus::Inactive,
+        }
+    }
+
+    pub fn edge(&self) -> &PathEdge {
+        &self.edge
+    }
+
+    fn arrival_distance<E: PathFollower>(&self, entity: &E) -> f64 {
+        if self.last_edge {
+            ARRIVAL_DISTANCE
+        } else {
+            // close enough that the next update would overshoot anyway
+            (entity.vehicle().max_speed * entity.time_step()).max(ARRIVAL_DISTANCE)
+        }
+    }
+}
+
+impl<E: PathFollower, M> Goal<E, M> for GoalTraverseEdge {
+    fn activate(&mut self, entity: &mut E) {
+        self.status = GoalStatus::Active;
+
+        let step = entity.vehicle().max_speed * entity.time_step();
+        let expected = if step > 0.0 {
+            (self.edge.length() / step).ceil() as u32
+        } else {
+            0
+        };
+        self.timeout =
+            Timeout::new((expected as f64 * EDGE_TIME_MARGIN) as u32 + EDGE_EXTRA_UPDATES);
+
+        let behavior = if self.last_edge {
+            Behavior::Arrive(self.edge.destination, Deceleration::Normal)
+        } else {
+            Behavior::Seek(self.edge.destination)
+        };
+        entity.vehicle_mut().set_behavior(Some(behavior));
+    }
+
+    fn process(&mut self, entity: &mut E) -> GoalStatus {
+        Goal::<E, M>::activate_if_inactive(self, entity);
+
+        if !entity.is_passable(&self.edge) || self.timeout.tick() {
+            self.status = GoalStatus::Failed;
+        } else if entity.vehicle().position.distance(self.edge.destination)
+            <= self.arrival_distance(entity)
+        {
+            self.status = GoalStatus::Completed;
+        }
+
+        self.status
+    }
+
+    fn terminate(&mut self, entity: &mut E) {
+        // the next edge sets its own behaviour, so only stop at the end or
+        // when giving up
+        if self.last_edge || self.status == GoalStatus::Failed {
+            entity.vehicle_mut().set_behavior(None);
+        }
+    }
+
+    fn status(&self) -> GoalStatus {
+        self.status
+    }
+}
+
+/// Walks a path edge by edge. Fails as soon as an edge does, leaving the
+/// parent to plan a new path.
+pub struct GoalFollowPath<E, M = ()> {
+    path: VecDeque<PathEdge>,
+    subgoals: CompositeGoal<E, M>,
+    status: GoalStatus,
+}
+
+impl<E, M> GoalFollowPath<E, M> {
+    pub fn new(path: Vec<PathEdge>) -> Self {
+        GoalFollowPath {
+            path: path.into(),
+            subgoals: CompositeGoal::new(),
+            status: GoalStatus::Inactive,
+        }
+    }
+
+    /// The edges not yet started on.
+    pub fn remaining_edges(&self) -> impl Iterator<Item = &PathEdge> {
+        self.path.iter()
+    }
+}
+
+impl<E: PathFollower + 'static, M: 'static> Goal<E, M> for GoalFollowPath<E, M> {
+    fn activate(&mut self, entity: &mut E) {
+        self.status = GoalStatus::Active;
+        self.subgoals.remove_all_subgoals(entity);
+
+        match self.path.pop_front() {
+            Some(edge) => {
+                let last_edge = self.path.is_empty();
+                self.subgoals
+                    .add_subgoal(Box::new(GoalTraverseEdge::new(edge, last_edge)));
+            }
+            None => self.status = GoalStatus::Completed,
+        }
+    }
+
+    fn process(&mut self, entity: &mut E) -> GoalStatus {
+        self.activate_if_inactive(entity);
+        if self.status == GoalStatus::Completed {
+            return self.status;
+        }
+
+        self.status = self.subgoals.process_subgoals(entity);
+        if self.status == GoalStatus::Completed && !self.path.is_empty() {
+            self.activate(entity);
+        }
+
+        self.status
+    }
+
+    fn terminate(&mut self, entity: &mut E) {
+        self.subgoals.remove_all_subgoals(entity);
+    }
+
+    fn status(&self) -> GoalStatus {
+        self.status
+    }
+
+    fn children(&self) -> Vec<&dyn Goal<E, M>> {
+        self.subgoals.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::path::edges_from_waypoints;
+    use crate::vector::Vector2D;
+
+    struct Walker {
+        vehicle: Vehicle,
+        blocked: Option<PathEdge>,
+    }
+
+    impl PathFollower for Walker {
+        fn vehicle(&self) -> &Vehicle {
+            &self.vehicle
+        }
+
+        fn vehicle_mut(&mut self) -> &mut Vehicle {
+            &mut self.vehicle
+        }
+
+        fn time_step(&self) -> f64 {
+            0.1
+        }
+
+        fn is_passable(&self, edge: &PathEdge) -> bool {
+            self.blocked != Some(*edge)
+        }
+    }
+
+    fn walk(walker: &mut Walker, goal: &mut GoalFollowPath<Walker>) -> GoalStatus {
+        for _ in 0..1000 {
+            match goal.process(walker) {
+                GoalStatus::Active => walker.vehicle.update(walker.time_step()),
+                status => return status,
+            }
+        }
+        panic!("still walking");
+    }
+
+    fn nimble() -> Vehicle {
+        let mut vehicle = Vehicle::new(Vector2D::zero(), 5.0, 20.0);
+        vehicle.mass = 0.2;
+        vehicle
+    }
+
+    fn corner() -> Vec<PathEdge> {
+        edges_from_waypoints(&[
+            Vector2D::zero(),
+            Vector2D::new(10.0, 0.0),
+            Vector2D::new(10.0, 10.0),
+        ])
+    }
+
+    #[test]
+    fn walks_every_edge_and_stops_at_the_end() {
+        let mut walker = Walker {
+            vehicle: nimble(),
+            blocked: None,
+        };
+        let mut goal = GoalFollowPath::new(corner());
+
+        assert_eq!(walk(&mut walker, &mut goal), GoalStatus::Completed);
+        assert!(walker.vehicle.position.distance(Vector2D::new(10.0, 10.0)) <= ARRIVAL_DISTANCE);
+
+        goal.terminate(&mut walker);
+        assert_eq!(walker.vehicle.behavior(), None);
+    }
+
+    #[test]
+    fn blocked_edge_fails_the_path() {
+        let path = corner();
+        let mut walker = Walker {
+            vehicle: nimble(),
+            blocked: Some(path[1]),
+        };
+        let mut goal = GoalFollowPath::new(path);
+
+        assert_eq!(walk(&mut walker, &mut goal), GoalStatus::Failed);
+        assert!(walker.vehicle.position.x > 5.0);
+    }
+
+    #[test]
+    fn edge_that_takes_too_long_times_out() {
+        let mut walker = Walker {
+            vehicle: nimble(),
+            blocked: None,
+        };
+        let mut edge = GoalTraverseEdge::new(corner()[0], false);
+
+        // the walker never moves
+        let mut updates = 0;
+        while Goal::<Walker>::process(&mut edge, &mut walker) == GoalStatus::Active {
+            updates += 1;
+        }
+        assert_eq!(Goal::<Walker>::status(&edge), GoalStatus::Failed);
+        assert_eq!(updates, 20 * 2 + 4);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::messaging::Telegram;
+
+/// Where a goal is in its life cycle.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum GoalStatus {
+    /// Waiting to be activated, either for the first time or to replan.
+    Inactive,
+    Active,
+    Completed,
+    Failed,
+}
+
+/// A goal pursued by an entity of type `E`, which may receive messages of
+/// type `M`.
+///
+/// Goals are activated before they are first processed, and again whenever
+/// they set themselves back to [`GoalStatus::Inactive`] to replan.
+/// [`Goal::terminate`] is called once the goal is finished or abandoned, to
+/// undo anything `activate` set up.
+pub trait Goal<E, M = ()> {
+    /// Sets the goal up, e.g. by planning a route or adding subgoals.
+    fn activate(&mut self, entity: &mut E);
+
+    /// Runs the goal for one update and returns its new status.
+    fn process(&mut self, entity: &mut E) -> GoalStatus;
+
+    fn terminate(&mut self, entity: &mut E);
+
+    fn status(&self) -> GoalStatus;
+
+    /// A short name for debug output; the type name without module paths by
+    /// default.
+    fn name(&self) -> String {
+        short_type_name(std::any::type_name::<Self>())
+    }
+
+    /// The subgoals, front first, for walking the goal hierarchy. Composite
+    /// goals should return [`CompositeGoal::iter`](super::CompositeGoal::iter).
+    fn children(&self) -> Vec<&dyn Goal<E, M>> {
+        vec![]
+    }
+
+    /// Offers a message to the goal, returning whether it was handled.
+    /// Composite goals should offer it to their front subgoal first, see
+    /// [`CompositeGoal::forward_message`](super::CompositeGoal::forward_message).
+    fn handle_message(&mut self, _entity: &mut E, _telegram: &Telegram<M>) -> bool {
+        false
+    }
+
+    /// Activates the goal if it's inactive; call at the start of `process`.
+    fn activate_if_inactive(&mut self, entity: &mut E) {
+        if self.is_inactive() {
+            self.activate(entity);
+        }
+    }
+
+    fn is_inactive(&self) -> bool {
+        self.status() == GoalStatus::Inactive
+    }
+
+    fn is_active(&self) -> bool {
+        self.status() == GoalStatus::Active
+    }
+
+    fn is_complete(&self) -> bool {
+        self.status() == GoalStatus::Completed
+    }
+
+    fn has_failed(&self) -> bool {
+        self.status() == GoalStatus::Failed
+    }
+}
+
+/// Strips the module paths from a type name, including those of any generic
+/// arguments, so `a::Errand<b::Dig>` becomes `Errand<Dig>`.
+fn short_type_name(full: &str) -> String {
+    let mut short = String::with_capacity(full.len());
+    let mut segment_start = 0;
+
+    for c in full.chars() {
+        if c == ':' {
+            short.truncate(segment_start);
+        } else {
+            short.push(c);
+            if !(c.is_alphanumeric() || c == '_') {
+                segment_start = short.len();
+            }
+        }
+    }
+
+    short
+}
@@ -0,0 +1,23 @@
+//! Goal-driven agent behaviour.
+//!
+//! Instead of a state machine choosing what to do next, an agent pursues a
+//! hierarchy of goals. Atomic goals do one simple thing (walk an edge, dig a
+//! nugget); composite goals break a larger aim into a stack of subgoals and
+//! work through them in turn, replanning when one fails. At the top, a
+//! [`Think`] goal weighs up how desirable each kind of goal is and pursues
+//! the best one.
+
+mod composite;
+pub mod describe;
+mod follow_path;
+mod goal;
+mod personality;
+mod replan;
+mod think;
+
+pub use composite::CompositeGoal;
+pub use follow_path::{GoalFollowPath, GoalTraverseEdge, PathFollower};
+pub use goal::{Goal, GoalStatus};
+pub use personality::{Personality, Trait};
+pub use replan::{ReplanPolicy, Timeout};
+pub use think::{GoalEvaluator, Think};
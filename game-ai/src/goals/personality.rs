@@ -0,0 +1,60 @@
+use serde::{Deserialize, Serialize};
+
+/// A character trait that makes some goals more or less appealing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+pub enum Trait {
+    Greed,
+    Sociability,
+    Laziness,
+    Aggression,
+}
+
+/// How strongly an agent leans towards each kind of goal.
+///
+/// Each trait multiplies the desirability of the goals tied to it, so 1 is
+/// neutral, 2 makes those goals twice as appealing and 0 rules them out.
+/// Traits left out of a config file default to 1.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+#[serde(default)]
+pub struct Personality {
+    pub greed: f64,
+    pub sociability: f64,
+    pub laziness: f64,
+    pub aggression: f64,
+}
+
+impl Personality {
+    pub fn bias(&self, tr: Trait) -> f64 {
+        match tr {
+            Trait::Greed => self.greed,
+            Trait::Sociability => self.sociability,
+            Trait::Laziness => self.laziness,
+            Trait::Aggression => self.aggression,
+        }
+    }
+}
+
+impl Default for Personality {
+    fn default() -> Self {
+        Personality {
+            greed: 1.0,
+            sociability: 1.0,
+            laziness: 1.0,
+            aggression: 1.0,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn missing_traits_are_neutral() {
+        let personality: Personality = ron::from_str("(greed: 1.5, laziness: 0.5)").unwrap();
+        assert_eq!(personality.bias(Trait::Greed), 1.5);
+        assert_eq!(personality.bias(Trait::Laziness), 0.5);
+        assert_eq!(personality.bias(Trait::Sociability), 1.0);
+        assert_eq!(personality.bias(Trait::Aggression), 1.0);
+    }
+}
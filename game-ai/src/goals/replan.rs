@@ -0,0 +1,59 @@
+/// What a composite goal does when one of its subgoals fails or its plan
+/// runs out of time.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum ReplanPolicy {
+    /// Fail straight away, passing the failure up to the parent.
+    #[default]
+    Propagate,
+    /// Replan up to this many times in a row before failing.
+    Retry(u32),
+    /// Keep replanning for as long as it takes.
+    RetryForever,
+}
+
+impl ReplanPolicy {
+    /// Whether to replan after `failures` consecutive failures.
+    pub fn should_replan(self, failures: u32) -> bool {
+        match self {
+            ReplanPolicy::Propagate => false,
+            ReplanPolicy::Retry(attempts) => failures <= attempts,
+            ReplanPolicy::RetryForever => true,
+        }
+    }
+}
+
+/// Counts updates against a limit, so a goal that's taking far longer than
+/// expected (stuck on a wall, waiting on a reply that never comes) can give up.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct Timeout {
+    limit: u32,
+    elapsed: u32,
+}
+
+impl Timeout {
+    pub fn new(limit: u32) -> Self {
+        Timeout { limit, elapsed: 0 }
+    }
+
+    pub fn restart(&mut self) {
+        self.elapsed = 0;
+    }
+
+    /// Counts one update, returning whether the limit has now been passed.
+    pub fn tick(&mut self) -> bool {
+        self.elapsed = self.elapsed.saturating_add(1);
+        self.is_expired()
+    }
+
+    pub fn is_expired(&self) -> bool {
+        self.elapsed > self.limit
+    }
+
+    pub fn elapsed(&self) -> u32 {
+        self.elapsed
+    }
+
+    pub fn limit(&self) -> u32 {
+        self.limit
+    }
+}
@@ -0,0 +1,321 @@
+use super::composite::CompositeGoal;
+use super::goal::{Goal, GoalStatus};
+use super::personality::{Personality, Trait};
+use crate::messaging::Telegram;
+
+/// Scores how much an entity wants to pursue one kind of top-level goal, and
+/// creates that goal when it wins.
+pub trait GoalEvaluator<E, M = ()> {
+    /// How desirable the goal is right now, usually between 0 and 1.
+    fn desirability(&self, entity: &E) -> f64;
+
+    fn create_goal(&self, entity: &E) -> Box<dyn Goal<E, M>>;
+
+    /// The personality trait that scales this evaluator's scores, if any.
+    fn personality_trait(&self) -> Option<Trait> {
+        None
+    }
+}
+
+struct WeightedEvaluator<E, M> {
+    evaluator: Box<dyn GoalEvaluator<E, M>>,
+    bias: f64,
+}
+
+/// The top-level goal of an agent's brain: it asks every evaluator how
+/// desirable its goal is and pursues the winner, arbitrating again whenever
+/// the current goal completes or fails.
+///
+/// Each evaluator's score is multiplied by a per-agent bias and by the
+/// agent's [`Personality`], so two agents sharing the same evaluators can
+/// still have different priorities. Ties go to the goal already being
+/// pursued, then to the evaluator added first.
+pub struct Think<E, M = ()> {
+    evaluators: Vec<WeightedEvaluator<E, M>>,
+    subgoals: CompositeGoal<E, M>,
+    personality: Personality,
+    current: Option<usize>,
+    status: GoalStatus,
+}
+
+impl<E, M> Think<E, M> {
+    pub fn new() -> Self {
+        Think {
+            evaluators: vec![],
+            subgoals: CompositeGoal::new(),
+            personality: Personality::default(),
+            current: None,
+            status: GoalStatus::Inactive,
+        }
+    }
+
+    /// Adds an evaluator with a bias of 1.
+    pub fn with_evaluator<V: GoalEvaluator<E, M> + 'static>(self, evaluator: V) -> Self {
+        self.with_biased_evaluator(evaluator, 1.0)
+    }
+
+    /// Adds an evaluator whose scores are multiplied by `bias`.
+    pub fn with_biased_evaluator<V: GoalEvaluator<E, M> + 'static>(
+        mut self,
+        evaluator: V,
+        bias: f64,
+    ) -> Self {
+        self.evaluators.push(WeightedEvaluator {
+            evaluator: Box::new(evaluator),
+            bias,
+        });
+        self
+    }
+
+    pub fn with_personality(mut self, personality: Personality) -> Self {
+        self.personality = personality;
+        self
+    }
+
+    pub fn personality(&self) -> &Personality {
+        &self.personality
+    }
+
+    pub fn set_personality(&mut self, personality: Personality) {
+        self.personality = personality;
+    }
+
+    /// The index of the evaluator whose goal is being pursued.
+    pub fn current_evaluator(&self) -> Option<usize> {
+        self.current
+    }
+
+    /// The biased score of every evaluator, in the order they were added.
+    pub fn scores(&self, entity: &E) -> Vec<f64> {
+        self.evaluators
+            .iter()
+            .map(|weighted| {
+                let personality = weighted
+                    .evaluator
+                    .personality_trait()
+                    .map_or(1.0, |tr| self.personality.bias(tr));
+                weighted.evaluator.desirability(entity) * weighted.bias * personality
+            })
+            .collect()
+    }
+
+    /// Picks the most desirable goal, replacing the current one if it differs.
+    pub fn arbitrate(&mut self, entity: &mut E) {
+        let scores = self.scores(entity);
+        let mut best = match self.current {
+            Some(current) if current < scores.len() => current,
+            _ if scores.is_empty() => return,
+            _ => 0,
+        };
+        for (index, &score) in scores.iter().enumerate() {
+            if score > scores[best] {
+                best = index;
+            }
+        }
+
+        if self.current != Some(best) || self.subgoals.is_empty() {
+            self.subgoals.remove_all_subgoals(entity);
+            let goal = self.evaluators[best].evaluator.create_goal(entity);
+            self.subgoals.add_subgoal(goal);
+            self.current = Some(best);
+        }
+    }
+
+    pub fn subgoals(&self) -> &CompositeGoal<E, M> {
+        &self.subgoals
+    }
+}
+
+impl<E, M> Default for Think<E, M> {
+    fn default() -> Self {
+        Think::new()
+    }
+}
+
+impl<E, M> Goal<E, M> for Think<E, M> {
+    fn activate(&mut self, entity: &mut E) {
+        self.arbitrate(entity);
+        self.status = GoalStatus::Active;
+    }
+
+    fn process(&mut self, entity: &mut E) -> GoalStatus {
+        self.activate_if_inactive(entity);
+
+        match self.subgoals.process_subgoals(entity) {
+            // the brain never finishes; pick something else to do next update
+            GoalStatus::Completed | GoalStatus::Failed => {
+                self.subgoals.remove_all_subgoals(entity);
+                self.status = GoalStatus::Inactive;
+            }
+            _ => (),
+        }
+
+        self.status
+    }
+
+    fn terminate(&mut self, entity: &mut E) {
+        self.subgoals.remove_all_subgoals(entity);
+        self.current = None;
+    }
+
+    fn status(&self) -> GoalStatus {
+        self.status
+    }
+
+    fn handle_message(&mut self, entity: &mut E, telegram: &Telegram<M>) -> bool {
+        self.subgoals.forward_message(entity, telegram)
+    }
+
+    fn children(&self) -> Vec<&dyn Goal<E, M>> {
+        self.subgoals.iter().collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[derive(Default)]
+    struct Miner {
+        thirst: f64,
+        fatigue: f64,
+        log: Vec<&'static str>,
+    }
+
+    /// Logs its name when activated; drinking quenches thirst straight away,
+    /// while sleeping goes on until interrupted.
+    struct Chore {
+        name: &'static str,
+        status: GoalStatus,
+    }
+
+    fn chore(name: &'static str) -> Box<dyn Goal<Miner>> {
+        Box::new(Chore {
+            name,
+            status: GoalStatus::Inactive,
+        })
+    }
+
+    impl Goal<Miner> for Chore {
+        fn activate(&mut self, miner: &mut Miner) {
+            miner.log.push(self.name);
+            self.status = GoalStatus::Active;
+        }
+
+        fn process(&mut self, miner: &mut Miner) -> GoalStatus {
+            self.activate_if_inactive(miner);
+            if self.name == "drink" {
+                miner.thirst = 0.0;
+                self.status = GoalStatus::Completed;
+            }
+            self.status
+        }
+
+        fn terminate(&mut self, _miner: &mut Miner) {}
+
+        fn status(&self) -> GoalStatus {
+            self.status
+        }
+    }
+
+    struct Thirst;
+
+    impl GoalEvaluator<Miner> for Thirst {
+        fn desirability(&self, miner: &Miner) -> f64 {
+            miner.thirst
+        }
+
+        fn create_goal(&self, _miner: &Miner) -> Box<dyn Goal<Miner>> {
+            chore("drink")
+        }
+    }
+
+    struct Fatigue;
+
+    impl GoalEvaluator<Miner> for Fatigue {
+        fn desirability(&self, miner: &Miner) -> f64 {
+            miner.fatigue
+        }
+
+        fn create_goal(&self, _miner: &Miner) -> Box<dyn Goal<Miner>> {
+            chore("sleep")
+        }
+
+        fn personality_trait(&self) -> Option<Trait> {
+            Some(Trait::Laziness)
+        }
+    }
+
+    #[test]
+    fn most_desirable_goal_wins_and_is_replaced_when_done() {
+        let mut miner = Miner {
+            thirst: 0.8,
+            fatigue: 0.5,
+            ..Miner::default()
+        };
+        let mut brain = Think::new().with_evaluator(Thirst).with_evaluator(Fatigue);
+
+        brain.process(&mut miner);
+        assert_eq!(brain.current_evaluator(), Some(0));
+        assert_eq!(brain.status(), GoalStatus::Inactive);
+
+        brain.process(&mut miner);
+        assert_eq!(brain.current_evaluator(), Some(1));
+        assert_eq!(miner.log, vec!["drink", "sleep"]);
+    }
+
+    #[test]
+    fn bias_changes_the_winner() {
+        let mut miner = Miner {
+            thirst: 0.8,
+            fatigue: 0.5,
+            ..Miner::default()
+        };
+        let mut brain = Think::new()
+            .with_evaluator(Thirst)
+            .with_biased_evaluator(Fatigue, 2.0);
+
+        brain.arbitrate(&mut miner);
+        assert_eq!(brain.current_evaluator(), Some(1));
+    }
+
+    #[test]
+    fn ties_keep_the_current_goal() {
+        let mut miner = Miner {
+            thirst: 0.5,
+            fatigue: 0.5,
+            ..Miner::default()
+        };
+        let mut brain = Think::new().with_evaluator(Thirst).with_evaluator(Fatigue);
+
+        brain.arbitrate(&mut miner);
+        assert_eq!(brain.current_evaluator(), Some(0));
+
+        miner.thirst = 0.4;
+        brain.arbitrate(&mut miner);
+        miner.thirst = 0.5;
+        brain.arbitrate(&mut miner);
+        assert_eq!(brain.current_evaluator(), Some(1));
+    }
+
+    #[test]
+    fn personality_scales_its_trait() {
+        let miner = Miner {
+            thirst: 0.8,
+            fatigue: 0.5,
+            ..Miner::default()
+        };
+        let lazy = Personality {
+            laziness: 2.0,
+            sociability: 0.0,
+            ..Personality::default()
+        };
+        let brain = Think::new()
+            .with_evaluator(Thirst)
+            .with_evaluator(Fatigue)
+            .with_personality(lazy);
+
+        // thirst has no trait, so only laziness applies
+        assert_eq!(brain.scores(&miner), vec![0.8, 1.0]);
+    }
+}
@@ -0,0 +1,295 @@
+//! Loading and saving graphs as RON, so maps can be authored by hand and
+//! shared between examples and tests.
+//!
+//! A graph file looks like this:
+//!
+//! ```text
+//! (
+//!     digraph: false,
+//!     nodes: [
+//!         (index: 0, position: (x: 0.0, y: 0.0), extra_info: Some("Shack")),
+//!         (index: 1, position: (x: 10.0, y: 0.0)),
+//!     ],
+//!     edges: [
+//!         (from: 0, to: 1, cost: 10.0),
+//!     ],
+//! )
+//! ```
+//!
+//! Edges of undirected graphs only need to be listed in one direction.
+//! Indices missing from the node list become removed nodes, so the indices
+//! written in the file are preserved on load. Each index may be listed once,
+//! below [`MAX_NODES`], and edges may only join nodes that are listed.
+
+use super::sparse_graph::{GraphEdge, SparseGraph};
+use crate::vector::Vector2D;
+use serde::{de::DeserializeOwned, Deserialize, Serialize};
+use std::{error, fmt, fs, io, path::Path};
+
+/// The most node slots a graph file may make room for, so a stray index
+/// can't have the loader allocate a slot for every index beneath it.
+pub const MAX_NODES: usize = 1 << 20;
+
+#[derive(Serialize, Deserialize)]
+struct NodeRecord<T> {
+    index: usize,
+    position: Vector2D,
+    #[serde(default = "none", skip_serializing_if = "Option::is_none")]
+    extra_info: Option<T>,
+}
+
+fn none<T>() -> Option<T> {
+    None
+}
+
+#[derive(Serialize, Deserialize)]
+struct GraphRecord<T> {
+    digraph: bool,
+    nodes: Vec<NodeRecord<T>>,
+    edges: Vec<GraphEdge>,
+}
+
+/// Errors raised while reading or writing a graph file.
+#[derive(Debug)]
+pub enum GraphFileError {
+    Io(io::Error),
+    Serialize(ron::Error),
+    Parse(ron::error::SpannedError),
+    /// A node's index is [`MAX_NODES`] or more.
+    NodeOutOfRange(usize),
+    /// Two nodes were listed with the same index.
+    DuplicateNode(usize),
+    /// An edge joins a node that isn't listed.
+    MissingNode(GraphEdge),
+}
+
+impl fmt::Display for GraphFileError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            GraphFileError::Io(e) => write!(f, "graph file i/o error: {}", e),
+            GraphFileError::Serialize(e) => write!(f, "could not write graph: {}", e),
+            GraphFileError::Parse(e) => write!(f, "could not parse graph: {}", e),
+            GraphFileError::NodeOutOfRange(index) => write!(
+                f,
+                "node {} is out of range, indices must be below {}",
+                index, MAX_NODES
+            ),
+            GraphFileError::DuplicateNode(index) => {
+                write!(f, "node {} is listed more than once", index)
+            }
+            GraphFileError::MissingNode(edge) => write!(
+                f,
+                "edge from {} to {} joins a node that isn't listed",
+                edge.from, edge.to
+            ),
+        }
+    }
+}
+
+impl error::Error for GraphFileError {}
+
+impl From<io::Error> for GraphFileError {
+    fn from(e: io::Error) -> Self {
+        GraphFileError::Io(e)
+    }
+}
+
+impl From<ron::Error> for GraphFileError {
+    fn from(e: ron::Error) -> Self {
+        GraphFileError::Serialize(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for GraphFileError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        GraphFileError::Parse(e)
+    }
+}
+
+impl<T: Clone + Serialize> SparseGraph<T> {
+    pub fn to_ron_string(&self) -> Result<String, GraphFileError> {
+        let nodes = self
+            .nodes()
+            .map(|node| NodeRecord {
+                index: node.index,
+                position: node.position,
+                extra_info: node.extra_info.clone(),
+            })
+            .collect();
+
+        let edges = self
+            .nodes()
+            .flat_map(|node| self.edges(node.index))
+            .filter(|edge| self.is_digraph() || edge.from <= edge.to)
+            .copied()
+            .collect();
+
+        let record = GraphRecord {
+            digraph: self.is_digraph(),
+            nodes,
+            edges,
+        };
+
+        Ok(ron::ser::to_string_pretty(
+            &record,
+            ron::ser::PrettyConfig::default(),
+        )?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), GraphFileError> {
+        fs::write(path, self.to_ron_string()?)?;
+        Ok(())
+    }
+}
+
+impl<T: DeserializeOwned> SparseGraph<T> {
+    pub fn from_ron_str(s: &str) -> Result<Self, GraphFileError> {
+        let record: GraphRecord<T> = ron::from_str(s)?;
+        let mut graph = SparseGraph::new(record.digraph);
+
+        if let Some(node) = record.nodes.iter().find(|node| node.index >= MAX_NODES) {
+            return Err(GraphFileError::NodeOutOfRange(node.index));
+        }
+
+        let num_nodes = record
+            .nodes
+            .iter()
+            .map(|node| node.index + 1)
+            .max()
+            .unwrap_or(0);
+        for _ in 0..num_nodes {
+            graph.add_node(Vector2D::zero());
+        }
+
+        let mut present = vec![false; num_nodes];
+        for node in record.nodes {
+            if present[node.index] {
+                return Err(GraphFileError::DuplicateNode(node.index));
+            }
+            present[node.index] = true;
+            if let Some(slot) = graph.node_mut(node.index) {
+                slot.position = node.position;
+                slot.extra_info = node.extra_info;
+            }
+        }
+
+        for (index, present) in present.into_iter().enumerate() {
+            if !present {
+                graph.remove_node(index);
+            }
+        }
+
+        for edge in record.edges {
+            if !graph.is_node_present(edge.from) || !graph.is_node_present(edge.to) {
+                return Err(GraphFileError::MissingNode(edge));
+            }
+            graph.add_edge(edge);
+        }
+
+        Ok(graph)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, GraphFileError> {
+        Self::from_ron_str(&fs::read_to_string(path)?)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::grid::{create_grid_graph, Grid};
+
+    #[test]
+    fn round_trip_keeps_nodes_edges_and_tags() {
+        let grid = Grid::from_rows(&["..#", "..."], 1.0);
+        let mut graph: SparseGraph<String> = create_grid_graph(&grid);
+        graph.node_mut(0).unwrap().extra_info = Some("Shack".to_string());
+
+        let loaded = SparseGraph::<String>::from_ron_str(&graph.to_ron_string().unwrap()).unwrap();
+
+        assert_eq!(loaded.num_nodes(), graph.num_nodes());
+        assert_eq!(loaded.num_edges(), graph.num_edges());
+        assert!(!loaded.is_node_present(2));
+        assert_eq!(loaded.node(0), graph.node(0));
+        assert_eq!(loaded.edge(0, 4), graph.edge(0, 4));
+    }
+
+    #[test]
+    fn round_trip_keeps_self_loops() {
+        for &digraph in &[false, true] {
+            let mut graph: SparseGraph<String> = SparseGraph::new(digraph);
+            let a = graph.add_node(Vector2D::zero());
+            let b = graph.add_node(Vector2D::new(1.0, 0.0));
+            graph.add_edge(GraphEdge::new(a, a, 2.0));
+            graph.add_edge(GraphEdge::new(a, b, 1.0));
+
+            let loaded =
+                SparseGraph::<String>::from_ron_str(&graph.to_ron_string().unwrap()).unwrap();
+
+            assert_eq!(loaded.num_edges(), graph.num_edges());
+            assert_eq!(loaded.edge(a, a), graph.edge(a, a));
+        }
+    }
+
+    #[test]
+    fn loads_the_bundled_town_map() {
+        let path = concat!(env!("CARGO_MANIFEST_DIR"), "/maps/town.ron");
+        let town = SparseGraph::<String>::load(path).unwrap();
+
+        let saloon = town
+            .nodes()
+            .find(|node| node.extra_info.as_deref() == Some("Saloon"))
+            .unwrap();
+        assert!(!town.edges(saloon.index).is_empty());
+        assert!(town.nodes().all(|node| !town.edges(node.index).is_empty()));
+    }
+
+    #[test]
+    fn reports_parse_errors() {
+        match SparseGraph::<String>::from_ron_str("(digraph: maybe)") {
+            Err(GraphFileError::Parse(_)) => (),
+            other => panic!("expected a parse error, got {:?}", other.map(|_| ())),
+        }
+    }
+
+    fn load_error(s: &str) -> GraphFileError {
+        match SparseGraph::<String>::from_ron_str(s) {
+            Err(e) => e,
+            Ok(_) => panic!("expected {} not to load", s),
+        }
+    }
+
+    #[test]
+    fn reports_edges_to_missing_nodes() {
+        let s = "(digraph: false, nodes: [(index: 0, position: (x: 0.0, y: 0.0))], \
+                 edges: [(from: 0, to: 3, cost: 1.0)])";
+        match load_error(s) {
+            GraphFileError::MissingNode(edge) => assert_eq!((edge.from, edge.to), (0, 3)),
+            e => panic!("expected a missing node, got {}", e),
+        }
+    }
+
+    #[test]
+    fn reports_duplicate_nodes() {
+        let s = "(digraph: false, nodes: [(index: 1, position: (x: 0.0, y: 0.0)), \
+                 (index: 1, position: (x: 5.0, y: 0.0))], edges: [])";
+        match load_error(s) {
+            GraphFileError::DuplicateNode(1) => (),
+            e => panic!("expected a duplicate node, got {}", e),
+        }
+    }
+
+    #[test]
+    fn reports_indices_out_of_range() {
+        for &index in &[MAX_NODES, usize::MAX] {
+            let s = format!(
+                "(digraph: false, nodes: [(index: {}, position: (x: 0.0, y: 0.0))], edges: [])",
+                index
+            );
+            match load_error(&s) {
+                GraphFileError::NodeOutOfRange(i) => assert_eq!(i, index),
+                e => panic!("expected an index out of range, got {}", e),
+            }
+        }
+    }
+}
@@ -0,0 +1,319 @@
+//! Flow fields: one pathing solution shared by every agent heading for the
+//! same goal.
+//!
+//! A flow field is built in three passes over a tile grid:
+//!
+//! 1. the *cost field* gives the price of entering each cell, with
+//!    [`IMPASSABLE`] marking cells that can't be entered at all;
+//! 2. the *integration field* holds the cheapest total cost from each cell to
+//!    the goal, found by a Dijkstra search outwards from the goal;
+//! 3. the *direction field* points each cell at the neighbour it's cheapest
+//!    to carry on through.
+//!
+//! Building the field costs about as much as a single search, after which any
+//! number of agents can look up which way to go in constant time. That makes
+//! it the better choice when a crowd converges on one place, e.g. everyone in
+//! town heading for the saloon.
+
+use super::grid::Grid;
+use super::path::PathGuide;
+use super::search::Candidate;
+use crate::vector::Vector2D;
+use std::collections::BinaryHeap;
+
+/// The cost of a cell that can't be entered.
+pub const IMPASSABLE: u8 = u8::MAX;
+
+/// How close to the centre of the goal cell counts as arrived.
+const ARRIVED_DISTANCE_SQ: f64 = 1e-6;
+
+/// Directions and costs towards a single goal cell, for every cell of a grid.
+#[derive(Clone, Debug)]
+pub struct FlowField {
+    grid: Grid,
+    goal: (usize, usize),
+    costs: Vec<u8>,
+    integration: Vec<f64>,
+    directions: Vec<Vector2D>,
+}
+
+impl FlowField {
+    /// Builds the field for `goal`, with every open cell costing 1 to enter.
+    pub fn new(grid: &Grid, goal: (usize, usize)) -> Self {
+        FlowField::with_costs(grid, cost_field(grid), goal)
+    }
+
+    /// Builds the field for `goal` from a cost field indexed by
+    /// [`Grid::cell_index`], so some cells (mud, shallow water, ...) can be
+    /// made more expensive than others.
+    ///
+    /// Panics if `costs` doesn't have one entry per cell.
+    pub fn with_costs(grid: &Grid, costs: Vec<u8>, goal: (usize, usize)) -> Self {
+        assert_eq!(
+            costs.len(),
+            grid.width() * grid.height(),
+            "cost field doesn't match the grid"
+        );
+
+        let mut field = FlowField {
+            grid: grid.clone(),
+            goal,
+            costs,
+            integration: vec![f64::INFINITY; grid.width() * grid.height()],
+            directions: vec![Vector2D::zero(); grid.width() * grid.height()],
+        };
+        field.integrate();
+        field.point_downhill();
+        field
+    }
+
+    pub fn goal(&self) -> (usize, usize) {
+        self.goal
+    }
+
+    pub fn grid(&self) -> &Grid {
+        &self.grid
+    }
+
+    /// The cost of entering a cell; cells off the grid are [`IMPASSABLE`].
+    pub fn cost(&self, x: usize, y: usize) -> u8 {
+        if self.grid.in_bounds(x, y) {
+            self.costs[self.grid.cell_index(x, y)]
+        } else {
+            IMPASSABLE
+        }
+    }
+
+    /// The total cost of getting from a cell to the goal, or `None` if the
+    /// goal can't be reached from it.
+    pub fn integrated_cost(&self, x: usize, y: usize) -> Option<f64> {
+        if !self.grid.in_bounds(x, y) {
+            return None;
+        }
+        Some(self.integration[self.grid.cell_index(x, y)]).filter(|cost| cost.is_finite())
+    }
+
+    pub fn is_reachable(&self, x: usize, y: usize) -> bool {
+        self.integrated_cost(x, y).is_some()
+    }
+
+    /// The unit direction to move in from a cell. Zero at the goal and in
+    /// cells the goal can't be reached from.
+    pub fn direction(&self, x: usize, y: usize) -> Vector2D {
+        if self.grid.in_bounds(x, y) {
+            self.directions[self.grid.cell_index(x, y)]
+        } else {
+            Vector2D::zero()
+        }
+    }
+
+    fn integrate(&mut self) {
+        let (gx, gy) = self.goal;
+        if self.cost(gx, gy) == IMPASSABLE {
+            return;
+        }
+
+        let goal = self.grid.cell_index(gx, gy);
+        self.integration[goal] = 0.0;
+        let mut frontier = BinaryHeap::new();
+        frontier.push(Candidate {
+            cost: 0.0,
+            node: goal,
+        });
+
+        while let Some(Candidate { cost, node }) = frontier.pop() {
+            if cost > self.integration[node] {
+                continue;
+            }
+
+            // the field is built outwards from the goal, so a step from a
+            // neighbour pays for entering this cell, not the neighbour
+            let (x, y) = self.grid.cell_coords(node);
+            let entry_cost = f64::from(self.costs[node]);
+            for (nx, ny, distance) in self.neighbours(x, y) {
+                let index = self.grid.cell_index(nx, ny);
+                let next = cost + distance * entry_cost;
+                if next < self.integration[index] {
+                    self.integration[index] = next;
+                    frontier.push(Candidate {
+                        cost: next,
+                        node: index,
+                    });
+                }
+            }
+        }
+    }
+
+    fn point_downhill(&mut self) {
+        for y in 0..self.grid.height() {
+            for x in 0..self.grid.width() {
+                let index = self.grid.cell_index(x, y);
+                if (x, y) == self.goal || !self.integration[index].is_finite() {
+                    continue;
+                }
+
+                // the cheapest way on, which isn't always the neighbour
+                // closest to the goal when that neighbour is costly to enter
+                let best = self
+                    .neighbours(x, y)
+                    .into_iter()
+                    .map(|(nx, ny, distance)| {
+                        let next = self.grid.cell_index(nx, ny);
+                        let cost = self.integration[next] + distance * f64::from(self.costs[next]);
+                        (nx, ny, cost)
+                    })
+                    .min_by(|a, b| a.2.partial_cmp(&b.2).unwrap());
+
+                if let Some((nx, ny, _)) = best {
+                    self.directions[index] =
+                        (self.grid.cell_center(nx, ny) - self.grid.cell_center(x, y)).normalize();
+                }
+            }
+        }
+    }
+
+    /// The passable neighbours of a cell in all eight directions, with the
+    /// distance to each in cells. Diagonals that would cut the corner of an
+    /// impassable cell are left out, as in
+    /// [`create_grid_graph`](super::grid::create_grid_graph).
+    fn neighbours(&self, x: usize, y: usize) -> Vec<(usize, usize, f64)> {
+        let mut neighbours = Vec::with_capacity(8);
+
+        for dy in -1isize..=1 {
+            for dx in -1isize..=1 {
+                if dx == 0 && dy == 0 {
+                    continue;
+                }
+
+                let nx = x as isize + dx;
+                let ny = y as isize + dy;
+                if nx < 0 || ny < 0 {
+                    continue;
+                }
+                let (nx, ny) = (nx as usize, ny as usize);
+                if self.cost(nx, ny) == IMPASSABLE {
+                    continue;
+                }
+
+                let diagonal = dx != 0 && dy != 0;
+                if diagonal {
+                    if self.cost(nx, y) == IMPASSABLE || self.cost(x, ny) == IMPASSABLE {
+                        continue;
+                    }
+                    neighbours.push((nx, ny, std::f64::consts::SQRT_2));
+                } else {
+                    neighbours.push((nx, ny, 1.0));
+                }
+            }
+        }
+
+        neighbours
+    }
+}
+
+impl PathGuide for FlowField {
+    fn heading(&self, position: Vector2D) -> Option<Vector2D> {
+        let (x, y) = self.grid.cell_at(position)?;
+        if !self.is_reachable(x, y) {
+            return None;
+        }
+
+        if (x, y) == self.goal {
+            let to_goal = self.grid.cell_center(x, y) - position;
+            return Some(to_goal.normalize()).filter(|_| to_goal.length_sq() > ARRIVED_DISTANCE_SQ);
+        }
+
+        Some(self.direction(x, y))
+    }
+}
+
+/// The default cost field for a grid: 1 for open cells and [`IMPASSABLE`] for
+/// blocked ones.
+pub fn cost_field(grid: &Grid) -> Vec<u8> {
+    (0..grid.width() * grid.height())
+        .map(|index| {
+            let (x, y) = grid.cell_coords(index);
+            if grid.is_blocked(x, y) {
+                IMPASSABLE
+            } else {
+                1
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::grid::create_grid_graph;
+    use crate::graph::search::FindNodeIndex;
+    use crate::graph::search::GraphSearchDijkstra;
+    use crate::graph::{SparseGraph, TimeSlicedSearch};
+
+    fn follow(field: &FlowField, mut cell: (usize, usize)) -> Vec<(usize, usize)> {
+        let mut visited = vec![cell];
+        while cell != field.goal() {
+            let step = field.direction(cell.0, cell.1);
+            assert!(!step.is_zero(), "stuck at {:?}", cell);
+            cell = (
+                (cell.0 as f64 + step.x.round()) as usize,
+                (cell.1 as f64 + step.y.round()) as usize,
+            );
+            assert!(visited.len() < 100, "looping through {:?}", visited);
+            visited.push(cell);
+        }
+        visited
+    }
+
+    #[test]
+    fn integration_matches_dijkstra() {
+        let grid = Grid::from_rows(&["......", ".####.", "......", "##.#.."], 1.0);
+        let goal = (0, 0);
+        let field = FlowField::new(&grid, goal);
+        let graph: SparseGraph = create_grid_graph(&grid);
+
+        for node in graph.nodes() {
+            let (x, y) = grid.cell_coords(node.index);
+            let mut search =
+                GraphSearchDijkstra::new(&graph, node.index, FindNodeIndex(grid.cell_index(0, 0)));
+            search.search(&graph);
+            let expected = search.cost_to_target();
+            let actual = field.integrated_cost(x, y).unwrap();
+            assert!((expected - actual).abs() < 1e-9, "({}, {})", x, y);
+        }
+    }
+
+    #[test]
+    fn directions_lead_round_walls_to_the_goal() {
+        let grid = Grid::from_rows(&["......", "#####.", "......"], 1.0);
+        let field = FlowField::new(&grid, (0, 0));
+
+        let route = follow(&field, (0, 2));
+        assert!(route.contains(&(5, 1)));
+        assert_eq!(field.direction(0, 0), Vector2D::zero());
+    }
+
+    #[test]
+    fn expensive_cells_are_avoided() {
+        let grid = Grid::new(3, 3, 1.0);
+        let mut costs = cost_field(&grid);
+        costs[grid.cell_index(1, 1)] = 10;
+        let field = FlowField::with_costs(&grid, costs, (2, 2));
+
+        assert!(!follow(&field, (0, 0)).contains(&(1, 1)));
+    }
+
+    #[test]
+    fn walled_off_cells_have_no_heading() {
+        let grid = Grid::from_rows(&["..#.", "..#."], 1.0);
+        let field = FlowField::new(&grid, (0, 0));
+
+        assert!(!field.is_reachable(3, 0));
+        assert_eq!(field.heading(grid.cell_center(3, 1)), None);
+        assert_eq!(field.heading(grid.cell_center(0, 0)), None);
+        assert_eq!(
+            field.heading(grid.cell_center(1, 0)),
+            Some(Vector2D::new(-1.0, 0.0))
+        );
+    }
+}
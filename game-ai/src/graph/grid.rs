@@ -0,0 +1,261 @@
+//! Building navigation graphs from tile grids.
+
+use super::sparse_graph::{GraphEdge, SparseGraph};
+use crate::vector::Vector2D;
+
+/// A rectangular grid of square cells, some of which are blocked.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Grid {
+    width: usize,
+    height: usize,
+    cell_size: f64,
+    blocked: Vec<bool>,
+}
+
+impl Grid {
+    /// Creates a grid with every cell open.
+    pub fn new(width: usize, height: usize, cell_size: f64) -> Self {
+        Grid {
+            width,
+            height,
+            cell_size,
+            blocked: vec![false; width * height],
+        }
+    }
+
+    /// Creates a grid from rows of text, where `#` marks a blocked cell and
+    /// anything else is open. The first row is `y = 0`.
+    pub fn from_rows(rows: &[&str], cell_size: f64) -> Self {
+        let height = rows.len();
+        let width = rows
+            .iter()
+            .map(|row| row.chars().count())
+            .max()
+            .unwrap_or(0);
+        let mut grid = Grid::new(width, height, cell_size);
+
+        for (y, row) in rows.iter().enumerate() {
+            for (x, c) in row.chars().enumerate() {
+                if c == '#' {
+                    grid.block(x, y);
+                }
+            }
+        }
+
+        grid
+    }
+
+    pub fn width(&self) -> usize {
+        self.width
+    }
+
+    pub fn height(&self) -> usize {
+        self.height
+    }
+
+    pub fn cell_size(&self) -> f64 {
+        self.cell_size
+    }
+
+    pub fn in_bounds(&self, x: usize, y: usize) -> bool {
+        x < self.width && y < self.height
+    }
+
+    /// The index of a cell, which is also the index of its graph node.
+    pub fn cell_index(&self, x: usize, y: usize) -> usize {
+        y * self.width + x
+    }
+
+    pub fn cell_coords(&self, index: usize) -> (usize, usize) {
+        (index % self.width, index / self.width)
+    }
+
+    /// The world position of the centre of a cell.
+    pub fn cell_center(&self, x: usize, y: usize) -> Vector2D {
+        Vector2D::new(
+            (x as f64 + 0.5) * self.cell_size,
+            (y as f64 + 0.5) * self.cell_size,
+        )
+    }
+
+    /// The cell containing a world position, if it lies on the grid.
+    pub fn cell_at(&self, position: Vector2D) -> Option<(usize, usize)> {
+        if position.x < 0.0 || position.y < 0.0 {
+            return None;
+        }
+
+        let x = (position.x / self.cell_size) as usize;
+        let y = (position.y / self.cell_size) as usize;
+        if self.in_bounds(x, y) {
+            Some((x, y))
+        } else {
+            None
+        }
+    }
+
+    pub fn block(&mut self, x: usize, y: usize) {
+        let index = self.cell_index(x, y);
+        self.blocked[index] = true;
+    }
+
+    pub fn unblock(&mut self, x: usize, y: usize) {
+        let index = self.cell_index(x, y);
+        self.blocked[index] = false;
+    }
+
+    /// Cells outside the grid count as blocked.
+    pub fn is_blocked(&self, x: usize, y: usize) -> bool {
+        !self.in_bounds(x, y) || self.blocked[self.cell_index(x, y)]
+    }
+}
+
+/// Builds a navgraph with one node per cell at the cell centre, connecting
+/// each open cell to its open neighbours in all eight directions. Edge costs
+/// are the distance between cell centres, so diagonals cost `sqrt(2)` times a
+/// straight step. Diagonal moves that would cut the corner of a blocked cell
+/// are left out.
+///
+/// Node indices match [`Grid::cell_index`]; nodes for blocked cells are
+/// removed from the graph.
+pub fn create_grid_graph<T>(grid: &Grid) -> SparseGraph<T> {
+    let mut graph = SparseGraph::new(false);
+
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            graph.add_node(grid.cell_center(x, y));
+        }
+    }
+
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            if grid.is_blocked(x, y) {
+                continue;
+            }
+            add_neighbour_edges(&mut graph, grid, x, y);
+        }
+    }
+
+    for y in 0..grid.height() {
+        for x in 0..grid.width() {
+            if grid.is_blocked(x, y) {
+                graph.remove_node(grid.cell_index(x, y));
+            }
+        }
+    }
+
+    graph
+}
+
+fn add_neighbour_edges<T>(graph: &mut SparseGraph<T>, grid: &Grid, x: usize, y: usize) {
+    let from = grid.cell_index(x, y);
+
+    for dy in -1isize..=1 {
+        for dx in -1isize..=1 {
+            if dx == 0 && dy == 0 {
+                continue;
+            }
+
+            let nx = x as isize + dx;
+            let ny = y as isize + dy;
+            if nx < 0 || ny < 0 {
+                continue;
+            }
+            let (nx, ny) = (nx as usize, ny as usize);
+            if grid.is_blocked(nx, ny) {
+                continue;
+            }
+
+            let diagonal = dx != 0 && dy != 0;
+            if diagonal && (grid.is_blocked(nx, y) || grid.is_blocked(x, ny)) {
+                continue;
+            }
+
+            let to = grid.cell_index(nx, ny);
+            let cost = grid.cell_center(x, y).distance(grid.cell_center(nx, ny));
+            graph.add_edge(GraphEdge::new(from, to, cost));
+        }
+    }
+}
+
+/// Marks every node reachable from `start` by following edges.
+/// The result is indexed by node index.
+pub fn flood_fill<T>(graph: &SparseGraph<T>, start: usize) -> Vec<bool> {
+    let mut reached = vec![false; graph.num_nodes()];
+    if !graph.is_node_present(start) {
+        return reached;
+    }
+
+    let mut stack = vec![start];
+    reached[start] = true;
+
+    while let Some(index) = stack.pop() {
+        for edge in graph.edges(index) {
+            if !reached[edge.to] {
+                reached[edge.to] = true;
+                stack.push(edge.to);
+            }
+        }
+    }
+
+    reached
+}
+
+/// Removes every node that cannot be reached from `start`, returning how many
+/// were removed. Use this to strip out islands an agent could never get to.
+pub fn remove_unreachable_nodes<T>(graph: &mut SparseGraph<T>, start: usize) -> usize {
+    let reached = flood_fill(graph, start);
+    let unreachable: Vec<usize> = graph
+        .nodes()
+        .map(|node| node.index)
+        .filter(|&index| !reached[index])
+        .collect();
+
+    for &index in &unreachable {
+        graph.remove_node(index);
+    }
+
+    unreachable.len()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn open_grid_has_diagonals_with_longer_cost() {
+        let grid = Grid::new(3, 3, 10.0);
+        let graph: SparseGraph = create_grid_graph(&grid);
+
+        assert_eq!(graph.num_active_nodes(), 9);
+        // the centre connects to all eight neighbours
+        assert_eq!(graph.edges(grid.cell_index(1, 1)).len(), 8);
+
+        let straight = graph.edge(0, grid.cell_index(1, 0)).unwrap().cost;
+        let diagonal = graph.edge(0, grid.cell_index(1, 1)).unwrap().cost;
+        assert!((straight - 10.0).abs() < 1e-9);
+        assert!((diagonal - 10.0 * 2f64.sqrt()).abs() < 1e-9);
+    }
+
+    #[test]
+    fn blocked_cells_have_no_node_and_corners_are_not_cut() {
+        let grid = Grid::from_rows(&["..", "#."], 1.0);
+        let graph: SparseGraph = create_grid_graph(&grid);
+
+        assert!(!graph.is_node_present(grid.cell_index(0, 1)));
+        // (0, 0) -> (1, 1) would clip the blocked cell at (0, 1)
+        assert!(!graph.is_edge_present(grid.cell_index(0, 0), grid.cell_index(1, 1)));
+        assert!(graph.is_edge_present(grid.cell_index(0, 0), grid.cell_index(1, 0)));
+    }
+
+    #[test]
+    fn islands_are_removed() {
+        let grid = Grid::from_rows(&["..#..", "..#..", "###.."], 1.0);
+        let mut graph: SparseGraph = create_grid_graph(&grid);
+
+        let removed = remove_unreachable_nodes(&mut graph, grid.cell_index(0, 0));
+
+        assert_eq!(removed, 6);
+        assert_eq!(graph.num_active_nodes(), 4);
+        assert!(!graph.is_node_present(grid.cell_index(4, 2)));
+    }
+}
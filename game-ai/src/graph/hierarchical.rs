@@ -0,0 +1,290 @@
+//! Two-level pathfinding for large maps.
+//!
+//! The nodes of a graph are grouped into clusters, each connected within
+//! itself, and a coarse graph is built with one node per cluster and an edge
+//! wherever a fine edge crosses between clusters. A path is found by first searching the small coarse graph for a
+//! corridor of clusters, then searching the fine graph one pair of clusters at
+//! a time and stitching the pieces together. Each fine search only ever looks
+//! at the nodes of two clusters, so the cost no longer grows with the size of
+//! the whole map.
+
+use super::grid::Grid;
+use super::search::{
+    GraphSearchAStar, GraphSearchDijkstra, TerminationCondition, TimeSlicedSearch,
+};
+use super::sparse_graph::{GraphEdge, NavGraphNode, SparseGraph, INVALID_NODE_INDEX};
+use crate::vector::Vector2D;
+use std::collections::HashMap;
+
+/// The coarse level of a hierarchical graph, built on top of a fine graph.
+#[derive(Clone, Debug)]
+pub struct HierarchicalGraph {
+    clusters: Vec<usize>,
+    coarse: SparseGraph,
+}
+
+/// Terminates when the search enters a given cluster.
+struct InCluster<'a> {
+    clusters: &'a [usize],
+    cluster: usize,
+}
+
+impl<'a, T> TerminationCondition<T> for InCluster<'a> {
+    fn is_satisfied(&self, _graph: &SparseGraph<T>, node: usize) -> bool {
+        self.clusters[node] == self.cluster
+    }
+}
+
+impl HierarchicalGraph {
+    /// Groups the nodes of `graph` using `cluster_of`, which may return any
+    /// identifier; nodes with the same identifier share a cluster, unless
+    /// they can't reach each other within it, when each part is a cluster of
+    /// its own.
+    pub fn new<T, F>(graph: &SparseGraph<T>, mut cluster_of: F) -> Self
+    where
+        F: FnMut(&NavGraphNode<T>) -> usize,
+    {
+        let mut ids = HashMap::new();
+        let mut groups = vec![INVALID_NODE_INDEX; graph.num_nodes()];
+        for node in graph.nodes() {
+            let next_id = ids.len();
+            groups[node.index] = *ids.entry(cluster_of(node)).or_insert(next_id);
+        }
+
+        // a wall can cut a group in two, so each part reachable on its own
+        // becomes a cluster, or the corridor could lead through the wall
+        let mut clusters = vec![INVALID_NODE_INDEX; graph.num_nodes()];
+        let mut sums: Vec<(Vector2D, f64)> = vec![];
+        for node in graph.nodes() {
+            if clusters[node.index] != INVALID_NODE_INDEX {
+                continue;
+            }
+            let cluster = sums.len();
+            sums.push((Vector2D::zero(), 0.0));
+            clusters[node.index] = cluster;
+            let mut open = vec![node.index];
+            while let Some(index) = open.pop() {
+                sums[cluster].0 += graph.node(index).unwrap().position;
+                sums[cluster].1 += 1.0;
+                for edge in graph.edges(index) {
+                    if groups[edge.to] == groups[index] && clusters[edge.to] == INVALID_NODE_INDEX {
+                        clusters[edge.to] = cluster;
+                        open.push(edge.to);
+                    }
+                }
+            }
+        }
+
+        let mut coarse = SparseGraph::new(graph.is_digraph());
+        for (sum, count) in sums {
+            coarse.add_node(sum / count);
+        }
+
+        for node in graph.nodes() {
+            for edge in graph.edges(node.index) {
+                let (from, to) = (clusters[edge.from], clusters[edge.to]);
+                if from != to && !coarse.is_edge_present(from, to) {
+                    let cost = coarse
+                        .node(from)
+                        .unwrap()
+                        .position
+                        .distance(coarse.node(to).unwrap().position);
+                    coarse.add_edge(GraphEdge::new(from, to, cost));
+                }
+            }
+        }
+
+        HierarchicalGraph { clusters, coarse }
+    }
+
+    /// Clusters a graph built by [`create_grid_graph`](super::grid::create_grid_graph)
+    /// into square blocks of `cluster_size` cells, split where walls cut a block
+    /// in two.
+    pub fn from_grid<T>(graph: &SparseGraph<T>, grid: &Grid, cluster_size: usize) -> Self {
+        let clusters_per_row = grid.width().div_ceil(cluster_size);
+        HierarchicalGraph::new(graph, |node| {
+            let (x, y) = grid.cell_coords(node.index);
+            (y / cluster_size) * clusters_per_row + x / cluster_size
+        })
+    }
+
+    /// The cluster a fine node belongs to.
+    pub fn cluster(&self, node: usize) -> Option<usize> {
+        self.clusters
+            .get(node)
+            .copied()
+            .filter(|&cluster| cluster != INVALID_NODE_INDEX)
+    }
+
+    pub fn num_clusters(&self) -> usize {
+        self.coarse.num_nodes()
+    }
+
+    /// The graph of clusters; node `i` is cluster `i`, placed at the centroid
+    /// of its fine nodes.
+    pub fn coarse_graph(&self) -> &SparseGraph {
+        &self.coarse
+    }
+
+    /// Finds a path of fine node indices from `source` to `target`, or `None`
+    /// if the target cannot be reached. The path is close to, but not always
+    /// exactly, the shortest one.
+    pub fn find_path<T>(
+        &self,
+        graph: &SparseGraph<T>,
+        source: usize,
+        target: usize,
+    ) -> Option<Vec<usize>> {
+        let (source_cluster, target_cluster) = (self.cluster(source)?, self.cluster(target)?);
+
+        let mut coarse_search = GraphSearchAStar::new(&self.coarse, source_cluster, target_cluster);
+        coarse_search.search(&self.coarse);
+        let corridor = coarse_search.path_to_target();
+        if corridor.is_empty() {
+            return None;
+        }
+
+        let mut path = vec![source];
+        let mut current = source;
+
+        for pair in corridor.windows(2) {
+            let condition = InCluster {
+                clusters: &self.clusters,
+                cluster: pair[1],
+            };
+            let mut search = GraphSearchDijkstra::new(graph, current, condition)
+                .restrict_to(self.nodes_in(&[pair[0], pair[1]]));
+            search.search(graph);
+
+            let segment = search.path_to_target();
+            if segment.is_empty() {
+                // the clusters touch, but not from where we entered this one
+                return self.find_path_in(graph, source, target, &corridor);
+            }
+            path.extend_from_slice(&segment[1..]);
+            current = *segment.last().unwrap();
+        }
+
+        let mut last_leg = GraphSearchAStar::new(graph, current, target)
+            .restrict_to(self.nodes_in(&[target_cluster]));
+        last_leg.search(graph);
+        let segment = last_leg.path_to_target();
+        if segment.is_empty() {
+            return self.find_path_in(graph, source, target, &corridor);
+        }
+        path.extend_from_slice(&segment[1..]);
+
+        Some(path)
+    }
+
+    /// Fallback: a single search restricted to the whole corridor.
+    fn find_path_in<T>(
+        &self,
+        graph: &SparseGraph<T>,
+        source: usize,
+        target: usize,
+        corridor: &[usize],
+    ) -> Option<Vec<usize>> {
+        let mut search =
+            GraphSearchAStar::new(graph, source, target).restrict_to(self.nodes_in(corridor));
+        search.search(graph);
+        Some(search.path_to_target()).filter(|path| !path.is_empty())
+    }
+
+    fn nodes_in(&self, clusters: &[usize]) -> Vec<bool> {
+        self.clusters
+            .iter()
+            .map(|cluster| clusters.contains(cluster))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::grid::create_grid_graph;
+
+    fn assert_valid_path<T>(graph: &SparseGraph<T>, path: &[usize], source: usize, target: usize) {
+        assert_eq!(path.first(), Some(&source));
+        assert_eq!(path.last(), Some(&target));
+        for pair in path.windows(2) {
+            assert!(graph.is_edge_present(pair[0], pair[1]), "{:?}", pair);
+        }
+    }
+
+    fn cost<T>(graph: &SparseGraph<T>, path: &[usize]) -> f64 {
+        path.windows(2)
+            .map(|pair| graph.edge(pair[0], pair[1]).unwrap().cost)
+            .sum()
+    }
+
+    #[test]
+    fn stitched_path_crosses_clusters() {
+        // a wall down the middle with a single gap near the bottom
+        let mut grid = Grid::new(32, 32, 1.0);
+        for y in 0..30 {
+            grid.block(16, y);
+        }
+        let graph: SparseGraph = create_grid_graph(&grid);
+        let hierarchy = HierarchicalGraph::from_grid(&graph, &grid, 8);
+        assert_eq!(hierarchy.num_clusters(), 16);
+
+        let source = grid.cell_index(2, 2);
+        let target = grid.cell_index(30, 2);
+        let path = hierarchy.find_path(&graph, source, target).unwrap();
+        assert_valid_path(&graph, &path, source, target);
+
+        let mut flat = GraphSearchAStar::new(&graph, source, target);
+        flat.search(&graph);
+        assert!(cost(&graph, &path) < flat.cost_to_target() * 1.5);
+    }
+
+    #[test]
+    fn same_cluster_path() {
+        let grid = Grid::new(16, 16, 1.0);
+        let graph: SparseGraph = create_grid_graph(&grid);
+        let hierarchy = HierarchicalGraph::from_grid(&graph, &grid, 8);
+
+        let (source, target) = (grid.cell_index(1, 1), grid.cell_index(6, 3));
+        let path = hierarchy.find_path(&graph, source, target).unwrap();
+        assert_valid_path(&graph, &path, source, target);
+    }
+
+    #[test]
+    fn a_wall_inside_a_cluster_splits_it() {
+        // the wall cuts the second column of clusters across its top row
+        let mut grid = Grid::new(12, 8, 1.0);
+        for y in 0..4 {
+            grid.block(5, y);
+        }
+        let graph: SparseGraph = create_grid_graph(&grid);
+        let hierarchy = HierarchicalGraph::from_grid(&graph, &grid, 4);
+        assert_eq!(hierarchy.num_clusters(), 7);
+        assert_ne!(
+            hierarchy.cluster(grid.cell_index(4, 0)),
+            hierarchy.cluster(grid.cell_index(6, 0))
+        );
+
+        let (source, target) = (grid.cell_index(0, 0), grid.cell_index(7, 0));
+        let mut flat = GraphSearchAStar::new(&graph, source, target);
+        flat.search(&graph);
+        assert!(!flat.path_to_target().is_empty());
+
+        let path = hierarchy.find_path(&graph, source, target).unwrap();
+        assert_valid_path(&graph, &path, source, target);
+    }
+
+    #[test]
+    fn unreachable_target() {
+        let mut grid = Grid::new(16, 16, 1.0);
+        for y in 0..16 {
+            grid.block(8, y);
+        }
+        let graph: SparseGraph = create_grid_graph(&grid);
+        let hierarchy = HierarchicalGraph::from_grid(&graph, &grid, 4);
+
+        assert!(hierarchy
+            .find_path(&graph, grid.cell_index(0, 0), grid.cell_index(15, 15))
+            .is_none());
+    }
+}
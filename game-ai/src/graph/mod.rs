@@ -0,0 +1,15 @@
+//! Navigation graphs, searches over them and the paths they produce.
+
+pub mod file;
+pub mod flow_field;
+pub mod grid;
+pub mod hierarchical;
+pub mod path;
+pub mod path_manager;
+pub mod search;
+mod sparse_graph;
+
+pub use file::GraphFileError;
+pub use path::PathGuide;
+pub use search::{SearchStatus, TimeSlicedSearch};
+pub use sparse_graph::{GraphEdge, NavGraphNode, SparseGraph, INVALID_NODE_INDEX};
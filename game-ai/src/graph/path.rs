@@ -0,0 +1,234 @@
+//! Paths as lists of edges between waypoints, and the smoothing passes
+//! that remove the zig-zags a graph search leaves behind.
+//!
+//! Smoothing only needs to know whether an agent can walk in a straight line
+//! between two points, so it takes that test as a callback and works with any
+//! map representation (tile grid raycast, wall segments, navmesh, ...).
+
+use crate::vector::Vector2D;
+
+/// A single leg of a path.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct PathEdge {
+    pub source: Vector2D,
+    pub destination: Vector2D,
+}
+
+impl PathEdge {
+    pub fn new(source: Vector2D, destination: Vector2D) -> Self {
+        PathEdge {
+            source,
+            destination,
+        }
+    }
+
+    pub fn length(&self) -> f64 {
+        self.source.distance(self.destination)
+    }
+}
+
+/// Turns a list of waypoints into the edges joining consecutive pairs.
+pub fn edges_from_waypoints(waypoints: &[Vector2D]) -> Vec<PathEdge> {
+    waypoints
+        .windows(2)
+        .map(|pair| PathEdge::new(pair[0], pair[1]))
+        .collect()
+}
+
+/// Turns a list of edges back into the waypoints visited, start included.
+pub fn waypoints(path: &[PathEdge]) -> Vec<Vector2D> {
+    let mut points = Vec::with_capacity(path.len() + 1);
+    if let Some(first) = path.first() {
+        points.push(first.source);
+    }
+    points.extend(path.iter().map(|edge| edge.destination));
+    points
+}
+
+/// Total distance covered by a path.
+pub fn path_length(path: &[PathEdge]) -> f64 {
+    path.iter().map(PathEdge::length).sum()
+}
+
+/// Tells an agent following a route which way to head from where it is.
+///
+/// This is what a path-following steering behaviour consumes, so the same
+/// behaviour can follow either a searched path or a shared
+/// [`FlowField`](super::flow_field::FlowField).
+pub trait PathGuide {
+    /// The unit direction to head in from `position`, or `None` once there's
+    /// nowhere further to go.
+    fn heading(&self, position: Vector2D) -> Option<Vector2D>;
+}
+
+/// Heads for the end of whichever edge is closest to the agent, so an agent
+/// pushed off the path rejoins it further along rather than going back. An
+/// agent on a corner is as close to the edge ending there as to the one
+/// starting there, and takes the later.
+impl PathGuide for [PathEdge] {
+    fn heading(&self, position: Vector2D) -> Option<Vector2D> {
+        let closest = self.iter().rev().min_by(|a, b| {
+            distance_sq_to_edge(position, a).total_cmp(&distance_sq_to_edge(position, b))
+        })?;
+
+        let to_destination = closest.destination - position;
+        if to_destination.is_zero() {
+            None
+        } else {
+            Some(to_destination.normalize())
+        }
+    }
+}
+
+fn distance_sq_to_edge(point: Vector2D, edge: &PathEdge) -> f64 {
+    let along = edge.destination - edge.source;
+    if along.is_zero() {
+        return point.distance_sq(edge.source);
+    }
+
+    let t = ((point - edge.source).dot(along) / along.length_sq()).clamp(0.0, 1.0);
+    point.distance_sq(edge.source + along * t)
+}
+
+/// Rough smoothing: checks each edge only against its immediate successor,
+/// merging the two whenever the agent can walk straight from the start of the
+/// first to the end of the second. Cheap, but can miss longer shortcuts.
+pub fn smooth_path_edges_quick<F>(path: &mut Vec<PathEdge>, mut can_walk_between: F)
+where
+    F: FnMut(Vector2D, Vector2D) -> bool,
+{
+    let mut e1 = 0;
+    let mut e2 = 1;
+
+    while e2 < path.len() {
+        if can_walk_between(path[e1].source, path[e2].destination) {
+            path[e1].destination = path[e2].destination;
+            path.remove(e2);
+        } else {
+            e1 = e2;
+            e2 += 1;
+        }
+    }
+}
+
+/// Precise smoothing: tests every edge against all the edges after it, so the
+/// longest walkable shortcut from each point is always taken. Quadratic in the
+/// number of edges, which is fine for the short paths agents normally follow.
+pub fn smooth_path_edges_precise<F>(path: &mut Vec<PathEdge>, mut can_walk_between: F)
+where
+    F: FnMut(Vector2D, Vector2D) -> bool,
+{
+    let mut e1 = 0;
+
+    while e1 < path.len() {
+        let mut e2 = e1 + 1;
+
+        while e2 < path.len() {
+            if can_walk_between(path[e1].source, path[e2].destination) {
+                path[e1].destination = path[e2].destination;
+                path.drain(e1 + 1..=e2);
+                e2 = e1 + 1;
+            } else {
+                e2 += 1;
+            }
+        }
+
+        e1 += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn v(x: f64, y: f64) -> Vector2D {
+        Vector2D::new(x, y)
+    }
+
+    // a staircase path along grid cells, with a wall blocking the diagonal
+    // from (1, 0) to (3, 2)
+    fn staircase() -> Vec<PathEdge> {
+        edges_from_waypoints(&[
+            v(0., 0.),
+            v(1., 0.),
+            v(2., 0.),
+            v(2., 1.),
+            v(3., 1.),
+            v(3., 2.),
+        ])
+    }
+
+    fn blocked(a: Vector2D, b: Vector2D) -> bool {
+        a == v(1., 0.) && b == v(3., 2.) || a == v(0., 0.) && b == v(3., 2.)
+    }
+
+    #[test]
+    fn waypoints_round_trip() {
+        let path = staircase();
+        assert_eq!(path.len(), 5);
+        assert_eq!(edges_from_waypoints(&waypoints(&path)), path);
+        assert!(waypoints(&[]).is_empty());
+    }
+
+    #[test]
+    fn smoothing_an_open_path_leaves_one_edge() {
+        let mut quick = staircase();
+        smooth_path_edges_quick(&mut quick, |_, _| true);
+        assert_eq!(quick, vec![PathEdge::new(v(0., 0.), v(3., 2.))]);
+
+        let mut precise = staircase();
+        smooth_path_edges_precise(&mut precise, |_, _| true);
+        assert_eq!(precise, quick);
+    }
+
+    #[test]
+    fn smoothing_never_takes_a_blocked_shortcut() {
+        let mut quick = staircase();
+        smooth_path_edges_quick(&mut quick, |a, b| !blocked(a, b));
+        for edge in &quick {
+            assert!(!blocked(edge.source, edge.destination));
+        }
+        assert_eq!(quick.first().unwrap().source, v(0., 0.));
+        assert_eq!(quick.last().unwrap().destination, v(3., 2.));
+
+        let mut precise = staircase();
+        smooth_path_edges_precise(&mut precise, |a, b| !blocked(a, b));
+        for edge in &precise {
+            assert!(!blocked(edge.source, edge.destination));
+        }
+        assert!(precise.len() <= quick.len());
+        assert!(path_length(&precise) <= path_length(&staircase()));
+    }
+
+    #[test]
+    fn nothing_walkable_leaves_path_untouched() {
+        let mut path = staircase();
+        smooth_path_edges_precise(&mut path, |_, _| false);
+        assert_eq!(path, staircase());
+    }
+
+    #[test]
+    fn guide_heads_along_the_closest_edge() {
+        let path = edges_from_waypoints(&[v(0., 0.), v(10., 0.), v(10., 10.)]);
+
+        assert_eq!(path.heading(v(5., 0.)), Some(v(1., 0.)));
+        assert_eq!(path.heading(v(5., 1.)), Some(v(5., -1.).normalize()));
+        assert_eq!(path.heading(v(11., 5.)), Some(v(-1., 5.).normalize()));
+        assert_eq!(path.heading(v(10., 10.)), None);
+        assert_eq!(Vec::<PathEdge>::new().heading(v(0., 0.)), None);
+    }
+
+    #[test]
+    fn guide_carries_on_round_a_corner() {
+        let path = edges_from_waypoints(&[v(0., 0.), v(10., 0.), v(10., 10.)]);
+
+        assert_eq!(path.heading(v(10., 0.)), Some(v(0., 1.)));
+    }
+
+    #[test]
+    fn guide_does_not_panic_when_lost() {
+        let path = edges_from_waypoints(&[v(0., 0.), v(10., 0.), v(10., 10.)]);
+
+        path.heading(v(f64::NAN, 0.));
+    }
+}
@@ -0,0 +1,311 @@
+//! Sharing a fixed amount of search work per frame between many agents.
+//!
+//! Rather than every agent running its search to completion as soon as it
+//! wants a path, each search is registered with a [`PathManager`], which
+//! advances the searches a cycle at a time until the frame's budget is spent.
+//! Frame times stay flat no matter how many agents ask for a path at once;
+//! the cost is that busy frames make agents wait a little longer for theirs.
+
+use super::search::{SearchStatus, TimeSlicedSearch};
+use super::sparse_graph::SparseGraph;
+use std::collections::VecDeque;
+
+pub static DEFAULT_CYCLES_PER_UPDATE: usize = 100; // nodes expanded per update across all searches
+
+/// How the budget is split between searches.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum BudgetPolicy {
+    /// One cycle per search in turn, carrying on where the last update left
+    /// off so every search gets an even share over time.
+    RoundRobin,
+    /// The highest priority search gets every cycle until it finishes;
+    /// searches of equal priority take turns.
+    Priority,
+}
+
+/// The result of a finished search.
+#[derive(Clone, Debug, PartialEq)]
+pub enum SearchOutcome {
+    Found { path: Vec<usize>, cost: f64 },
+    NotFound,
+}
+
+struct ManagedSearch<K, T> {
+    owner: K,
+    priority: i32,
+    search: Box<dyn TimeSlicedSearch<T> + Send + Sync>,
+    /// How many cycles it's had so far.
+    cycles: usize,
+}
+
+/// Owns the active time-sliced searches, at most one per owner.
+pub struct PathManager<K, T> {
+    searches: VecDeque<ManagedSearch<K, T>>,
+    cycles_per_update: usize,
+    policy: BudgetPolicy,
+}
+
+impl<K: PartialEq, T> PathManager<K, T> {
+    pub fn new(cycles_per_update: usize, policy: BudgetPolicy) -> Self {
+        PathManager {
+            searches: VecDeque::new(),
+            cycles_per_update,
+            policy,
+        }
+    }
+
+    pub fn cycles_per_update(&self) -> usize {
+        self.cycles_per_update
+    }
+
+    pub fn set_cycles_per_update(&mut self, cycles: usize) {
+        self.cycles_per_update = cycles;
+    }
+
+    pub fn policy(&self) -> BudgetPolicy {
+        self.policy
+    }
+
+    pub fn set_policy(&mut self, policy: BudgetPolicy) {
+        self.policy = policy;
+    }
+
+    /// Adds a search for `owner` with the default priority of zero, replacing
+    /// any search the owner already had running.
+    pub fn register(&mut self, owner: K, search: Box<dyn TimeSlicedSearch<T> + Send + Sync>) {
+        self.register_with_priority(owner, search, 0);
+    }
+
+    pub fn register_with_priority(
+        &mut self,
+        owner: K,
+        search: Box<dyn TimeSlicedSearch<T> + Send + Sync>,
+        priority: i32,
+    ) {
+        self.cancel(&owner);
+        self.searches.push_back(ManagedSearch {
+            owner,
+            priority,
+            search,
+            cycles: 0,
+        });
+    }
+
+    /// Adds a search for `owner` that had already had `cycles` cycles, e.g.
+    /// one saved with the game, running it that far again. Searches are
+    /// deterministic, so it ends up just as it was.
+    pub fn resume(
+        &mut self,
+        owner: K,
+        mut search: Box<dyn TimeSlicedSearch<T> + Send + Sync>,
+        cycles: usize,
+        graph: &SparseGraph<T>,
+    ) {
+        for _ in 0..cycles {
+            search.cycle_once(graph);
+        }
+        self.register(owner, search);
+        if let Some(resumed) = self.searches.back_mut() {
+            resumed.cycles = cycles;
+        }
+    }
+
+    /// Drops the search belonging to `owner`, e.g. when the agent dies, so it
+    /// stops taking a share of the budget. Returns whether there was one.
+    pub fn cancel(&mut self, owner: &K) -> bool {
+        let before = self.searches.len();
+        self.searches.retain(|managed| managed.owner != *owner);
+        self.searches.len() < before
+    }
+
+    pub fn is_searching(&self, owner: &K) -> bool {
+        self.searches.iter().any(|managed| managed.owner == *owner)
+    }
+
+    /// Number of searches still in progress.
+    pub fn num_active_searches(&self) -> usize {
+        self.searches.len()
+    }
+
+    /// The owners of the searches still in progress, in the order they take
+    /// their turns, with how many cycles each has had.
+    pub fn searches(&self) -> impl Iterator<Item = (&K, usize)> {
+        self.searches
+            .iter()
+            .map(|managed| (&managed.owner, managed.cycles))
+    }
+
+    /// Spends this update's budget on the active searches and returns the
+    /// owners whose searches finished, in the order they finished.
+    pub fn update_searches(&mut self, graph: &SparseGraph<T>) -> Vec<(K, SearchOutcome)> {
+        let mut finished = vec![];
+        let mut cycles = self.cycles_per_update;
+
+        while cycles > 0 {
+            let index = match self.next_search() {
+                Some(index) => index,
+                None => break,
+            };
+            let mut managed = self.searches.remove(index).unwrap();
+            cycles -= 1;
+            managed.cycles += 1;
+
+            match managed.search.cycle_once(graph) {
+                SearchStatus::SearchIncomplete => self.searches.push_back(managed),
+                SearchStatus::TargetFound => {
+                    let outcome = SearchOutcome::Found {
+                        path: managed.search.path_to_target(),
+                        cost: managed.search.cost_to_target(),
+                    };
+                    finished.push((managed.owner, outcome));
+                }
+                SearchStatus::TargetNotFound => {
+                    finished.push((managed.owner, SearchOutcome::NotFound));
+                }
+            }
+        }
+
+        finished
+    }
+
+    fn next_search(&self) -> Option<usize> {
+        match self.policy {
+            BudgetPolicy::RoundRobin if self.searches.is_empty() => None,
+            BudgetPolicy::RoundRobin => Some(0),
+            BudgetPolicy::Priority => {
+                let highest = self.searches.iter().map(|m| m.priority).max()?;
+                self.searches.iter().position(|m| m.priority == highest)
+            }
+        }
+    }
+}
+
+impl<K: PartialEq, T> Default for PathManager<K, T> {
+    fn default() -> Self {
+        PathManager::new(DEFAULT_CYCLES_PER_UPDATE, BudgetPolicy::RoundRobin)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::grid::{create_grid_graph, Grid};
+    use crate::graph::search::GraphSearchAStar;
+
+    fn corridor() -> (Grid, SparseGraph) {
+        let grid = Grid::new(20, 1, 1.0);
+        let graph = create_grid_graph(&grid);
+        (grid, graph)
+    }
+
+    fn search_across(graph: &SparseGraph) -> Box<GraphSearchAStar> {
+        Box::new(GraphSearchAStar::new(graph, 0, 19))
+    }
+
+    #[test]
+    fn budget_limits_cycles_per_update() {
+        let (_, graph) = corridor();
+        let mut manager = PathManager::new(5, BudgetPolicy::RoundRobin);
+        manager.register("bob", search_across(&graph));
+
+        // 20 nodes need 20 cycles at 5 per update
+        for _ in 0..3 {
+            assert!(manager.update_searches(&graph).is_empty());
+        }
+        let finished = manager.update_searches(&graph);
+        assert_eq!(finished.len(), 1);
+        assert!(matches!(finished[0], ("bob", SearchOutcome::Found { .. })));
+        assert_eq!(manager.num_active_searches(), 0);
+    }
+
+    #[test]
+    fn round_robin_shares_the_budget() {
+        let (_, graph) = corridor();
+        let mut manager = PathManager::new(10, BudgetPolicy::RoundRobin);
+        manager.register("bob", search_across(&graph));
+        manager.register("elsa", search_across(&graph));
+
+        for _ in 0..3 {
+            assert!(manager.update_searches(&graph).is_empty());
+        }
+        let finished: Vec<_> = manager
+            .update_searches(&graph)
+            .into_iter()
+            .map(|(owner, _)| owner)
+            .collect();
+        assert_eq!(finished, vec!["bob", "elsa"]);
+    }
+
+    #[test]
+    fn priority_search_finishes_first() {
+        let (_, graph) = corridor();
+        let mut manager = PathManager::new(20, BudgetPolicy::Priority);
+        manager.register("bob", search_across(&graph));
+        manager.register_with_priority("sheriff", search_across(&graph), 1);
+
+        let finished = manager.update_searches(&graph);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].0, "sheriff");
+        assert!(manager.is_searching(&"bob"));
+    }
+
+    #[test]
+    fn a_resumed_search_carries_on_where_it_left_off() {
+        let (_, graph) = corridor();
+        let mut manager = PathManager::new(5, BudgetPolicy::RoundRobin);
+        manager.register("bob", search_across(&graph));
+        manager.register("elsa", search_across(&graph));
+        manager.update_searches(&graph);
+        let saved: Vec<_> = manager
+            .searches()
+            .map(|(&owner, cycles)| (owner, cycles))
+            .collect();
+        assert_eq!(saved, vec![("elsa", 2), ("bob", 3)]);
+
+        let mut resumed = PathManager::new(5, BudgetPolicy::RoundRobin);
+        for &(owner, cycles) in &saved {
+            resumed.resume(owner, search_across(&graph), cycles, &graph);
+        }
+        for _ in 0..8 {
+            assert_eq!(
+                resumed.update_searches(&graph),
+                manager.update_searches(&graph)
+            );
+            assert!(resumed.searches().eq(manager.searches()));
+        }
+        assert_eq!(resumed.num_active_searches(), 0);
+    }
+
+    #[test]
+    fn a_cancelled_search_leaves_the_budget_to_the_rest() {
+        let (_, graph) = corridor();
+        let mut manager = PathManager::new(10, BudgetPolicy::RoundRobin);
+        manager.register("bob", search_across(&graph));
+        manager.register("elsa", search_across(&graph));
+        manager.update_searches(&graph);
+
+        assert!(manager.cancel(&"bob"));
+        assert!(!manager.cancel(&"bob"));
+        assert!(!manager.is_searching(&"bob"));
+        // elsa's had 5 of her 20 cycles, and now gets all 10 an update
+        assert!(manager.update_searches(&graph).is_empty());
+        let finished = manager.update_searches(&graph);
+        assert_eq!(finished.len(), 1);
+        assert_eq!(finished[0].0, "elsa");
+        assert_eq!(manager.num_active_searches(), 0);
+    }
+
+    #[test]
+    fn registering_again_replaces_the_search() {
+        let (_, graph) = corridor();
+        let mut manager = PathManager::default();
+        manager.register("bob", search_across(&graph));
+        manager.register("bob", Box::new(GraphSearchAStar::new(&graph, 0, 1)));
+        assert_eq!(manager.num_active_searches(), 1);
+
+        match &manager.update_searches(&graph)[..] {
+            [(_, SearchOutcome::Found { path, .. })] => assert_eq!(path, &vec![0, 1]),
+            other => panic!("unexpected outcome {:?}", other),
+        }
+    }
+}
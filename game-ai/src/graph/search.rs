@@ -0,0 +1,508 @@
+//! Graph searches that can be run to completion or a step at a time.
+//!
+//! Each search owns only its bookkeeping, and the graph is passed to every
+//! [`TimeSlicedSearch::cycle_once`] call. That lets searches be stored on an
+//! agent and advanced a few cycles per frame while the graph lives elsewhere.
+
+use super::path::PathEdge;
+use super::sparse_graph::{SparseGraph, INVALID_NODE_INDEX};
+use std::cmp::Ordering;
+use std::collections::{BinaryHeap, VecDeque};
+
+/// The outcome of a single search cycle.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum SearchStatus {
+    TargetFound,
+    TargetNotFound,
+    SearchIncomplete,
+}
+
+/// A search that examines one node per cycle.
+pub trait TimeSlicedSearch<T> {
+    /// Expands the next node of the search.
+    fn cycle_once(&mut self, graph: &SparseGraph<T>) -> SearchStatus;
+
+    /// The node indices from source to target, or nothing if no target has
+    /// been found (yet).
+    fn path_to_target(&self) -> Vec<usize>;
+
+    /// The total edge cost of the path to the target.
+    fn cost_to_target(&self) -> f64;
+
+    /// Cycles the search until it either finds the target or runs out of nodes.
+    fn search(&mut self, graph: &SparseGraph<T>) -> SearchStatus {
+        loop {
+            match self.cycle_once(graph) {
+                SearchStatus::SearchIncomplete => (),
+                status => return status,
+            }
+        }
+    }
+}
+
+/// Decides whether a node reached by [`GraphSearchDijkstra`] ends the search.
+pub trait TerminationCondition<T> {
+    fn is_satisfied(&self, graph: &SparseGraph<T>, node: usize) -> bool;
+}
+
+/// Terminates when a specific node is reached.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FindNodeIndex(pub usize);
+
+impl<T> TerminationCondition<T> for FindNodeIndex {
+    fn is_satisfied(&self, _graph: &SparseGraph<T>, node: usize) -> bool {
+        node == self.0
+    }
+}
+
+/// Terminates at the first node tagged with the given item, which makes
+/// [`GraphSearchDijkstra`] find the closest such node.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct FindExtraInfo<T>(pub T);
+
+impl<T: PartialEq> TerminationCondition<T> for FindExtraInfo<T> {
+    fn is_satisfied(&self, graph: &SparseGraph<T>, node: usize) -> bool {
+        graph
+            .node(node)
+            .and_then(|node| node.extra_info.as_ref())
+            .is_some_and(|item| *item == self.0)
+    }
+}
+
+/// Finds the closest node to `source`, by path cost, that is tagged with
+/// `item`. Returns the path to it, or `None` if no such node is reachable.
+pub fn find_closest_item<T: PartialEq>(
+    graph: &SparseGraph<T>,
+    source: usize,
+    item: T,
+) -> Option<Vec<usize>> {
+    let mut search = GraphSearchDijkstra::new(graph, source, FindExtraInfo(item));
+    match search.search(graph) {
+        SearchStatus::TargetFound => Some(search.path_to_target()),
+        _ => None,
+    }
+}
+
+/// Turns a list of node indices into the edges between their positions.
+pub fn path_edges<T>(graph: &SparseGraph<T>, nodes: &[usize]) -> Vec<PathEdge> {
+    nodes
+        .windows(2)
+        .filter_map(|pair| {
+            let from = graph.node(pair[0])?;
+            let to = graph.node(pair[1])?;
+            Some(PathEdge::new(from.position, to.position))
+        })
+        .collect()
+}
+
+fn route(parents: &[usize], source: usize, target: usize) -> Vec<usize> {
+    if target == INVALID_NODE_INDEX {
+        return vec![];
+    }
+
+    let mut nodes = vec![target];
+    let mut node = target;
+    while node != source {
+        node = parents[node];
+        nodes.push(node);
+    }
+    nodes.reverse();
+    nodes
+}
+
+fn is_allowed(allowed: &Option<Vec<bool>>, node: usize) -> bool {
+    allowed
+        .as_ref()
+        .is_none_or(|allowed| allowed.get(node).copied().unwrap_or(false))
+}
+
+/// A priority queue entry ordered so the lowest cost pops first.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub(super) struct Candidate {
+    pub(super) cost: f64,
+    pub(super) node: usize,
+}
+
+impl Eq for Candidate {}
+
+impl Ord for Candidate {
+    fn cmp(&self, other: &Self) -> Ordering {
+        other
+            .cost
+            .partial_cmp(&self.cost)
+            .unwrap_or(Ordering::Equal)
+            .then_with(|| other.node.cmp(&self.node))
+    }
+}
+
+impl PartialOrd for Candidate {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+/// Breadth first search: finds the path with the fewest edges, ignoring costs.
+#[derive(Clone, Debug)]
+pub struct GraphSearchBfs {
+    source: usize,
+    target: usize,
+    found: usize,
+    parents: Vec<usize>,
+    visited: Vec<bool>,
+    queue: VecDeque<usize>,
+}
+
+impl GraphSearchBfs {
+    pub fn new<T>(graph: &SparseGraph<T>, source: usize, target: usize) -> Self {
+        let mut search = GraphSearchBfs {
+            source,
+            target,
+            found: INVALID_NODE_INDEX,
+            parents: vec![INVALID_NODE_INDEX; graph.num_nodes()],
+            visited: vec![false; graph.num_nodes()],
+            queue: VecDeque::new(),
+        };
+
+        if graph.is_node_present(source) {
+            search.visited[source] = true;
+            search.queue.push_back(source);
+        }
+        search
+    }
+
+    pub fn path_to_target(&self) -> Vec<usize> {
+        route(&self.parents, self.source, self.found)
+    }
+
+    /// The number of edges on the path, as BFS treats every edge alike.
+    pub fn cost_to_target(&self) -> f64 {
+        self.path_to_target().len().saturating_sub(1) as f64
+    }
+}
+
+impl<T> TimeSlicedSearch<T> for GraphSearchBfs {
+    fn cycle_once(&mut self, graph: &SparseGraph<T>) -> SearchStatus {
+        let node = match self.queue.pop_front() {
+            Some(node) => node,
+            None => return SearchStatus::TargetNotFound,
+        };
+
+        if node == self.target {
+            self.found = node;
+            return SearchStatus::TargetFound;
+        }
+
+        for edge in graph.edges(node) {
+            if !self.visited[edge.to] {
+                self.visited[edge.to] = true;
+                self.parents[edge.to] = node;
+                self.queue.push_back(edge.to);
+            }
+        }
+
+        SearchStatus::SearchIncomplete
+    }
+
+    fn path_to_target(&self) -> Vec<usize> {
+        GraphSearchBfs::path_to_target(self)
+    }
+
+    fn cost_to_target(&self) -> f64 {
+        GraphSearchBfs::cost_to_target(self)
+    }
+}
+
+/// Dijkstra's algorithm: expands nodes in order of cost from the source until
+/// one satisfies the termination condition, so it also finds the closest of
+/// several possible targets.
+#[derive(Clone, Debug)]
+pub struct GraphSearchDijkstra<C> {
+    source: usize,
+    found: usize,
+    condition: C,
+    allowed: Option<Vec<bool>>,
+    costs: Vec<f64>,
+    parents: Vec<usize>,
+    settled: Vec<bool>,
+    frontier: BinaryHeap<Candidate>,
+}
+
+impl<C> GraphSearchDijkstra<C> {
+    pub fn new<T>(graph: &SparseGraph<T>, source: usize, condition: C) -> Self {
+        let mut search = GraphSearchDijkstra {
+            source,
+            found: INVALID_NODE_INDEX,
+            condition,
+            allowed: None,
+            costs: vec![f64::INFINITY; graph.num_nodes()],
+            parents: vec![INVALID_NODE_INDEX; graph.num_nodes()],
+            settled: vec![false; graph.num_nodes()],
+            frontier: BinaryHeap::new(),
+        };
+
+        if graph.is_node_present(source) {
+            search.costs[source] = 0.0;
+            search.frontier.push(Candidate {
+                cost: 0.0,
+                node: source,
+            });
+        }
+        search
+    }
+
+    /// Limits the search to the nodes marked `true`, indexed by node index.
+    pub fn restrict_to(mut self, allowed: Vec<bool>) -> Self {
+        self.allowed = Some(allowed);
+        self
+    }
+
+    pub fn path_to_target(&self) -> Vec<usize> {
+        route(&self.parents, self.source, self.found)
+    }
+
+    pub fn cost_to_target(&self) -> f64 {
+        self.costs.get(self.found).copied().unwrap_or(0.0)
+    }
+}
+
+impl<T, C: TerminationCondition<T>> TimeSlicedSearch<T> for GraphSearchDijkstra<C> {
+    fn cycle_once(&mut self, graph: &SparseGraph<T>) -> SearchStatus {
+        let node = loop {
+            match self.frontier.pop() {
+                Some(candidate) if self.settled[candidate.node] => continue,
+                Some(candidate) => break candidate.node,
+                None => return SearchStatus::TargetNotFound,
+            }
+        };
+
+        self.settled[node] = true;
+
+        if self.condition.is_satisfied(graph, node) {
+            self.found = node;
+            return SearchStatus::TargetFound;
+        }
+
+        for edge in graph.edges(node) {
+            if !is_allowed(&self.allowed, edge.to) {
+                continue;
+            }
+
+            let cost = self.costs[node] + edge.cost;
+            if !self.settled[edge.to] && cost < self.costs[edge.to] {
+                self.costs[edge.to] = cost;
+                self.parents[edge.to] = node;
+                self.frontier.push(Candidate {
+                    cost,
+                    node: edge.to,
+                });
+            }
+        }
+
+        SearchStatus::SearchIncomplete
+    }
+
+    fn path_to_target(&self) -> Vec<usize> {
+        GraphSearchDijkstra::path_to_target(self)
+    }
+
+    fn cost_to_target(&self) -> f64 {
+        GraphSearchDijkstra::cost_to_target(self)
+    }
+}
+
+/// A* search guided by the straight line distance to the target.
+#[derive(Clone, Debug)]
+pub struct GraphSearchAStar {
+    source: usize,
+    target: usize,
+    found: usize,
+    allowed: Option<Vec<bool>>,
+    g_costs: Vec<f64>,
+    parents: Vec<usize>,
+    settled: Vec<bool>,
+    frontier: BinaryHeap<Candidate>,
+}
+
+impl GraphSearchAStar {
+    pub fn new<T>(graph: &SparseGraph<T>, source: usize, target: usize) -> Self {
+        let mut search = GraphSearchAStar {
+            source,
+            target,
+            found: INVALID_NODE_INDEX,
+            allowed: None,
+            g_costs: vec![f64::INFINITY; graph.num_nodes()],
+            parents: vec![INVALID_NODE_INDEX; graph.num_nodes()],
+            settled: vec![false; graph.num_nodes()],
+            frontier: BinaryHeap::new(),
+        };
+
+        if graph.is_node_present(source) && graph.is_node_present(target) {
+            search.g_costs[source] = 0.0;
+            search.frontier.push(Candidate {
+                cost: 0.0,
+                node: source,
+            });
+        }
+        search
+    }
+
+    /// Limits the search to the nodes marked `true`, indexed by node index.
+    pub fn restrict_to(mut self, allowed: Vec<bool>) -> Self {
+        self.allowed = Some(allowed);
+        self
+    }
+
+    pub fn path_to_target(&self) -> Vec<usize> {
+        route(&self.parents, self.source, self.found)
+    }
+
+    pub fn cost_to_target(&self) -> f64 {
+        self.g_costs.get(self.found).copied().unwrap_or(0.0)
+    }
+
+    fn heuristic<T>(&self, graph: &SparseGraph<T>, node: usize) -> f64 {
+        match (graph.node(node), graph.node(self.target)) {
+            (Some(a), Some(b)) => a.position.distance(b.position),
+            _ => 0.0,
+        }
+    }
+}
+
+impl<T> TimeSlicedSearch<T> for GraphSearchAStar {
+    fn cycle_once(&mut self, graph: &SparseGraph<T>) -> SearchStatus {
+        let node = loop {
+            match self.frontier.pop() {
+                Some(candidate) if self.settled[candidate.node] => continue,
+                Some(candidate) => break candidate.node,
+                None => return SearchStatus::TargetNotFound,
+            }
+        };
+
+        self.settled[node] = true;
+
+        if node == self.target {
+            self.found = node;
+            return SearchStatus::TargetFound;
+        }
+
+        for edge in graph.edges(node) {
+            if !is_allowed(&self.allowed, edge.to) {
+                continue;
+            }
+
+            let g_cost = self.g_costs[node] + edge.cost;
+            if !self.settled[edge.to] && g_cost < self.g_costs[edge.to] {
+                self.g_costs[edge.to] = g_cost;
+                self.parents[edge.to] = node;
+                self.frontier.push(Candidate {
+                    cost: g_cost + self.heuristic(graph, edge.to),
+                    node: edge.to,
+                });
+            }
+        }
+
+        SearchStatus::SearchIncomplete
+    }
+
+    fn path_to_target(&self) -> Vec<usize> {
+        GraphSearchAStar::path_to_target(self)
+    }
+
+    fn cost_to_target(&self) -> f64 {
+        GraphSearchAStar::cost_to_target(self)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::graph::grid::{create_grid_graph, Grid};
+
+    fn maze() -> (Grid, SparseGraph) {
+        let grid = Grid::from_rows(&[".....", ".###.", ".#...", ".#.#.", "...#."], 1.0);
+        let graph = create_grid_graph(&grid);
+        (grid, graph)
+    }
+
+    #[test]
+    fn astar_and_dijkstra_agree_on_cost() {
+        let (grid, graph) = maze();
+        let source = grid.cell_index(0, 0);
+        let target = grid.cell_index(4, 4);
+
+        let mut astar = GraphSearchAStar::new(&graph, source, target);
+        assert_eq!(astar.search(&graph), SearchStatus::TargetFound);
+
+        let mut dijkstra = GraphSearchDijkstra::new(&graph, source, FindNodeIndex(target));
+        assert_eq!(dijkstra.search(&graph), SearchStatus::TargetFound);
+
+        assert!((astar.cost_to_target() - dijkstra.cost_to_target()).abs() < 1e-9);
+        let path = astar.path_to_target();
+        assert_eq!(path.first(), Some(&source));
+        assert_eq!(path.last(), Some(&target));
+        for pair in path.windows(2) {
+            assert!(graph.is_edge_present(pair[0], pair[1]));
+        }
+    }
+
+    #[test]
+    fn bfs_finds_fewest_edges() {
+        let (grid, graph) = maze();
+        let mut bfs = GraphSearchBfs::new(&graph, grid.cell_index(0, 0), grid.cell_index(0, 4));
+        assert_eq!(bfs.search(&graph), SearchStatus::TargetFound);
+        assert_eq!(bfs.cost_to_target(), 4.0);
+    }
+
+    #[test]
+    fn searches_can_be_time_sliced() {
+        let (grid, graph) = maze();
+        let mut astar = GraphSearchAStar::new(&graph, grid.cell_index(0, 0), grid.cell_index(4, 4));
+
+        let mut cycles = 1;
+        while astar.cycle_once(&graph) == SearchStatus::SearchIncomplete {
+            assert!(astar.path_to_target().is_empty());
+            cycles += 1;
+        }
+        assert!(cycles > 1);
+        assert!(!astar.path_to_target().is_empty());
+    }
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum ItemType {
+        Gold,
+        Water,
+    }
+
+    #[test]
+    fn dijkstra_finds_the_closest_item_by_path_cost() {
+        let grid = Grid::from_rows(&["....", "###.", "...."], 1.0);
+        let mut graph: SparseGraph<ItemType> = create_grid_graph(&grid);
+        // as the crow flies (0, 2) is nearest, but the wall makes (3, 0) closer
+        graph.node_mut(grid.cell_index(0, 2)).unwrap().extra_info = Some(ItemType::Water);
+        graph.node_mut(grid.cell_index(3, 0)).unwrap().extra_info = Some(ItemType::Water);
+        graph.node_mut(grid.cell_index(1, 2)).unwrap().extra_info = Some(ItemType::Gold);
+
+        let path = find_closest_item(&graph, grid.cell_index(0, 0), ItemType::Water).unwrap();
+        assert_eq!(path.last(), Some(&grid.cell_index(3, 0)));
+
+        let path = find_closest_item(&graph, grid.cell_index(0, 0), ItemType::Gold).unwrap();
+        assert_eq!(path.last(), Some(&grid.cell_index(1, 2)));
+    }
+
+    #[test]
+    fn missing_item_is_not_found() {
+        let grid = Grid::new(3, 3, 1.0);
+        let graph: SparseGraph<ItemType> = create_grid_graph(&grid);
+        assert!(find_closest_item(&graph, 0, ItemType::Gold).is_none());
+    }
+
+    #[test]
+    fn unreachable_target_is_not_found() {
+        let grid = Grid::from_rows(&[".#."], 1.0);
+        let graph: SparseGraph = create_grid_graph(&grid);
+
+        let mut astar = GraphSearchAStar::new(&graph, 0, 2);
+        assert_eq!(astar.search(&graph), SearchStatus::TargetNotFound);
+        assert!(astar.path_to_target().is_empty());
+    }
+}
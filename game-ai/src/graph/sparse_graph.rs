@@ -0,0 +1,216 @@
+use crate::vector::Vector2D;
+use serde::{Deserialize, Serialize};
+
+/// Index given to nodes that have been removed from a graph.
+pub const INVALID_NODE_INDEX: usize = usize::MAX;
+
+/// A graph node with a position in the world, as used for navigation.
+///
+/// Nodes can carry optional extra information of type `T`, e.g. the item
+/// found at that spot.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NavGraphNode<T = ()> {
+    pub index: usize,
+    pub position: Vector2D,
+    pub extra_info: Option<T>,
+}
+
+impl<T> NavGraphNode<T> {
+    pub fn new(index: usize, position: Vector2D) -> Self {
+        NavGraphNode {
+            index,
+            position,
+            extra_info: None,
+        }
+    }
+
+    pub fn is_active(&self) -> bool {
+        self.index != INVALID_NODE_INDEX
+    }
+}
+
+/// A directed, weighted connection between two nodes.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct GraphEdge {
+    pub from: usize,
+    pub to: usize,
+    pub cost: f64,
+}
+
+impl GraphEdge {
+    pub fn new(from: usize, to: usize, cost: f64) -> Self {
+        GraphEdge { from, to, cost }
+    }
+}
+
+/// A graph stored as a list of nodes and an adjacency list of edges per node.
+///
+/// Removing a node keeps its slot (marked with [`INVALID_NODE_INDEX`]) so the
+/// indices of the remaining nodes stay valid.
+#[derive(Clone, Debug)]
+pub struct SparseGraph<T = ()> {
+    nodes: Vec<NavGraphNode<T>>,
+    edges: Vec<Vec<GraphEdge>>,
+    digraph: bool,
+}
+
+impl<T> SparseGraph<T> {
+    /// Creates an empty graph. Edges added to an undirected graph are
+    /// automatically mirrored.
+    pub fn new(digraph: bool) -> Self {
+        SparseGraph {
+            nodes: vec![],
+            edges: vec![],
+            digraph,
+        }
+    }
+
+    pub fn is_digraph(&self) -> bool {
+        self.digraph
+    }
+
+    /// Adds a node at `position` and returns its index.
+    pub fn add_node(&mut self, position: Vector2D) -> usize {
+        let index = self.nodes.len();
+        self.nodes.push(NavGraphNode::new(index, position));
+        self.edges.push(vec![]);
+        index
+    }
+
+    /// Removes a node and every edge leading to or from it.
+    pub fn remove_node(&mut self, index: usize) {
+        if !self.is_node_present(index) {
+            return;
+        }
+
+        self.nodes[index].index = INVALID_NODE_INDEX;
+        let leaving = std::mem::take(&mut self.edges[index]);
+
+        if self.digraph {
+            for edges in self.edges.iter_mut() {
+                edges.retain(|edge| edge.to != index);
+            }
+        } else {
+            // every edge is mirrored, so only the neighbours can point back here
+            for edge in leaving {
+                self.edges[edge.to].retain(|edge| edge.to != index);
+            }
+        }
+    }
+
+    pub fn node(&self, index: usize) -> Option<&NavGraphNode<T>> {
+        self.nodes.get(index).filter(|node| node.is_active())
+    }
+
+    pub fn node_mut(&mut self, index: usize) -> Option<&mut NavGraphNode<T>> {
+        self.nodes.get_mut(index).filter(|node| node.is_active())
+    }
+
+    /// Iterates over the nodes that have not been removed.
+    pub fn nodes(&self) -> impl Iterator<Item = &NavGraphNode<T>> {
+        self.nodes.iter().filter(|node| node.is_active())
+    }
+
+    /// Iterates over the nodes tagged with `item`.
+    pub fn nodes_with<'a>(&'a self, item: &'a T) -> impl Iterator<Item = &'a NavGraphNode<T>>
+    where
+        T: PartialEq,
+    {
+        self.nodes()
+            .filter(move |node| node.extra_info.as_ref() == Some(item))
+    }
+
+    /// The index of the node nearest to `position`, if the graph has any.
+    pub fn closest_node(&self, position: Vector2D) -> Option<usize> {
+        self.nodes()
+            .map(|node| (node.index, node.position.distance_sq(position)))
+            .min_by(|a, b| a.1.partial_cmp(&b.1).unwrap_or(std::cmp::Ordering::Equal))
+            .map(|(index, _)| index)
+    }
+
+    pub fn is_node_present(&self, index: usize) -> bool {
+        self.node(index).is_some()
+    }
+
+    /// Adds an edge between two present nodes, ignoring duplicates.
+    pub fn add_edge(&mut self, edge: GraphEdge) {
+        if !self.is_node_present(edge.from) || !self.is_node_present(edge.to) {
+            return;
+        }
+
+        if !self.is_edge_present(edge.from, edge.to) {
+            self.edges[edge.from].push(edge);
+        }
+
+        if !self.digraph && !self.is_edge_present(edge.to, edge.from) {
+            self.edges[edge.to].push(GraphEdge::new(edge.to, edge.from, edge.cost));
+        }
+    }
+
+    pub fn remove_edge(&mut self, from: usize, to: usize) {
+        if let Some(edges) = self.edges.get_mut(from) {
+            edges.retain(|edge| edge.to != to);
+        }
+
+        if !self.digraph {
+            if let Some(edges) = self.edges.get_mut(to) {
+                edges.retain(|edge| edge.to != from);
+            }
+        }
+    }
+
+    pub fn edge(&self, from: usize, to: usize) -> Option<&GraphEdge> {
+        self.edges.get(from)?.iter().find(|edge| edge.to == to)
+    }
+
+    pub fn is_edge_present(&self, from: usize, to: usize) -> bool {
+        self.edge(from, to).is_some()
+    }
+
+    /// The edges leaving `from`.
+    pub fn edges(&self, from: usize) -> &[GraphEdge] {
+        self.edges.get(from).map(Vec::as_slice).unwrap_or(&[])
+    }
+
+    /// Number of node slots, including removed nodes. Node indices are always
+    /// below this value.
+    pub fn num_nodes(&self) -> usize {
+        self.nodes.len()
+    }
+
+    pub fn num_active_nodes(&self) -> usize {
+        self.nodes().count()
+    }
+
+    pub fn num_edges(&self) -> usize {
+        self.edges.iter().map(Vec::len).sum()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.nodes.is_empty()
+    }
+
+    pub fn clear(&mut self) {
+        self.nodes.clear();
+        self.edges.clear();
+    }
+
+    /// The same graph with each node's extra information swapped for what `f`
+    /// makes of it, e.g. to turn the names a map file tags its nodes with
+    /// into the game's own type.
+    pub fn map_extra_info<U>(self, mut f: impl FnMut(T) -> U) -> SparseGraph<U> {
+        SparseGraph {
+            nodes: self
+                .nodes
+                .into_iter()
+                .map(|node| NavGraphNode {
+                    index: node.index,
+                    position: node.position,
+                    extra_info: node.extra_info.map(&mut f),
+                })
+                .collect(),
+            edges: self.edges,
+            digraph: self.digraph,
+        }
+    }
+}
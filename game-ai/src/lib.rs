@@ -0,0 +1,22 @@
+//! Reusable building blocks from "Programming Game AI by Example",
+//! shared by the westworld examples.
+
+pub mod cell_space;
+pub mod config;
+pub mod data_fsm;
+pub mod entity;
+pub mod fuzzy;
+pub mod geometry;
+pub mod goals;
+pub mod graph;
+pub mod messaging;
+pub mod perception;
+pub mod regulator;
+pub mod smoother;
+pub mod steering;
+#[cfg(feature = "trace")]
+pub mod trace;
+pub mod triggers;
+pub mod vector;
+
+pub use vector::Vector2D;
@@ -0,0 +1,37 @@
+//! Messages passed between agents.
+
+/// A message from one entity to another, identified by their ids.
+///
+/// `M` is the game's own message type, usually an enum such as
+/// `HiHoneyImHome` / `StewReady`, which can carry any extra information the
+/// receiver needs.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Telegram<M> {
+    pub sender: usize,
+    pub receiver: usize,
+    pub message: M,
+    /// When the message is to be (or was) delivered, in seconds of game time.
+    pub dispatch_time: f64,
+}
+
+impl<M> Telegram<M> {
+    pub fn new(sender: usize, receiver: usize, message: M, dispatch_time: f64) -> Self {
+        Telegram {
+            sender,
+            receiver,
+            message,
+            dispatch_time,
+        }
+    }
+}
+
+/// The delivery time of messages that are handled as soon as they're sent.
+pub const SEND_MSG_IMMEDIATELY: f64 = 0.0;
+
+/// Anything with an id that messages can be sent to.
+pub trait MessageReceiver<M> {
+    fn id(&self) -> usize;
+
+    /// Returns true if the message was handled.
+    fn handle_message(&mut self, telegram: &Telegram<M>) -> bool;
+}
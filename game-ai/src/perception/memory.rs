@@ -0,0 +1,216 @@
+use crate::vector::Vector2D;
+use std::collections::HashMap;
+use std::hash::Hash;
+
+/// What an agent remembers about one other entity. Times are in seconds of
+/// game time.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct MemoryRecord {
+    /// When the entity was last seen or heard.
+    pub time_last_sensed: f64,
+    /// When the entity came into view, for telling how long it has been
+    /// visible.
+    pub time_became_visible: f64,
+    /// When the entity was last seen.
+    pub time_last_visible: f64,
+    /// Where the entity was when last seen or heard.
+    pub last_sensed_position: Vector2D,
+    /// Whether the entity is currently in the field of view.
+    pub within_fov: bool,
+    /// Whether there's a clear line of fire to the entity, even if it's not
+    /// in view.
+    pub shootable: bool,
+}
+
+impl MemoryRecord {
+    fn new(position: Vector2D) -> Self {
+        MemoryRecord {
+            time_last_sensed: f64::NEG_INFINITY,
+            time_became_visible: f64::NEG_INFINITY,
+            time_last_visible: f64::NEG_INFINITY,
+            last_sensed_position: position,
+            within_fov: false,
+            shootable: false,
+        }
+    }
+}
+
+/// An agent's short term memory of the entities it has sensed, keyed by
+/// entity id. Records are forgotten once their entity hasn't been sensed for
+/// longer than the memory span.
+#[derive(Clone, Debug)]
+pub struct MemoryMap<K = usize> {
+    memory_span: f64,
+    records: HashMap<K, MemoryRecord>,
+}
+
+impl<K: Copy + Eq + Hash> MemoryMap<K> {
+    /// A memory that keeps records for `memory_span` seconds after their
+    /// entity was last sensed.
+    pub fn new(memory_span: f64) -> Self {
+        MemoryMap {
+            memory_span,
+            records: HashMap::new(),
+        }
+    }
+
+    pub fn memory_span(&self) -> f64 {
+        self.memory_span
+    }
+
+    /// Records what the agent's eyes report about an entity this update:
+    /// whether it's in the field of view and whether there's a clear line of
+    /// sight to it. Only entities in view have their position updated.
+    pub fn update_vision(
+        &mut self,
+        id: K,
+        position: Vector2D,
+        within_fov: bool,
+        in_line_of_sight: bool,
+        time: f64,
+    ) {
+        let record = self
+            .records
+            .entry(id)
+            .or_insert_with(|| MemoryRecord::new(position));
+
+        record.shootable = in_line_of_sight;
+        if in_line_of_sight && within_fov {
+            record.time_last_sensed = time;
+            record.time_last_visible = time;
+            record.last_sensed_position = position;
+            if !record.within_fov {
+                record.within_fov = true;
+                record.time_became_visible = time;
+            }
+        } else {
+            record.within_fov = false;
+        }
+    }
+
+    /// Records hearing an entity make a noise at `position`.
+    pub fn update_with_sound(&mut self, id: K, position: Vector2D, time: f64) {
+        let record = self
+            .records
+            .entry(id)
+            .or_insert_with(|| MemoryRecord::new(position));
+        record.time_last_sensed = time;
+        record.last_sensed_position = position;
+    }
+
+    /// Forgets an entity straight away, e.g. when it dies.
+    pub fn forget(&mut self, id: &K) {
+        self.records.remove(id);
+    }
+
+    /// Forgets every entity that hasn't been sensed within the memory span.
+    pub fn expire(&mut self, time: f64) {
+        let span = self.memory_span;
+        self.records
+            .retain(|_, record| time - record.time_last_sensed <= span);
+    }
+
+    pub fn record(&self, id: &K) -> Option<&MemoryRecord> {
+        self.records.get(id)
+    }
+
+    pub fn is_within_fov(&self, id: &K) -> bool {
+        self.record(id).is_some_and(|record| record.within_fov)
+    }
+
+    pub fn is_shootable(&self, id: &K) -> bool {
+        self.record(id).is_some_and(|record| record.shootable)
+    }
+
+    pub fn last_sensed_position(&self, id: &K) -> Option<Vector2D> {
+        self.record(id).map(|record| record.last_sensed_position)
+    }
+
+    /// How long the entity has been in view, or zero if it isn't.
+    pub fn time_visible(&self, id: &K, time: f64) -> f64 {
+        match self.record(id) {
+            Some(record) if record.within_fov => time - record.time_became_visible,
+            _ => 0.0,
+        }
+    }
+
+    /// How long since the entity was last in view, or `None` if it has never
+    /// been seen.
+    pub fn time_out_of_view(&self, id: &K, time: f64) -> Option<f64> {
+        self.record(id)
+            .map(|record| time - record.time_last_visible)
+            .filter(|elapsed| elapsed.is_finite())
+    }
+
+    /// How long since the entity was last seen or heard.
+    pub fn time_since_last_sensed(&self, id: &K, time: f64) -> Option<f64> {
+        self.record(id)
+            .map(|record| time - record.time_last_sensed)
+            .filter(|elapsed| elapsed.is_finite())
+    }
+
+    /// The entities sensed within the memory span, in no particular order.
+    pub fn recently_sensed(&self, time: f64) -> Vec<K> {
+        self.records
+            .iter()
+            .filter(|(_, record)| time - record.time_last_sensed <= self.memory_span)
+            .map(|(&id, _)| id)
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn sightings_track_visibility() {
+        let mut memory = MemoryMap::new(5.0);
+        let here = Vector2D::new(1.0, 2.0);
+
+        memory.update_vision(7, here, true, true, 1.0);
+        memory.update_vision(7, here, true, true, 2.5);
+        assert!(memory.is_within_fov(&7));
+        assert_eq!(memory.time_visible(&7, 3.0), 2.0);
+
+        // behind us, but in the clear
+        let there = Vector2D::new(5.0, 5.0);
+        memory.update_vision(7, there, false, true, 4.0);
+        assert!(!memory.is_within_fov(&7));
+        assert!(memory.is_shootable(&7));
+        assert_eq!(memory.time_visible(&7, 4.0), 0.0);
+        assert_eq!(memory.time_out_of_view(&7, 4.0), Some(1.5));
+        assert_eq!(memory.last_sensed_position(&7), Some(here));
+    }
+
+    #[test]
+    fn sounds_are_remembered_without_sight() {
+        let mut memory = MemoryMap::new(5.0);
+        let bang = Vector2D::new(10.0, 0.0);
+        memory.update_with_sound(3, bang, 1.0);
+
+        assert_eq!(memory.last_sensed_position(&3), Some(bang));
+        assert_eq!(memory.time_since_last_sensed(&3, 2.0), Some(1.0));
+        assert_eq!(memory.time_out_of_view(&3, 2.0), None);
+        assert!(!memory.is_shootable(&3));
+    }
+
+    #[test]
+    fn records_expire_after_the_memory_span() {
+        let mut memory = MemoryMap::new(5.0);
+        memory.update_with_sound(1, Vector2D::zero(), 0.0);
+        memory.update_with_sound(2, Vector2D::zero(), 3.0);
+        // in view range of the eyes but blocked, so never actually sensed
+        memory.update_vision(3, Vector2D::zero(), true, false, 3.0);
+
+        assert_eq!(memory.recently_sensed(6.0), vec![2]);
+
+        memory.expire(6.0);
+        assert!(memory.record(&1).is_none());
+        assert!(memory.record(&2).is_some());
+        assert!(memory.record(&3).is_none());
+
+        memory.forget(&2);
+        assert!(memory.recently_sensed(6.0).is_empty());
+    }
+}
@@ -0,0 +1,12 @@
+//! What an agent knows about the others around it.
+//!
+//! Rather than reading other agents' positions straight from the world, an
+//! agent remembers what it has seen and heard in a [`MemoryMap`], so it can
+//! react to an opponent it glimpsed a moment ago, but not to one it has
+//! never sensed.
+
+mod memory;
+mod sight;
+
+pub use memory::{MemoryMap, MemoryRecord};
+pub use sight::{has_line_of_sight, is_within_fov, Viewpoint};
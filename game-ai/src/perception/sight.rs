@@ -0,0 +1,122 @@
+use super::memory::MemoryMap;
+use crate::geometry::{is_path_obstructed, Wall2D};
+use crate::vector::Vector2D;
+use std::hash::Hash;
+
+/// Where an agent is looking from and how wide its view is.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Viewpoint {
+    pub position: Vector2D,
+    /// Unit vector the agent is facing.
+    pub heading: Vector2D,
+    /// The full angle of the view cone, in radians.
+    pub fov: f64,
+}
+
+impl Viewpoint {
+    pub fn new(position: Vector2D, heading: Vector2D, fov: f64) -> Self {
+        Viewpoint {
+            position,
+            heading,
+            fov,
+        }
+    }
+
+    /// Whether `target` lies inside the view cone, ignoring walls.
+    pub fn is_within_fov(&self, target: Vector2D) -> bool {
+        is_within_fov(self.position, self.heading, self.fov, target)
+    }
+
+    /// Whether no wall stands between the viewpoint and `target`.
+    pub fn has_line_of_sight(&self, target: Vector2D, walls: &[Wall2D]) -> bool {
+        has_line_of_sight(self.position, target, walls)
+    }
+}
+
+/// Whether `target` is within `fov` radians (the full cone angle) of
+/// `heading`, as seen from `position`.
+pub fn is_within_fov(position: Vector2D, heading: Vector2D, fov: f64, target: Vector2D) -> bool {
+    let to_target = (target - position).normalize();
+    if to_target.is_zero() {
+        return true;
+    }
+    heading.dot(to_target) >= (fov / 2.0).cos()
+}
+
+/// Whether a ray from `from` to `to` crosses none of the walls.
+pub fn has_line_of_sight(from: Vector2D, to: Vector2D, walls: &[Wall2D]) -> bool {
+    !is_path_obstructed(from, to, walls)
+}
+
+impl<K: Copy + Eq + Hash> MemoryMap<K> {
+    /// Looks at every entity from `viewpoint` and records which are in view
+    /// and which are in the clear, as [`update_vision`](MemoryMap::update_vision).
+    pub fn update_vision_from<I>(
+        &mut self,
+        viewpoint: &Viewpoint,
+        entities: I,
+        walls: &[Wall2D],
+        time: f64,
+    ) where
+        I: IntoIterator<Item = (K, Vector2D)>,
+    {
+        for (id, position) in entities {
+            let in_line_of_sight = viewpoint.has_line_of_sight(position, walls);
+            let within_fov = viewpoint.is_within_fov(position);
+            self.update_vision(id, position, within_fov, in_line_of_sight, time);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use std::f64::consts::FRAC_PI_2;
+
+    fn v(x: f64, y: f64) -> Vector2D {
+        Vector2D::new(x, y)
+    }
+
+    #[test]
+    fn fov_cone() {
+        // looking along x with a 90 degree cone
+        let eyes = Viewpoint::new(v(0.0, 0.0), v(1.0, 0.0), FRAC_PI_2);
+        assert!(eyes.is_within_fov(v(10.0, 0.0)));
+        assert!(eyes.is_within_fov(v(10.0, 9.9)));
+        assert!(!eyes.is_within_fov(v(10.0, 10.1)));
+        assert!(!eyes.is_within_fov(v(-10.0, 0.0)));
+        assert!(eyes.is_within_fov(v(0.0, 0.0)));
+    }
+
+    #[test]
+    fn walls_block_line_of_sight() {
+        let walls = [
+            Wall2D::new(v(5.0, -1.0), v(5.0, 1.0)),
+            Wall2D::new(v(-5.0, 5.0), v(5.0, 5.0)),
+        ];
+        let eyes = Viewpoint::new(v(0.0, 0.0), v(1.0, 0.0), FRAC_PI_2);
+        assert!(!eyes.has_line_of_sight(v(10.0, 0.0), &walls));
+        assert!(eyes.has_line_of_sight(v(10.0, 3.0), &walls));
+        assert!(!eyes.has_line_of_sight(v(0.0, 10.0), &walls));
+        assert!(eyes.has_line_of_sight(v(-3.0, -3.0), &walls));
+    }
+
+    #[test]
+    fn memory_updated_from_a_viewpoint() {
+        let walls = [Wall2D::new(v(5.0, -1.0), v(5.0, 1.0))];
+        let eyes = Viewpoint::new(v(0.0, 0.0), v(1.0, 0.0), FRAC_PI_2);
+        let mut memory = MemoryMap::new(5.0);
+
+        let entities = vec![
+            (1, v(10.0, 3.0)),  // in view
+            (2, v(10.0, 0.0)),  // behind the wall
+            (3, v(-10.0, 0.0)), // behind us
+        ];
+        memory.update_vision_from(&eyes, entities, &walls, 1.0);
+
+        assert!(memory.is_within_fov(&1) && memory.is_shootable(&1));
+        assert!(!memory.is_within_fov(&2) && !memory.is_shootable(&2));
+        assert!(!memory.is_within_fov(&3) && memory.is_shootable(&3));
+        assert_eq!(memory.recently_sensed(1.0), vec![1]);
+    }
+}
@@ -0,0 +1,233 @@
+//! Regulators limit how often something runs, so expensive work such as
+//! path planning, perception or arbitration between goals can happen a few
+//! times a second while steering still runs every frame.
+//!
+//! A little random jitter is added to each period so that agents created on
+//! the same frame don't all do their expensive work on the same frames
+//! forever after.
+//!
+//! [`Regulator`] follows the wall clock; [`TickRegulator`] counts calls, for
+//! fixed-step and headless simulations that must replay identically.
+
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::time::{Duration, Instant};
+
+static NEXT_SEED: AtomicU64 = AtomicU64::new(0x2545_f491_4f6c_dd1d);
+
+/// A small xorshift generator; jitter needs to be spread out, not secure.
+#[derive(Clone, Debug)]
+struct Jitter(u64);
+
+impl Jitter {
+    fn new(seed: u64) -> Self {
+        Jitter(seed.max(1))
+    }
+
+    fn from_entropy() -> Self {
+        Jitter::new(NEXT_SEED.fetch_add(0x9e37_79b9_7f4a_7c15, Ordering::Relaxed))
+    }
+
+    /// A value in `[-1, 1)`.
+    fn next(&mut self) -> f64 {
+        self.0 ^= self.0 << 13;
+        self.0 ^= self.0 >> 7;
+        self.0 ^= self.0 << 17;
+        (self.0 >> 11) as f64 / (1u64 << 52) as f64 - 1.0
+    }
+}
+
+/// Ready a set number of times per second of real time.
+#[derive(Clone, Debug)]
+pub struct Regulator {
+    period: Option<Duration>,
+    jitter: Duration,
+    next_update: Instant,
+    rng: Jitter,
+}
+
+impl Regulator {
+    /// A regulator ready `updates_per_second` times a second. Zero means
+    /// never, and infinity means on every call. The first update comes at a
+    /// random point within the first period.
+    pub fn new(updates_per_second: f64) -> Self {
+        Regulator::starting_at(updates_per_second, Instant::now())
+    }
+
+    /// As [`new`](Regulator::new), counting from `now`.
+    pub fn starting_at(updates_per_second: f64, now: Instant) -> Self {
+        let period = if updates_per_second > 0.0 {
+            Some(Duration::from_secs_f64(1.0 / updates_per_second))
+        } else {
+            None
+        };
+        let mut rng = Jitter::from_entropy();
+        let first = period.map_or(Duration::ZERO, |period| {
+            period.mul_f64((rng.next() + 1.0) / 2.0)
+        });
+
+        Regulator {
+            period,
+            jitter: Duration::ZERO,
+            next_update: now + first,
+            rng,
+        }
+    }
+
+    /// Varies each period by up to `jitter` either way.
+    pub fn with_jitter(mut self, jitter: Duration) -> Self {
+        self.jitter = jitter;
+        self
+    }
+
+    pub fn is_ready(&mut self) -> bool {
+        self.is_ready_at(Instant::now())
+    }
+
+    /// Whether the regulator is ready at `now`, scheduling the next update
+    /// if it is.
+    pub fn is_ready_at(&mut self, now: Instant) -> bool {
+        let period = match self.period {
+            Some(period) => period,
+            None => return false,
+        };
+        if now < self.next_update {
+            return false;
+        }
+
+        let offset = self.jitter.mul_f64(self.rng.next().abs());
+        self.next_update = if self.rng.next() < 0.0 {
+            now + period.saturating_sub(offset)
+        } else {
+            now + period + offset
+        };
+        true
+    }
+}
+
+/// Ready once every so many ticks, for fixed-step simulations.
+#[derive(Clone, Debug)]
+pub struct TickRegulator {
+    period: u32,
+    jitter: u32,
+    countdown: u32,
+    rng: Jitter,
+}
+
+impl TickRegulator {
+    /// A regulator ready on every `period`th call, starting with the first.
+    /// A period of zero means never.
+    pub fn new(period: u32) -> Self {
+        TickRegulator {
+            period,
+            jitter: 0,
+            countdown: 0,
+            rng: Jitter::new(1),
+        }
+    }
+
+    /// Delays the first update by `offset` ticks, to stagger agents sharing
+    /// the same period.
+    pub fn with_offset(mut self, offset: u32) -> Self {
+        self.countdown = offset;
+        self
+    }
+
+    /// Varies each period by up to `jitter` ticks either way, using a
+    /// generator seeded with `seed` so runs can be repeated exactly.
+    pub fn with_jitter(mut self, jitter: u32, seed: u64) -> Self {
+        self.jitter = jitter;
+        self.rng = Jitter::new(seed);
+        self
+    }
+
+    /// Call once per tick.
+    pub fn is_ready(&mut self) -> bool {
+        if self.period == 0 {
+            return false;
+        }
+        if self.countdown > 0 {
+            self.countdown -= 1;
+            return false;
+        }
+
+        let jitter = (self.rng.next() * f64::from(self.jitter)).round() as i64;
+        let period = (i64::from(self.period) + jitter).max(1);
+        self.countdown = (period - 1) as u32;
+        true
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn ready_ticks(regulator: &mut TickRegulator, ticks: usize) -> Vec<usize> {
+        (0..ticks).filter(|_| regulator.is_ready()).collect()
+    }
+
+    #[test]
+    fn tick_regulator_period_and_offset() {
+        assert_eq!(
+            ready_ticks(&mut TickRegulator::new(3), 10),
+            vec![0, 3, 6, 9]
+        );
+        assert_eq!(
+            ready_ticks(&mut TickRegulator::new(3).with_offset(1), 10),
+            vec![1, 4, 7]
+        );
+        assert_eq!(ready_ticks(&mut TickRegulator::new(1), 3), vec![0, 1, 2]);
+        assert!(ready_ticks(&mut TickRegulator::new(0), 10).is_empty());
+    }
+
+    #[test]
+    fn tick_jitter_is_repeatable() {
+        let mut a = TickRegulator::new(5).with_jitter(2, 42);
+        let mut b = TickRegulator::new(5).with_jitter(2, 42);
+        let ticks = ready_ticks(&mut a, 200);
+        assert_eq!(ticks, ready_ticks(&mut b, 200));
+
+        for pair in ticks.windows(2) {
+            assert!((3..=7).contains(&(pair[1] - pair[0])), "{:?}", pair);
+        }
+        assert!(ticks.windows(2).any(|pair| pair[1] - pair[0] != 5));
+        // on average, still once every five ticks
+        assert!((36..=44).contains(&ticks.len()), "{}", ticks.len());
+    }
+
+    #[test]
+    fn regulator_follows_the_clock() {
+        let start = Instant::now();
+        let mut regulator = Regulator::starting_at(2.0, start);
+
+        let mut count = 0;
+        for ms in (0..10_000).step_by(10) {
+            if regulator.is_ready_at(start + Duration::from_millis(ms)) {
+                count += 1;
+            }
+        }
+        assert!((19..=21).contains(&count), "{}", count);
+
+        let mut never = Regulator::starting_at(0.0, start);
+        assert!(!never.is_ready_at(start + Duration::from_secs(100)));
+    }
+
+    #[test]
+    fn regulator_jitter_stays_in_bounds() {
+        let start = Instant::now();
+        let mut regulator =
+            Regulator::starting_at(10.0, start).with_jitter(Duration::from_millis(20));
+
+        let mut last = None;
+        for ms in 0..5_000 {
+            let now = start + Duration::from_millis(ms);
+            if regulator.is_ready_at(now) {
+                if let Some(last) = last {
+                    let gap = now - last;
+                    assert!(gap >= Duration::from_millis(80), "{:?}", gap);
+                    assert!(gap <= Duration::from_millis(121), "{:?}", gap);
+                }
+                last = Some(now);
+            }
+        }
+    }
+}
@@ -0,0 +1,120 @@
+//! Moving averages for values that jitter from update to update: frame
+//! times, noisy sensor readings, headings and the like.
+
+use crate::vector::Vector2D;
+use std::collections::VecDeque;
+use std::ops::{Add, Div};
+
+/// A value that can be averaged.
+pub trait Smoothable: Copy + Add<Output = Self> + Div<f64, Output = Self> {
+    fn zero() -> Self;
+}
+
+impl Smoothable for f64 {
+    fn zero() -> Self {
+        0.0
+    }
+}
+
+impl Smoothable for Vector2D {
+    fn zero() -> Self {
+        Vector2D::zero()
+    }
+}
+
+/// The mean of the last `window` values it's been given.
+///
+/// Until the window fills up, the mean is over the values seen so far, so the
+/// first few results aren't dragged towards zero.
+///
+/// Averaging headings gives a vector that's shorter than one whenever they
+/// disagree; normalize the result if you need a unit vector.
+#[derive(Clone, Debug)]
+pub struct Smoother<T> {
+    window: usize,
+    history: VecDeque<T>,
+}
+
+impl<T: Smoothable> Smoother<T> {
+    /// Panics if `window` is zero.
+    pub fn new(window: usize) -> Self {
+        assert!(
+            window > 0,
+            "a smoother needs a window of at least one value"
+        );
+        Smoother {
+            window,
+            history: VecDeque::with_capacity(window),
+        }
+    }
+
+    pub fn window(&self) -> usize {
+        self.window
+    }
+
+    /// Adds the latest value, dropping the oldest if the window is full, and
+    /// returns the new average.
+    pub fn update(&mut self, value: T) -> T {
+        if self.history.len() == self.window {
+            self.history.pop_front();
+        }
+        self.history.push_back(value);
+        self.average()
+    }
+
+    /// The average of the values in the window, or zero if there are none.
+    pub fn average(&self) -> T {
+        if self.history.is_empty() {
+            return T::zero();
+        }
+        let sum = self
+            .history
+            .iter()
+            .fold(T::zero(), |sum, &value| sum + value);
+        sum / self.history.len() as f64
+    }
+
+    /// Forgets every value, e.g. after a teleport or a long pause.
+    pub fn clear(&mut self) {
+        self.history.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn averages_over_the_window() {
+        let mut smoother = Smoother::new(3);
+        assert_eq!(smoother.average(), 0.0);
+        assert_eq!(smoother.update(3.0), 3.0);
+        assert_eq!(smoother.update(6.0), 4.5);
+        assert_eq!(smoother.update(9.0), 6.0);
+        // the 3 falls out of the window
+        assert_eq!(smoother.update(12.0), 9.0);
+
+        smoother.clear();
+        assert_eq!(smoother.update(1.0), 1.0);
+    }
+
+    #[test]
+    fn smooths_headings() {
+        let mut smoother = Smoother::new(4);
+        for &heading in &[
+            Vector2D::new(1.0, 0.0),
+            Vector2D::new(0.0, 1.0),
+            Vector2D::new(1.0, 0.0),
+            Vector2D::new(0.0, 1.0),
+        ] {
+            smoother.update(heading);
+        }
+        assert_eq!(smoother.average(), Vector2D::new(0.5, 0.5));
+    }
+
+    #[test]
+    #[should_panic]
+    fn empty_window() {
+        Smoother::<f64>::new(0);
+    }
+}
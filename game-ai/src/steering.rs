@@ -0,0 +1,229 @@
+//! Steering behaviours: the forces that move an agent towards where its
+//! goals want it to be.
+//!
+//! Goals don't move agents themselves. They switch a [`Vehicle`]'s
+//! [`Behavior`] on and off, and the agent's own update turns that behaviour
+//! into a force and integrates it.
+
+use crate::graph::PathGuide;
+use crate::vector::Vector2D;
+use serde::{Deserialize, Serialize};
+
+/// How quickly [`arrive`] slows down as it nears the target.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Deceleration {
+    Slow,
+    Normal,
+    Fast,
+}
+
+impl Deceleration {
+    fn factor(self) -> f64 {
+        match self {
+            Deceleration::Slow => 3.0,
+            Deceleration::Normal => 2.0,
+            Deceleration::Fast => 1.0,
+        }
+    }
+}
+
+// scales how far out arrive starts to slow down
+static DECELERATION_TWEAKER: f64 = 0.3;
+
+/// The force that turns `velocity` towards `target` at full speed.
+pub fn seek(position: Vector2D, velocity: Vector2D, max_speed: f64, target: Vector2D) -> Vector2D {
+    let desired = (target - position).normalize() * max_speed;
+    desired - velocity
+}
+
+/// Like [`seek`], but slows down to stop at the target instead of
+/// overshooting it.
+pub fn arrive(
+    position: Vector2D,
+    velocity: Vector2D,
+    max_speed: f64,
+    target: Vector2D,
+    deceleration: Deceleration,
+) -> Vector2D {
+    let to_target = target - position;
+    let distance = to_target.length();
+    if distance <= f64::EPSILON {
+        return -velocity;
+    }
+
+    let speed = (distance / (deceleration.factor() * DECELERATION_TWEAKER)).min(max_speed);
+    to_target * (speed / distance) - velocity
+}
+
+/// Seeks where an evader moving at `evader_velocity` will be by the time the
+/// pursuer could get there, or straight at it if it's dead ahead and coming
+/// this way.
+pub fn pursuit(
+    position: Vector2D,
+    velocity: Vector2D,
+    max_speed: f64,
+    evader_position: Vector2D,
+    evader_velocity: Vector2D,
+) -> Vector2D {
+    let to_evader = evader_position - position;
+    let heading = velocity.normalize();
+    let evader_heading = evader_velocity.normalize();
+    // cos(18 degrees): facing each other to within 18 degrees either way
+    if to_evader.dot(heading) > 0.0 && heading.dot(evader_heading) < -0.95 {
+        return seek(position, velocity, max_speed, evader_position);
+    }
+
+    let look_ahead = to_evader.length() / (max_speed + evader_velocity.length());
+    seek(
+        position,
+        velocity,
+        max_speed,
+        evader_position + evader_velocity * look_ahead,
+    )
+}
+
+/// Heads full speed wherever `guide` says, which may be a searched path or a
+/// shared [`FlowField`](crate::graph::flow_field::FlowField). Brakes once
+/// there's nowhere further to go.
+pub fn follow<G: PathGuide + ?Sized>(
+    position: Vector2D,
+    velocity: Vector2D,
+    max_speed: f64,
+    guide: &G,
+) -> Vector2D {
+    match guide.heading(position) {
+        Some(heading) => heading * max_speed - velocity,
+        None => -velocity,
+    }
+}
+
+/// A steering behaviour switched on by an agent's goals.
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Behavior {
+    Seek(Vector2D),
+    Arrive(Vector2D, Deceleration),
+    /// Chases an evader at the given position and velocity, which the
+    /// behaviour's owner keeps up to date.
+    Pursuit(Vector2D, Vector2D),
+}
+
+/// A point mass moved by steering forces.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Vehicle {
+    pub position: Vector2D,
+    pub velocity: Vector2D,
+    /// The direction of travel, kept when the vehicle stops.
+    pub heading: Vector2D,
+    pub mass: f64,
+    pub max_speed: f64,
+    pub max_force: f64,
+    behavior: Option<Behavior>,
+}
+
+impl Vehicle {
+    pub fn new(position: Vector2D, max_speed: f64, max_force: f64) -> Self {
+        Vehicle {
+            position,
+            velocity: Vector2D::zero(),
+            heading: Vector2D::new(1.0, 0.0),
+            mass: 1.0,
+            max_speed,
+            max_force,
+            behavior: None,
+        }
+    }
+
+    pub fn behavior(&self) -> Option<Behavior> {
+        self.behavior
+    }
+
+    pub fn set_behavior(&mut self, behavior: Option<Behavior>) {
+        self.behavior = behavior;
+    }
+
+    pub fn speed(&self) -> f64 {
+        self.velocity.length()
+    }
+
+    /// The force the current behaviour asks for, limited to `max_force`.
+    /// With no behaviour the vehicle brakes.
+    pub fn steering_force(&self) -> Vector2D {
+        let force = match self.behavior {
+            Some(Behavior::Seek(target)) => {
+                seek(self.position, self.velocity, self.max_speed, target)
+            }
+            Some(Behavior::Arrive(target, deceleration)) => arrive(
+                self.position,
+                self.velocity,
+                self.max_speed,
+                target,
+                deceleration,
+            ),
+            Some(Behavior::Pursuit(evader_position, evader_velocity)) => pursuit(
+                self.position,
+                self.velocity,
+                self.max_speed,
+                evader_position,
+                evader_velocity,
+            ),
+            None => -self.velocity,
+        };
+        force.truncate(self.max_force)
+    }
+
+    /// Moves the vehicle `dt` seconds along under its steering force.
+    pub fn update(&mut self, dt: f64) {
+        let acceleration = self.steering_force() / self.mass;
+        self.velocity = (self.velocity + acceleration * dt).truncate(self.max_speed);
+        self.position += self.velocity * dt;
+
+        if self.velocity.length_sq() > 1e-8 {
+            self.heading = self.velocity.normalize();
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn seek_accelerates_towards_the_target() {
+        let force = seek(
+            Vector2D::zero(),
+            Vector2D::zero(),
+            5.0,
+            Vector2D::new(0.0, 10.0),
+        );
+        assert_eq!(force, Vector2D::new(0.0, 5.0));
+    }
+
+    #[test]
+    fn arrive_stops_at_the_target() {
+        let target = Vector2D::new(30.0, 40.0);
+        let mut vehicle = Vehicle::new(Vector2D::zero(), 10.0, 20.0);
+        vehicle.set_behavior(Some(Behavior::Arrive(target, Deceleration::Normal)));
+
+        for _ in 0..400 {
+            vehicle.update(0.05);
+        }
+
+        assert!(vehicle.position.distance(target) < 0.1);
+        assert!(vehicle.speed() < 0.1);
+        assert!(vehicle.speed() <= vehicle.max_speed);
+    }
+
+    #[test]
+    fn pursuit_heads_off_the_evader() {
+        let force = pursuit(
+            Vector2D::zero(),
+            Vector2D::zero(),
+            10.0,
+            Vector2D::new(10.0, 0.0),
+            Vector2D::new(0.0, 10.0),
+        );
+        // aims ahead of the evader rather than at where it is now
+        assert!(force.y > 0.0);
+        assert!(force.x > 0.0);
+    }
+}
@@ -0,0 +1,126 @@
+//! Writing the agents' state spans out as a Chrome trace, for `--trace=FILE`
+//! in the westworld examples. Open it in Perfetto or `chrome://tracing`: each
+//! state's a bar named `agent: state` for as long as it was on the agent's
+//! stack, and the transitions and messages are marks along them.
+//!
+//! The examples open a span called [`STATE`] for each state, with `agent` and
+//! `state` fields, and it's those that are named this way; anything else keeps
+//! its own name.
+
+use std::path::Path;
+use tracing::field::{Field, Visit};
+use tracing::span::{Attributes, Id};
+use tracing::Subscriber;
+use tracing_chrome::{ChromeLayerBuilder, EventOrSpan, TraceStyle};
+use tracing_subscriber::layer::{Context, Layer};
+use tracing_subscriber::registry::LookupSpan;
+
+pub use tracing_chrome::FlushGuard;
+
+/// What each state's span is called.
+pub const STATE: &str = "state";
+
+/// A layer writing every span and event to the trace at `path`, and the guard
+/// that finishes the file once it's dropped, so keep it until the run's over.
+pub fn chrome_layer<S>(path: &Path) -> (impl Layer<S>, FlushGuard)
+where
+    S: Subscriber + for<'span> LookupSpan<'span> + Send + Sync,
+{
+    let (chrome, guard) = ChromeLayerBuilder::new()
+        .file(path)
+        // a bar from when the state's pushed until it's popped, rather than
+        // just the moments it's running
+        .trace_style(TraceStyle::Async)
+        .include_args(true)
+        .name_fn(Box::new(|data| match data {
+            EventOrSpan::Span(span) => match span.extensions().get::<Named>() {
+                Some(Named(name)) => name.clone(),
+                None => span.name().to_string(),
+            },
+            EventOrSpan::Event(event) => {
+                let mut fields = Fields::default();
+                event.record(&mut fields);
+                fields
+                    .message
+                    .unwrap_or_else(|| event.metadata().name().to_string())
+            }
+        }))
+        .build();
+    (NameStates.and_then(chrome), guard)
+}
+
+/// A state span's name in the trace, taken from its fields as it opens.
+struct Named(String);
+
+/// Names each state span for the agent and the state, before the trace is
+/// told it's opened.
+struct NameStates;
+
+impl<S> Layer<S> for NameStates
+where
+    S: Subscriber + for<'span> LookupSpan<'span>,
+{
+    fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+        if attrs.metadata().name() != STATE {
+            return;
+        }
+        let mut fields = Fields::default();
+        attrs.record(&mut fields);
+        if let (Some(span), Some(agent), Some(state)) = (ctx.span(id), fields.agent, fields.state) {
+            span.extensions_mut()
+                .insert(Named(format!("{}: {}", agent, state)));
+        }
+    }
+}
+
+/// The fields the trace's names are made from.
+#[derive(Default)]
+struct Fields {
+    agent: Option<String>,
+    state: Option<String>,
+    message: Option<String>,
+}
+
+impl Visit for Fields {
+    fn record_str(&mut self, field: &Field, value: &str) {
+        self.record(field, value.to_string());
+    }
+
+    fn record_debug(&mut self, field: &Field, value: &dyn std::fmt::Debug) {
+        self.record(field, format!("{:?}", value));
+    }
+}
+
+impl Fields {
+    fn record(&mut self, field: &Field, value: String) {
+        match field.name() {
+            "agent" => self.agent = Some(value),
+            "state" => self.state = Some(value),
+            "message" => self.message = Some(value),
+            _ => {}
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use tracing_subscriber::prelude::*;
+
+    #[test]
+    fn states_are_named_for_the_agent_and_the_state() {
+        let path = std::env::temp_dir().join(format!("game-ai-trace-{}.json", std::process::id()));
+        let (layer, guard) = chrome_layer(&path);
+        let subscriber = tracing_subscriber::registry().with(layer);
+        tracing::subscriber::with_default(subscriber, || {
+            let span = tracing::info_span!(STATE, agent = "Bob", state = "Dig");
+            span.in_scope(|| tracing::info!(transition = "pop", "transition"));
+        });
+        drop(guard);
+
+        let trace = std::fs::read_to_string(&path).unwrap();
+        std::fs::remove_file(&path).unwrap();
+        assert!(trace.contains(r#""name":"Bob: Dig""#), "{}", trace);
+        assert!(trace.contains(r#""name":"transition""#), "{}", trace);
+    }
+}
@@ -0,0 +1,54 @@
+//! Triggers: regions of the world that do something to whichever agents
+//! touch them, such as item pickups that respawn after a while, or the
+//! sound of a gunshot reaching everyone within earshot.
+//!
+//! Each trigger has a [`TriggerRegion`] and is owned by a [`TriggerSystem`],
+//! which every tick updates the triggers and offers each agent to each of
+//! them.
+
+mod region;
+mod respawning;
+mod sound;
+mod system;
+
+pub use region::TriggerRegion;
+pub use respawning::RespawningGiver;
+pub use sound::SoundNotify;
+pub use system::TriggerSystem;
+
+use crate::vector::Vector2D;
+
+/// An agent that can set off triggers.
+pub trait TriggerTarget {
+    fn position(&self) -> Vector2D;
+
+    fn bounding_radius(&self) -> f64;
+
+    /// Agents that are e.g. dead or spawning can be left out of trigger tests.
+    fn is_ready_for_triggers(&self) -> bool {
+        true
+    }
+}
+
+pub trait Trigger<E: TriggerTarget> {
+    fn region(&self) -> &TriggerRegion;
+
+    /// Inactive triggers can't be set off, but stay in the system.
+    fn is_active(&self) -> bool;
+
+    /// Triggers that return true are dropped by the system on its next update.
+    fn is_to_be_removed(&self) -> bool {
+        false
+    }
+
+    /// Sets the trigger off if it's active and `entity` is touching it.
+    fn try_trigger(&mut self, entity: &mut E);
+
+    /// Called once per tick, before any agents are tried.
+    fn update(&mut self);
+
+    fn is_touching(&self, entity: &E) -> bool {
+        self.region()
+            .is_touching(entity.position(), entity.bounding_radius())
+    }
+}
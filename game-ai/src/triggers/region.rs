@@ -0,0 +1,95 @@
+use crate::vector::Vector2D;
+
+/// The area covered by a trigger.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum TriggerRegion {
+    Circle {
+        center: Vector2D,
+        radius: f64,
+    },
+    /// An axis aligned rectangle.
+    Rectangle {
+        top_left: Vector2D,
+        bottom_right: Vector2D,
+    },
+}
+
+impl TriggerRegion {
+    pub fn circle(center: Vector2D, radius: f64) -> Self {
+        TriggerRegion::Circle { center, radius }
+    }
+
+    /// A rectangle spanning the two corners, whichever way round they are.
+    pub fn rectangle(a: Vector2D, b: Vector2D) -> Self {
+        TriggerRegion::Rectangle {
+            top_left: Vector2D::new(a.x.min(b.x), a.y.min(b.y)),
+            bottom_right: Vector2D::new(a.x.max(b.x), a.y.max(b.y)),
+        }
+    }
+
+    /// Whether a circle at `position` with `radius` overlaps the region.
+    pub fn is_touching(&self, position: Vector2D, radius: f64) -> bool {
+        match *self {
+            TriggerRegion::Circle {
+                center,
+                radius: region_radius,
+            } => position.distance_sq(center) < (radius + region_radius).powi(2),
+            TriggerRegion::Rectangle {
+                top_left,
+                bottom_right,
+            } => {
+                let closest = Vector2D::new(
+                    position.x.max(top_left.x).min(bottom_right.x),
+                    position.y.max(top_left.y).min(bottom_right.y),
+                );
+                position.distance_sq(closest) < radius * radius
+                    || (radius == 0.0 && closest == position)
+            }
+        }
+    }
+
+    /// The smallest axis aligned box containing the region, as its top left
+    /// and bottom right corners.
+    pub fn bounds(&self) -> (Vector2D, Vector2D) {
+        match *self {
+            TriggerRegion::Circle { center, radius } => (
+                center - Vector2D::new(radius, radius),
+                center + Vector2D::new(radius, radius),
+            ),
+            TriggerRegion::Rectangle {
+                top_left,
+                bottom_right,
+            } => (top_left, bottom_right),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn circle() {
+        let region = TriggerRegion::circle(Vector2D::new(10.0, 10.0), 2.0);
+        assert!(region.is_touching(Vector2D::new(10.0, 10.0), 0.0));
+        assert!(region.is_touching(Vector2D::new(13.0, 10.0), 1.5));
+        assert!(!region.is_touching(Vector2D::new(13.0, 10.0), 1.0));
+        assert!(!region.is_touching(Vector2D::new(20.0, 20.0), 5.0));
+    }
+
+    #[test]
+    fn rectangle() {
+        let region = TriggerRegion::rectangle(Vector2D::new(4.0, 2.0), Vector2D::new(0.0, 0.0));
+        assert_eq!(
+            region.bounds(),
+            (Vector2D::new(0.0, 0.0), Vector2D::new(4.0, 2.0))
+        );
+
+        assert!(region.is_touching(Vector2D::new(1.0, 1.0), 0.0));
+        assert!(region.is_touching(Vector2D::new(5.0, 1.0), 1.5));
+        assert!(!region.is_touching(Vector2D::new(5.0, 1.0), 0.5));
+        // past the corner, the distance is to the corner itself
+        assert!(!region.is_touching(Vector2D::new(5.0, 3.0), 1.2));
+        assert!(region.is_touching(Vector2D::new(5.0, 3.0), 1.5));
+    }
+}
@@ -0,0 +1,58 @@
+use super::{Trigger, TriggerRegion, TriggerTarget};
+
+/// Gives something to the first agent to touch it, then disappears for a
+/// number of ticks before respawning, like a gold nugget lying in the mine.
+pub struct RespawningGiver<E> {
+    region: TriggerRegion,
+    respawn_delay: u32,
+    ticks_until_respawn: u32,
+    give: Box<dyn FnMut(&mut E) + Send + Sync>,
+}
+
+impl<E> RespawningGiver<E> {
+    /// A giver calling `give` on whoever sets it off, then staying inactive
+    /// for `respawn_delay` ticks.
+    pub fn new<F>(region: TriggerRegion, respawn_delay: u32, give: F) -> Self
+    where
+        F: FnMut(&mut E) + Send + Sync + 'static,
+    {
+        RespawningGiver {
+            region,
+            respawn_delay,
+            ticks_until_respawn: 0,
+            give: Box::new(give),
+        }
+    }
+
+    pub fn respawn_delay(&self) -> u32 {
+        self.respawn_delay
+    }
+
+    /// Ticks left before the giver is active again; zero while it's active.
+    pub fn ticks_until_respawn(&self) -> u32 {
+        self.ticks_until_respawn
+    }
+}
+
+impl<E: TriggerTarget> Trigger<E> for RespawningGiver<E> {
+    fn region(&self) -> &TriggerRegion {
+        &self.region
+    }
+
+    fn is_active(&self) -> bool {
+        self.ticks_until_respawn == 0
+    }
+
+    fn try_trigger(&mut self, entity: &mut E) {
+        if self.is_active() && self.is_touching(entity) {
+            (self.give)(entity);
+            // the extra tick is spent by this tick's update, which has
+            // already happened
+            self.ticks_until_respawn = self.respawn_delay + 1;
+        }
+    }
+
+    fn update(&mut self) {
+        self.ticks_until_respawn = self.ticks_until_respawn.saturating_sub(1);
+    }
+}
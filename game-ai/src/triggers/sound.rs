@@ -0,0 +1,174 @@
+use super::{Trigger, TriggerRegion, TriggerTarget};
+use crate::messaging::{MessageReceiver, Telegram, SEND_MSG_IMMEDIATELY};
+use crate::vector::Vector2D;
+
+/// A noise such as a gunshot or a shout, heard by every agent within range
+/// for a few ticks after it's made.
+///
+/// Each agent that comes within range while the sound lasts is sent one
+/// telegram from the noise's source carrying `message`. The source doesn't
+/// hear itself.
+pub struct SoundNotify<M> {
+    region: TriggerRegion,
+    source: usize,
+    message: M,
+    lifetime: u32,
+    age: u32,
+    heard: Vec<usize>,
+}
+
+impl<M> SoundNotify<M> {
+    /// A sound made by the entity with id `source` at `position`, audible
+    /// within `range` for `lifetime` ticks.
+    pub fn new(source: usize, position: Vector2D, range: f64, message: M, lifetime: u32) -> Self {
+        SoundNotify {
+            region: TriggerRegion::circle(position, range),
+            source,
+            message,
+            lifetime,
+            age: 0,
+            heard: vec![],
+        }
+    }
+
+    pub fn source(&self) -> usize {
+        self.source
+    }
+
+    /// Whether the sound can still be heard.
+    pub fn is_audible(&self) -> bool {
+        self.age <= self.lifetime
+    }
+
+    /// The ids of the agents that have heard the sound so far.
+    pub fn heard_by(&self) -> &[usize] {
+        &self.heard
+    }
+}
+
+impl<M, E> Trigger<E> for SoundNotify<M>
+where
+    M: Clone,
+    E: TriggerTarget + MessageReceiver<M>,
+{
+    fn region(&self) -> &TriggerRegion {
+        &self.region
+    }
+
+    fn is_active(&self) -> bool {
+        self.is_audible()
+    }
+
+    fn is_to_be_removed(&self) -> bool {
+        !self.is_audible()
+    }
+
+    fn try_trigger(&mut self, entity: &mut E) {
+        let id = entity.id();
+        if !self.is_audible()
+            || id == self.source
+            || self.heard.contains(&id)
+            || !self.is_touching(entity)
+        {
+            return;
+        }
+
+        let telegram = Telegram::new(self.source, id, self.message.clone(), SEND_MSG_IMMEDIATELY);
+        entity.handle_message(&telegram);
+        self.heard.push(id);
+    }
+
+    fn update(&mut self) {
+        self.age += 1;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::triggers::TriggerSystem;
+
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    enum Noise {
+        Gunshot,
+    }
+
+    struct Deputy {
+        id: usize,
+        position: Vector2D,
+        heard: Vec<(usize, Noise)>,
+    }
+
+    fn deputy(id: usize, x: f64) -> Deputy {
+        Deputy {
+            id,
+            position: Vector2D::new(x, 0.0),
+            heard: vec![],
+        }
+    }
+
+    impl TriggerTarget for Deputy {
+        fn position(&self) -> Vector2D {
+            self.position
+        }
+
+        fn bounding_radius(&self) -> f64 {
+            1.0
+        }
+    }
+
+    impl MessageReceiver<Noise> for Deputy {
+        fn id(&self) -> usize {
+            self.id
+        }
+
+        fn handle_message(&mut self, telegram: &Telegram<Noise>) -> bool {
+            self.heard.push((telegram.sender, telegram.message));
+            true
+        }
+    }
+
+    #[test]
+    fn agents_in_range_hear_the_source_once() {
+        let mut system = TriggerSystem::new();
+        let mut deputies = vec![deputy(0, 0.0), deputy(1, 5.0), deputy(2, 50.0)];
+        system.register(SoundNotify::new(
+            0,
+            Vector2D::zero(),
+            10.0,
+            Noise::Gunshot,
+            3,
+        ));
+
+        system.update(&mut deputies);
+        system.update(&mut deputies);
+        assert!(deputies[0].heard.is_empty());
+        assert_eq!(deputies[1].heard, vec![(0, Noise::Gunshot)]);
+        assert!(deputies[2].heard.is_empty());
+    }
+
+    #[test]
+    fn sound_fades_after_its_lifetime() {
+        let mut system = TriggerSystem::new();
+        let mut deputies = vec![deputy(1, 50.0), deputy(2, 50.0)];
+        system.register(SoundNotify::new(
+            0,
+            Vector2D::zero(),
+            10.0,
+            Noise::Gunshot,
+            2,
+        ));
+
+        system.update(&mut deputies);
+        deputies[0].position = Vector2D::new(5.0, 0.0);
+        system.update(&mut deputies);
+        assert_eq!(deputies[0].heard.len(), 1);
+
+        // too late to hear it
+        deputies[1].position = Vector2D::new(5.0, 0.0);
+        system.update(&mut deputies);
+        assert!(deputies[1].heard.is_empty());
+        system.update(&mut deputies);
+        assert!(system.is_empty());
+    }
+}
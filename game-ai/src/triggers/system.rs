@@ -0,0 +1,270 @@
+use super::{Trigger, TriggerTarget};
+use crate::cell_space::CellSpacePartition;
+
+/// Owns the triggers in the world and tests agents against them each tick.
+///
+/// By default every agent is tried against every trigger. With a
+/// [`CellSpacePartition`], trigger regions are registered in the cells they
+/// cover and each agent is only tried against the triggers in the cells
+/// around it, which pays off once there are more than a handful of either.
+pub struct TriggerSystem<E> {
+    triggers: Vec<Box<dyn Trigger<E> + Send + Sync>>,
+    // indices into `triggers`
+    partition: Option<CellSpacePartition<usize>>,
+}
+
+impl<E: TriggerTarget> TriggerSystem<E> {
+    pub fn new() -> Self {
+        TriggerSystem {
+            triggers: vec![],
+            partition: None,
+        }
+    }
+
+    /// A system looking triggers up in `partition`, which should cover the
+    /// world the agents move in.
+    pub fn with_partition(mut partition: CellSpacePartition<usize>) -> Self {
+        partition.clear();
+        TriggerSystem {
+            triggers: vec![],
+            partition: Some(partition),
+        }
+    }
+
+    pub fn register<T>(&mut self, trigger: T)
+    where
+        T: Trigger<E> + Send + Sync + 'static,
+    {
+        if let Some(partition) = &mut self.partition {
+            let (top_left, bottom_right) = trigger.region().bounds();
+            partition.add_area(self.triggers.len(), top_left, bottom_right);
+        }
+        self.triggers.push(Box::new(trigger));
+    }
+
+    pub fn clear(&mut self) {
+        self.triggers.clear();
+        if let Some(partition) = &mut self.partition {
+            partition.clear();
+        }
+    }
+
+    pub fn len(&self) -> usize {
+        self.triggers.len()
+    }
+
+    pub fn is_empty(&self) -> bool {
+        self.triggers.is_empty()
+    }
+
+    pub fn triggers(&self) -> impl Iterator<Item = &(dyn Trigger<E> + Send + Sync)> {
+        self.triggers.iter().map(|trigger| trigger.as_ref())
+    }
+
+    /// Drops finished triggers, updates the rest, then tries every ready
+    /// agent against every trigger near it.
+    pub fn update(&mut self, entities: &mut [E]) {
+        let count = self.triggers.len();
+        self.triggers.retain(|trigger| !trigger.is_to_be_removed());
+        if self.triggers.len() != count {
+            self.repartition();
+        }
+
+        for trigger in &mut self.triggers {
+            trigger.update();
+        }
+
+        for entity in entities.iter_mut() {
+            if !entity.is_ready_for_triggers() {
+                continue;
+            }
+
+            match &self.partition {
+                Some(partition) => {
+                    let nearby =
+                        partition.query_radius(entity.position(), entity.bounding_radius());
+                    for index in nearby {
+                        self.triggers[index].try_trigger(entity);
+                    }
+                }
+                None => {
+                    for trigger in &mut self.triggers {
+                        trigger.try_trigger(entity);
+                    }
+                }
+            }
+        }
+    }
+
+    // removing triggers shifts the indices of those after them
+    fn repartition(&mut self) {
+        if let Some(partition) = &mut self.partition {
+            partition.clear();
+            for (index, trigger) in self.triggers.iter().enumerate() {
+                let (top_left, bottom_right) = trigger.region().bounds();
+                partition.add_area(index, top_left, bottom_right);
+            }
+        }
+    }
+}
+
+impl<E: TriggerTarget> Default for TriggerSystem<E> {
+    fn default() -> Self {
+        TriggerSystem::new()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::cell_space::CellSpacePartition;
+    use crate::triggers::{RespawningGiver, TriggerRegion};
+    use crate::vector::Vector2D;
+
+    struct Miner {
+        position: Vector2D,
+        gold: u32,
+        asleep: bool,
+    }
+
+    impl Miner {
+        fn at(x: f64) -> Self {
+            Miner {
+                position: Vector2D::new(x, 0.0),
+                gold: 0,
+                asleep: false,
+            }
+        }
+    }
+
+    impl TriggerTarget for Miner {
+        fn position(&self) -> Vector2D {
+            self.position
+        }
+
+        fn bounding_radius(&self) -> f64 {
+            0.5
+        }
+
+        fn is_ready_for_triggers(&self) -> bool {
+            !self.asleep
+        }
+    }
+
+    fn nugget(x: f64, respawn_delay: u32) -> RespawningGiver<Miner> {
+        RespawningGiver::new(
+            TriggerRegion::circle(Vector2D::new(x, 0.0), 1.0),
+            respawn_delay,
+            |miner: &mut Miner| miner.gold += 1,
+        )
+    }
+
+    #[test]
+    fn nugget_respawns_after_its_delay() {
+        let mut system = TriggerSystem::new();
+        system.register(nugget(0.0, 3));
+        let mut miners = vec![Miner::at(0.0), Miner::at(0.5), Miner::at(10.0)];
+
+        // only the first miner to touch it gets the nugget
+        system.update(&mut miners);
+        assert_eq!(
+            miners.iter().map(|m| m.gold).collect::<Vec<_>>(),
+            vec![1, 0, 0]
+        );
+
+        for _ in 0..3 {
+            assert!(!system.triggers().next().unwrap().is_active());
+            system.update(&mut miners);
+        }
+        assert_eq!(miners[0].gold + miners[1].gold, 1);
+
+        system.update(&mut miners);
+        assert_eq!(miners[0].gold + miners[1].gold, 2);
+    }
+
+    #[test]
+    fn agents_not_ready_are_skipped() {
+        let mut system = TriggerSystem::new();
+        system.register(nugget(0.0, 0));
+        let mut miners = vec![Miner::at(0.0)];
+        miners[0].asleep = true;
+
+        system.update(&mut miners);
+        assert_eq!(miners[0].gold, 0);
+
+        miners[0].asleep = false;
+        system.update(&mut miners);
+        system.update(&mut miners);
+        assert_eq!(miners[0].gold, 2);
+    }
+
+    struct OneShot {
+        region: TriggerRegion,
+        fired: bool,
+    }
+
+    impl Trigger<Miner> for OneShot {
+        fn region(&self) -> &TriggerRegion {
+            &self.region
+        }
+
+        fn is_active(&self) -> bool {
+            !self.fired
+        }
+
+        fn is_to_be_removed(&self) -> bool {
+            self.fired
+        }
+
+        fn try_trigger(&mut self, miner: &mut Miner) {
+            if self.is_active() && self.is_touching(miner) {
+                miner.gold += 10;
+                self.fired = true;
+            }
+        }
+
+        fn update(&mut self) {}
+    }
+
+    #[test]
+    fn partitioned_system_only_tries_nearby_triggers() {
+        let partition = CellSpacePartition::new(100.0, 10.0, 10, 1);
+        let mut system = TriggerSystem::with_partition(partition);
+        for x in 0..10 {
+            system.register(nugget(f64::from(x) * 10.0 + 5.0, 100));
+        }
+        let mut miners = vec![Miner::at(25.0), Miner::at(94.0), Miner::at(50.0)];
+
+        system.update(&mut miners);
+        assert_eq!(
+            miners.iter().map(|m| m.gold).collect::<Vec<_>>(),
+            vec![1, 1, 0]
+        );
+        let active = system.triggers().filter(|t| t.is_active()).count();
+        assert_eq!(active, 8);
+    }
+
+    #[test]
+    fn finished_triggers_are_removed() {
+        let partition = CellSpacePartition::new(200.0, 200.0, 4, 4);
+        let mut system = TriggerSystem::with_partition(partition);
+        system.register(OneShot {
+            region: TriggerRegion::circle(Vector2D::zero(), 1.0),
+            fired: false,
+        });
+        system.register(nugget(100.0, 5));
+        let mut miners = vec![Miner::at(0.0)];
+
+        system.update(&mut miners);
+        assert_eq!(miners[0].gold, 10);
+        assert_eq!(system.len(), 2);
+
+        system.update(&mut miners);
+        assert_eq!(system.len(), 1);
+
+        // the nugget that's left has moved down to index 0
+        miners[0].position = Vector2D::new(100.0, 0.0);
+        system.update(&mut miners);
+        assert_eq!(miners[0].gold, 11);
+    }
+}
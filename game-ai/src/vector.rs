@@ -0,0 +1,121 @@
+use serde::{Deserialize, Serialize};
+use std::ops::{Add, AddAssign, Div, Mul, Neg, Sub, SubAssign};
+
+/// A 2D vector used for positions, headings and velocities.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub struct Vector2D {
+    pub x: f64,
+    pub y: f64,
+}
+
+impl Vector2D {
+    pub fn new(x: f64, y: f64) -> Self {
+        Vector2D { x, y }
+    }
+
+    pub fn zero() -> Self {
+        Vector2D { x: 0.0, y: 0.0 }
+    }
+
+    pub fn is_zero(&self) -> bool {
+        self.x == 0.0 && self.y == 0.0
+    }
+
+    pub fn length(&self) -> f64 {
+        self.length_sq().sqrt()
+    }
+
+    pub fn length_sq(&self) -> f64 {
+        self.x * self.x + self.y * self.y
+    }
+
+    pub fn dot(&self, other: Vector2D) -> f64 {
+        self.x * other.x + self.y * other.y
+    }
+
+    /// The vector perpendicular to this one.
+    pub fn perp(&self) -> Vector2D {
+        Vector2D::new(-self.y, self.x)
+    }
+
+    /// Returns a unit length copy of this vector, or zero if it has no length.
+    pub fn normalize(&self) -> Vector2D {
+        let length = self.length();
+        if length > f64::EPSILON {
+            *self / length
+        } else {
+            Vector2D::zero()
+        }
+    }
+
+    /// Returns a copy of this vector no longer than `max`.
+    pub fn truncate(&self, max: f64) -> Vector2D {
+        if self.length_sq() > max * max {
+            self.normalize() * max
+        } else {
+            *self
+        }
+    }
+
+    pub fn distance(&self, other: Vector2D) -> f64 {
+        (*self - other).length()
+    }
+
+    pub fn distance_sq(&self, other: Vector2D) -> f64 {
+        (*self - other).length_sq()
+    }
+}
+
+impl Add for Vector2D {
+    type Output = Vector2D;
+
+    fn add(self, rhs: Vector2D) -> Vector2D {
+        Vector2D::new(self.x + rhs.x, self.y + rhs.y)
+    }
+}
+
+impl AddAssign for Vector2D {
+    fn add_assign(&mut self, rhs: Vector2D) {
+        self.x += rhs.x;
+        self.y += rhs.y;
+    }
+}
+
+impl Sub for Vector2D {
+    type Output = Vector2D;
+
+    fn sub(self, rhs: Vector2D) -> Vector2D {
+        Vector2D::new(self.x - rhs.x, self.y - rhs.y)
+    }
+}
+
+impl SubAssign for Vector2D {
+    fn sub_assign(&mut self, rhs: Vector2D) {
+        self.x -= rhs.x;
+        self.y -= rhs.y;
+    }
+}
+
+impl Mul<f64> for Vector2D {
+    type Output = Vector2D;
+
+    fn mul(self, rhs: f64) -> Vector2D {
+        Vector2D::new(self.x * rhs, self.y * rhs)
+    }
+}
+
+impl Div<f64> for Vector2D {
+    type Output = Vector2D;
+
+    fn div(self, rhs: f64) -> Vector2D {
+        Vector2D::new(self.x / rhs, self.y / rhs)
+    }
+}
+
+impl Neg for Vector2D {
+    type Output = Vector2D;
+
+    fn neg(self) -> Vector2D {
+        Vector2D::new(-self.x, -self.y)
+    }
+}
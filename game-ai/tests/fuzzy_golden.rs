@@ -0,0 +1,146 @@
+//! Golden tests for fuzzy inference: each module's response curve is
+//! compared against a CSV in `tests/golden`, so changes to set shapes or
+//! defuzzification can't alter behaviour unnoticed.
+//!
+//! After an intended change, regenerate the curves with
+//! `UPDATE_GOLDEN=1 cargo test --test fuzzy_golden` and review the diff.
+//! Set `FUZZY_PLOT_DIR` to also write each curve as gnuplot data.
+
+use game_ai::fuzzy::{fairly, very, Curve, DefuzzifyMethod, FuzzyModule};
+use game_ai::fuzzy_rules;
+use std::env;
+use std::path::PathBuf;
+
+const TOLERANCE: f64 = 1e-9;
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.csv", name))
+}
+
+fn assert_golden(name: &str, curve: &Curve) {
+    if let Ok(dir) = env::var("FUZZY_PLOT_DIR") {
+        let path = PathBuf::from(dir).join(format!("{}.dat", name));
+        std::fs::write(&path, curve.to_gnuplot()).unwrap();
+    }
+
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        curve.save_csv(&path).unwrap();
+        return;
+    }
+
+    let golden = Curve::load_csv(&path)
+        .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1)", path.display(), e));
+    assert_eq!(
+        (&curve.input, &curve.output),
+        (&golden.input, &golden.output)
+    );
+    match curve.max_deviation(&golden) {
+        Some((_, deviation)) if deviation <= TOLERANCE => (),
+        Some((x, deviation)) => panic!(
+            "{} drifted from its golden curve by {} at {} = {}",
+            name, deviation, curve.input, x
+        ),
+        None => panic!("{} is no longer sampled at the golden inputs", name),
+    }
+}
+
+/// The rocket launcher's desirability from the book's weapon selection.
+fn rocket_launcher() -> FuzzyModule {
+    let mut module = FuzzyModule::new();
+
+    let distance = module.create_variable("DistToTarget");
+    let close = distance.add_left_shoulder_set("Target_Close", 0.0, 25.0, 150.0);
+    let medium = distance.add_triangle_set("Target_Medium", 25.0, 150.0, 300.0);
+    let far = distance.add_right_shoulder_set("Target_Far", 150.0, 300.0, 1000.0);
+
+    let ammo = module.create_variable("AmmoStatus");
+    let low = ammo.add_triangle_set("Ammo_Low", 0.0, 0.0, 10.0);
+    let okay = ammo.add_triangle_set("Ammo_Okay", 0.0, 10.0, 30.0);
+    let loads = ammo.add_right_shoulder_set("Ammo_Loads", 10.0, 30.0, 100.0);
+
+    let desirability = module.create_variable("Desirability");
+    let undesirable = desirability.add_left_shoulder_set("Undesirable", 0.0, 25.0, 50.0);
+    let desirable = desirability.add_triangle_set("Desirable", 25.0, 50.0, 75.0);
+    let very_desirable = desirability.add_right_shoulder_set("VeryDesirable", 50.0, 75.0, 100.0);
+
+    module.add_rules(fuzzy_rules! {
+        if (close & loads) then undesirable;
+        if (close & okay) then undesirable;
+        if (close & low) then undesirable;
+        if (medium & loads) then very_desirable;
+        if (medium & okay) then very_desirable;
+        if (medium & low) then desirable;
+        if (far & loads) then desirable;
+        if (far & okay) then undesirable;
+        if (far & low) then undesirable;
+    });
+    module
+}
+
+fn distance_sweep(module: &mut FuzzyModule, method: DefuzzifyMethod) -> Curve {
+    module.sweep("DistToTarget", "Desirability", method, (0.0, 1000.0), 81)
+}
+
+#[test]
+fn rocket_launcher_max_av() {
+    let mut module = rocket_launcher();
+    module.fuzzify("AmmoStatus", 20.0);
+    assert_golden(
+        "rocket_launcher_max_av",
+        &distance_sweep(&mut module, DefuzzifyMethod::MaxAv),
+    );
+}
+
+#[test]
+fn rocket_launcher_centroid() {
+    let mut module = rocket_launcher();
+    module.fuzzify("AmmoStatus", 20.0);
+    assert_golden(
+        "rocket_launcher_centroid",
+        &distance_sweep(&mut module, DefuzzifyMethod::Centroid(50)),
+    );
+}
+
+#[test]
+fn rocket_launcher_by_ammo() {
+    let mut module = rocket_launcher();
+    module.fuzzify("DistToTarget", 200.0);
+    let curve = module.sweep(
+        "AmmoStatus",
+        "Desirability",
+        DefuzzifyMethod::MaxAv,
+        (0.0, 100.0),
+        51,
+    );
+    assert_golden("rocket_launcher_by_ammo", &curve);
+}
+
+#[test]
+fn hedges() {
+    let mut module = FuzzyModule::new();
+    let thirst = module.create_variable("Thirst");
+    let quenched = thirst.add_left_shoulder_set("Quenched", 0.0, 2.0, 5.0);
+    let thirsty = thirst.add_triangle_set("Thirsty", 2.0, 5.0, 8.0);
+    let parched = thirst.add_right_shoulder_set("Parched", 5.0, 8.0, 10.0);
+
+    let desirability = module.create_variable("Desirability");
+    let low = desirability.add_left_shoulder_set("Low", 0.0, 25.0, 50.0);
+    let high = desirability.add_right_shoulder_set("High", 50.0, 75.0, 100.0);
+
+    module.add_rules(fuzzy_rules! {
+        if (quenched | fairly(thirsty)) then low;
+        if (very(thirsty) | parched) then high;
+    });
+
+    let curve = module.sweep(
+        "Thirst",
+        "Desirability",
+        DefuzzifyMethod::MaxAv,
+        (0.0, 10.0),
+        41,
+    );
+    assert_golden("hedges", &curve);
+}
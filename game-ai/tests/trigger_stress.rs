@@ -0,0 +1,99 @@
+//! Thousands of triggers and agents: the partitioned trigger system must set
+//! off exactly the same triggers as testing every agent against every
+//! trigger.
+
+use game_ai::cell_space::CellSpacePartition;
+use game_ai::triggers::{RespawningGiver, TriggerRegion, TriggerSystem, TriggerTarget};
+use game_ai::Vector2D;
+use rand::rngs::StdRng;
+use rand::{Rng, SeedableRng};
+use std::time::Instant;
+
+const WORLD_SIZE: f64 = 1000.0;
+const TRIGGERS: usize = 2000;
+const AGENTS: usize = 2000;
+const TICKS: usize = 5;
+
+#[derive(Clone)]
+struct Agent {
+    position: Vector2D,
+    pickups: Vec<usize>,
+}
+
+impl TriggerTarget for Agent {
+    fn position(&self) -> Vector2D {
+        self.position
+    }
+
+    fn bounding_radius(&self) -> f64 {
+        2.0
+    }
+}
+
+fn random_position(rng: &mut StdRng) -> Vector2D {
+    Vector2D::new(
+        rng.gen_range(0.0..WORLD_SIZE),
+        rng.gen_range(0.0..WORLD_SIZE),
+    )
+}
+
+fn populate(system: &mut TriggerSystem<Agent>, rng: &mut StdRng) {
+    for id in 0..TRIGGERS {
+        let region = if id % 2 == 0 {
+            TriggerRegion::circle(random_position(rng), rng.gen_range(1.0..10.0))
+        } else {
+            let corner = random_position(rng);
+            TriggerRegion::rectangle(corner, corner + Vector2D::new(15.0, 5.0))
+        };
+        system.register(RespawningGiver::new(region, 2, move |agent: &mut Agent| {
+            agent.pickups.push(id)
+        }));
+    }
+}
+
+fn run(mut system: TriggerSystem<Agent>, mut agents: Vec<Agent>, seed: u64) -> Vec<Agent> {
+    let mut rng = StdRng::seed_from_u64(seed);
+    for _ in 0..TICKS {
+        system.update(&mut agents);
+        for agent in &mut agents {
+            agent.position = random_position(&mut rng);
+        }
+    }
+    agents
+}
+
+#[test]
+fn partitioned_triggers_match_all_pairs() {
+    let mut rng = StdRng::seed_from_u64(7);
+    let agents: Vec<Agent> = (0..AGENTS)
+        .map(|_| Agent {
+            position: random_position(&mut rng),
+            pickups: vec![],
+        })
+        .collect();
+
+    let mut all_pairs = TriggerSystem::new();
+    populate(&mut all_pairs, &mut StdRng::seed_from_u64(11));
+    let mut partitioned =
+        TriggerSystem::with_partition(CellSpacePartition::new(WORLD_SIZE, WORLD_SIZE, 40, 40));
+    populate(&mut partitioned, &mut StdRng::seed_from_u64(11));
+
+    let start = Instant::now();
+    let expected = run(all_pairs, agents.clone(), 13);
+    let all_pairs_time = start.elapsed();
+
+    let start = Instant::now();
+    let actual = run(partitioned, agents, 13);
+    let partitioned_time = start.elapsed();
+
+    let pickups: usize = expected.iter().map(|agent| agent.pickups.len()).sum();
+    assert!(pickups > 0, "no triggers were set off");
+    for (expected, actual) in expected.iter().zip(&actual) {
+        assert_eq!(expected.pickups, actual.pickups);
+    }
+
+    println!(
+        "{} pickups: all pairs {:?}, partitioned {:?}",
+        pickups, all_pairs_time, partitioned_time
+    );
+}
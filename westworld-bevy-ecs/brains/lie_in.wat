@@ -0,0 +1,16 @@
+;; Lying in of a morning: he stays abed till ten, when he calls out to his
+;; wife and heads off to dig.
+(module
+  (import "westworld" "stat" (func $stat (param i32) (result i32)))
+  (import "westworld" "switch" (func $switch (param i32 i32)))
+  (import "westworld" "send" (func $send (param i32 i32)))
+  (memory (export "memory") 1)
+  (data (i32.const 0) "EnterMineAndDigForNugget")
+  (data (i32.const 32) "HiHoneyImHome")
+
+  (func (export "update")
+    ;; the hour
+    (if (i32.ge_s (call $stat (i32.const 6)) (i32.const 10))
+      (then
+        (call $send (i32.const 32) (i32.const 13))
+        (call $switch (i32.const 0) (i32.const 24))))))
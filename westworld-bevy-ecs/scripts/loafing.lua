@@ -0,0 +1,14 @@
+-- Loafing about town, a miner in no hurry to dig: lying in at the shack till
+-- eight, then propping up the bar at the saloon till noon, when he heads
+-- back to work.
+return {
+    update = function(miner)
+        if miner.hour < 8 then
+            return nil
+        elseif miner.hour >= 12 then
+            return { switch = "EnterMineAndDigForNugget" }
+        elseif miner.location ~= "Saloon" then
+            return { push = "TravelTo(Saloon)" }
+        end
+    end,
+}
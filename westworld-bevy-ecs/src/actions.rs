@@ -0,0 +1,88 @@
+//! What the agents do, as [`ActionTaken`] messages for whatever's presenting
+//! the world to show, rather than each state saying so itself. States take
+//! an action through their [`Outbox`], alongside the messages they send, and
+//! [`log_actions`] is the one presentation so far.
+
+use crate::messaging::{add_tick_messages, Outbox, POST_MESSAGES};
+use crate::miner::Miner;
+use crate::narration::narrate_miners;
+use crate::{Name, Phase};
+use bevy_app::{App, FixedUpdate, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+
+/// Something an agent did worth showing.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub enum AgentAction {
+    DugNugget,
+    /// Paid this much gold into the bank.
+    Deposited(i32),
+    Drank,
+    Slept,
+}
+
+/// An agent's action, sent once the agents have all taken their turns.
+#[derive(Message, Copy, Clone, Debug, PartialEq)]
+pub struct ActionTaken {
+    pub agent: Entity,
+    pub action: AgentAction,
+}
+
+/// Sends on the actions the agents took this tick, in the order they were
+/// spawned, like their messages.
+pub fn post_actions(mut outboxes: Query<&mut Outbox>, mut actions: MessageWriter<ActionTaken>) {
+    let in_turn = outboxes
+        .iter_mut()
+        .sort_by_key::<Entity, _>(|entity| entity.index_u32());
+    for mut outbox in in_turn {
+        let agent = outbox.owner();
+        actions.write_batch(
+            outbox
+                .drain_actions()
+                .map(|action| ActionTaken { agent, action }),
+        );
+    }
+}
+
+/// Says what each agent did, as the states used to themselves.
+pub fn log_actions(
+    mut actions: MessageReader<ActionTaken>,
+    names: Query<&Name>,
+    miners: Query<&Miner>,
+) {
+    for &ActionTaken { agent, action } in actions.read() {
+        let name = match names.get(agent) {
+            Ok(name) => name,
+            Err(_) => continue,
+        };
+        match action {
+            AgentAction::DugNugget => info!("{}: Pickin' up a nugget", name),
+            AgentAction::Deposited(amount) => {
+                let savings = miners.get(agent).map_or(0, Miner::wealth);
+                info!(
+                    "{}: Depositing {} gold. Total savings now: {}",
+                    name, amount, savings
+                );
+            }
+            AgentAction::Drank => info!("{}: That's mighty fine sippin liquer", name),
+            AgentAction::Slept => info!("{}: ZZZZ... ", name),
+        }
+    }
+}
+
+pub struct ActionsPlugin;
+
+impl Plugin for ActionsPlugin {
+    fn build(&self, app: &mut App) {
+        add_tick_messages::<ActionTaken>(app);
+        app.add_systems(
+            FixedUpdate,
+            post_actions.in_set(Phase::Dispatch).before(POST_MESSAGES),
+        );
+        // what they did before where they're off to next
+        app.add_systems(
+            FixedUpdate,
+            log_actions.in_set(Phase::Log).before(narrate_miners),
+        );
+    }
+}
@@ -0,0 +1,145 @@
+//! A side panel in the inspector's window listing every agent: its whole
+//! state stack, top first, what a miner's got and where anyone's headed,
+//! redrawn every frame. It comes with `--inspect`, alongside the world
+//! inspector, and shows the same as the terminal dashboard for the demos
+//! that need a window of their own.
+
+use crate::bandit::BanditState;
+use crate::barfly::BarFlyState;
+use crate::miner::{Miner, MinerState};
+use crate::partner::PartnerState;
+use crate::sheriff::{Sheriff, SheriffState};
+use crate::{Location, Name};
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_inspector_egui::bevy_egui::{egui, EguiContexts, EguiPrimaryContextPass};
+use fsm::StateStack;
+use std::fmt::Debug;
+
+/// What the panel shows of one agent.
+struct Card {
+    name: String,
+    location: Location,
+    states: Vec<String>,
+    stats: Vec<(&'static str, i32)>,
+    target: Option<String>,
+}
+
+impl Card {
+    fn new<S: Clone + Debug>(name: &Name, location: &Location, stack: &StateStack<S>) -> Self {
+        Card {
+            name: name.to_string(),
+            location: *location,
+            states: stack
+                .iter()
+                .rev()
+                .map(|state| format!("{:?}", state))
+                .collect(),
+            stats: vec![],
+            target: None,
+        }
+    }
+
+    fn with_miner(mut self, miner: &Miner, stack: &StateStack<MinerState>) -> Self {
+        self.stats = vec![
+            ("Gold", miner.gold()),
+            ("Bank", miner.wealth()),
+            ("Thirst", miner.thirst()),
+            ("Fatigue", miner.fatigue()),
+            ("Hunger", miner.hunger()),
+        ];
+        if let Some(MinerState::TravelTo(to)) = stack.last() {
+            self.target = Some(format!("the {:?}", to));
+        }
+        self
+    }
+
+    fn show(&self, ui: &mut egui::Ui) {
+        egui::CollapsingHeader::new(&self.name)
+            .default_open(true)
+            .show(ui, |ui| {
+                ui.label(format!("At the {:?}", self.location));
+                if let Some(target) = &self.target {
+                    ui.label(format!("Headed for {}", target));
+                }
+                if !self.stats.is_empty() {
+                    egui::Grid::new(&self.name).show(ui, |ui| {
+                        for (stat, value) in &self.stats {
+                            ui.label(*stat);
+                            ui.label(value.to_string());
+                            ui.end_row();
+                        }
+                    });
+                }
+                for state in &self.states {
+                    ui.monospace(state);
+                }
+            });
+    }
+}
+
+type Agents<'w, 's, 'a, S> = Query<'w, 's, (&'a Name, &'a Location, &'a StateStack<S>)>;
+
+/// Agents of a kind with a component of their own worth showing.
+type AgentsWith<'w, 's, 'a, T, S> =
+    Query<'w, 's, (&'a Name, &'a Location, &'a T, &'a StateStack<S>)>;
+
+fn agent_cards<S>(agents: &Agents<S>) -> Vec<Card>
+where
+    S: Clone + Debug + Send + Sync + 'static,
+{
+    agents
+        .iter()
+        .map(|(name, location, stack)| Card::new(name, location, stack))
+        .collect()
+}
+
+pub fn draw_agent_panel(
+    mut contexts: EguiContexts,
+    names: Query<&Name>,
+    (miners, sheriffs): (
+        AgentsWith<Miner, MinerState>,
+        AgentsWith<Sheriff, SheriffState>,
+    ),
+    (partners, barflies, bandits): (
+        Agents<PartnerState>,
+        Agents<BarFlyState>,
+        Agents<BanditState>,
+    ),
+) -> Result {
+    let mut cards: Vec<Card> = miners
+        .iter()
+        .map(|(name, location, miner, stack)| {
+            Card::new(name, location, stack).with_miner(miner, stack)
+        })
+        .collect();
+    cards.extend(agent_cards(&partners));
+    cards.extend(agent_cards(&barflies));
+    cards.extend(agent_cards(&bandits));
+    cards.extend(sheriffs.iter().map(|(name, location, sheriff, stack)| {
+        let mut card = Card::new(name, location, stack);
+        card.target = sheriff
+            .chasing
+            .and_then(|bandit| names.get(bandit).ok())
+            .map(Name::to_string);
+        card
+    }));
+
+    egui::SidePanel::right("agents").show(contexts.ctx_mut()?, |ui| {
+        ui.heading("Agents");
+        egui::ScrollArea::vertical().show(ui, |ui| {
+            for card in &cards {
+                card.show(ui);
+            }
+        });
+    });
+    Ok(())
+}
+
+pub struct AgentPanelPlugin;
+
+impl Plugin for AgentPanelPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(EguiPrimaryContextPass, draw_agent_panel);
+    }
+}
@@ -0,0 +1,510 @@
+//! Running any kind of agent's state machine: [`FsmPlugin`] adds the systems
+//! updating each agent's [`StateStack`], handing it the messages sent it, and
+//! stopping it once the run is over or it's despawned. All a kind of agent
+//! has to say is how its states' data is made up, by implementing
+//! [`AgentHandler`], and it's spawned with what every agent has by
+//! [`spawn_agent`], so the town's messaging, census and logging take it in
+//! along with the rest.
+
+use crate::messaging::{Outbox, Telegram};
+use crate::replay::{Observers, Watch};
+use crate::shutdown::WindDown;
+use crate::trace::{self, Spans, StateSpans};
+use crate::{Label, Location, Name, Phase};
+use bevy_app::{App, FixedUpdate, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_ecs::query::{QueryData, QueryItem};
+use bevy_ecs::system::EntityCommands;
+use bevy_ecs::system::{StaticSystemParam, SystemParam, SystemParamItem};
+use bevy_tasks::{ComputeTaskPool, TaskPool};
+use bevy_utils::Parallel;
+use fsm::{Handler, StateMachine, StateStack};
+use std::fmt::Debug;
+use std::marker::PhantomData;
+
+/// Spawns an agent with the components every kind has: its name, an
+/// [`Outbox`] for the messages it sends and where it is, for its kind to add
+/// its own to, and its states.
+pub fn spawn_agent<'a>(
+    commands: &'a mut Commands,
+    name: &str,
+    location: Location,
+) -> EntityCommands<'a> {
+    let mut agent = commands.spawn_empty();
+    let id = agent.id();
+    agent
+        .insert(Name(name.to_string()))
+        .insert(Outbox::new(id))
+        .insert(location);
+    agent
+}
+
+/// What an [`AgentHandler`] is asked to do with an agent's states.
+pub enum Run<'a> {
+    /// Update the state on top.
+    Update,
+    /// Hand the state on top a message.
+    Message(&'a Telegram),
+    /// Stop every state, once the run is over.
+    Stop,
+}
+
+impl Run<'_> {
+    /// Does it with `handler`, and the state data made up for it.
+    pub fn on<S: Clone, D, H: Handler<S, D, Telegram>>(
+        &self,
+        handler: &H,
+        states: &mut StateStack<S>,
+        state_data: &mut D,
+    ) {
+        match self {
+            Run::Update => StateMachine::update(handler, states, state_data),
+            Run::Message(telegram) => {
+                StateMachine::handle_message(handler, states, state_data, telegram);
+            }
+            Run::Stop => StateMachine::stop(handler, states, state_data),
+        }
+    }
+}
+
+/// A kind of agent with states `S`, run by an [`FsmPlugin`], whose states
+/// look at the agent's own components `D` and whatever else in town they
+/// need.
+pub trait AgentHandler<S: Clone, D: QueryData>: Send + Sync + 'static {
+    /// What the states look at besides the agent's own components.
+    type Town: SystemParam;
+
+    /// Makes up an agent's state data from its components and the town, and
+    /// runs its states with it.
+    fn run(
+        town: &mut SystemParamItem<'_, '_, Self::Town>,
+        name: &Name,
+        agent: QueryItem<'_, '_, D>,
+        states: &mut StateStack<S>,
+        run: Run,
+    );
+}
+
+/// A kind of agent whose updates can all run at once, spread across
+/// threads by [`update_agents_at_once`]. While they run the town is only
+/// looked at, as it stood before any of them, with each agent's share of
+/// whatever the [`Round`](ParallelAgentHandler::Round) hands out; whatever
+/// they'd change there is caught up with afterwards, agent by agent in the
+/// order they were spawned. So a seeded run goes the same way whichever
+/// threads the agents ran on, or whether they ran at once at all.
+pub trait ParallelAgentHandler<S: Clone, D: QueryData>: AgentHandler<S, D> {
+    /// What's handed out to the agents for a round of updates.
+    type Round: Sync;
+    /// What an agent's update leaves for the town to catch up with.
+    type Deferred: Send;
+
+    /// Gets a round of updates ready, before any agent's run.
+    fn round(town: &mut SystemParamItem<'_, '_, Self::Town>) -> Self::Round;
+
+    /// Makes up an agent's state data from its components, the town and its
+    /// share of the round, and updates its states with it. `turn` is its
+    /// place in the order the agents were spawned.
+    fn update(
+        town: &SystemParamItem<'_, '_, Self::Town>,
+        round: &Self::Round,
+        turn: u64,
+        name: &Name,
+        agent: QueryItem<'_, '_, D>,
+        states: &mut StateStack<S>,
+    ) -> Self::Deferred;
+
+    /// Catches the town up with what an agent's update left it.
+    fn catch_up(town: &mut SystemParamItem<'_, '_, Self::Town>, deferred: Self::Deferred);
+}
+
+/// How many agents of a kind there have to be before
+/// [`update_agents_at_once`] spreads their updates across threads. Fewer are
+/// run one after another, which is quicker than waking the threads for them.
+#[derive(Resource, Copy, Clone, Debug, PartialEq)]
+pub struct ParallelAbove(pub usize);
+
+impl Default for ParallelAbove {
+    fn default() -> Self {
+        ParallelAbove(64)
+    }
+}
+
+type Agents<'a, S, D> = (Entity, &'a Name, &'a mut StateStack<S>, D);
+
+pub fn update_agents<S, H, D>(
+    mut town: StaticSystemParam<H::Town>,
+    mut log: Observers,
+    mut spans: Spans,
+    mut agents: Query<Agents<S, D>>,
+) where
+    S: Clone + Debug + Send + Sync + 'static,
+    H: AgentHandler<S, D>,
+    D: QueryData + 'static,
+{
+    // in the order they were spawned, which a restored snapshot keeps,
+    // whichever tables they've since moved between, as seeded runs have to
+    // draw the same random numbers in the same order
+    let in_turn = agents
+        .iter_mut()
+        .sort_by_key::<Entity, _>(|entity| entity.index_u32());
+    for (entity, name, mut states, agent) in in_turn {
+        let watch = Watch::new(&log, &states);
+        trace::traced(&mut spans, entity, name, &mut states, |states| {
+            H::run(&mut town, name, agent, states, Run::Update)
+        });
+        watch.finish(&mut log, name, &states);
+    }
+}
+
+/// Updates the agents of a [`ParallelAgentHandler`] all at once, if there
+/// are more than [`ParallelAbove`] of them and their states aren't being
+/// traced, whose spans have to be entered one at a time; otherwise one after
+/// another, to the same effect.
+pub fn update_agents_at_once<S, H, D>(
+    mut town: StaticSystemParam<H::Town>,
+    mut log: Observers,
+    mut spans: Spans,
+    above: Res<ParallelAbove>,
+    mut agents: Query<Agents<S, D>>,
+) where
+    S: Clone + Debug + Send + Sync + 'static,
+    H: ParallelAgentHandler<S, D>,
+    D: QueryData + 'static,
+    for<'w, 's> SystemParamItem<'w, 's, H::Town>: Sync,
+{
+    let mut spawned: Vec<Entity> = agents.iter().map(|(entity, ..)| entity).collect();
+    if spawned.is_empty() {
+        return;
+    }
+    spawned.sort_by_key(|entity| entity.index_u32());
+    let round = H::round(&mut town);
+
+    let mut updated = Vec::with_capacity(spawned.len());
+    if spawned.len() > above.0 && !trace::kept(&spans) {
+        ComputeTaskPool::get_or_init(TaskPool::default);
+        let (town, log) = (&*town, &log);
+        let mut done = Parallel::<Vec<_>>::default();
+        agents
+            .par_iter_mut()
+            .for_each(|(entity, name, mut states, agent)| {
+                let turn = spawned
+                    .binary_search_by_key(&entity.index_u32(), |spawned| spawned.index_u32())
+                    .unwrap() as u64;
+                let watch = Watch::new(log, &states);
+                let deferred = H::update(town, &round, turn, name, agent, &mut states);
+                done.borrow_local_mut()
+                    .push((turn, watch.end(name, &states), deferred));
+            });
+        done.drain_into(&mut updated);
+        updated.sort_by_key(|&(turn, ..)| turn);
+    } else {
+        let in_turn = agents
+            .iter_mut()
+            .sort_by_key::<Entity, _>(|entity| entity.index_u32());
+        for (turn, (entity, name, mut states, agent)) in (0..).zip(in_turn) {
+            let watch = Watch::new(&log, &states);
+            let deferred = trace::traced(&mut spans, entity, name, &mut states, |states| {
+                H::update(&town, &round, turn, name, agent, states)
+            });
+            updated.push((turn, watch.end(name, &states), deferred));
+        }
+    }
+
+    for (_, watched, deferred) in updated {
+        watched.record(&mut log);
+        H::catch_up(&mut town, deferred);
+    }
+}
+
+pub fn deliver_to_agents<S, H, D>(
+    mut town: StaticSystemParam<H::Town>,
+    mut log: Observers,
+    mut spans: Spans,
+    mut telegrams: MessageReader<Telegram>,
+    mut agents: Query<Agents<S, D>>,
+) where
+    S: Clone + Debug + Send + Sync + 'static,
+    H: AgentHandler<S, D>,
+    D: QueryData + 'static,
+{
+    for telegram in telegrams.read() {
+        if let Ok((entity, name, mut states, agent)) = agents.get_mut(telegram.receiver) {
+            let watch = Watch::new(&log, &states);
+            trace::traced(&mut spans, entity, name, &mut states, |states| {
+                trace::handed(telegram);
+                H::run(&mut town, name, agent, states, Run::Message(telegram))
+            });
+            watch.finish(&mut log, name, &states);
+        }
+    }
+}
+
+/// Stops every agent's state machine, once the run is over.
+pub fn stop_agents<S, H, D>(
+    mut town: StaticSystemParam<H::Town>,
+    mut spans: Spans,
+    mut agents: Query<Agents<S, D>>,
+) where
+    S: Clone + Debug + Send + Sync + 'static,
+    H: AgentHandler<S, D>,
+    D: QueryData + 'static,
+{
+    for (entity, name, mut states, agent) in agents.iter_mut() {
+        trace::traced(&mut spans, entity, name, &mut states, |states| {
+            H::run(&mut town, name, agent, states, Run::Stop)
+        });
+    }
+}
+
+/// Stops the states of an agent being despawned, while its components are
+/// still there for them, so they let go of whatever they hold. Any messages
+/// they send on the way out go with it; whoever they were for has to notice
+/// the agent's gone for themselves.
+pub fn stop_despawned_agent<S, H, D>(
+    removed: On<Remove, StateStack<S>>,
+    mut town: StaticSystemParam<H::Town>,
+    mut spans: Spans,
+    mut agents: Query<Agents<S, D>>,
+) where
+    S: Clone + Debug + Send + Sync + 'static,
+    H: AgentHandler<S, D>,
+    D: QueryData + 'static,
+{
+    if let Ok((entity, name, mut states, agent)) = agents.get_mut(removed.entity) {
+        // already stopped, or taken over by another
+        if !states.is_empty() {
+            trace::traced(&mut spans, entity, name, &mut states, |states| {
+                H::run(&mut town, name, agent, states, Run::Stop)
+            });
+        }
+    }
+}
+
+/// Which kind of agent an [`FsmPlugin`] is for, without holding one.
+type Kind<S, H, D> = PhantomData<fn() -> (S, H, D)>;
+
+/// Runs the state machines of every agent with states `S`, components `D`
+/// and handler `H`. Each kind of agent takes its turn after the kind it's
+/// added [`after`](FsmPlugin::after), so seeded runs replay exactly.
+pub struct FsmPlugin<S, H, D> {
+    update: Label,
+    deliver: Label,
+    after: Option<(Label, Label)>,
+    /// Adds the system updating the agents, in the set given.
+    updates: fn(&mut App, Label),
+    kind: Kind<S, H, D>,
+}
+
+impl<S, H, D> FsmPlugin<S, H, D>
+where
+    S: Clone + Debug + Send + Sync + 'static,
+    H: AgentHandler<S, D>,
+    D: QueryData + 'static,
+{
+    /// Updates the agents one after another in the `update` set, and hands
+    /// them their messages in the `deliver` set.
+    pub fn new(update: Label, deliver: Label) -> Self {
+        FsmPlugin {
+            update,
+            deliver,
+            after: None,
+            updates: |app, update| {
+                app.add_systems(
+                    FixedUpdate,
+                    update_agents::<S, H, D>
+                        .in_set(update)
+                        .in_set(Phase::Decide),
+                );
+            },
+            kind: PhantomData,
+        }
+    }
+
+    /// Updates the agents all at once in the `update` set, once there are
+    /// enough of them, and hands them their messages in the `deliver` set.
+    pub fn at_once(update: Label, deliver: Label) -> Self
+    where
+        H: ParallelAgentHandler<S, D>,
+        for<'w, 's> SystemParamItem<'w, 's, H::Town>: Sync,
+    {
+        FsmPlugin {
+            updates: |app, update| {
+                app.init_resource::<ParallelAbove>();
+                app.add_systems(
+                    FixedUpdate,
+                    update_agents_at_once::<S, H, D>
+                        .in_set(update)
+                        .in_set(Phase::Decide),
+                );
+            },
+            ..Self::new(update, deliver)
+        }
+    }
+
+    /// Has the agents take their turn, and hear their messages, after those
+    /// of the kind with these labels.
+    pub fn after(mut self, update: Label, deliver: Label) -> Self {
+        self.after = Some((update, deliver));
+        self
+    }
+}
+
+impl<S, H, D> Plugin for FsmPlugin<S, H, D>
+where
+    S: Clone + Debug + Send + Sync + 'static,
+    H: AgentHandler<S, D>,
+    D: QueryData + 'static,
+{
+    fn build(&self, app: &mut App) {
+        app.init_resource::<StateSpans>();
+        (self.updates)(app, self.update);
+        app.add_systems(
+            FixedUpdate,
+            deliver_to_agents::<S, H, D>
+                .in_set(self.deliver)
+                .in_set(Phase::Perceive),
+        );
+        app.add_systems(WindDown, stop_agents::<S, H, D>.in_set(self.update));
+        app.add_observer(stop_despawned_agent::<S, H, D>);
+        if let Some((update, deliver)) = self.after {
+            app.configure_sets(
+                FixedUpdate,
+                (self.update.after(update), self.deliver.after(deliver)),
+            );
+            app.configure_sets(WindDown, self.update.after(update));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::{add_tick_messages, Message};
+    use bevy_app::FixedFirst;
+    use fsm::StateTransition;
+
+    /// Counts up each update until it's told to stop.
+    #[derive(Component, Default)]
+    struct Count(usize);
+
+    #[derive(Resource, Default)]
+    struct Stopped(usize);
+
+    struct Counter;
+
+    impl<'a> Handler<bool, (&'a mut Count, &'a mut Stopped), Telegram> for Counter {
+        fn on_stop(&self, _state: &bool, data: &mut (&'a mut Count, &'a mut Stopped)) {
+            data.1 .0 += 1;
+        }
+
+        fn update(
+            &self,
+            &counting: &bool,
+            data: &mut (&'a mut Count, &'a mut Stopped),
+        ) -> StateTransition<bool> {
+            if counting {
+                data.0 .0 += 1;
+            }
+            StateTransition::None
+        }
+
+        fn on_message(
+            &self,
+            _state: &bool,
+            _data: &mut (&'a mut Count, &'a mut Stopped),
+            _telegram: &Telegram,
+        ) -> Option<StateTransition<bool>> {
+            Some(StateTransition::Switch(false))
+        }
+    }
+
+    impl AgentHandler<bool, &'static mut Count> for Counter {
+        type Town = ResMut<'static, Stopped>;
+
+        fn run(
+            stopped: &mut ResMut<Stopped>,
+            _name: &Name,
+            mut count: Mut<Count>,
+            states: &mut StateStack<bool>,
+            run: Run,
+        ) {
+            run.on(&Counter, states, &mut (&mut *count, &mut **stopped));
+        }
+    }
+
+    static UPDATE_COUNTERS: Label = Label("update_counters");
+    static DELIVER_TO_COUNTERS: Label = Label("deliver_to_counters");
+
+    fn tick(app: &mut App) {
+        app.world_mut().run_schedule(FixedFirst);
+        app.world_mut().run_schedule(FixedUpdate);
+    }
+
+    #[test]
+    fn runs_every_agent_of_the_kind() {
+        let mut app = App::new();
+        add_tick_messages::<Telegram>(&mut app);
+        app.init_resource::<Stopped>();
+        app.add_plugins(FsmPlugin::<bool, Counter, &mut Count>::new(
+            UPDATE_COUNTERS,
+            DELIVER_TO_COUNTERS,
+        ));
+        let agents: Vec<Entity> = ["Tom", "Dick"]
+            .iter()
+            .map(|name| {
+                app.world_mut()
+                    .spawn((
+                        Name(name.to_string()),
+                        Count::default(),
+                        StateStack::new_initial_state(true),
+                    ))
+                    .id()
+            })
+            .collect();
+
+        tick(&mut app);
+        app.world_mut().write_message(Telegram {
+            sender: agents[0],
+            receiver: agents[1],
+            message: Message::Punch,
+        });
+        tick(&mut app);
+        tick(&mut app);
+        let counts: Vec<usize> = agents
+            .iter()
+            .map(|&agent| app.world().get::<Count>(agent).unwrap().0)
+            .collect();
+        assert_eq!(counts, vec![3, 1]);
+
+        // the one state switched out, then each of theirs
+        assert_eq!(app.world().resource::<Stopped>().0, 1);
+        crate::shutdown::wind_down(app.world_mut());
+        assert_eq!(app.world().resource::<Stopped>().0, 3);
+    }
+
+    #[test]
+    fn stops_an_agent_despawned() {
+        let mut app = App::new();
+        add_tick_messages::<Telegram>(&mut app);
+        app.init_resource::<Stopped>();
+        app.add_plugins(FsmPlugin::<bool, Counter, &mut Count>::new(
+            UPDATE_COUNTERS,
+            DELIVER_TO_COUNTERS,
+        ));
+        let tom = app
+            .world_mut()
+            .spawn((
+                Name("Tom".to_string()),
+                Count::default(),
+                StateStack::new_initial_state(true),
+            ))
+            .id();
+        tick(&mut app);
+
+        app.world_mut().despawn(tom);
+        assert_eq!(app.world().resource::<Stopped>().0, 1);
+        // and isn't stopped again once the run's over
+        crate::shutdown::wind_down(app.world_mut());
+        assert_eq!(app.world().resource::<Stopped>().0, 1);
+    }
+}
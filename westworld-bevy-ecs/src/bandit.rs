@@ -0,0 +1,405 @@
+use std::ops::DerefMut;
+
+use crate::agents::{spawn_agent, AgentHandler, FsmPlugin, Run};
+use crate::barfly::{DELIVER_TO_BARFLIES, INIT_BARFLIES, UPDATE_BARFLIES};
+use crate::messaging::{Message, Outbox, Telegram, SEND_MSG_IMMEDIATELY};
+use crate::miner::Miner;
+use crate::path_planner::NavGraph;
+use crate::riders::{self, Mount, RIDE};
+use crate::scenario::Scenario;
+use crate::sheriff::Sheriff;
+use crate::{Label, Location, Name};
+use bevy_app::{App, FixedUpdate, Plugin, Startup};
+use bevy_ecs::prelude::*;
+use bevy_ecs::query::QueryItem;
+use bevy_ecs::system::SystemParamItem;
+use bevy_log::prelude::*;
+use bevy_reflect::Reflect;
+use game_ai::steering::{Behavior, Deceleration, Vehicle};
+use game_ai::Vector2D;
+use serde::{Deserialize, Serialize};
+
+pub static LIE_LOW_TIME: u32 = 200; // updates spent at the hideout between robberies
+pub static JAIL_TIME: u32 = 40; // updates spent behind bars once caught
+pub static ROBBERY_SHARE: i32 = 50; // percentage of every miner's savings taken in a robbery
+pub static BANDIT_SPEED: f64 = 7.0;
+
+/// Label of the system updating the bandits, for systems that must run in a fixed order with it.
+pub static UPDATE_BANDITS: Label = Label("update_bandits");
+/// Label of the system delivering messages to the bandits.
+pub static DELIVER_TO_BANDITS: Label = Label("deliver_to_bandits");
+/// Label of the startup system spawning the bandits.
+pub static INIT_BANDITS: Label = Label("init_bandits");
+
+/// The sheriffs who hear the bank's alarm.
+pub type Lawmen = [Entity];
+
+pub type BanditStateData<'a> = (
+    &'a Name,
+    &'a mut Location,
+    &'a mut Bandit,
+    &'a mut Vehicle,
+    &'a mut Outbox,
+    &'a NavGraph,
+    &'a Lawmen,
+);
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct Bandit {
+    loot: i32,
+    lying_low: u32,
+    jail_time: u32,
+    robbing: bool,
+}
+
+impl Bandit {
+    pub fn new() -> Self {
+        Bandit {
+            loot: 0,
+            lying_low: LIE_LOW_TIME,
+            jail_time: 0,
+            robbing: false,
+        }
+    }
+
+    /// True, once, if he's just held up the bank.
+    fn take_robbery(&mut self) -> bool {
+        std::mem::take(&mut self.robbing)
+    }
+}
+
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BanditState {
+    LieLow,
+    RideToBank,
+    RobBank,
+    Flee,
+    Jailed,
+}
+
+pub fn position_of(graph: &NavGraph, location: Location) -> Vector2D {
+    graph
+        .position_of(location)
+        .unwrap_or_else(|| panic!("there's no {:?} in town", location))
+}
+
+pub struct LieLow;
+
+impl<'a> fsm::Handler<BanditState, BanditStateData<'a>, Telegram> for LieLow {
+    fn on_start(&self, _state: &BanditState, (_name, _location, bandit, ..): &mut BanditStateData) {
+        bandit.lying_low = LIE_LOW_TIME;
+    }
+
+    fn update(
+        &self,
+        _state: &BanditState,
+        (name, location, bandit, vehicle, _outbox, graph, _lawmen): &mut BanditStateData,
+    ) -> fsm::StateTransition<BanditState> {
+        let hideout = position_of(graph, Location::Hideout);
+        if !riders::arrived(vehicle, hideout) {
+            vehicle.set_behavior(Some(Behavior::Arrive(hideout, Deceleration::Fast)));
+            return fsm::StateTransition::None;
+        }
+        if **location != Location::Hideout {
+            info!("{}: Back at the hideout. Ah'll lie low a spell", name);
+            **location = Location::Hideout;
+        }
+
+        if bandit.lying_low == 0 {
+            fsm::StateTransition::Switch(BanditState::RideToBank)
+        } else {
+            bandit.lying_low -= 1;
+            fsm::StateTransition::None
+        }
+    }
+}
+
+pub struct RideToBank;
+
+impl<'a> fsm::Handler<BanditState, BanditStateData<'a>, Telegram> for RideToBank {
+    fn on_start(&self, _state: &BanditState, (name, ..): &mut BanditStateData) {
+        info!("{}: Time ah made me a withdrawal", name);
+    }
+
+    fn update(
+        &self,
+        _state: &BanditState,
+        (_name, _location, _bandit, vehicle, _outbox, graph, _lawmen): &mut BanditStateData,
+    ) -> fsm::StateTransition<BanditState> {
+        let bank = position_of(graph, Location::Bank);
+        if riders::arrived(vehicle, bank) {
+            fsm::StateTransition::Switch(BanditState::RobBank)
+        } else {
+            vehicle.set_behavior(Some(Behavior::Arrive(bank, Deceleration::Fast)));
+            fsm::StateTransition::None
+        }
+    }
+}
+
+pub struct RobBank;
+
+impl<'a> fsm::Handler<BanditState, BanditStateData<'a>, Telegram> for RobBank {
+    fn on_start(&self, _state: &BanditState, (name, location, ..): &mut BanditStateData) {
+        info!("{}: Reach fer the sky! This here's a robbery!", name);
+        **location = Location::Bank;
+    }
+
+    fn update(
+        &self,
+        _state: &BanditState,
+        (_name, _location, bandit, _vehicle, outbox, _graph, lawmen): &mut BanditStateData,
+    ) -> fsm::StateTransition<BanditState> {
+        // the savings are taken from the miners once every bandit has run
+        bandit.robbing = true;
+        for &sheriff in lawmen.iter() {
+            outbox.dispatch(SEND_MSG_IMMEDIATELY, sheriff, Message::BankRobbed);
+        }
+        fsm::StateTransition::Switch(BanditState::Flee)
+    }
+}
+
+pub struct Flee;
+
+impl<'a> fsm::Handler<BanditState, BanditStateData<'a>, Telegram> for Flee {
+    fn on_start(&self, _state: &BanditState, (name, ..): &mut BanditStateData) {
+        info!("{}: Yee-haw! So long, suckers!", name);
+    }
+
+    fn update(
+        &self,
+        _state: &BanditState,
+        (name, _location, bandit, vehicle, _outbox, graph, _lawmen): &mut BanditStateData,
+    ) -> fsm::StateTransition<BanditState> {
+        let hideout = position_of(graph, Location::Hideout);
+        if riders::arrived(vehicle, hideout) {
+            info!(
+                "{}: Lost 'em. That's {} gold in mah saddlebags",
+                name, bandit.loot
+            );
+            fsm::StateTransition::Switch(BanditState::LieLow)
+        } else {
+            vehicle.set_behavior(Some(Behavior::Arrive(hideout, Deceleration::Fast)));
+            fsm::StateTransition::None
+        }
+    }
+}
+
+pub struct Jailed;
+
+impl<'a> fsm::Handler<BanditState, BanditStateData<'a>, Telegram> for Jailed {
+    fn on_start(
+        &self,
+        _state: &BanditState,
+        (name, location, bandit, vehicle, _outbox, graph, _lawmen): &mut BanditStateData,
+    ) {
+        info!(
+            "{}: Dagnabbit! The sheriff's took all {} gold o' mah loot",
+            name, bandit.loot
+        );
+        bandit.loot = 0;
+        bandit.jail_time = JAIL_TIME;
+        **location = Location::Jail;
+        **vehicle = riders::horse(position_of(graph, Location::Jail), vehicle.max_speed);
+    }
+
+    fn update(
+        &self,
+        _state: &BanditState,
+        (_name, _location, bandit, ..): &mut BanditStateData,
+    ) -> fsm::StateTransition<BanditState> {
+        if bandit.jail_time == 0 {
+            return fsm::StateTransition::Switch(BanditState::LieLow);
+        }
+
+        bandit.jail_time -= 1;
+        fsm::StateTransition::None
+    }
+
+    fn on_stop(&self, _state: &BanditState, (name, ..): &mut BanditStateData) {
+        info!("{}: Free as a bird. Back to the hideout", name);
+    }
+
+    // he's already behind bars
+    fn on_message(
+        &self,
+        _state: &BanditState,
+        _state_data: &mut BanditStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<BanditState>> {
+        match telegram.message {
+            Message::Arrested => Some(fsm::StateTransition::None),
+            _ => None,
+        }
+    }
+}
+
+pub struct BanditHandler;
+
+impl<'a> fsm::Handler<BanditState, BanditStateData<'a>, Telegram> for BanditHandler {
+    fn on_start(&self, state: &BanditState, state_data: &mut BanditStateData<'a>) {
+        match state {
+            BanditState::LieLow => LieLow.on_start(state, state_data),
+            BanditState::RideToBank => RideToBank.on_start(state, state_data),
+            BanditState::RobBank => RobBank.on_start(state, state_data),
+            BanditState::Flee => Flee.on_start(state, state_data),
+            BanditState::Jailed => Jailed.on_start(state, state_data),
+        }
+    }
+
+    fn on_stop(&self, state: &BanditState, state_data: &mut BanditStateData<'a>) {
+        match state {
+            BanditState::LieLow => LieLow.on_stop(state, state_data),
+            BanditState::RideToBank => RideToBank.on_stop(state, state_data),
+            BanditState::RobBank => RobBank.on_stop(state, state_data),
+            BanditState::Flee => Flee.on_stop(state, state_data),
+            BanditState::Jailed => Jailed.on_stop(state, state_data),
+        }
+    }
+
+    fn on_pause(&self, state: &BanditState, state_data: &mut BanditStateData<'a>) {
+        match state {
+            BanditState::LieLow => LieLow.on_pause(state, state_data),
+            BanditState::RideToBank => RideToBank.on_pause(state, state_data),
+            BanditState::RobBank => RobBank.on_pause(state, state_data),
+            BanditState::Flee => Flee.on_pause(state, state_data),
+            BanditState::Jailed => Jailed.on_pause(state, state_data),
+        }
+    }
+
+    fn on_resume(&self, state: &BanditState, state_data: &mut BanditStateData<'a>) {
+        match state {
+            BanditState::LieLow => LieLow.on_resume(state, state_data),
+            BanditState::RideToBank => RideToBank.on_resume(state, state_data),
+            BanditState::RobBank => RobBank.on_resume(state, state_data),
+            BanditState::Flee => Flee.on_resume(state, state_data),
+            BanditState::Jailed => Jailed.on_resume(state, state_data),
+        }
+    }
+
+    fn update(
+        &self,
+        state: &BanditState,
+        state_data: &mut BanditStateData<'a>,
+    ) -> fsm::StateTransition<BanditState> {
+        match state {
+            BanditState::LieLow => LieLow.update(state, state_data),
+            BanditState::RideToBank => RideToBank.update(state, state_data),
+            BanditState::RobBank => RobBank.update(state, state_data),
+            BanditState::Flee => Flee.update(state, state_data),
+            BanditState::Jailed => Jailed.update(state, state_data),
+        }
+    }
+
+    fn on_message(
+        &self,
+        state: &BanditState,
+        state_data: &mut BanditStateData<'a>,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<BanditState>> {
+        let handled = match state {
+            BanditState::LieLow => LieLow.on_message(state, state_data, telegram),
+            BanditState::RideToBank => RideToBank.on_message(state, state_data, telegram),
+            BanditState::RobBank => RobBank.on_message(state, state_data, telegram),
+            BanditState::Flee => Flee.on_message(state, state_data, telegram),
+            BanditState::Jailed => Jailed.on_message(state, state_data, telegram),
+        };
+
+        handled.or(match telegram.message {
+            Message::Arrested => Some(fsm::StateTransition::Switch(BanditState::Jailed)),
+            _ => None,
+        })
+    }
+}
+
+pub struct BanditPlugin;
+
+impl Plugin for BanditPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scenario>();
+        app.add_systems(
+            Startup,
+            init_bandits.in_set(INIT_BANDITS).after(INIT_BARFLIES),
+        );
+        app.add_plugins(
+            FsmPlugin::<BanditState, BanditHandler, BanditComponents>::new(
+                UPDATE_BANDITS,
+                DELIVER_TO_BANDITS,
+            )
+            .after(UPDATE_BARFLIES, DELIVER_TO_BARFLIES),
+        );
+        app.configure_sets(FixedUpdate, UPDATE_BANDITS.before(RIDE));
+    }
+}
+
+pub fn init_bandits(mut commands: Commands, graph: Res<NavGraph>, scenario: Res<Scenario>) {
+    info!("initialising {} bandits", scenario.bandits.len());
+    for spec in &scenario.bandits {
+        let location = spec.location.unwrap_or(Location::Hideout);
+        spawn_agent(&mut commands, &spec.name, location)
+            .insert(Bandit::new())
+            .insert(Mount(riders::horse(
+                position_of(&graph, location),
+                BANDIT_SPEED,
+            )))
+            .insert(fsm::StateStack::<BanditState>::new_initial_state(
+                spec.state.unwrap_or(BanditState::LieLow),
+            ));
+    }
+}
+
+/// A bandit's own components, besides his name and states.
+pub type BanditComponents = (
+    &'static mut Location,
+    &'static mut Bandit,
+    &'static mut Mount,
+    &'static mut Outbox,
+);
+
+type BanditTown = (
+    Res<'static, NavGraph>,
+    Query<'static, 'static, Entity, With<Sheriff>>,
+    Query<'static, 'static, (&'static Name, &'static mut Miner)>,
+);
+
+impl AgentHandler<BanditState, BanditComponents> for BanditHandler {
+    type Town = BanditTown;
+
+    fn run(
+        (graph, sheriffs, miners): &mut SystemParamItem<BanditTown>,
+        name: &Name,
+        (mut location, mut bandit, mut vehicle, mut outbox): QueryItem<BanditComponents>,
+        state_stack: &mut fsm::StateStack<BanditState>,
+        run: Run,
+    ) {
+        // only a bandit taking his turn looks out for the law
+        let lawmen: Vec<Entity> = match run {
+            Run::Update => sheriffs.iter().collect(),
+            _ => vec![],
+        };
+        let mut state_data = (
+            name,
+            location.deref_mut(),
+            bandit.deref_mut(),
+            &mut vehicle.0,
+            outbox.deref_mut(),
+            &**graph,
+            &lawmen[..],
+        );
+        run.on(&BanditHandler, state_stack, &mut state_data);
+
+        if let Run::Update = run {
+            if bandit.take_robbery() {
+                for (miner_name, mut miner) in miners.iter_mut() {
+                    let stolen = miner.lose_savings(ROBBERY_SHARE);
+                    if stolen > 0 {
+                        info!(
+                            "{}: The bank's been robbed! There goes {} o' mah savings",
+                            miner_name, stolen
+                        );
+                    }
+                    bandit.loot += stolen;
+                }
+            }
+        }
+    }
+}
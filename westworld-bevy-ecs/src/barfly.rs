@@ -0,0 +1,289 @@
+use std::ops::DerefMut;
+
+use crate::agents::{spawn_agent, AgentHandler, FsmPlugin, Run};
+use crate::messaging::{Message, Outbox, Telegram, SEND_MSG_IMMEDIATELY};
+use crate::miner::{Miner, INIT_MINERS};
+use crate::partner::{DELIVER_TO_PARTNERS, INIT_PARTNERS, UPDATE_PARTNERS};
+use crate::rng::WorldRng;
+use crate::scenario::Scenario;
+use crate::{Label, Location, Name};
+use bevy_app::{App, Plugin, Startup};
+use bevy_ecs::entity::{EntityMapper, MapEntities};
+use bevy_ecs::prelude::*;
+use bevy_ecs::query::QueryItem;
+use bevy_ecs::system::SystemParamItem;
+use bevy_log::prelude::*;
+use bevy_reflect::Reflect;
+use rand::seq::SliceRandom;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub static INSULT_CHANCE: f32 = 0.5; // chance per update of picking on someone in the saloon
+pub static KNOCKOUT_TIME: u32 = 3; // updates spent on the floor after a punch
+
+/// Label of the system updating the bar flies, for systems that must run in a fixed order with it.
+pub static UPDATE_BARFLIES: Label = Label("update_barflies");
+/// Label of the system delivering messages to the bar flies.
+pub static DELIVER_TO_BARFLIES: Label = Label("deliver_to_barflies");
+/// Label of the startup system spawning the bar flies.
+pub static INIT_BARFLIES: Label = Label("init_barflies");
+
+/// The miners drinking in the saloon this update.
+pub type Patrons = [Entity];
+
+pub type BarFlyStateData<'a> = (
+    &'a Name,
+    &'a mut BarFly,
+    &'a mut Outbox,
+    &'a Patrons,
+    &'a mut WorldRng,
+);
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct BarFly {
+    knocked_out: u32,
+    picked_on: Vec<Entity>,
+}
+
+impl BarFly {
+    pub fn new() -> Self {
+        BarFly {
+            knocked_out: 0,
+            picked_on: vec![],
+        }
+    }
+}
+
+impl MapEntities for BarFly {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        for miner in &mut self.picked_on {
+            *miner = entity_mapper.get_mapped(*miner);
+        }
+    }
+}
+
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum BarFlyState {
+    HangAroundSaloon,
+    Sprawled,
+}
+
+pub struct HangAroundSaloon;
+
+impl<'a> fsm::Handler<BarFlyState, BarFlyStateData<'a>, Telegram> for HangAroundSaloon {
+    fn update(
+        &self,
+        _state: &BarFlyState,
+        (name, barfly, outbox, patrons, rng): &mut BarFlyStateData,
+    ) -> fsm::StateTransition<BarFlyState> {
+        // each miner only gets picked on once a visit
+        barfly.picked_on.retain(|miner| patrons.contains(miner));
+        let newcomers: Vec<Entity> = patrons
+            .iter()
+            .filter(|miner| !barfly.picked_on.contains(miner))
+            .copied()
+            .collect();
+
+        if rng.gen::<f32>() < INSULT_CHANCE {
+            if let Some(&victim) = newcomers.choose(rng) {
+                info!("{}: Hey, ya mangy varmint! Yer mother was a mule", name);
+                outbox.dispatch(SEND_MSG_IMMEDIATELY, victim, Message::Insult);
+                barfly.picked_on.push(victim);
+            }
+        }
+
+        fsm::StateTransition::None
+    }
+
+    fn on_message(
+        &self,
+        _state: &BarFlyState,
+        (name, barfly, ..): &mut BarFlyStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<BarFlyState>> {
+        match telegram.message {
+            Message::Punch => {
+                info!("{}: Oof!", name);
+                barfly.knocked_out = KNOCKOUT_TIME;
+                Some(fsm::StateTransition::Push(BarFlyState::Sprawled))
+            }
+            _ => None,
+        }
+    }
+}
+
+pub struct Sprawled;
+
+impl<'a> fsm::Handler<BarFlyState, BarFlyStateData<'a>, Telegram> for Sprawled {
+    fn update(
+        &self,
+        _state: &BarFlyState,
+        (name, barfly, ..): &mut BarFlyStateData,
+    ) -> fsm::StateTransition<BarFlyState> {
+        if barfly.knocked_out == 0 {
+            return fsm::StateTransition::Pop;
+        }
+
+        barfly.knocked_out -= 1;
+        info!("{}: *lies on the saloon floor seein' stars*", name);
+        fsm::StateTransition::None
+    }
+
+    fn on_stop(&self, _state: &BarFlyState, (name, ..): &mut BarFlyStateData) {
+        info!(
+            "{}: Climbin' back onto mah stool. Ah'll get ya next time",
+            name
+        );
+    }
+
+    // there's no fight left in him
+    fn on_message(
+        &self,
+        _state: &BarFlyState,
+        _state_data: &mut BarFlyStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<BarFlyState>> {
+        match telegram.message {
+            Message::Punch => Some(fsm::StateTransition::None),
+            _ => None,
+        }
+    }
+}
+
+pub struct BarFlyHandler;
+
+impl<'a> fsm::Handler<BarFlyState, BarFlyStateData<'a>, Telegram> for BarFlyHandler {
+    fn on_start(&self, state: &BarFlyState, state_data: &mut BarFlyStateData<'a>) {
+        match state {
+            BarFlyState::HangAroundSaloon => HangAroundSaloon.on_start(state, state_data),
+            BarFlyState::Sprawled => Sprawled.on_start(state, state_data),
+        }
+    }
+
+    fn on_stop(&self, state: &BarFlyState, state_data: &mut BarFlyStateData<'a>) {
+        match state {
+            BarFlyState::HangAroundSaloon => HangAroundSaloon.on_stop(state, state_data),
+            BarFlyState::Sprawled => Sprawled.on_stop(state, state_data),
+        }
+    }
+
+    fn on_pause(&self, state: &BarFlyState, state_data: &mut BarFlyStateData<'a>) {
+        match state {
+            BarFlyState::HangAroundSaloon => HangAroundSaloon.on_pause(state, state_data),
+            BarFlyState::Sprawled => Sprawled.on_pause(state, state_data),
+        }
+    }
+
+    fn on_resume(&self, state: &BarFlyState, state_data: &mut BarFlyStateData<'a>) {
+        match state {
+            BarFlyState::HangAroundSaloon => HangAroundSaloon.on_resume(state, state_data),
+            BarFlyState::Sprawled => Sprawled.on_resume(state, state_data),
+        }
+    }
+
+    fn update(
+        &self,
+        state: &BarFlyState,
+        state_data: &mut BarFlyStateData<'a>,
+    ) -> fsm::StateTransition<BarFlyState> {
+        match state {
+            BarFlyState::HangAroundSaloon => HangAroundSaloon.update(state, state_data),
+            BarFlyState::Sprawled => Sprawled.update(state, state_data),
+        }
+    }
+
+    fn on_message(
+        &self,
+        state: &BarFlyState,
+        state_data: &mut BarFlyStateData<'a>,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<BarFlyState>> {
+        match state {
+            BarFlyState::HangAroundSaloon => {
+                HangAroundSaloon.on_message(state, state_data, telegram)
+            }
+            BarFlyState::Sprawled => Sprawled.on_message(state, state_data, telegram),
+        }
+    }
+}
+
+pub struct BarFlyPlugin;
+
+impl Plugin for BarFlyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scenario>();
+        app.add_systems(
+            Startup,
+            init_barflies
+                .in_set(INIT_BARFLIES)
+                .after(INIT_MINERS)
+                .after(INIT_PARTNERS),
+        );
+        // runs after the miners so it sees who has just walked into the saloon,
+        // and after the partners so seeded runs draw random numbers in a fixed order
+        app.add_plugins(
+            FsmPlugin::<BarFlyState, BarFlyHandler, BarFlyComponents>::new(
+                UPDATE_BARFLIES,
+                DELIVER_TO_BARFLIES,
+            )
+            .after(UPDATE_PARTNERS, DELIVER_TO_PARTNERS),
+        );
+    }
+}
+
+pub fn init_barflies(mut commands: Commands, scenario: Res<Scenario>) {
+    info!("initialising {} bar flies", scenario.barflies.len());
+    for spec in &scenario.barflies {
+        spawn_agent(
+            &mut commands,
+            &spec.name,
+            spec.location.unwrap_or(Location::Saloon),
+        )
+        .insert(BarFly::new())
+        .insert(fsm::StateStack::<BarFlyState>::new_initial_state(
+            spec.state.unwrap_or(BarFlyState::HangAroundSaloon),
+        ));
+    }
+}
+
+type Drinkers<'a> = (Entity, &'a Location);
+
+/// A bar fly's own components, besides his name and states.
+pub type BarFlyComponents = (&'static mut BarFly, &'static mut Outbox);
+
+type BarFlyTown = (
+    ResMut<'static, WorldRng>,
+    Query<'static, 'static, Drinkers<'static>, With<Miner>>,
+);
+
+impl AgentHandler<BarFlyState, BarFlyComponents> for BarFlyHandler {
+    type Town = BarFlyTown;
+
+    fn run(
+        (rng, miners): &mut SystemParamItem<BarFlyTown>,
+        name: &Name,
+        (mut barfly, mut outbox): QueryItem<BarFlyComponents>,
+        state_stack: &mut fsm::StateStack<BarFlyState>,
+        run: Run,
+    ) {
+        // only a bar fly taking his turn looks round at who's in, in the
+        // order they were spawned
+        let patrons: Vec<Entity> = match run {
+            Run::Update => miners
+                .iter()
+                .sort_by_key::<Entity, _>(|entity| entity.index_u32())
+                .filter(|(_, location)| **location == Location::Saloon)
+                .map(|(entity, _)| entity)
+                .collect(),
+            _ => vec![],
+        };
+        let mut state_data = (
+            name,
+            barfly.deref_mut(),
+            outbox.deref_mut(),
+            &patrons[..],
+            rng.deref_mut(),
+        );
+        run.on(&BarFlyHandler, state_stack, &mut state_data);
+    }
+}
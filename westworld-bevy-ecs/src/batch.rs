@@ -0,0 +1,232 @@
+//! Running many simulations side by side, headless, and summing up how the
+//! agents spent their time, for comparing one set of rules with another.
+
+use crate::bandit::BanditState;
+use crate::barfly::BarFlyState;
+use crate::cli::Cli;
+use crate::miner::{Miner, MinerState};
+use crate::partner::PartnerState;
+use crate::rng::Seed;
+use crate::scenario::Scenario;
+use crate::sheriff::SheriffState;
+use crate::{Location, Settings, WorldPlugins};
+use bevy_app::{App, FixedLast};
+use bevy_ecs::prelude::*;
+use fsm::StateStack;
+use game_ai::config::Config;
+use std::collections::{BTreeMap, HashMap};
+use std::fmt::{self, Debug};
+use std::num::NonZeroUsize;
+use std::thread;
+
+pub static DEFAULT_TICKS: u64 = 1000;
+static WEALTH_ROWS: usize = 10; // how many ticks to show the average wealth at
+
+/// `--batch=N` runs N simulations for `--ticks=T` ticks each (1000 by
+/// default) and prints a summary instead of the log. Run `i` is seeded with
+/// `--seed=S` plus `i`, so any one of them can be watched on its own.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Batch {
+    pub runs: u64,
+    pub ticks: u64,
+    pub seed: u64,
+}
+
+impl Batch {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        Some(Batch {
+            runs: cli.batch?,
+            ticks: cli.ticks.unwrap_or(DEFAULT_TICKS),
+            seed: Seed::from_cli(cli).0,
+        })
+    }
+
+    /// Runs the simulations across as many threads as there are cores.
+    pub fn run(&self, scenario: &Scenario, config: &Config) -> Stats {
+        let workers = thread::available_parallelism()
+            .map_or(1, NonZeroUsize::get)
+            .min(self.runs as usize)
+            .max(1);
+        let handles: Vec<_> = (0..workers)
+            .map(|worker| {
+                let batch = self.clone();
+                let scenario = scenario.clone();
+                let config = config.clone();
+                thread::spawn(move || {
+                    let mut stats = Stats::default();
+                    for run in (worker as u64..batch.runs).step_by(workers) {
+                        let seed = batch.seed.wrapping_add(run);
+                        stats.merge(simulate(&scenario, &config, seed, batch.ticks));
+                    }
+                    stats
+                })
+            })
+            .collect();
+
+        let mut stats = Stats::default();
+        for handle in handles {
+            stats.merge(handle.join().expect("a simulation panicked"));
+        }
+        stats
+    }
+}
+
+/// Runs one simulation for `ticks` updates on the current thread.
+pub fn simulate(scenario: &Scenario, config: &Config, seed: u64, ticks: u64) -> Stats {
+    let mut app = App::new();
+    app.insert_resource(scenario.clone())
+        .insert_resource(Settings(config.clone()))
+        .insert_resource(Seed(seed))
+        .init_resource::<Stats>()
+        .add_plugins(WorldPlugins)
+        .add_systems(FixedLast, collect_stats);
+    app.finish();
+    app.cleanup();
+    for _ in 0..ticks {
+        app.update();
+    }
+
+    let mut stats = app.world_mut().remove_resource::<Stats>().unwrap();
+    stats.runs = 1;
+    stats.miners = scenario.miners.len() as u64;
+    stats
+}
+
+/// What one or more runs added up to.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct Stats {
+    pub runs: u64,
+    pub miners: u64,
+    /// The gold carried and banked by all the miners, tick by tick.
+    pub wealth: Vec<i64>,
+    /// Ticks spent in each state, keyed by the kind of agent and the state
+    /// at the top of its stack.
+    pub states: BTreeMap<(&'static str, String), u64>,
+    pub saloon_visits: u64,
+}
+
+impl Stats {
+    pub fn merge(&mut self, other: Stats) {
+        self.runs += other.runs;
+        self.miners += other.miners;
+        if self.wealth.len() < other.wealth.len() {
+            self.wealth.resize(other.wealth.len(), 0);
+        }
+        for (total, wealth) in self.wealth.iter_mut().zip(other.wealth) {
+            *total += wealth;
+        }
+        for (state, ticks) in other.states {
+            *self.states.entry(state).or_default() += ticks;
+        }
+        self.saloon_visits += other.saloon_visits;
+    }
+
+    fn count_state<S: Clone + Debug>(&mut self, kind: &'static str, stack: &StateStack<S>) {
+        if let Some(state) = stack.last() {
+            *self
+                .states
+                .entry((kind, format!("{:?}", state)))
+                .or_default() += 1;
+        }
+    }
+}
+
+impl fmt::Display for Stats {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let miners = self.miners.max(1) as f64;
+
+        writeln!(f, "average wealth per miner")?;
+        let step = (self.wealth.len() / WEALTH_ROWS).max(1);
+        for tick in (step..=self.wealth.len()).step_by(step) {
+            let wealth = self.wealth[tick - 1] as f64 / miners;
+            writeln!(f, "  tick {:>6}  {:>8.1}", tick, wealth)?;
+        }
+
+        writeln!(f, "time spent per state")?;
+        let mut totals: HashMap<&str, u64> = HashMap::new();
+        for ((kind, _), ticks) in &self.states {
+            *totals.entry(kind).or_default() += ticks;
+        }
+        for ((kind, state), ticks) in &self.states {
+            let share = 100.0 * *ticks as f64 / totals[kind] as f64;
+            writeln!(f, "  {:<8} {:<28} {:>5.1}%", kind, state, share)?;
+        }
+
+        write!(
+            f,
+            "saloon visits: {:.1} per miner per run",
+            self.saloon_visits as f64 / miners
+        )
+    }
+}
+
+pub fn collect_stats(
+    mut stats: ResMut<Stats>,
+    mut last_seen: Local<HashMap<Entity, Location>>,
+    miners: Query<(Entity, &Miner, &Location, &StateStack<MinerState>)>,
+    partners: Query<&StateStack<PartnerState>>,
+    barflies: Query<&StateStack<BarFlyState>>,
+    bandits: Query<&StateStack<BanditState>>,
+    sheriffs: Query<&StateStack<SheriffState>>,
+) {
+    let mut wealth = 0;
+    for (entity, miner, location, stack) in miners.iter() {
+        wealth += i64::from(miner.gold() + miner.wealth());
+        stats.count_state("miner", stack);
+        let was = last_seen.insert(entity, *location);
+        if *location == Location::Saloon && was != Some(Location::Saloon) {
+            stats.saloon_visits += 1;
+        }
+    }
+    stats.wealth.push(wealth);
+    for stack in partners.iter() {
+        stats.count_state("partner", stack);
+    }
+    for stack in barflies.iter() {
+        stats.count_state("barfly", stack);
+    }
+    for stack in bandits.iter() {
+        stats.count_state("bandit", stack);
+    }
+    for stack in sheriffs.iter() {
+        stats.count_state("sheriff", stack);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn merging_adds_up_tick_by_tick() {
+        let mut a = Stats {
+            runs: 1,
+            miners: 1,
+            wealth: vec![1, 2],
+            saloon_visits: 1,
+            ..Stats::default()
+        };
+        a.states.insert(("miner", "QuenchThirst".to_string()), 2);
+        let mut b = a.clone();
+        b.wealth.push(3);
+
+        a.merge(b);
+        assert_eq!(a.runs, 2);
+        assert_eq!(a.wealth, vec![2, 4, 3]);
+        assert_eq!(a.states[&("miner", "QuenchThirst".to_string())], 4);
+        assert_eq!(a.saloon_visits, 2);
+    }
+
+    #[test]
+    fn simulation_counts_every_tick() {
+        let stats = simulate(&Scenario::default(), &Config::default(), 1, 48);
+        assert_eq!(stats.wealth.len(), 48);
+        let miner_ticks: u64 = stats
+            .states
+            .iter()
+            .filter(|((kind, _), _)| *kind == "miner")
+            .map(|(_, ticks)| ticks)
+            .sum();
+        assert_eq!(miner_ticks, 48);
+    }
+}
@@ -0,0 +1,135 @@
+//! Timing the miners updating all at once, across threads, against the same
+//! run with them updating one after another, with `--benchmark`. Both runs
+//! start from the same seed and have to leave the same world, so it's the
+//! threads that are timed and not some shortcut one run took.
+//!
+//! It takes a town of a few thousand miners for the threads to pay off:
+//!
+//! ```text
+//! westworld-bevy-ecs --benchmark --miners=5000 --partners=0 --ticks=100
+//! ```
+
+use crate::agents::ParallelAbove;
+use crate::cli::Cli;
+use crate::rerun::state_hash;
+use crate::rng::Seed;
+use crate::scenario::Scenario;
+use crate::{Settings, WorldPlugins};
+use bevy_app::App;
+use bevy_tasks::{ComputeTaskPool, TaskPool};
+use game_ai::config::Config;
+use std::fmt;
+use std::time::{Duration, Instant};
+
+pub static DEFAULT_TICKS: u64 = 100;
+
+/// `--benchmark` runs the town for `--ticks=T` ticks (100 by default) from
+/// `--seed=S` twice, the miners updating one after another and then all at
+/// once.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Benchmark {
+    pub ticks: u64,
+    pub seed: u64,
+}
+
+/// How long each run took, and on how many threads the second ran.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Timings {
+    pub ticks: u64,
+    pub in_turn: Duration,
+    pub at_once: Duration,
+    pub threads: usize,
+}
+
+impl Timings {
+    /// How many times faster the run with the miners all at once went.
+    pub fn speedup(&self) -> f64 {
+        self.in_turn.as_secs_f64() / self.at_once.as_secs_f64()
+    }
+}
+
+impl fmt::Display for Timings {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let per_tick = |run: Duration| run.as_secs_f64() * 1000.0 / self.ticks as f64;
+        writeln!(
+            f,
+            "one after another: {:.2} ms a tick",
+            per_tick(self.in_turn)
+        )?;
+        writeln!(
+            f,
+            "all at once:       {:.2} ms a tick across {} threads",
+            per_tick(self.at_once),
+            self.threads
+        )?;
+        write!(f, "{:.2}x as fast", self.speedup())
+    }
+}
+
+impl Benchmark {
+    /// Reads `--benchmark`, `--ticks=N` and `--seed=N`.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.benchmark.then(|| Benchmark {
+            ticks: cli.ticks.unwrap_or(DEFAULT_TICKS),
+            seed: Seed::from_cli(cli).0,
+        })
+    }
+
+    /// Times both runs, returning an error if they left different worlds.
+    pub fn run(&self, scenario: &Scenario, config: &Config) -> Result<Timings, String> {
+        let (in_turn, first) = self.time(scenario, config, ParallelAbove(usize::MAX));
+        let (at_once, second) = self.time(scenario, config, ParallelAbove(0));
+        if first != second {
+            return Err(format!(
+                "updating the miners at once left a different world: {:016x} rather than {:016x}",
+                second, first
+            ));
+        }
+        Ok(Timings {
+            ticks: self.ticks,
+            in_turn,
+            at_once,
+            threads: ComputeTaskPool::get_or_init(TaskPool::default).thread_num(),
+        })
+    }
+
+    /// Runs the town, updating the miners at once if there are more than
+    /// `above`, returning how long its ticks took and the hash of the world
+    /// it left.
+    fn time(&self, scenario: &Scenario, config: &Config, above: ParallelAbove) -> (Duration, u64) {
+        let mut app = App::new();
+        app.insert_resource(scenario.clone())
+            .insert_resource(Settings(config.clone()))
+            .insert_resource(Seed(self.seed))
+            .insert_resource(above)
+            .add_plugins(WorldPlugins);
+        app.finish();
+        app.cleanup();
+        let start = Instant::now();
+        for _ in 0..self.ticks {
+            app.update();
+        }
+        let elapsed = start.elapsed();
+        (elapsed, state_hash(app.world_mut()))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::population::Population;
+
+    #[test]
+    fn the_miners_at_once_leave_the_same_world() {
+        let benchmark = Benchmark {
+            ticks: 100,
+            seed: 3,
+        };
+        let town = Scenario::populated(&Population {
+            miners: 40,
+            partners: 10,
+        });
+        let timings = benchmark.run(&town, &Config::default()).unwrap();
+        assert_eq!(timings.ticks, 100);
+    }
+}
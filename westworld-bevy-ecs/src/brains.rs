@@ -0,0 +1,470 @@
+//! Miner states compiled to WebAssembly, for brains written by whoever likes
+//! in whatever compiles to it, run sandboxed so they can't touch anything
+//! but the miner they're running. Every `*.wasm` or `*.wat` file in the
+//! `--brains=DIR` directory is a state, [`MinerState::Brain`] with the
+//! number of its file in order of their names, exporting whichever of
+//! `on_start`, `update` and `on_stop` it needs, all taking and returning
+//! nothing.
+//!
+//! All a brain can do is call what the town gives it, imported from the
+//! `westworld` module:
+//!
+//! - `stat(which: i32) -> i32` reads the miner's gold (0), wealth (1),
+//!   thirst (2), fatigue (3) or hunger (4), or the day (5) or hour (6);
+//! - `switch(name: i32, len: i32)` and `push(name: i32, len: i32)` ask to go
+//!   on to the state named by the string at `name` in the brain's exported
+//!   `memory`, named as a scenario file would or as another brain by its
+//!   file's name, and `pop()` and `quit()` ask for those, the last asked for
+//!   being what happens;
+//! - `send(message: i32, len: i32)` sends his wife a [`Message`] by name.
+//!
+//! A brain keeps nothing from one call to the next, and is stopped if it
+//! runs on too long or grows its memory or tables past a small cap. One that goes wrong ends its state. Brains are only
+//! loaded built with `--features wasm-brains`; without it there are none,
+//! and a brain's state ends on its first update.
+
+#[cfg(feature = "wasm-brains")]
+use crate::cli::Cli;
+use crate::messaging::Telegram;
+use crate::miner::{MinerState, MinerStateData};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use fsm::StateTransition;
+use std::{error, fmt};
+
+#[cfg(feature = "wasm-brains")]
+use crate::messaging::{Message, SEND_MSG_IMMEDIATELY};
+#[cfg(feature = "wasm-brains")]
+use bevy_app::{App, Plugin};
+#[cfg(feature = "wasm-brains")]
+use std::path::{Path, PathBuf};
+#[cfg(feature = "wasm-brains")]
+use std::{fs, io};
+#[cfg(feature = "wasm-brains")]
+use wasmtime::{
+    Caller, Config, Engine, Extern, Instance, Linker, Module, Store, StoreLimits,
+    StoreLimitsBuilder,
+};
+
+/// How much a brain's allowed to do in one call before it's stopped.
+#[cfg(feature = "wasm-brains")]
+static FUEL: u64 = 100_000;
+/// The most memory a brain can have, in bytes: sixteen wasm pages.
+#[cfg(feature = "wasm-brains")]
+static MEMORY: usize = 16 * 65536;
+/// The most elements a brain's table can hold.
+#[cfg(feature = "wasm-brains")]
+static TABLE_ELEMENTS: usize = 1000;
+
+/// Errors raised while loading or running a brain.
+#[derive(Debug)]
+pub enum BrainError {
+    #[cfg(feature = "wasm-brains")]
+    Io(io::Error),
+    #[cfg(feature = "wasm-brains")]
+    Wasm(wasmtime::Error),
+    /// A brain asked for a state that there isn't.
+    #[cfg(feature = "wasm-brains")]
+    UnknownState(String),
+    /// A brain tried to send a message that there isn't.
+    #[cfg(feature = "wasm-brains")]
+    UnknownMessage(String),
+    NoSuchBrain(u16),
+}
+
+impl fmt::Display for BrainError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            #[cfg(feature = "wasm-brains")]
+            BrainError::Io(e) => write!(f, "brain i/o error: {}", e),
+            #[cfg(feature = "wasm-brains")]
+            BrainError::Wasm(e) => write!(f, "brain error: {:#}", e),
+            #[cfg(feature = "wasm-brains")]
+            BrainError::UnknownState(name) => write!(f, "no state called {:?}", name),
+            #[cfg(feature = "wasm-brains")]
+            BrainError::UnknownMessage(name) => write!(f, "no message called {:?}", name),
+            BrainError::NoSuchBrain(brain) => write!(f, "no brain number {}", brain),
+        }
+    }
+}
+
+impl error::Error for BrainError {}
+
+#[cfg(feature = "wasm-brains")]
+impl From<io::Error> for BrainError {
+    fn from(e: io::Error) -> Self {
+        BrainError::Io(e)
+    }
+}
+
+#[cfg(feature = "wasm-brains")]
+impl From<wasmtime::Error> for BrainError {
+    fn from(e: wasmtime::Error) -> Self {
+        BrainError::Wasm(e)
+    }
+}
+
+/// What a brain's been told, and what it's asked for, over one call.
+#[cfg(feature = "wasm-brains")]
+#[derive(Default)]
+struct Call {
+    stats: Vec<i32>,
+    asked: Option<Asked>,
+    sent: Vec<String>,
+    /// How far its memory and tables can grow.
+    limits: StoreLimits,
+}
+
+/// A transition a brain's asked for, before its state's been looked up.
+#[cfg(feature = "wasm-brains")]
+enum Asked {
+    Switch(String),
+    Push(String),
+    Pop,
+    Quit,
+}
+
+/// The string a brain's passed at `at` in its memory.
+#[cfg(feature = "wasm-brains")]
+fn string(caller: &mut Caller<'_, Call>, at: i32, len: i32) -> wasmtime::Result<String> {
+    let memory = caller
+        .get_export("memory")
+        .and_then(Extern::into_memory)
+        .ok_or_else(|| wasmtime::Error::msg("no memory exported"))?;
+    let bytes = memory
+        .data(&caller)
+        .get(at as usize..)
+        .and_then(|from| from.get(..len as usize))
+        .ok_or_else(|| wasmtime::Error::msg("string out of bounds"))?;
+    Ok(String::from_utf8_lossy(bytes).into_owned())
+}
+
+/// The compiled brains.
+#[derive(Resource, Default)]
+pub struct Brains {
+    /// Each brain's name, where its state's number says.
+    names: Vec<String>,
+    #[cfg(feature = "wasm-brains")]
+    engine: Option<Engine>,
+    #[cfg(feature = "wasm-brains")]
+    modules: Vec<Module>,
+}
+
+impl Brains {
+    /// The name of the brain running as `Brain(brain)`.
+    pub fn name(&self, brain: u16) -> Option<&str> {
+        self.names.get(brain as usize).map(String::as_str)
+    }
+
+    /// Runs a brain's `hook` for a miner, if it has one, sending what
+    /// messages it asks to and returning what it asks for next.
+    #[cfg(feature = "wasm-brains")]
+    pub fn call(
+        &self,
+        brain: u16,
+        hook: &str,
+        (_name, _location, miner, outbox, _economy, clock, ..): &mut MinerStateData,
+    ) -> Result<StateTransition<MinerState>, BrainError> {
+        let (engine, module) = match (&self.engine, self.modules.get(brain as usize)) {
+            (Some(engine), Some(module)) => (engine, module),
+            _ => return Err(BrainError::NoSuchBrain(brain)),
+        };
+        if module.get_export(hook).is_none() {
+            return Ok(StateTransition::None);
+        }
+        let stats = vec![
+            miner.gold(),
+            miner.wealth(),
+            miner.thirst(),
+            miner.fatigue(),
+            miner.hunger(),
+            clock.day() as i32,
+            clock.hour() as i32,
+        ];
+        let limits = StoreLimitsBuilder::new()
+            .memory_size(MEMORY)
+            .table_elements(TABLE_ELEMENTS)
+            .instances(1)
+            .memories(1)
+            .tables(1)
+            .trap_on_grow_failure(true)
+            .build();
+        let mut store = Store::new(
+            engine,
+            Call {
+                stats,
+                limits,
+                ..Default::default()
+            },
+        );
+        store.limiter(|call| &mut call.limits);
+        store.set_fuel(FUEL)?;
+        let instance = self.linker(engine)?.instantiate(&mut store, module)?;
+        call_hook(&instance, &mut store, hook)?;
+
+        let Call { asked, sent, .. } = store.into_data();
+        for message in sent {
+            let message: Message =
+                ron::from_str(&message).map_err(|_| BrainError::UnknownMessage(message))?;
+            if let Some(wife) = miner.wife {
+                outbox.dispatch(SEND_MSG_IMMEDIATELY, wife, message);
+            }
+        }
+        Ok(match asked {
+            None => StateTransition::None,
+            Some(Asked::Switch(name)) => StateTransition::Switch(self.state(&name)?),
+            Some(Asked::Push(name)) => StateTransition::Push(self.state(&name)?),
+            Some(Asked::Pop) => StateTransition::Pop,
+            Some(Asked::Quit) => StateTransition::Quit,
+        })
+    }
+
+    #[cfg(not(feature = "wasm-brains"))]
+    pub fn call(
+        &self,
+        brain: u16,
+        _hook: &str,
+        _state_data: &mut MinerStateData,
+    ) -> Result<StateTransition<MinerState>, BrainError> {
+        Err(BrainError::NoSuchBrain(brain))
+    }
+
+    /// Everything the town gives a brain to call.
+    #[cfg(feature = "wasm-brains")]
+    fn linker(&self, engine: &Engine) -> wasmtime::Result<Linker<Call>> {
+        let mut linker = Linker::new(engine);
+        linker.func_wrap(
+            "westworld",
+            "stat",
+            |caller: Caller<'_, Call>, which: i32| {
+                caller
+                    .data()
+                    .stats
+                    .get(which as usize)
+                    .copied()
+                    .unwrap_or(0)
+            },
+        )?;
+        linker.func_wrap(
+            "westworld",
+            "switch",
+            |mut caller: Caller<'_, Call>, at: i32, len: i32| {
+                caller.data_mut().asked = Some(Asked::Switch(string(&mut caller, at, len)?));
+                Ok(())
+            },
+        )?;
+        linker.func_wrap(
+            "westworld",
+            "push",
+            |mut caller: Caller<'_, Call>, at: i32, len: i32| {
+                caller.data_mut().asked = Some(Asked::Push(string(&mut caller, at, len)?));
+                Ok(())
+            },
+        )?;
+        linker.func_wrap("westworld", "pop", |mut caller: Caller<'_, Call>| {
+            caller.data_mut().asked = Some(Asked::Pop);
+        })?;
+        linker.func_wrap("westworld", "quit", |mut caller: Caller<'_, Call>| {
+            caller.data_mut().asked = Some(Asked::Quit);
+        })?;
+        linker.func_wrap(
+            "westworld",
+            "send",
+            |mut caller: Caller<'_, Call>, at: i32, len: i32| {
+                let message = string(&mut caller, at, len)?;
+                caller.data_mut().sent.push(message);
+                Ok(())
+            },
+        )?;
+        Ok(linker)
+    }
+
+    /// The state called `name`: one of the brains, or else one of
+    /// [`MinerState`]'s.
+    #[cfg(feature = "wasm-brains")]
+    fn state(&self, name: &str) -> Result<MinerState, BrainError> {
+        match self.names.iter().position(|brain| brain == name) {
+            Some(brain) => Ok(MinerState::Brain(brain as u16)),
+            None => ron::from_str(name).map_err(|_| BrainError::UnknownState(name.to_string())),
+        }
+    }
+
+    /// Compiles `wasm`, in either the binary or the text format, as the next
+    /// brain, called `name`.
+    #[cfg(feature = "wasm-brains")]
+    pub fn add(&mut self, name: &str, wasm: &[u8]) -> Result<u16, BrainError> {
+        let engine = match &self.engine {
+            Some(engine) => engine.clone(),
+            None => {
+                let mut config = Config::new();
+                config.consume_fuel(true);
+                let engine = Engine::new(&config)?;
+                self.engine = Some(engine.clone());
+                engine
+            }
+        };
+        self.modules.push(Module::new(&engine, wasm)?);
+        self.names.push(name.to_string());
+        Ok(self.names.len() as u16 - 1)
+    }
+
+    /// Loads every `*.wasm` and `*.wat` file in `dir`, in order of their
+    /// names.
+    #[cfg(feature = "wasm-brains")]
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, BrainError> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        paths.retain(|path| {
+            path.extension()
+                .is_some_and(|extension| extension == "wasm" || extension == "wat")
+        });
+        paths.sort();
+        let mut brains = Brains::default();
+        for path in paths {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            brains.add(&name, &fs::read(&path)?)?;
+        }
+        Ok(brains)
+    }
+}
+
+/// Calls the brain's `hook`.
+#[cfg(feature = "wasm-brains")]
+fn call_hook(instance: &Instance, store: &mut Store<Call>, hook: &str) -> wasmtime::Result<()> {
+    instance
+        .get_typed_func::<(), ()>(&mut *store, hook)?
+        .call(store, ())
+}
+
+/// A state run by one of the brains.
+pub struct Brain(pub u16);
+
+impl Brain {
+    fn run(&self, hook: &str, state_data: &mut MinerStateData) -> StateTransition<MinerState> {
+        let brains = state_data.8;
+        brains.call(self.0, hook, state_data).unwrap_or_else(|e| {
+            let brain = brains.name(self.0).unwrap_or("?");
+            warn!("{}: {} in {}: {}", state_data.0, hook, brain, e);
+            StateTransition::Pop
+        })
+    }
+}
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for Brain {
+    fn on_start(&self, _state: &MinerState, state_data: &mut MinerStateData) {
+        self.run("on_start", state_data);
+    }
+
+    fn update(
+        &self,
+        _state: &MinerState,
+        state_data: &mut MinerStateData,
+    ) -> StateTransition<MinerState> {
+        self.run("update", state_data)
+    }
+
+    fn on_stop(&self, _state: &MinerState, state_data: &mut MinerStateData) {
+        self.run("on_stop", state_data);
+    }
+}
+
+/// Loads the brains with `--brains=DIR`.
+#[cfg(feature = "wasm-brains")]
+pub struct BrainsPlugin(pub PathBuf);
+
+#[cfg(feature = "wasm-brains")]
+impl BrainsPlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.brains.clone().map(BrainsPlugin)
+    }
+}
+
+#[cfg(feature = "wasm-brains")]
+impl Plugin for BrainsPlugin {
+    fn build(&self, app: &mut App) {
+        let brains = Brains::load(&self.0)
+            .unwrap_or_else(|e| panic!("could not load {}: {}", self.0.display(), e));
+        info!("loaded {} brains", brains.names.len());
+        app.insert_resource(brains);
+    }
+}
+
+#[cfg(all(test, feature = "wasm-brains"))]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+    use crate::testing::{build_test_app, tick};
+    use crate::Location;
+    use fsm::StateStack;
+
+    /// Miner Bob in the first brain's state, with `brains` for his states.
+    fn brained_miner(brains: &[(&str, &str)]) -> App {
+        let scenario =
+            Scenario::from_ron_str(r#"(miners: [(name: "Miner Bob", state: Brain(0))])"#).unwrap();
+        let mut app = build_test_app(scenario, 1);
+        let mut loaded = Brains::default();
+        for (name, wat) in brains {
+            loaded.add(name, wat.as_bytes()).unwrap();
+        }
+        app.insert_resource(loaded);
+        app
+    }
+
+    fn bobs_states(app: &mut App) -> Vec<MinerState> {
+        let states = app
+            .world_mut()
+            .query::<&StateStack<MinerState>>()
+            .single(app.world())
+            .unwrap();
+        states.iter().copied().collect()
+    }
+
+    #[test]
+    fn a_brain_says_what_the_miner_does_next() {
+        let mut app = brained_miner(&[("lie_in", include_str!("../brains/lie_in.wat"))]);
+        tick(&mut app, 3);
+        assert_eq!(bobs_states(&mut app), vec![MinerState::Brain(0)]);
+        // at ten he's off to work, walking there first
+        tick(&mut app, 1);
+        assert_eq!(
+            bobs_states(&mut app),
+            vec![
+                MinerState::EnterMineAndDigForNugget,
+                MinerState::TravelTo(Location::Goldmine)
+            ]
+        );
+    }
+
+    #[test]
+    fn a_brain_that_runs_on_is_stopped() {
+        let mut app = brained_miner(&[(
+            "forever",
+            r#"(module (func (export "update") (loop (br 0))))"#,
+        )]);
+        tick(&mut app, 1);
+        assert_eq!(bobs_states(&mut app), vec![]);
+    }
+
+    #[test]
+    fn a_brain_that_grows_too_big_is_stopped() {
+        let mut app = brained_miner(&[(
+            "greedy",
+            r#"(module
+                (memory (export "memory") 1)
+                (func (export "update") (drop (memory.grow (i32.const 100)))))"#,
+        )]);
+        tick(&mut app, 1);
+        assert_eq!(bobs_states(&mut app), vec![]);
+    }
+
+    #[test]
+    fn a_brain_starting_too_big_is_stopped() {
+        let mut app = brained_miner(&[(
+            "bloated",
+            r#"(module (memory (export "memory") 100) (func (export "update")))"#,
+        )]);
+        tick(&mut app, 1);
+        assert_eq!(bobs_states(&mut app), vec![]);
+    }
+}
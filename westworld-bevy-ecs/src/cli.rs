@@ -0,0 +1,221 @@
+//! The command line, with `--help` saying what's on it. Every plugin that
+//! can be asked for from the command line takes what it needs from [`Cli`]
+//! with its own `from_cli`.
+
+use bevy_app::App;
+use bevy_log::tracing_subscriber::fmt;
+use bevy_log::{BoxedFmtLayer, LogPlugin};
+use clap::{Parser, ValueEnum};
+use game_ai::config::{Config, ConfigError};
+use std::io;
+use std::path::PathBuf;
+
+/// Westworld's miners and their wives in bevy's ECS, a tick an hour.
+#[derive(Parser, Clone, Debug, PartialEq)]
+#[command(version)]
+pub struct Cli {
+    /// The town to start with, rather than one of --miners and --partners.
+    #[arg(long, value_name = "FILE")]
+    pub scenario: Option<PathBuf>,
+    /// How many miners there are, without a scenario.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub miners: usize,
+    /// How many miners' wives there are, without a scenario.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub partners: usize,
+    /// The tuning, which any fields left out of keep their defaults.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+    /// Seeds the run, a random seed without it.
+    #[arg(long, value_name = "N")]
+    pub seed: Option<u64>,
+
+    /// Milliseconds between ticks, the config's tick_interval_ms by default.
+    #[arg(
+        long,
+        value_name = "MS",
+        conflicts_with = "hz",
+        value_parser = clap::value_parser!(u64).range(1..)
+    )]
+    pub tick_ms: Option<u64>,
+    /// Ticks a second, rather than --tick-ms, up to one a nanosecond.
+    #[arg(
+        long,
+        value_name = "N",
+        value_parser = clap::value_parser!(u32).range(1..=1_000_000_000)
+    )]
+    pub hz: Option<u32>,
+    /// Frames a second, for reading the keyboard and redrawing.
+    #[arg(
+        long,
+        value_name = "N",
+        default_value_t = 60,
+        value_parser = clap::value_parser!(u32).range(1..)
+    )]
+    pub fps: u32,
+    /// Stops after this many ticks.
+    #[arg(long, value_name = "N")]
+    pub ticks: Option<u64>,
+    /// Runs flat out, with no keyboard controls.
+    #[arg(long)]
+    pub fast: bool,
+    /// No keyboard controls, at the usual pace.
+    #[arg(long)]
+    pub headless: bool,
+    /// Shows the dashboard in the terminal rather than the log.
+    #[arg(long)]
+    pub tui: bool,
+    /// How the log's written.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = LogFormat::Full)]
+    pub log_format: LogFormat,
+    /// Writes everyone's states to FILE as a Chrome trace, to open in Perfetto.
+    #[arg(long, value_name = "FILE")]
+    pub trace: Option<PathBuf>,
+    /// Writes what everyone says to FILE as well, a blank line after each
+    /// tick, to compare runs against.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["trace", "tui"])]
+    pub transcript: Option<PathBuf>,
+
+    /// Runs the town this many times across all cores, printing a summary.
+    #[arg(long, value_name = "RUNS")]
+    pub batch: Option<u64>,
+    /// Runs the town twice side by side, checking both go the same way.
+    #[arg(long, conflicts_with = "batch")]
+    pub verify: bool,
+    /// Saves and loads the second run's world after TICK ticks, checking it
+    /// carries on the same.
+    #[arg(long, value_name = "TICK", requires = "verify")]
+    pub verify_reload: Option<u64>,
+    /// Times the miners updating all at once across threads against one
+    /// after another, checking both runs leave the same world.
+    #[arg(long, conflicts_with_all = ["batch", "verify"])]
+    pub benchmark: bool,
+    /// Starts from a saved bevy scene.
+    #[arg(long, value_name = "FILE")]
+    pub scene: Option<PathBuf>,
+    /// Records the run, to play back with --replay.
+    #[arg(long, value_name = "FILE", conflicts_with = "replay")]
+    pub record: Option<PathBuf>,
+    /// Plays back a recorded run, checking it goes the same way.
+    #[arg(long, value_name = "FILE")]
+    pub replay: Option<PathBuf>,
+    /// Records the run's seed, config, telegrams sent in and random numbers,
+    /// to run it again with --rerun.
+    #[arg(long, value_name = "FILE", conflicts_with_all = ["rerun", "load", "scene"])]
+    pub record_inputs: Option<PathBuf>,
+    /// Runs a run recorded with --record-inputs again, checking it ends the
+    /// same.
+    #[arg(
+        long,
+        value_name = "FILE",
+        conflicts_with_all = ["replay", "record", "load", "scene", "control", "watch"]
+    )]
+    pub rerun: Option<PathBuf>,
+    /// Writes the run's events, a JSON object a line.
+    #[arg(long, value_name = "FILE")]
+    pub events: Option<PathBuf>,
+    /// Keeps metrics, summed up at the end of the run.
+    #[arg(long)]
+    pub metrics: bool,
+    /// Serves the metrics for Prometheus to scrape.
+    #[cfg(feature = "prometheus")]
+    #[arg(long, value_name = "ADDRESS")]
+    pub prometheus: Option<String>,
+    /// Streams the town over a WebSocket, with a page to watch it on.
+    #[cfg(feature = "websocket")]
+    #[arg(long, value_name = "ADDRESS")]
+    pub serve: Option<String>,
+    /// Takes requests to pause, step, change the speed, send telegrams and
+    /// change the config, a line of JSON each.
+    #[arg(long, value_name = "ADDRESS")]
+    pub control: Option<String>,
+    /// Logs how hard the AI's working every second.
+    #[arg(long)]
+    pub diagnostics: bool,
+    /// Resumes a saved world.
+    #[arg(long, value_name = "FILE")]
+    pub load: Option<PathBuf>,
+    /// Saves the world once the run's over.
+    #[arg(long, value_name = "FILE")]
+    pub save: Option<PathBuf>,
+    /// Loads the scripted states from the scripts in here.
+    #[arg(long, value_name = "DIR")]
+    pub scripts: Option<PathBuf>,
+    /// Loads the brains compiled to WebAssembly in here.
+    #[cfg(feature = "wasm-brains")]
+    #[arg(long, value_name = "DIR")]
+    pub brains: Option<PathBuf>,
+    /// Loads the config and the scripts again whenever they're saved.
+    #[arg(long)]
+    pub watch: bool,
+    /// Opens a window onto the agents, to watch and tweak them live.
+    #[cfg(feature = "inspector")]
+    #[arg(long)]
+    pub inspect: bool,
+}
+
+impl Cli {
+    /// The `--config` file, or the defaults without one.
+    pub fn config(&self) -> Result<Config, ConfigError> {
+        match &self.config {
+            Some(path) => Config::load(path),
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+/// How the log's written.
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq)]
+pub enum LogFormat {
+    /// Bevy's, with the time, level and where it's from.
+    #[default]
+    Full,
+    /// Tracing's compact lines.
+    Compact,
+    /// Just the level and the message, to diff one run against another.
+    Plain,
+}
+
+impl LogFormat {
+    pub fn plugin(self) -> LogPlugin {
+        let fmt_layer: fn(&mut App) -> Option<BoxedFmtLayer> = match self {
+            LogFormat::Full => |_| None,
+            LogFormat::Compact => |_| {
+                Some(Box::new(
+                    fmt::Layer::default().compact().with_writer(io::stderr),
+                ))
+            },
+            LogFormat::Plain => |_| {
+                Some(Box::new(
+                    fmt::Layer::default()
+                        .without_time()
+                        .with_target(false)
+                        .with_ansi(false)
+                        .with_writer(io::stderr),
+                ))
+            },
+        };
+        LogPlugin {
+            fmt_layer,
+            ..LogPlugin::default()
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn flags_take_an_equals_or_a_space() {
+        let cli = Cli::try_parse_from(["westworld", "--seed=42", "--miners", "3"]).unwrap();
+        assert_eq!((cli.seed, cli.miners, cli.partners), (Some(42), 3, 1));
+    }
+
+    #[test]
+    fn typos_and_bad_numbers_are_refused() {
+        assert!(Cli::try_parse_from(["westworld", "--seeds=42"]).is_err());
+        assert!(Cli::try_parse_from(["westworld", "--ticks=lots"]).is_err());
+        assert!(Cli::try_parse_from(["westworld", "--hz=2", "--tick-ms=50"]).is_err());
+    }
+}
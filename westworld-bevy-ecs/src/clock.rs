@@ -0,0 +1,187 @@
+use bevy_app::{App, FixedFirst, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+pub static HOURS_PER_DAY: u64 = 24;
+
+/// The time of day in Westworld, advanced once per tick.
+/// Insert your own before adding [`ClockPlugin`] to change the pace or the
+/// time the simulation starts.
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct Clock {
+    ticks: u64,
+    ticks_per_hour: u64,
+}
+
+impl Clock {
+    pub fn new(ticks_per_hour: u64) -> Self {
+        assert!(
+            ticks_per_hour > 0,
+            "the clock must have at least one tick per hour"
+        );
+        Clock {
+            ticks: 0,
+            ticks_per_hour,
+        }
+    }
+
+    /// A clock showing `hour` o'clock on the first day.
+    pub fn starting_at(ticks_per_hour: u64, hour: u64) -> Self {
+        let mut clock = Clock::new(ticks_per_hour);
+        clock.ticks = (hour % HOURS_PER_DAY) * ticks_per_hour;
+        clock
+    }
+
+    pub fn tick(&mut self) {
+        self.ticks += 1;
+    }
+
+    /// The hour of the day, from 0 to 23.
+    pub fn hour(&self) -> u64 {
+        (self.ticks / self.ticks_per_hour) % HOURS_PER_DAY
+    }
+
+    /// Days since the clock started, counting from 0.
+    pub fn day(&self) -> u64 {
+        self.ticks / self.ticks_per_hour / HOURS_PER_DAY
+    }
+
+    /// True on the tick a new hour begins.
+    pub fn on_the_hour(&self) -> bool {
+        self.ticks.is_multiple_of(self.ticks_per_hour)
+    }
+
+    /// The hours gone by this tick: one on the tick a new hour begins, and
+    /// none the rest of the hour. Whatever changes by the hour changes by
+    /// this much a tick, so it keeps the same pace however many ticks there
+    /// are to an hour.
+    pub fn hours_passed(&self) -> i32 {
+        if self.on_the_hour() {
+            1
+        } else {
+            0
+        }
+    }
+
+    /// Ten at night until six in the morning, when decent folk are abed.
+    pub fn is_night(&self) -> bool {
+        !(6..22).contains(&self.hour())
+    }
+
+    pub fn mine_open(&self) -> bool {
+        (6..18).contains(&self.hour())
+    }
+
+    /// Every seventh day is the Sabbath, with a meetin' at the church from six
+    /// in the evening until bedtime.
+    pub fn church_service(&self) -> bool {
+        self.day() % 7 == 6 && (18..22).contains(&self.hour())
+    }
+
+    /// The saloon only opens its doors in the evening.
+    pub fn saloon_open(&self) -> bool {
+        self.hour() >= 17
+    }
+}
+
+impl Default for Clock {
+    fn default() -> Self {
+        Clock::starting_at(1, 6)
+    }
+}
+
+impl fmt::Display for Clock {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "day {}, {:02}:00", self.day() + 1, self.hour())
+    }
+}
+
+pub fn advance_clock(mut clock: ResMut<Clock>) {
+    clock.tick();
+    if clock.on_the_hour() {
+        match clock.hour() {
+            6 => info!("Sun's up on day {}", clock.day() + 1),
+            22 => info!("Night falls on day {}", clock.day() + 1),
+            _ => (),
+        }
+    }
+}
+
+pub struct ClockPlugin;
+
+impl Plugin for ClockPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Clock>();
+        app.add_systems(FixedFirst, advance_clock);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn ticks_roll_over_into_hours_and_days() {
+        let mut clock = Clock::starting_at(2, 23);
+        assert_eq!(clock.hour(), 23);
+        assert_eq!(clock.day(), 0);
+
+        clock.tick();
+        assert_eq!(clock.hour(), 23);
+        assert!(!clock.on_the_hour());
+
+        clock.tick();
+        assert_eq!(clock.hour(), 0);
+        assert_eq!(clock.day(), 1);
+        assert!(clock.on_the_hour());
+    }
+
+    #[test]
+    fn hours_pass_at_the_same_pace_however_fast_the_clock_ticks() {
+        for ticks_per_hour in [1, 2, 4] {
+            let mut clock = Clock::starting_at(ticks_per_hour, 8);
+            let hours: i32 = (0..3 * ticks_per_hour)
+                .map(|_| {
+                    clock.tick();
+                    clock.hours_passed()
+                })
+                .sum();
+            assert_eq!(hours, 3, "at {} ticks an hour", ticks_per_hour);
+        }
+    }
+
+    #[test]
+    fn opening_hours() {
+        let noon = Clock::starting_at(1, 12);
+        assert!(noon.mine_open());
+        assert!(!noon.saloon_open());
+        assert!(!noon.is_night());
+
+        let evening = Clock::starting_at(1, 19);
+        assert!(!evening.mine_open());
+        assert!(evening.saloon_open());
+        assert!(!evening.is_night());
+
+        let small_hours = Clock::starting_at(1, 3);
+        assert!(!small_hours.mine_open());
+        assert!(!small_hours.saloon_open());
+        assert!(small_hours.is_night());
+    }
+
+    #[test]
+    fn church_is_sunday_evenings() {
+        let mut clock = Clock::starting_at(1, 19);
+        assert!(!clock.church_service());
+        for _ in 0..6 * HOURS_PER_DAY {
+            clock.tick();
+        }
+        assert_eq!(clock.day(), 6);
+        assert!(clock.church_service());
+        for _ in 0..3 {
+            clock.tick();
+        }
+        assert!(!clock.church_service());
+    }
+}
@@ -0,0 +1,211 @@
+//! Keyboard controls for the simulation, read a line at a time from stdin on
+//! a background thread and applied once a frame, pausing and stepping the
+//! fixed timestep the agents update on.
+//!
+//! - space (or just Enter) pauses and resumes
+//! - `n` steps one tick, pausing if running
+//! - `0.25x`, `1x`, `10x` or `max` changes the [`Speed`]
+//! - `q` quits
+
+use crate::cli::Cli;
+use crate::timestep::Speed;
+use bevy_app::{App, AppExit, First, Plugin, Startup};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use bevy_time::{Fixed, Time, TimeSystems, Virtual};
+use game_ai::config::Config;
+use std::io::{stdin, BufRead};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+use std::thread;
+use std::time::Duration;
+
+pub static HELP: &str = "Controls: space to pause/resume, n to step, 0.25x/1x/10x/max for the speed, q to quit (then Enter)";
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Command {
+    TogglePause,
+    Step,
+    Speed(Speed),
+    Quit,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Option<Command> {
+        match line.trim() {
+            "" => Some(Command::TogglePause),
+            "n" => Some(Command::Step),
+            "q" => Some(Command::Quit),
+            line => line.parse().ok().map(Command::Speed),
+        }
+    }
+}
+
+/// Lines of input waiting to be read, from stdin unless another source is
+/// inserted before adding [`ControlsPlugin`].
+#[derive(Resource)]
+pub struct Input(Mutex<Receiver<String>>);
+
+impl Input {
+    pub fn new(lines: Receiver<String>) -> Self {
+        Input(Mutex::new(lines))
+    }
+
+    /// Starts reading stdin in the background.
+    pub fn from_stdin() -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+        Input::new(lines)
+    }
+}
+
+/// Pausing stops the virtual clock, so no more ticks fall due until it's
+/// resumed; stepping pauses and adds exactly one tick's worth of time.
+pub fn read_input(
+    input: Res<Input>,
+    mut time: ResMut<Time<Virtual>>,
+    mut fixed: ResMut<Time<Fixed>>,
+    mut speed: Option<ResMut<Speed>>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    let lines = input.0.lock().unwrap();
+    while let Ok(line) = lines.try_recv() {
+        match Command::parse(&line) {
+            Some(Command::TogglePause) => {
+                if time.is_paused() {
+                    time.unpause();
+                    info!("resumed");
+                } else {
+                    time.pause();
+                    info!("paused");
+                }
+            }
+            // one tick per step, the rest wait for the following frames
+            Some(Command::Step) => {
+                time.pause();
+                let timestep = fixed.timestep();
+                fixed.accumulate_overstep(timestep);
+                break;
+            }
+            Some(Command::Speed(to)) => match speed.as_mut() {
+                Some(speed) => **speed = to,
+                None => info!("the speed's fixed running flat out"),
+            },
+            Some(Command::Quit) => {
+                exit.write(AppExit::Success);
+            }
+            None => info!("{}", HELP),
+        }
+    }
+}
+
+pub fn log_help() {
+    info!("{}", HELP);
+}
+
+pub struct ControlsPlugin;
+
+impl Plugin for ControlsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.world().contains_resource::<Input>() {
+            app.insert_resource(Input::from_stdin());
+        }
+        // before the time's updated, so a pause takes effect this frame
+        app.add_systems(First, read_input.before(TimeSystems));
+        app.add_systems(Startup, log_help);
+    }
+}
+
+/// How fast the simulation runs: a tick every `--tick-ms=N` milliseconds, or
+/// `--hz=N` ticks a second (the config's `tick_interval_ms` by default),
+/// stopping after `--ticks=N` if given. `--fast` runs headless, one tick per
+/// update with no waiting in between, and no keyboard controls.
+///
+/// Otherwise the app updates `--fps=N` times a second, 60 by default, to
+/// read the keyboard and redraw the dashboard, idling in between.
+#[derive(Resource, Clone, Debug, PartialEq)]
+pub struct Pace {
+    pub interval: Duration,
+    pub ticks: Option<u64>,
+    pub fast: bool,
+    pub frame: Duration,
+}
+
+impl Pace {
+    pub fn from_cli(config: &Config, cli: &Cli) -> Self {
+        let interval = match (cli.tick_ms, cli.hz) {
+            (Some(ms), _) => Duration::from_millis(ms),
+            (None, Some(hz)) => Duration::from_secs(1) / hz,
+            (None, None) => config.tick_interval(),
+        };
+        // the rates are at least one, as the arguments are checked when parsed
+        Pace {
+            interval,
+            ticks: cli.ticks,
+            fast: cli.fast,
+            frame: Duration::from_secs(1) / cli.fps,
+        }
+    }
+
+    /// Whether to stop before running tick number `tick`, counting from 0.
+    pub fn finished(&self, tick: u64) -> bool {
+        self.ticks.is_some_and(|ticks| tick >= ticks)
+    }
+}
+
+impl Default for Pace {
+    fn default() -> Self {
+        Pace {
+            interval: Duration::from_millis(800),
+            ticks: None,
+            fast: false,
+            frame: Duration::from_secs(1) / 60,
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn parse_commands() {
+        assert_eq!(Command::parse(" "), Some(Command::TogglePause));
+        assert_eq!(Command::parse(""), Some(Command::TogglePause));
+        assert_eq!(Command::parse("n"), Some(Command::Step));
+        assert_eq!(Command::parse("q\r"), Some(Command::Quit));
+        assert_eq!(Command::parse("10x"), Some(Command::Speed(Speed::Tenfold)));
+        assert_eq!(Command::parse("max"), Some(Command::Speed(Speed::Max)));
+        assert_eq!(Command::parse("x"), None);
+    }
+
+    #[test]
+    fn pace_from_cli() {
+        let cli = Cli::parse_from(["westworld", "--tick-ms=50", "--ticks=3", "--fps=20"]);
+        let pace = Pace::from_cli(&Config::default(), &cli);
+        assert_eq!(pace.interval, Duration::from_millis(50));
+        assert_eq!(pace.frame, Duration::from_millis(50));
+        assert!(!pace.finished(2));
+        assert!(pace.finished(3));
+
+        let cli = Cli::parse_from(["westworld", "--hz=20", "--fast"]);
+        let fast = Pace::from_cli(&Config::default(), &cli);
+        assert_eq!(fast.interval, Duration::from_millis(50));
+        assert!(fast.fast);
+        assert!(!fast.finished(u64::MAX));
+    }
+
+    #[test]
+    fn rates_that_would_never_tick_are_refused() {
+        for arg in ["--tick-ms=0", "--hz=0", "--fps=0", "--hz=2000000000"] {
+            assert!(Cli::try_parse_from(["westworld", arg]).is_err(), "{}", arg);
+        }
+    }
+}
@@ -0,0 +1,144 @@
+//! The state on top of each agent's stack as a component of its own,
+//! [`CurrentState`], so that systems wanting to react to an agent changing
+//! what it's doing can filter on `Changed<CurrentState<MinerState>>` rather
+//! than looking through the stack every tick.
+
+use crate::bandit::BanditState;
+use crate::barfly::BarFlyState;
+use crate::miner::MinerState;
+use crate::partner::PartnerState;
+use crate::sheriff::SheriffState;
+use crate::{Label, Phase};
+use bevy_app::{App, FixedUpdate, Plugin, PostStartup};
+use bevy_ecs::prelude::*;
+use bevy_reflect::{Reflect, TypePath};
+use fsm::StateStack;
+// for the derived impls, which expect the 2021 prelude
+use std::iter::FromIterator;
+
+/// Label of the systems keeping [`CurrentState`] up to date, for systems
+/// reacting to it to run after.
+pub static TRACK_CURRENT_STATE: Label = Label("track_current_state");
+
+/// The state on top of an agent's [`StateStack`], changed only when a
+/// different state comes to the top, and removed when the stack's empty.
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct CurrentState<S: Clone + PartialEq + Reflect + TypePath>(pub S);
+
+/// Agents of any kind with a state on their stacks, who are still running.
+pub type Running = Or<(
+    With<CurrentState<MinerState>>,
+    With<CurrentState<PartnerState>>,
+    With<CurrentState<BarFlyState>>,
+    With<CurrentState<BanditState>>,
+    With<CurrentState<SheriffState>>,
+)>;
+
+type Currents<'a, S> = (Entity, &'a StateStack<S>, Option<&'a mut CurrentState<S>>);
+
+/// Copies the top of every stack that's changed, leaving [`CurrentState`]
+/// untouched when the same state's still on top.
+pub fn track_current_state<S: Clone + PartialEq + Reflect + TypePath>(
+    mut commands: Commands,
+    mut stacks: Query<Currents<S>, Changed<StateStack<S>>>,
+) {
+    for (entity, stack, current) in stacks.iter_mut() {
+        match (stack.last(), current) {
+            (Some(state), Some(mut current)) => {
+                current.set_if_neq(CurrentState(state.clone()));
+            }
+            (Some(state), None) => {
+                commands.entity(entity).insert(CurrentState(state.clone()));
+            }
+            (None, Some(_)) => {
+                commands.entity(entity).remove::<CurrentState<S>>();
+            }
+            (None, None) => {}
+        }
+    }
+}
+
+pub struct CurrentStatePlugin;
+
+impl Plugin for CurrentStatePlugin {
+    fn build(&self, app: &mut App) {
+        // in the order the agents are spawned, like their turns
+        let track = || {
+            (
+                track_current_state::<MinerState>,
+                track_current_state::<PartnerState>,
+                track_current_state::<BarFlyState>,
+                track_current_state::<BanditState>,
+                track_current_state::<SheriffState>,
+            )
+                .chain()
+                .in_set(TRACK_CURRENT_STATE)
+                .in_set(Phase::Log)
+        };
+        // once the agents are spawned, and after every tick's transitions
+        app.add_systems(PostStartup, track());
+        app.add_systems(FixedUpdate, track());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::Location;
+
+    #[derive(Resource, Default)]
+    struct Changes(usize);
+
+    fn count_changes(
+        mut changes: ResMut<Changes>,
+        states: Query<(), Changed<CurrentState<MinerState>>>,
+    ) {
+        changes.0 += states.iter().count();
+    }
+
+    #[test]
+    fn changes_only_when_a_different_state_is_on_top() {
+        let mut world = World::new();
+        world.init_resource::<Changes>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems((track_current_state::<MinerState>, count_changes).chain());
+        let bob = world
+            .spawn(StateStack::new_initial_state(
+                MinerState::GoHomeAndSleepTilRested,
+            ))
+            .id();
+
+        schedule.run(&mut world);
+        assert_eq!(
+            world.get::<CurrentState<MinerState>>(bob),
+            Some(&CurrentState(MinerState::GoHomeAndSleepTilRested))
+        );
+        assert_eq!(world.resource::<Changes>().0, 1);
+
+        // touched, but with the same state on top
+        world
+            .get_mut::<StateStack<MinerState>>(bob)
+            .unwrap()
+            .set_changed();
+        schedule.run(&mut world);
+        assert_eq!(world.resource::<Changes>().0, 1);
+
+        world
+            .get_mut::<StateStack<MinerState>>(bob)
+            .unwrap()
+            .push(MinerState::TravelTo(Location::Saloon));
+        schedule.run(&mut world);
+        assert_eq!(
+            world.get::<CurrentState<MinerState>>(bob),
+            Some(&CurrentState(MinerState::TravelTo(Location::Saloon)))
+        );
+        assert_eq!(world.resource::<Changes>().0, 2);
+
+        let mut stack = world.get_mut::<StateStack<MinerState>>(bob).unwrap();
+        stack.pop();
+        stack.pop();
+        schedule.run(&mut world);
+        assert!(world.get::<CurrentState<MinerState>>(bob).is_none());
+    }
+}
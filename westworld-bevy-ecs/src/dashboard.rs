@@ -0,0 +1,289 @@
+//! A terminal dashboard, shown with `--tui` in place of the plain log: a live
+//! table of every agent, with the action log scrolling underneath. Keys act
+//! immediately, without Enter.
+
+use crate::bandit::BanditState;
+use crate::barfly::BarFlyState;
+use crate::clock::Clock;
+use crate::controls::Input;
+use crate::goldmine::Goldmine;
+use crate::miner::{Miner, MinerState};
+use crate::partner::PartnerState;
+use crate::sheriff::SheriffState;
+use crate::weather::Weather;
+use crate::{Location, Name};
+use bevy_app::{App, Last, MainScheduleOrder, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::ScheduleLabel;
+use bevy_log::tracing_subscriber::layer::{Context, Layer};
+use bevy_log::tracing_subscriber::prelude::*;
+use bevy_log::tracing_subscriber::Registry;
+use bevy_time::{Time, Virtual};
+use fsm::StateStack;
+use ratatui::backend::CrosstermBackend;
+use ratatui::crossterm::event::{self, Event, KeyCode, KeyEventKind, KeyModifiers};
+use ratatui::crossterm::execute;
+use ratatui::crossterm::terminal::{
+    disable_raw_mode, enable_raw_mode, EnterAlternateScreen, LeaveAlternateScreen,
+};
+use ratatui::layout::{Constraint, Layout};
+use ratatui::style::{Modifier, Style};
+use ratatui::text::Line;
+use ratatui::widgets::{Block, Borders, Paragraph, Row, Table};
+use ratatui::Terminal;
+use std::collections::VecDeque;
+use std::fmt::{Debug, Write as _};
+use std::io::{self, stdout, Stdout};
+use std::panic;
+use std::sync::mpsc;
+use std::sync::{Arc, Mutex};
+use std::thread;
+use tracing::field::{Field, Visit};
+use tracing::{Event as LogEvent, Level, Subscriber};
+
+/// The schedule drawing the dashboard, after [`Last`] so it still runs while
+/// the simulation is paused.
+#[derive(ScheduleLabel, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct DrawDashboard;
+
+pub static LOG_LINES: usize = 500; // lines of the log kept for the lower pane
+
+/// The most recent lines of the log.
+#[derive(Resource, Clone, Default)]
+pub struct LogPane(Arc<Mutex<VecDeque<String>>>);
+
+impl LogPane {
+    pub fn push(&self, line: String) {
+        let mut lines = self.0.lock().unwrap();
+        if lines.len() == LOG_LINES {
+            lines.pop_front();
+        }
+        lines.push_back(line);
+    }
+
+    /// Up to `count` of the latest lines, oldest first.
+    pub fn latest(&self, count: usize) -> Vec<String> {
+        let lines = self.0.lock().unwrap();
+        lines
+            .iter()
+            .skip(lines.len().saturating_sub(count))
+            .cloned()
+            .collect()
+    }
+}
+
+/// Sends log messages to the [`LogPane`] instead of the terminal.
+struct CaptureLog(LogPane);
+
+impl<S: Subscriber> Layer<S> for CaptureLog {
+    fn on_event(&self, event: &LogEvent<'_>, _ctx: Context<'_, S>) {
+        // more verbose levels compare greater
+        if *event.metadata().level() > Level::INFO {
+            return;
+        }
+        let mut message = String::new();
+        event.record(&mut MessageField(&mut message));
+        self.0.push(message);
+    }
+}
+
+struct MessageField<'a>(&'a mut String);
+
+impl Visit for MessageField<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        }
+    }
+}
+
+/// The terminal, in raw mode on the alternate screen until dropped.
+pub struct Tui(Terminal<CrosstermBackend<Stdout>>);
+
+impl Tui {
+    pub fn start() -> io::Result<Self> {
+        enable_raw_mode()?;
+        execute!(stdout(), EnterAlternateScreen)?;
+
+        // put the terminal back before a panic message is printed
+        let report = panic::take_hook();
+        panic::set_hook(Box::new(move |info| {
+            restore_terminal();
+            report(info);
+        }));
+
+        Ok(Tui(Terminal::new(CrosstermBackend::new(stdout()))?))
+    }
+}
+
+impl Drop for Tui {
+    fn drop(&mut self) {
+        restore_terminal();
+        let _ = self.0.show_cursor();
+    }
+}
+
+fn restore_terminal() {
+    let _ = disable_raw_mode();
+    let _ = execute!(stdout(), LeaveAlternateScreen);
+}
+
+/// Reads key presses as the same commands the stdin controls understand.
+fn read_keys() -> Input {
+    let (sender, lines) = mpsc::channel();
+    thread::spawn(move || loop {
+        let key = match event::read() {
+            Ok(Event::Key(key)) if key.kind == KeyEventKind::Press => key,
+            Ok(_) => continue,
+            Err(_) => break,
+        };
+        let line = match key.code {
+            // raw mode swallows the interrupt, so Ctrl-C quits too
+            KeyCode::Char('c') if key.modifiers.contains(KeyModifiers::CONTROL) => "q".to_string(),
+            KeyCode::Char(c) => c.to_string(),
+            KeyCode::Enter => String::new(),
+            _ => continue,
+        };
+        if sender.send(line).is_err() {
+            break;
+        }
+    });
+    Input::new(lines)
+}
+
+struct AgentRow {
+    cells: Vec<String>,
+}
+
+impl AgentRow {
+    fn new(name: &Name, states: String, location: &Location) -> Self {
+        AgentRow {
+            cells: vec![name.to_string(), states, format!("{:?}", location)],
+        }
+    }
+
+    fn with_miner(mut self, miner: &Miner) -> Self {
+        self.cells.extend(
+            [
+                miner.gold(),
+                miner.wealth(),
+                miner.thirst(),
+                miner.fatigue(),
+                miner.hunger(),
+            ]
+            .iter()
+            .map(|stat| stat.to_string()),
+        );
+        self
+    }
+}
+
+type Agents<'w, 's, 'a, S> = Query<'w, 's, (&'a Name, &'a Location, &'a StateStack<S>)>;
+
+fn agent_rows<S>(agents: &Agents<S>) -> Vec<AgentRow>
+where
+    S: Clone + Debug + Send + Sync + 'static,
+{
+    agents
+        .iter()
+        .map(|(name, location, stack)| AgentRow::new(name, stack.to_string(), location))
+        .collect()
+}
+
+pub fn draw_dashboard(
+    mut tui: NonSendMut<Tui>,
+    log: Res<LogPane>,
+    (clock, weather, mines): (Res<Clock>, Res<Weather>, Query<&Goldmine>),
+    time: Res<Time<Virtual>>,
+    miners: Query<(&Name, &Location, &Miner, &StateStack<MinerState>)>,
+    (partners, barflies, bandits, sheriffs): (
+        Agents<PartnerState>,
+        Agents<BarFlyState>,
+        Agents<BanditState>,
+        Agents<SheriffState>,
+    ),
+) {
+    let mut rows: Vec<AgentRow> = miners
+        .iter()
+        .map(|(name, location, miner, stack)| {
+            AgentRow::new(name, stack.to_string(), location).with_miner(miner)
+        })
+        .collect();
+    rows.extend(agent_rows(&partners));
+    rows.extend(agent_rows(&barflies));
+    rows.extend(agent_rows(&bandits));
+    rows.extend(agent_rows(&sheriffs));
+
+    let paused = time.is_paused();
+    let reserve: i32 = mines.iter().map(Goldmine::reserve).sum();
+    let title = format!(
+        " Westworld, {}, {}, {} nuggets in the mine{} ",
+        *clock,
+        *weather,
+        reserve,
+        if paused { " (paused)" } else { "" }
+    );
+
+    let _ = tui.0.draw(|frame| {
+        let [agents_area, log_area] = Layout::vertical([
+            Constraint::Length(rows.len() as u16 + 3),
+            Constraint::Min(3),
+        ])
+        .areas(frame.area());
+
+        let header = Row::new(vec![
+            "Name", "States", "Location", "Gold", "Bank", "Thirst", "Fatigue", "Hunger",
+        ])
+        .style(Style::default().add_modifier(Modifier::BOLD));
+        let widths = [
+            Constraint::Length(14),
+            Constraint::Min(30),
+            Constraint::Length(9),
+            Constraint::Length(5),
+            Constraint::Length(5),
+            Constraint::Length(7),
+            Constraint::Length(8),
+            Constraint::Length(7),
+        ];
+        let table = Table::new(rows.into_iter().map(|row| Row::new(row.cells)), widths)
+            .header(header)
+            .block(Block::default().borders(Borders::ALL).title(title));
+        frame.render_widget(table, agents_area);
+
+        let lines = log
+            .latest(log_area.height.saturating_sub(2) as usize)
+            .into_iter()
+            .map(Line::from)
+            .collect::<Vec<_>>();
+        let help = " space pause/resume, n step, q quit ";
+        let pane = Paragraph::new(lines).block(
+            Block::default()
+                .borders(Borders::ALL)
+                .title(" Log ")
+                .title_bottom(help),
+        );
+        frame.render_widget(pane, log_area);
+    });
+}
+
+/// Replaces bevy's log output with the dashboard. Add it instead of
+/// `LogPlugin`, and before `ControlsPlugin` so the keys drive the controls.
+pub struct DashboardPlugin;
+
+impl Plugin for DashboardPlugin {
+    fn build(&self, app: &mut App) {
+        let log = LogPane::default();
+        let subscriber = Registry::default().with(CaptureLog(log.clone()));
+        tracing::subscriber::set_global_default(subscriber)
+            .expect("the dashboard needs to be the only log subscriber");
+
+        let tui = Tui::start().expect("could not start the dashboard");
+        app.insert_non_send_resource(tui);
+        app.insert_resource(log);
+        app.insert_resource(read_keys());
+        app.world_mut()
+            .resource_mut::<MainScheduleOrder>()
+            .insert_after(Last, DrawDashboard);
+        app.add_systems(DrawDashboard, draw_dashboard);
+    }
+}
@@ -0,0 +1,295 @@
+//! Checking a seeded run always goes the same way, with `--verify`: the town
+//! is run twice side by side, each on its own thread, and every transition
+//! and message made in one is checked against the other, as is the world
+//! they're left with. With `--verify-reload=TICK` the second run saves its
+//! world after that many ticks and carries on from the saved copy, checking
+//! a snapshot picks up exactly where it left off.
+//!
+//! The first difference is reported with the events leading up to it, which
+//! is usually enough to tell which system let something nondeterministic in.
+
+use crate::batch::DEFAULT_TICKS;
+use crate::cli::Cli;
+use crate::replay::{self, Record, ReplayLog};
+use crate::rerun::state_hash;
+use crate::rng::Seed;
+use crate::scenario::Scenario;
+use crate::snapshot::{self, Snapshot};
+use crate::{Settings, WorldPlugins};
+use bevy_app::{App, FixedFirst};
+use game_ai::config::Config;
+use std::fmt;
+use std::path::PathBuf;
+use std::thread;
+
+/// How many events before the first difference are shown with it.
+static CONTEXT: usize = 5;
+
+/// `--verify` runs the town twice for `--ticks=T` ticks (1000 by default)
+/// from `--seed=S`, reloading the second at `--verify-reload=TICK` if given.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Verify {
+    pub ticks: u64,
+    pub seed: u64,
+    pub reload: Option<u64>,
+}
+
+/// What a run did, and the hash of the world it left.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Run {
+    pub records: Vec<Record>,
+    pub hash: u64,
+}
+
+/// Where two runs went different ways.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Divergence {
+    /// The events both runs made before they differed.
+    pub before: Vec<Record>,
+    /// What each run did next, if anything.
+    pub first: Option<Record>,
+    pub second: Option<Record>,
+    /// The hashes of the worlds they were left with.
+    pub hashes: (u64, u64),
+}
+
+impl fmt::Display for Divergence {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let next = |record: &Option<Record>| {
+            record
+                .as_ref()
+                .map_or_else(|| "nothing more".to_string(), ToString::to_string)
+        };
+        if self.first.is_none() && self.second.is_none() {
+            return write!(
+                f,
+                "the runs did the same but left different worlds: {:016x} and {:016x}",
+                self.hashes.0, self.hashes.1
+            );
+        }
+        writeln!(f, "the runs diverged after:")?;
+        for record in &self.before {
+            writeln!(f, "  {}", record)?;
+        }
+        writeln!(f, "the first did {}", next(&self.first))?;
+        write!(f, "the second did {}", next(&self.second))
+    }
+}
+
+impl Verify {
+    /// Reads `--verify`, `--verify-reload=TICK`, `--ticks=N` and `--seed=N`.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.verify.then(|| Verify {
+            ticks: cli.ticks.unwrap_or(DEFAULT_TICKS),
+            seed: Seed::from_cli(cli).0,
+            reload: cli.verify_reload,
+        })
+    }
+
+    /// Runs the town twice at once, returning the run if both went the same
+    /// way.
+    pub fn run(&self, scenario: &Scenario, config: &Config) -> Result<Run, Box<Divergence>> {
+        let runs: Vec<_> = [None, self.reload]
+            .iter()
+            .map(|&reload| {
+                let verify = self.clone();
+                let scenario = scenario.clone();
+                let config = config.clone();
+                thread::spawn(move || {
+                    simulate(&scenario, &config, verify.seed, verify.ticks, reload)
+                })
+            })
+            .collect();
+        let mut runs = runs
+            .into_iter()
+            .map(|run| run.join().expect("a simulation panicked"));
+        let (first, second) = (runs.next().unwrap(), runs.next().unwrap());
+        compare(&first, &second).map_or(Ok(first), |divergence| Err(Box::new(divergence)))
+    }
+}
+
+/// Where `second` first differs from `first`, if it does.
+pub fn compare(first: &Run, second: &Run) -> Option<Divergence> {
+    let same = first
+        .records
+        .iter()
+        .zip(&second.records)
+        .take_while(|(a, b)| a == b)
+        .count();
+    if same == first.records.len() && same == second.records.len() && first.hash == second.hash {
+        return None;
+    }
+    Some(Divergence {
+        before: first.records[same.saturating_sub(CONTEXT)..same].to_vec(),
+        first: first.records.get(same).cloned(),
+        second: second.records.get(same).cloned(),
+        hashes: (first.hash, second.hash),
+    })
+}
+
+/// Runs the town for `ticks` updates on the current thread, keeping what it
+/// does, and saving and loading it again after `reload` ticks if given.
+pub fn simulate(
+    scenario: &Scenario,
+    config: &Config,
+    seed: u64,
+    ticks: u64,
+    reload: Option<u64>,
+) -> Run {
+    let town = || {
+        let mut app = App::new();
+        app.insert_resource(scenario.clone())
+            .insert_resource(Settings(config.clone()))
+            .insert_resource(Seed(seed))
+            .insert_resource(ReplayLog::recording(PathBuf::new()))
+            .add_plugins(WorldPlugins)
+            .add_systems(FixedFirst, replay::count_ticks);
+        app
+    };
+    let reload = reload.filter(|&tick| tick < ticks);
+    let mut app = town();
+    let mut records = run(&mut app, reload.unwrap_or(ticks));
+    if let Some(tick) = reload {
+        // through the file format, as --save and --load would go
+        let saved = ron::to_string(&Snapshot::capture(app.world_mut())).unwrap();
+        app = town();
+        snapshot::resume(&mut app, ron::from_str(&saved).unwrap());
+        records.extend(
+            run(&mut app, ticks - tick)
+                .into_iter()
+                .map(|record| later(record, tick)),
+        );
+    }
+    Run {
+        records,
+        hash: state_hash(app.world_mut()),
+    }
+}
+
+/// Runs `ticks` ticks, returning the records made.
+fn run(app: &mut App, ticks: u64) -> Vec<Record> {
+    app.finish();
+    app.cleanup();
+    for _ in 0..ticks {
+        app.update();
+    }
+    app.world().resource::<ReplayLog>().records().to_vec()
+}
+
+/// `record` as made `by` ticks later, for a run picked up part way through.
+fn later(record: Record, by: u64) -> Record {
+    match record {
+        Record::Transition {
+            tick,
+            agent,
+            from,
+            to,
+        } => Record::Transition {
+            tick: tick + by,
+            agent,
+            from,
+            to,
+        },
+        Record::Message {
+            tick,
+            sender,
+            receiver,
+            message,
+            delay,
+        } => Record::Message {
+            tick: tick + by,
+            sender,
+            receiver,
+            message,
+            delay,
+        },
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::Message;
+    use crate::population::Population;
+
+    fn town() -> Scenario {
+        Scenario::populated(&Population {
+            miners: 2,
+            partners: 2,
+        })
+    }
+
+    #[test]
+    fn a_seeded_run_goes_the_same_way_twice() {
+        let verify = Verify {
+            ticks: 200,
+            seed: 11,
+            reload: None,
+        };
+        let run = verify.run(&town(), &Config::default()).unwrap();
+        assert!(!run.records.is_empty());
+    }
+
+    #[test]
+    fn a_reloaded_run_carries_on_the_same() {
+        // some with telegrams still waiting to be delivered
+        for reload in (10..200).step_by(10) {
+            let verify = Verify {
+                ticks: 200,
+                seed: 11,
+                reload: Some(reload),
+            };
+            if let Err(divergence) = verify.run(&town(), &Config::default()) {
+                panic!("reloading at tick {}: {}", reload, divergence);
+            }
+        }
+    }
+
+    #[test]
+    fn a_crowd_updated_at_once_reloads_the_same() {
+        // more miners than are updated one after another
+        let crowd = Scenario::populated(&Population {
+            miners: 100,
+            partners: 20,
+        });
+        let verify = Verify {
+            ticks: 100,
+            seed: 5,
+            reload: Some(50),
+        };
+        if let Err(divergence) = verify.run(&crowd, &Config::default()) {
+            panic!("{}", divergence);
+        }
+    }
+
+    #[test]
+    fn the_first_difference_is_shown_with_what_came_before() {
+        let message = |tick| Record::Message {
+            tick,
+            sender: "Miner Bob".to_string(),
+            receiver: "Elsa".to_string(),
+            message: Message::HiHoneyImHome,
+            delay: 0,
+        };
+        let first = Run {
+            records: (1..=8).map(message).collect(),
+            hash: 1,
+        };
+        let mut second = first.clone();
+        second.records[7] = message(9);
+
+        let divergence = compare(&first, &second).unwrap();
+        assert_eq!(divergence.before, first.records[2..7]);
+        assert_eq!(divergence.first, Some(message(8)));
+        assert_eq!(divergence.second, Some(message(9)));
+        assert!(divergence.to_string().contains("the second did tick 9"));
+
+        assert_eq!(compare(&first, &first), None);
+        second = first.clone();
+        second.hash = 2;
+        assert!(compare(&first, &second)
+            .unwrap()
+            .to_string()
+            .contains("left different worlds"));
+    }
+}
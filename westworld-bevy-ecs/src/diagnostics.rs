@@ -0,0 +1,126 @@
+//! How hard the AI's working, as bevy diagnostics: state transitions and
+//! messages a second, path searches under way and agents updated each tick.
+//! `--diagnostics` logs them every second, so there's no need for a profiler
+//! to see where a run's time goes.
+
+use crate::cli::Cli;
+use crate::current_state::Running;
+use crate::path_planner::PathSearches;
+use crate::replay::Record;
+use bevy_app::{App, Plugin, Update};
+use bevy_diagnostic::{
+    Diagnostic, DiagnosticPath, Diagnostics, DiagnosticsPlugin, LogDiagnosticsPlugin,
+    RegisterDiagnostic,
+};
+use bevy_ecs::prelude::*;
+use bevy_time::{Real, Time};
+use std::mem;
+
+pub const TRANSITIONS: DiagnosticPath = DiagnosticPath::const_new("ai/transitions");
+pub const MESSAGES: DiagnosticPath = DiagnosticPath::const_new("ai/messages");
+pub const PATH_SEARCHES: DiagnosticPath = DiagnosticPath::const_new("ai/path_searches");
+pub const AGENTS_UPDATED: DiagnosticPath = DiagnosticPath::const_new("ai/agents_updated");
+
+/// Transitions and messages since the last frame's measurements.
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct Tally {
+    pub transitions: u64,
+    pub messages: u64,
+}
+
+impl Tally {
+    /// Counts what an agent did.
+    pub fn record(&mut self, record: &Record) {
+        match record {
+            Record::Transition { .. } => self.transitions += 1,
+            Record::Message { .. } => self.messages += 1,
+        }
+    }
+}
+
+/// Takes this frame's measurements, turning the tally into rates.
+pub fn measure_ai(
+    mut diagnostics: Diagnostics,
+    time: Res<Time<Real>>,
+    mut tally: ResMut<Tally>,
+    searches: Res<PathSearches>,
+    // everyone with anything on their stacks is updated every tick
+    agents: Query<(), Running>,
+) {
+    let tally = mem::take(&mut *tally);
+    let seconds = time.delta_secs_f64();
+    // nothing's had the time to happen on the first frame
+    if seconds > 0.0 {
+        diagnostics.add_measurement(&TRANSITIONS, || tally.transitions as f64 / seconds);
+        diagnostics.add_measurement(&MESSAGES, || tally.messages as f64 / seconds);
+    }
+    diagnostics.add_measurement(&PATH_SEARCHES, || searches.num_active_searches() as f64);
+    diagnostics.add_measurement(&AGENTS_UPDATED, || agents.iter().count() as f64);
+}
+
+/// Keeps the AI's diagnostics with `--diagnostics`, logging them every
+/// second if `log` is set.
+pub struct AiDiagnosticsPlugin {
+    pub log: bool,
+}
+
+impl AiDiagnosticsPlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.diagnostics.then_some(AiDiagnosticsPlugin { log: true })
+    }
+}
+
+impl Plugin for AiDiagnosticsPlugin {
+    fn build(&self, app: &mut App) {
+        if !app.is_plugin_added::<DiagnosticsPlugin>() {
+            app.add_plugins(DiagnosticsPlugin);
+        }
+        app.init_resource::<Tally>()
+            .register_diagnostic(Diagnostic::new(TRANSITIONS).with_suffix("/s"))
+            .register_diagnostic(Diagnostic::new(MESSAGES).with_suffix("/s"))
+            .register_diagnostic(Diagnostic::new(PATH_SEARCHES))
+            .register_diagnostic(Diagnostic::new(AGENTS_UPDATED));
+        app.add_systems(Update, measure_ai);
+        if self.log {
+            app.add_plugins(LogDiagnosticsPlugin {
+                filter: Some(
+                    vec![TRANSITIONS, MESSAGES, PATH_SEARCHES, AGENTS_UPDATED]
+                        .into_iter()
+                        .collect(),
+                ),
+                ..Default::default()
+            });
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::population::Population;
+    use crate::scenario::Scenario;
+    use crate::testing::{build_test_app, tick};
+    use bevy_diagnostic::DiagnosticsStore;
+
+    #[test]
+    fn counts_the_agents_updated() {
+        let mut app = build_test_app(
+            Scenario::populated(&Population {
+                miners: 2,
+                partners: 1,
+            }),
+            1,
+        );
+        app.add_plugins(AiDiagnosticsPlugin { log: false });
+        tick(&mut app, 3);
+
+        let store = app.world().resource::<DiagnosticsStore>();
+        let agents = store.get(&AGENTS_UPDATED).and_then(Diagnostic::value);
+        // with the bar fly, the bandit and the sheriff
+        assert_eq!(agents, Some(6.0));
+        assert!(store
+            .get(&PATH_SEARCHES)
+            .and_then(Diagnostic::value)
+            .is_some());
+    }
+}
@@ -0,0 +1,90 @@
+use crate::miner::{Miner, UPDATE_MINERS};
+use crate::timer::Timer;
+use crate::weather::CHANGE_WEATHER;
+use crate::{Name, Phase};
+use bevy_app::{App, FixedUpdate, Plugin, Startup};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use serde::{Deserialize, Serialize};
+
+/// Prices and interest. Insert your own before adding [`EconomyPlugin`] to
+/// change them.
+#[derive(Resource, Clone, Debug)]
+pub struct Economy {
+    pub whiskey_price: i32,
+    pub meal_price: i32,
+    /// What Elsa's ingredients cost her husband, if he can pay.
+    pub stew_cost: i32,
+    /// Percentage of their savings paid to miners each interest period.
+    pub interest_rate: i32,
+    /// Updates between interest payments.
+    pub interest_period: usize,
+}
+
+impl Default for Economy {
+    fn default() -> Self {
+        Economy {
+            whiskey_price: 2,
+            meal_price: 3,
+            stew_cost: 1,
+            interest_rate: 5,
+            interest_period: 50,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum EconomyEvent {
+    PayInterest,
+}
+
+pub type EconomyTimer = Timer<EconomyEvent>;
+
+pub fn start_economy(mut commands: Commands, economy: Res<Economy>) {
+    let mut timer = EconomyTimer::with_max_interval(economy.interest_period + 1);
+    timer.schedule(economy.interest_period, EconomyEvent::PayInterest);
+    commands.insert_resource(timer);
+}
+
+pub fn run_economy(
+    economy: Res<Economy>,
+    mut timer: ResMut<EconomyTimer>,
+    mut miners: Query<(&Name, &mut Miner)>,
+) {
+    for event in timer.tick() {
+        match event {
+            EconomyEvent::PayInterest => {
+                for (name, mut miner) in miners.iter_mut() {
+                    let interest = miner.add_interest(economy.interest_rate);
+                    if interest > 0 {
+                        info!(
+                            "{}: Well ah'll be! The bank paid me {} in interest. Total savings now: {}",
+                            name,
+                            interest,
+                            miner.wealth()
+                        );
+                    }
+                }
+                timer.schedule(economy.interest_period, EconomyEvent::PayInterest);
+            }
+        }
+    }
+}
+
+pub struct EconomyPlugin;
+
+impl Plugin for EconomyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Economy>();
+        app.add_systems(Startup, start_economy);
+        // interest lands before the miners spend it, and before the weather
+        // leaves them parched
+        app.add_systems(
+            FixedUpdate,
+            run_economy
+                .in_set(Phase::Decide)
+                .before(CHANGE_WEATHER)
+                .before(UPDATE_MINERS),
+        );
+    }
+}
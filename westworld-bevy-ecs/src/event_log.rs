@@ -0,0 +1,160 @@
+//! Writing every state transition and message to a JSON-lines file as the
+//! run goes, one event per line, for picking runs apart afterwards with jq or
+//! pandas. Each event has the `tick` it happened on, the `entity` that made it
+//! happen, the `event` type and its details:
+//!
+//! ```text
+//! {"entity":"Miner Bob","event":"transition","from":"GoHomeAndSleepTilRested","tick":1,"to":"EnterMineAndDigForNugget > TravelTo(Goldmine)"}
+//! {"delay":0,"entity":"Miner Bob","event":"message","message":"HiHoneyImHome","receiver":"Elsa","tick":25}
+//! ```
+
+use crate::cli::Cli;
+use crate::replay::Record;
+use bevy_app::{App, FixedFirst, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use serde_json::{json, Value};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::{Path, PathBuf};
+
+/// The file the run's events are written to.
+#[derive(Resource)]
+pub struct EventLog {
+    path: PathBuf,
+    out: BufWriter<File>,
+    tick: u64,
+    written: usize,
+}
+
+impl EventLog {
+    pub fn create<P: AsRef<Path>>(path: P) -> io::Result<Self> {
+        Ok(EventLog {
+            path: path.as_ref().to_path_buf(),
+            out: BufWriter::new(File::create(path)?),
+            tick: 0,
+            written: 0,
+        })
+    }
+
+    /// The tick being run, counting from 1.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Writes `record` as the next line of the file.
+    pub fn record(&mut self, record: &Record) {
+        match writeln!(self.out, "{}", event(record)) {
+            Ok(()) => self.written += 1,
+            Err(e) => error!("{}: {}", self.path.display(), e),
+        }
+    }
+}
+
+/// The line `record` is written as.
+pub fn event(record: &Record) -> Value {
+    match record {
+        Record::Transition {
+            tick,
+            agent,
+            from,
+            to,
+        } => json!({
+            "tick": tick,
+            "entity": agent,
+            "event": "transition",
+            "from": from,
+            "to": to,
+        }),
+        Record::Message {
+            tick,
+            sender,
+            receiver,
+            message,
+            delay,
+        } => json!({
+            "tick": tick,
+            "entity": sender,
+            "event": "message",
+            "receiver": receiver,
+            "message": message,
+            "delay": delay,
+        }),
+    }
+}
+
+pub fn count_ticks(mut log: ResMut<EventLog>) {
+    log.tick += 1;
+}
+
+/// Flushes what's left of the event log to its file, once the run is over.
+pub fn finish(world: &mut World) {
+    let mut log = match world.remove_resource::<EventLog>() {
+        Some(log) => log,
+        None => return,
+    };
+    match log.out.flush() {
+        Ok(()) => info!("wrote {} events to {}", log.written, log.path.display()),
+        Err(e) => error!("{}: {}", log.path.display(), e),
+    }
+}
+
+/// Writes the run's events to `--events=FILE`. Call [`finish`] once the run
+/// is over.
+pub struct EventLogPlugin(pub PathBuf);
+
+impl EventLogPlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.events.clone().map(EventLogPlugin)
+    }
+}
+
+impl Plugin for EventLogPlugin {
+    fn build(&self, app: &mut App) {
+        let log = EventLog::create(&self.0)
+            .unwrap_or_else(|e| panic!("could not create {}: {}", self.0.display(), e));
+        app.insert_resource(log);
+        app.add_systems(FixedFirst, count_ticks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::Message;
+    use std::env;
+    use std::fs;
+
+    #[test]
+    fn each_event_is_a_line_of_json() {
+        let path = env::temp_dir().join("westworld-events-test.jsonl");
+        let mut log = EventLog::create(&path).unwrap();
+        log.record(&Record::Transition {
+            tick: 1,
+            agent: "Miner Bob".to_string(),
+            from: "GoHomeAndSleepTilRested".to_string(),
+            to: "EnterMineAndDigForNugget".to_string(),
+        });
+        log.record(&Record::Message {
+            tick: 2,
+            sender: "Miner Bob".to_string(),
+            receiver: "Elsa".to_string(),
+            message: Message::HiHoneyImHome,
+            delay: 0,
+        });
+        log.out.flush().unwrap();
+
+        let written = fs::read_to_string(&path).unwrap();
+        let events: Vec<serde_json::Value> = written
+            .lines()
+            .map(|line| serde_json::from_str(line).unwrap())
+            .collect();
+        assert_eq!(events.len(), 2);
+        assert_eq!(events[0]["event"], "transition");
+        assert_eq!(events[0]["entity"], "Miner Bob");
+        assert_eq!(events[1]["event"], "message");
+        assert_eq!(events[1]["message"], "HiHoneyImHome");
+        assert_eq!(events[1]["tick"], 2);
+        fs::remove_file(path).unwrap();
+    }
+}
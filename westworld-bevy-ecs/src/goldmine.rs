@@ -0,0 +1,519 @@
+//! The goldmine every miner digs in. It holds only so much gold, and once
+//! it's played out the miners go prospecting until one of them strikes a new
+//! seam somewhere out in the hills.
+//!
+//! There's only room for so many miners to dig at once. A miner turning up
+//! asks the goldmine for a dig slot, and it either grants him one or puts him
+//! in the queue for the next to come free. A miner despawned gives his up
+//! without having to say so.
+
+use crate::messaging::{
+    post_messages, Message, Outbox, Telegram, DELIVER_DELAYED_MESSAGES, SEND_MSG_IMMEDIATELY,
+};
+use crate::miner::{Miner, MinerState, INIT_MINERS, UPDATE_MINERS};
+use crate::path_planner::NavGraph;
+use crate::rng::WorldRng;
+use crate::weather::CHANGE_WEATHER;
+use crate::{Label, Location, Name, Phase};
+use bevy_app::{App, FixedUpdate, Plugin, Startup};
+use bevy_ecs::entity::{EntityMapper, MapEntities};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use fsm::StateStack;
+use game_ai::Vector2D;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::ops::Deref;
+
+/// Miners who can dig in the goldmine at once.
+pub static DIG_SLOTS: usize = 2;
+
+/// Label of the system striking new seams, for systems that must run in a
+/// fixed order with it.
+pub static PROSPECT: Label = Label("prospect");
+/// Label of the system answering the miners asking to dig.
+pub static ALLOT_DIG_SLOTS: Label = Label("allot_dig_slots");
+
+/// How much gold there is to be found, and how hard it is to find. Insert
+/// your own before adding [`GoldminePlugin`] to change it.
+#[derive(Resource, Clone, Debug)]
+pub struct Geology {
+    /// Fewest nuggets in a newly struck seam.
+    pub poorest_seam: i32,
+    /// Most nuggets in a newly struck seam, and in the mine the town starts with.
+    pub richest_seam: i32,
+    /// Fewest updates of prospecting before a new seam is struck.
+    pub shortest_search: usize,
+    /// Most updates of prospecting before a new seam is struck.
+    pub longest_search: usize,
+    /// Corners of the hills new seams are struck in.
+    pub hills: (Vector2D, Vector2D),
+}
+
+impl Default for Geology {
+    fn default() -> Self {
+        Geology {
+            poorest_seam: 60,
+            richest_seam: 150,
+            shortest_search: 10,
+            longest_search: 40,
+            hills: (Vector2D::new(-10.0, 30.0), Vector2D::new(50.0, 60.0)),
+        }
+    }
+}
+
+/// The gold left in the mine, shared by every miner digging there. The
+/// miners update all at once, each with a [`Claim`] on the mine as it was
+/// before any of them dug, and the nuggets are set aside for them first, so
+/// each goes to exactly one of them however many are digging.
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct Goldmine {
+    reserve: i32,
+    position: Vector2D,
+    /// Updates of prospecting left before a new seam is struck, counted down
+    /// once the mine's played out and somebody's looking.
+    search: Option<usize>,
+    /// Nuggets dug out since the town started, across every seam.
+    #[serde(default)]
+    mined: i32,
+    /// Miners who can dig at once.
+    #[serde(default = "dig_slots")]
+    slots: usize,
+    /// Miners holding a dig slot.
+    #[serde(default)]
+    diggers: Vec<Entity>,
+    /// Miners waiting for a slot to come free, first come first served.
+    #[serde(default)]
+    queue: VecDeque<Entity>,
+}
+
+fn dig_slots() -> usize {
+    DIG_SLOTS
+}
+
+impl Goldmine {
+    pub fn new(reserve: i32, position: Vector2D) -> Self {
+        Goldmine {
+            reserve,
+            position,
+            search: None,
+            mined: 0,
+            slots: DIG_SLOTS,
+            diggers: vec![],
+            queue: VecDeque::new(),
+        }
+    }
+
+    /// Takes a nugget from the mine. Returns false, taking nothing, if it's
+    /// played out.
+    pub fn dig(&mut self) -> bool {
+        if self.played_out() {
+            return false;
+        }
+        self.reserve -= 1;
+        self.mined += 1;
+        true
+    }
+
+    pub fn played_out(&self) -> bool {
+        self.reserve <= 0
+    }
+
+    pub fn reserve(&self) -> i32 {
+        self.reserve
+    }
+
+    pub fn mined(&self) -> i32 {
+        self.mined
+    }
+
+    pub fn position(&self) -> Vector2D {
+        self.position
+    }
+
+    /// Lets `miner` dig if there's a slot free, or puts him in the queue for
+    /// the next one. Returns whether he can dig.
+    pub fn request_slot(&mut self, miner: Entity) -> bool {
+        if self.diggers.contains(&miner) {
+            return true;
+        }
+        if self.diggers.len() < self.slots {
+            self.diggers.push(miner);
+            return true;
+        }
+        if !self.queue.contains(&miner) {
+            self.queue.push_back(miner);
+        }
+        false
+    }
+
+    /// Frees `miner`'s slot, or takes him out of the queue. Returns whoever's
+    /// first in the queue, if there's now a slot for him.
+    pub fn free_slot(&mut self, miner: Entity) -> Option<Entity> {
+        self.diggers.retain(|&digger| digger != miner);
+        self.queue.retain(|&waiting| waiting != miner);
+        if self.diggers.len() >= self.slots {
+            return None;
+        }
+        let next = self.queue.pop_front()?;
+        self.diggers.push(next);
+        Some(next)
+    }
+
+    /// The goldmine's answer to a miner's message, and who it's for.
+    pub fn answer(&mut self, telegram: &Telegram) -> Option<(Entity, Message)> {
+        match telegram.message {
+            Message::RequestDigSlot if self.request_slot(telegram.sender) => {
+                Some((telegram.sender, Message::DigSlotGranted))
+            }
+            Message::RequestDigSlot => Some((telegram.sender, Message::DigSlotDenied)),
+            Message::DigSlotFreed => self
+                .free_slot(telegram.sender)
+                .map(|next| (next, Message::DigSlotGranted)),
+            _ => None,
+        }
+    }
+
+    /// `miner`'s claim on the mine for an update: a nugget, if there are
+    /// enough left to go round those let in to dig before him. Anyone
+    /// digging without a slot comes after them all.
+    pub fn claim(&self, miner: Entity) -> Claim<'_> {
+        let turn = self
+            .diggers
+            .iter()
+            .position(|&digger| digger == miner)
+            .unwrap_or(self.diggers.len());
+        Claim {
+            mine: self,
+            nugget: (turn as i64) < i64::from(self.reserve),
+            dug: false,
+        }
+    }
+
+    /// Moves the mine to a newly struck seam holding `reserve` nuggets.
+    pub fn strike(&mut self, reserve: i32, position: Vector2D) {
+        self.reserve = reserve;
+        self.position = position;
+        self.search = None;
+    }
+}
+
+/// A miner's view of the goldmine while he runs, as it stood before anyone
+/// dug this update, and the nugget set aside for him, if there is one.
+/// Whatever he digs comes out of the mine itself once everyone's updated.
+pub struct Claim<'a> {
+    mine: &'a Goldmine,
+    nugget: bool,
+    dug: bool,
+}
+
+impl<'a> Claim<'a> {
+    /// A look at the mine with nothing to dig, for a miner handling a
+    /// message or stopping.
+    pub fn looking(mine: &'a Goldmine) -> Self {
+        Claim {
+            mine,
+            nugget: false,
+            dug: false,
+        }
+    }
+
+    /// Takes the nugget set aside for him. Returns false, taking nothing, if
+    /// there isn't one or he's had it.
+    pub fn dig(&mut self) -> bool {
+        if !self.nugget || self.dug {
+            return false;
+        }
+        self.dug = true;
+        true
+    }
+
+    /// Whether he took his nugget, to be dug out of the mine.
+    pub fn dug(&self) -> bool {
+        self.dug
+    }
+}
+
+impl Deref for Claim<'_> {
+    type Target = Goldmine;
+
+    fn deref(&self) -> &Goldmine {
+        self.mine
+    }
+}
+
+impl MapEntities for Goldmine {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        for miner in self.diggers.iter_mut().chain(self.queue.iter_mut()) {
+            *miner = entity_mapper.get_mapped(*miner);
+        }
+    }
+}
+
+/// The town starts with a mine as rich as they come, where the map has it, so
+/// that startup doesn't draw a random number.
+pub fn open_goldmine(mut commands: Commands, geology: Res<Geology>, graph: Res<NavGraph>) {
+    let position = graph.position_of(Location::Goldmine).unwrap_or_default();
+    let mut mine = commands.spawn_empty();
+    let id = mine.id();
+    mine.insert(Name("Goldmine".to_string()))
+        .insert(Outbox::new(id))
+        .insert(Goldmine::new(geology.richest_seam, position));
+}
+
+/// Answers the miners asking to dig, or giving up their places, as soon as
+/// their messages are posted, so each of them hears back before he next
+/// updates.
+pub fn allot_dig_slots(
+    mut telegrams: MessageReader<Telegram>,
+    mut mines: Query<(&mut Goldmine, &mut Outbox)>,
+) {
+    for telegram in telegrams.read() {
+        if let Ok((mut mine, mut outbox)) = mines.get_mut(telegram.receiver) {
+            if let Some((miner, answer)) = mine.answer(telegram) {
+                outbox.dispatch(SEND_MSG_IMMEDIATELY, miner, answer);
+            }
+        }
+    }
+}
+
+/// Frees the slots, and places in the queue, of miners who've been despawned,
+/// whose own word that they're done was lost with them.
+pub fn release_despawned_miners(
+    mut gone: RemovedComponents<Miner>,
+    mut mines: Query<(&mut Goldmine, &mut Outbox)>,
+) {
+    for miner in gone.read() {
+        for (mut mine, mut outbox) in mines.iter_mut() {
+            if let Some(next) = mine.free_slot(miner) {
+                outbox.dispatch(SEND_MSG_IMMEDIATELY, next, Message::DigSlotGranted);
+            }
+        }
+    }
+}
+
+/// Counts down the search for a new seam while any miner is prospecting, and
+/// moves the mine there once the first of them strikes it.
+pub fn prospect(
+    geology: Res<Geology>,
+    mut rng: ResMut<WorldRng>,
+    mut graph: ResMut<NavGraph>,
+    mut mines: Query<&mut Goldmine>,
+    miners: Query<(&Name, &StateStack<MinerState>)>,
+) {
+    for mut mine in mines.iter_mut() {
+        if !mine.played_out() {
+            continue;
+        }
+        let prospector = miners
+            .iter()
+            .find(|(_, states)| states.last() == Some(&MinerState::Prospect));
+        let name = match prospector {
+            Some((name, _)) => name,
+            None => continue,
+        };
+
+        let search = mine
+            .search
+            .get_or_insert_with(|| rng.gen_range(geology.shortest_search..=geology.longest_search));
+        if *search > 0 {
+            *search -= 1;
+            continue;
+        }
+
+        let (from, to) = geology.hills;
+        let position = Vector2D::new(rng.gen_range(from.x..=to.x), rng.gen_range(from.y..=to.y));
+        let reserve = rng.gen_range(geology.poorest_seam..=geology.richest_seam);
+        mine.strike(reserve, position);
+        graph.relocate(Location::Goldmine, position);
+        info!(
+            "{}: Eureka! Struck a new seam o' gold out at ({:.0}, {:.0})",
+            name, position.x, position.y
+        );
+    }
+}
+
+pub struct GoldminePlugin;
+
+impl Plugin for GoldminePlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Geology>();
+        // the mine's spawned first, as it is when a snapshot's restored
+        app.add_systems(Startup, open_goldmine.before(INIT_MINERS));
+        // the prospectors hear of a strike the update it's made, and the
+        // weather draws its random numbers first
+        app.add_systems(
+            FixedUpdate,
+            prospect
+                .in_set(PROSPECT)
+                .in_set(Phase::Decide)
+                .after(CHANGE_WEATHER)
+                .before(UPDATE_MINERS),
+        );
+        // the answers are posted straight after, rather than next tick, and
+        // the slots of any miners gone are handed on first
+        app.add_systems(
+            FixedUpdate,
+            (release_despawned_miners, allot_dig_slots)
+                .chain()
+                .in_set(ALLOT_DIG_SLOTS)
+                .in_set(Phase::Dispatch)
+                .after(DELIVER_DELAYED_MESSAGES),
+        );
+        app.add_systems(
+            FixedUpdate,
+            post_messages.in_set(Phase::Dispatch).after(ALLOT_DIG_SLOTS),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::agents::{deliver_to_agents, update_agents_at_once, ParallelAbove};
+    use crate::brains::Brains;
+    use crate::clock::Clock;
+    use crate::economy::Economy;
+    use crate::messaging::{update_messages, MessageTimer};
+    use crate::miner::{Miner, MinerComponents, MinerHandler};
+    use crate::occupancy::Occupancy;
+    use crate::path_planner::{NavGraph, PathPlanner};
+    use crate::roads::Roads;
+    use crate::scripting::Scripts;
+    use crate::weather::Weather;
+    use crate::Settings;
+    use rand::SeedableRng;
+
+    /// A town with `mine` and `miners` miners all at it, ready to dig.
+    fn mining_town(mine: Goldmine, miners: usize) -> (World, Vec<Entity>) {
+        let mut world = World::new();
+        world.insert_resource(Economy::default());
+        world.insert_resource(Clock::starting_at(1, 12));
+        world.insert_resource(Weather::Sunny);
+        world.insert_resource(Settings::default());
+        world.insert_resource(MessageTimer::new());
+        world.insert_resource(Messages::<Telegram>::default());
+        world.insert_resource(Occupancy::default());
+        world.insert_resource(Roads::town());
+        world.insert_resource(NavGraph::town());
+        world.insert_resource(Scripts::default());
+        world.insert_resource(Brains::default());
+        world.insert_resource(WorldRng::seed_from_u64(0));
+        world.insert_resource(ParallelAbove::default());
+        let mut goldmine = world.spawn_empty();
+        let id = goldmine.id();
+        goldmine.insert(Outbox::new(id)).insert(mine);
+        let miners = (0..miners)
+            .map(|i| {
+                let mut miner = world.spawn_empty();
+                let id = miner.id();
+                miner
+                    .insert(Name(format!("Miner {}", i)))
+                    .insert(Outbox::new(id))
+                    .insert(Location::Goldmine)
+                    .insert(Miner::new())
+                    .insert(PathPlanner::new(Vector2D::default()))
+                    .insert(StateStack::new_initial_state(
+                        MinerState::EnterMineAndDigForNugget,
+                    ));
+                id
+            })
+            .collect();
+        (world, miners)
+    }
+
+    /// The miners and the goldmine, with messages passed between them as
+    /// [`MessagingPlugin`](crate::messaging::MessagingPlugin) does.
+    fn mining_schedule() -> Schedule {
+        let mut schedule = Schedule::default();
+        schedule.add_systems(
+            (
+                update_messages::<Telegram>,
+                deliver_to_agents::<MinerState, MinerHandler, MinerComponents>,
+                update_agents_at_once::<MinerState, MinerHandler, MinerComponents>,
+                post_messages,
+                release_despawned_miners,
+                allot_dig_slots,
+                post_messages,
+            )
+                .chain(),
+        );
+        schedule
+    }
+
+    fn state(world: &World, miner: Entity) -> Option<MinerState> {
+        world
+            .get::<StateStack<MinerState>>(miner)
+            .unwrap()
+            .last()
+            .copied()
+    }
+
+    #[test]
+    fn the_last_nugget_goes_to_one_miner_only() {
+        let (mut world, miners) = mining_town(Goldmine::new(1, Vector2D::default()), 2);
+        let mut schedule = mining_schedule();
+        // they ask to dig, then dig
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+
+        let gold: i32 = miners
+            .iter()
+            .map(|&miner| world.get::<Miner>(miner).unwrap().gold())
+            .sum();
+        assert_eq!(gold, 1);
+        let prospecting = miners
+            .iter()
+            .filter(|&&miner| state(&world, miner) == Some(MinerState::TravelTo(Location::Hills)))
+            .count();
+        assert_eq!(prospecting, 1);
+    }
+
+    #[test]
+    fn a_miner_turned_away_digs_once_a_slot_comes_free() {
+        let (mut world, miners) = mining_town(Goldmine::new(100, Vector2D::default()), 3);
+        let mut schedule = mining_schedule();
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+
+        let waiting: Vec<Entity> = miners
+            .iter()
+            .copied()
+            .filter(|&miner| state(&world, miner) == Some(MinerState::WaitInQueue))
+            .collect();
+        assert_eq!(waiting.len(), 1);
+        let waiting = waiting[0];
+        assert_eq!(world.get::<Miner>(waiting).unwrap().gold(), 0);
+
+        // the other two fill their pockets and head for the bank
+        for _ in 0..3 {
+            schedule.run(&mut world);
+        }
+        assert_eq!(
+            state(&world, waiting),
+            Some(MinerState::EnterMineAndDigForNugget)
+        );
+        assert_eq!(world.get::<Miner>(waiting).unwrap().gold(), 1);
+    }
+
+    #[test]
+    fn a_despawned_miner_gives_up_his_slot() {
+        let (mut world, miners) = mining_town(Goldmine::new(100, Vector2D::default()), 3);
+        let mut schedule = mining_schedule();
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+        let (waiting, digging): (Vec<Entity>, Vec<Entity>) = miners
+            .iter()
+            .partition(|&&miner| state(&world, miner) == Some(MinerState::WaitInQueue));
+
+        world.despawn(digging[0]);
+        schedule.run(&mut world);
+        schedule.run(&mut world);
+        assert_eq!(
+            state(&world, waiting[0]),
+            Some(MinerState::EnterMineAndDigForNugget)
+        );
+        assert_eq!(world.get::<Miner>(waiting[0]).unwrap().gold(), 1);
+    }
+}
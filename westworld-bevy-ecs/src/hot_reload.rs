@@ -0,0 +1,194 @@
+//! Loading the tuning and the scripted states again whenever they're saved,
+//! with `--watch`, so they can be tried out on a town already running rather
+//! than starting it over each time. The `--config=FILE` file and the scripts
+//! in `--scripts=DIR` are watched, and any changed are loaded again before
+//! the next tick. One that won't load is
+//! left as it was, and says why.
+//!
+//! Miners in a scripted state carry on in it with the new script. The tick
+//! interval stays as it started.
+
+use crate::cli::Cli;
+use crate::rerun::{Input, InputLog};
+use crate::scripting::{self, Scripts};
+use crate::Settings;
+use bevy_app::{App, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use game_ai::config::Config;
+use notify::{Event, EventKind, RecommendedWatcher, RecursiveMode, Watcher};
+use std::path::{Path, PathBuf};
+use std::sync::mpsc::{self, Receiver};
+use std::sync::Mutex;
+
+/// What's watched, and the changes to it seen so far.
+#[derive(Resource)]
+pub struct Watched {
+    config: Option<PathBuf>,
+    scripts: Option<PathBuf>,
+    changes: Mutex<Receiver<notify::Result<Event>>>,
+    /// Watching for as long as it's kept.
+    _watcher: Option<RecommendedWatcher>,
+}
+
+impl Watched {
+    /// Watches `config` and the `scripts` directory.
+    pub fn start(config: Option<PathBuf>, scripts: Option<PathBuf>) -> notify::Result<Self> {
+        let (sender, changes) = mpsc::channel();
+        let mut watcher = notify::recommended_watcher(sender)?;
+        // editors often save by swapping in a new file, which is only seen
+        // from the directory it's in
+        let config = config.map(|config| absolute(&config));
+        if let Some(dir) = config.as_deref().and_then(Path::parent) {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+        let scripts = scripts.map(|scripts| absolute(&scripts));
+        if let Some(dir) = &scripts {
+            watcher.watch(dir, RecursiveMode::NonRecursive)?;
+        }
+        Ok(Watched {
+            config,
+            scripts,
+            changes: Mutex::new(changes),
+            _watcher: Some(watcher),
+        })
+    }
+
+    /// Whether the config file and any scripts have changed since last asked.
+    fn changed(&self) -> (bool, bool) {
+        let (mut config, mut scripts) = (false, false);
+        for event in self.changes.lock().unwrap().try_iter() {
+            let event = match event {
+                Ok(event) => event,
+                Err(e) => {
+                    warn!("stopped watching for changes: {}", e);
+                    continue;
+                }
+            };
+            if !matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                continue;
+            }
+            for path in &event.paths {
+                config |= self.config.as_deref() == Some(path.as_path());
+                scripts |= self.is_script(path);
+            }
+        }
+        (config, scripts)
+    }
+
+    fn is_script(&self, path: &Path) -> bool {
+        self.scripts.as_deref() == path.parent() && scripting::is_script(path)
+    }
+}
+
+/// `path`, from the working directory if it's relative, to compare with the
+/// paths of the changes seen.
+fn absolute(path: &Path) -> PathBuf {
+    path.canonicalize().unwrap_or_else(|_| path.to_path_buf())
+}
+
+/// Loads whatever's changed again.
+pub fn reload_changes(
+    watched: Res<Watched>,
+    mut settings: ResMut<Settings>,
+    mut scripts: ResMut<Scripts>,
+    mut inputs: Option<ResMut<InputLog>>,
+) {
+    let (config_changed, scripts_changed) = watched.changed();
+    if let (true, Some(path)) = (config_changed, &watched.config) {
+        match Config::load(path) {
+            Ok(config) => {
+                info!("reloaded {}", path.display());
+                if let Some(inputs) = inputs.as_mut() {
+                    inputs.note(|tick| Input::Config {
+                        tick,
+                        config: config.clone(),
+                    });
+                }
+                settings.0 = config;
+            }
+            Err(e) => warn!("could not reload {}: {}", path.display(), e),
+        }
+    }
+    if let (true, Some(dir)) = (scripts_changed, &watched.scripts) {
+        match scripts.add_dir(dir) {
+            Ok(()) => info!("reloaded the scripts in {}", dir.display()),
+            Err(e) => warn!("could not reload {}: {}", dir.display(), e),
+        }
+    }
+}
+
+/// Watches the config file and the scripts with `--watch`.
+pub struct HotReloadPlugin {
+    pub config: Option<PathBuf>,
+    pub scripts: Option<PathBuf>,
+}
+
+impl HotReloadPlugin {
+    /// Reads `--watch`, and what there is to watch from `--config=FILE` and
+    /// `--scripts=DIR`.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.watch.then(|| HotReloadPlugin {
+            config: cli.config.clone(),
+            scripts: cli.scripts.clone(),
+        })
+    }
+}
+
+impl Plugin for HotReloadPlugin {
+    fn build(&self, app: &mut App) {
+        let watched = Watched::start(self.config.clone(), self.scripts.clone())
+            .unwrap_or_else(|e| panic!("could not watch for changes: {}", e));
+        app.insert_resource(watched);
+        app.add_systems(Update, reload_changes);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_ecs::system::RunSystemOnce;
+    use notify::event::{DataChange, ModifyKind};
+    use std::fs;
+    use std::sync::mpsc::Sender;
+
+    /// A world watching `config`, with the changes to it sent by hand rather
+    /// than waited on.
+    fn watching(config: &Path) -> (World, Sender<notify::Result<Event>>) {
+        let (sender, changes) = mpsc::channel();
+        let mut world = World::new();
+        world.insert_resource(Settings::default());
+        world.insert_resource(Scripts::default());
+        world.insert_resource(Watched {
+            config: Some(config.to_path_buf()),
+            scripts: None,
+            changes: Mutex::new(changes),
+            _watcher: None,
+        });
+        (world, sender)
+    }
+
+    #[test]
+    fn reloads_the_config_once_its_changed() {
+        let path = std::env::temp_dir().join(format!("westworld-{}.ron", std::process::id()));
+        fs::write(&path, "(thirst_level: 9)").unwrap();
+        let (mut world, changes) = watching(&path);
+
+        world.run_system_once(reload_changes).unwrap();
+        assert_eq!(world.resource::<Settings>().thirst_level, 5);
+
+        let saved = Event::new(EventKind::Modify(ModifyKind::Data(DataChange::Content)))
+            .add_path(path.clone());
+        changes.send(Ok(saved)).unwrap();
+        world.run_system_once(reload_changes).unwrap();
+        assert_eq!(world.resource::<Settings>().thirst_level, 9);
+
+        // one that won't load leaves it as it was
+        fs::write(&path, "(thirst_level: ").unwrap();
+        let saved = Event::new(EventKind::Modify(ModifyKind::Any)).add_path(path.clone());
+        changes.send(Ok(saved)).unwrap();
+        world.run_system_once(reload_changes).unwrap();
+        assert_eq!(world.resource::<Settings>().thirst_level, 9);
+        fs::remove_file(path).unwrap();
+    }
+}
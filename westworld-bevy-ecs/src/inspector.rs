@@ -0,0 +1,58 @@
+//! A window onto the agents with bevy-inspector-egui, for watching them and
+//! tweaking their thirst, fatigue and the rest while the simulation runs,
+//! with the [`agent_panel`](crate::agent_panel) down the side. Built with
+//! `--features inspector` and opened with `--inspect`.
+//!
+//! The window brings bevy's own runner with it, which carries on until the
+//! window's closed or Ctrl-C, so what the headless runner does once a run is
+//! over (`--ticks`, `--save`, `--record` and the like) doesn't happen.
+
+use crate::agent_panel::AgentPanelPlugin;
+use crate::cli::Cli;
+use crate::shutdown::Shutdown;
+use bevy::app::{App, AppExit, Last, Plugin, Startup, TerminalCtrlCHandlerPlugin};
+use bevy::camera::Camera2d;
+use bevy::time::TimePlugin;
+use bevy::DefaultPlugins;
+use bevy_app::PluginGroup;
+use bevy_ecs::prelude::*;
+use bevy_inspector_egui::bevy_egui::EguiPlugin;
+use bevy_inspector_egui::quick::WorldInspectorPlugin;
+
+pub struct InspectorPlugin;
+
+impl InspectorPlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.inspect.then_some(InspectorPlugin)
+    }
+}
+
+impl Plugin for InspectorPlugin {
+    fn build(&self, app: &mut App) {
+        // the fixed timestep's already set up, and so is Ctrl-C
+        app.add_plugins(
+            DefaultPlugins
+                .build()
+                .disable::<TimePlugin>()
+                .disable::<TerminalCtrlCHandlerPlugin>(),
+        );
+        app.add_plugins(EguiPlugin::default());
+        app.add_plugins(WorldInspectorPlugin::new());
+        app.add_plugins(AgentPanelPlugin);
+        app.add_systems(Startup, spawn_camera);
+        app.add_systems(Last, exit_on_shutdown);
+    }
+}
+
+/// The inspector draws over the view of a camera, though there's nothing
+/// else to see.
+pub fn spawn_camera(mut commands: Commands) {
+    commands.spawn(Camera2d);
+}
+
+/// The window's runner doesn't know about the signal handlers.
+pub fn exit_on_shutdown(shutdown: Option<Res<Shutdown>>, mut exit: MessageWriter<AppExit>) {
+    if shutdown.is_some_and(|shutdown| shutdown.requested()) {
+        exit.write(AppExit::Success);
+    }
+}
@@ -1,17 +1,90 @@
-use std::io::Write;
+//! `--transcript=FILE`: everything the town says at info level written out
+//! plain as well, a blank line after each tick, for runs to be compared line
+//! by line.
 
-pub trait Named<'a> {
-    fn name(&'a self) -> &'a str;
+use bevy_app::{App, FixedLast};
+use bevy_log::tracing_subscriber::layer::{Context, Layer};
+use bevy_log::LogPlugin;
+use std::fmt::{Debug, Write as _};
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+use tracing::field::{Field, Visit};
+use tracing::{Event, Level, Subscriber};
+
+/// Where everything said is also written, from `--transcript`.
+static TRANSCRIPT: OnceLock<Mutex<BufWriter<File>>> = OnceLock::new();
+
+/// Writes everything said to `path` as well, a tick's worth at a time and
+/// plain whatever the log's format, for runs to be compared line by line.
+pub fn set_transcript(path: &Path) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    if TRANSCRIPT.set(Mutex::new(file)).is_err() {
+        panic!("the transcript's already set");
+    }
+    Ok(())
+}
+
+/// Writes `line` to the transcript, if there is one.
+fn transcribe(line: &str) {
+    if let Some(transcript) = TRANSCRIPT.get() {
+        let mut transcript = transcript.lock().unwrap();
+        writeln!(transcript, "{}", line).expect("could not write the transcript");
+    }
+}
+
+/// Marks the end of a tick in the transcript, with a blank line.
+pub fn end_tick() {
+    transcribe("");
 }
 
-pub trait Log {
-    fn log<'a, N: Named<'a>>(&self, named: &'a N, msg: String);
+/// Finishes writing the transcript, if there is one.
+pub fn finish_transcript() {
+    if let Some(transcript) = TRANSCRIPT.get() {
+        transcript
+            .lock()
+            .unwrap()
+            .flush()
+            .expect("could not write the transcript");
+    }
+}
+
+/// Has `log` write what's said to the transcript at `path` as well, with
+/// each tick ended by a blank line.
+pub fn transcribe_to(app: &mut App, log: LogPlugin, path: &Path) -> LogPlugin {
+    set_transcript(path).unwrap_or_else(|e| panic!("could not write {}: {}", path.display(), e));
+    app.add_systems(FixedLast, end_tick);
+    LogPlugin {
+        custom_layer: |_| Some(Box::new(Transcriber)),
+        ..log
+    }
+}
+
+/// Writes the town's info messages to the transcript, leaving out the
+/// engine's and any more verbose ones.
+struct Transcriber;
+
+impl<S: Subscriber> Layer<S> for Transcriber {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let metadata = event.metadata();
+        if *metadata.level() != Level::INFO
+            || !metadata.target().starts_with(env!("CARGO_CRATE_NAME"))
+        {
+            return;
+        }
+        let mut message = String::new();
+        event.record(&mut MessageField(&mut message));
+        transcribe(&message);
+    }
 }
 
-pub struct ConsoleLog;
+struct MessageField<'a>(&'a mut String);
 
-impl Log for ConsoleLog {
-    fn log<'a, N: Named<'a>>(&self, named: &'a N, msg: String) {
-        println!("{}: {}", named.name(), msg);
+impl Visit for MessageField<'_> {
+    fn record_debug(&mut self, field: &Field, value: &dyn Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        }
     }
 }
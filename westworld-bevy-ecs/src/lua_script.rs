@@ -0,0 +1,84 @@
+//! The Lua backend for the scripted states, built with `--features lua`.
+//!
+//! A Lua script returns a table of its hooks, each handed a table of the
+//! miner: his `name`, `location`, `gold`, `wealth`, `thirst`, `fatigue` and
+//! `hunger`, and the `day` and `hour`. `update` answers `nil` to carry on,
+//! `"pop"`, `"quit"`, or `{ switch = "QuenchThirst" }` or
+//! `{ push = "TravelTo(Saloon)" }`.
+
+use crate::scripting::{Agent, ScriptError, ScriptedState};
+use fsm::StateTransition;
+use mlua::{Function, Lua, RegistryKey, Table, Value};
+use std::sync::Mutex;
+
+impl From<mlua::Error> for ScriptError {
+    fn from(e: mlua::Error) -> Self {
+        ScriptError::Script(Box::new(e))
+    }
+}
+
+/// A Lua script, in a Lua of its own.
+pub struct LuaScript {
+    lua: Mutex<Lua>,
+    /// The script's table of hooks, kept in the Lua registry.
+    hooks: RegistryKey,
+}
+
+impl LuaScript {
+    /// Runs `source`, called `name` in its errors, for its hooks.
+    pub fn new(name: &str, source: &str) -> Result<Self, ScriptError> {
+        let lua = Lua::new();
+        let hooks: Table = lua.load(source).set_name(name).eval()?;
+        let hooks = lua.create_registry_value(hooks)?;
+        Ok(LuaScript {
+            lua: Mutex::new(lua),
+            hooks,
+        })
+    }
+}
+
+impl ScriptedState for LuaScript {
+    fn call(&self, hook: &str, agent: &Agent) -> Result<StateTransition<String>, ScriptError> {
+        let lua = self.lua.lock().unwrap();
+        let hooks: Table = lua.registry_value(&self.hooks)?;
+        let hook = match hooks.get::<_, Option<Function>>(hook)? {
+            Some(hook) => hook,
+            None => return Ok(StateTransition::None),
+        };
+
+        let miner = lua.create_table()?;
+        miner.set("name", agent.name.as_str())?;
+        miner.set("location", agent.location.as_str())?;
+        miner.set("gold", agent.gold)?;
+        miner.set("wealth", agent.wealth)?;
+        miner.set("thirst", agent.thirst)?;
+        miner.set("fatigue", agent.fatigue)?;
+        miner.set("hunger", agent.hunger)?;
+        miner.set("day", agent.day)?;
+        miner.set("hour", agent.hour)?;
+        let asked = hook.call(miner)?;
+        transition(asked)
+    }
+}
+
+/// The transition a script's `update` asked for.
+fn transition(asked: Value) -> Result<StateTransition<String>, ScriptError> {
+    match asked {
+        Value::Nil => Ok(StateTransition::None),
+        Value::String(asked) => match asked.to_str()? {
+            "pop" => Ok(StateTransition::Pop),
+            "quit" => Ok(StateTransition::Quit),
+            other => Err(ScriptError::BadAnswer(format!("{:?}", other))),
+        },
+        Value::Table(asked) => {
+            if let Some(name) = asked.get::<_, Option<String>>("switch")? {
+                Ok(StateTransition::Switch(name))
+            } else if let Some(name) = asked.get::<_, Option<String>>("push")? {
+                Ok(StateTransition::Push(name))
+            } else {
+                Err(ScriptError::BadAnswer(format!("{:?}", asked)))
+            }
+        }
+        other => Err(ScriptError::BadAnswer(format!("{:?}", other))),
+    }
+}
@@ -1,17 +1,104 @@
 use std::fmt;
+use std::ops::Deref;
 
-use bevy_app::App;
+use actions::ActionsPlugin;
+use bandit::BanditPlugin;
+use barfly::BarFlyPlugin;
+use bevy_app::{PluginGroup, PluginGroupBuilder};
 use bevy_ecs::prelude::*;
-use bevy_log::LogPlugin;
+use bevy_reflect::Reflect;
+use clock::ClockPlugin;
+use current_state::CurrentStatePlugin;
+use economy::EconomyPlugin;
+use game_ai::config::Config;
+use goldmine::GoldminePlugin;
+use messaging::MessagingPlugin;
 use miner::MinerPlugin;
+use narration::NarrationPlugin;
+use occupancy::OccupancyPlugin;
+use partner::PartnerPlugin;
+use path_planner::PathPlannerPlugin;
+use reflection::ReflectionPlugin;
+use riders::RidersPlugin;
+use rng::RngPlugin;
+use roads::RoadsPlugin;
+use serde::{Deserialize, Serialize};
+use sheriff::SheriffPlugin;
+use timestep::TimestepPlugin;
+use weather::WeatherPlugin;
 
-mod fsm;
+mod actions;
+#[cfg(feature = "inspector")]
+mod agent_panel;
+mod agents;
+mod bandit;
+mod barfly;
+mod batch;
+#[cfg(not(target_arch = "wasm32"))]
+mod benchmark;
+mod brains;
+mod cli;
+mod clock;
+mod controls;
+mod current_state;
+#[cfg(not(target_arch = "wasm32"))]
+mod dashboard;
+#[cfg(not(target_arch = "wasm32"))]
+mod determinism;
+mod diagnostics;
+mod economy;
+mod event_log;
+mod goldmine;
+#[cfg(not(target_arch = "wasm32"))]
+mod hot_reload;
+#[cfg(feature = "inspector")]
+mod inspector;
 mod log;
+#[cfg(feature = "lua")]
+mod lua_script;
+mod messaging;
+mod metrics;
 mod miner;
-// mod timer;
+mod narration;
+#[cfg(not(target_arch = "wasm32"))]
+mod native;
+mod occupancy;
+mod partner;
+mod path_planner;
+mod population;
+mod reflection;
+#[cfg(not(target_arch = "wasm32"))]
+mod remote;
+mod replay;
+#[cfg(not(target_arch = "wasm32"))]
+mod rerun;
+#[cfg(feature = "rhai")]
+mod rhai_script;
+mod riders;
+mod rng;
+mod roads;
+mod scenario;
+mod scene;
+mod scripting;
+mod sheriff;
+mod shutdown;
+mod snapshot;
+#[cfg(feature = "websocket")]
+mod stream;
+#[cfg(test)]
+mod testing;
+mod timer;
+mod timestep;
+mod trace;
+mod weather;
+#[cfg(target_arch = "wasm32")]
+mod web;
 
+#[derive(Component)]
 pub struct Person;
 
+#[derive(Component, Reflect)]
+#[reflect(Component)]
 pub struct Name(String);
 
 impl fmt::Display for Name {
@@ -20,24 +107,96 @@ impl fmt::Display for Name {
     }
 }
 
-#[derive(Copy, Clone, Debug, PartialEq)]
+#[derive(Component, Reflect, Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+#[reflect(Component)]
 pub enum Location {
     Goldmine,
     Bank,
     Shack,
     Saloon,
+    /// Where the bandit lies low between robberies.
+    Hideout,
+    /// The sheriff's office, with a cell out back.
+    Jail,
+    /// Out prospecting for a new seam once the goldmine's played out.
+    Hills,
+    /// Where the preacher holds his Sunday meetin'.
+    Church,
 }
 
-fn runner(mut app: App) {
-    loop {
-        app.update();
+/// The [`Config`] the world's running with, as a resource: the one it was
+/// started with, unless it's been changed and reloaded since.
+#[derive(Resource, Default)]
+pub struct Settings(pub Config);
+
+impl Deref for Settings {
+    type Target = Config;
+
+    fn deref(&self) -> &Config {
+        &self.0
+    }
+}
+
+/// Names a set of systems for others to be ordered before or after.
+#[derive(SystemSet, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct Label(pub &'static str);
+
+/// The parts of a tick, run one after the other in
+/// [`FixedUpdate`](bevy_app::FixedUpdate). Systems within a phase are
+/// ordered among themselves with [`Label`]s.
+#[derive(SystemSet, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub enum Phase {
+    /// The agents hear the messages sent them last tick, and the town counts
+    /// who's where.
+    Perceive,
+    /// The weather, prices and the goldmine change, and then each agent takes
+    /// its turn.
+    Decide,
+    /// Whoever's moved goes on their way.
+    Act,
+    /// The messages sent this tick are posted, and the delayed ones that have
+    /// come due are sent on for next tick.
+    Dispatch,
+    /// Whatever's watching the agents catches up with what they did.
+    Log,
+}
+
+/// Everything that makes up the world, for the example itself and for
+/// [`batch`] runs.
+pub struct WorldPlugins;
+
+impl PluginGroup for WorldPlugins {
+    fn build(self) -> PluginGroupBuilder {
+        PluginGroupBuilder::start::<Self>()
+            .add(TimestepPlugin)
+            .add(RngPlugin)
+            .add(ClockPlugin)
+            .add(MessagingPlugin)
+            .add(EconomyPlugin)
+            .add(WeatherPlugin)
+            .add(PathPlannerPlugin)
+            .add(RoadsPlugin)
+            .add(OccupancyPlugin)
+            .add(GoldminePlugin)
+            .add(MinerPlugin)
+            .add(PartnerPlugin)
+            .add(BarFlyPlugin)
+            .add(RidersPlugin)
+            .add(BanditPlugin)
+            .add(SheriffPlugin)
+            .add(CurrentStatePlugin)
+            .add(NarrationPlugin)
+            .add(ActionsPlugin)
+            .add(ReflectionPlugin)
     }
 }
 
+#[cfg(not(target_arch = "wasm32"))]
+fn main() {
+    native::run();
+}
+
+#[cfg(target_arch = "wasm32")]
 fn main() {
-    App::build()
-        .add_plugin(LogPlugin)
-        .add_plugin(MinerPlugin)
-        .set_runner(runner)
-        .run();
+    web::run();
 }
@@ -0,0 +1,193 @@
+use crate::actions::AgentAction;
+use crate::replay::{observe, observed, Observers, Record};
+use crate::timer::Timer;
+use crate::{Label, Name, Phase};
+use bevy_app::{App, FixedFirst, FixedUpdate, Plugin};
+use bevy_ecs::entity::{EntityMapper, MapEntities};
+use bevy_ecs::prelude::*;
+use serde::{Deserialize, Serialize};
+
+pub static SEND_MSG_IMMEDIATELY: usize = 0;
+
+/// Label of the system posting the messages the agents sent this tick.
+pub static POST_MESSAGES: Label = Label("post_messages");
+/// Label of the system sending the delayed messages that fall due next tick.
+pub static DELIVER_DELAYED_MESSAGES: Label = Label("deliver_delayed_messages");
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Message {
+    HiHoneyImHome,
+    StewReady,
+    Insult,
+    Punch,
+    /// The bank's alarm, sent in the robber's name so the sheriff knows who
+    /// he's after.
+    BankRobbed,
+    Arrested,
+    /// A miner at the goldmine asking to dig.
+    RequestDigSlot,
+    /// The goldmine has room for him.
+    DigSlotGranted,
+    /// The goldmine's full, so he's been put in the queue.
+    DigSlotDenied,
+    /// A miner's done digging, or done waiting, and gives up his place.
+    DigSlotFreed,
+    /// The path an agent asked his planner for has been found.
+    PathReady,
+    /// His planner couldn't find the path he asked for.
+    PathNotFound,
+}
+
+/// A message from one agent to another, delivered as a bevy message.
+#[derive(Message, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Telegram {
+    pub sender: Entity,
+    pub receiver: Entity,
+    pub message: Message,
+}
+
+impl MapEntities for Telegram {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        self.sender = entity_mapper.get_mapped(self.sender);
+        self.receiver = entity_mapper.get_mapped(self.receiver);
+    }
+}
+
+/// The messages an agent has sent this tick, and the actions it's taken.
+/// State handlers write to it, [`post_messages`] hands the messages over to
+/// the dispatcher and [`post_actions`](crate::actions::post_actions) sends
+/// the actions on to be shown.
+#[derive(Component)]
+pub struct Outbox {
+    owner: Entity,
+    queued: Vec<(usize, Telegram)>,
+    actions: Vec<AgentAction>,
+}
+
+impl Outbox {
+    pub fn new(owner: Entity) -> Self {
+        Outbox {
+            owner,
+            queued: vec![],
+            actions: vec![],
+        }
+    }
+
+    /// The agent the outbox belongs to, for messages it sends itself.
+    pub fn owner(&self) -> Entity {
+        self.owner
+    }
+
+    /// Sends a message `delay` updates from now, or on the next update if the
+    /// delay is [`SEND_MSG_IMMEDIATELY`].
+    pub fn dispatch(&mut self, delay: usize, receiver: Entity, message: Message) {
+        let telegram = Telegram {
+            sender: self.owner,
+            receiver,
+            message,
+        };
+        self.queued.push((delay, telegram));
+    }
+
+    /// Takes the messages sent since the last time, with their delays.
+    pub fn drain(&mut self) -> impl Iterator<Item = (usize, Telegram)> + '_ {
+        self.queued.drain(..)
+    }
+
+    /// Lets whatever's presenting the world know the agent did `action`.
+    pub fn act(&mut self, action: AgentAction) {
+        self.actions.push(action);
+    }
+
+    /// Takes the actions taken since the last time.
+    pub fn drain_actions(&mut self) -> impl Iterator<Item = AgentAction> + '_ {
+        self.actions.drain(..)
+    }
+}
+
+/// Telegrams waiting for their delivery time.
+pub type MessageTimer = Timer<Telegram>;
+
+/// Delivers immediate messages straight away and schedules the rest.
+pub fn post_messages(
+    mut timer: ResMut<MessageTimer>,
+    mut log: Observers,
+    mut outboxes: Query<&mut Outbox>,
+    names: Query<&Name>,
+    mut telegrams: MessageWriter<Telegram>,
+) {
+    // in the order the agents were spawned, whichever tables they've since
+    // moved between as components come and go
+    let in_turn = outboxes
+        .iter_mut()
+        .sort_by_key::<Entity, _>(|entity| entity.index_u32());
+    for mut outbox in in_turn {
+        for (delay, telegram) in outbox.drain() {
+            if observed(&log) {
+                let name = |entity| {
+                    names
+                        .get(entity)
+                        .map_or_else(|_| format!("{:?}", entity), Name::to_string)
+                };
+                observe(&mut log, |tick| Record::Message {
+                    tick,
+                    sender: name(telegram.sender),
+                    receiver: name(telegram.receiver),
+                    message: telegram.message,
+                    delay,
+                });
+            }
+            if delay == SEND_MSG_IMMEDIATELY {
+                telegrams.write(telegram);
+            } else {
+                timer.schedule(delay, telegram);
+            }
+        }
+    }
+}
+
+/// Delivers the delayed messages that fall due next tick.
+pub fn deliver_delayed_messages(
+    mut timer: ResMut<MessageTimer>,
+    mut telegrams: MessageWriter<Telegram>,
+) {
+    for telegram in timer.tick() {
+        telegrams.write(telegram);
+    }
+}
+
+/// Keeps messages of type `M` until the end of the tick after the one they
+/// were sent in. Bevy's own [`add_message`](App::add_message) keeps them by
+/// the frame, which may run any number of ticks, or none.
+pub fn add_tick_messages<M: bevy_ecs::message::Message>(app: &mut App) {
+    app.init_resource::<Messages<M>>();
+    app.add_systems(FixedFirst, update_messages::<M>);
+}
+
+pub fn update_messages<M: bevy_ecs::message::Message>(mut messages: ResMut<Messages<M>>) {
+    messages.update();
+}
+
+/// Messages are posted after the agents have updated, and read by each kind
+/// of agent's handler before they next update. Delayed messages are sent at
+/// the same point the tick before they fall due, so at the end of a tick
+/// every message still to be read is in the current batch of messages.
+pub struct MessagingPlugin;
+
+impl Plugin for MessagingPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(MessageTimer::new());
+        add_tick_messages::<Telegram>(app);
+        app.add_systems(
+            FixedUpdate,
+            post_messages.in_set(POST_MESSAGES).in_set(Phase::Dispatch),
+        );
+        app.add_systems(
+            FixedUpdate,
+            deliver_delayed_messages
+                .in_set(DELIVER_DELAYED_MESSAGES)
+                .in_set(Phase::Dispatch)
+                .after(POST_MESSAGES),
+        );
+    }
+}
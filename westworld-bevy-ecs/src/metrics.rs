@@ -0,0 +1,251 @@
+//! Counters and gauges kept over a run, reported when it's over, for keeping
+//! an eye on long headless runs. Built with the `prometheus` feature, they can
+//! also be scraped while the run goes with `--prometheus=ADDRESS`.
+
+use crate::cli::Cli;
+use crate::goldmine::Goldmine;
+use crate::miner::Miner;
+use crate::replay::Record;
+use crate::Label;
+use bevy_app::{App, FixedLast, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use std::collections::BTreeMap;
+use std::fmt;
+
+#[derive(Resource, Clone, Debug, Default, PartialEq)]
+pub struct Metrics {
+    /// Ticks simulated.
+    pub ticks: u64,
+    /// State transitions, by the state the agent went into.
+    pub transitions: BTreeMap<String, u64>,
+    /// Messages dispatched, by message.
+    pub messages: BTreeMap<String, u64>,
+    /// Nuggets dug out of the goldmine, across every seam.
+    pub gold_mined: i64,
+    /// Gold the miners have in the bank.
+    pub gold_banked: i64,
+    /// Nuggets left in the goldmine.
+    pub mine_reserve: i64,
+}
+
+impl Metrics {
+    /// Counts what an agent did.
+    pub fn record(&mut self, record: &Record) {
+        let (counts, key) = match record {
+            // the running state is the last on the stack
+            Record::Transition { to, .. } => (
+                &mut self.transitions,
+                to.rsplit(" > ").next().unwrap_or_default().to_string(),
+            ),
+            Record::Message { message, .. } => (&mut self.messages, format!("{:?}", message)),
+        };
+        *counts.entry(key).or_default() += 1;
+    }
+
+    /// The metrics in Prometheus' text exposition format.
+    #[cfg(feature = "prometheus")]
+    pub fn exposition(&self) -> String {
+        let mut text = String::new();
+        let mut metric = |name: &str, kind: &str, help: &str, samples: Vec<(String, i64)>| {
+            text += &format!("# HELP westworld_{} {}\n", name, help);
+            text += &format!("# TYPE westworld_{} {}\n", name, kind);
+            for (labels, value) in samples {
+                text += &format!("westworld_{}{} {}\n", name, labels, value);
+            }
+        };
+        let labelled = |label: &str, counts: &BTreeMap<String, u64>| {
+            counts
+                .iter()
+                .map(|(key, &count)| (format!("{{{}=\"{}\"}}", label, key), count as i64))
+                .collect()
+        };
+        let unlabelled = |value| vec![(String::new(), value)];
+
+        metric(
+            "ticks_total",
+            "counter",
+            "Ticks simulated.",
+            unlabelled(self.ticks as i64),
+        );
+        metric(
+            "transitions_total",
+            "counter",
+            "State transitions, by the state entered.",
+            labelled("state", &self.transitions),
+        );
+        metric(
+            "messages_total",
+            "counter",
+            "Messages dispatched, by message.",
+            labelled("message", &self.messages),
+        );
+        metric(
+            "gold_mined_total",
+            "counter",
+            "Nuggets dug out of the goldmine.",
+            unlabelled(self.gold_mined),
+        );
+        metric(
+            "gold_banked",
+            "gauge",
+            "Gold the miners have in the bank.",
+            unlabelled(self.gold_banked),
+        );
+        metric(
+            "mine_reserve",
+            "gauge",
+            "Nuggets left in the goldmine.",
+            unlabelled(self.mine_reserve),
+        );
+        text
+    }
+}
+
+impl fmt::Display for Metrics {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        writeln!(f, "ticks simulated: {}", self.ticks)?;
+        writeln!(f, "gold mined: {}", self.gold_mined)?;
+        writeln!(f, "gold in the bank: {}", self.gold_banked)?;
+        writeln!(f, "gold left in the mine: {}", self.mine_reserve)?;
+        writeln!(f, "transitions into each state:")?;
+        for (state, count) in &self.transitions {
+            writeln!(f, "  {:<28}{:>6}", state, count)?;
+        }
+        writeln!(f, "messages dispatched:")?;
+        for (message, count) in &self.messages {
+            writeln!(f, "  {:<28}{:>6}", message, count)?;
+        }
+        Ok(())
+    }
+}
+
+/// Counts the tick and takes the gauges' readings, once everyone's done.
+pub fn measure(mut metrics: ResMut<Metrics>, mines: Query<&Goldmine>, miners: Query<&Miner>) {
+    metrics.ticks += 1;
+    metrics.gold_mined = mines.iter().map(|mine| mine.mined() as i64).sum();
+    metrics.mine_reserve = mines.iter().map(|mine| mine.reserve() as i64).sum();
+    metrics.gold_banked = miners.iter().map(|miner| miner.wealth() as i64).sum();
+}
+
+/// Reports the metrics, once the run is over.
+pub fn finish(world: &mut World) {
+    if let Some(metrics) = world.remove_resource::<Metrics>() {
+        for line in metrics.to_string().lines() {
+            info!("{}", line);
+        }
+    }
+}
+
+/// Keeps [`Metrics`] with `--metrics`, or with `--prometheus=ADDRESS` when
+/// built with the `prometheus` feature, which also serves them there. Call
+/// [`finish`] once the run is over.
+pub struct MetricsPlugin {
+    #[cfg(feature = "prometheus")]
+    pub prometheus: Option<String>,
+}
+
+impl MetricsPlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        #[cfg(feature = "prometheus")]
+        let enabled = cli.metrics || cli.prometheus.is_some();
+        #[cfg(not(feature = "prometheus"))]
+        let enabled = cli.metrics;
+        if !enabled {
+            return None;
+        }
+        Some(MetricsPlugin {
+            #[cfg(feature = "prometheus")]
+            prometheus: cli.prometheus.clone(),
+        })
+    }
+}
+
+impl Plugin for MetricsPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Metrics>();
+        app.add_systems(FixedLast, measure.in_set(MEASURE));
+        #[cfg(feature = "prometheus")]
+        if let Some(address) = &self.prometheus {
+            let exposition = prometheus::serve(address)
+                .unwrap_or_else(|e| panic!("could not serve metrics on {}: {}", address, e));
+            info!("serving metrics on http://{}/metrics", address);
+            app.insert_resource(exposition);
+            app.add_systems(FixedLast, prometheus::publish.after(MEASURE));
+        }
+    }
+}
+
+/// Label of the system taking the gauges' readings.
+pub static MEASURE: Label = Label("measure");
+
+/// A bare-bones HTTP endpoint for Prometheus to scrape, answering every
+/// request with the latest metrics.
+#[cfg(feature = "prometheus")]
+pub mod prometheus {
+    use super::Metrics;
+    use bevy_ecs::prelude::*;
+    use std::io::{self, Read, Write};
+    use std::net::TcpListener;
+    use std::sync::{Arc, Mutex};
+    use std::thread;
+
+    /// The metrics as last published, shared with the serving thread.
+    #[derive(Resource, Clone, Default)]
+    pub struct Exposition(Arc<Mutex<String>>);
+
+    /// Starts answering scrapes on `address` in the background.
+    pub fn serve(address: &str) -> io::Result<Exposition> {
+        let listener = TcpListener::bind(address)?;
+        let exposition = Exposition::default();
+        let published = exposition.clone();
+        thread::spawn(move || {
+            for mut stream in listener.incoming().flatten() {
+                // the request doesn't matter, only that it's been sent
+                let _ = stream.read(&mut [0; 1024]);
+                let body = published.0.lock().unwrap().clone();
+                let _ = write!(
+                    stream,
+                    "HTTP/1.0 200 OK\r\nContent-Type: text/plain; version=0.0.4\r\nContent-Length: {}\r\n\r\n{}",
+                    body.len(),
+                    body
+                );
+            }
+        });
+        Ok(exposition)
+    }
+
+    pub fn publish(metrics: Res<Metrics>, exposition: Res<Exposition>) {
+        *exposition.0.lock().unwrap() = metrics.exposition();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::Message;
+
+    #[test]
+    fn transitions_count_towards_the_state_entered() {
+        let mut metrics = Metrics::default();
+        let transition = |to: &str| Record::Transition {
+            tick: 1,
+            agent: "Miner Bob".to_string(),
+            from: "GoHomeAndSleepTilRested".to_string(),
+            to: to.to_string(),
+        };
+        metrics.record(&transition("EnterMineAndDigForNugget > TravelTo(Goldmine)"));
+        metrics.record(&transition("EnterMineAndDigForNugget"));
+        metrics.record(&Record::Message {
+            tick: 1,
+            sender: "Elsa".to_string(),
+            receiver: "Miner Bob".to_string(),
+            message: Message::StewReady,
+            delay: 0,
+        });
+
+        assert_eq!(metrics.transitions["TravelTo(Goldmine)"], 1);
+        assert_eq!(metrics.transitions["EnterMineAndDigForNugget"], 1);
+        assert_eq!(metrics.messages["StewReady"], 1);
+    }
+}
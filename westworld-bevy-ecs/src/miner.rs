@@ -1,90 +1,363 @@
-use std::ops::{Deref, DerefMut};
-
-use crate::fsm::{self, Handler};
-use crate::{
-    log::{ConsoleLog, Log, Named},
-    Location, Name,
-};
-use bevy_app::{AppBuilder, Plugin};
+use std::ops::DerefMut;
+
+use crate::actions::AgentAction;
+use crate::agents::{spawn_agent, AgentHandler, FsmPlugin, ParallelAgentHandler, Run};
+use crate::brains::{Brain, Brains};
+use crate::clock::Clock;
+use crate::economy::Economy;
+use crate::goldmine::{Claim, Goldmine};
+use crate::messaging::{Message, Outbox, Telegram, SEND_MSG_IMMEDIATELY};
+use crate::occupancy::Occupancy;
+use crate::path_planner::{plan_paths, NavGraph, Path, PathNotFound, PathPlanner, PathReady};
+use crate::rng::{Streams, WorldRng};
+use crate::roads::Roads;
+use crate::scenario::Scenario;
+use crate::scripting::{Scripted, Scripts};
+use crate::weather::Weather;
+use crate::{Label, Location, Name, Phase, Settings};
+use bevy_app::{App, FixedUpdate, Plugin, Startup};
+use bevy_ecs::entity::{EntityMapper, MapEntities};
 use bevy_ecs::prelude::*;
+use bevy_ecs::query::QueryItem;
+use bevy_ecs::system::SystemParamItem;
 use bevy_log::prelude::*;
+use bevy_reflect::Reflect;
+use game_ai::config::Config;
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub static HUNGER_LEVEL: i32 = 8; // above this value a miner is hungry
+pub static THIRST_PER_HOUR: i32 = 1; // how much thirstier a miner gets every hour
+pub static HUNGER_PER_HOUR: i32 = 1; // how much hungrier hard work makes him every hour
+pub static FATIGUE_PER_HOUR: i32 = 1; // how much wearier hard work makes him every hour
+pub static REST_PER_HOUR: i32 = 1; // how much fatigue every hour's sleep takes off
+pub static CARD_STAKE: i32 = 1; // gold a hand of cards is played for
 
-pub static COMFORT_LEVEL: i32 = 5; // the amount of gold a miner must have before he feels comfortable
-pub static MAX_NUGGETS: i32 = 3; // the amount of nuggets a miner can carry
-pub static THIRST_LEVEL: i32 = 5; // above this value a miner is thirsty
-pub static TIREDNESS_THRESHOLD: i32 = 5; // above this value a miner is sleepy
+/// Label of the system updating the miners, for systems that react to what they did.
+pub static UPDATE_MINERS: Label = Label("update_miners");
+/// Label of the system delivering messages to the miners.
+pub static DELIVER_TO_MINERS: Label = Label("deliver_to_miners");
+/// Label of the startup system spawning the miners.
+pub static INIT_MINERS: Label = Label("init_miners");
 
-pub type MinerStateData<'a> = (&'a Name, &'a mut Location, &'a mut Miner);
+pub type MinerStateData<'a> = (
+    &'a Name,
+    &'a mut Location,
+    &'a mut Miner,
+    &'a mut Outbox,
+    &'a Economy,
+    &'a Clock,
+    &'a Weather,
+    &'a Scripts,
+    &'a Brains,
+    Claim<'a>,
+    &'a Config,
+    Entity,
+    &'a Occupancy,
+    &'a Roads,
+    &'a mut WorldRng,
+    &'a NavGraph,
+    Planning<'a>,
+);
 //pub type MinerStateData = (Name, Location, Miner);
 
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[component(map_entities)]
+#[reflect(Component)]
 pub struct Miner {
+    pub wife: Option<Entity>,
+    opponent: Option<Entity>,
     gold: i32,
     bank: i32,
     thirst: i32,
     fatigue: i32,
+    hunger: i32,
+    /// Updates left on foot before he gets to the next place on his way.
+    journey: usize,
+    /// The next place on his way, if he's found a way there.
+    #[serde(default)]
+    next_stop: Option<Location>,
+    /// Whether he's been let into the goldmine to dig.
+    #[serde(default)]
+    dig_slot: DigSlot,
+    /// Whether he's waiting to hear back about the path he asked for.
+    #[serde(default)]
+    awaiting_path: bool,
+    /// The places still to pass on the way his planner found him, after the
+    /// next stop.
+    #[serde(default)]
+    route: Vec<Location>,
+}
+
+/// Where a miner stands with the goldmine's dig slots.
+#[derive(Reflect, Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum DigSlot {
+    /// He hasn't asked for one.
+    #[default]
+    None,
+    /// He's asked, and is waiting on an answer or waiting in the queue.
+    Asked,
+    /// He's digging.
+    Granted,
 }
 
 impl Miner {
     pub fn new() -> Self {
         Miner {
+            wife: None,
+            opponent: None,
             gold: 0,
             bank: 0,
             thirst: 0,
             fatigue: 0,
+            hunger: 0,
+            journey: 0,
+            next_stop: None,
+            dig_slot: DigSlot::None,
+            awaiting_path: false,
+            route: vec![],
         }
     }
+    /// A miner starting out with `gold` in his pockets and `wealth` in the bank.
+    pub fn with_gold(mut self, gold: i32, wealth: i32) -> Self {
+        self.gold = gold;
+        self.bank = wealth;
+        self
+    }
     pub fn add_to_gold_carried(&mut self, gold: i32) {
         self.gold += gold;
         if self.gold < 0 {
             self.gold = 0;
         }
     }
-    pub fn increase_fatigue(&mut self) {
-        self.fatigue += 1;
+    /// Works him harder for the hours the `clock` says have passed, and
+    /// likewise for his thirst, hunger and rest.
+    pub fn increase_fatigue(&mut self, clock: &Clock) {
+        self.fatigue += FATIGUE_PER_HOUR * clock.hours_passed();
+    }
+    pub fn decrease_fatigue(&mut self, clock: &Clock) {
+        self.fatigue -= REST_PER_HOUR * clock.hours_passed();
+    }
+    pub fn pockets_full(&self, config: &Config) -> bool {
+        self.gold >= config.max_nuggets
     }
-    pub fn decrease_fatigue(&mut self) {
-        self.fatigue -= 1;
+    pub fn increase_thirst(&mut self, clock: &Clock) {
+        self.thirst += THIRST_PER_HOUR * clock.hours_passed();
     }
-    pub fn pockets_full(&self) -> bool {
-        self.gold >= MAX_NUGGETS
+    pub fn thirsty(&self, config: &Config) -> bool {
+        self.thirst > config.thirst_level
+    }
+    pub fn increase_hunger(&mut self, clock: &Clock) {
+        self.hunger += HUNGER_PER_HOUR * clock.hours_passed();
+    }
+    pub fn hungry(&self) -> bool {
+        self.hunger > HUNGER_LEVEL
+    }
+    pub fn eat(&mut self) {
+        self.hunger = 0;
+    }
+    pub fn can_afford(&self, price: i32) -> bool {
+        self.bank >= price
+    }
+    /// Pays from the miner's savings. Returns false, paying nothing, if he
+    /// can't afford it.
+    pub fn pay(&mut self, price: i32) -> bool {
+        if !self.can_afford(price) {
+            return false;
+        }
+        self.bank -= price;
+        true
     }
-    pub fn increase_thirst(&mut self) {
-        self.thirst += 1;
+    /// Takes winnings into the miner's savings.
+    pub fn win(&mut self, amount: i32) {
+        self.bank += amount;
     }
-    pub fn thirsty(&self) -> bool {
-        self.thirst > THIRST_LEVEL
+    pub fn buy_and_drink_whiskey(&mut self, price: i32) -> bool {
+        if !self.pay(price) {
+            return false;
+        }
+        self.thirst = 0;
+        true
     }
-    pub fn buy_and_drink_whiskey(&mut self) {
-        self.bank -= 2;
+    pub fn drink_water(&mut self) {
         self.thirst = 0;
     }
-    pub fn move_gold_to_bank(&mut self) {
-        self.bank += self.gold;
+    /// Adds `rate` percent to the miner's savings, rounded up, and returns how
+    /// much that was.
+    pub fn add_interest(&mut self, rate: i32) -> i32 {
+        let interest = (self.bank.max(0) * rate + 99) / 100;
+        self.bank += interest;
+        interest
+    }
+    /// Takes `percent` of the miner's savings, rounded down, and returns how
+    /// much that was.
+    pub fn lose_savings(&mut self, percent: i32) -> i32 {
+        let lost = self.bank.max(0) * percent / 100;
+        self.bank -= lost;
+        lost
+    }
+    /// Banks the gold he's carrying, and returns how much that was.
+    pub fn move_gold_to_bank(&mut self) -> i32 {
+        let deposit = self.gold;
+        self.bank += deposit;
         self.gold = 0;
+        deposit
     }
     pub fn wealth(&self) -> i32 {
         self.bank
     }
-    pub fn fatigued(&self) -> bool {
-        self.fatigue > TIREDNESS_THRESHOLD
+    pub fn comfortable(&self, config: &Config) -> bool {
+        self.bank >= config.comfort_level
+    }
+    pub fn gold(&self) -> i32 {
+        self.gold
+    }
+    pub fn thirst(&self) -> i32 {
+        self.thirst
+    }
+    pub fn fatigue(&self) -> i32 {
+        self.fatigue
+    }
+    pub fn hunger(&self) -> i32 {
+        self.hunger
+    }
+    pub fn fatigued(&self, config: &Config) -> bool {
+        self.fatigue > config.tiredness_threshold
+    }
+}
+
+impl MapEntities for Miner {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        self.wife.map_entities(entity_mapper);
+        self.opponent.map_entities(entity_mapper);
     }
 }
 
-#[derive(Copy, Clone)]
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
 pub enum MinerState {
     EnterMineAndDigForNugget,
     VisitBankAndDepositGold,
     QuenchThirst,
     GoHomeAndSleepTilRested,
+    EatStew,
+    FightDrunkard,
+    GoHomeForStew,
+    VisitSaloonForFood,
+    Broke,
+    Prospect,
+    TravelTo(Location),
+    /// Stood outside the goldmine until there's room for him to dig.
+    WaitInQueue,
+    /// Fishing the creek out back of the shack of an evening.
+    GoFishing,
+    /// A few hands of cards at the saloon of an evening.
+    PlayCards,
+    /// At the Sunday meetin'.
+    VisitChurch,
+    /// One of the states written in Lua, by the number of its script.
+    Scripted(u16),
+    /// One of the states compiled to WebAssembly, by the number of its brain.
+    Brain(u16),
+}
+
+impl MinerState {
+    /// Where the miner has to be to be in this state, if anywhere in particular.
+    pub fn location(&self) -> Option<Location> {
+        match self {
+            MinerState::EnterMineAndDigForNugget | MinerState::WaitInQueue => {
+                Some(Location::Goldmine)
+            }
+            MinerState::VisitBankAndDepositGold => Some(Location::Bank),
+            MinerState::GoHomeAndSleepTilRested
+            | MinerState::GoHomeForStew
+            | MinerState::GoFishing => Some(Location::Shack),
+            MinerState::QuenchThirst | MinerState::VisitSaloonForFood | MinerState::PlayCards => {
+                Some(Location::Saloon)
+            }
+            MinerState::Prospect => Some(Location::Hills),
+            MinerState::VisitChurch => Some(Location::Church),
+            MinerState::EatStew
+            | MinerState::FightDrunkard
+            | MinerState::Broke
+            | MinerState::TravelTo(_)
+            | MinerState::Scripted(_)
+            | MinerState::Brain(_) => None,
+        }
+    }
+}
+
+/// Has a miner switching to a state somewhere else walk there first, rather
+/// than turning up straight away.
+fn on_foot(
+    transition: fsm::StateTransition<MinerState>,
+    from: Location,
+) -> fsm::StateTransition<MinerState> {
+    match transition {
+        fsm::StateTransition::Switch(next) => match next.location() {
+            Some(to) if to != from => {
+                fsm::StateTransition::Sequence(vec![MinerState::TravelTo(to), next])
+            }
+            _ => fsm::StateTransition::Switch(next),
+        },
+        transition => transition,
+    }
+}
+
+/// Where a miner heads to work: the goldmine, unless it's played out.
+fn back_to_work(goldmine: &Goldmine) -> MinerState {
+    if goldmine.played_out() {
+        MinerState::Prospect
+    } else {
+        MinerState::EnterMineAndDigForNugget
+    }
+}
+
+/// Whether a miner out working leaves off to drink or eat.
+fn tend_to_needs(
+    miner: &Miner,
+    economy: &Economy,
+    clock: &Clock,
+    config: &Config,
+) -> fsm::StateTransition<MinerState> {
+    if miner.thirsty(config) && !miner.can_afford(economy.whiskey_price) {
+        fsm::StateTransition::Push(MinerState::Broke)
+    } else if miner.thirsty(config) && clock.saloon_open() {
+        fsm::StateTransition::Switch(MinerState::QuenchThirst)
+    } else if miner.hungry() && miner.wife.is_some() {
+        fsm::StateTransition::Switch(MinerState::GoHomeForStew)
+    } else if miner.hungry() && clock.saloon_open() && miner.can_afford(economy.meal_price) {
+        fsm::StateTransition::Switch(MinerState::VisitSaloonForFood)
+    } else {
+        fsm::StateTransition::None
+    }
+}
+
+/// Asks the goldmine to let a miner dig, unless he's asked already.
+fn ask_to_dig(miner: &mut Miner, outbox: &mut Outbox, mine: Entity) {
+    if miner.dig_slot == DigSlot::None {
+        outbox.dispatch(SEND_MSG_IMMEDIATELY, mine, Message::RequestDigSlot);
+        miner.dig_slot = DigSlot::Asked;
+    }
+}
+
+/// Gives up a miner's dig slot, or his place in the queue for one.
+fn stop_digging(miner: &mut Miner, outbox: &mut Outbox, mine: Entity) {
+    if miner.dig_slot != DigSlot::None {
+        outbox.dispatch(SEND_MSG_IMMEDIATELY, mine, Message::DigSlotFreed);
+        miner.dig_slot = DigSlot::None;
+    }
 }
 
 pub struct EnterMineAndDigForNugget;
 
-impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for EnterMineAndDigForNugget {
-    fn on_start(&self, state: &MinerState, (name, location, miner): &mut MinerStateData) {
-        if **location != Location::Goldmine {
-            info!("{}: Walkin' to the goldmine", name);
-            **location = Location::Goldmine;
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for EnterMineAndDigForNugget {
+    fn on_start(
+        &self,
+        _state: &MinerState,
+        (_name, location, miner, outbox, .., mine, _occupancy, _roads, _rng, _graph, _planner): &mut MinerStateData,
+    ) {
+        if **location == Location::Goldmine {
+            ask_to_dig(miner, outbox, *mine);
         }
     }
 
@@ -94,143 +367,903 @@ impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for EnterMineAndDigForNugg
 
     fn update(
         &self,
-        state: &MinerState,
-        (name, location, miner): &mut MinerStateData,
+        _state: &MinerState,
+        (
+            name,
+            _location,
+            miner,
+            outbox,
+            economy,
+            clock,
+            _weather,
+            _scripts,
+            _brains,
+            goldmine,
+            config,
+            mine,
+            _occupancy,
+            _roads,
+            rng,
+            _graph,
+            _planner,
+        ): &mut MinerStateData,
     ) -> fsm::StateTransition<MinerState> {
-        miner.increase_thirst();
+        if !clock.mine_open() {
+            info!("{}: Mine's closin' up fer the night", name);
+            return pastime(
+                miner,
+                clock,
+                rng,
+                fsm::StateTransition::Switch(MinerState::GoHomeAndSleepTilRested),
+            );
+        }
+        if miner.dig_slot != DigSlot::Granted {
+            ask_to_dig(miner, outbox, *mine);
+            return fsm::StateTransition::None;
+        }
+        // whoever digs first gets the last nugget
+        if !goldmine.dig() {
+            return fsm::StateTransition::Switch(MinerState::Prospect);
+        }
+
+        miner.increase_thirst(clock);
+        miner.increase_hunger(clock);
         miner.add_to_gold_carried(1);
-        miner.increase_fatigue();
+        miner.increase_fatigue(clock);
 
-        info!("{}: Pickin' up a nugget", name);
+        outbox.act(AgentAction::DugNugget);
 
-        if miner.pockets_full() {
+        if miner.pockets_full(config) {
             fsm::StateTransition::Switch(MinerState::VisitBankAndDepositGold)
-        } else if miner.thirsty() {
-            fsm::StateTransition::Switch(MinerState::QuenchThirst)
         } else {
-            fsm::StateTransition::None
+            tend_to_needs(miner, economy, clock, config)
         }
     }
 
-    fn on_stop(&self, state: &MinerState, (name, _location, _miner): &mut MinerStateData) {
+    fn on_stop(
+        &self,
+        _state: &MinerState,
+        (name, _location, miner, outbox, .., mine, _occupancy, _roads, _rng, _graph, _planner): &mut MinerStateData,
+    ) {
+        stop_digging(miner, outbox, *mine);
         info!(
             "{}: Ah'm leavin' the goldmine with mah pockets full o' sweet gold",
             name
         );
     }
+
+    // anything he stops to do frees his slot for somebody else
+    fn on_pause(
+        &self,
+        _state: &MinerState,
+        (_name, _location, miner, outbox, .., mine, _occupancy, _roads, _rng, _graph, _planner): &mut MinerStateData,
+    ) {
+        if miner.dig_slot == DigSlot::Granted {
+            stop_digging(miner, outbox, *mine);
+        }
+    }
+
+    fn on_message(
+        &self,
+        _state: &MinerState,
+        (name, _location, miner, ..): &mut MinerStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<MinerState>> {
+        match telegram.message {
+            Message::DigSlotGranted => {
+                miner.dig_slot = DigSlot::Granted;
+                Some(fsm::StateTransition::None)
+            }
+            Message::DigSlotDenied => {
+                info!("{}: Mine's full up. Ah'll wait mah turn", name);
+                Some(fsm::StateTransition::Push(MinerState::WaitInQueue))
+            }
+            _ => None,
+        }
+    }
 }
 
-pub struct VisitBankAndDepositGold;
+/// Waits outside the goldmine for a dig slot to come free, or for the mine to
+/// close.
+pub struct WaitInQueue;
 
-impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for VisitBankAndDepositGold {
-    fn on_start(&self, state: &MinerState, (name, location, miner): &mut MinerStateData) {
-        if **location != Location::Bank {
-            info!("{}: Goin' to the bank. Yes siree", name);
-            **location = Location::Bank;
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for WaitInQueue {
+    fn update(
+        &self,
+        _state: &MinerState,
+        (name, _location, miner, _outbox, _economy, clock, ..): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        miner.increase_thirst(clock);
+        if !clock.mine_open() {
+            return fsm::StateTransition::Pop;
         }
+        info!("{}: Standin' in line fer the mine", name);
+        fsm::StateTransition::None
     }
 
-    fn on_resume(&self, state: &MinerState, miner: &mut MinerStateData) {
-        self.on_start(state, miner);
+    fn on_message(
+        &self,
+        _state: &MinerState,
+        (name, _location, miner, ..): &mut MinerStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<MinerState>> {
+        match telegram.message {
+            Message::DigSlotGranted => {
+                info!("{}: Mah turn at last", name);
+                miner.dig_slot = DigSlot::Granted;
+                Some(fsm::StateTransition::Pop)
+            }
+            _ => None,
+        }
     }
+}
 
+pub struct VisitBankAndDepositGold;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for VisitBankAndDepositGold {
     fn update(
         &self,
-        state: &MinerState,
-        (name, location, miner): &mut MinerStateData,
-    ) -> fsm::StateTransition<MinerState> {
-        miner.increase_thirst();
-        miner.move_gold_to_bank();
-        info!(
-            "{}: Depositing gold. Total savings now: {}",
+        _state: &MinerState,
+        (
             name,
-            miner.wealth()
-        );
+            _location,
+            miner,
+            outbox,
+            _economy,
+            clock,
+            _weather,
+            _scripts,
+            _brains,
+            goldmine,
+            config,
+            _mine,
+            _occupancy,
+            _roads,
+            _rng,
+            _graph,
+            _planner,
+        ): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        miner.increase_thirst(clock);
+        miner.increase_hunger(clock);
+        let deposit = miner.move_gold_to_bank();
+        outbox.act(AgentAction::Deposited(deposit));
 
-        if miner.wealth() >= COMFORT_LEVEL {
+        if miner.comfortable(config) {
             info!(
                 "{}: WooHoo! Rich enough for now. Back home to mah li'lle lady",
                 name
             );
             fsm::StateTransition::Switch(MinerState::GoHomeAndSleepTilRested)
+        } else if !clock.mine_open() {
+            info!("{}: Too late fer diggin'. Back home", name);
+            fsm::StateTransition::Switch(MinerState::GoHomeAndSleepTilRested)
         } else {
-            fsm::StateTransition::Switch(MinerState::EnterMineAndDigForNugget)
+            fsm::StateTransition::Switch(back_to_work(goldmine))
         }
     }
 
-    fn on_stop(&self, state: &MinerState, (name, _location, _miner): &mut MinerStateData) {
+    fn on_stop(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
         info!("{}: Leavin' the bank", name);
     }
 }
 
 pub struct GoHomeAndSleepTilRested;
 
-impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for GoHomeAndSleepTilRested {
-    fn on_start(&self, state: &MinerState, (name, location, miner): &mut MinerStateData) {
-        if **location != Location::Shack {
-            info!("{}: Walkin' home", name);
-            **location = Location::Shack;
-        }
-    }
-
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for GoHomeAndSleepTilRested {
     fn update(
         &self,
-        state: &MinerState,
-        (name, location, miner): &mut MinerStateData,
+        _state: &MinerState,
+        (
+            name,
+            _location,
+            miner,
+            outbox,
+            economy,
+            clock,
+            weather,
+            _scripts,
+            _brains,
+            goldmine,
+            config,
+            _mine,
+            _occupancy,
+            _roads,
+            rng,
+            _graph,
+            _planner,
+        ): &mut MinerStateData,
     ) -> fsm::StateTransition<MinerState> {
-        miner.increase_thirst();
-        if !miner.fatigued() {
+        miner.increase_thirst(clock);
+        // he sleeps through the night however rested he is
+        if clock.is_night() || miner.fatigued(config) {
+            miner.decrease_fatigue(clock);
+            outbox.act(AgentAction::Slept);
+            fsm::StateTransition::None
+        } else if **weather == Weather::Rain {
+            info!(
+                "{}: Rainin' cats an' dogs out there. Ah'm stayin' put",
+                name
+            );
+            fsm::StateTransition::None
+        } else if clock.mine_open() {
             info!(
                 "{}: What a God darn fantastic nap! Time to find more gold",
                 name
             );
-            fsm::StateTransition::Switch(MinerState::EnterMineAndDigForNugget)
+            fsm::StateTransition::Switch(back_to_work(goldmine))
+        } else if miner.thirsty(config)
+            && clock.saloon_open()
+            && miner.can_afford(economy.whiskey_price)
+        {
+            fsm::StateTransition::Switch(MinerState::QuenchThirst)
         } else {
-            miner.decrease_fatigue();
-            info!("{}: ZZZZ... ", name);
-            fsm::StateTransition::None
+            let transition = pastime(miner, clock, rng, fsm::StateTransition::None);
+            if let fsm::StateTransition::None = transition {
+                info!("{}: Whittlin' on the porch", name);
+            }
+            transition
         }
     }
 
-    fn on_stop(&self, state: &MinerState, (name, _location, _miner): &mut MinerStateData) {
+    fn on_stop(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
         info!("{}: Leaving the house", name);
     }
+
+    fn on_message(
+        &self,
+        _state: &MinerState,
+        (name, ..): &mut MinerStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<MinerState>> {
+        match telegram.message {
+            Message::StewReady => {
+                info!("{}: Okay Hun, ahm a comin'!", name);
+                Some(fsm::StateTransition::Push(MinerState::EatStew))
+            }
+            _ => None,
+        }
+    }
 }
 
-pub struct QuenchThirst;
+/// How a miner passes an evening with nothing pressing: mostly as he would
+/// anyway, now and then down at the creek or over cards, and at the meetin'
+/// whenever there is one.
+fn pastime(
+    miner: &Miner,
+    clock: &Clock,
+    rng: &mut WorldRng,
+    otherwise: fsm::StateTransition<MinerState>,
+) -> fsm::StateTransition<MinerState> {
+    let mut pastimes = vec![
+        (6, otherwise),
+        (2, fsm::StateTransition::Switch(MinerState::GoFishing)),
+    ];
+    if miner.can_afford(CARD_STAKE) {
+        pastimes.push((2, fsm::StateTransition::Switch(MinerState::PlayCards)));
+    }
+    if clock.church_service() {
+        pastimes.push((10, fsm::StateTransition::Switch(MinerState::VisitChurch)));
+    }
+    fsm::StateTransition::weighted(rng, pastimes)
+}
+
+/// Whether a miner gives up his evening: at bedtime, or when a need comes on
+/// him.
+fn evening_over(
+    miner: &Miner,
+    economy: &Economy,
+    clock: &Clock,
+    config: &Config,
+) -> fsm::StateTransition<MinerState> {
+    if clock.is_night() {
+        fsm::StateTransition::Switch(MinerState::GoHomeAndSleepTilRested)
+    } else {
+        tend_to_needs(miner, economy, clock, config)
+    }
+}
+
+pub struct GoFishing;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for GoFishing {
+    fn on_start(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
+        info!("{}: Reckon the catfish are bitin'", name);
+    }
+
+    fn update(
+        &self,
+        _state: &MinerState,
+        (
+            name,
+            _location,
+            miner,
+            _outbox,
+            economy,
+            clock,
+            _weather,
+            _scripts,
+            _brains,
+            _goldmine,
+            config,
+            _mine,
+            _occupancy,
+            _roads,
+            rng,
+            _graph,
+            _planner,
+        ): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        miner.increase_thirst(clock);
+        if rng.gen_ratio(1, 3) {
+            info!("{}: Got me a whopper!", name);
+        } else {
+            info!("{}: Nary a nibble", name);
+        }
+        evening_over(miner, economy, clock, config)
+    }
+
+    fn on_stop(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
+        info!("{}: Puttin' away mah rod", name);
+    }
+
+    fn on_message(
+        &self,
+        _state: &MinerState,
+        (name, ..): &mut MinerStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<MinerState>> {
+        match telegram.message {
+            Message::StewReady => {
+                info!("{}: Comin', Hun! The fish'll keep", name);
+                Some(fsm::StateTransition::Push(MinerState::EatStew))
+            }
+            _ => None,
+        }
+    }
+}
 
-impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for QuenchThirst {
-    fn on_start(&self, state: &MinerState, (name, location, miner): &mut MinerStateData) {
-        if **location != Location::Saloon {
-            **location = Location::Saloon;
-            info!("{}: Boy, ah sure is thusty! Walking to the saloon", name);
+/// Plays hands of cards for [`CARD_STAKE`] apiece until he's cleaned out or
+/// the evening's over.
+pub struct PlayCards;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for PlayCards {
+    fn update(
+        &self,
+        _state: &MinerState,
+        (
+            name,
+            _location,
+            miner,
+            _outbox,
+            economy,
+            clock,
+            _weather,
+            _scripts,
+            _brains,
+            _goldmine,
+            config,
+            _mine,
+            _occupancy,
+            _roads,
+            rng,
+            _graph,
+            _planner,
+        ): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        miner.increase_thirst(clock);
+        if !miner.pay(CARD_STAKE) {
+            info!("{}: Cleaned out. Ah'm done fer the night", name);
+            return fsm::StateTransition::Switch(MinerState::GoHomeAndSleepTilRested);
         }
+        if rng.gen_bool(0.5) {
+            miner.win(2 * CARD_STAKE);
+            info!("{}: Read 'em an' weep, boys!", name);
+        } else {
+            info!("{}: Dang. Deal me another", name);
+        }
+        evening_over(miner, economy, clock, config)
+    }
+
+    fn on_stop(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
+        info!("{}: Cashin' in mah chips", name);
     }
+}
+
+/// Sits through the Sunday meetin' until the preacher's done.
+pub struct VisitChurch;
 
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for VisitChurch {
     fn update(
         &self,
-        state: &MinerState,
-        (name, location, miner): &mut MinerStateData,
+        _state: &MinerState,
+        (name, _location, miner, _outbox, _economy, clock, ..): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        miner.increase_thirst(clock);
+        if clock.church_service() {
+            info!("{}: Amen!", name);
+            fsm::StateTransition::None
+        } else {
+            info!("{}: Meetin's over. Time ah was abed", name);
+            fsm::StateTransition::Switch(MinerState::GoHomeAndSleepTilRested)
+        }
+    }
+
+    fn on_stop(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
+        info!("{}: Leavin' the church", name);
+    }
+}
+
+/// Where a miner goes when he's done at the saloon: back to work, or once the
+/// mine's shut, home unless some pastime takes him.
+fn after_saloon(
+    miner: &Miner,
+    clock: &Clock,
+    goldmine: &Goldmine,
+    rng: &mut WorldRng,
+) -> fsm::StateTransition<MinerState> {
+    if clock.mine_open() {
+        fsm::StateTransition::Switch(back_to_work(goldmine))
+    } else {
+        pastime(
+            miner,
+            clock,
+            rng,
+            fsm::StateTransition::Switch(MinerState::GoHomeAndSleepTilRested),
+        )
+    }
+}
+
+pub struct QuenchThirst;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for QuenchThirst {
+    fn update(
+        &self,
+        _state: &MinerState,
+        (
+            name,
+            location,
+            miner,
+            outbox,
+            economy,
+            clock,
+            _weather,
+            _scripts,
+            _brains,
+            goldmine,
+            config,
+            _mine,
+            occupancy,
+            _roads,
+            rng,
+            _graph,
+            _planner,
+        ): &mut MinerStateData,
     ) -> fsm::StateTransition<MinerState> {
-        miner.increase_thirst();
-        if miner.thirsty() {
-            miner.buy_and_drink_whiskey();
-            info!("{}: That's mighty fine sippin liquer", name);
-            fsm::StateTransition::Switch(MinerState::EnterMineAndDigForNugget)
+        miner.increase_thirst(clock);
+        if miner.thirsty(config) {
+            if miner.buy_and_drink_whiskey(economy.whiskey_price) {
+                outbox.act(AgentAction::Drank);
+                let company = occupancy.company(**location, outbox.owner());
+                if let Some((last, others)) = company.split_last() {
+                    let company = match others {
+                        [] => last.to_string(),
+                        others => format!("{} an' {}", others.join(", "), last),
+                    };
+                    info!("{}: Evenin', {}! How's things with y'all?", name, company);
+                }
+                after_saloon(miner, clock, goldmine, rng)
+            } else {
+                fsm::StateTransition::Switch(MinerState::Broke)
+            }
         } else {
             println!("ERROR!\nERROR!\nERROR!");
             fsm::StateTransition::Quit
         }
     }
 
-    fn on_stop(&self, state: &MinerState, (name, _location, _miner): &mut MinerStateData) {
-        info!("{}: Leaving the saloon, feelin' good", name);
+    fn on_stop(
+        &self,
+        _state: &MinerState,
+        (name, _location, miner, .., config, _mine, _occupancy, _roads, _rng, _graph, _planner): &mut MinerStateData,
+    ) {
+        if miner.thirsty(config) {
+            info!("{}: Leaving the saloon, still parched", name);
+        } else {
+            info!("{}: Leaving the saloon, feelin' good", name);
+        }
+    }
+}
+
+pub struct EatStew;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for EatStew {
+    fn on_start(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
+        info!("{}: Smells Reaaal goood Elsa!", name);
+    }
+
+    fn update(
+        &self,
+        _state: &MinerState,
+        (name, _location, miner, _outbox, economy, ..): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        info!("{}: Tastes real good too!", name);
+        // Elsa feeds him whether he can pay or not
+        miner.pay(economy.stew_cost);
+        miner.eat();
+        fsm::StateTransition::Pop
+    }
+
+    fn on_stop(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
+        info!(
+            "{}: Thankya li'lle lady. Ah better get back to whatever ah wuz doin'",
+            name
+        );
+    }
+}
+
+pub struct FightDrunkard;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for FightDrunkard {
+    fn on_start(
+        &self,
+        _state: &MinerState,
+        (name, _location, miner, outbox, ..): &mut MinerStateData,
+    ) {
+        if let Some(opponent) = miner.opponent {
+            info!("{}: Why you no-good drunk! Take that!", name);
+            outbox.dispatch(SEND_MSG_IMMEDIATELY, opponent, Message::Punch);
+        }
+    }
+
+    fn update(
+        &self,
+        _state: &MinerState,
+        (name, ..): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        info!("{}: Dustin' off mah hands", name);
+        fsm::StateTransition::Pop
+    }
+
+    fn on_stop(
+        &self,
+        _state: &MinerState,
+        (_name, _location, miner, _outbox, ..): &mut MinerStateData,
+    ) {
+        miner.opponent = None;
+    }
+
+    // one fight at a time
+    fn on_message(
+        &self,
+        _state: &MinerState,
+        _state_data: &mut MinerStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<MinerState>> {
+        match telegram.message {
+            Message::Insult => Some(fsm::StateTransition::None),
+            _ => None,
+        }
+    }
+}
+
+pub struct GoHomeForStew;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for GoHomeForStew {
+    fn update(
+        &self,
+        _state: &MinerState,
+        (
+            name,
+            _location,
+            miner,
+            _outbox,
+            _economy,
+            _clock,
+            weather,
+            _scripts,
+            _brains,
+            goldmine,
+            _config,
+            _mine,
+            _occupancy,
+            _roads,
+            _rng,
+            _graph,
+            _planner,
+        ): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        if miner.hungry() {
+            info!("{}: Sittin' at the table waitin' on mah supper", name);
+            fsm::StateTransition::None
+        } else if **weather == Weather::Rain {
+            fsm::StateTransition::Switch(MinerState::GoHomeAndSleepTilRested)
+        } else {
+            fsm::StateTransition::Switch(back_to_work(goldmine))
+        }
+    }
+
+    fn on_stop(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
+        info!("{}: Leaving the house", name);
+    }
+
+    fn on_message(
+        &self,
+        _state: &MinerState,
+        (name, ..): &mut MinerStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<MinerState>> {
+        match telegram.message {
+            Message::StewReady => {
+                info!("{}: Okay Hun, ahm a comin'!", name);
+                Some(fsm::StateTransition::Push(MinerState::EatStew))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Where a miner with nobody cooking for him gets his supper.
+pub struct VisitSaloonForFood;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for VisitSaloonForFood {
+    fn update(
+        &self,
+        _state: &MinerState,
+        (
+            name,
+            _location,
+            miner,
+            _outbox,
+            economy,
+            clock,
+            _weather,
+            _scripts,
+            _brains,
+            goldmine,
+            _config,
+            _mine,
+            _occupancy,
+            _roads,
+            rng,
+            _graph,
+            _planner,
+        ): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        miner.increase_thirst(clock);
+        if miner.pay(economy.meal_price) {
+            miner.eat();
+            info!("{}: Beans an' bacon. Can't beat it", name);
+        } else {
+            info!("{}: Ah can't afford a plate o' beans", name);
+        }
+        after_saloon(miner, clock, goldmine, rng)
+    }
+
+    fn on_stop(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
+        info!("{}: Leaving the saloon, belly full", name);
+    }
+}
+
+/// Too poor for whiskey, so a thirsty miner makes do with the horse trough.
+pub struct Broke;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for Broke {
+    fn on_start(&self, _state: &MinerState, (name, ..): &mut MinerStateData) {
+        info!(
+            "{}: Ah ain't got the money fer whiskey. Reckon the horse trough'll have to do",
+            name
+        );
+    }
+
+    fn update(
+        &self,
+        _state: &MinerState,
+        (name, _location, miner, ..): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        miner.drink_water();
+        info!("{}: *slurp* Tastes o' horse", name);
+        fsm::StateTransition::Pop
+    }
+}
+
+/// Roams the hills looking for a new seam once the goldmine's played out,
+/// until somebody strikes one.
+pub struct Prospect;
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for Prospect {
+    fn update(
+        &self,
+        _state: &MinerState,
+        (
+            name,
+            _location,
+            miner,
+            _outbox,
+            economy,
+            clock,
+            _weather,
+            _scripts,
+            _brains,
+            goldmine,
+            config,
+            _mine,
+            _occupancy,
+            _roads,
+            _rng,
+            _graph,
+            _planner,
+        ): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        if !goldmine.played_out() {
+            info!("{}: Gold! Ah'm headin' fer the new diggin's", name);
+            return fsm::StateTransition::Switch(MinerState::EnterMineAndDigForNugget);
+        }
+        if !clock.mine_open() {
+            info!("{}: Too dark fer prospectin'. Back home", name);
+            return fsm::StateTransition::Switch(MinerState::GoHomeAndSleepTilRested);
+        }
+
+        miner.increase_thirst(clock);
+        miner.increase_hunger(clock);
+        miner.increase_fatigue(clock);
+        info!("{}: Pannin' the creeks fer color", name);
+        tend_to_needs(miner, economy, clock, config)
+    }
+}
+
+/// Walks to a place along the roads, a road at a time, getting thirstier and
+/// wearier on the way. He asks his [`PathPlanner`] for the way there first,
+/// and sets off once he hears back: with a [`Message::PathReady`] he follows
+/// the places on the planned [`Path`], and with a [`Message::PathNotFound`],
+/// the place not being on the map, he finds his own way along the roads.
+/// Where the next road on his way has closed he goes round another way from
+/// there, and where there's no way through he waits for one. Whoever gets
+/// home says so to his wife.
+pub struct TravelTo(pub Location);
+
+impl TravelTo {
+    /// Sets off along the road to the next place on his route, or, if it's
+    /// closed or he has no route, along the first road of the quickest way
+    /// there.
+    fn set_off(&self, name: &Name, from: Location, miner: &mut Miner, roads: &Roads) {
+        let planned = miner.route.first().copied();
+        miner.next_stop = match planned.filter(|&next| roads.walking_time(from, next).is_some()) {
+            Some(next) => {
+                miner.route.remove(0);
+                Some(next)
+            }
+            None => {
+                if planned.is_some() {
+                    info!("{}: Road's closed. Ah'll go round another way", name);
+                }
+                miner.route.clear();
+                roads
+                    .route(from, self.0)
+                    .and_then(|route| route.first().copied())
+            }
+        };
+        miner.journey = miner
+            .next_stop
+            .and_then(|next| roads.walking_time(from, next))
+            .unwrap_or(0);
+    }
+
+    /// Asks his planner for the way there, from wherever he is.
+    fn ask_the_way(&self, miner: &mut Miner, planner: &mut PathPlanner) {
+        planner.request_path_to_item(self.0);
+        miner.next_stop = None;
+        miner.route.clear();
+        miner.awaiting_path = true;
+    }
+}
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for TravelTo {
+    fn on_start(
+        &self,
+        _state: &MinerState,
+        (_name, _location, miner, .., _graph, (planner, _path)): &mut MinerStateData,
+    ) {
+        self.ask_the_way(miner, planner);
+    }
+
+    /// Any answer that came while he was doing something else went to that
+    /// instead, so he asks again.
+    fn on_resume(
+        &self,
+        _state: &MinerState,
+        (_name, _location, miner, .., _graph, (planner, _path)): &mut MinerStateData,
+    ) {
+        if miner.awaiting_path {
+            self.ask_the_way(miner, planner);
+        }
+    }
+
+    fn on_stop(&self, _state: &MinerState, (_name, _location, miner, ..): &mut MinerStateData) {
+        miner.awaiting_path = false;
+        miner.route.clear();
+    }
+
+    fn on_message(
+        &self,
+        _state: &MinerState,
+        (name, location, miner, .., roads, _rng, graph, (_planner, path)): &mut MinerStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<MinerState>> {
+        if !miner.awaiting_path {
+            return None;
+        }
+        match telegram.message {
+            Message::PathReady => {
+                let here = **location;
+                miner.route = path
+                    .map(|path| graph.places_along(&path.0))
+                    .unwrap_or_default()
+                    .into_iter()
+                    .skip_while(|&place| place == here)
+                    .collect();
+            }
+            Message::PathNotFound => {
+                info!(
+                    "{}: The {:?} ain't on mah map. Ah'll find mah own way",
+                    name, self.0
+                );
+                miner.route.clear();
+            }
+            _ => return None,
+        }
+        miner.awaiting_path = false;
+        self.set_off(name, **location, miner, roads);
+        if miner.next_stop.is_none() {
+            info!(
+                "{}: No way through to the {:?}. Reckon I'll wait",
+                name, self.0
+            );
+        }
+        Some(fsm::StateTransition::None)
+    }
+
+    fn update(
+        &self,
+        _state: &MinerState,
+        (name, location, miner, outbox, _economy, clock, .., roads, _rng, graph, (planner, _path)): &mut MinerStateData,
+    ) -> fsm::StateTransition<MinerState> {
+        miner.increase_thirst(clock);
+        miner.increase_fatigue(clock);
+        if miner.awaiting_path {
+            return fsm::StateTransition::None;
+        }
+        if miner.next_stop.is_none() {
+            self.set_off(name, **location, miner, roads);
+        }
+        let next = match miner.next_stop {
+            Some(next) => next,
+            None => return fsm::StateTransition::None,
+        };
+        miner.journey = miner.journey.saturating_sub(1);
+        if miner.journey > 0 {
+            return fsm::StateTransition::None;
+        }
+
+        **location = next;
+        if let Some(position) = graph.position_of(next) {
+            planner.set_position(position);
+        }
+        if next != self.0 {
+            self.set_off(name, next, miner, roads);
+            return fsm::StateTransition::None;
+        }
+
+        miner.next_stop = None;
+        if let (Location::Shack, Some(wife)) = (self.0, miner.wife) {
+            outbox.dispatch(SEND_MSG_IMMEDIATELY, wife, Message::HiHoneyImHome);
+        }
+        fsm::StateTransition::Pop
     }
 }
 
 pub struct MinerHandler;
 
-impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for MinerHandler {
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for MinerHandler {
     fn on_start(&self, state: &MinerState, state_data: &mut MinerStateData<'a>) {
         match state {
             MinerState::EnterMineAndDigForNugget => {
@@ -243,6 +1276,19 @@ impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for MinerHandler {
                 GoHomeAndSleepTilRested.on_start(state, state_data)
             }
             MinerState::QuenchThirst => QuenchThirst.on_start(state, state_data),
+            MinerState::EatStew => EatStew.on_start(state, state_data),
+            MinerState::FightDrunkard => FightDrunkard.on_start(state, state_data),
+            MinerState::GoHomeForStew => GoHomeForStew.on_start(state, state_data),
+            MinerState::VisitSaloonForFood => VisitSaloonForFood.on_start(state, state_data),
+            MinerState::Broke => Broke.on_start(state, state_data),
+            MinerState::Prospect => Prospect.on_start(state, state_data),
+            MinerState::TravelTo(to) => TravelTo(*to).on_start(state, state_data),
+            MinerState::WaitInQueue => WaitInQueue.on_start(state, state_data),
+            MinerState::GoFishing => GoFishing.on_start(state, state_data),
+            MinerState::PlayCards => PlayCards.on_start(state, state_data),
+            MinerState::VisitChurch => VisitChurch.on_start(state, state_data),
+            MinerState::Scripted(script) => Scripted(*script).on_start(state, state_data),
+            MinerState::Brain(brain) => Brain(*brain).on_start(state, state_data),
         }
     }
 
@@ -258,6 +1304,19 @@ impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for MinerHandler {
                 GoHomeAndSleepTilRested.on_stop(state, state_data)
             }
             MinerState::QuenchThirst => QuenchThirst.on_stop(state, state_data),
+            MinerState::EatStew => EatStew.on_stop(state, state_data),
+            MinerState::FightDrunkard => FightDrunkard.on_stop(state, state_data),
+            MinerState::GoHomeForStew => GoHomeForStew.on_stop(state, state_data),
+            MinerState::VisitSaloonForFood => VisitSaloonForFood.on_stop(state, state_data),
+            MinerState::Broke => Broke.on_stop(state, state_data),
+            MinerState::Prospect => Prospect.on_stop(state, state_data),
+            MinerState::TravelTo(to) => TravelTo(*to).on_stop(state, state_data),
+            MinerState::WaitInQueue => WaitInQueue.on_stop(state, state_data),
+            MinerState::GoFishing => GoFishing.on_stop(state, state_data),
+            MinerState::PlayCards => PlayCards.on_stop(state, state_data),
+            MinerState::VisitChurch => VisitChurch.on_stop(state, state_data),
+            MinerState::Scripted(script) => Scripted(*script).on_stop(state, state_data),
+            MinerState::Brain(brain) => Brain(*brain).on_stop(state, state_data),
         }
     }
 
@@ -273,6 +1332,19 @@ impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for MinerHandler {
                 GoHomeAndSleepTilRested.on_pause(state, state_data)
             }
             MinerState::QuenchThirst => QuenchThirst.on_pause(state, state_data),
+            MinerState::EatStew => EatStew.on_pause(state, state_data),
+            MinerState::FightDrunkard => FightDrunkard.on_pause(state, state_data),
+            MinerState::GoHomeForStew => GoHomeForStew.on_pause(state, state_data),
+            MinerState::VisitSaloonForFood => VisitSaloonForFood.on_pause(state, state_data),
+            MinerState::Broke => Broke.on_pause(state, state_data),
+            MinerState::Prospect => Prospect.on_pause(state, state_data),
+            MinerState::TravelTo(to) => TravelTo(*to).on_pause(state, state_data),
+            MinerState::WaitInQueue => WaitInQueue.on_pause(state, state_data),
+            MinerState::GoFishing => GoFishing.on_pause(state, state_data),
+            MinerState::PlayCards => PlayCards.on_pause(state, state_data),
+            MinerState::VisitChurch => VisitChurch.on_pause(state, state_data),
+            MinerState::Scripted(script) => Scripted(*script).on_pause(state, state_data),
+            MinerState::Brain(brain) => Brain(*brain).on_pause(state, state_data),
         }
     }
 
@@ -288,6 +1360,19 @@ impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for MinerHandler {
                 GoHomeAndSleepTilRested.on_resume(state, state_data)
             }
             MinerState::QuenchThirst => QuenchThirst.on_resume(state, state_data),
+            MinerState::EatStew => EatStew.on_resume(state, state_data),
+            MinerState::FightDrunkard => FightDrunkard.on_resume(state, state_data),
+            MinerState::GoHomeForStew => GoHomeForStew.on_resume(state, state_data),
+            MinerState::VisitSaloonForFood => VisitSaloonForFood.on_resume(state, state_data),
+            MinerState::Broke => Broke.on_resume(state, state_data),
+            MinerState::Prospect => Prospect.on_resume(state, state_data),
+            MinerState::TravelTo(to) => TravelTo(*to).on_resume(state, state_data),
+            MinerState::WaitInQueue => WaitInQueue.on_resume(state, state_data),
+            MinerState::GoFishing => GoFishing.on_resume(state, state_data),
+            MinerState::PlayCards => PlayCards.on_resume(state, state_data),
+            MinerState::VisitChurch => VisitChurch.on_resume(state, state_data),
+            MinerState::Scripted(script) => Scripted(*script).on_resume(state, state_data),
+            MinerState::Brain(brain) => Brain(*brain).on_resume(state, state_data),
         }
     }
 
@@ -296,7 +1381,7 @@ impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for MinerHandler {
         state: &MinerState,
         state_data: &mut MinerStateData<'a>,
     ) -> fsm::StateTransition<MinerState> {
-        match state {
+        let transition = match state {
             MinerState::EnterMineAndDigForNugget => {
                 EnterMineAndDigForNugget.update(state, state_data)
             }
@@ -307,41 +1392,652 @@ impl<'a> fsm::Handler<MinerState, MinerStateData<'a>> for MinerHandler {
                 GoHomeAndSleepTilRested.update(state, state_data)
             }
             MinerState::QuenchThirst => QuenchThirst.update(state, state_data),
-        }
+            MinerState::EatStew => EatStew.update(state, state_data),
+            MinerState::FightDrunkard => FightDrunkard.update(state, state_data),
+            MinerState::GoHomeForStew => GoHomeForStew.update(state, state_data),
+            MinerState::VisitSaloonForFood => VisitSaloonForFood.update(state, state_data),
+            MinerState::Broke => Broke.update(state, state_data),
+            MinerState::Prospect => Prospect.update(state, state_data),
+            MinerState::TravelTo(to) => TravelTo(*to).update(state, state_data),
+            MinerState::WaitInQueue => WaitInQueue.update(state, state_data),
+            MinerState::GoFishing => GoFishing.update(state, state_data),
+            MinerState::PlayCards => PlayCards.update(state, state_data),
+            MinerState::VisitChurch => VisitChurch.update(state, state_data),
+            MinerState::Scripted(script) => Scripted(*script).update(state, state_data),
+            MinerState::Brain(brain) => Brain(*brain).update(state, state_data),
+        };
+        on_foot(transition, *state_data.1)
+    }
+
+    fn on_message(
+        &self,
+        state: &MinerState,
+        state_data: &mut MinerStateData<'a>,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<MinerState>> {
+        let handled = match state {
+            MinerState::EnterMineAndDigForNugget => {
+                EnterMineAndDigForNugget.on_message(state, state_data, telegram)
+            }
+            MinerState::VisitBankAndDepositGold => {
+                VisitBankAndDepositGold.on_message(state, state_data, telegram)
+            }
+            MinerState::GoHomeAndSleepTilRested => {
+                GoHomeAndSleepTilRested.on_message(state, state_data, telegram)
+            }
+            MinerState::QuenchThirst => QuenchThirst.on_message(state, state_data, telegram),
+            MinerState::EatStew => EatStew.on_message(state, state_data, telegram),
+            MinerState::FightDrunkard => FightDrunkard.on_message(state, state_data, telegram),
+            MinerState::GoHomeForStew => GoHomeForStew.on_message(state, state_data, telegram),
+            MinerState::VisitSaloonForFood => {
+                VisitSaloonForFood.on_message(state, state_data, telegram)
+            }
+            MinerState::Broke => Broke.on_message(state, state_data, telegram),
+            MinerState::Prospect => Prospect.on_message(state, state_data, telegram),
+            MinerState::TravelTo(to) => TravelTo(*to).on_message(state, state_data, telegram),
+            MinerState::WaitInQueue => WaitInQueue.on_message(state, state_data, telegram),
+            MinerState::GoFishing => GoFishing.on_message(state, state_data, telegram),
+            MinerState::PlayCards => PlayCards.on_message(state, state_data, telegram),
+            MinerState::VisitChurch => VisitChurch.on_message(state, state_data, telegram),
+            MinerState::Scripted(script) => {
+                Scripted(*script).on_message(state, state_data, telegram)
+            }
+            MinerState::Brain(brain) => Brain(*brain).on_message(state, state_data, telegram),
+        };
+
+        // an insult interrupts whatever he's doing, as long as he's still in
+        // the saloon to hear it
+        handled.or_else(|| match telegram.message {
+            Message::Insult if *state_data.1 == Location::Saloon => {
+                state_data.2.opponent = Some(telegram.sender);
+                Some(fsm::StateTransition::Push(MinerState::FightDrunkard))
+            }
+            // a slot he's no longer there for goes straight back
+            Message::DigSlotGranted | Message::DigSlotDenied => {
+                let (_name, _location, miner, outbox, .., mine, _, _, _, _, _) = state_data;
+                stop_digging(miner, outbox, *mine);
+                Some(fsm::StateTransition::None)
+            }
+            _ => None,
+        })
     }
 }
 
 pub struct MinerPlugin;
 
 impl Plugin for MinerPlugin {
-    fn build(&self, app: &mut AppBuilder) {
-        app.add_startup_system(init_miners.system());
-        app.add_system(update_miners.system());
-    }
-}
-
-pub fn init_miners(mut commands: Commands) {
-    info!("initialising miners");
-    commands
-        .spawn()
-        .insert(Name("Miner Bob".to_string()))
-        .insert(Location::Shack)
-        .insert(Miner::new())
-        .insert(fsm::StateStack::<MinerState>::new_initial_state(
-            MinerState::GoHomeAndSleepTilRested,
-        ));
-}
-
-pub fn update_miners(
-    mut miners: Query<(
-        &Name,
-        &mut Location,
-        &mut Miner,
-        &mut fsm::StateStack<MinerState>,
-    )>,
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scenario>();
+        app.init_resource::<Settings>();
+        app.init_resource::<Occupancy>();
+        app.init_resource::<Scripts>();
+        app.init_resource::<Brains>();
+        app.add_systems(Startup, init_miners.in_set(INIT_MINERS));
+        app.add_plugins(
+            FsmPlugin::<MinerState, MinerHandler, MinerComponents>::at_once(
+                UPDATE_MINERS,
+                DELIVER_TO_MINERS,
+            ),
+        );
+        app.add_systems(FixedUpdate, tell_paths.in_set(Phase::Act).after(plan_paths));
+        app.add_systems(FixedUpdate, log_routes.in_set(Phase::Log));
+    }
+}
+
+pub fn init_miners(mut commands: Commands, graph: Res<NavGraph>, scenario: Res<Scenario>) {
+    info!("initialising {} miners", scenario.miners.len());
+    for spec in &scenario.miners {
+        let location = spec.location.unwrap_or(Location::Shack);
+        let position = graph.position_of(location).unwrap_or_default();
+        spawn_agent(&mut commands, &spec.name, location)
+            .insert(Miner::new().with_gold(spec.gold, spec.wealth))
+            .insert(PathPlanner::new(position))
+            .insert(fsm::StateStack::<MinerState>::new_initial_state(
+                spec.state.unwrap_or(MinerState::GoHomeAndSleepTilRested),
+            ));
+    }
+}
+
+/// Everything in town the miners' states look at besides the goldmine.
+type TownResources<'a> = (
+    Res<'a, Economy>,
+    Res<'a, Clock>,
+    Res<'a, Weather>,
+    Res<'a, Settings>,
+    Res<'a, Occupancy>,
+    Res<'a, Roads>,
+    Res<'a, NavGraph>,
+);
+
+/// A miner's path planner, and the last path it planned for him, if any.
+pub type Planning<'a> = (&'a mut PathPlanner, Option<&'a Path>);
+
+/// A miner's own components, besides his name and states.
+pub type MinerComponents = (
+    &'static mut Location,
+    &'static mut Miner,
+    &'static mut Outbox,
+    &'static mut PathPlanner,
+    Option<&'static Path>,
+);
+
+type MinerTown = (
+    TownResources<'static>,
+    Res<'static, Scripts>,
+    Res<'static, Brains>,
+    ResMut<'static, WorldRng>,
+    Query<'static, 'static, (Entity, &'static mut Goldmine)>,
+);
+
+/// Makes up a miner's state data from his components, the town, his claim
+/// on the goldmine and his random numbers, and runs his states with it.
+/// Returns whether he dug the nugget set aside for him.
+fn run_miner(
+    ((economy, clock, weather, config, occupancy, roads, graph), scripts, brains): (
+        &TownResources,
+        &Scripts,
+        &Brains,
+    ),
+    (mine, goldmine): (Entity, Claim),
+    rng: &mut WorldRng,
+    name: &Name,
+    (mut location, mut miner, mut outbox, mut planner, path): QueryItem<MinerComponents>,
+    state_stack: &mut fsm::StateStack<MinerState>,
+    run: Run,
+) -> bool {
+    let mut here = *location;
+    let mut stack_data = (
+        name,
+        &mut here,
+        miner.deref_mut(),
+        outbox.deref_mut(),
+        &**economy,
+        &**clock,
+        &**weather,
+        scripts,
+        brains,
+        goldmine,
+        &***config,
+        mine,
+        &**occupancy,
+        &**roads,
+        rng,
+        &**graph,
+        (planner.deref_mut(), path),
+    );
+    run.on(&MinerHandler, state_stack, &mut stack_data);
+    let dug = stack_data.9.dug();
+    location.set_if_neq(here);
+
+    if let Run::Stop = run {
+        info!(
+            "{}: Callin' it a day with {} gold in the bank and {} in mah pockets",
+            name, miner.bank, miner.gold
+        );
+    }
+    dug
+}
+
+impl AgentHandler<MinerState, MinerComponents> for MinerHandler {
+    type Town = MinerTown;
+
+    /// Runs a miner on his own, with the world's random numbers, digging
+    /// straight out of the goldmine.
+    fn run(
+        (resources, scripts, brains, rng, mines): &mut SystemParamItem<MinerTown>,
+        name: &Name,
+        agent: QueryItem<MinerComponents>,
+        state_stack: &mut fsm::StateStack<MinerState>,
+        run: Run,
+    ) {
+        let (mine, goldmine) = mines.single().unwrap();
+        let claim = match run {
+            Run::Update => goldmine.claim(agent.2.owner()),
+            _ => Claim::looking(goldmine),
+        };
+        let town = (&*resources, &**scripts, &**brains);
+        if run_miner(town, (mine, claim), rng, name, agent, state_stack, run) {
+            mines.single_mut().unwrap().1.dig();
+        }
+    }
+}
+
+/// The miners update all at once, each with a stream of random numbers of
+/// his own and a claim on the goldmine, and the nuggets they dig come out of
+/// it afterwards.
+impl ParallelAgentHandler<MinerState, MinerComponents> for MinerHandler {
+    type Round = Streams;
+    /// Whether he dug.
+    type Deferred = bool;
+
+    fn round(town: &mut SystemParamItem<MinerTown>) -> Streams {
+        town.3.streams()
+    }
+
+    fn update(
+        (resources, scripts, brains, _rng, mines): &SystemParamItem<MinerTown>,
+        streams: &Streams,
+        turn: u64,
+        name: &Name,
+        agent: QueryItem<MinerComponents>,
+        state_stack: &mut fsm::StateStack<MinerState>,
+    ) -> bool {
+        let (mine, goldmine) = mines.single().unwrap();
+        let claim = goldmine.claim(agent.2.owner());
+        let town = (resources, &**scripts, &**brains);
+        let mut rng = streams.stream(turn);
+        run_miner(
+            town,
+            (mine, claim),
+            &mut rng,
+            name,
+            agent,
+            state_stack,
+            Run::Update,
+        )
+    }
+
+    fn catch_up(town: &mut SystemParamItem<MinerTown>, dug: bool) {
+        if dug {
+            town.4.single_mut().unwrap().1.dig();
+        }
+    }
+}
+
+/// Tells each miner whose path has been planned, or given up on, so that
+/// he hears it with his other messages next tick.
+pub fn tell_paths(
+    mut ready: MessageReader<PathReady>,
+    mut not_found: MessageReader<PathNotFound>,
+    miners: Query<(), With<Miner>>,
+    mut telegrams: MessageWriter<Telegram>,
+) {
+    let ready = ready
+        .read()
+        .map(|PathReady(entity)| (*entity, Message::PathReady));
+    let not_found = not_found
+        .read()
+        .map(|PathNotFound(entity)| (*entity, Message::PathNotFound));
+    for (miner, message) in ready.chain(not_found) {
+        if miners.contains(miner) {
+            telegrams.write(Telegram {
+                sender: miner,
+                receiver: miner,
+                message,
+            });
+        }
+    }
+}
+
+pub fn log_routes(
+    mut ready: MessageReader<PathReady>,
+    mut not_found: MessageReader<PathNotFound>,
+    miners: Query<(&Name, Option<&Path>)>,
 ) {
-    for (name, mut location, mut miner, mut state_stack) in miners.iter_mut() {
-        let mut stack_data = (name, location.deref_mut(), miner.deref_mut());
-        fsm::StateMachine::update(&MinerHandler, &mut state_stack, &mut stack_data);
+    for PathReady(entity) in ready.read() {
+        if let Ok((name, Some(path))) = miners.get(*entity) {
+            debug!("{}: Route planned with {} legs", name, path.0.len());
+        }
+    }
+
+    // the hills aren't on the map, so every trip prospecting ends up here
+    for PathNotFound(entity) in not_found.read() {
+        if let Ok((name, _)) = miners.get(*entity) {
+            debug!("{}: Can't find a route", name);
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use fsm::{StateMachine, StateStack};
+    use game_ai::graph::path::edges_from_waypoints;
+    use game_ai::Vector2D;
+    use rand::SeedableRng;
+
+    /// One miner and everything his states look at, run through
+    /// [`MinerHandler`] an update at a time with nothing else going on in town
+    /// but the goldmine answering him, and his paths planned as soon as he
+    /// asks.
+    struct Bench {
+        name: Name,
+        location: Location,
+        miner: Miner,
+        outbox: Outbox,
+        economy: Economy,
+        clock: Clock,
+        weather: Weather,
+        scripts: Scripts,
+        brains: Brains,
+        goldmine: Goldmine,
+        config: Config,
+        mine: Entity,
+        occupancy: Occupancy,
+        roads: Roads,
+        rng: WorldRng,
+        graph: NavGraph,
+        planner: PathPlanner,
+        path: Option<Path>,
+        states: StateStack<MinerState>,
+    }
+
+    impl Bench {
+        fn new(state: MinerState) -> Self {
+            Bench {
+                name: Name("Miner Bob".to_string()),
+                location: Location::Shack,
+                miner: Miner::new(),
+                outbox: Outbox::new(Entity::from_raw_u32(0).unwrap()),
+                economy: Economy::default(),
+                clock: Clock::default(),
+                weather: Weather::Sunny,
+                scripts: Scripts::default(),
+                brains: Brains::default(),
+                goldmine: Goldmine::new(100, Vector2D::default()),
+                config: Config::default(),
+                mine: Entity::from_raw_u32(1).unwrap(),
+                occupancy: Occupancy::default(),
+                roads: Roads::town(),
+                rng: WorldRng::seed_from_u64(0),
+                graph: NavGraph::town(),
+                planner: PathPlanner::new(Vector2D::default()),
+                path: None,
+                states: StateStack::new_initial_state(state),
+            }
+        }
+
+        /// Runs the miner's states with his state data, and a claim on the
+        /// goldmine if `digging`, returning whether he dug.
+        fn run(
+            &mut self,
+            digging: bool,
+            run: impl FnOnce(&mut StateStack<MinerState>, &mut MinerStateData),
+        ) -> bool {
+            let claim = if digging {
+                self.goldmine.claim(self.outbox.owner())
+            } else {
+                Claim::looking(&self.goldmine)
+            };
+            let mut data = (
+                &self.name,
+                &mut self.location,
+                &mut self.miner,
+                &mut self.outbox,
+                &self.economy,
+                &self.clock,
+                &self.weather,
+                &self.scripts,
+                &self.brains,
+                claim,
+                &self.config,
+                self.mine,
+                &self.occupancy,
+                &self.roads,
+                &mut self.rng,
+                &self.graph,
+                (&mut self.planner, self.path.as_ref()),
+            );
+            run(&mut self.states, &mut data);
+            data.9.dug()
+        }
+
+        /// Plans him a path from where his planner has him by way of `places`,
+        /// returning whether they're all on the map.
+        fn plan(&mut self, places: &[Location]) -> bool {
+            let mut waypoints = vec![self.planner.position()];
+            for &place in places {
+                match self.graph.position_of(place) {
+                    Some(position) => waypoints.push(position),
+                    None => return false,
+                }
+            }
+            self.path = Some(Path(edges_from_waypoints(&waypoints)));
+            true
+        }
+
+        /// Tells him he's heard back about his path.
+        fn tell(&mut self, message: Message) {
+            let me = self.outbox.owner();
+            let telegram = Telegram {
+                sender: me,
+                receiver: me,
+                message,
+            };
+            self.run(false, |states, data| {
+                StateMachine::handle_message(&MinerHandler, states, data, &telegram);
+            });
+        }
+
+        /// Sends him on his way to `to`, without an answer from his planner.
+        fn travel_to(&mut self, to: Location) {
+            self.run(false, |states, data| {
+                StateMachine::push(&MinerHandler, MinerState::TravelTo(to), states, data)
+            });
+        }
+
+        /// Runs an update without moving the clock on, returning where he is.
+        fn walk(&mut self) -> Location {
+            self.run(false, |states, data| {
+                StateMachine::update(&MinerHandler, states, data)
+            });
+            self.location
+        }
+
+        /// Runs an update and moves the clock on, returning what the miner's
+        /// doing afterwards, where, and his gold, savings, thirst and fatigue.
+        fn tick(&mut self) -> (String, Location, i32, i32, i32, i32) {
+            let mine = self.mine;
+            if self.run(true, |states, data| {
+                StateMachine::update(&MinerHandler, states, data)
+            }) {
+                self.goldmine.dig();
+            }
+            // the goldmine's answers, and word of his path, arrive before his
+            // next update
+            loop {
+                let sent: Vec<_> = self.outbox.drain().map(|(_, telegram)| telegram).collect();
+                let mut answers: Vec<_> = sent
+                    .iter()
+                    .filter(|telegram| telegram.receiver == mine)
+                    .filter_map(|telegram| self.goldmine.answer(telegram))
+                    .collect();
+                if let (Some(&MinerState::TravelTo(to)), true) =
+                    (self.states.last(), self.miner.awaiting_path)
+                {
+                    let found = self.plan(&[to]);
+                    answers.push((
+                        self.outbox.owner(),
+                        if found {
+                            Message::PathReady
+                        } else {
+                            Message::PathNotFound
+                        },
+                    ));
+                }
+                if answers.is_empty() {
+                    break;
+                }
+                for (miner, message) in answers {
+                    let telegram = Telegram {
+                        sender: mine,
+                        receiver: miner,
+                        message,
+                    };
+                    self.run(false, |states, data| {
+                        StateMachine::handle_message(&MinerHandler, states, data, &telegram);
+                    });
+                }
+            }
+            self.clock.tick();
+            (
+                self.states.to_string(),
+                self.location,
+                self.miner.gold(),
+                self.miner.wealth(),
+                self.miner.thirst(),
+                self.miner.fatigue(),
+            )
+        }
+    }
+
+    /// Where a bachelor who starts the day asleep in his shack is, and what
+    /// he's got, after each of his first 50 updates with the clock at an hour
+    /// an update: two trips to the bank on the first day, a drink in the
+    /// evening and back to the goldmine the next morning.
+    #[rustfmt::skip]
+    static FIRST_FIFTY_TICKS: &[(&str, Location, i32, i32, i32, i32)] = &[
+        // states, location, gold, savings, thirst, fatigue
+        ("EnterMineAndDigForNugget > TravelTo(Goldmine)", Location::Shack, 0, 0, 1, 0),
+        ("EnterMineAndDigForNugget > TravelTo(Goldmine)", Location::Shack, 0, 0, 2, 1),
+        ("EnterMineAndDigForNugget",                      Location::Goldmine, 0, 0, 3, 2),
+        ("EnterMineAndDigForNugget",                      Location::Goldmine, 1, 0, 4, 3),
+        ("EnterMineAndDigForNugget",                      Location::Goldmine, 2, 0, 5, 4),
+        ("VisitBankAndDepositGold > TravelTo(Bank)",      Location::Goldmine, 3, 0, 6, 5),
+        ("VisitBankAndDepositGold",                       Location::Bank, 3, 0, 7, 6),
+        ("EnterMineAndDigForNugget > TravelTo(Goldmine)", Location::Bank, 0, 3, 8, 6),
+        ("EnterMineAndDigForNugget",                      Location::Goldmine, 0, 3, 9, 7),
+        ("EnterMineAndDigForNugget",                      Location::Goldmine, 1, 3, 10, 8),
+        ("EnterMineAndDigForNugget",                      Location::Goldmine, 2, 3, 11, 9),
+        ("VisitBankAndDepositGold > TravelTo(Bank)",      Location::Goldmine, 3, 3, 12, 10),
+        ("VisitBankAndDepositGold",                       Location::Bank, 3, 3, 13, 11),
+        ("GoHomeAndSleepTilRested > TravelTo(Shack)",     Location::Bank, 0, 6, 14, 11),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 15, 12),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 16, 11),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 17, 10),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 18, 9),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 19, 8),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 20, 7),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 21, 6),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 22, 5),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 23, 4),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 6, 24, 3),
+        ("EnterMineAndDigForNugget > TravelTo(Goldmine)", Location::Shack, 0, 6, 25, 3),
+        ("EnterMineAndDigForNugget > TravelTo(Goldmine)", Location::Shack, 0, 6, 26, 4),
+        ("EnterMineAndDigForNugget",                      Location::Goldmine, 0, 6, 27, 5),
+        ("EnterMineAndDigForNugget",                      Location::Goldmine, 1, 6, 28, 6),
+        ("EnterMineAndDigForNugget",                      Location::Goldmine, 2, 6, 29, 7),
+        ("VisitBankAndDepositGold > TravelTo(Bank)",      Location::Goldmine, 3, 6, 30, 8),
+        ("VisitBankAndDepositGold",                       Location::Bank, 3, 6, 31, 9),
+        ("GoHomeAndSleepTilRested > TravelTo(Shack)",     Location::Bank, 0, 9, 32, 9),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 9, 33, 10),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 9, 34, 9),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 9, 35, 8),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 9, 36, 7),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 9, 37, 6),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 9, 38, 5),
+        ("QuenchThirst > TravelTo(Saloon)",               Location::Shack, 0, 9, 39, 5),
+        ("QuenchThirst",                                  Location::Saloon, 0, 9, 40, 6),
+        ("GoFishing > TravelTo(Shack)",                   Location::Saloon, 0, 7, 0, 6),
+        ("GoFishing",                                     Location::Shack, 0, 7, 1, 7),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 7, 2, 7),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 7, 3, 6),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 7, 4, 5),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 7, 5, 4),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 7, 6, 3),
+        ("GoHomeAndSleepTilRested",                       Location::Shack, 0, 7, 7, 2),
+        ("EnterMineAndDigForNugget > TravelTo(Goldmine)", Location::Shack, 0, 7, 8, 2),
+        ("EnterMineAndDigForNugget > TravelTo(Goldmine)", Location::Shack, 0, 7, 9, 3),
+    ];
+
+    #[test]
+    fn a_miner_goes_through_the_same_states_every_time() {
+        let mut bench = Bench::new(MinerState::GoHomeAndSleepTilRested);
+        for (tick, &(states, location, gold, wealth, thirst, fatigue)) in
+            FIRST_FIFTY_TICKS.iter().enumerate()
+        {
+            assert_eq!(
+                bench.tick(),
+                (states.to_string(), location, gold, wealth, thirst, fatigue),
+                "after tick {}",
+                tick + 1
+            );
+        }
+    }
+
+    #[test]
+    fn a_miner_waits_to_hear_of_his_path_before_setting_off() {
+        let mut bench = Bench::new(MinerState::GoHomeAndSleepTilRested);
+        bench.travel_to(Location::Goldmine);
+        for _ in 0..3 {
+            assert_eq!(bench.walk(), Location::Shack);
+        }
+
+        bench.plan(&[Location::Goldmine]);
+        bench.tell(Message::PathReady);
+        bench.walk();
+        assert_eq!(bench.walk(), Location::Goldmine);
+        assert_eq!(bench.states.to_string(), "GoHomeAndSleepTilRested");
+    }
+
+    #[test]
+    fn a_miner_walks_the_way_his_planner_found() {
+        let mut bench = Bench::new(MinerState::GoHomeAndSleepTilRested);
+        bench.travel_to(Location::Goldmine);
+        // the long way round, rather than the road straight there
+        bench.plan(&[Location::Church, Location::Saloon, Location::Goldmine]);
+        bench.tell(Message::PathReady);
+
+        let walked: Vec<Location> = (0..3).map(|_| bench.walk()).collect();
+        assert_eq!(
+            walked,
+            vec![Location::Church, Location::Saloon, Location::Goldmine]
+        );
+        assert_eq!(bench.states.to_string(), "GoHomeAndSleepTilRested");
+    }
+
+    #[test]
+    fn a_miner_goes_round_a_closed_road_on_his_way() {
+        let mut bench = Bench::new(MinerState::GoHomeAndSleepTilRested);
+        bench.travel_to(Location::Goldmine);
+        bench.plan(&[Location::Church, Location::Saloon, Location::Goldmine]);
+        bench.tell(Message::PathReady);
+        bench.roads.close(Location::Church, Location::Saloon);
+
+        assert_eq!(bench.walk(), Location::Church);
+        // back by the shack, the only road left from the church
+        let walked: Vec<Location> = (0..3).map(|_| bench.walk()).collect();
+        assert_eq!(
+            walked,
+            vec![Location::Shack, Location::Shack, Location::Goldmine]
+        );
+    }
+
+    #[test]
+    fn a_miner_off_the_map_finds_his_own_way() {
+        let mut bench = Bench::new(MinerState::GoHomeAndSleepTilRested);
+        bench.travel_to(Location::Hills);
+        bench.tell(Message::PathNotFound);
+
+        let walked: Vec<Location> = (0..3).map(|_| bench.walk()).collect();
+        assert_eq!(
+            walked,
+            vec![Location::Shack, Location::Goldmine, Location::Hills]
+        );
+    }
+
+    #[test]
+    fn banking_is_told_as_an_action() {
+        let mut bench = Bench::new(MinerState::VisitBankAndDepositGold);
+        bench.location = Location::Bank;
+        bench.miner = Miner::new().with_gold(3, 4);
+        bench.tick();
+        let actions: Vec<AgentAction> = bench.outbox.drain_actions().collect();
+        assert_eq!(actions, vec![AgentAction::Deposited(3)]);
+        assert_eq!(bench.miner.wealth(), 7);
+    }
+
+    #[test]
+    fn thirst_comes_on_by_the_hour_however_fast_the_clock_ticks() {
+        let mut bench = Bench::new(MinerState::GoHomeAndSleepTilRested);
+        bench.clock = Clock::starting_at(4, 23);
+        for _ in 0..8 {
+            let (states, location, ..) = bench.tick();
+            assert_eq!(
+                (states.as_str(), location),
+                ("GoHomeAndSleepTilRested", Location::Shack)
+            );
+        }
+        assert_eq!(bench.miner.thirst(), 2);
     }
 }
@@ -0,0 +1,71 @@
+//! What the miners say as they walk about town, logged from watching their
+//! [`CurrentState`] and [`Location`] change rather than by their states
+//! themselves: why they're setting off when they start to travel, and each
+//! place they pass on the way. Everything else they say, they still say for
+//! themselves.
+
+use crate::current_state::{CurrentState, TRACK_CURRENT_STATE};
+use crate::miner::MinerState;
+use crate::{Location, Name, Phase};
+use bevy_app::{App, FixedUpdate, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use fsm::StateStack;
+
+/// What a miner says setting off somewhere to do `purpose`.
+pub fn setting_off(purpose: &MinerState) -> Option<&'static str> {
+    match purpose {
+        MinerState::EnterMineAndDigForNugget => Some("Walkin' to the goldmine"),
+        MinerState::VisitBankAndDepositGold => Some("Goin' to the bank. Yes siree"),
+        MinerState::GoHomeAndSleepTilRested => Some("Walkin' home"),
+        MinerState::QuenchThirst => Some("Boy, ah sure is thusty! Walking to the saloon"),
+        MinerState::GoHomeForStew => Some("Mah belly's a-rumblin'. Walkin' home fer supper"),
+        MinerState::VisitSaloonForFood => {
+            Some("Mah belly's a-rumblin'. Walkin' to the saloon fer some grub")
+        }
+        MinerState::PlayCards => Some("Feelin' lucky. Off to the saloon fer a hand o' cards"),
+        MinerState::VisitChurch => Some("Sunday meetin' tonight. Best put on mah Sunday best"),
+        MinerState::Prospect => {
+            Some("This here mine's plum played out. Off to the hills to prospect fer a new one")
+        }
+        _ => None,
+    }
+}
+
+type Travellers<'a> = (
+    &'a Name,
+    Ref<'a, Location>,
+    &'a StateStack<MinerState>,
+    Ref<'a, CurrentState<MinerState>>,
+);
+
+type Moved = Or<(Changed<CurrentState<MinerState>>, Changed<Location>)>;
+
+pub fn narrate_miners(miners: Query<Travellers, Moved>) {
+    for (name, location, stack, current) in miners.iter() {
+        let to = match current.0 {
+            MinerState::TravelTo(to) => to,
+            _ => continue,
+        };
+        if current.is_changed() {
+            // the state he's on his way to, under the travelling
+            let line = stack.iter().rev().nth(1).and_then(setting_off);
+            if let Some(line) = line {
+                info!("{}: {}", name, line);
+            }
+        } else if location.is_changed() && *location != to {
+            info!("{}: Passin' by the {:?}", name, *location);
+        }
+    }
+}
+
+pub struct NarrationPlugin;
+
+impl Plugin for NarrationPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(
+            FixedUpdate,
+            narrate_miners.in_set(Phase::Log).after(TRACK_CURRENT_STATE),
+        );
+    }
+}
@@ -0,0 +1,187 @@
+//! Running the example in a terminal: reading the command line, and the
+//! runner pacing the frames until the run's over or it's stopped.
+
+use std::thread;
+use std::time::Instant;
+
+use crate::batch::Batch;
+use crate::benchmark::Benchmark;
+use crate::cli::Cli;
+use crate::controls::{ControlsPlugin, Pace};
+use crate::dashboard::DashboardPlugin;
+use crate::determinism::Verify;
+use crate::diagnostics::AiDiagnosticsPlugin;
+use crate::event_log::{self, EventLogPlugin};
+use crate::hot_reload::HotReloadPlugin;
+use crate::log;
+use crate::metrics::{self, MetricsPlugin};
+use crate::population::Population;
+use crate::remote::RemoteControlPlugin;
+use crate::replay::{self, ReplayPlugin};
+use crate::rerun::{self, RerunPlugin};
+use crate::rng::Seed;
+use crate::scenario::Scenario;
+use crate::scene::ScenePlugin;
+use crate::shutdown::{self, Shutdown};
+use crate::snapshot::{self, SnapshotPlugin};
+use crate::timestep::Speed;
+use crate::trace;
+use crate::{Settings, WorldPlugins};
+use bevy_app::{App, AppExit};
+use bevy_time::{Time, Virtual};
+use clap::Parser;
+
+fn runner(mut app: App) -> AppExit {
+    app.finish();
+    app.cleanup();
+    let pace = app
+        .world()
+        .get_resource::<Pace>()
+        .cloned()
+        .unwrap_or_default();
+    let shutdown = app.world().get_resource::<Shutdown>().cloned();
+    loop {
+        let started = Instant::now();
+        app.update();
+
+        if app.should_exit().is_some() {
+            break;
+        }
+        if shutdown.as_ref().is_some_and(Shutdown::requested) {
+            break;
+        }
+
+        // the fixed timestep decides when the agents tick, so there's no
+        // hurry to come round again unless running flat out
+        let paused = app.world().resource::<Time<Virtual>>().is_paused();
+        let flat_out = pace.fast || app.world().get_resource::<Speed>() == Some(&Speed::Max);
+        if paused || !flat_out {
+            thread::sleep(pace.frame.saturating_sub(started.elapsed()));
+        }
+    }
+    let world = app.world_mut();
+    replay::finish(world);
+    rerun::finish(world);
+    event_log::finish(world);
+    log::finish_transcript();
+    metrics::finish(world);
+    snapshot::save(world);
+    shutdown::wind_down(world);
+    AppExit::Success
+}
+
+pub fn run() {
+    let cli = Cli::parse();
+    let scenario = Scenario::from_cli(&cli)
+        .unwrap_or_else(|| Scenario::populated(&Population::from_cli(&cli)));
+    let config = cli.config().unwrap_or_else(|e| panic!("{}", e));
+    if let Some(batch) = Batch::from_cli(&cli) {
+        println!(
+            "{} runs of {} ticks, seeded from {} (watch one with --seed=N --ticks={})",
+            batch.runs, batch.ticks, batch.seed, batch.ticks
+        );
+        println!("{}", batch.run(&scenario, &config));
+        return;
+    }
+    if let Some(verify) = Verify::from_cli(&cli) {
+        match verify.run(&scenario, &config) {
+            Ok(run) => println!(
+                "{} ticks from seed {} went the same way twice: {} events, hash {:016x}",
+                verify.ticks,
+                verify.seed,
+                run.records.len(),
+                run.hash
+            ),
+            Err(divergence) => panic!("{}", divergence),
+        }
+        return;
+    }
+
+    if let Some(benchmark) = Benchmark::from_cli(&cli) {
+        println!(
+            "{} ticks of {} miners from seed {}, twice",
+            benchmark.ticks,
+            scenario.miners.len(),
+            benchmark.seed
+        );
+        match benchmark.run(&scenario, &config) {
+            Ok(timings) => println!("{}", timings),
+            Err(e) => panic!("{}", e),
+        }
+        return;
+    }
+
+    let pace = Pace::from_cli(&config, &cli);
+    let mut app = App::new();
+    // set first, for the inspector's window to take over
+    app.set_runner(runner);
+    app.insert_resource(scenario)
+        .insert_resource(Settings(config))
+        .insert_resource(Seed::from_cli(&cli))
+        .insert_resource(Shutdown::install());
+    if cli.tui {
+        app.add_plugins(DashboardPlugin);
+    } else {
+        let log = cli.log_format.plugin();
+        match (cli.trace.clone(), &cli.transcript) {
+            (Some(path), _) => {
+                let log = trace::write_to(&mut app, log, path);
+                app.add_plugins(log)
+            }
+            (None, Some(path)) => {
+                let log = log::transcribe_to(&mut app, log, path);
+                app.add_plugins(log)
+            }
+            (None, None) => app.add_plugins(log),
+        };
+    }
+    // the dashboard keeps its keys even when running flat out, or there'd be
+    // no way to quit it
+    if cli.tui || !(pace.fast || cli.headless) {
+        app.add_plugins(ControlsPlugin);
+    }
+    app.insert_resource(pace).add_plugins(WorldPlugins);
+    if let Some(scene) = ScenePlugin::from_cli(&cli) {
+        app.add_plugins(scene);
+    }
+    if let Some(replay) = ReplayPlugin::from_cli(&cli) {
+        app.add_plugins(replay);
+    }
+    if let Some(rerun) = RerunPlugin::from_cli(&cli) {
+        app.add_plugins(rerun);
+    }
+    if let Some(events) = EventLogPlugin::from_cli(&cli) {
+        app.add_plugins(events);
+    }
+    if let Some(metrics) = MetricsPlugin::from_cli(&cli) {
+        app.add_plugins(metrics);
+    }
+    #[cfg(feature = "websocket")]
+    if let Some(stream) = crate::stream::StreamPlugin::from_cli(&cli) {
+        app.add_plugins(stream);
+    }
+    if let Some(control) = RemoteControlPlugin::from_cli(&cli) {
+        app.add_plugins(control);
+    }
+    if let Some(diagnostics) = AiDiagnosticsPlugin::from_cli(&cli) {
+        app.add_plugins(diagnostics);
+    }
+    if let Some(snapshot) = SnapshotPlugin::from_cli(&cli) {
+        app.add_plugins(snapshot);
+    }
+    #[cfg(feature = "wasm-brains")]
+    if let Some(brains) = crate::brains::BrainsPlugin::from_cli(&cli) {
+        app.add_plugins(brains);
+    }
+    if let Some(scripts) = crate::scripting::ScriptingPlugin::from_cli(&cli) {
+        app.add_plugins(scripts);
+    }
+    if let Some(hot_reload) = HotReloadPlugin::from_cli(&cli) {
+        app.add_plugins(hot_reload);
+    }
+    #[cfg(feature = "inspector")]
+    if let Some(inspector) = crate::inspector::InspectorPlugin::from_cli(&cli) {
+        app.add_plugins(inspector);
+    }
+    app.run();
+}
@@ -0,0 +1,100 @@
+//! Who's where in town, for agents to take one another into account: a
+//! miner who finds company at the saloon passes the time of day with them.
+//!
+//! Every agent's [`Location`] is the one record of where he is, so rather
+//! than have each state say when it comes and goes, the town takes a census
+//! of them before anyone updates.
+
+use crate::sheriff::DELIVER_TO_SHERIFFS;
+use crate::{Label, Location, Name, Phase};
+use bevy_app::{App, FixedUpdate, Plugin};
+use bevy_ecs::prelude::*;
+use std::collections::HashMap;
+
+/// Label of the system taking the census.
+pub static TAKE_CENSUS: Label = Label("take_census");
+
+/// Everyone at each place in town, in the order they take their turns.
+#[derive(Resource, Clone, Debug, Default)]
+pub struct Occupancy {
+    present: HashMap<Location, Vec<(Entity, String)>>,
+}
+
+impl Occupancy {
+    pub fn enter(&mut self, location: Location, entity: Entity, name: String) {
+        self.present
+            .entry(location)
+            .or_default()
+            .push((entity, name));
+    }
+
+    /// Everyone at `location`.
+    pub fn at(&self, location: Location) -> impl Iterator<Item = (Entity, &str)> {
+        self.present
+            .get(&location)
+            .into_iter()
+            .flatten()
+            .map(|(entity, name)| (*entity, name.as_str()))
+    }
+
+    /// The names of everyone at `location` but `entity`.
+    pub fn company(&self, location: Location, entity: Entity) -> Vec<&str> {
+        self.at(location)
+            .filter(|&(occupant, _)| occupant != entity)
+            .map(|(_, name)| name)
+            .collect()
+    }
+}
+
+/// Counts everyone where they ended up last update.
+pub fn take_census(mut occupancy: ResMut<Occupancy>, agents: Query<(Entity, &Name, &Location)>) {
+    occupancy.present.clear();
+    // in the order they were spawned, whatever else there is in the world
+    let in_turn = agents
+        .iter()
+        .sort_by_key::<Entity, _>(|entity| entity.index_u32());
+    for (entity, name, location) in in_turn {
+        occupancy.enter(*location, entity, name.to_string());
+    }
+}
+
+pub struct OccupancyPlugin;
+
+impl Plugin for OccupancyPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Occupancy>();
+        // once everyone's heard their messages
+        app.add_systems(
+            FixedUpdate,
+            take_census
+                .in_set(TAKE_CENSUS)
+                .in_set(Phase::Perceive)
+                .after(DELIVER_TO_SHERIFFS),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn company_is_everyone_else_there() {
+        let mut world = World::new();
+        let bob = world.spawn_empty().id();
+        let jake = world.spawn_empty().id();
+        let slim = world.spawn_empty().id();
+        let mut occupancy = Occupancy::default();
+        occupancy.enter(Location::Saloon, bob, "Miner Bob".to_string());
+        occupancy.enter(Location::Saloon, slim, "Barfly Slim".to_string());
+        occupancy.enter(Location::Goldmine, jake, "Miner Jake".to_string());
+
+        assert_eq!(
+            occupancy.company(Location::Saloon, bob),
+            vec!["Barfly Slim"]
+        );
+        assert!(occupancy.company(Location::Goldmine, jake).is_empty());
+        assert_eq!(occupancy.at(Location::Goldmine).count(), 1);
+        assert_eq!(occupancy.at(Location::Bank).count(), 0);
+    }
+}
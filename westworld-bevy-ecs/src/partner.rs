@@ -0,0 +1,332 @@
+use std::ops::DerefMut;
+
+use crate::agents::{spawn_agent, AgentHandler, FsmPlugin, Run};
+use crate::messaging::{Message, Outbox, Telegram, SEND_MSG_IMMEDIATELY};
+use crate::miner::{Miner, DELIVER_TO_MINERS, INIT_MINERS, UPDATE_MINERS};
+use crate::rng::WorldRng;
+use crate::scenario::Scenario;
+use crate::{Label, Location, Name};
+use bevy_app::{App, Plugin, PostStartup, Startup};
+use bevy_ecs::entity::{EntityMapper, MapEntities};
+use bevy_ecs::prelude::*;
+use bevy_ecs::query::QueryItem;
+use bevy_log::prelude::*;
+use bevy_reflect::Reflect;
+use rand::distributions::{Distribution, Standard};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+
+pub static BATHROOM_CHANCE: f32 = 0.1; // chance per update of needing the bathroom
+pub static STEW_COOKING_TIME: usize = 2; // updates until the stew is ready
+
+/// Label of the system updating the partners, for systems that must run in a fixed order with it.
+pub static UPDATE_PARTNERS: Label = Label("update_partners");
+/// Label of the system delivering messages to the partners.
+pub static DELIVER_TO_PARTNERS: Label = Label("deliver_to_partners");
+/// Label of the startup system spawning the partners.
+pub static INIT_PARTNERS: Label = Label("init_partners");
+
+pub type PartnerStateData<'a> = (
+    &'a Name,
+    &'a mut Location,
+    &'a mut Partner,
+    &'a mut Outbox,
+    &'a mut WorldRng,
+);
+
+enum PartnerChore {
+    Mopping,
+    Washing,
+    BedMaking,
+}
+
+impl Distribution<PartnerChore> for Standard {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> PartnerChore {
+        match rng.gen_range(0..3) {
+            0 => PartnerChore::Mopping,
+            1 => PartnerChore::Washing,
+            2 => PartnerChore::BedMaking,
+            _ => unreachable!(),
+        }
+    }
+}
+
+#[derive(Component, Reflect, Clone, Debug, Serialize, Deserialize)]
+#[component(map_entities)]
+#[reflect(Component)]
+pub struct Partner {
+    pub husband: Option<Entity>,
+    cooking: bool,
+}
+
+impl Partner {
+    pub fn new() -> Self {
+        Partner {
+            husband: None,
+            cooking: false,
+        }
+    }
+}
+
+impl MapEntities for Partner {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        self.husband.map_entities(entity_mapper);
+    }
+}
+
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum PartnerState {
+    DoHouseWork,
+    VisitBathroom,
+    CookStew,
+}
+
+pub struct DoHouseWork;
+
+impl<'a> fsm::Handler<PartnerState, PartnerStateData<'a>, Telegram> for DoHouseWork {
+    fn update(
+        &self,
+        _state: &PartnerState,
+        (name, _location, _partner, _outbox, rng): &mut PartnerStateData,
+    ) -> fsm::StateTransition<PartnerState> {
+        if rng.gen::<f32>() < BATHROOM_CHANCE {
+            return fsm::StateTransition::Push(PartnerState::VisitBathroom);
+        }
+
+        match rng.gen() {
+            PartnerChore::Mopping => info!("{}: Moppin' the floor", name),
+            PartnerChore::Washing => info!("{}: Washin' the dishes", name),
+            PartnerChore::BedMaking => info!("{}: Makin' the bed", name),
+        }
+
+        fsm::StateTransition::None
+    }
+}
+
+pub struct VisitBathroom;
+
+impl<'a> fsm::Handler<PartnerState, PartnerStateData<'a>, Telegram> for VisitBathroom {
+    fn on_start(&self, _state: &PartnerState, (name, ..): &mut PartnerStateData) {
+        info!("{}: Walkin' to the can", name);
+    }
+
+    fn on_resume(&self, state: &PartnerState, state_data: &mut PartnerStateData) {
+        self.on_start(state, state_data);
+    }
+
+    fn update(
+        &self,
+        _state: &PartnerState,
+        (name, ..): &mut PartnerStateData,
+    ) -> fsm::StateTransition<PartnerState> {
+        info!("{}: Ahhhhhh! Sweet relief", name);
+        fsm::StateTransition::Pop
+    }
+
+    fn on_stop(&self, _state: &PartnerState, (name, ..): &mut PartnerStateData) {
+        info!("{}: Leavin' the Jon", name);
+    }
+}
+
+pub struct CookStew;
+
+impl<'a> fsm::Handler<PartnerState, PartnerStateData<'a>, Telegram> for CookStew {
+    fn on_start(
+        &self,
+        _state: &PartnerState,
+        (name, _location, partner, outbox, ..): &mut PartnerStateData,
+    ) {
+        if !partner.cooking {
+            info!("{}: Putting the stew in the oven", name);
+            let owner = outbox.owner();
+            outbox.dispatch(STEW_COOKING_TIME, owner, Message::StewReady);
+            partner.cooking = true;
+        }
+    }
+
+    fn update(
+        &self,
+        _state: &PartnerState,
+        (name, ..): &mut PartnerStateData,
+    ) -> fsm::StateTransition<PartnerState> {
+        info!("{}: Fussin' over food", name);
+        fsm::StateTransition::None
+    }
+
+    fn on_stop(&self, _state: &PartnerState, (name, ..): &mut PartnerStateData) {
+        info!("{}: Puttin' the stew on the table", name);
+    }
+
+    fn on_message(
+        &self,
+        _state: &PartnerState,
+        (name, _location, partner, outbox, ..): &mut PartnerStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<PartnerState>> {
+        match telegram.message {
+            Message::StewReady => {
+                info!("{}: StewReady! Lets eat", name);
+                if let Some(husband) = partner.husband {
+                    outbox.dispatch(SEND_MSG_IMMEDIATELY, husband, Message::StewReady);
+                }
+                partner.cooking = false;
+                Some(fsm::StateTransition::Pop)
+            }
+            // already on it
+            Message::HiHoneyImHome => Some(fsm::StateTransition::None),
+            _ => None,
+        }
+    }
+}
+
+pub struct PartnerHandler;
+
+impl<'a> fsm::Handler<PartnerState, PartnerStateData<'a>, Telegram> for PartnerHandler {
+    fn on_start(&self, state: &PartnerState, state_data: &mut PartnerStateData<'a>) {
+        match state {
+            PartnerState::DoHouseWork => DoHouseWork.on_start(state, state_data),
+            PartnerState::VisitBathroom => VisitBathroom.on_start(state, state_data),
+            PartnerState::CookStew => CookStew.on_start(state, state_data),
+        }
+    }
+
+    fn on_stop(&self, state: &PartnerState, state_data: &mut PartnerStateData<'a>) {
+        match state {
+            PartnerState::DoHouseWork => DoHouseWork.on_stop(state, state_data),
+            PartnerState::VisitBathroom => VisitBathroom.on_stop(state, state_data),
+            PartnerState::CookStew => CookStew.on_stop(state, state_data),
+        }
+    }
+
+    fn on_pause(&self, state: &PartnerState, state_data: &mut PartnerStateData<'a>) {
+        match state {
+            PartnerState::DoHouseWork => DoHouseWork.on_pause(state, state_data),
+            PartnerState::VisitBathroom => VisitBathroom.on_pause(state, state_data),
+            PartnerState::CookStew => CookStew.on_pause(state, state_data),
+        }
+    }
+
+    fn on_resume(&self, state: &PartnerState, state_data: &mut PartnerStateData<'a>) {
+        match state {
+            PartnerState::DoHouseWork => DoHouseWork.on_resume(state, state_data),
+            PartnerState::VisitBathroom => VisitBathroom.on_resume(state, state_data),
+            PartnerState::CookStew => CookStew.on_resume(state, state_data),
+        }
+    }
+
+    fn update(
+        &self,
+        state: &PartnerState,
+        state_data: &mut PartnerStateData<'a>,
+    ) -> fsm::StateTransition<PartnerState> {
+        match state {
+            PartnerState::DoHouseWork => DoHouseWork.update(state, state_data),
+            PartnerState::VisitBathroom => VisitBathroom.update(state, state_data),
+            PartnerState::CookStew => CookStew.update(state, state_data),
+        }
+    }
+
+    /// Whatever she's doing, she'll cook when her husband gets home.
+    fn on_message(
+        &self,
+        state: &PartnerState,
+        state_data: &mut PartnerStateData<'a>,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<PartnerState>> {
+        let handled = match state {
+            PartnerState::DoHouseWork => DoHouseWork.on_message(state, state_data, telegram),
+            PartnerState::VisitBathroom => VisitBathroom.on_message(state, state_data, telegram),
+            PartnerState::CookStew => CookStew.on_message(state, state_data, telegram),
+        };
+
+        handled.or_else(|| match telegram.message {
+            Message::HiHoneyImHome => {
+                info!(
+                    "{}: Hi honey. Let me make you some of mah fine country stew",
+                    state_data.0
+                );
+                Some(fsm::StateTransition::Push(PartnerState::CookStew))
+            }
+            _ => None,
+        })
+    }
+}
+
+pub struct PartnerPlugin;
+
+impl Plugin for PartnerPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scenario>();
+        // agents are spawned in a fixed order too, as it decides the order
+        // their messages are posted in
+        app.add_systems(
+            Startup,
+            init_partners.in_set(INIT_PARTNERS).after(INIT_MINERS),
+        );
+        app.add_systems(PostStartup, marry);
+        // agents take turns in a fixed order so seeded runs replay exactly
+        app.add_plugins(
+            FsmPlugin::<PartnerState, PartnerHandler, PartnerComponents>::new(
+                UPDATE_PARTNERS,
+                DELIVER_TO_PARTNERS,
+            )
+            .after(UPDATE_MINERS, DELIVER_TO_MINERS),
+        );
+    }
+}
+
+pub fn init_partners(mut commands: Commands, scenario: Res<Scenario>) {
+    info!("initialising {} partners", scenario.partners.len());
+    for spec in &scenario.partners {
+        spawn_agent(
+            &mut commands,
+            &spec.name,
+            spec.location.unwrap_or(Location::Shack),
+        )
+        .insert(Partner::new())
+        .insert(fsm::StateStack::<PartnerState>::new_initial_state(
+            spec.state.unwrap_or(PartnerState::DoHouseWork),
+        ));
+    }
+}
+
+/// A partner's own components, besides her name and states.
+pub type PartnerComponents = (
+    &'static mut Location,
+    &'static mut Partner,
+    &'static mut Outbox,
+);
+
+impl AgentHandler<PartnerState, PartnerComponents> for PartnerHandler {
+    type Town = ResMut<'static, WorldRng>;
+
+    fn run(
+        rng: &mut ResMut<WorldRng>,
+        name: &Name,
+        (mut location, mut partner, mut outbox): QueryItem<PartnerComponents>,
+        state_stack: &mut fsm::StateStack<PartnerState>,
+        run: Run,
+    ) {
+        let mut state_data = (
+            name,
+            location.deref_mut(),
+            partner.deref_mut(),
+            outbox.deref_mut(),
+            rng.deref_mut(),
+        );
+        run.on(&PartnerHandler, state_stack, &mut state_data);
+    }
+}
+
+/// Pairs up unmarried miners and partners so they know who to send messages to.
+pub fn marry(mut miners: Query<(Entity, &mut Miner)>, mut partners: Query<(Entity, &mut Partner)>) {
+    let grooms = miners.iter_mut().filter(|(_, miner)| miner.wife.is_none());
+    let brides = partners
+        .iter_mut()
+        .filter(|(_, partner)| partner.husband.is_none());
+
+    for ((groom, mut miner), (bride, mut partner)) in grooms.zip(brides) {
+        miner.wife = Some(bride);
+        partner.husband = Some(groom);
+    }
+}
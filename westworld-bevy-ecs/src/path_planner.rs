@@ -0,0 +1,478 @@
+use crate::messaging::add_tick_messages;
+use crate::{Location, Phase};
+use bevy_app::{App, FixedUpdate, Plugin};
+use bevy_ecs::entity::{EntityMapper, MapEntities};
+use bevy_ecs::prelude::*;
+use game_ai::graph::path::{edges_from_waypoints, PathEdge};
+use game_ai::graph::path_manager::{BudgetPolicy, PathManager, SearchOutcome};
+use game_ai::graph::search::{FindExtraInfo, GraphSearchDijkstra};
+use game_ai::graph::{GraphEdge, SparseGraph, TimeSlicedSearch};
+use game_ai::Vector2D;
+use serde::{Deserialize, Serialize};
+use std::ops::{Deref, DerefMut};
+
+pub static SEARCH_CYCLES_PER_UPDATE: usize = 50; // graph nodes expanded per update, shared by all planners
+
+static TOWN_MAP: &str = include_str!("../../game-ai/maps/town.ron");
+
+/// The navigation graph agents plan their paths over.
+#[derive(Resource)]
+pub struct NavGraph(pub SparseGraph<Location>);
+
+impl NavGraph {
+    /// The town, as drawn in the bundled map: each location is a node,
+    /// joined by roads through a crossroads.
+    pub fn town() -> Self {
+        let map: SparseGraph<String> =
+            SparseGraph::from_ron_str(TOWN_MAP).expect("bundled town map is valid");
+        NavGraph(map.map_extra_info(|tag| {
+            ron::from_str(&tag).expect("every place on the bundled town map is a location")
+        }))
+    }
+
+    /// Moves the node tagged with `location` to `position`, keeping its roads.
+    pub fn relocate(&mut self, location: Location, position: Vector2D) {
+        let node = match self.0.nodes_with(&location).next() {
+            Some(node) => node.index,
+            None => return,
+        };
+        self.0.node_mut(node).unwrap().position = position;
+        let neighbours: Vec<usize> = self.0.edges(node).iter().map(|edge| edge.to).collect();
+        for to in neighbours {
+            self.0.remove_edge(node, to);
+            join(&mut self.0, node, to);
+        }
+    }
+
+    pub fn position_of(&self, location: Location) -> Option<Vector2D> {
+        self.0
+            .nodes_with(&location)
+            .next()
+            .map(|node| node.position)
+    }
+
+    /// The places `path` passes through, in order, leaving out the
+    /// crossroads and anywhere else that isn't one.
+    pub fn places_along(&self, path: &[PathEdge]) -> Vec<Location> {
+        path.iter()
+            .filter_map(|edge| {
+                self.0
+                    .nodes()
+                    .find(|node| node.position == edge.destination)
+                    .and_then(|node| node.extra_info)
+            })
+            .collect()
+    }
+}
+
+/// Builds a road between two nodes, as long as the distance between them.
+fn join(graph: &mut SparseGraph<Location>, from: usize, to: usize) {
+    let cost = graph
+        .node(from)
+        .unwrap()
+        .position
+        .distance(graph.node(to).unwrap().position);
+    graph.add_edge(GraphEdge::new(from, to, cost));
+}
+
+/// The searches of every planner, sharing one budget of cycles per update.
+#[derive(Resource)]
+pub struct PathSearches(PathManager<Entity, Location>);
+
+impl PathSearches {
+    pub fn new(cycles_per_update: usize, policy: BudgetPolicy) -> Self {
+        PathSearches(PathManager::new(cycles_per_update, policy))
+    }
+}
+
+/// A search still under way, as saved with the world: whose it is, where it
+/// started, what it's looking for and how many cycles it's had.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct SavedSearch {
+    pub owner: Entity,
+    pub from: Vector2D,
+    pub item: Location,
+    pub cycles: usize,
+}
+
+impl MapEntities for SavedSearch {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        self.owner = entity_mapper.get_mapped(self.owner);
+    }
+}
+
+/// The searches under way, in the order they take their turns.
+pub fn save_searches(world: &World) -> Vec<SavedSearch> {
+    let searches = match world.get_resource::<PathSearches>() {
+        Some(searches) => searches,
+        None => return vec![],
+    };
+    searches
+        .searches()
+        .filter_map(|(&owner, cycles)| {
+            let (from, item) = world.get::<PathPlanner>(owner)?.searched?;
+            Some(SavedSearch {
+                owner,
+                from,
+                item,
+                cycles,
+            })
+        })
+        .collect()
+}
+
+/// Takes up `saved` searches where they left off, in place of any under way.
+pub fn resume_searches(world: &mut World, saved: Vec<SavedSearch>) {
+    world.resource_scope(|world, mut searches: Mut<PathSearches>| {
+        **searches = PathManager::new(searches.cycles_per_update(), searches.policy());
+        for search in saved {
+            let graph = &world.resource::<NavGraph>().0;
+            let resumed = match create_search(graph, search.from, search.item) {
+                Some(resumed) => resumed,
+                None => continue,
+            };
+            searches.resume(search.owner, resumed, search.cycles, graph);
+            if let Some(mut planner) = world.get_mut::<PathPlanner>(search.owner) {
+                planner.searched = Some((search.from, search.item));
+            }
+        }
+    });
+}
+
+impl Deref for PathSearches {
+    type Target = PathManager<Entity, Location>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl DerefMut for PathSearches {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+type BoxedSearch = Box<dyn TimeSlicedSearch<Location> + Send + Sync>;
+
+/// The search for the closest place tagged with `item` from `from`, or
+/// `None` if `from` can't be mapped onto the graph at all.
+fn create_search(
+    graph: &SparseGraph<Location>,
+    from: Vector2D,
+    item: Location,
+) -> Option<BoxedSearch> {
+    let source = graph.closest_node(from)?;
+    Some(Box::new(GraphSearchDijkstra::new(
+        graph,
+        source,
+        FindExtraInfo(item),
+    )))
+}
+
+/// Plans paths for its entity. A new request replaces any search still in
+/// progress; the outcome arrives as a [`PathReady`] or [`PathNotFound`] message,
+/// with the path itself attached to the entity as a [`Path`] component.
+#[derive(Component)]
+pub struct PathPlanner {
+    position: Vector2D,
+    /// Where the next search starts from, and the place it looks for.
+    request: Option<(Vector2D, Location)>,
+    /// The same for the search under way, or the last one to finish.
+    searched: Option<(Vector2D, Location)>,
+}
+
+impl PathPlanner {
+    pub fn new(position: Vector2D) -> Self {
+        PathPlanner {
+            position,
+            request: None,
+            searched: None,
+        }
+    }
+
+    /// Where future paths are planned from.
+    pub fn position(&self) -> Vector2D {
+        self.position
+    }
+
+    /// Moves the point future paths are planned from.
+    pub fn set_position(&mut self, position: Vector2D) {
+        self.position = position;
+    }
+
+    /// Requests a path from the current position to the closest place tagged
+    /// with `item`.
+    pub fn request_path_to_item(&mut self, item: Location) {
+        self.request = Some((self.position, item));
+    }
+
+    /// The searched route, plus the leg from the start position onto the
+    /// graph.
+    fn complete_path(&self, nodes: &[usize], graph: &SparseGraph<Location>) -> Vec<PathEdge> {
+        let from = self.searched.map_or(self.position, |(from, _)| from);
+        let mut waypoints = vec![from];
+        waypoints.extend(
+            nodes
+                .iter()
+                .filter_map(|&index| graph.node(index))
+                .map(|node| node.position),
+        );
+        waypoints.dedup();
+
+        edges_from_waypoints(&waypoints)
+    }
+}
+
+/// The most recently planned path of an entity.
+#[derive(Component)]
+pub struct Path(pub Vec<PathEdge>);
+
+#[derive(Message)]
+pub struct PathReady(pub Entity);
+
+#[derive(Message)]
+pub struct PathNotFound(pub Entity);
+
+pub fn plan_paths(
+    mut commands: Commands,
+    graph: Res<NavGraph>,
+    mut searches: ResMut<PathSearches>,
+    mut planners: Query<(Entity, &mut PathPlanner)>,
+    mut ready: MessageWriter<PathReady>,
+    mut not_found: MessageWriter<PathNotFound>,
+) {
+    // in the order the planners were spawned, whichever tables they've since
+    // moved between, so the searches take their turns in the same order
+    let in_turn = planners
+        .iter_mut()
+        .sort_by_key::<Entity, _>(|entity| entity.index_u32());
+    for (entity, mut planner) in in_turn {
+        if let Some((from, item)) = planner.request.take() {
+            planner.searched = Some((from, item));
+            match create_search(&graph.0, from, item) {
+                Some(search) => searches.register(entity, search),
+                None => {
+                    searches.cancel(&entity);
+                    not_found.write(PathNotFound(entity));
+                }
+            }
+        }
+    }
+
+    for (entity, outcome) in searches.update_searches(&graph.0) {
+        match (outcome, planners.get_mut(entity)) {
+            (SearchOutcome::Found { path, .. }, Ok((_, planner))) => {
+                commands
+                    .entity(entity)
+                    .insert(Path(planner.complete_path(&path, &graph.0)));
+                ready.write(PathReady(entity));
+            }
+            _ => {
+                not_found.write(PathNotFound(entity));
+            }
+        }
+    }
+}
+
+/// Cancels the searches of planners that have been despawned, or lost their
+/// planner, so they stop using up the budget and their paths aren't
+/// announced to no one.
+pub fn cancel_abandoned_searches(
+    mut gone: RemovedComponents<PathPlanner>,
+    mut searches: ResMut<PathSearches>,
+) {
+    for entity in gone.read() {
+        searches.cancel(&entity);
+    }
+}
+
+pub struct PathPlannerPlugin;
+
+impl Plugin for PathPlannerPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(NavGraph::town());
+        app.insert_resource(PathSearches::new(
+            SEARCH_CYCLES_PER_UPDATE,
+            BudgetPolicy::RoundRobin,
+        ));
+        add_tick_messages::<PathReady>(app);
+        add_tick_messages::<PathNotFound>(app);
+        app.add_systems(
+            FixedUpdate,
+            (cancel_abandoned_searches, plan_paths)
+                .chain()
+                .in_set(Phase::Act),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_app::FixedFirst;
+
+    /// The town's paths planned with `cycles` cycles an update.
+    fn town(cycles: usize) -> App {
+        let mut app = App::new();
+        app.add_plugins(PathPlannerPlugin);
+        app.insert_resource(PathSearches::new(cycles, BudgetPolicy::RoundRobin));
+        app
+    }
+
+    fn planner_at(app: &mut App, location: Location) -> Entity {
+        let position = app.world().resource::<NavGraph>().position_of(location);
+        app.world_mut()
+            .spawn(PathPlanner::new(position.unwrap()))
+            .id()
+    }
+
+    fn request(app: &mut App, planner: Entity, item: Location) {
+        let mut planner = app.world_mut().get_mut::<PathPlanner>(planner).unwrap();
+        planner.request_path_to_item(item);
+    }
+
+    /// Runs a tick, returning who heard their path was ready and who heard
+    /// it wasn't found.
+    fn tick(app: &mut App) -> (Vec<Entity>, Vec<Entity>) {
+        app.world_mut().run_schedule(FixedFirst);
+        app.world_mut().run_schedule(FixedUpdate);
+        let world = app.world();
+        let ready = world.resource::<Messages<PathReady>>();
+        let not_found = world.resource::<Messages<PathNotFound>>();
+        (
+            ready
+                .iter_current_update_messages()
+                .map(|PathReady(entity)| *entity)
+                .collect(),
+            not_found
+                .iter_current_update_messages()
+                .map(|PathNotFound(entity)| *entity)
+                .collect(),
+        )
+    }
+
+    #[test]
+    fn the_town_map_has_every_location_but_the_hills() {
+        let town = NavGraph::town();
+        for &location in &[
+            Location::Goldmine,
+            Location::Bank,
+            Location::Shack,
+            Location::Saloon,
+            Location::Hideout,
+            Location::Jail,
+            Location::Church,
+        ] {
+            assert!(town.position_of(location).is_some(), "{:?}", location);
+        }
+        assert_eq!(town.position_of(Location::Hills), None);
+        assert_eq!(
+            town.position_of(Location::Saloon),
+            Some(Vector2D::new(40.0, 20.0))
+        );
+    }
+
+    #[test]
+    fn the_path_asked_for_is_attached_and_announced() {
+        let mut app = town(SEARCH_CYCLES_PER_UPDATE);
+        let bob = planner_at(&mut app, Location::Shack);
+        request(&mut app, bob, Location::Saloon);
+
+        assert_eq!(tick(&mut app), (vec![bob], vec![]));
+        let path = &app.world().get::<Path>(bob).unwrap().0;
+        let graph = app.world().resource::<NavGraph>();
+        assert_eq!(
+            path.first().map(|edge| edge.source),
+            graph.position_of(Location::Shack)
+        );
+        assert_eq!(
+            path.last().map(|edge| edge.destination),
+            graph.position_of(Location::Saloon)
+        );
+        // by way of the crossroads, which isn't a place
+        assert_eq!(graph.places_along(path), vec![Location::Saloon]);
+        // nothing more's heard until he asks again
+        assert_eq!(tick(&mut app), (vec![], vec![]));
+    }
+
+    #[test]
+    fn a_place_not_on_the_map_is_not_found() {
+        let mut app = town(SEARCH_CYCLES_PER_UPDATE);
+        let bob = planner_at(&mut app, Location::Shack);
+        request(&mut app, bob, Location::Hills);
+
+        assert_eq!(tick(&mut app), (vec![], vec![bob]));
+        assert!(app.world().get::<Path>(bob).is_none());
+    }
+
+    #[test]
+    fn searches_take_turns_with_the_cycles_of_each_update() {
+        let mut app = town(2);
+        let bob = planner_at(&mut app, Location::Shack);
+        let elsa = planner_at(&mut app, Location::Shack);
+        request(&mut app, bob, Location::Goldmine);
+        request(&mut app, elsa, Location::Goldmine);
+
+        let mut heard = vec![];
+        for _ in 0..10 {
+            let (ready, not_found) = tick(&mut app);
+            assert!(not_found.is_empty());
+            heard.push(ready);
+        }
+        // a cycle each an update, so they hear on the same update, in the
+        // order they were spawned
+        let update = heard.iter().position(|ready| !ready.is_empty()).unwrap();
+        assert!(update > 0, "found without waiting for the budget");
+        assert_eq!(heard[update], vec![bob, elsa]);
+        assert!(heard[update + 1..].iter().all(Vec::is_empty));
+    }
+
+    #[test]
+    fn a_despawned_planner_gives_up_its_search() {
+        let mut app = town(2);
+        let bob = planner_at(&mut app, Location::Shack);
+        let elsa = planner_at(&mut app, Location::Shack);
+        request(&mut app, bob, Location::Goldmine);
+        request(&mut app, elsa, Location::Goldmine);
+        tick(&mut app);
+        app.world_mut().despawn(bob);
+
+        let mut heard = vec![];
+        for _ in 0..10 {
+            let (ready, not_found) = tick(&mut app);
+            assert!(!ready.contains(&bob) && !not_found.contains(&bob));
+            heard.extend(ready);
+            let searches = app.world().resource::<PathSearches>();
+            assert!(!searches.is_searching(&bob));
+        }
+        assert_eq!(heard, vec![elsa]);
+    }
+
+    #[test]
+    fn a_saved_search_resumes_where_it_left_off() {
+        let mut played = town(1);
+        let bob = planner_at(&mut played, Location::Bank);
+        request(&mut played, bob, Location::Church);
+        tick(&mut played);
+        tick(&mut played);
+
+        let saved = save_searches(played.world());
+        assert_eq!(saved.len(), 1);
+        assert_eq!((saved[0].owner, saved[0].cycles), (bob, 2));
+        // a fresh world hands out the same entity
+        let mut resumed = town(1);
+        assert_eq!(planner_at(&mut resumed, Location::Bank), bob);
+        resume_searches(resumed.world_mut(), saved);
+
+        loop {
+            let heard = tick(&mut played);
+            assert_eq!(tick(&mut resumed), heard);
+            if heard != (vec![], vec![]) {
+                break;
+            }
+        }
+        let path = |app: &App| app.world().get::<Path>(bob).unwrap().0.len();
+        assert_eq!(path(&resumed), path(&played));
+    }
+}
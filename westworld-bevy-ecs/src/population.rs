@@ -0,0 +1,78 @@
+use crate::cli::Cli;
+
+static MINER_NAMES: &[&str] = &["Bob", "Jake", "Ezra", "Hank", "Cletus", "Amos"];
+static PARTNER_NAMES: &[&str] = &["Elsa", "Martha", "Clara", "Ida", "Mabel", "Ruth"];
+
+/// How many of each kind of agent to spawn at startup.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Population {
+    pub miners: usize,
+    pub partners: usize,
+}
+
+impl Population {
+    /// Reads `--miners=N` and `--partners=N`, defaulting to one of each.
+    pub fn from_cli(cli: &Cli) -> Self {
+        Population {
+            miners: cli.miners,
+            partners: cli.partners,
+        }
+    }
+
+    pub fn miner_name(i: usize) -> String {
+        format!("Miner {}", generated_name(MINER_NAMES, i))
+    }
+
+    pub fn partner_name(i: usize) -> String {
+        generated_name(PARTNER_NAMES, i)
+    }
+}
+
+impl Default for Population {
+    fn default() -> Self {
+        Population {
+            miners: 1,
+            partners: 1,
+        }
+    }
+}
+
+/// Names are used in order, then numbered once they run out.
+fn generated_name(names: &[&str], i: usize) -> String {
+    let name = names[i % names.len()];
+    match i / names.len() {
+        0 => name.to_string(),
+        n => format!("{} {}", name, n + 1),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+
+    #[test]
+    fn counts_from_cli() {
+        let cli = Cli::parse_from(["westworld", "--partners=0", "--miners=3"]);
+        assert_eq!(
+            Population::from_cli(&cli),
+            Population {
+                miners: 3,
+                partners: 0
+            }
+        );
+        let cli = Cli::parse_from(["westworld"]);
+        assert_eq!(Population::from_cli(&cli), Population::default());
+    }
+
+    #[test]
+    fn names_are_numbered_once_used_up() {
+        assert_eq!(Population::miner_name(0), "Miner Bob");
+        assert_eq!(Population::partner_name(1), "Martha");
+        assert_eq!(Population::miner_name(MINER_NAMES.len()), "Miner Bob 2");
+        assert_eq!(
+            Population::partner_name(2 * PARTNER_NAMES.len() + 1),
+            "Martha 3"
+        );
+    }
+}
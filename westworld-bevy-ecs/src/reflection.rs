@@ -0,0 +1,116 @@
+//! Registering the agents' components with bevy's reflection, so they can be
+//! looked up and serialized by type name, as scenes and the inspector do.
+//!
+//! [`StateStack`] stays free of bevy, so each agent also carries a
+//! [`StackView`] copy of its states that bevy can reflect.
+
+use crate::bandit::BanditState;
+use crate::barfly::BarFlyState;
+use crate::current_state::CurrentState;
+use crate::miner::{DigSlot, Miner, MinerState};
+use crate::partner::{Partner, PartnerState};
+use crate::sheriff::SheriffState;
+use crate::{Location, Name};
+use bevy_app::{App, FixedLast, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_reflect::{FromReflect, GetTypeRegistration, Reflect, TypePath, Typed};
+use fsm::StateStack;
+// for the derived impls, which expect the 2021 prelude
+use std::iter::FromIterator;
+
+/// The states on an agent's stack, bottom first, copied from its
+/// [`StateStack`] at the end of every tick.
+#[derive(Component, Reflect, Clone, Debug, PartialEq)]
+#[reflect(Component)]
+pub struct StackView<S: Clone + Reflect + TypePath>(pub Vec<S>);
+
+impl<S: Clone + Reflect + TypePath> From<&StateStack<S>> for StackView<S> {
+    fn from(stack: &StateStack<S>) -> Self {
+        StackView(stack.iter().cloned().collect())
+    }
+}
+
+type Views<'a, S> = (Entity, &'a StateStack<S>, Option<&'a mut StackView<S>>);
+
+pub fn view_states<S: Clone + Reflect + TypePath>(
+    mut commands: Commands,
+    mut stacks: Query<Views<S>, Changed<StateStack<S>>>,
+) {
+    for (entity, stack, view) in stacks.iter_mut() {
+        match view {
+            Some(mut view) => view.0 = stack.iter().cloned().collect(),
+            None => {
+                commands.entity(entity).insert(StackView::from(stack));
+            }
+        }
+    }
+}
+
+pub struct ReflectionPlugin;
+
+impl ReflectionPlugin {
+    fn add_states<S: Clone + PartialEq + FromReflect + TypePath + Typed + GetTypeRegistration>(
+        app: &mut App,
+    ) {
+        app.register_type::<S>();
+        app.register_type::<StackView<S>>();
+        app.register_type::<CurrentState<S>>();
+        app.add_systems(FixedLast, view_states::<S>);
+    }
+}
+
+impl Plugin for ReflectionPlugin {
+    fn build(&self, app: &mut App) {
+        app.register_type::<Name>()
+            .register_type::<Location>()
+            .register_type::<Miner>()
+            .register_type::<Partner>()
+            .register_type::<DigSlot>();
+        Self::add_states::<MinerState>(app);
+        Self::add_states::<PartnerState>(app);
+        Self::add_states::<BarFlyState>(app);
+        Self::add_states::<BanditState>(app);
+        Self::add_states::<SheriffState>(app);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_reflect::serde::{ReflectDeserializer, ReflectSerializer};
+    use serde::de::DeserializeSeed;
+
+    #[test]
+    fn components_round_trip_by_type_name() {
+        let mut app = App::new();
+        app.add_plugins(ReflectionPlugin);
+        let registry = app.world().resource::<AppTypeRegistry>().read();
+
+        let view = StackView(vec![
+            MinerState::GoHomeAndSleepTilRested,
+            MinerState::TravelTo(Location::Saloon),
+        ]);
+        let components: Vec<Box<dyn Reflect>> = vec![
+            Box::new(Name("Miner Bob".to_string())),
+            Box::new(Location::Saloon),
+            Box::new(Miner::new()),
+            Box::new(view),
+        ];
+        for component in components {
+            let saved = ron::to_string(&ReflectSerializer::new(
+                component.as_partial_reflect(),
+                &registry,
+            ))
+            .unwrap();
+            let restored = ReflectDeserializer::new(&registry)
+                .deserialize(&mut ron::Deserializer::from_str(&saved).unwrap())
+                .unwrap();
+            assert_eq!(
+                component.reflect_partial_eq(restored.as_ref()),
+                Some(true),
+                "{}",
+                saved
+            );
+        }
+    }
+}
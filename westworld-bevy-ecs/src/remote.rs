@@ -0,0 +1,370 @@
+//! Driving the town from another program, with `--control=ADDRESS`: test
+//! scripts and demos connect over TCP and send requests, a line of JSON each,
+//! each answered with a line of JSON saying whether it worked. With `nc`:
+//!
+//! ```text
+//! > {"command": "pause"}
+//! < {"ok":true}
+//! > {"command": "step", "ticks": 3}
+//! < {"ok":true}
+//! > {"command": "stack", "agent": "Miner Bob"}
+//! < {"ok":true,"stack":["EnterMineAndDigForNugget","TravelTo(Goldmine)"]}
+//! > {"command": "send", "to": "Miner Bob", "message": "StewReady", "from": "Elsa"}
+//! < {"ok":true}
+//! > {"command": "set", "field": "thirst_level", "value": 8}
+//! < {"ok":true}
+//! > {"command": "speed", "speed": "10x"}
+//! < {"ok":true}
+//! > {"command": "resume"}
+//! < {"ok":true}
+//! ```
+//!
+//! A telegram sent without a `from` is sent in the receiver's own name, and
+//! is posted with the messages of the next tick, `delay` ticks after that if
+//! it's given one. Setting the config's `tick_interval_ms` changes nothing,
+//! the pace being fixed once the run starts, as with `--watch`; change the
+//! speed instead, which can't be changed with `--fast`.
+//!
+//! Requests are carried out between frames, in the order they arrive, before
+//! the frame's ticks run.
+
+use crate::bandit::BanditState;
+use crate::barfly::BarFlyState;
+use crate::cli::Cli;
+use crate::messaging::{Message, Outbox};
+use crate::miner::MinerState;
+use crate::partner::PartnerState;
+use crate::rerun::{Input, InputLog};
+use crate::sheriff::SheriffState;
+use crate::timestep::Speed;
+use crate::{Name, Settings};
+use bevy_app::{App, First, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use bevy_time::{Fixed, Time, TimeSystems, Virtual};
+use fsm::StateStack;
+use serde::Deserialize;
+use serde_json::{json, Value};
+use std::fmt::Debug;
+use std::io::{self, BufRead, BufReader, Write};
+use std::net::{TcpListener, TcpStream};
+use std::sync::mpsc::{self, Receiver, Sender};
+use std::sync::Mutex;
+use std::thread;
+
+/// What can be asked for.
+#[derive(Clone, Debug, PartialEq, Deserialize)]
+#[serde(tag = "command", rename_all = "snake_case")]
+pub enum Request {
+    Pause,
+    Resume,
+    /// Runs this many ticks, pausing if running.
+    Step {
+        #[serde(default = "one")]
+        ticks: u32,
+    },
+    /// Sends a telegram to the agent called `to`.
+    Send {
+        to: String,
+        message: Message,
+        #[serde(default)]
+        from: Option<String>,
+        #[serde(default)]
+        delay: usize,
+    },
+    /// The states on an agent's stack, from the bottom up.
+    Stack {
+        agent: String,
+    },
+    /// Runs at `speed`: "0.25x", "1x", "10x" or "max".
+    Speed {
+        speed: Speed,
+    },
+    /// Changes a field of the config.
+    Set {
+        field: String,
+        value: Value,
+    },
+}
+
+fn one() -> u32 {
+    1
+}
+
+/// A request, and where to send the answer to it.
+type Asked = (Request, Sender<Value>);
+
+/// The requests waiting to be carried out.
+#[derive(Resource)]
+pub struct Requests(Mutex<Receiver<Asked>>);
+
+/// Starts taking requests on `address` in the background, a thread to each
+/// client.
+pub fn listen(address: &str) -> io::Result<Requests> {
+    let listener = TcpListener::bind(address)?;
+    let (sender, requests) = mpsc::channel();
+    thread::spawn(move || {
+        for client in listener.incoming().flatten() {
+            let requests = sender.clone();
+            thread::spawn(move || {
+                if let Err(e) = converse(client, requests) {
+                    warn!("control: {}", e);
+                }
+            });
+        }
+    });
+    Ok(Requests(Mutex::new(requests)))
+}
+
+/// Answers each line `client` sends, until it hangs up or the run's over.
+fn converse(client: TcpStream, requests: Sender<Asked>) -> io::Result<()> {
+    let mut out = client.try_clone()?;
+    for line in BufReader::new(client).lines() {
+        let line = line?;
+        if line.trim().is_empty() {
+            continue;
+        }
+        let answer = match serde_json::from_str(&line) {
+            Ok(request) => {
+                let (reply, answer) = mpsc::channel();
+                if requests.send((request, reply)).is_err() {
+                    break;
+                }
+                match answer.recv() {
+                    Ok(answer) => answer,
+                    Err(_) => break,
+                }
+            }
+            Err(e) => answer(Err(e.to_string())),
+        };
+        writeln!(out, "{}", answer)?;
+    }
+    Ok(())
+}
+
+/// The line answering a request, with whatever it asked for if it worked.
+fn answer(result: Result<Value, String>) -> Value {
+    match result {
+        Ok(mut answer) => {
+            answer["ok"] = json!(true);
+            answer
+        }
+        Err(error) => json!({"ok": false, "error": error}),
+    }
+}
+
+/// The agent called `name`.
+pub fn find(world: &mut World, name: &str) -> Result<Entity, String> {
+    world
+        .query::<(Entity, &Name)>()
+        .iter(world)
+        .find(|(_, agent)| agent.0 == name)
+        .map(|(entity, _)| entity)
+        .ok_or_else(|| format!("nobody called {:?}", name))
+}
+
+/// The states on `entity`'s stack if it's a stack of `S`.
+fn stack_of<S>(world: &World, entity: Entity) -> Option<Vec<String>>
+where
+    S: Clone + Debug + Send + Sync + 'static,
+{
+    let stack = world.get::<StateStack<S>>(entity)?;
+    Some(stack.iter().map(|state| format!("{:?}", state)).collect())
+}
+
+/// Carries out `request`, returning what it asked for.
+pub fn carry_out(world: &mut World, request: Request) -> Result<Value, String> {
+    match request {
+        Request::Pause => world.resource_mut::<Time<Virtual>>().pause(),
+        Request::Resume => world.resource_mut::<Time<Virtual>>().unpause(),
+        Request::Step { ticks } => {
+            world.resource_mut::<Time<Virtual>>().pause();
+            let mut fixed = world.resource_mut::<Time<Fixed>>();
+            let timestep = fixed.timestep();
+            fixed.accumulate_overstep(timestep * ticks);
+        }
+        Request::Send {
+            to,
+            message,
+            from,
+            delay,
+        } => {
+            let receiver = find(world, &to)?;
+            let sender = match &from {
+                Some(from) => find(world, from)?,
+                None => receiver,
+            };
+            let mut outbox = world
+                .get_mut::<Outbox>(sender)
+                .ok_or_else(|| "only agents can send telegrams".to_string())?;
+            outbox.dispatch(delay, receiver, message);
+            if let Some(mut inputs) = world.get_resource_mut::<InputLog>() {
+                inputs.note(|tick| Input::Telegram {
+                    tick,
+                    sender: from.unwrap_or_else(|| to.clone()),
+                    receiver: to,
+                    message,
+                    delay,
+                });
+            }
+        }
+        Request::Stack { agent } => {
+            let entity = find(world, &agent)?;
+            let stack = stack_of::<MinerState>(world, entity)
+                .or_else(|| stack_of::<PartnerState>(world, entity))
+                .or_else(|| stack_of::<BarFlyState>(world, entity))
+                .or_else(|| stack_of::<BanditState>(world, entity))
+                .or_else(|| stack_of::<SheriffState>(world, entity))
+                .ok_or_else(|| format!("{} has no states", agent))?;
+            return Ok(json!({ "stack": stack }));
+        }
+        Request::Speed { speed } => {
+            let mut running = world
+                .get_resource_mut::<Speed>()
+                .ok_or_else(|| "the speed's fixed running flat out".to_string())?;
+            *running = speed;
+        }
+        Request::Set { field, value } => {
+            let mut settings = world.resource_mut::<Settings>();
+            let mut config = serde_json::to_value(&settings.0).map_err(|e| e.to_string())?;
+            match config.get_mut(&field) {
+                Some(old) => *old = value.clone(),
+                None => return Err(format!("the config has no field called {:?}", field)),
+            }
+            settings.0 = serde_json::from_value(config).map_err(|e| e.to_string())?;
+            let config = settings.0.clone();
+            if let Some(mut inputs) = world.get_resource_mut::<InputLog>() {
+                inputs.note(|tick| Input::Config { tick, config });
+            }
+            info!("{} set to {}", field, value);
+        }
+    }
+    Ok(json!({}))
+}
+
+/// Carries out the requests that have come in since the last frame.
+pub fn answer_requests(world: &mut World) {
+    world.resource_scope(|world, requests: Mut<Requests>| {
+        let requests = requests.0.lock().unwrap();
+        while let Ok((request, reply)) = requests.try_recv() {
+            debug!("control: {:?}", request);
+            // the client may have gone since asking
+            let _ = reply.send(answer(carry_out(world, request)));
+        }
+    });
+}
+
+/// Takes requests on `--control=ADDRESS`.
+pub struct RemoteControlPlugin(pub String);
+
+impl RemoteControlPlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.control.clone().map(RemoteControlPlugin)
+    }
+}
+
+impl Plugin for RemoteControlPlugin {
+    fn build(&self, app: &mut App) {
+        let requests = listen(&self.0)
+            .unwrap_or_else(|e| panic!("could not take requests on {}: {}", self.0, e));
+        info!("taking requests on {}", self.0);
+        app.insert_resource(requests);
+        // before the time's updated, so a pause or a step takes effect this
+        // frame, as the keyboard's do
+        app.add_systems(First, answer_requests.before(TimeSystems));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use crate::population::Population;
+    use crate::scenario::Scenario;
+    use crate::testing::{build_test_app, tick};
+
+    fn town() -> App {
+        let mut app = build_test_app(
+            Scenario::populated(&Population {
+                miners: 1,
+                partners: 1,
+            }),
+            7,
+        );
+        app.insert_resource(Settings::default());
+        tick(&mut app, 1);
+        app
+    }
+
+    #[test]
+    fn requests_are_a_line_of_json() {
+        let request = |line| serde_json::from_str::<Request>(line);
+        assert_eq!(request(r#"{"command": "pause"}"#).unwrap(), Request::Pause);
+        assert_eq!(
+            request(r#"{"command": "step"}"#).unwrap(),
+            Request::Step { ticks: 1 }
+        );
+        assert_eq!(
+            request(r#"{"command": "send", "to": "Elsa", "message": "HiHoneyImHome"}"#).unwrap(),
+            Request::Send {
+                to: "Elsa".to_string(),
+                message: Message::HiHoneyImHome,
+                from: None,
+                delay: 0,
+            }
+        );
+        assert!(request(r#"{"command": "dance"}"#).is_err());
+    }
+
+    #[test]
+    fn an_agents_stack_can_be_asked_for() {
+        let mut app = town();
+        let answer = answer(carry_out(
+            app.world_mut(),
+            Request::Stack {
+                agent: "Miner Bob".to_string(),
+            },
+        ));
+        assert_eq!(
+            answer,
+            json!({"ok": true, "stack": ["EnterMineAndDigForNugget", "TravelTo(Goldmine)"]})
+        );
+
+        let nobody = carry_out(
+            app.world_mut(),
+            Request::Stack {
+                agent: "Miner Ben".to_string(),
+            },
+        );
+        assert_eq!(nobody, Err("nobody called \"Miner Ben\"".to_string()));
+    }
+
+    #[test]
+    fn the_config_can_be_changed_live() {
+        let mut app = town();
+        let set = |field: &str, value| Request::Set {
+            field: field.to_string(),
+            value,
+        };
+        assert!(carry_out(app.world_mut(), set("thirst_level", json!(8))).is_ok());
+        assert_eq!(app.world().resource::<Settings>().thirst_level, 8);
+
+        assert!(carry_out(app.world_mut(), set("thirst", json!(8))).is_err());
+        assert!(carry_out(app.world_mut(), set("thirst_level", json!("very"))).is_err());
+        assert_eq!(app.world().resource::<Settings>().thirst_level, 8);
+    }
+
+    #[test]
+    fn stepping_runs_that_many_ticks_and_no_more() {
+        let mut app = town();
+        let hour = |app: &App| app.world().resource::<Clock>().hour();
+        let paused_at = hour(&app);
+        carry_out(app.world_mut(), Request::Pause).unwrap();
+        tick(&mut app, 3);
+        assert_eq!(hour(&app), paused_at);
+
+        carry_out(app.world_mut(), Request::Step { ticks: 2 }).unwrap();
+        tick(&mut app, 3);
+        assert_eq!(hour(&app), paused_at + 2);
+    }
+}
@@ -0,0 +1,435 @@
+//! Recording a run to a file and playing it back.
+//!
+//! A recording keeps the seed and scenario the run started from, every
+//! random number drawn, and every state transition and message in the order
+//! they happened. Playing it back starts a fresh run from the same scenario,
+//! feeds it the recorded random numbers and checks each transition and message
+//! against the recording, panicking at the first one that differs.
+
+use crate::cli::Cli;
+use crate::controls::Pace;
+use crate::diagnostics::Tally;
+use crate::event_log::EventLog;
+use crate::messaging::Message;
+use crate::metrics::Metrics;
+use crate::rng::{Seed, WorldRng};
+use crate::scenario::Scenario;
+#[cfg(feature = "websocket")]
+use crate::stream::Stream;
+use crate::Name;
+use bevy_app::{App, FixedFirst, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use fsm::StateStack;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt::{self, Debug};
+use std::path::{Path, PathBuf};
+use std::{error, fs, io};
+
+/// Something an agent did that a replay has to reproduce.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Record {
+    Transition {
+        tick: u64,
+        agent: String,
+        from: String,
+        to: String,
+    },
+    Message {
+        tick: u64,
+        sender: String,
+        receiver: String,
+        message: Message,
+        delay: usize,
+    },
+}
+
+impl fmt::Display for Record {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Record::Transition {
+                tick,
+                agent,
+                from,
+                to,
+            } => write!(
+                f,
+                "tick {}: {} went from [{}] to [{}]",
+                tick, agent, from, to
+            ),
+            Record::Message {
+                tick,
+                sender,
+                receiver,
+                message,
+                delay,
+            } => write!(
+                f,
+                "tick {}: {} sent {:?} to {} with delay {}",
+                tick, sender, message, receiver, delay
+            ),
+        }
+    }
+}
+
+/// A recorded run, as saved to a replay file.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Replay {
+    pub seed: u64,
+    pub scenario: Scenario,
+    pub ticks: u64,
+    pub draws: Vec<u64>,
+    pub records: Vec<Record>,
+}
+
+/// Errors raised while reading or writing a replay file.
+#[derive(Debug)]
+pub enum ReplayError {
+    Io(io::Error),
+    Serialize(ron::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for ReplayError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ReplayError::Io(e) => write!(f, "replay file i/o error: {}", e),
+            ReplayError::Serialize(e) => write!(f, "could not write replay: {}", e),
+            ReplayError::Parse(e) => write!(f, "could not parse replay: {}", e),
+        }
+    }
+}
+
+impl error::Error for ReplayError {}
+
+impl From<io::Error> for ReplayError {
+    fn from(e: io::Error) -> Self {
+        ReplayError::Io(e)
+    }
+}
+
+impl From<ron::Error> for ReplayError {
+    fn from(e: ron::Error) -> Self {
+        ReplayError::Serialize(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for ReplayError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        ReplayError::Parse(e)
+    }
+}
+
+impl Replay {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ReplayError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), ReplayError> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, ron)?;
+        Ok(())
+    }
+}
+
+enum Mode {
+    Recording {
+        path: PathBuf,
+        records: Vec<Record>,
+    },
+    Playing {
+        expected: VecDeque<Record>,
+        checked: usize,
+    },
+}
+
+/// Collects what happens in a run being recorded, or checks it against the
+/// recording being played back.
+#[derive(Resource)]
+pub struct ReplayLog {
+    mode: Mode,
+    tick: u64,
+}
+
+impl ReplayLog {
+    pub fn recording(path: PathBuf) -> Self {
+        ReplayLog {
+            mode: Mode::Recording {
+                path,
+                records: vec![],
+            },
+            tick: 0,
+        }
+    }
+
+    pub fn playing(records: Vec<Record>) -> Self {
+        ReplayLog {
+            mode: Mode::Playing {
+                expected: records.into(),
+                checked: 0,
+            },
+            tick: 0,
+        }
+    }
+
+    /// The tick being run, counting from 1.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// What's been recorded so far; nothing, if playing back.
+    pub fn records(&self) -> &[Record] {
+        match &self.mode {
+            Mode::Recording { records, .. } => records,
+            Mode::Playing { .. } => &[],
+        }
+    }
+
+    /// Keeps `record`, or panics if it isn't the next one in the replay.
+    pub fn record(&mut self, record: Record) {
+        match &mut self.mode {
+            Mode::Recording { records, .. } => records.push(record),
+            Mode::Playing { expected, checked } => match expected.pop_front() {
+                Some(next) if next == record => *checked += 1,
+                Some(next) => panic!("replay diverged: expected {}, got {}", next, record),
+                None => panic!("replay diverged: the recording ended before {}", record),
+            },
+        }
+    }
+}
+
+/// Whatever is keeping track of what the agents do: the replay being recorded
+/// or played back, the event log, the metrics, the diagnostics and the
+/// stream to the browser.
+pub type Observers<'a> = (
+    Option<ResMut<'a, ReplayLog>>,
+    Option<ResMut<'a, EventLog>>,
+    Option<ResMut<'a, Metrics>>,
+    Option<ResMut<'a, Tally>>,
+    Streamed<'a>,
+);
+
+#[cfg(feature = "websocket")]
+type Streamed<'a> = Option<ResMut<'a, Stream>>;
+/// Nothing, without the stream built in.
+#[cfg(not(feature = "websocket"))]
+type Streamed<'a> = ();
+
+/// Whether anything is keeping track, so there's any point making records.
+pub fn observed(observers: &Observers) -> bool {
+    #[cfg(feature = "websocket")]
+    let streamed = observers.4.is_some();
+    #[cfg(not(feature = "websocket"))]
+    let streamed = false;
+    observers.0.is_some()
+        || observers.1.is_some()
+        || observers.2.is_some()
+        || observers.3.is_some()
+        || streamed
+}
+
+/// Hands each observer the record made for the tick it's on.
+pub fn observe(observers: &mut Observers, record: impl Fn(u64) -> Record) {
+    if let Some(log) = observers.0.as_mut() {
+        let record = record(log.tick());
+        log.record(record);
+    }
+    if let Some(log) = observers.1.as_mut() {
+        let record = record(log.tick());
+        log.record(&record);
+    }
+    // the counts don't care which tick it was
+    if let Some(metrics) = observers.2.as_mut() {
+        metrics.record(&record(0));
+    }
+    if let Some(tally) = observers.3.as_mut() {
+        tally.record(&record(0));
+    }
+    #[cfg(feature = "websocket")]
+    if let Some(stream) = observers.4.as_mut() {
+        let record = record(stream.tick());
+        stream.record(&record);
+    }
+}
+
+/// An agent's state stack as it was before it ran, so the transition it made
+/// can be recorded afterwards. Does nothing unless there are [`Observers`].
+pub struct Watch(Option<String>);
+
+impl Watch {
+    pub fn new<S: Clone + Debug>(log: &Observers, stack: &StateStack<S>) -> Self {
+        Watch(
+            Some(stack)
+                .filter(|_| observed(log))
+                .map(ToString::to_string),
+        )
+    }
+
+    pub fn finish<S: Clone + Debug>(
+        self,
+        log: &mut Observers,
+        agent: &Name,
+        stack: &StateStack<S>,
+    ) {
+        self.end(agent, stack).record(log);
+    }
+
+    /// The transition made, if any, to be recorded once the observers can
+    /// be got at.
+    pub fn end<S: Clone + Debug>(self, agent: &Name, stack: &StateStack<S>) -> Watched {
+        Watched(self.0.and_then(|from| {
+            let to = stack.to_string();
+            (from != to).then(|| (agent.to_string(), from, to))
+        }))
+    }
+}
+
+/// The transition an agent made while [`Watch`]ed: its name, and its stack
+/// before and after.
+pub struct Watched(Option<(String, String, String)>);
+
+impl Watched {
+    pub fn record(self, log: &mut Observers) {
+        if let Some((agent, from, to)) = self.0 {
+            observe(log, |tick| Record::Transition {
+                tick,
+                agent: agent.clone(),
+                from: from.clone(),
+                to: to.clone(),
+            });
+        }
+    }
+}
+
+pub fn count_ticks(mut log: ResMut<ReplayLog>) {
+    log.tick += 1;
+}
+
+/// Saves the recording, or checks that all of the replay was played, once
+/// the run is over.
+pub fn finish(world: &mut World) {
+    let log = match world.remove_resource::<ReplayLog>() {
+        Some(log) => log,
+        None => return,
+    };
+    match log.mode {
+        Mode::Recording { path, records } => {
+            let replay = Replay {
+                seed: world.get_resource::<Seed>().unwrap().0,
+                scenario: world.get_resource::<Scenario>().unwrap().clone(),
+                ticks: log.tick,
+                draws: world
+                    .get_resource::<WorldRng>()
+                    .unwrap()
+                    .recorded()
+                    .to_vec(),
+                records,
+            };
+            match replay.save(&path) {
+                Ok(()) => info!("recorded {} ticks to {}", log.tick, path.display()),
+                Err(e) => error!("{}: {}", path.display(), e),
+            }
+        }
+        Mode::Playing { expected, checked } => {
+            if let Some(next) = expected.front() {
+                panic!("replay diverged: the run ended before {}", next);
+            }
+            let unplayed = world.get_resource::<WorldRng>().unwrap().unplayed();
+            if unplayed > 0 {
+                panic!(
+                    "replay diverged: the run ended with {} random numbers left undrawn",
+                    unplayed
+                );
+            }
+            info!(
+                "replay matched: {} ticks, {} transitions and messages",
+                log.tick, checked
+            );
+        }
+    }
+}
+
+/// Records the run with `--record=FILE` or plays one back with
+/// `--replay=FILE`. Add it after [`crate::rng::RngPlugin`], and call
+/// [`finish`] once the run is over.
+pub enum ReplayPlugin {
+    Record(PathBuf),
+    Play(Replay),
+}
+
+impl ReplayPlugin {
+    /// Reads `--record=FILE` or `--replay=FILE`, loading the replay file.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        if let Some(path) = &cli.record {
+            return Some(ReplayPlugin::Record(path.clone()));
+        }
+        cli.replay.as_ref().map(|path| {
+            let replay = Replay::load(path)
+                .unwrap_or_else(|e| panic!("could not load {}: {}", path.display(), e));
+            ReplayPlugin::Play(replay)
+        })
+    }
+}
+
+impl Plugin for ReplayPlugin {
+    fn build(&self, app: &mut App) {
+        match self {
+            ReplayPlugin::Record(path) => {
+                let world = app.world_mut();
+                world.get_resource_mut::<WorldRng>().unwrap().record();
+                world.insert_resource(ReplayLog::recording(path.clone()));
+            }
+            ReplayPlugin::Play(replay) => {
+                let mut rng = WorldRng::seed_from_u64(replay.seed);
+                rng.play(replay.draws.clone());
+                let world = app.world_mut();
+                world.insert_resource(rng);
+                world.insert_resource(Seed(replay.seed));
+                world.insert_resource(replay.scenario.clone());
+                if let Some(mut pace) = world.get_resource_mut::<Pace>() {
+                    pace.ticks = Some(replay.ticks);
+                }
+                world.insert_resource(ReplayLog::playing(replay.records.clone()));
+            }
+        }
+        app.add_systems(FixedFirst, count_ticks);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::messaging::Message;
+
+    fn message(tick: u64) -> Record {
+        Record::Message {
+            tick,
+            sender: "Miner Bob".to_string(),
+            receiver: "Elsa".to_string(),
+            message: Message::HiHoneyImHome,
+            delay: 0,
+        }
+    }
+
+    #[test]
+    fn replay_round_trips_through_ron() {
+        let replay = Replay {
+            seed: 42,
+            scenario: Scenario::default(),
+            ticks: 3,
+            draws: vec![1, 2, 3],
+            records: vec![message(2)],
+        };
+        let ron = ron::to_string(&replay).unwrap();
+        assert_eq!(ron::from_str::<Replay>(&ron).unwrap(), replay);
+    }
+
+    #[test]
+    #[should_panic(expected = "replay diverged")]
+    fn playing_back_a_different_record_panics() {
+        let mut log = ReplayLog::playing(vec![message(2)]);
+        log.record(message(3));
+    }
+}
@@ -0,0 +1,527 @@
+//! Recording everything that goes into a run, to run it again and check it
+//! ends up the same.
+//!
+//! Where a [`crate::replay`] keeps what the agents did, this keeps what was
+//! done to them: the seed, the scenario and the config the run started from,
+//! then in the order they came every telegram sent in over `--control`,
+//! every change to the config, and the random numbers drawn each tick. With
+//! it is a hash of the world as the run left it. Running it again starts
+//! from the same place, makes the same changes before the same ticks, hands
+//! out the same random numbers, and checks the world comes out with the same
+//! hash, so a run that went strangely can be sent in with a bug report and
+//! shown to go the same way again.
+//!
+//! Scripts and brains aren't kept, so a run using them has to be run again
+//! with the same ones.
+
+use crate::cli::Cli;
+use crate::controls::Pace;
+use crate::messaging::{Message, Outbox, Telegram};
+use crate::remote::find;
+use crate::rng::{Seed, WorldRng};
+use crate::scenario::Scenario;
+use crate::snapshot::Snapshot;
+use crate::Settings;
+use bevy_app::{App, FixedFirst, FixedLast, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use game_ai::config::Config;
+use rand::SeedableRng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+use std::fmt;
+use std::path::{Path, PathBuf};
+use std::{error, fs, io};
+
+/// Something done to the run from outside it, before the tick after `tick`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum Input {
+    /// A telegram sent to `receiver` as though from `sender`.
+    Telegram {
+        tick: u64,
+        sender: String,
+        receiver: String,
+        message: Message,
+        delay: usize,
+    },
+    /// The config, as it was changed to.
+    Config { tick: u64, config: Config },
+    /// The random numbers drawn up to the end of `tick`, since the last.
+    Draws { tick: u64, draws: Vec<u64> },
+}
+
+impl Input {
+    fn tick(&self) -> u64 {
+        match self {
+            Input::Telegram { tick, .. }
+            | Input::Config { tick, .. }
+            | Input::Draws { tick, .. } => *tick,
+        }
+    }
+}
+
+impl fmt::Display for Input {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Input::Telegram {
+                tick,
+                sender,
+                receiver,
+                message,
+                delay,
+            } => write!(
+                f,
+                "tick {}: {:?} sent to {} from {} with delay {}",
+                tick, message, receiver, sender, delay
+            ),
+            Input::Config { tick, .. } => write!(f, "tick {}: the config changed", tick),
+            Input::Draws { tick, draws } => {
+                write!(f, "tick {}: {} random numbers drawn", tick, draws.len())
+            }
+        }
+    }
+}
+
+/// A recorded run's inputs, as saved to a file to run it again from.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Inputs {
+    pub seed: u64,
+    pub scenario: Scenario,
+    pub config: Config,
+    pub ticks: u64,
+    pub inputs: Vec<Input>,
+    /// The hash of the world at the end of the run, from [`state_hash`].
+    pub hash: u64,
+}
+
+/// Errors raised while reading or writing an inputs file.
+#[derive(Debug)]
+pub enum RerunError {
+    Io(io::Error),
+    Serialize(ron::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for RerunError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            RerunError::Io(e) => write!(f, "inputs file i/o error: {}", e),
+            RerunError::Serialize(e) => write!(f, "could not write inputs: {}", e),
+            RerunError::Parse(e) => write!(f, "could not parse inputs: {}", e),
+        }
+    }
+}
+
+impl error::Error for RerunError {}
+
+impl From<io::Error> for RerunError {
+    fn from(e: io::Error) -> Self {
+        RerunError::Io(e)
+    }
+}
+
+impl From<ron::Error> for RerunError {
+    fn from(e: ron::Error) -> Self {
+        RerunError::Serialize(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for RerunError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        RerunError::Parse(e)
+    }
+}
+
+impl Inputs {
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, RerunError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), RerunError> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, ron)?;
+        Ok(())
+    }
+}
+
+/// A hash of the world between ticks: of its snapshot and the config. The
+/// generator's left out, as running again hands out the recorded numbers
+/// rather than drawing its own, and the snapshot's restored to an empty
+/// world first, numbering the entities afresh, so a world that's been saved
+/// and loaded hashes the same as one that hasn't.
+pub fn state_hash(world: &mut World) -> u64 {
+    let mut numbered = World::new();
+    numbered.insert_resource(Messages::<Telegram>::default());
+    Snapshot::capture(world)
+        .restore(&mut numbered)
+        .unwrap_or_else(|e| panic!("could not hash the world: {}", e));
+    let mut snapshot = Snapshot::capture(&mut numbered);
+    snapshot.rng = WorldRng::seed_from_u64(0);
+    let mut hash = fnv1a(FNV_OFFSET, ron::to_string(&snapshot).unwrap().as_bytes());
+    if let Some(settings) = world.get_resource::<Settings>() {
+        hash = fnv1a(hash, ron::to_string(&settings.0).unwrap().as_bytes());
+    }
+    hash
+}
+
+const FNV_OFFSET: u64 = 0xcbf2_9ce4_8422_2325;
+
+/// 64-bit FNV-1a, which unlike std's hashers is the same from one build to
+/// the next.
+fn fnv1a(hash: u64, bytes: &[u8]) -> u64 {
+    bytes.iter().fold(hash, |hash, &byte| {
+        (hash ^ u64::from(byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+enum Mode {
+    Recording {
+        path: PathBuf,
+        config: Config,
+        inputs: Vec<Input>,
+        /// How many of the generator's recorded numbers have been kept.
+        drawn: usize,
+    },
+    Rerunning {
+        pending: VecDeque<Input>,
+        hash: u64,
+        applied: usize,
+    },
+}
+
+/// Collects the inputs to a run being recorded, or makes them again in a run
+/// being run again.
+#[derive(Resource)]
+pub struct InputLog {
+    mode: Mode,
+    tick: u64,
+}
+
+impl InputLog {
+    pub fn recording(path: PathBuf, config: Config) -> Self {
+        InputLog {
+            mode: Mode::Recording {
+                path,
+                config,
+                inputs: vec![],
+                drawn: 0,
+            },
+            tick: 0,
+        }
+    }
+
+    pub fn rerunning(inputs: &Inputs) -> Self {
+        InputLog {
+            mode: Mode::Rerunning {
+                pending: inputs.inputs.iter().cloned().collect(),
+                hash: inputs.hash,
+                applied: 0,
+            },
+            tick: 0,
+        }
+    }
+
+    /// Keeps the input made before the next tick, if recording.
+    pub fn note(&mut self, input: impl FnOnce(u64) -> Input) {
+        if let Mode::Recording { inputs, .. } = &mut self.mode {
+            inputs.push(input(self.tick));
+        }
+    }
+
+    /// The next input to make again, if it's due before the next tick.
+    fn due(&mut self) -> Option<Input> {
+        let tick = self.tick;
+        if let Mode::Rerunning {
+            pending, applied, ..
+        } = &mut self.mode
+        {
+            let due = matches!(
+                pending.front(),
+                Some(input) if input.tick() == tick && !matches!(input, Input::Draws { .. })
+            );
+            if due {
+                *applied += 1;
+                return pending.pop_front();
+            }
+        }
+        None
+    }
+}
+
+/// Makes `input` again.
+fn apply(world: &mut World, input: Input) {
+    match input {
+        Input::Telegram {
+            sender,
+            receiver,
+            message,
+            delay,
+            ..
+        } => {
+            let find = |world: &mut World, name: &str| {
+                find(world, name).unwrap_or_else(|e| panic!("rerun diverged: {}", e))
+            };
+            let receiver = find(world, &receiver);
+            let sender = find(world, &sender);
+            let mut outbox = world
+                .get_mut::<Outbox>(sender)
+                .expect("rerun diverged: only agents can send telegrams");
+            outbox.dispatch(delay, receiver, message);
+        }
+        Input::Config { config, .. } => world.insert_resource(Settings(config)),
+        Input::Draws { .. } => unreachable!("random numbers are handed out, not applied"),
+    }
+}
+
+/// Makes again whatever was done to the run before the next tick.
+fn apply_due(world: &mut World) {
+    while let Some(input) = world.resource_mut::<InputLog>().due() {
+        apply(world, input);
+    }
+}
+
+/// Makes the inputs due before this tick, and moves on to it.
+pub fn next_tick(world: &mut World) {
+    apply_due(world);
+    world.resource_mut::<InputLog>().tick += 1;
+}
+
+/// Keeps the random numbers drawn this tick, or checks as many were drawn as
+/// were recorded.
+pub fn count_draws(mut log: ResMut<InputLog>, rng: Res<WorldRng>) {
+    let tick = log.tick;
+    match &mut log.mode {
+        Mode::Recording { inputs, drawn, .. } => {
+            let draws = &rng.recorded()[*drawn..];
+            if !draws.is_empty() {
+                inputs.push(Input::Draws {
+                    tick,
+                    draws: draws.to_vec(),
+                });
+                *drawn += draws.len();
+            }
+        }
+        Mode::Rerunning { pending, .. } => {
+            let recorded = match pending.front() {
+                Some(Input::Draws { tick: at, draws }) if *at == tick => draws.len(),
+                _ => 0,
+            };
+            if recorded > 0 {
+                pending.pop_front();
+            }
+            // everything left to hand out, less what's to come
+            let left: usize = pending
+                .iter()
+                .map(|input| match input {
+                    Input::Draws { draws, .. } => draws.len(),
+                    _ => 0,
+                })
+                .sum();
+            if rng.unplayed() != left {
+                panic!(
+                    "rerun diverged: tick {} drew {} random numbers, not {}",
+                    tick,
+                    (recorded + left) as i64 - rng.unplayed() as i64,
+                    recorded
+                );
+            }
+        }
+    }
+}
+
+/// Saves the inputs with the hash of the world, or checks the world's hash
+/// matches the recording's, once the run is over.
+pub fn finish(world: &mut World) {
+    if !world.contains_resource::<InputLog>() {
+        return;
+    }
+    // anything done after the last tick still counts towards the hash
+    apply_due(world);
+    let log = world.remove_resource::<InputLog>().unwrap();
+    let hash = state_hash(world);
+    match log.mode {
+        Mode::Recording {
+            path,
+            config,
+            inputs,
+            ..
+        } => {
+            let inputs = Inputs {
+                seed: world.get_resource::<Seed>().unwrap().0,
+                scenario: world.get_resource::<Scenario>().unwrap().clone(),
+                config,
+                ticks: log.tick,
+                inputs,
+                hash,
+            };
+            match inputs.save(&path) {
+                Ok(()) => info!(
+                    "recorded the inputs to {} ticks to {}, ending with hash {:016x}",
+                    log.tick,
+                    path.display(),
+                    hash
+                ),
+                Err(e) => error!("{}: {}", path.display(), e),
+            }
+        }
+        Mode::Rerunning {
+            pending,
+            hash: recorded,
+            applied,
+        } => {
+            if let Some(next) = pending.front() {
+                panic!("rerun diverged: the run ended before {}", next);
+            }
+            if hash != recorded {
+                panic!(
+                    "rerun diverged: the world ended with hash {:016x}, not {:016x}",
+                    hash, recorded
+                );
+            }
+            info!(
+                "rerun matched: {} ticks, {} inputs, hash {:016x}",
+                log.tick, applied, hash
+            );
+        }
+    }
+}
+
+/// Records the run's inputs with `--record-inputs=FILE` or runs them again
+/// with `--rerun=FILE`. Add it after [`crate::rng::RngPlugin`], and call
+/// [`finish`] once the run is over.
+pub enum RerunPlugin {
+    Record(PathBuf),
+    Rerun(Inputs),
+}
+
+impl RerunPlugin {
+    /// Reads `--record-inputs=FILE` or `--rerun=FILE`, loading the inputs.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        if let Some(path) = &cli.record_inputs {
+            return Some(RerunPlugin::Record(path.clone()));
+        }
+        cli.rerun.as_ref().map(|path| {
+            let inputs = Inputs::load(path)
+                .unwrap_or_else(|e| panic!("could not load {}: {}", path.display(), e));
+            RerunPlugin::Rerun(inputs)
+        })
+    }
+}
+
+impl Plugin for RerunPlugin {
+    fn build(&self, app: &mut App) {
+        match self {
+            RerunPlugin::Record(path) => {
+                app.init_resource::<Settings>();
+                let world = app.world_mut();
+                let config = world.resource::<Settings>().0.clone();
+                world.resource_mut::<WorldRng>().record();
+                world.insert_resource(InputLog::recording(path.clone(), config));
+            }
+            RerunPlugin::Rerun(inputs) => {
+                let mut rng = WorldRng::seed_from_u64(inputs.seed);
+                rng.play(
+                    inputs
+                        .inputs
+                        .iter()
+                        .filter_map(|input| match input {
+                            Input::Draws { draws, .. } => Some(draws),
+                            _ => None,
+                        })
+                        .flatten()
+                        .copied()
+                        .collect(),
+                );
+                let world = app.world_mut();
+                world.insert_resource(rng);
+                world.insert_resource(Seed(inputs.seed));
+                world.insert_resource(inputs.scenario.clone());
+                world.insert_resource(Settings(inputs.config.clone()));
+                if let Some(mut pace) = world.get_resource_mut::<Pace>() {
+                    pace.ticks = Some(inputs.ticks);
+                }
+                world.insert_resource(InputLog::rerunning(inputs));
+            }
+        }
+        app.add_systems(FixedFirst, next_tick)
+            .add_systems(FixedLast, count_draws);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::population::Population;
+    use crate::remote::{carry_out, Request};
+    use crate::testing::{build_test_app, tick};
+    use serde_json::json;
+
+    /// A run with a telegram sent in and the config changed along the way,
+    /// recorded to `path`.
+    fn record(path: &Path) {
+        let mut app = build_test_app(
+            Scenario::populated(&Population {
+                miners: 1,
+                partners: 1,
+            }),
+            3,
+        );
+        app.add_plugins(RerunPlugin::Record(path.to_path_buf()));
+        tick(&mut app, 10);
+        let send = Request::Send {
+            to: "Elsa".to_string(),
+            message: Message::HiHoneyImHome,
+            from: Some("Miner Bob".to_string()),
+            delay: 0,
+        };
+        carry_out(app.world_mut(), send).unwrap();
+        tick(&mut app, 10);
+        let set = Request::Set {
+            field: "thirst_level".to_string(),
+            value: json!(2),
+        };
+        carry_out(app.world_mut(), set).unwrap();
+        tick(&mut app, 10);
+        finish(app.world_mut());
+    }
+
+    fn rerun(inputs: Inputs) {
+        let mut app = build_test_app(Scenario::default(), 0);
+        app.add_plugins(RerunPlugin::Rerun(inputs.clone()));
+        tick(&mut app, inputs.ticks as usize);
+        finish(app.world_mut());
+    }
+
+    fn recorded(name: &str) -> Inputs {
+        let path =
+            std::env::temp_dir().join(format!("westworld-{}-{}.ron", name, std::process::id()));
+        record(&path);
+        let inputs = Inputs::load(&path).unwrap();
+        fs::remove_file(&path).unwrap();
+        inputs
+    }
+
+    #[test]
+    fn a_recorded_run_runs_again_the_same() {
+        let inputs = recorded("rerun");
+        assert_eq!(inputs.ticks, 30);
+        let made: Vec<_> = inputs
+            .inputs
+            .iter()
+            .filter(|input| !matches!(input, Input::Draws { .. }))
+            .map(Input::tick)
+            .collect();
+        assert_eq!(made, [10, 20]);
+        rerun(inputs);
+    }
+
+    #[test]
+    #[should_panic(expected = "rerun diverged")]
+    fn a_run_leaving_a_different_world_diverges() {
+        let mut inputs = recorded("diverged");
+        inputs
+            .inputs
+            .retain(|input| !matches!(input, Input::Config { .. }));
+        rerun(inputs);
+    }
+}
@@ -0,0 +1,93 @@
+//! The Rhai backend for the scripted states, built with `--features rhai`.
+//! Rhai's all Rust, so it goes wherever the rest of the town does, the web
+//! build included.
+//!
+//! A Rhai script is a set of functions, its hooks, each handed a map of the
+//! miner with the same fields as the Lua backend's table. `update` answers
+//! `()` to carry on, `"pop"`, `"quit"`, or `#{ "switch": "QuenchThirst" }` or
+//! `#{ push: "TravelTo(Saloon)" }`, `switch` needing its quotes as it's one of
+//! Rhai's keywords.
+
+use crate::scripting::{Agent, ScriptError, ScriptedState};
+use fsm::StateTransition;
+use rhai::{Dynamic, Engine, Map, Scope, AST};
+
+/// How much a hook may do before it's taken to be stuck and stopped.
+const MAX_OPERATIONS: u64 = 100_000;
+
+impl From<Box<rhai::EvalAltResult>> for ScriptError {
+    fn from(e: Box<rhai::EvalAltResult>) -> Self {
+        ScriptError::Script(e)
+    }
+}
+
+impl From<rhai::ParseError> for ScriptError {
+    fn from(e: rhai::ParseError) -> Self {
+        ScriptError::Script(Box::new(e))
+    }
+}
+
+/// A Rhai script, compiled.
+pub struct RhaiScript {
+    engine: Engine,
+    ast: AST,
+}
+
+impl RhaiScript {
+    pub fn new(source: &str) -> Result<Self, ScriptError> {
+        let mut engine = Engine::new();
+        engine.set_max_operations(MAX_OPERATIONS);
+        let ast = engine.compile(source)?;
+        Ok(RhaiScript { engine, ast })
+    }
+}
+
+impl ScriptedState for RhaiScript {
+    fn call(&self, hook: &str, agent: &Agent) -> Result<StateTransition<String>, ScriptError> {
+        if !self.ast.iter_functions().any(|f| f.name == hook) {
+            return Ok(StateTransition::None);
+        }
+
+        let mut miner = Map::new();
+        miner.insert("name".into(), agent.name.clone().into());
+        miner.insert("location".into(), agent.location.clone().into());
+        miner.insert("gold".into(), (agent.gold as i64).into());
+        miner.insert("wealth".into(), (agent.wealth as i64).into());
+        miner.insert("thirst".into(), (agent.thirst as i64).into());
+        miner.insert("fatigue".into(), (agent.fatigue as i64).into());
+        miner.insert("hunger".into(), (agent.hunger as i64).into());
+        miner.insert("day".into(), (agent.day as i64).into());
+        miner.insert("hour".into(), (agent.hour as i64).into());
+        let asked: Dynamic = self
+            .engine
+            .call_fn(&mut Scope::new(), &self.ast, hook, (miner,))?;
+        transition(asked)
+    }
+}
+
+/// The transition a script's `update` asked for.
+fn transition(asked: Dynamic) -> Result<StateTransition<String>, ScriptError> {
+    if asked.is_unit() {
+        return Ok(StateTransition::None);
+    }
+    if let Some(asked) = asked.read_lock::<rhai::ImmutableString>() {
+        return match asked.as_str() {
+            "pop" => Ok(StateTransition::Pop),
+            "quit" => Ok(StateTransition::Quit),
+            other => Err(ScriptError::BadAnswer(format!("{:?}", other))),
+        };
+    }
+    if let Some(asked) = asked.read_lock::<Map>() {
+        let named = |key: &str| {
+            asked
+                .get(key)
+                .and_then(|name| name.clone().into_string().ok())
+        };
+        if let Some(name) = named("switch") {
+            return Ok(StateTransition::Switch(name));
+        } else if let Some(name) = named("push") {
+            return Ok(StateTransition::Push(name));
+        }
+    }
+    Err(ScriptError::BadAnswer(asked.to_string()))
+}
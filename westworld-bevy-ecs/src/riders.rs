@@ -0,0 +1,60 @@
+//! Agents who ride about town on horseback. Rather than stepping straight from
+//! one [`crate::Location`] to the next, a rider has a [`Mount`] whose
+//! steering behaviour its states set, and which [`ride`] moves along each
+//! update.
+
+use crate::{Label, Phase};
+use bevy_app::{App, FixedUpdate, Plugin};
+use bevy_ecs::prelude::*;
+use game_ai::steering::Vehicle;
+use game_ai::Vector2D;
+use std::ops::{Deref, DerefMut};
+
+pub static SECONDS_PER_UPDATE: f64 = 0.5; // how far the riders move each update
+pub static HORSE_MAX_FORCE: f64 = 4.0; // how hard a horse can turn or brake
+pub static ARRIVAL_DISTANCE: f64 = 1.0; // how close a rider has to be to have arrived
+
+/// Label of the system moving the riders.
+pub static RIDE: Label = Label("ride");
+
+/// The [`Vehicle`] a rider gets about on.
+#[derive(Component)]
+pub struct Mount(pub Vehicle);
+
+impl Deref for Mount {
+    type Target = Vehicle;
+
+    fn deref(&self) -> &Vehicle {
+        &self.0
+    }
+}
+
+impl DerefMut for Mount {
+    fn deref_mut(&mut self) -> &mut Vehicle {
+        &mut self.0
+    }
+}
+
+/// A rider's horse, standing at `position`.
+pub fn horse(position: Vector2D, max_speed: f64) -> Vehicle {
+    Vehicle::new(position, max_speed, HORSE_MAX_FORCE)
+}
+
+pub fn arrived(vehicle: &Vehicle, target: Vector2D) -> bool {
+    vehicle.position.distance(target) < ARRIVAL_DISTANCE
+}
+
+pub fn ride(mut riders: Query<&mut Mount>) {
+    for mut vehicle in riders.iter_mut() {
+        vehicle.update(SECONDS_PER_UPDATE);
+    }
+}
+
+/// Moves the riders once they've all decided where they're heading.
+pub struct RidersPlugin;
+
+impl Plugin for RidersPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_systems(FixedUpdate, ride.in_set(RIDE).in_set(Phase::Act));
+    }
+}
@@ -0,0 +1,217 @@
+use crate::cli::Cli;
+use bevy_app::{App, Plugin, Startup};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use rand::{RngCore, SeedableRng};
+use rand_chacha::ChaCha8Rng;
+use serde::{Deserialize, Serialize};
+use std::collections::VecDeque;
+
+#[derive(Default)]
+enum Draws {
+    #[default]
+    Live,
+    Recording(Vec<u64>),
+    Playing(VecDeque<u64>),
+}
+
+/// Where every random decision in the simulation comes from, so that a run
+/// can be replayed exactly from its [`Seed`].
+///
+/// It can also keep every number it hands out, or hand out numbers kept from
+/// an earlier run, for [`crate::replay`]. Only the generator itself is kept
+/// in a snapshot.
+#[derive(Resource, Serialize, Deserialize)]
+pub struct WorldRng {
+    rng: ChaCha8Rng,
+    #[serde(skip)]
+    draws: Draws,
+}
+
+impl WorldRng {
+    /// A copy of the generator as it stands, neither recording nor playing
+    /// back.
+    pub fn live_copy(&self) -> Self {
+        WorldRng {
+            rng: self.rng.clone(),
+            draws: Draws::Live,
+        }
+    }
+
+    /// Starts keeping every number drawn.
+    pub fn record(&mut self) {
+        self.draws = Draws::Recording(vec![]);
+    }
+
+    /// The numbers drawn since recording started.
+    pub fn recorded(&self) -> &[u64] {
+        match &self.draws {
+            Draws::Recording(draws) => draws,
+            _ => &[],
+        }
+    }
+
+    /// Hands out `draws` in order instead of generating numbers.
+    pub fn play(&mut self, draws: Vec<u64>) {
+        self.draws = Draws::Playing(draws.into());
+    }
+
+    /// How many of the numbers being played back haven't been drawn yet.
+    pub fn unplayed(&self) -> usize {
+        match &self.draws {
+            Draws::Playing(draws) => draws.len(),
+            _ => 0,
+        }
+    }
+
+    /// Draws the seed for a round of agents each taking a turn with
+    /// numbers of their own, which they can do all at once as none of them
+    /// need wait for the others' draws.
+    pub fn streams(&mut self) -> Streams {
+        Streams(self.next_u64())
+    }
+
+    fn draw(&mut self, generate: impl FnOnce(&mut ChaCha8Rng) -> u64) -> u64 {
+        match &mut self.draws {
+            Draws::Live => generate(&mut self.rng),
+            Draws::Recording(draws) => {
+                let n = generate(&mut self.rng);
+                draws.push(n);
+                n
+            }
+            Draws::Playing(draws) => draws
+                .pop_front()
+                .expect("replay diverged: the run drew more random numbers than were recorded"),
+        }
+    }
+}
+
+impl SeedableRng for WorldRng {
+    type Seed = <ChaCha8Rng as SeedableRng>::Seed;
+
+    fn from_seed(seed: Self::Seed) -> Self {
+        WorldRng {
+            rng: ChaCha8Rng::from_seed(seed),
+            draws: Draws::Live,
+        }
+    }
+}
+
+impl RngCore for WorldRng {
+    fn next_u32(&mut self) -> u32 {
+        self.draw(|rng| rng.next_u32().into()) as u32
+    }
+
+    fn next_u64(&mut self) -> u64 {
+        self.draw(|rng| rng.next_u64())
+    }
+
+    fn fill_bytes(&mut self, dest: &mut [u8]) {
+        for chunk in dest.chunks_mut(8) {
+            let bytes = self.next_u64().to_le_bytes();
+            chunk.copy_from_slice(&bytes[..chunk.len()]);
+        }
+    }
+
+    fn try_fill_bytes(&mut self, dest: &mut [u8]) -> Result<(), rand::Error> {
+        self.fill_bytes(dest);
+        Ok(())
+    }
+}
+
+/// Independent streams of random numbers drawn from one seed, one for each
+/// turn in a round, so that it makes no difference what order the turns
+/// are taken in.
+#[derive(Copy, Clone, Debug)]
+pub struct Streams(u64);
+
+impl Streams {
+    /// The numbers for the `turn`th turn of the round.
+    pub fn stream(&self, turn: u64) -> WorldRng {
+        let mut rng = ChaCha8Rng::seed_from_u64(self.0);
+        rng.set_stream(turn);
+        WorldRng {
+            rng,
+            draws: Draws::Live,
+        }
+    }
+}
+
+/// Seeds the [`WorldRng`]. Insert your own before adding [`RngPlugin`];
+/// otherwise one is picked at random.
+#[derive(Resource, Copy, Clone, Debug, PartialEq)]
+pub struct Seed(pub u64);
+
+impl Seed {
+    /// Reads `--seed=N`, picking a random seed without one.
+    pub fn from_cli(cli: &Cli) -> Self {
+        cli.seed.map(Seed).unwrap_or_default()
+    }
+}
+
+impl Default for Seed {
+    fn default() -> Self {
+        Seed(rand::random())
+    }
+}
+
+pub fn log_seed(seed: Res<Seed>) {
+    info!("random seed {} (replay with --seed={})", seed.0, seed.0);
+}
+
+pub struct RngPlugin;
+
+impl Plugin for RngPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Seed>();
+        let seed = *app.world().get_resource::<Seed>().unwrap();
+        app.insert_resource(WorldRng::seed_from_u64(seed.0));
+        app.add_systems(Startup, log_seed);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use clap::Parser;
+    use rand::Rng;
+
+    #[test]
+    fn seed_from_cli() {
+        let cli = Cli::parse_from(["westworld", "--miners=2", "--seed=42"]);
+        assert_eq!(Seed::from_cli(&cli), Seed(42));
+    }
+
+    #[test]
+    fn same_seed_same_numbers() {
+        let mut a = WorldRng::seed_from_u64(7);
+        let mut b = WorldRng::seed_from_u64(7);
+        let xs: Vec<u32> = (0..8).map(|_| a.gen()).collect();
+        let ys: Vec<u32> = (0..8).map(|_| b.gen()).collect();
+        assert_eq!(xs, ys);
+    }
+
+    #[test]
+    fn each_turn_has_numbers_of_its_own() {
+        let streams = WorldRng::seed_from_u64(7).streams();
+        let draw = |turn| -> Vec<u32> {
+            let mut rng = streams.stream(turn);
+            (0..8).map(|_| rng.gen()).collect()
+        };
+        assert_eq!(draw(1), draw(1));
+        assert_ne!(draw(0), draw(1));
+    }
+
+    #[test]
+    fn plays_back_recorded_numbers() {
+        let mut a = WorldRng::seed_from_u64(7);
+        a.record();
+        let xs: Vec<f32> = (0..8).map(|_| a.gen()).collect();
+
+        let mut b = WorldRng::seed_from_u64(8);
+        b.play(a.recorded().to_vec());
+        let ys: Vec<f32> = (0..8).map(|_| b.gen()).collect();
+        assert_eq!(xs, ys);
+        assert_eq!(b.unplayed(), 0);
+    }
+}
@@ -0,0 +1,163 @@
+//! The roads between places in town, for miners to find their way along. The
+//! town is a graph with a node for each place and an edge for each road,
+//! costing the updates it takes to walk it, so a miner heading somewhere with
+//! no road straight there goes by way of the places in between. Roads close
+//! now and then, and whoever's on his way picks another route from wherever
+//! he's got to.
+
+use crate::Location;
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use game_ai::graph::search::{FindExtraInfo, GraphSearchDijkstra};
+use game_ai::graph::{GraphEdge, SparseGraph, TimeSlicedSearch};
+use game_ai::Vector2D;
+
+/// How many updates it takes to walk each road in town, either way.
+pub static ROADS: &[(Location, Location, usize)] = &[
+    (Location::Shack, Location::Goldmine, 2),
+    (Location::Shack, Location::Bank, 1),
+    (Location::Shack, Location::Saloon, 1),
+    (Location::Goldmine, Location::Bank, 1),
+    (Location::Goldmine, Location::Saloon, 1),
+    (Location::Goldmine, Location::Hills, 1),
+    (Location::Goldmine, Location::Hideout, 2),
+    (Location::Bank, Location::Saloon, 1),
+    (Location::Bank, Location::Jail, 1),
+    (Location::Shack, Location::Church, 1),
+    (Location::Church, Location::Saloon, 1),
+];
+
+/// Every road in town, and whether it's open.
+#[derive(Resource)]
+pub struct Roads(SparseGraph<Location>);
+
+impl Roads {
+    /// The town with all of its [`ROADS`] open.
+    pub fn town() -> Self {
+        let mut graph = SparseGraph::new(false);
+        for &location in &[
+            Location::Goldmine,
+            Location::Bank,
+            Location::Shack,
+            Location::Saloon,
+            Location::Hideout,
+            Location::Jail,
+            Location::Hills,
+            Location::Church,
+        ] {
+            let node = graph.add_node(Vector2D::new(0.0, 0.0));
+            graph.node_mut(node).unwrap().extra_info = Some(location);
+        }
+
+        let mut roads = Roads(graph);
+        for &(from, to, _) in ROADS {
+            roads.open(from, to);
+        }
+        roads
+    }
+
+    fn node(&self, location: Location) -> Option<usize> {
+        self.0.nodes_with(&location).next().map(|node| node.index)
+    }
+
+    /// Opens the road between two places, if there is one. Returns whether it
+    /// was closed.
+    pub fn open(&mut self, from: Location, to: Location) -> bool {
+        let time = match ROADS
+            .iter()
+            .find(|&&(a, b, _)| (a, b) == (from, to) || (b, a) == (from, to))
+        {
+            Some(&(_, _, time)) => time,
+            None => return false,
+        };
+        match (self.node(from), self.node(to)) {
+            (Some(from), Some(to)) if !self.0.is_edge_present(from, to) => {
+                self.0.add_edge(GraphEdge::new(from, to, time as f64));
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// Closes the road between two places. Returns whether it was open.
+    pub fn close(&mut self, from: Location, to: Location) -> bool {
+        match (self.node(from), self.node(to)) {
+            (Some(from), Some(to)) if self.0.is_edge_present(from, to) => {
+                self.0.remove_edge(from, to);
+                true
+            }
+            _ => false,
+        }
+    }
+
+    /// The places passed on the quickest way from `from` to `to` along the
+    /// open roads, ending with `to`, or `None` if there's no way through.
+    pub fn route(&self, from: Location, to: Location) -> Option<Vec<Location>> {
+        let source = self.node(from)?;
+        let mut search = GraphSearchDijkstra::new(&self.0, source, FindExtraInfo(to));
+        search.search(&self.0);
+        let route: Vec<Location> = search
+            .path_to_target()
+            .into_iter()
+            .skip(1)
+            .filter_map(|node| self.0.node(node)?.extra_info)
+            .collect();
+        match route.last() {
+            Some(&last) if last == to => Some(route),
+            _ if from == to => Some(route),
+            _ => None,
+        }
+    }
+
+    /// How many updates it takes to walk the open road between two places.
+    pub fn walking_time(&self, from: Location, to: Location) -> Option<usize> {
+        let edge = self.0.edge(self.node(from)?, self.node(to)?)?;
+        Some(edge.cost as usize)
+    }
+}
+
+pub struct RoadsPlugin;
+
+impl Plugin for RoadsPlugin {
+    fn build(&self, app: &mut App) {
+        app.insert_resource(Roads::town());
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn the_way_round_a_closed_road_goes_by_the_places_between() {
+        let mut roads = Roads::town();
+        assert_eq!(
+            roads.route(Location::Shack, Location::Goldmine),
+            Some(vec![Location::Goldmine])
+        );
+        assert_eq!(
+            roads.route(Location::Shack, Location::Hills),
+            Some(vec![Location::Goldmine, Location::Hills])
+        );
+
+        assert!(roads.close(Location::Goldmine, Location::Shack));
+        assert_eq!(
+            roads.walking_time(Location::Shack, Location::Goldmine),
+            None
+        );
+        assert_eq!(
+            roads.route(Location::Shack, Location::Goldmine),
+            Some(vec![Location::Bank, Location::Goldmine])
+        );
+
+        assert!(roads.close(Location::Goldmine, Location::Hills));
+        assert_eq!(roads.route(Location::Shack, Location::Hills), None);
+
+        assert!(roads.open(Location::Shack, Location::Goldmine));
+        assert!(!roads.open(Location::Shack, Location::Goldmine));
+        assert_eq!(
+            roads.walking_time(Location::Goldmine, Location::Shack),
+            Some(2)
+        );
+    }
+}
@@ -0,0 +1,228 @@
+//! Scenario files declaring who's in town when a run starts: every agent's
+//! name, where they start out and what they're doing, and how much gold each
+//! miner has. Load one with `--scenario=FILE`; without one the town is the
+//! usual one, with as many miners and partners as `--miners=N` and
+//! `--partners=N` ask for. A few are bundled in `scenarios/`.
+//!
+//! A scenario file looks like this. Anyone left out of it stays away, and
+//! agents start where and how they usually would unless it says otherwise:
+//!
+//! ```text
+//! (
+//!     miners: [
+//!         (name: "Miner Bob", location: Bank, wealth: 20),
+//!         (name: "Miner Jake", state: EnterMineAndDigForNugget, gold: 2),
+//!     ],
+//!     partners: [(name: "Elsa")],
+//!     sheriffs: [(name: "Sheriff Wyatt", state: Patrol)],
+//! )
+//! ```
+//!
+//! Partners marry the miners in the order they're listed. The miners and
+//! partners can come from a bevy scene instead, see [`crate::scene`].
+
+use crate::bandit::BanditState;
+use crate::barfly::BarFlyState;
+use crate::cli::Cli;
+use crate::miner::MinerState;
+use crate::partner::PartnerState;
+use crate::population::Population;
+use crate::sheriff::SheriffState;
+use crate::Location;
+use bevy_ecs::prelude::*;
+use ron::extensions::Extensions;
+use serde::{Deserialize, Serialize};
+use std::path::Path;
+use std::{error, fmt, fs, io};
+
+/// A miner in a scenario.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct MinerSpec {
+    pub name: String,
+    #[serde(default)]
+    pub location: Option<Location>,
+    #[serde(default)]
+    pub state: Option<MinerState>,
+    /// Nuggets in his pockets.
+    #[serde(default)]
+    pub gold: i32,
+    /// Gold in the bank.
+    #[serde(default)]
+    pub wealth: i32,
+}
+
+impl MinerSpec {
+    pub fn named(name: String) -> Self {
+        MinerSpec {
+            name,
+            location: None,
+            state: None,
+            gold: 0,
+            wealth: 0,
+        }
+    }
+}
+
+/// Any other agent in a scenario, starting in a state of type `S`.
+#[derive(Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct AgentSpec<S> {
+    pub name: String,
+    pub location: Option<Location>,
+    pub state: Option<S>,
+}
+
+impl<S> AgentSpec<S> {
+    pub fn named(name: String) -> Self {
+        AgentSpec {
+            name,
+            location: None,
+            state: None,
+        }
+    }
+}
+
+/// Everyone in town when the run starts, in the order they take their turns.
+#[derive(Resource, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub struct Scenario {
+    #[serde(default)]
+    pub miners: Vec<MinerSpec>,
+    #[serde(default)]
+    pub partners: Vec<AgentSpec<PartnerState>>,
+    #[serde(default)]
+    pub barflies: Vec<AgentSpec<BarFlyState>>,
+    #[serde(default)]
+    pub bandits: Vec<AgentSpec<BanditState>>,
+    #[serde(default)]
+    pub sheriffs: Vec<AgentSpec<SheriffState>>,
+}
+
+/// Errors raised while reading a scenario file.
+#[derive(Debug)]
+pub enum ScenarioError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+}
+
+impl fmt::Display for ScenarioError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScenarioError::Io(e) => write!(f, "scenario file i/o error: {}", e),
+            ScenarioError::Parse(e) => write!(f, "could not parse scenario: {}", e),
+        }
+    }
+}
+
+impl error::Error for ScenarioError {}
+
+impl From<io::Error> for ScenarioError {
+    fn from(e: io::Error) -> Self {
+        ScenarioError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for ScenarioError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        ScenarioError::Parse(e)
+    }
+}
+
+impl Scenario {
+    /// The usual town: `population` miners and partners, with Slim propping
+    /// up the bar, Black Bart at his hideout and Sheriff Wyatt on patrol.
+    pub fn populated(population: &Population) -> Self {
+        Scenario {
+            miners: (0..population.miners)
+                .map(|i| MinerSpec::named(Population::miner_name(i)))
+                .collect(),
+            partners: (0..population.partners)
+                .map(|i| AgentSpec::named(Population::partner_name(i)))
+                .collect(),
+            barflies: vec![AgentSpec::named("Barfly Slim".to_string())],
+            bandits: vec![AgentSpec::named("Black Bart".to_string())],
+            sheriffs: vec![AgentSpec::named("Sheriff Wyatt".to_string())],
+        }
+    }
+
+    /// Parses a scenario, which can leave `Some` off locations and states.
+    pub fn from_ron_str(s: &str) -> Result<Self, ScenarioError> {
+        Ok(ron::Options::default()
+            .with_default_extension(Extensions::IMPLICIT_SOME)
+            .from_str(s)?)
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, ScenarioError> {
+        Self::from_ron_str(&fs::read_to_string(path)?)
+    }
+
+    /// Loads `--scenario=FILE`, if it's given.
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.scenario.as_ref().map(|path| {
+            Scenario::load(path)
+                .unwrap_or_else(|e| panic!("could not load {}: {}", path.display(), e))
+        })
+    }
+}
+
+/// The usual town, with one miner and his wife.
+impl Default for Scenario {
+    fn default() -> Self {
+        Scenario::populated(&Population::default())
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::miner::{init_miners, Miner};
+    use crate::path_planner::NavGraph;
+    use crate::Name;
+    use fsm::StateStack;
+
+    static BUNDLED: &[&str] = &[
+        include_str!("../scenarios/bachelors.ron"),
+        include_str!("../scenarios/bank_job.ron"),
+    ];
+
+    #[test]
+    fn bundled_scenarios_parse() {
+        for scenario in BUNDLED {
+            let scenario = Scenario::from_ron_str(scenario).unwrap();
+            assert!(!scenario.miners.is_empty());
+        }
+    }
+
+    #[test]
+    fn miners_start_as_the_scenario_says() {
+        let scenario = Scenario::from_ron_str(
+            r#"(miners: [
+                (name: "Miner Bob", location: Bank, state: VisitBankAndDepositGold, gold: 2, wealth: 7),
+                (name: "Miner Jake"),
+            ])"#,
+        )
+        .unwrap();
+        let mut world = World::new();
+        world.insert_resource(scenario);
+        world.insert_resource(NavGraph::town());
+        let mut schedule = Schedule::default();
+        schedule.add_systems(init_miners);
+        schedule.run(&mut world);
+
+        let mut miners = world.query::<(&Name, &Location, &Miner, &StateStack<MinerState>)>();
+        let miners: Vec<_> = miners.iter(&world).collect();
+        assert_eq!(miners.len(), 2);
+        let (_, location, miner, states) = miners
+            .iter()
+            .find(|(name, ..)| name.0 == "Miner Bob")
+            .unwrap();
+        assert_eq!(**location, Location::Bank);
+        assert_eq!((miner.gold(), miner.wealth()), (2, 7));
+        assert_eq!(states.last(), Some(&MinerState::VisitBankAndDepositGold));
+        let (_, location, miner, states) = miners
+            .iter()
+            .find(|(name, ..)| name.0 == "Miner Jake")
+            .unwrap();
+        assert_eq!(**location, Location::Shack);
+        assert_eq!(miner.wealth(), 0);
+        assert_eq!(states.last(), Some(&MinerState::GoHomeAndSleepTilRested));
+    }
+}
@@ -0,0 +1,275 @@
+//! Spawning the miners and their wives from a bevy scene, `--scene=FILE`,
+//! rather than from the scenario. The scene holds each one's reflected
+//! components: their [`Name`], [`Location`], [`Miner`] or [`Partner`], and
+//! their states as a [`StackView`], bottom first. Wives and husbands can be
+//! given by entity, or left out to marry in the usual way. Everyone else in
+//! town still comes from the scenario. `scenarios/bob_and_elsa.scn.ron` is an
+//! example.
+//!
+//! What a scene can't hold, their outboxes and path planners, is made up for
+//! them once they're spawned. Nor can it hold the messages they'd have sent
+//! on starting their states, so it's best to start them in states that don't
+//! wait on one, which rules out cooking stew.
+
+use crate::cli::Cli;
+use crate::messaging::Outbox;
+use crate::miner::{Miner, MinerState, INIT_MINERS};
+use crate::partner::{Partner, PartnerState};
+use crate::path_planner::{NavGraph, PathPlanner};
+use crate::reflection::StackView;
+use crate::scenario::Scenario;
+use crate::Location;
+use bevy_app::{App, Plugin, Startup};
+use bevy_ecs::entity::EntityHashMap;
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use bevy_reflect::{Reflect, TypePath};
+use bevy_scene::serde::SceneDeserializer;
+use fsm::StateStack;
+use serde::de::DeserializeSeed;
+use std::path::{Path, PathBuf};
+use std::{error, fmt, fs, io};
+
+/// Errors raised while reading a scene file.
+#[derive(Debug)]
+pub enum SceneError {
+    Io(io::Error),
+    Parse(ron::error::SpannedError),
+    Spawn(bevy_scene::SceneSpawnError),
+}
+
+impl fmt::Display for SceneError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SceneError::Io(e) => write!(f, "scene file i/o error: {}", e),
+            SceneError::Parse(e) => write!(f, "could not parse scene: {}", e),
+            SceneError::Spawn(e) => write!(f, "could not spawn scene: {}", e),
+        }
+    }
+}
+
+impl error::Error for SceneError {}
+
+impl From<io::Error> for SceneError {
+    fn from(e: io::Error) -> Self {
+        SceneError::Io(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for SceneError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        SceneError::Parse(e)
+    }
+}
+
+impl From<bevy_scene::SceneSpawnError> for SceneError {
+    fn from(e: bevy_scene::SceneSpawnError) -> Self {
+        SceneError::Spawn(e)
+    }
+}
+
+/// Spawns the scene in `s` into `world`, whose [`AppTypeRegistry`] has to
+/// know every component in it, and makes up what the agents in it are
+/// missing. Returns the entities spawned.
+pub fn spawn_from_ron_str(world: &mut World, s: &str) -> Result<Vec<Entity>, SceneError> {
+    let scene = {
+        let registry = world.resource::<AppTypeRegistry>().read();
+        let mut deserializer = ron::Deserializer::from_str(s)?;
+        SceneDeserializer {
+            type_registry: &registry,
+        }
+        .deserialize(&mut deserializer)
+        .map_err(|e| deserializer.span_error(e))?
+    };
+    let mut ids = EntityHashMap::default();
+    scene.write_to_world(world, &mut ids)?;
+
+    let spawned: Vec<Entity> = scene
+        .entities
+        .iter()
+        .map(|entity| ids[&entity.entity])
+        .collect();
+    for &entity in &spawned {
+        fill_in(world, entity);
+    }
+    Ok(spawned)
+}
+
+pub fn spawn<P: AsRef<Path>>(world: &mut World, path: P) -> Result<Vec<Entity>, SceneError> {
+    spawn_from_ron_str(world, &fs::read_to_string(path)?)
+}
+
+/// Gives a miner or a wife spawned from a scene what `init_miners` and
+/// `init_partners` would have.
+fn fill_in(world: &mut World, entity: Entity) {
+    let location = match world.get::<Location>(entity) {
+        Some(location) => *location,
+        None => {
+            world.entity_mut(entity).insert(Location::Shack);
+            Location::Shack
+        }
+    };
+    if world.get::<Miner>(entity).is_some() {
+        let position = world
+            .resource::<NavGraph>()
+            .position_of(location)
+            .unwrap_or_default();
+        world
+            .entity_mut(entity)
+            .insert(Outbox::new(entity))
+            .insert(PathPlanner::new(position));
+        insert_states(world, entity, MinerState::GoHomeAndSleepTilRested);
+    } else if world.get::<Partner>(entity).is_some() {
+        world.entity_mut(entity).insert(Outbox::new(entity));
+        insert_states(world, entity, PartnerState::DoHouseWork);
+    }
+}
+
+/// The states in the scene's [`StackView`], if it has any.
+fn insert_states<S: Clone + Send + Sync + Reflect + TypePath>(
+    world: &mut World,
+    entity: Entity,
+    initial_state: S,
+) {
+    let mut stack = StateStack::new();
+    match world.get::<StackView<S>>(entity) {
+        Some(view) if !view.0.is_empty() => {
+            for state in &view.0 {
+                stack.push(state.clone());
+            }
+        }
+        _ => stack.push(initial_state),
+    }
+    world.entity_mut(entity).insert(stack);
+}
+
+/// The scene file to spawn at startup.
+#[derive(Resource)]
+pub struct SceneFile(pub PathBuf);
+
+pub fn spawn_scene(world: &mut World) {
+    if let Some(SceneFile(path)) = world.remove_resource::<SceneFile>() {
+        match spawn(world, &path) {
+            Ok(spawned) => info!("spawned {} agents from {}", spawned.len(), path.display()),
+            Err(e) => panic!("could not load {}: {}", path.display(), e),
+        }
+    }
+}
+
+pub struct ScenePlugin {
+    pub path: PathBuf,
+}
+
+impl ScenePlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        let path = cli.scene.clone()?;
+        Some(ScenePlugin { path })
+    }
+}
+
+impl Plugin for ScenePlugin {
+    fn build(&self, app: &mut App) {
+        // the scene brings its own miners and wives
+        let mut scenario = app
+            .world_mut()
+            .get_resource_or_insert_with(Scenario::default);
+        scenario.miners.clear();
+        scenario.partners.clear();
+        app.insert_resource(SceneFile(self.path.clone()));
+        app.add_systems(Startup, spawn_scene.in_set(INIT_MINERS));
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::partner::marry;
+    use crate::reflection::ReflectionPlugin;
+    use crate::Name;
+
+    static BOB_AND_ELSA: &str = include_str!("../scenarios/bob_and_elsa.scn.ron");
+
+    fn world() -> World {
+        let mut app = App::new();
+        app.add_plugins(ReflectionPlugin);
+        let mut world = std::mem::take(app.world_mut());
+        world.insert_resource(NavGraph::town());
+        world
+    }
+
+    #[test]
+    fn bundled_scene_spawns_bob_and_elsa() {
+        let mut world = world();
+        let spawned = spawn_from_ron_str(&mut world, BOB_AND_ELSA).unwrap();
+        assert_eq!(spawned.len(), 2);
+
+        let mut miners = world.query::<(Entity, &Name, &Miner, &StateStack<MinerState>)>();
+        let (bob, name, miner, states) = miners.single(&world).unwrap();
+        assert_eq!(name.to_string(), "Miner Bob");
+        assert_eq!(states.last(), Some(&MinerState::EnterMineAndDigForNugget));
+        assert!(world.get::<Outbox>(bob).is_some());
+        assert!(world.get::<PathPlanner>(bob).is_some());
+        let wife = miner.wife;
+
+        let mut partners = world.query::<(Entity, &Name, &Partner, &StateStack<PartnerState>)>();
+        let (elsa, name, partner, states) = partners.single(&world).unwrap();
+        assert_eq!(name.to_string(), "Elsa");
+        assert_eq!(states.last(), Some(&PartnerState::VisitBathroom));
+        assert_eq!(wife, Some(elsa));
+        assert_eq!(partner.husband, Some(bob));
+    }
+
+    #[test]
+    fn states_default_when_left_out() {
+        let mut world = world();
+        spawn_from_ron_str(
+            &mut world,
+            r#"(
+                resources: {},
+                entities: {
+                    4294967295: (
+                        components: {
+                            "westworld_bevy_ecs::Name": ("Miner Jake"),
+                            "westworld_bevy_ecs::miner::Miner": (
+                                wife: None,
+                                opponent: None,
+                                gold: 3,
+                                bank: 0,
+                                thirst: 0,
+                                fatigue: 0,
+                                hunger: 0,
+                                journey: 0,
+                                next_stop: None,
+                                dig_slot: None,
+                                awaiting_path: false,
+                                route: [],
+                            ),
+                        },
+                    ),
+                    4294967294: (
+                        components: {
+                            "westworld_bevy_ecs::Name": ("Grace"),
+                            "westworld_bevy_ecs::partner::Partner": (
+                                husband: None,
+                                cooking: false,
+                            ),
+                        },
+                    ),
+                },
+            )"#,
+        )
+        .unwrap();
+
+        let mut miners = world.query::<(&Miner, &Location, &StateStack<MinerState>)>();
+        let (miner, location, states) = miners.single(&world).unwrap();
+        assert_eq!(miner.gold(), 3);
+        assert_eq!(*location, Location::Shack);
+        assert_eq!(states.last(), Some(&MinerState::GoHomeAndSleepTilRested));
+
+        let mut schedule = Schedule::default();
+        schedule.add_systems(marry);
+        schedule.run(&mut world);
+        let mut miners = world.query::<&Miner>();
+        assert!(miners.single(&world).unwrap().wife.is_some());
+    }
+}
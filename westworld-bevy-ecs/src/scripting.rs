@@ -0,0 +1,396 @@
+//! Miner states written in a scripting language, for trying out new
+//! behaviour without a rebuild. Every script in the `--scripts=DIR`
+//! directory is a state, [`MinerState::Scripted`] with the number of its file
+//! in order of their names, and its extension says which language it's in:
+//! Lua for `*.lua`, built with `--features lua`, or Rhai for `*.rhai`, built
+//! with `--features rhai`. Each language is a [`ScriptedState`] backend, so
+//! one state can be written in one and the next in the other.
+//!
+//! A script has whichever of `on_start`, `update` and `on_stop` it needs.
+//! Each is handed the miner as an [`Agent`], and `update` says what happens
+//! next by name: nothing to carry on, `"pop"`, `"quit"`, or a `switch` or
+//! `push` naming a state as a scenario file would, `"QuenchThirst"` or
+//! `"TravelTo(Saloon)"`, or another script by its file's name without the
+//! extension. Just how each is written is up to the language.
+//!
+//! A script that goes wrong ends its state, rather than leaving the miner
+//! stuck in it, and one in a language this wasn't built with won't load.
+
+use crate::cli::Cli;
+use crate::messaging::Telegram;
+use crate::miner::{MinerState, MinerStateData};
+use bevy_app::{App, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use fsm::StateTransition;
+use std::path::{Path, PathBuf};
+use std::{error, fmt, fs, io};
+
+#[cfg(feature = "lua")]
+use crate::lua_script::LuaScript;
+#[cfg(feature = "rhai")]
+use crate::rhai_script::RhaiScript;
+
+/// Errors raised while loading or running a script.
+#[derive(Debug)]
+pub enum ScriptError {
+    Io(io::Error),
+    /// What went wrong in the script, as its language tells it.
+    #[cfg(any(feature = "lua", feature = "rhai"))]
+    Script(Box<dyn error::Error + Send + Sync>),
+    /// A script asked for a state that there isn't.
+    UnknownState(String),
+    /// A script's `update` answered with something that isn't a transition.
+    #[cfg(any(feature = "lua", feature = "rhai"))]
+    BadAnswer(String),
+    NoSuchScript(u16),
+    /// A script in a language this wasn't built with.
+    NoBackend(PathBuf),
+}
+
+impl fmt::Display for ScriptError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            ScriptError::Io(e) => write!(f, "script i/o error: {}", e),
+            #[cfg(any(feature = "lua", feature = "rhai"))]
+            ScriptError::Script(e) => write!(f, "script error: {}", e),
+            ScriptError::UnknownState(name) => write!(f, "no state called {:?}", name),
+            #[cfg(any(feature = "lua", feature = "rhai"))]
+            ScriptError::BadAnswer(answer) => write!(f, "no such transition as {}", answer),
+            ScriptError::NoSuchScript(script) => write!(f, "no script number {}", script),
+            ScriptError::NoBackend(path) => {
+                write!(f, "not built with the language of {}", path.display())
+            }
+        }
+    }
+}
+
+impl error::Error for ScriptError {}
+
+impl From<io::Error> for ScriptError {
+    fn from(e: io::Error) -> Self {
+        ScriptError::Io(e)
+    }
+}
+
+/// The miner a script's running for, as it's handed to each hook.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Agent {
+    pub name: String,
+    pub location: String,
+    pub gold: i32,
+    pub wealth: i32,
+    pub thirst: i32,
+    pub fatigue: i32,
+    pub hunger: i32,
+    pub day: u64,
+    pub hour: u64,
+}
+
+/// A script compiled by the backend for its language, ready to run.
+pub trait ScriptedState: Send + Sync {
+    /// Runs the script's `hook` for `agent`, if it has one, and what it asks
+    /// for next, the states still called what the script called them.
+    fn call(&self, hook: &str, agent: &Agent) -> Result<StateTransition<String>, ScriptError>;
+}
+
+/// The scripted states.
+#[derive(Resource, Default)]
+pub struct Scripts {
+    /// Each script's name, where its state's number says.
+    names: Vec<String>,
+    states: Vec<Box<dyn ScriptedState>>,
+}
+
+impl Scripts {
+    /// The name of the script running as `Scripted(script)`.
+    pub fn name(&self, script: u16) -> Option<&str> {
+        self.names.get(script as usize).map(String::as_str)
+    }
+
+    /// Runs a script's `hook` for a miner, if it has one, and what it asks
+    /// for next.
+    pub fn call(
+        &self,
+        script: u16,
+        hook: &str,
+        (name, location, miner, _outbox, _economy, clock, ..): &MinerStateData,
+    ) -> Result<StateTransition<MinerState>, ScriptError> {
+        let state = self
+            .states
+            .get(script as usize)
+            .ok_or(ScriptError::NoSuchScript(script))?;
+        let agent = Agent {
+            name: name.to_string(),
+            location: format!("{:?}", **location),
+            gold: miner.gold(),
+            wealth: miner.wealth(),
+            thirst: miner.thirst(),
+            fatigue: miner.fatigue(),
+            hunger: miner.hunger(),
+            day: clock.day(),
+            hour: clock.hour(),
+        };
+        Ok(match state.call(hook, &agent)? {
+            StateTransition::None => StateTransition::None,
+            StateTransition::Pop => StateTransition::Pop,
+            StateTransition::Push(name) => StateTransition::Push(self.state(&name)?),
+            StateTransition::Switch(name) => StateTransition::Switch(self.state(&name)?),
+            StateTransition::Sequence(names) => StateTransition::Sequence(
+                names
+                    .iter()
+                    .map(|name| self.state(name))
+                    .collect::<Result<_, _>>()?,
+            ),
+            StateTransition::Quit => StateTransition::Quit,
+        })
+    }
+
+    /// The state called `name`: one of the scripts, or else one of
+    /// [`MinerState`]'s.
+    fn state(&self, name: &str) -> Result<MinerState, ScriptError> {
+        match self.names.iter().position(|script| script == name) {
+            Some(script) => Ok(MinerState::Scripted(script as u16)),
+            None => ron::from_str(name).map_err(|_| ScriptError::UnknownState(name.to_string())),
+        }
+    }
+
+    /// Adds `state` as the state called `name`: a new one, or in place of
+    /// the script of that name, the miners in it carrying on with the new
+    /// one from their next update.
+    pub fn add(&mut self, name: &str, state: Box<dyn ScriptedState>) -> u16 {
+        match self.names.iter().position(|script| script == name) {
+            Some(script) => {
+                self.states[script] = state;
+                script as u16
+            }
+            None => {
+                self.states.push(state);
+                self.names.push(name.to_string());
+                self.names.len() as u16 - 1
+            }
+        }
+    }
+
+    /// Loads every script in `dir`, in order of their names. Scripts already
+    /// loaded keep their numbers, so it loads them again after they're
+    /// changed without upsetting the miners in them.
+    pub fn add_dir<P: AsRef<Path>>(&mut self, dir: P) -> Result<(), ScriptError> {
+        let mut paths: Vec<PathBuf> = fs::read_dir(dir)?
+            .map(|entry| entry.map(|entry| entry.path()))
+            .collect::<Result<_, _>>()?;
+        paths.retain(|path| is_script(path));
+        paths.sort();
+        for path in paths {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            self.add(&name, compile(&path)?);
+        }
+        Ok(())
+    }
+
+    /// The scripts in `dir`.
+    pub fn load<P: AsRef<Path>>(dir: P) -> Result<Self, ScriptError> {
+        let mut scripts = Scripts::default();
+        scripts.add_dir(dir)?;
+        Ok(scripts)
+    }
+}
+
+/// Whether `path` is a script, in any of the languages, built with it or not.
+pub fn is_script(path: &Path) -> bool {
+    path.extension()
+        .is_some_and(|extension| extension == "lua" || extension == "rhai")
+}
+
+/// The script at `path`, compiled by the backend for its language.
+fn compile(path: &Path) -> Result<Box<dyn ScriptedState>, ScriptError> {
+    match path.extension().and_then(|extension| extension.to_str()) {
+        #[cfg(feature = "lua")]
+        Some("lua") => {
+            let name = path.file_stem().unwrap_or_default().to_string_lossy();
+            Ok(Box::new(LuaScript::new(&name, &fs::read_to_string(path)?)?))
+        }
+        #[cfg(feature = "rhai")]
+        Some("rhai") => Ok(Box::new(RhaiScript::new(&fs::read_to_string(path)?)?)),
+        _ => Err(ScriptError::NoBackend(path.to_path_buf())),
+    }
+}
+
+/// A state run by one of the scripts.
+pub struct Scripted(pub u16);
+
+impl Scripted {
+    fn run(&self, hook: &str, state_data: &mut MinerStateData) -> StateTransition<MinerState> {
+        let scripts = state_data.7;
+        scripts.call(self.0, hook, state_data).unwrap_or_else(|e| {
+            let script = scripts.name(self.0).unwrap_or("?");
+            warn!("{}: {} in {}: {}", state_data.0, hook, script, e);
+            StateTransition::Pop
+        })
+    }
+}
+
+impl<'a> fsm::Handler<MinerState, MinerStateData<'a>, Telegram> for Scripted {
+    fn on_start(&self, _state: &MinerState, state_data: &mut MinerStateData) {
+        self.run("on_start", state_data);
+    }
+
+    fn update(
+        &self,
+        _state: &MinerState,
+        state_data: &mut MinerStateData,
+    ) -> StateTransition<MinerState> {
+        self.run("update", state_data)
+    }
+
+    fn on_stop(&self, _state: &MinerState, state_data: &mut MinerStateData) {
+        self.run("on_stop", state_data);
+    }
+}
+
+/// Loads the scripted states with `--scripts=DIR`.
+pub struct ScriptingPlugin(pub PathBuf);
+
+impl ScriptingPlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.scripts.clone().map(ScriptingPlugin)
+    }
+}
+
+impl Plugin for ScriptingPlugin {
+    fn build(&self, app: &mut App) {
+        let scripts = Scripts::load(&self.0)
+            .unwrap_or_else(|e| panic!("could not load {}: {}", self.0.display(), e));
+        info!("loaded {} scripted states", scripts.names.len());
+        app.insert_resource(scripts);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::scenario::Scenario;
+    use crate::testing::{build_test_app, tick};
+    use crate::Location;
+    use fsm::StateStack;
+
+    /// A state that asks for the same thing every update.
+    struct Asking(fn() -> StateTransition<String>);
+
+    impl ScriptedState for Asking {
+        fn call(&self, hook: &str, _agent: &Agent) -> Result<StateTransition<String>, ScriptError> {
+            Ok(match hook {
+                "update" => (self.0)(),
+                _ => StateTransition::None,
+            })
+        }
+    }
+
+    /// Miner Bob in the first scripted state, with `scripts` for his states.
+    fn scripted_miner(scripts: Vec<(&str, Box<dyn ScriptedState>)>) -> App {
+        let scenario =
+            Scenario::from_ron_str(r#"(miners: [(name: "Miner Bob", state: Scripted(0))])"#)
+                .unwrap();
+        let mut app = build_test_app(scenario, 1);
+        let mut loaded = Scripts::default();
+        for (name, state) in scripts {
+            loaded.add(name, state);
+        }
+        app.insert_resource(loaded);
+        app
+    }
+
+    fn bob(app: &mut App) -> (Location, Vec<MinerState>) {
+        let (location, states) = app
+            .world_mut()
+            .query::<(&Location, &StateStack<MinerState>)>()
+            .single(app.world())
+            .unwrap();
+        (*location, states.iter().copied().collect())
+    }
+
+    #[test]
+    fn a_script_can_switch_to_another_script() {
+        let mut app = scripted_miner(vec![
+            (
+                "first",
+                Box::new(Asking(|| StateTransition::Switch("second".to_string()))),
+            ),
+            ("second", Box::new(Asking(|| StateTransition::None))),
+        ]);
+        tick(&mut app, 1);
+        assert_eq!(bob(&mut app).1, vec![MinerState::Scripted(1)]);
+    }
+
+    #[test]
+    fn a_script_asking_for_no_such_state_ends_it() {
+        let mut app = scripted_miner(vec![(
+            "lost",
+            Box::new(Asking(|| StateTransition::Push("Dancing".to_string()))),
+        )]);
+        tick(&mut app, 1);
+        assert_eq!(bob(&mut app).1, vec![]);
+    }
+
+    #[test]
+    #[cfg(feature = "lua")]
+    fn a_lua_script_says_where_the_miner_goes_next() {
+        let loafing = LuaScript::new("loafing", include_str!("../scripts/loafing.lua")).unwrap();
+        let mut app = scripted_miner(vec![("loafing", Box::new(loafing))]);
+        tick(&mut app, 1);
+        assert_eq!(
+            bob(&mut app),
+            (Location::Shack, vec![MinerState::Scripted(0)])
+        );
+        // at eight he's off to the saloon
+        tick(&mut app, 1);
+        assert_eq!(
+            bob(&mut app).1,
+            vec![
+                MinerState::Scripted(0),
+                MinerState::TravelTo(Location::Saloon)
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(feature = "rhai")]
+    fn a_rhai_script_says_where_the_miner_goes_next() {
+        let whittling = RhaiScript::new(include_str!("../scripts/whittling.rhai")).unwrap();
+        let mut app = scripted_miner(vec![("whittling", Box::new(whittling))]);
+        tick(&mut app, 1);
+        assert_eq!(
+            bob(&mut app),
+            (Location::Shack, vec![MinerState::Scripted(0)])
+        );
+        // come the evening he's off to the saloon for a hand of cards
+        tick(&mut app, 11);
+        assert_eq!(
+            bob(&mut app).1,
+            vec![
+                MinerState::PlayCards,
+                MinerState::TravelTo(Location::Saloon)
+            ]
+        );
+    }
+
+    #[test]
+    #[cfg(all(feature = "lua", feature = "rhai"))]
+    fn a_lua_script_can_switch_to_a_rhai_one() {
+        let lua = LuaScript::new(
+            "first",
+            r#"return { update = function() return { switch = "second" } end }"#,
+        )
+        .unwrap();
+        let rhai = RhaiScript::new("").unwrap();
+        let mut app = scripted_miner(vec![("first", Box::new(lua)), ("second", Box::new(rhai))]);
+        tick(&mut app, 1);
+        assert_eq!(bob(&mut app).1, vec![MinerState::Scripted(1)]);
+    }
+
+    #[test]
+    fn a_script_in_a_language_not_built_with_wont_load() {
+        let path = Path::new("scripts/dancing.bf");
+        assert!(matches!(compile(path), Err(ScriptError::NoBackend(_))));
+    }
+}
@@ -0,0 +1,318 @@
+use std::ops::DerefMut;
+
+use crate::agents::{spawn_agent, AgentHandler, FsmPlugin, Run};
+use crate::bandit::{position_of, BanditState, DELIVER_TO_BANDITS, INIT_BANDITS, UPDATE_BANDITS};
+use crate::messaging::{Message, Outbox, Telegram, SEND_MSG_IMMEDIATELY};
+use crate::path_planner::NavGraph;
+use crate::riders::{self, Mount, RIDE};
+use crate::scenario::Scenario;
+use crate::{Label, Location, Name};
+use bevy_app::{App, FixedUpdate, Plugin, Startup};
+use bevy_ecs::entity::{EntityMapper, MapEntities};
+use bevy_ecs::prelude::*;
+use bevy_ecs::query::QueryItem;
+use bevy_ecs::system::SystemParamItem;
+use bevy_log::prelude::*;
+use bevy_reflect::Reflect;
+use game_ai::steering::{Behavior, Deceleration, Vehicle};
+use game_ai::Vector2D;
+use serde::{Deserialize, Serialize};
+
+pub static PATROL: &[Location] = &[
+    Location::Bank,
+    Location::Saloon,
+    Location::Goldmine,
+    Location::Shack,
+];
+pub static CATCH_DISTANCE: f64 = 2.0; // how close the sheriff has to get to make an arrest
+pub static SHERIFF_SPEED: f64 = 7.5;
+
+/// Label of the system updating the sheriffs, for systems that must run in a fixed order with it.
+pub static UPDATE_SHERIFFS: Label = Label("update_sheriffs");
+/// Label of the system delivering messages to the sheriffs.
+pub static DELIVER_TO_SHERIFFS: Label = Label("deliver_to_sheriffs");
+
+/// Where a bandit is and where he's heading, for a sheriff to give chase.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct Suspect {
+    pub bandit: Entity,
+    pub position: Vector2D,
+    pub velocity: Vector2D,
+    pub on_the_run: bool,
+}
+
+/// Every bandit in town this update.
+pub type Suspects = [Suspect];
+
+pub type SheriffStateData<'a> = (
+    &'a Name,
+    &'a mut Location,
+    &'a mut Sheriff,
+    &'a mut Vehicle,
+    &'a mut Outbox,
+    &'a NavGraph,
+    &'a Suspects,
+);
+
+#[derive(Component, Clone, Debug, Serialize, Deserialize)]
+pub struct Sheriff {
+    beat: usize,
+    /// The bandit he's after, if he's after one.
+    pub chasing: Option<Entity>,
+}
+
+impl Sheriff {
+    pub fn new() -> Self {
+        Sheriff {
+            beat: 0,
+            chasing: None,
+        }
+    }
+}
+
+impl MapEntities for Sheriff {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        self.chasing.map_entities(entity_mapper);
+    }
+}
+
+#[derive(Reflect, Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum SheriffState {
+    Patrol,
+    Pursue,
+}
+
+/// Rides from one place to the next on his beat, round and round.
+pub struct Patrol;
+
+impl<'a> fsm::Handler<SheriffState, SheriffStateData<'a>, Telegram> for Patrol {
+    fn on_start(&self, _state: &SheriffState, (name, ..): &mut SheriffStateData) {
+        info!("{}: Makin' mah rounds", name);
+    }
+
+    fn update(
+        &self,
+        _state: &SheriffState,
+        (_name, location, sheriff, vehicle, _outbox, graph, _suspects): &mut SheriffStateData,
+    ) -> fsm::StateTransition<SheriffState> {
+        let mut stop = PATROL[sheriff.beat];
+        if riders::arrived(vehicle, position_of(graph, stop)) {
+            **location = stop;
+            sheriff.beat = (sheriff.beat + 1) % PATROL.len();
+            stop = PATROL[sheriff.beat];
+        }
+        vehicle.set_behavior(Some(Behavior::Arrive(
+            position_of(graph, stop),
+            Deceleration::Normal,
+        )));
+        fsm::StateTransition::None
+    }
+
+    fn on_message(
+        &self,
+        _state: &SheriffState,
+        (name, _location, sheriff, ..): &mut SheriffStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<SheriffState>> {
+        match telegram.message {
+            Message::BankRobbed => {
+                info!("{}: Somebody's robbin' the bank! After 'im!", name);
+                sheriff.chasing = Some(telegram.sender);
+                Some(fsm::StateTransition::Switch(SheriffState::Pursue))
+            }
+            _ => None,
+        }
+    }
+}
+
+/// Chases the bandit he's after until he catches him or he gets away.
+pub struct Pursue;
+
+impl<'a> fsm::Handler<SheriffState, SheriffStateData<'a>, Telegram> for Pursue {
+    fn update(
+        &self,
+        _state: &SheriffState,
+        (name, _location, sheriff, vehicle, outbox, _graph, suspects): &mut SheriffStateData,
+    ) -> fsm::StateTransition<SheriffState> {
+        let suspect = suspects
+            .iter()
+            .find(|suspect| Some(suspect.bandit) == sheriff.chasing);
+        let suspect = match suspect {
+            Some(suspect) if suspect.on_the_run => suspect,
+            _ => {
+                info!("{}: Dang varmint got clean away", name);
+                return fsm::StateTransition::Switch(SheriffState::Patrol);
+            }
+        };
+
+        if vehicle.position.distance(suspect.position) < CATCH_DISTANCE {
+            info!("{}: Yer under arrest, ya no-good bandit!", name);
+            outbox.dispatch(SEND_MSG_IMMEDIATELY, suspect.bandit, Message::Arrested);
+            fsm::StateTransition::Switch(SheriffState::Patrol)
+        } else {
+            vehicle.set_behavior(Some(Behavior::Pursuit(suspect.position, suspect.velocity)));
+            fsm::StateTransition::None
+        }
+    }
+
+    fn on_stop(
+        &self,
+        _state: &SheriffState,
+        (_name, _location, sheriff, ..): &mut SheriffStateData,
+    ) {
+        sheriff.chasing = None;
+    }
+
+    // one bandit at a time
+    fn on_message(
+        &self,
+        _state: &SheriffState,
+        _state_data: &mut SheriffStateData,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<SheriffState>> {
+        match telegram.message {
+            Message::BankRobbed => Some(fsm::StateTransition::None),
+            _ => None,
+        }
+    }
+}
+
+pub struct SheriffHandler;
+
+impl<'a> fsm::Handler<SheriffState, SheriffStateData<'a>, Telegram> for SheriffHandler {
+    fn on_start(&self, state: &SheriffState, state_data: &mut SheriffStateData<'a>) {
+        match state {
+            SheriffState::Patrol => Patrol.on_start(state, state_data),
+            SheriffState::Pursue => Pursue.on_start(state, state_data),
+        }
+    }
+
+    fn on_stop(&self, state: &SheriffState, state_data: &mut SheriffStateData<'a>) {
+        match state {
+            SheriffState::Patrol => Patrol.on_stop(state, state_data),
+            SheriffState::Pursue => Pursue.on_stop(state, state_data),
+        }
+    }
+
+    fn on_pause(&self, state: &SheriffState, state_data: &mut SheriffStateData<'a>) {
+        match state {
+            SheriffState::Patrol => Patrol.on_pause(state, state_data),
+            SheriffState::Pursue => Pursue.on_pause(state, state_data),
+        }
+    }
+
+    fn on_resume(&self, state: &SheriffState, state_data: &mut SheriffStateData<'a>) {
+        match state {
+            SheriffState::Patrol => Patrol.on_resume(state, state_data),
+            SheriffState::Pursue => Pursue.on_resume(state, state_data),
+        }
+    }
+
+    fn update(
+        &self,
+        state: &SheriffState,
+        state_data: &mut SheriffStateData<'a>,
+    ) -> fsm::StateTransition<SheriffState> {
+        match state {
+            SheriffState::Patrol => Patrol.update(state, state_data),
+            SheriffState::Pursue => Pursue.update(state, state_data),
+        }
+    }
+
+    fn on_message(
+        &self,
+        state: &SheriffState,
+        state_data: &mut SheriffStateData<'a>,
+        telegram: &Telegram,
+    ) -> Option<fsm::StateTransition<SheriffState>> {
+        match state {
+            SheriffState::Patrol => Patrol.on_message(state, state_data, telegram),
+            SheriffState::Pursue => Pursue.on_message(state, state_data, telegram),
+        }
+    }
+}
+
+pub struct SheriffPlugin;
+
+impl Plugin for SheriffPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Scenario>();
+        app.add_systems(Startup, init_sheriffs.after(INIT_BANDITS));
+        // runs after the bandits so he hears of a robbery the moment it's done
+        app.add_plugins(
+            FsmPlugin::<SheriffState, SheriffHandler, SheriffComponents>::new(
+                UPDATE_SHERIFFS,
+                DELIVER_TO_SHERIFFS,
+            )
+            .after(UPDATE_BANDITS, DELIVER_TO_BANDITS),
+        );
+        app.configure_sets(FixedUpdate, UPDATE_SHERIFFS.before(RIDE));
+    }
+}
+
+pub fn init_sheriffs(mut commands: Commands, graph: Res<NavGraph>, scenario: Res<Scenario>) {
+    info!("initialising {} sheriffs", scenario.sheriffs.len());
+    for spec in &scenario.sheriffs {
+        let location = spec.location.unwrap_or(Location::Jail);
+        spawn_agent(&mut commands, &spec.name, location)
+            .insert(Sheriff::new())
+            .insert(Mount(riders::horse(
+                position_of(&graph, location),
+                SHERIFF_SPEED,
+            )))
+            .insert(fsm::StateStack::<SheriffState>::new_initial_state(
+                spec.state.unwrap_or(SheriffState::Patrol),
+            ));
+    }
+}
+
+/// A sheriff's own components, besides his name and states.
+pub type SheriffComponents = (
+    &'static mut Location,
+    &'static mut Sheriff,
+    &'static mut Mount,
+    &'static mut Outbox,
+);
+
+type Bandits<'a> = (Entity, &'a Mount, &'a fsm::StateStack<BanditState>);
+
+type SheriffTown = (
+    Res<'static, NavGraph>,
+    Query<'static, 'static, Bandits<'static>, Without<Sheriff>>,
+);
+
+impl AgentHandler<SheriffState, SheriffComponents> for SheriffHandler {
+    type Town = SheriffTown;
+
+    fn run(
+        (graph, bandits): &mut SystemParamItem<SheriffTown>,
+        name: &Name,
+        (mut location, mut sheriff, mut vehicle, mut outbox): QueryItem<SheriffComponents>,
+        state_stack: &mut fsm::StateStack<SheriffState>,
+        run: Run,
+    ) {
+        // only a sheriff taking his turn keeps an eye on the bandits
+        let suspects: Vec<Suspect> = match run {
+            Run::Update => bandits
+                .iter()
+                .map(|(bandit, vehicle, states)| Suspect {
+                    bandit,
+                    position: vehicle.position,
+                    velocity: vehicle.velocity,
+                    on_the_run: states.last() == Some(&BanditState::Flee),
+                })
+                .collect(),
+            _ => vec![],
+        };
+        let mut state_data = (
+            name,
+            location.deref_mut(),
+            sheriff.deref_mut(),
+            &mut vehicle.0,
+            outbox.deref_mut(),
+            &**graph,
+            &suspects[..],
+        );
+        run.on(&SheriffHandler, state_stack, &mut state_data);
+    }
+}
@@ -0,0 +1,41 @@
+//! Stopping cleanly on Ctrl-C, or SIGTERM on unix, when run in a terminal.
+//! The handler only raises a flag; the runner finishes the frame it's on,
+//! then [`wind_down`] stops every agent's state machine so the states still
+//! on their stacks get their `on_stop`.
+
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::ScheduleLabel;
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Resource, Clone)]
+pub struct Shutdown(Arc<AtomicBool>);
+
+impl Shutdown {
+    /// Installs the signal handlers.
+    #[cfg(not(target_arch = "wasm32"))]
+    pub fn install() -> Self {
+        let flag = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&flag))
+            .expect("couldn't install the Ctrl-C handler");
+        #[cfg(unix)]
+        signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&flag))
+            .expect("couldn't install the SIGTERM handler");
+        Shutdown(flag)
+    }
+
+    pub fn requested(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
+
+/// The schedule stopping every kind of agent's state machine, in the order
+/// they take their turns, added to by each [`FsmPlugin`](crate::agents::FsmPlugin).
+#[derive(ScheduleLabel, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct WindDown;
+
+/// Stops every agent, once the run is over, however it ended.
+pub fn wind_down(world: &mut World) {
+    // there's no schedule to run if there are no agents
+    let _ = world.try_run_schedule(WindDown);
+}
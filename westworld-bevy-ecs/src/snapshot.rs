@@ -0,0 +1,609 @@
+//! Saving the whole world to a file and picking up from it later.
+//!
+//! A snapshot holds every agent with its state stack, the clock, the weather,
+//! the goldmine, the random number generator, the timers' pending events and the messages
+//! posted but not yet read, so a run resumed from one carries on exactly as it
+//! would have. Paths still being searched for are kept as how far each search
+//! had got, and searched that far again when they're restored.
+
+use crate::bandit::{Bandit, BanditState, BANDIT_SPEED};
+use crate::barfly::{BarFly, BarFlyState};
+use crate::cli::Cli;
+use crate::clock::Clock;
+use crate::economy::EconomyTimer;
+use crate::goldmine::Goldmine;
+use crate::messaging::{MessageTimer, Outbox, Telegram};
+use crate::miner::{Miner, MinerState};
+use crate::partner::{Partner, PartnerState};
+use crate::path_planner::{
+    self, resume_searches, save_searches, NavGraph, PathPlanner, PathSearches, SavedSearch,
+};
+use crate::riders::{self, Mount};
+use crate::rng::WorldRng;
+use crate::sheriff::{Sheriff, SheriffState, SHERIFF_SPEED};
+use crate::weather::{Weather, WeatherTimer};
+use crate::{Location, Name};
+use bevy_app::{App, MainScheduleOrder, Plugin, PostStartup};
+use bevy_ecs::entity::{EntityHashMap, EntityMapper, MapEntities};
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::ScheduleLabel;
+use bevy_log::prelude::*;
+use fsm::StateStack;
+use game_ai::graph::path::{edges_from_waypoints, waypoints};
+use game_ai::steering::{Behavior, Vehicle};
+use game_ai::Vector2D;
+use serde::{Deserialize, Serialize};
+use std::path::{Path, PathBuf};
+use std::{error, fmt, fs, io};
+
+/// The startup schedule that swaps the freshly spawned world for a saved one.
+#[derive(ScheduleLabel, Copy, Clone, Debug, PartialEq, Eq, Hash)]
+pub struct LoadSnapshot;
+
+#[derive(Serialize, Deserialize)]
+pub enum Body {
+    Miner {
+        miner: Miner,
+        position: Vector2D,
+        /// The waypoints of the last path planned for him, which he may not
+        /// have heard of yet.
+        #[serde(default)]
+        path: Option<Vec<Vector2D>>,
+        states: StateStack<MinerState>,
+    },
+    Partner {
+        partner: Partner,
+        states: StateStack<PartnerState>,
+    },
+    BarFly {
+        barfly: BarFly,
+        states: StateStack<BarFlyState>,
+    },
+    Bandit {
+        bandit: Bandit,
+        horse: Horse,
+        states: StateStack<BanditState>,
+    },
+    Sheriff {
+        sheriff: Sheriff,
+        horse: Horse,
+        states: StateStack<SheriffState>,
+    },
+}
+
+/// Where a rider is, how fast he's going and how he's steering.
+#[derive(Serialize, Deserialize)]
+pub struct Horse {
+    pub position: Vector2D,
+    pub velocity: Vector2D,
+    /// Which way he's facing, which steers him as much as his velocity does.
+    #[serde(default = "facing_east")]
+    pub heading: Vector2D,
+    /// What he was steering by last, which a state that's got where it's
+    /// going leaves him to slow down by.
+    #[serde(default)]
+    pub behavior: Option<Behavior>,
+}
+
+/// The way a horse faces when he's first saddled.
+fn facing_east() -> Vector2D {
+    Vector2D::new(1.0, 0.0)
+}
+
+impl Horse {
+    fn new(vehicle: &Vehicle) -> Self {
+        Horse {
+            position: vehicle.position,
+            velocity: vehicle.velocity,
+            heading: vehicle.heading,
+            behavior: vehicle.behavior(),
+        }
+    }
+
+    fn mount(&self, max_speed: f64) -> Mount {
+        let mut vehicle = riders::horse(self.position, max_speed);
+        vehicle.velocity = self.velocity;
+        vehicle.heading = self.heading;
+        vehicle.set_behavior(self.behavior);
+        Mount(vehicle)
+    }
+}
+
+#[derive(Serialize, Deserialize)]
+pub struct Agent {
+    pub id: Entity,
+    pub name: String,
+    pub location: Location,
+    pub body: Body,
+}
+
+/// The agents respawned from a snapshot, by the ids they were saved under,
+/// and the first id the snapshot refers to without having saved.
+#[derive(Default)]
+struct Respawned {
+    ids: EntityHashMap<Entity>,
+    missing: Option<Entity>,
+}
+
+impl EntityMapper for Respawned {
+    fn get_mapped(&mut self, saved: Entity) -> Entity {
+        match self.ids.get(&saved) {
+            Some(&id) => id,
+            None => {
+                self.missing.get_or_insert(saved);
+                saved
+            }
+        }
+    }
+
+    fn set_mapped(&mut self, saved: Entity, id: Entity) {
+        self.ids.insert(saved, id);
+    }
+}
+
+/// Empties `entity`'s stack of states `S`, if it has one, without stopping
+/// them.
+fn forget_states<S: Clone + Send + Sync + 'static>(world: &mut World, entity: Entity) {
+    if let Some(mut states) = world.get_mut::<StateStack<S>>(entity) {
+        *states = StateStack::new();
+    }
+}
+
+/// Everything needed to resume a run, as saved to a snapshot file.
+#[derive(Resource, Serialize, Deserialize)]
+pub struct Snapshot {
+    pub clock: Clock,
+    pub rng: WorldRng,
+    pub economy: EconomyTimer,
+    pub weather: Weather,
+    pub forecast: WeatherTimer,
+    pub goldmine: Goldmine,
+    /// The goldmine's entity, which the miners send messages to.
+    #[serde(default)]
+    pub goldmine_id: Option<Entity>,
+    pub messages: MessageTimer,
+    /// Messages posted last tick that are still to be read.
+    pub telegrams: Vec<Telegram>,
+    pub agents: Vec<Agent>,
+    /// The paths still being searched for, in the order they take their turns.
+    #[serde(default)]
+    pub searches: Vec<SavedSearch>,
+}
+
+/// Errors raised while reading, writing or restoring a snapshot.
+#[derive(Debug)]
+pub enum SnapshotError {
+    Io(io::Error),
+    Serialize(ron::Error),
+    Parse(ron::error::SpannedError),
+    Entities(Entity),
+}
+
+impl fmt::Display for SnapshotError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            SnapshotError::Io(e) => write!(f, "snapshot file i/o error: {}", e),
+            SnapshotError::Serialize(e) => write!(f, "could not write snapshot: {}", e),
+            SnapshotError::Parse(e) => write!(f, "could not parse snapshot: {}", e),
+            SnapshotError::Entities(e) => write!(f, "snapshot refers to a missing agent: {}", e),
+        }
+    }
+}
+
+impl error::Error for SnapshotError {}
+
+impl From<io::Error> for SnapshotError {
+    fn from(e: io::Error) -> Self {
+        SnapshotError::Io(e)
+    }
+}
+
+impl From<ron::Error> for SnapshotError {
+    fn from(e: ron::Error) -> Self {
+        SnapshotError::Serialize(e)
+    }
+}
+
+impl From<ron::error::SpannedError> for SnapshotError {
+    fn from(e: ron::error::SpannedError) -> Self {
+        SnapshotError::Parse(e)
+    }
+}
+
+impl Snapshot {
+    /// Takes a snapshot of the world between ticks. Agents are kept in the
+    /// order they take their turns, and respawned in the same order.
+    pub fn capture(world: &mut World) -> Self {
+        let mut agents = vec![];
+        let mut miners = world.query::<(
+            Entity,
+            &Name,
+            &Location,
+            &Miner,
+            &PathPlanner,
+            Option<&path_planner::Path>,
+            &StateStack<MinerState>,
+        )>();
+        for (id, name, location, miner, planner, path, states) in miners.iter(world) {
+            agents.push(Agent {
+                id,
+                name: name.to_string(),
+                location: *location,
+                body: Body::Miner {
+                    miner: miner.clone(),
+                    position: planner.position(),
+                    path: path.map(|path| waypoints(&path.0)),
+                    states: states.clone(),
+                },
+            });
+        }
+        let mut partners = world.query::<(
+            Entity,
+            &Name,
+            &Location,
+            &Partner,
+            &StateStack<PartnerState>,
+        )>();
+        for (id, name, location, partner, states) in partners.iter(world) {
+            agents.push(Agent {
+                id,
+                name: name.to_string(),
+                location: *location,
+                body: Body::Partner {
+                    partner: partner.clone(),
+                    states: states.clone(),
+                },
+            });
+        }
+        let mut barflies =
+            world.query::<(Entity, &Name, &Location, &BarFly, &StateStack<BarFlyState>)>();
+        for (id, name, location, barfly, states) in barflies.iter(world) {
+            agents.push(Agent {
+                id,
+                name: name.to_string(),
+                location: *location,
+                body: Body::BarFly {
+                    barfly: barfly.clone(),
+                    states: states.clone(),
+                },
+            });
+        }
+        let mut bandits = world.query::<(
+            Entity,
+            &Name,
+            &Location,
+            &Bandit,
+            &Mount,
+            &StateStack<BanditState>,
+        )>();
+        for (id, name, location, bandit, vehicle, states) in bandits.iter(world) {
+            agents.push(Agent {
+                id,
+                name: name.to_string(),
+                location: *location,
+                body: Body::Bandit {
+                    bandit: bandit.clone(),
+                    horse: Horse::new(vehicle),
+                    states: states.clone(),
+                },
+            });
+        }
+        let mut sheriffs = world.query::<(
+            Entity,
+            &Name,
+            &Location,
+            &Sheriff,
+            &Mount,
+            &StateStack<SheriffState>,
+        )>();
+        for (id, name, location, sheriff, vehicle, states) in sheriffs.iter(world) {
+            agents.push(Agent {
+                id,
+                name: name.to_string(),
+                location: *location,
+                body: Body::Sheriff {
+                    sheriff: sheriff.clone(),
+                    horse: Horse::new(vehicle),
+                    states: states.clone(),
+                },
+            });
+        }
+
+        let telegrams = world
+            .get_resource::<Messages<Telegram>>()
+            .unwrap()
+            .iter_current_update_messages()
+            .copied()
+            .collect();
+        // in the order they were spawned, whichever tables they've since
+        // moved between
+        agents.sort_by_key(|agent| agent.id.index_u32());
+        let (goldmine_id, goldmine) = world
+            .query::<(Entity, &Goldmine)>()
+            .iter(world)
+            .next()
+            .unwrap();
+        Snapshot {
+            clock: world.get_resource::<Clock>().unwrap().clone(),
+            rng: world.get_resource::<WorldRng>().unwrap().live_copy(),
+            economy: world.get_resource::<EconomyTimer>().unwrap().clone(),
+            weather: *world.get_resource::<Weather>().unwrap(),
+            forecast: world.get_resource::<WeatherTimer>().unwrap().clone(),
+            goldmine: goldmine.clone(),
+            goldmine_id: Some(goldmine_id),
+            messages: world.get_resource::<MessageTimer>().unwrap().clone(),
+            telegrams,
+            agents,
+            searches: save_searches(world),
+        }
+    }
+
+    /// Replaces the agents and the state of the world with the snapshot's.
+    pub fn restore(self, world: &mut World) -> Result<(), SnapshotError> {
+        let spawned: Vec<Entity> = world
+            .query_filtered::<Entity, With<Name>>()
+            .iter(world)
+            .collect();
+        for entity in spawned {
+            // the restored agents take over where these left off, so theirs
+            // aren't stopped
+            forget_states::<MinerState>(world, entity);
+            forget_states::<PartnerState>(world, entity);
+            forget_states::<BarFlyState>(world, entity);
+            forget_states::<BanditState>(world, entity);
+            forget_states::<SheriffState>(world, entity);
+            world.despawn(entity);
+        }
+        let mines: Vec<Entity> = world
+            .query_filtered::<Entity, With<Goldmine>>()
+            .iter(world)
+            .collect();
+        for mine in mines {
+            world.despawn(mine);
+        }
+        if let Some(mut graph) = world.get_resource_mut::<NavGraph>() {
+            graph.relocate(Location::Goldmine, self.goldmine.position());
+        }
+        // the agents take their turns in the order of their entities, and the
+        // ones just despawned are handed out again last first, so they're
+        // sorted before being given out in turn
+        let mut ids: Vec<Entity> = (0..=self.agents.len())
+            .map(|_| world.spawn_empty().id())
+            .collect();
+        ids.sort_by_key(|id| id.index_u32());
+        let mine = ids[0];
+
+        let mut respawned = Respawned::default();
+        if let Some(id) = self.goldmine_id {
+            respawned.set_mapped(id, mine);
+        }
+        for (agent, &id) in self.agents.iter().zip(&ids[1..]) {
+            respawned.set_mapped(agent.id, id);
+        }
+        for agent in self.agents {
+            let id = respawned.get_mapped(agent.id);
+            let mut entity = world.entity_mut(id);
+            entity
+                .insert(Name(agent.name))
+                .insert(Outbox::new(id))
+                .insert(agent.location);
+            match agent.body {
+                Body::Miner {
+                    mut miner,
+                    position,
+                    path,
+                    states,
+                } => {
+                    miner.map_entities(&mut respawned);
+                    entity
+                        .insert(miner)
+                        .insert(PathPlanner::new(position))
+                        .insert(states);
+                    if let Some(path) = path {
+                        entity.insert(path_planner::Path(edges_from_waypoints(&path)));
+                    }
+                }
+                Body::Partner {
+                    mut partner,
+                    states,
+                } => {
+                    partner.map_entities(&mut respawned);
+                    entity.insert(partner).insert(states);
+                }
+                Body::BarFly { mut barfly, states } => {
+                    barfly.map_entities(&mut respawned);
+                    entity.insert(barfly).insert(states);
+                }
+                Body::Bandit {
+                    bandit,
+                    horse,
+                    states,
+                } => {
+                    entity
+                        .insert(bandit)
+                        .insert(horse.mount(BANDIT_SPEED))
+                        .insert(states);
+                }
+                Body::Sheriff {
+                    mut sheriff,
+                    horse,
+                    states,
+                } => {
+                    sheriff.map_entities(&mut respawned);
+                    entity
+                        .insert(sheriff)
+                        .insert(horse.mount(SHERIFF_SPEED))
+                        .insert(states);
+                }
+            }
+        }
+
+        let mut goldmine = self.goldmine;
+        goldmine.map_entities(&mut respawned);
+        world
+            .entity_mut(mine)
+            .insert(Name("Goldmine".to_string()))
+            .insert(Outbox::new(mine))
+            .insert(goldmine);
+
+        let mut telegrams = world.get_resource_mut::<Messages<Telegram>>().unwrap();
+        // the goldmine answers what's sent to it as soon as it's posted, so
+        // it's answered these already, and would again if they were restored
+        let goldmine_id = self.goldmine_id;
+        let unanswered = self
+            .telegrams
+            .into_iter()
+            .filter(|telegram| Some(telegram.receiver) != goldmine_id);
+        for mut telegram in unanswered {
+            telegram.map_entities(&mut respawned);
+            telegrams.write(telegram);
+        }
+        let mut messages = self.messages;
+        messages.map_entities(&mut respawned);
+        let mut searches = self.searches;
+        for search in searches.iter_mut() {
+            search.map_entities(&mut respawned);
+        }
+        if let Some(missing) = respawned.missing {
+            return Err(SnapshotError::Entities(missing));
+        }
+        if world.contains_resource::<PathSearches>() {
+            resume_searches(world, searches);
+        }
+        world.insert_resource(self.clock);
+        world.insert_resource(self.rng);
+        world.insert_resource(self.economy);
+        world.insert_resource(self.weather);
+        world.insert_resource(self.forecast);
+        world.insert_resource(messages);
+        Ok(())
+    }
+
+    pub fn load<P: AsRef<Path>>(path: P) -> Result<Self, SnapshotError> {
+        Ok(ron::from_str(&fs::read_to_string(path)?)?)
+    }
+
+    pub fn save<P: AsRef<Path>>(&self, path: P) -> Result<(), SnapshotError> {
+        let ron = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())?;
+        fs::write(path, ron)?;
+        Ok(())
+    }
+}
+
+/// Where to save the world once the run is over.
+#[derive(Resource)]
+pub struct SaveTo(pub PathBuf);
+
+/// Saves the world if `--save` was given, once the run is over.
+pub fn save(world: &mut World) {
+    let path = match world.get_resource::<SaveTo>() {
+        Some(SaveTo(path)) => path.clone(),
+        None => return,
+    };
+    match Snapshot::capture(world).save(&path) {
+        Ok(()) => info!("saved the world to {}", path.display()),
+        Err(e) => error!("{}: {}", path.display(), e),
+    }
+}
+
+pub fn load_snapshot(world: &mut World) {
+    if let Some(snapshot) = world.remove_resource::<Snapshot>() {
+        info!("picking up on {}", snapshot.clock);
+        // startup runs before the first tick's FixedFirst schedule, which moves
+        // the clock on and brings the restored messages up to be read, as it
+        // would have the saved world
+        if let Err(e) = snapshot.restore(world) {
+            panic!("could not restore the snapshot: {}", e);
+        }
+    }
+}
+
+/// Has `app` pick up from `snapshot` once it's started, rather than the
+/// world it spawned.
+pub fn resume(app: &mut App, snapshot: Snapshot) {
+    app.insert_resource(snapshot);
+    app.world_mut()
+        .resource_mut::<MainScheduleOrder>()
+        .insert_startup_after(PostStartup, LoadSnapshot);
+    app.add_systems(LoadSnapshot, load_snapshot);
+}
+
+/// Resumes the world from `--load=FILE` and saves it to `--save=FILE` when
+/// the run is over. Call [`save`] once the run is over.
+pub struct SnapshotPlugin {
+    pub load: Option<PathBuf>,
+    pub save: Option<PathBuf>,
+}
+
+impl SnapshotPlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        (cli.load.is_some() || cli.save.is_some()).then(|| SnapshotPlugin {
+            load: cli.load.clone(),
+            save: cli.save.clone(),
+        })
+    }
+}
+
+impl Plugin for SnapshotPlugin {
+    fn build(&self, app: &mut App) {
+        if let Some(path) = &self.load {
+            let snapshot = Snapshot::load(path)
+                .unwrap_or_else(|e| panic!("could not load {}: {}", path.display(), e));
+            resume(app, snapshot);
+        }
+        if let Some(path) = &self.save {
+            app.insert_resource(SaveTo(path.clone()));
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::population::Population;
+    use crate::scenario::Scenario;
+    use crate::testing::{build_test_app, tick};
+
+    #[test]
+    fn restoring_a_snapshot_gives_back_the_same_world() {
+        let mut app = build_test_app(
+            Scenario::populated(&Population {
+                miners: 2,
+                partners: 2,
+            }),
+            1,
+        );
+        tick(&mut app, 58);
+        let mut snapshot = Snapshot::capture(app.world_mut());
+        let saved = ron::to_string(&snapshot).unwrap();
+        // less the telegrams the goldmine's answered already
+        assert!(snapshot
+            .telegrams
+            .iter()
+            .any(|telegram| Some(telegram.receiver) == snapshot.goldmine_id));
+        let goldmine_id = snapshot.goldmine_id;
+        snapshot
+            .telegrams
+            .retain(|telegram| Some(telegram.receiver) != goldmine_id);
+        let restored = ron::to_string(&snapshot).unwrap();
+
+        let mut world = World::new();
+        world.insert_resource(Messages::<Telegram>::default());
+        // the agents' observers were spawned before anything else, and the
+        // restored world numbers its entities after as many
+        let observers = app
+            .world_mut()
+            .query::<&Observer>()
+            .iter(app.world())
+            .count();
+        for _ in 0..observers {
+            world.spawn_empty();
+        }
+        let snapshot: Snapshot = ron::from_str(&saved).unwrap();
+        snapshot.restore(&mut world).unwrap();
+        assert_eq!(
+            ron::to_string(&Snapshot::capture(&mut world)).unwrap(),
+            restored
+        );
+    }
+}
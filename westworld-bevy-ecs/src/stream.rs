@@ -0,0 +1,334 @@
+//! Streaming the town as it runs over a WebSocket, with `--serve=ADDRESS`, to
+//! watch it from somewhere else or in a browser. Whoever connects to
+//! `ws://ADDRESS/` is sent a frame of JSON every tick: the time, where the
+//! places in town are, every agent with where it is and what it's doing, and
+//! the tick's transitions and messages just as the event log writes them:
+//!
+//! ```text
+//! {"tick":1,"day":0,"hour":7,"clock":"day 1, 07:00","places":[{"location":"Goldmine","x":0.0,"y":0.0},...],
+//!  "agents":[{"name":"Miner Bob","location":"Shack","state":"EnterMineAndDigForNugget > TravelTo(Goldmine)","x":0.0,"y":0.0,"gold":0,...},...],
+//!  "events":[{"entity":"Miner Bob","event":"transition",...}]}
+//! ```
+//!
+//! Browsing to `http://ADDRESS/` gets a page that draws the frames as they
+//! come in. The town never waits for anyone watching: frames are made only
+//! while someone's connected, and whoever falls too far behind misses some.
+
+use crate::cli::Cli;
+use crate::clock::Clock;
+use crate::event_log;
+use crate::miner::{Miner, MinerState};
+use crate::partner::PartnerState;
+use crate::path_planner::{NavGraph, PathPlanner};
+use crate::replay::Record;
+use crate::riders::Mount;
+use crate::{bandit::BanditState, barfly::BarFlyState, sheriff::SheriffState};
+use crate::{Location, Name};
+use bevy_app::{App, FixedFirst, FixedLast, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use fsm::StateStack;
+use serde_json::{json, Value};
+use std::fmt::Debug;
+use std::mem;
+use tokio::sync::broadcast::Sender;
+
+/// The frames waiting to be sent to each client, beyond which the oldest are
+/// dropped.
+static FRAMES_KEPT: usize = 64;
+
+/// The frames being sent to whoever's connected, and the events to go in the
+/// next one.
+#[derive(Resource)]
+pub struct Stream {
+    frames: Sender<String>,
+    tick: u64,
+    events: Vec<Value>,
+}
+
+impl Stream {
+    pub fn new(frames: Sender<String>) -> Self {
+        Stream {
+            frames,
+            tick: 0,
+            events: Vec::new(),
+        }
+    }
+
+    /// The tick being run, counting from 1.
+    pub fn tick(&self) -> u64 {
+        self.tick
+    }
+
+    /// Keeps `record` for the end of the tick's frame.
+    pub fn record(&mut self, record: &Record) {
+        self.events.push(event_log::event(record));
+    }
+}
+
+pub fn count_ticks(mut stream: ResMut<Stream>) {
+    stream.tick += 1;
+}
+
+type Agents<'w, 's, 'a, S> = Query<
+    'w,
+    's,
+    (
+        Entity,
+        &'a Name,
+        &'a Location,
+        &'a StateStack<S>,
+        Option<&'a PathPlanner>,
+        Option<&'a Mount>,
+        Option<&'a Miner>,
+    ),
+>;
+
+/// Each agent in `agents` as it goes in a frame, with the entity to put them
+/// in turn.
+fn agents<S>(agents: &Agents<S>, graph: &NavGraph) -> Vec<(Entity, Value)>
+where
+    S: Clone + Debug + Send + Sync + 'static,
+{
+    agents
+        .iter()
+        .map(|(entity, name, location, stack, planner, mount, miner)| {
+            let position = match (mount, planner) {
+                (Some(mount), _) => Some(mount.position),
+                (None, Some(planner)) => Some(planner.position()),
+                (None, None) => graph.position_of(*location),
+            };
+            let mut agent = json!({
+                "name": name.to_string(),
+                "location": location,
+                "state": stack.to_string(),
+                "x": position.map(|position| position.x),
+                "y": position.map(|position| position.y),
+            });
+            if let Some(miner) = miner {
+                agent["gold"] = json!(miner.gold());
+                agent["wealth"] = json!(miner.wealth());
+                agent["thirst"] = json!(miner.thirst());
+                agent["fatigue"] = json!(miner.fatigue());
+                agent["hunger"] = json!(miner.hunger());
+            }
+            (entity, agent)
+        })
+        .collect()
+}
+
+/// Sends everyone connected the tick's frame.
+pub fn send_frame(
+    mut stream: ResMut<Stream>,
+    (clock, graph): (Res<Clock>, Res<NavGraph>),
+    miners: Agents<MinerState>,
+    (partners, barflies, bandits, sheriffs): (
+        Agents<PartnerState>,
+        Agents<BarFlyState>,
+        Agents<BanditState>,
+        Agents<SheriffState>,
+    ),
+) {
+    let events = mem::take(&mut stream.events);
+    if stream.frames.receiver_count() == 0 {
+        return;
+    }
+    let mut in_turn = agents(&miners, &graph);
+    in_turn.extend(agents(&partners, &graph));
+    in_turn.extend(agents(&barflies, &graph));
+    in_turn.extend(agents(&bandits, &graph));
+    in_turn.extend(agents(&sheriffs, &graph));
+    in_turn.sort_by_key(|(entity, _)| entity.index_u32());
+    let places: Vec<Value> = graph
+        .0
+        .nodes()
+        .filter_map(|node| {
+            node.extra_info.map(|location| {
+                json!({"location": location, "x": node.position.x, "y": node.position.y})
+            })
+        })
+        .collect();
+    let frame = json!({
+        "tick": stream.tick,
+        "day": clock.day(),
+        "hour": clock.hour(),
+        "clock": clock.to_string(),
+        "places": places,
+        "agents": in_turn.into_iter().map(|(_, agent)| agent).collect::<Vec<_>>(),
+        "events": events,
+    });
+    // nobody left to send it to is no matter
+    let _ = stream.frames.send(frame.to_string());
+}
+
+/// Streams the town to whoever connects to `--serve=ADDRESS`.
+pub struct StreamPlugin(pub String);
+
+impl StreamPlugin {
+    pub fn from_cli(cli: &Cli) -> Option<Self> {
+        cli.serve.clone().map(StreamPlugin)
+    }
+}
+
+impl Plugin for StreamPlugin {
+    fn build(&self, app: &mut App) {
+        let frames = server::serve(&self.0)
+            .unwrap_or_else(|e| panic!("could not serve on {}: {}", self.0, e));
+        info!(
+            "streaming the town to ws://{0}/, to watch at http://{0}/",
+            self.0
+        );
+        app.insert_resource(Stream::new(frames));
+        app.add_systems(FixedFirst, count_ticks);
+        app.add_systems(FixedLast, send_frame);
+    }
+}
+
+/// The server, on a thread of its own so the town's never kept waiting:
+/// WebSocket clients are sent the frames, and anyone else the viewer.
+mod server {
+    use super::FRAMES_KEPT;
+    use bevy_log::prelude::*;
+    use futures_util::SinkExt;
+    use std::{io, net, thread};
+    use tokio::io::{AsyncReadExt, AsyncWriteExt};
+    use tokio::net::{TcpListener, TcpStream};
+    use tokio::runtime;
+    use tokio::sync::broadcast::{self, error::RecvError, Receiver, Sender};
+    use tokio_tungstenite::tungstenite::Message;
+
+    static VIEWER: &str = include_str!("../viewer/index.html");
+
+    /// Starts listening on `address` in the background, returning where to
+    /// send the frames.
+    pub fn serve(address: &str) -> io::Result<Sender<String>> {
+        // bound here rather than on the thread, for a bad address to be
+        // reported straight away
+        let listener = net::TcpListener::bind(address)?;
+        listener.set_nonblocking(true)?;
+        let runtime = runtime::Builder::new_current_thread().enable_io().build()?;
+        let (frames, _) = broadcast::channel(FRAMES_KEPT);
+        let clients = frames.clone();
+        thread::spawn(move || runtime.block_on(accept(listener, clients)));
+        Ok(frames)
+    }
+
+    async fn accept(listener: net::TcpListener, frames: Sender<String>) {
+        let listener = match TcpListener::from_std(listener) {
+            Ok(listener) => listener,
+            Err(e) => {
+                error!("stream: {}", e);
+                return;
+            }
+        };
+        loop {
+            match listener.accept().await {
+                Ok((client, _)) => {
+                    tokio::spawn(connect(client, frames.subscribe()));
+                }
+                Err(e) => warn!("stream: {}", e),
+            }
+        }
+    }
+
+    /// Streams to `client` if it asked for a WebSocket, and sends it the
+    /// viewer if not.
+    async fn connect(client: TcpStream, frames: Receiver<String>) {
+        let mut head = [0; 1024];
+        let read = client.peek(&mut head).await.unwrap_or(0);
+        let head = String::from_utf8_lossy(&head[..read]).to_ascii_lowercase();
+        if head.contains("upgrade: websocket") {
+            send_frames(client, frames).await
+        } else {
+            send_viewer(client).await
+        }
+    }
+
+    async fn send_frames(client: TcpStream, mut frames: Receiver<String>) {
+        let mut socket = match tokio_tungstenite::accept_async(client).await {
+            Ok(socket) => socket,
+            Err(e) => {
+                warn!("stream: {}", e);
+                return;
+            }
+        };
+        loop {
+            match frames.recv().await {
+                Ok(frame) => {
+                    if socket.send(Message::text(frame)).await.is_err() {
+                        return;
+                    }
+                }
+                Err(RecvError::Lagged(missed)) => {
+                    debug!("stream: a client missed {} frames", missed)
+                }
+                Err(RecvError::Closed) => {
+                    let _ = socket.close(None).await;
+                    return;
+                }
+            }
+        }
+    }
+
+    async fn send_viewer(mut client: TcpStream) {
+        // the request doesn't matter, only that it's been sent
+        let _ = client.read(&mut [0; 1024]).await;
+        let response = format!(
+            "HTTP/1.0 200 OK\r\nContent-Type: text/html; charset=utf-8\r\nContent-Length: {}\r\n\r\n{}",
+            VIEWER.len(),
+            VIEWER
+        );
+        let _ = client.write_all(response.as_bytes()).await;
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::population::Population;
+    use crate::scenario::Scenario;
+    use crate::testing::{build_test_app, tick};
+    use tokio::sync::broadcast;
+
+    #[test]
+    fn every_tick_is_sent_with_the_agents_and_what_they_did() {
+        let mut app = build_test_app(
+            Scenario::populated(&Population {
+                miners: 1,
+                partners: 1,
+            }),
+            7,
+        );
+        let (frames, mut client) = broadcast::channel(FRAMES_KEPT);
+        app.insert_resource(Stream::new(frames))
+            .add_systems(FixedFirst, count_ticks)
+            .add_systems(FixedLast, send_frame);
+        tick(&mut app, 2);
+
+        let frames: Vec<Value> = (0..2)
+            .map(|_| serde_json::from_str(&client.try_recv().unwrap()).unwrap())
+            .collect();
+        assert_eq!(frames[0]["tick"], 1);
+        assert_eq!(frames[1]["tick"], 2);
+        let agents = frames[0]["agents"].as_array().unwrap();
+        let names: Vec<&str> = agents
+            .iter()
+            .map(|agent| agent["name"].as_str().unwrap())
+            .collect();
+        assert_eq!(
+            names,
+            [
+                "Miner Bob",
+                "Elsa",
+                "Barfly Slim",
+                "Black Bart",
+                "Sheriff Wyatt"
+            ]
+        );
+        assert!(agents[0]["gold"].is_number());
+        assert!(agents[1]["x"].is_number());
+        assert_eq!(frames[0]["events"][0]["event"], "transition");
+        assert_eq!(frames[0]["events"][0]["entity"], "Miner Bob");
+        assert!(!frames[0]["places"].as_array().unwrap().is_empty());
+    }
+}
@@ -0,0 +1,58 @@
+//! The whole world headless, for tests to run: no logging, no window and no
+//! runner, just [`WorldPlugins`] ticked by hand, one tick an update.
+
+use crate::rng::Seed;
+use crate::scenario::Scenario;
+use crate::WorldPlugins;
+use bevy_app::{App, PluginsState};
+
+/// The town `scenario` describes, seeded with `seed`, ready to [`tick`] once
+/// any more plugins the test wants are added.
+pub fn build_test_app(scenario: Scenario, seed: u64) -> App {
+    let mut app = App::new();
+    app.insert_resource(scenario)
+        .insert_resource(Seed(seed))
+        .add_plugins(WorldPlugins);
+    app
+}
+
+/// Runs `ticks` ticks of the world, finishing setting it up first if it
+/// hasn't ticked yet.
+pub fn tick(app: &mut App, ticks: usize) {
+    if app.plugins_state() != PluginsState::Cleaned {
+        app.finish();
+        app.cleanup();
+    }
+    for _ in 0..ticks {
+        app.update();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::clock::Clock;
+    use crate::goldmine::Goldmine;
+    use crate::population::Population;
+
+    #[test]
+    fn a_day_in_town() {
+        let mut app = build_test_app(
+            Scenario::populated(&Population {
+                miners: 1,
+                partners: 1,
+            }),
+            7,
+        );
+        tick(&mut app, 24);
+
+        let clock = app.world().resource::<Clock>();
+        assert_eq!((clock.day(), clock.hour()), (1, 6));
+        let mine = app
+            .world_mut()
+            .query::<&Goldmine>()
+            .single(app.world())
+            .unwrap();
+        assert!(mine.mined() > 0, "nothing dug all day");
+    }
+}
@@ -1,44 +1,89 @@
-use bevy_app::Plugin;
-use bevy_ecs::prelude::*;
-use wheel_timer::WheelTimer;
+//! A hashed timing wheel: events are scheduled a number of ticks ahead and
+//! handed back on the tick they fall due, at a constant cost per tick.
 
-static MAX_INTERVAL: usize = 20;
+use bevy_ecs::entity::{EntityMapper, MapEntities};
+use bevy_ecs::prelude::Resource;
+use serde::{Deserialize, Serialize};
 
-#[derive(Clone, Debug)]
-pub enum Event {
-    Message,
-}
+pub static MAX_INTERVAL: usize = 20; // ticks ahead an event can be scheduled
 
-pub struct Timer {
-    wheeltimer: WheelTimer<Event>,
+#[derive(Resource, Clone, Debug, Serialize, Deserialize)]
+pub struct Timer<T> {
+    slots: Vec<Vec<T>>,
+    current: usize,
 }
 
-impl Timer {
+impl<T> Timer<T> {
     pub fn new() -> Self {
+        Timer::with_max_interval(MAX_INTERVAL)
+    }
+
+    pub fn with_max_interval(max_interval: usize) -> Self {
         Timer {
-            wheeltimer: WheelTimer::new(MAX_INTERVAL),
+            slots: (0..max_interval).map(|_| vec![]).collect(),
+            current: 0,
         }
     }
-    pub fn tick(&mut self) -> Vec<Event> {
-        self.wheeltimer.tick()
+
+    /// Advances one tick and returns the events that fell due.
+    pub fn tick(&mut self) -> Vec<T> {
+        self.current = (self.current + 1) % self.slots.len();
+        std::mem::take(&mut self.slots[self.current])
+    }
+
+    /// Schedules an event `delay` ticks from now. A delay of zero fires on
+    /// the next tick, the same as a delay of one.
+    ///
+    /// Panics if the delay is not less than the maximum interval.
+    pub fn schedule(&mut self, delay: usize, event: T) {
+        assert!(
+            delay < self.slots.len(),
+            "can't schedule more than {} ticks ahead",
+            self.slots.len() - 1
+        );
+        let slot = (self.current + delay.max(1)) % self.slots.len();
+        self.slots[slot].push(event);
     }
+}
 
-    pub fn schedule(&mut self, delay: usize, event: Event) {
-        self.wheeltimer.schedule(delay, event);
+impl<T: MapEntities> MapEntities for Timer<T> {
+    fn map_entities<E: EntityMapper>(&mut self, entity_mapper: &mut E) {
+        for event in self.slots.iter_mut().flatten() {
+            event.map_entities(entity_mapper);
+        }
     }
 }
 
-fn timer(mut wheeltimer: ResMut<Timer>) {
-    for event in wheeltimer.tick() {
-        println!("timer event: {:?}", event);
+impl<T> Default for Timer<T> {
+    fn default() -> Self {
+        Timer::new()
     }
 }
 
-pub struct TimerPlugin;
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn events_fire_after_their_delay() {
+        let mut timer = Timer::with_max_interval(4);
+        timer.schedule(2, "stew");
+        timer.schedule(0, "now");
+
+        assert_eq!(timer.tick(), vec!["now"]);
+        assert_eq!(timer.tick(), vec!["stew"]);
+
+        // wrapping round the wheel
+        timer.schedule(3, "interest");
+        assert!(timer.tick().is_empty());
+        assert!(timer.tick().is_empty());
+        assert_eq!(timer.tick(), vec!["interest"]);
+        assert!(timer.tick().is_empty());
+    }
 
-impl Plugin for TimerPlugin {
-    fn build(&self, app: &mut bevy_app::AppBuilder) {
-        app.insert_resource(Timer::new());
-        app.add_system(timer.system());
+    #[test]
+    #[should_panic]
+    fn delay_beyond_the_wheel() {
+        Timer::with_max_interval(4).schedule(4, ());
     }
 }
@@ -0,0 +1,293 @@
+//! Running the simulation on bevy's fixed timestep. The agents update in
+//! [`FixedMain`](bevy_app::FixedMain), once per tick of [`Pace::interval`],
+//! however often the app itself updates, each tick going through the
+//! [`Phase`]s in turn; the dashboard and the keyboard controls keep to the
+//! regular update, once a frame. The run's over after `--ticks=N` ticks, or
+//! once every agent's state machine has stopped.
+//!
+//! The [`Speed`] can be changed while it runs, which changes how many ticks
+//! fall due a second rather than how long a tick is, so the agents do the
+//! same whatever the speed.
+
+use crate::controls::Pace;
+use crate::current_state::Running;
+use crate::{Location, Phase};
+use bevy_app::{App, AppExit, First, FixedLast, FixedUpdate, Plugin};
+use bevy_ecs::prelude::*;
+use bevy_ecs::schedule::ExecutorKind;
+use bevy_log::prelude::*;
+use bevy_time::{Fixed, Time, TimePlugin, TimeSystems, TimeUpdateStrategy, Virtual};
+use serde::{Deserialize, Serialize};
+use std::fmt;
+use std::str::FromStr;
+
+/// Ticks in real time if there's a [`Pace`] that isn't `--fast`, and
+/// otherwise exactly one tick per update, as batch runs and tests want.
+pub struct TimestepPlugin;
+
+impl Plugin for TimestepPlugin {
+    fn build(&self, app: &mut App) {
+        app.add_plugins(TimePlugin);
+        let pace = app.world().get_resource::<Pace>().cloned();
+        let interval = pace
+            .as_ref()
+            .map_or(Pace::default().interval, |pace| pace.interval);
+        // the first tick's due straight away, rather than a tick's time after
+        // the first update
+        let mut fixed = Time::<Fixed>::from_duration(interval);
+        fixed.accumulate_overstep(interval);
+        app.insert_resource(fixed);
+        // a tick longer than the catch-up limit would never come round
+        let mut time = app.world_mut().resource_mut::<Time<Virtual>>();
+        let max_delta = time.max_delta().max(interval);
+        time.set_max_delta(max_delta);
+        if pace.is_none_or(|pace| pace.fast) {
+            app.insert_resource(TimeUpdateStrategy::FixedTimesteps(1));
+        } else {
+            app.init_resource::<Speed>();
+            app.add_systems(First, change_speed.before(TimeSystems));
+            app.add_systems(First, run_flat_out.after(TimeSystems));
+        }
+        app.configure_sets(
+            FixedUpdate,
+            (
+                Phase::Perceive,
+                Phase::Decide,
+                Phase::Act,
+                Phase::Dispatch,
+                Phase::Log,
+            )
+                .chain(),
+        );
+        app.add_systems(FixedLast, (stop_after_ticks, stop_when_everyone_has));
+    }
+
+    // the systems run one after another, in the same order every tick, for
+    // a seeded run to go the same way every time; it's only the agents of a
+    // kind whose updates are made to that which run across threads
+    fn cleanup(&self, app: &mut App) {
+        let mut schedules = app.world_mut().resource_mut::<Schedules>();
+        for (_, schedule) in schedules.iter_mut() {
+            schedule.set_executor_kind(ExecutorKind::SingleThreaded);
+        }
+    }
+}
+
+/// How many times faster than `--tick-ms` the ticks come, while running in
+/// real time, changed from the keyboard or with `--control`.
+#[derive(Resource, Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Speed {
+    #[serde(rename = "0.25x")]
+    Quarter,
+    #[default]
+    #[serde(rename = "1x")]
+    Normal,
+    #[serde(rename = "10x")]
+    Tenfold,
+    /// A tick every frame, with no waiting in between, as `--fast` runs.
+    #[serde(rename = "max")]
+    Max,
+}
+
+impl Speed {
+    /// How fast the virtual clock runs, against the real one.
+    fn relative(self) -> f64 {
+        match self {
+            Speed::Quarter => 0.25,
+            Speed::Normal | Speed::Max => 1.0,
+            Speed::Tenfold => 10.0,
+        }
+    }
+}
+
+impl fmt::Display for Speed {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let speed = match self {
+            Speed::Quarter => "0.25x",
+            Speed::Normal => "1x",
+            Speed::Tenfold => "10x",
+            Speed::Max => "max",
+        };
+        write!(f, "{}", speed)
+    }
+}
+
+impl FromStr for Speed {
+    type Err = String;
+
+    fn from_str(s: &str) -> Result<Self, Self::Err> {
+        match s.trim() {
+            "0.25x" => Ok(Speed::Quarter),
+            "1x" => Ok(Speed::Normal),
+            "10x" => Ok(Speed::Tenfold),
+            "max" => Ok(Speed::Max),
+            _ => Err(format!("no speed called {:?}: 0.25x, 1x, 10x or max", s)),
+        }
+    }
+}
+
+/// Runs the virtual clock faster or slower once the speed's changed, so more
+/// or fewer ticks fall due each frame, each as long as ever.
+pub fn change_speed(speed: Res<Speed>, mut time: ResMut<Time<Virtual>>) {
+    if speed.is_changed() {
+        time.set_relative_speed_f64(speed.relative());
+        if !speed.is_added() {
+            info!("running at {}", *speed);
+        }
+    }
+}
+
+/// Makes a tick due every frame at full speed, however little time's gone by
+/// since the last, as stepping does.
+pub fn run_flat_out(speed: Res<Speed>, time: Res<Time<Virtual>>, mut fixed: ResMut<Time<Fixed>>) {
+    if *speed == Speed::Max && !time.is_paused() && fixed.overstep() < fixed.timestep() {
+        let timestep = fixed.timestep();
+        fixed.accumulate_overstep(timestep);
+    }
+}
+
+/// Asks the app to exit once `--ticks=N` ticks have run, dropping any more
+/// that were due this frame.
+pub fn stop_after_ticks(
+    pace: Option<Res<Pace>>,
+    mut ticks: Local<u64>,
+    mut fixed: ResMut<Time<Fixed>>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    *ticks += 1;
+    if pace.is_some_and(|pace| pace.finished(*ticks)) {
+        let overstep = fixed.overstep();
+        fixed.discard_overstep(overstep);
+        exit.write(AppExit::Success);
+    }
+}
+
+/// Asks the app to exit once there are agents and none of them has a state
+/// left, as the examples without bevy stop once their state machines have.
+pub fn stop_when_everyone_has(
+    agents: Query<(), With<Location>>,
+    running: Query<(), Running>,
+    mut exit: MessageWriter<AppExit>,
+) {
+    if !agents.is_empty() && running.is_empty() {
+        info!("everyone's stopped");
+        exit.write(AppExit::Success);
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::current_state::CurrentState;
+    use crate::miner::MinerState;
+    use crate::rng::Seed;
+    use crate::WorldPlugins;
+    use bevy_ecs::schedule::{LogLevel, ScheduleBuildSettings};
+    use std::time::Duration;
+
+    #[derive(Resource, Default)]
+    struct Ticks(u64);
+
+    fn count(mut ticks: ResMut<Ticks>) {
+        ticks.0 += 1;
+    }
+
+    #[test]
+    fn one_tick_per_update_when_fast() {
+        let mut app = App::new();
+        app.insert_resource(Pace {
+            interval: Duration::from_secs(1),
+            ticks: Some(3),
+            fast: true,
+            ..Pace::default()
+        })
+        .init_resource::<Ticks>()
+        .add_plugins(TimestepPlugin)
+        .add_systems(FixedUpdate, count);
+
+        for _ in 0..2 {
+            app.update();
+        }
+        assert_eq!(app.world().resource::<Ticks>().0, 2);
+        assert!(app.should_exit().is_none());
+
+        app.update();
+        assert_eq!(app.world().resource::<Ticks>().0, 3);
+        assert!(app.should_exit().is_some());
+    }
+
+    #[test]
+    fn the_speed_changes_how_many_ticks_fall_due() {
+        let mut app = App::new();
+        app.insert_resource(Pace {
+            interval: Duration::from_millis(100),
+            ..Pace::default()
+        })
+        .init_resource::<Ticks>()
+        .add_plugins(TimestepPlugin)
+        .insert_resource(TimeUpdateStrategy::ManualDuration(Duration::from_millis(
+            100,
+        )))
+        .add_systems(FixedUpdate, count);
+        let ticks = |app: &mut App, updates| {
+            let before = app.world().resource::<Ticks>().0;
+            for _ in 0..updates {
+                app.update();
+            }
+            app.world().resource::<Ticks>().0 - before
+        };
+        ticks(&mut app, 1);
+        assert_eq!(ticks(&mut app, 4), 4);
+
+        app.insert_resource(Speed::Quarter);
+        assert_eq!(ticks(&mut app, 4), 1);
+        app.insert_resource(Speed::Tenfold);
+        assert_eq!(ticks(&mut app, 2), 20);
+
+        // however little time goes by
+        app.insert_resource(TimeUpdateStrategy::ManualDuration(Duration::ZERO));
+        app.insert_resource(Speed::Normal);
+        assert_eq!(ticks(&mut app, 3), 0);
+        app.insert_resource(Speed::Max);
+        assert_eq!(ticks(&mut app, 3), 3);
+    }
+
+    #[test]
+    fn exits_once_every_agent_has_stopped() {
+        let mut app = App::new();
+        app.add_plugins(TimestepPlugin);
+        let bob = app
+            .world_mut()
+            .spawn((
+                Location::Shack,
+                CurrentState(MinerState::GoHomeAndSleepTilRested),
+            ))
+            .id();
+        app.world_mut().spawn(Location::Shack);
+        app.update();
+        assert!(app.should_exit().is_none());
+
+        app.world_mut()
+            .entity_mut(bob)
+            .remove::<CurrentState<MinerState>>();
+        app.update();
+        assert!(app.should_exit().is_some());
+    }
+
+    #[test]
+    fn every_tick_runs_in_the_same_order() {
+        let mut app = App::new();
+        app.insert_resource(Seed(7)).add_plugins(WorldPlugins);
+        // any two systems touching the same data without an order between
+        // them fail the schedule
+        app.edit_schedule(FixedUpdate, |schedule| {
+            schedule.set_build_settings(ScheduleBuildSettings {
+                ambiguity_detection: LogLevel::Error,
+                ..Default::default()
+            });
+        });
+        app.finish();
+        app.cleanup();
+        app.update();
+    }
+}
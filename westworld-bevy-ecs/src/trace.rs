@@ -0,0 +1,244 @@
+//! Each agent's states as tracing spans, open for as long as they're on its
+//! stack and entered whenever it runs, with the transitions it makes and the
+//! messages it's handed as events inside them; and `--trace=FILE`, writing
+//! them out as a Chrome trace.
+//!
+//! The spans follow the stacks rather than the handlers, like [`Watch`]
+//! does, so every kind of agent is traced without its states knowing. They're
+//! at debug level, so there's nothing to them unless something's listening.
+//!
+//! [`Watch`]: crate::replay::Watch
+
+use crate::messaging::Telegram;
+use crate::Name;
+use bevy_ecs::prelude::*;
+use fsm::StateStack;
+use game_ai::trace::STATE;
+use std::collections::HashMap;
+use std::fmt::Debug;
+use tracing::{debug, debug_span, Level, Span};
+
+/// Where the spans and events come from, to let them through the log's filter.
+const TARGET: &str = module_path!();
+
+/// The spans of each agent's states, from the bottom of its stack up, with
+/// the state each is for.
+#[derive(Resource, Default)]
+pub struct StateSpans(HashMap<Entity, Vec<(String, Span)>>);
+
+/// The agents' state spans, if they're being kept.
+pub type Spans<'a> = Option<ResMut<'a, StateSpans>>;
+
+/// Runs `run` on the agent's states inside the span of the one on top, if
+/// spans are being kept.
+pub fn traced<S: Clone + Debug, R>(
+    spans: &mut Spans,
+    agent: Entity,
+    name: &Name,
+    states: &mut StateStack<S>,
+    run: impl FnOnce(&mut StateStack<S>) -> R,
+) -> R {
+    match spans {
+        Some(spans) => spans.run(agent, name, states, run),
+        None => run(states),
+    }
+}
+
+/// Whether the agents' state spans are being kept, and so have to be
+/// entered by one agent at a time.
+pub fn kept(spans: &Spans) -> bool {
+    spans.is_some() && tracing::enabled!(Level::DEBUG)
+}
+
+impl StateSpans {
+    /// Runs `run` on the agent's states inside the span of the one on top,
+    /// opening and closing spans either side to match the stack.
+    fn run<S: Clone + Debug, R>(
+        &mut self,
+        agent: Entity,
+        name: &Name,
+        states: &mut StateStack<S>,
+        run: impl FnOnce(&mut StateStack<S>) -> R,
+    ) -> R {
+        if !tracing::enabled!(Level::DEBUG) {
+            return run(states);
+        }
+        self.sync(agent, name, states);
+        let result = self.running(agent).in_scope(|| run(states));
+        self.sync(agent, name, states);
+        result
+    }
+
+    /// The span of the state on top of the agent's stack.
+    fn running(&self, agent: Entity) -> Span {
+        self.0
+            .get(&agent)
+            .and_then(|spans| spans.last())
+            .map_or_else(Span::none, |(_, span)| span.clone())
+    }
+
+    /// Closes the spans of the states that have gone from the agent's stack,
+    /// from the top down, and opens them for the ones that have come, each
+    /// inside the one beneath it. Any change is a transition, made in the
+    /// span of the state that was on top.
+    fn sync<S: Clone + Debug>(&mut self, agent: Entity, name: &Name, states: &StateStack<S>) {
+        let states: Vec<String> = states.iter().map(|state| format!("{:?}", state)).collect();
+        let spans = self.0.entry(agent).or_default();
+        let kept = spans
+            .iter()
+            .zip(&states)
+            .take_while(|((was, _), is)| was == *is)
+            .count();
+        if kept == spans.len() && kept == states.len() {
+            return;
+        }
+
+        if let Some((_, span)) = spans.last() {
+            let from: Vec<_> = spans.iter().map(|(state, _)| state.as_str()).collect();
+            debug!(
+                parent: span,
+                from = %from.join(" > "),
+                to = %states.join(" > "),
+                "transition"
+            );
+        }
+        while spans.len() > kept {
+            spans.pop();
+        }
+        for state in &states[kept..] {
+            let beneath = spans.last().and_then(|(_, span)| span.id());
+            let span = debug_span!(parent: beneath, STATE, agent = %name, state = %state);
+            spans.push((state.clone(), span));
+        }
+        if spans.is_empty() {
+            self.0.remove(&agent);
+        }
+    }
+}
+
+/// Notes a message being handed to an agent, in the span of its running
+/// state.
+pub fn handed(telegram: &Telegram) {
+    debug!(sender = ?telegram.sender, message = ?telegram.message, "message");
+}
+
+#[cfg(not(target_arch = "wasm32"))]
+pub use writer::write_to;
+
+/// `--trace=FILE`, natively; there's no file to write to in a browser.
+#[cfg(not(target_arch = "wasm32"))]
+mod writer {
+    use bevy_app::App;
+    use bevy_ecs::prelude::*;
+    use bevy_log::LogPlugin;
+    use game_ai::trace::{self, FlushGuard};
+    use std::path::PathBuf;
+
+    /// Where the trace's written.
+    #[derive(Resource)]
+    struct TraceFile(PathBuf);
+
+    /// Finishes the trace when the app's dropped.
+    struct Flush {
+        _guard: FlushGuard,
+    }
+
+    /// Has `log` write the agents' states to the trace at `path` as well,
+    /// letting their spans through.
+    pub fn write_to(app: &mut App, log: LogPlugin, path: PathBuf) -> LogPlugin {
+        app.insert_resource(TraceFile(path));
+        LogPlugin {
+            filter: format!("{},{}=debug", log.filter, super::TARGET),
+            custom_layer: |app| {
+                let path = app.world().resource::<TraceFile>().0.clone();
+                let (layer, guard) = trace::chrome_layer(&path);
+                app.insert_non_send_resource(Flush { _guard: guard });
+                Some(Box::new(layer))
+            },
+            ..log
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use bevy_log::tracing_subscriber::layer::{Context, Layer};
+    use bevy_log::tracing_subscriber::prelude::*;
+    use bevy_log::tracing_subscriber::registry::LookupSpan;
+    use std::sync::{Arc, Mutex};
+    use tracing::span::{Attributes, Id};
+    use tracing::Subscriber;
+
+    /// Notes each span opening and closing, with the state it's for.
+    #[derive(Clone, Default)]
+    struct Spans(Arc<Mutex<Vec<String>>>);
+
+    #[derive(Clone, Debug)]
+    enum Miner {
+        Dig,
+        Drink,
+        Sleep,
+    }
+
+    #[derive(Default)]
+    struct State(String);
+
+    impl tracing::field::Visit for State {
+        fn record_debug(&mut self, field: &tracing::field::Field, value: &dyn Debug) {
+            if field.name() == "state" {
+                self.0 = format!("{:?}", value);
+            }
+        }
+    }
+
+    impl<S: Subscriber + for<'a> LookupSpan<'a>> Layer<S> for Spans {
+        fn on_new_span(&self, attrs: &Attributes<'_>, id: &Id, ctx: Context<'_, S>) {
+            let mut state = State::default();
+            attrs.record(&mut state);
+            self.0.lock().unwrap().push(format!("open {}", state.0));
+            ctx.span(id).unwrap().extensions_mut().insert(state);
+        }
+
+        fn on_close(&self, id: Id, ctx: Context<'_, S>) {
+            let span = ctx.span(&id).unwrap();
+            let state = span.extensions().get::<State>().unwrap().0.clone();
+            self.0.lock().unwrap().push(format!("close {}", state));
+        }
+    }
+
+    #[test]
+    fn spans_follow_the_stack() {
+        let seen = Spans::default();
+        let subscriber = bevy_log::tracing_subscriber::registry().with(seen.clone());
+        tracing::subscriber::with_default(subscriber, || {
+            let mut spans = StateSpans::default();
+            let agent = Entity::from_raw_u32(1).unwrap();
+            let name = Name("Miner Bob".to_string());
+            let mut states = StateStack::new_initial_state(Miner::Dig);
+            spans.run(agent, &name, &mut states, |states| {
+                states.push(Miner::Drink)
+            });
+            spans.run(agent, &name, &mut states, |states| {
+                states.pop();
+                states.pop();
+                states.push(Miner::Sleep);
+            });
+            spans.run(agent, &name, &mut states, |states| {
+                states.pop();
+            });
+            assert!(spans.0.is_empty());
+        });
+        assert_eq!(
+            *seen.0.lock().unwrap(),
+            [
+                "open Dig",
+                "open Drink",
+                "close Drink",
+                "close Dig",
+                "open Sleep",
+                "close Sleep"
+            ]
+        );
+    }
+}
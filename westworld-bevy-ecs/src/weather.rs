@@ -0,0 +1,216 @@
+//! The weather over Westworld, for agents to take into account alongside
+//! their own needs: a heatwave makes the miners thirstier, and rain keeps them
+//! at home or, if they're out, sends them the long way round.
+
+use crate::clock::Clock;
+use crate::miner::{Miner, UPDATE_MINERS};
+use crate::rng::WorldRng;
+use crate::roads::Roads;
+use crate::timer::Timer;
+use crate::{Label, Location, Phase};
+use bevy_app::{App, FixedUpdate, Plugin, Startup};
+use bevy_ecs::prelude::*;
+use bevy_log::prelude::*;
+use rand::distributions::{Distribution, Standard};
+use rand::Rng;
+use serde::{Deserialize, Serialize};
+use std::fmt;
+
+/// Label of the system changing the weather.
+pub static CHANGE_WEATHER: Label = Label("change_weather");
+
+#[derive(Resource, Copy, Clone, Debug, Default, PartialEq, Serialize, Deserialize)]
+pub enum Weather {
+    #[default]
+    Sunny,
+    Rain,
+    Heatwave,
+}
+
+impl fmt::Display for Weather {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match self {
+            Weather::Sunny => write!(f, "sunny"),
+            Weather::Rain => write!(f, "raining"),
+            Weather::Heatwave => write!(f, "heatwave"),
+        }
+    }
+}
+
+/// Mostly sunny, with the odd spell of rain or heat.
+impl Distribution<Weather> for Standard {
+    fn sample<R: rand::Rng + ?Sized>(&self, rng: &mut R) -> Weather {
+        match rng.gen_range(0..4) {
+            0 | 1 => Weather::Sunny,
+            2 => Weather::Rain,
+            3 => Weather::Heatwave,
+            _ => unreachable!(),
+        }
+    }
+}
+
+/// Roads that wash out while it rains.
+pub static WASHED_OUT_IN_RAIN: &[(Location, Location)] = &[(Location::Shack, Location::Goldmine)];
+
+/// How long the weather holds. Insert your own before adding
+/// [`WeatherPlugin`] to change it.
+#[derive(Resource, Clone, Debug)]
+pub struct Forecast {
+    /// Fewest updates before the weather can change.
+    pub shortest_spell: usize,
+    /// Most updates before the weather changes.
+    pub longest_spell: usize,
+}
+
+impl Default for Forecast {
+    fn default() -> Self {
+        Forecast {
+            shortest_spell: 12,
+            longest_spell: 48,
+        }
+    }
+}
+
+#[derive(Copy, Clone, Debug, PartialEq, Serialize, Deserialize)]
+pub enum WeatherEvent {
+    Change,
+}
+
+pub type WeatherTimer = Timer<WeatherEvent>;
+
+/// The first spell lasts as long as it can be relied on to, so that startup
+/// doesn't draw a random number.
+pub fn start_weather(mut commands: Commands, forecast: Res<Forecast>) {
+    let mut timer = WeatherTimer::with_max_interval(forecast.longest_spell + 1);
+    timer.schedule(forecast.shortest_spell, WeatherEvent::Change);
+    commands.insert_resource(timer);
+}
+
+pub fn change_weather(
+    forecast: Res<Forecast>,
+    mut rng: ResMut<WorldRng>,
+    mut timer: ResMut<WeatherTimer>,
+    mut weather: ResMut<Weather>,
+) {
+    for event in timer.tick() {
+        match event {
+            WeatherEvent::Change => {
+                let next = rng.gen();
+                if next != *weather {
+                    match next {
+                        Weather::Sunny => info!("The sun's come out"),
+                        Weather::Rain => info!("It's started to rain"),
+                        Weather::Heatwave => info!("A heatwave's rolled in"),
+                    }
+                    *weather = next;
+                }
+                let spell = rng.gen_range(forecast.shortest_spell..=forecast.longest_spell);
+                timer.schedule(spell, WeatherEvent::Change);
+            }
+        }
+    }
+}
+
+/// The heat makes every miner thirstier, whatever he's doing.
+pub fn parch_miners(weather: Res<Weather>, clock: Res<Clock>, mut miners: Query<&mut Miner>) {
+    if *weather == Weather::Heatwave {
+        for mut miner in miners.iter_mut() {
+            miner.increase_thirst(&clock);
+        }
+    }
+}
+
+/// Closes the roads the rain washes out for as long as it lasts. Done every
+/// update rather than on a change, so that the roads follow a restored weather
+/// too.
+pub fn wash_out_roads(weather: Res<Weather>, mut roads: ResMut<Roads>) {
+    for &(from, to) in WASHED_OUT_IN_RAIN {
+        if *weather == Weather::Rain {
+            if roads.close(from, to) {
+                info!("The road from the {:?} to the {:?}'s washed out", from, to);
+            }
+        } else if roads.open(from, to) {
+            info!("The road from the {:?} to the {:?}'s open again", from, to);
+        }
+    }
+}
+
+pub struct WeatherPlugin;
+
+impl Plugin for WeatherPlugin {
+    fn build(&self, app: &mut App) {
+        app.init_resource::<Forecast>();
+        app.init_resource::<Weather>();
+        app.add_systems(Startup, start_weather);
+        // the miners decide what to do in the weather as it is now, and the
+        // partners draw their random numbers after it
+        app.add_systems(
+            FixedUpdate,
+            change_weather
+                .in_set(CHANGE_WEATHER)
+                .in_set(Phase::Decide)
+                .before(UPDATE_MINERS),
+        );
+        app.add_systems(
+            FixedUpdate,
+            wash_out_roads
+                .in_set(Phase::Decide)
+                .after(CHANGE_WEATHER)
+                .before(UPDATE_MINERS),
+        );
+        app.add_systems(
+            FixedUpdate,
+            parch_miners
+                .in_set(Phase::Decide)
+                .after(CHANGE_WEATHER)
+                .before(UPDATE_MINERS),
+        );
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn only_a_heatwave_parches_the_miners() {
+        let mut world = World::new();
+        let miner = world.spawn_empty().insert(Miner::new()).id();
+        world.init_resource::<Clock>();
+        let mut schedule = Schedule::default();
+        schedule.add_systems(parch_miners);
+
+        world.insert_resource(Weather::Rain);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Miner>(miner).unwrap().thirst(), 0);
+
+        world.insert_resource(Weather::Heatwave);
+        schedule.run(&mut world);
+        assert_eq!(world.get::<Miner>(miner).unwrap().thirst(), 1);
+    }
+
+    #[test]
+    fn rain_closes_the_washed_out_roads_until_it_stops() {
+        let mut world = World::new();
+        world.insert_resource(Roads::town());
+        let mut schedule = Schedule::default();
+        schedule.add_systems(wash_out_roads);
+
+        world.insert_resource(Weather::Rain);
+        schedule.run(&mut world);
+        let roads = world.get_resource::<Roads>().unwrap();
+        assert_eq!(
+            roads.walking_time(Location::Shack, Location::Goldmine),
+            None
+        );
+        assert_eq!(roads.walking_time(Location::Shack, Location::Bank), Some(1));
+
+        world.insert_resource(Weather::Sunny);
+        schedule.run(&mut world);
+        let roads = world.get_resource::<Roads>().unwrap();
+        assert_eq!(
+            roads.walking_time(Location::Shack, Location::Goldmine),
+            Some(2)
+        );
+    }
+}
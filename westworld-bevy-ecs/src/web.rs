@@ -0,0 +1,242 @@
+//! Running the example in a browser, built for `wasm32-unknown-unknown`: the
+//! town's drawn on a `<canvas id="westworld">` every frame, and whatever the
+//! agents say is written out beneath it in a `<pre id="westworld-log">`,
+//! either of which the page is given if it hasn't one of its own. There's no
+//! command line, so it's the usual town, on a random seed, in real time.
+
+use crate::clock::Clock;
+use crate::controls::Pace;
+use crate::path_planner::{NavGraph, PathPlanner};
+use crate::riders::Mount;
+use crate::rng::Seed;
+use crate::scenario::Scenario;
+use crate::{Location, Name, Settings, WorldPlugins};
+use bevy_app::{App, AppExit, Plugin, Update};
+use bevy_ecs::prelude::*;
+use bevy_log::tracing::field::{Field, Visit};
+use bevy_log::tracing::{Event, Subscriber};
+use bevy_log::tracing_subscriber::layer::{Context, Layer};
+use bevy_log::{BoxedLayer, LogPlugin};
+use game_ai::Vector2D;
+use std::cell::RefCell;
+use std::fmt::{self, Write};
+use std::rc::Rc;
+use wasm_bindgen::prelude::*;
+use wasm_bindgen::JsCast;
+use web_sys::{CanvasRenderingContext2d, Document, Element, HtmlCanvasElement};
+
+static CANVAS: &str = "westworld";
+static LOG: &str = "westworld-log";
+static LOG_LINES: u32 = 200; // lines kept under the canvas, the oldest dropped first
+static SCALE: f64 = 6.0; // canvas pixels to a unit of the map
+static MAP_CORNERS: (Vector2D, Vector2D) = (
+    Vector2D { x: -30.0, y: -30.0 },
+    Vector2D { x: 60.0, y: 70.0 },
+);
+
+pub fn run() {
+    console_error_panic_hook::set_once();
+    let mut app = App::new();
+    app.set_runner(runner);
+    app.insert_resource(Scenario::default())
+        .insert_resource(Settings::default())
+        .insert_resource(Seed::default())
+        .insert_resource(Pace::default())
+        .add_plugins(LogPlugin {
+            custom_layer: screen_log,
+            ..Default::default()
+        })
+        .add_plugins(WorldPlugins)
+        .add_plugins(WebPlugin);
+    app.run();
+}
+
+/// What the browser calls back for another frame.
+type Frame = Closure<dyn FnMut()>;
+
+/// Updates the app each time the browser's ready for another frame. The
+/// browser calls back rather than being waited on, so this hands the app over
+/// and returns straight away.
+fn runner(mut app: App) -> AppExit {
+    app.finish();
+    app.cleanup();
+    let frame: Rc<RefCell<Option<Frame>>> = Rc::new(RefCell::new(None));
+    let next = Rc::clone(&frame);
+    *frame.borrow_mut() = Some(Closure::new(move || {
+        app.update();
+        if app.should_exit().is_none() {
+            request_animation_frame(next.borrow().as_ref().unwrap());
+        }
+    }));
+    request_animation_frame(frame.borrow().as_ref().unwrap());
+    AppExit::Success
+}
+
+fn request_animation_frame(callback: &Frame) {
+    web_sys::window()
+        .expect("no window")
+        .request_animation_frame(callback.as_ref().unchecked_ref())
+        .expect("couldn't ask for the next frame");
+}
+
+fn document() -> Document {
+    web_sys::window()
+        .and_then(|window| window.document())
+        .expect("no document")
+}
+
+/// The element with `id`, added to the end of the page as a `tag` if it
+/// isn't there yet.
+fn element(document: &Document, id: &str, tag: &str) -> Element {
+    document.get_element_by_id(id).unwrap_or_else(|| {
+        let element = document
+            .create_element(tag)
+            .unwrap_or_else(|_| panic!("couldn't make a <{}>", tag));
+        element.set_id(id);
+        document
+            .body()
+            .expect("no <body>")
+            .append_child(&element)
+            .unwrap_or_else(|_| panic!("couldn't add the <{}> to the page", tag));
+        element
+    })
+}
+
+/// The log line of an event, which is its message.
+#[derive(Default)]
+struct Line(String);
+
+impl Visit for Line {
+    fn record_debug(&mut self, field: &Field, value: &dyn fmt::Debug) {
+        if field.name() == "message" {
+            let _ = write!(self.0, "{:?}", value);
+        }
+    }
+}
+
+/// Writes every log line out under the canvas, as the terminal would show it.
+struct ScreenLog;
+
+impl<S: Subscriber> Layer<S> for ScreenLog {
+    fn on_event(&self, event: &Event<'_>, _ctx: Context<'_, S>) {
+        let mut line = Line::default();
+        event.record(&mut line);
+        let log = match document().get_element_by_id(LOG) {
+            Some(log) => log,
+            None => return,
+        };
+        let _ = log.append_with_str_1(&format!("{}\n", line.0));
+        while log.child_nodes().length() > LOG_LINES {
+            if let Some(oldest) = log.first_child() {
+                let _ = log.remove_child(&oldest);
+            }
+        }
+        log.set_scroll_top(log.scroll_height());
+    }
+}
+
+fn screen_log(_app: &mut App) -> Option<BoxedLayer> {
+    Some(Box::new(ScreenLog))
+}
+
+/// What the town's drawn on.
+pub struct Canvas(CanvasRenderingContext2d);
+
+impl Canvas {
+    /// Where on the canvas `position` on the map is, north up.
+    fn point(position: Vector2D) -> (f64, f64) {
+        let (from, to) = MAP_CORNERS;
+        ((position.x - from.x) * SCALE, (to.y - position.y) * SCALE)
+    }
+
+    fn dot(&self, position: Vector2D, radius: f64, colour: &str) {
+        let (x, y) = Canvas::point(position);
+        self.0.set_fill_style_str(colour);
+        self.0.begin_path();
+        let _ = self.0.arc(x, y, radius, 0.0, std::f64::consts::TAU);
+        self.0.fill();
+    }
+
+    fn label(&self, position: Vector2D, text: &str) {
+        let (x, y) = Canvas::point(position);
+        self.0.set_fill_style_str("black");
+        let _ = self.0.fill_text(text, x + 8.0, y + 4.0);
+    }
+}
+
+/// Redraws the roads, the places along them and everybody where they are.
+pub fn draw_town(
+    canvas: NonSend<Canvas>,
+    clock: Res<Clock>,
+    graph: Res<NavGraph>,
+    agents: Query<(&Name, &Location, Option<&PathPlanner>, Option<&Mount>)>,
+) {
+    let (from, to) = MAP_CORNERS;
+    let context = &canvas.0;
+    context.clear_rect(0.0, 0.0, (to.x - from.x) * SCALE, (to.y - from.y) * SCALE);
+    context.set_font("12px sans-serif");
+
+    context.set_stroke_style_str("tan");
+    context.set_line_width(4.0);
+    for node in graph.0.nodes() {
+        for edge in graph.0.edges(node.index) {
+            if let Some(other) = graph.0.node(edge.to) {
+                let (x, y) = Canvas::point(node.position);
+                context.begin_path();
+                context.move_to(x, y);
+                let (x, y) = Canvas::point(other.position);
+                context.line_to(x, y);
+                context.stroke();
+            }
+        }
+    }
+    for node in graph.0.nodes() {
+        if let Some(location) = node.extra_info {
+            canvas.dot(node.position, 10.0, "saddlebrown");
+            canvas.label(node.position, &format!("{:?}", location));
+        }
+    }
+
+    let in_turn = agents
+        .iter()
+        .sort_by_key::<Entity, _>(|entity| entity.index_u32());
+    for (i, (name, location, planner, mount)) in in_turn.enumerate() {
+        let position = match (mount, planner) {
+            (Some(mount), _) => mount.position,
+            (None, Some(planner)) => planner.position(),
+            (None, None) => match graph.position_of(*location) {
+                Some(position) => position,
+                None => continue,
+            },
+        };
+        canvas.dot(position, 4.0, "steelblue");
+        // a line apiece, so those in the same place don't write over each other
+        let below = Vector2D::new(position.x, position.y - 2.5 * (i % 4) as f64);
+        canvas.label(below, &name.to_string());
+    }
+    let _ = context.fill_text(&clock.to_string(), 8.0, 16.0);
+}
+
+pub struct WebPlugin;
+
+impl Plugin for WebPlugin {
+    fn build(&self, app: &mut App) {
+        let document = document();
+        let canvas: HtmlCanvasElement = element(&document, CANVAS, "canvas")
+            .dyn_into()
+            .expect("#westworld isn't a <canvas>");
+        let (from, to) = MAP_CORNERS;
+        canvas.set_width(((to.x - from.x) * SCALE) as u32);
+        canvas.set_height(((to.y - from.y) * SCALE) as u32);
+        let context: CanvasRenderingContext2d = canvas
+            .get_context("2d")
+            .ok()
+            .flatten()
+            .and_then(|context| context.dyn_into().ok())
+            .expect("no 2d context for the canvas");
+        element(&document, LOG, "pre");
+
+        app.insert_non_send_resource(Canvas(context));
+        app.add_systems(Update, draw_town);
+    }
+}
@@ -0,0 +1,92 @@
+use crate::goals::{GoalDepositGold, GoalDrink, GoalGetGold, GoalRest};
+use crate::miner::Miner;
+use game_ai::goals::{Goal, GoalEvaluator, Personality, Think, Trait};
+
+/// Digging is what Bob does when nothing else is pressing.
+pub static GET_GOLD_DESIRABILITY: f64 = 0.5;
+
+/// A miner's brain: whichever of these is most desirable is what he does next.
+pub fn brain(personality: Personality) -> Think<Miner> {
+    Think::new()
+        .with_personality(personality)
+        .with_evaluator(GetGoldEvaluator)
+        .with_evaluator(DepositGoldEvaluator)
+        .with_evaluator(RestEvaluator)
+        .with_evaluator(DrinkEvaluator)
+}
+
+fn ratio(value: i32, limit: i32) -> f64 {
+    (value as f64 / limit as f64).max(0.0)
+}
+
+pub struct GetGoldEvaluator;
+
+impl GoalEvaluator<Miner> for GetGoldEvaluator {
+    fn desirability(&self, _miner: &Miner) -> f64 {
+        GET_GOLD_DESIRABILITY
+    }
+
+    fn create_goal(&self, _miner: &Miner) -> Box<dyn Goal<Miner>> {
+        Box::new(GoalGetGold::new())
+    }
+
+    fn personality_trait(&self) -> Option<Trait> {
+        Some(Trait::Greed)
+    }
+}
+
+/// Only worth the walk once his pockets are (nearly) full.
+pub struct DepositGoldEvaluator;
+
+impl GoalEvaluator<Miner> for DepositGoldEvaluator {
+    fn desirability(&self, miner: &Miner) -> f64 {
+        ratio(miner.gold_carried(), miner.config().max_nuggets).powi(2) * 0.8
+    }
+
+    fn create_goal(&self, _miner: &Miner) -> Box<dyn Goal<Miner>> {
+        Box::new(GoalDepositGold::new())
+    }
+
+    fn personality_trait(&self) -> Option<Trait> {
+        Some(Trait::Greed)
+    }
+}
+
+/// Grows with fatigue, and a comfortable bank balance makes a nap more tempting.
+pub struct RestEvaluator;
+
+impl GoalEvaluator<Miner> for RestEvaluator {
+    fn desirability(&self, miner: &Miner) -> f64 {
+        let comfort = if miner.wealth() >= miner.config().comfort_level {
+            0.4
+        } else {
+            0.0
+        };
+        ratio(miner.fatigue(), miner.config().tiredness_threshold) * 0.4 + comfort
+    }
+
+    fn create_goal(&self, _miner: &Miner) -> Box<dyn Goal<Miner>> {
+        Box::new(GoalRest::new())
+    }
+
+    fn personality_trait(&self) -> Option<Trait> {
+        Some(Trait::Laziness)
+    }
+}
+
+/// Outweighs digging once he's thirstier than his `thirst_level`.
+pub struct DrinkEvaluator;
+
+impl GoalEvaluator<Miner> for DrinkEvaluator {
+    fn desirability(&self, miner: &Miner) -> f64 {
+        ratio(miner.thirst(), miner.config().thirst_level).powi(2) * GET_GOLD_DESIRABILITY
+    }
+
+    fn create_goal(&self, _miner: &Miner) -> Box<dyn Goal<Miner>> {
+        Box::new(GoalDrink::new())
+    }
+
+    fn personality_trait(&self) -> Option<Trait> {
+        Some(Trait::Sociability)
+    }
+}
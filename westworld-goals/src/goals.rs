@@ -0,0 +1,272 @@
+use crate::miner::{Location, Miner};
+use crate::town;
+use game_ai::goals::{CompositeGoal, Goal, GoalFollowPath, GoalStatus, PathFollower, ReplanPolicy};
+use std::marker::PhantomData;
+
+/// Walks to a location along the town's roads, planning a new route if the
+/// one it's on fails. Completes straight away if the miner is already there.
+pub struct GoalTravelTo {
+    destination: Location,
+    subgoals: CompositeGoal<Miner>,
+    status: GoalStatus,
+}
+
+impl GoalTravelTo {
+    pub fn new(destination: Location) -> Self {
+        GoalTravelTo {
+            destination,
+            subgoals: CompositeGoal::new().with_replan_policy(ReplanPolicy::Retry(2)),
+            status: GoalStatus::Inactive,
+        }
+    }
+}
+
+impl Goal<Miner> for GoalTravelTo {
+    fn activate(&mut self, miner: &mut Miner) {
+        self.status = GoalStatus::Active;
+        self.subgoals.remove_all_subgoals(miner);
+
+        if miner.location == self.destination {
+            self.status = GoalStatus::Completed;
+            return;
+        }
+
+        match town::plan_path(miner.vehicle().position, self.destination) {
+            Some(path) => {
+                miner.log(format!("Walkin' to the {:?}", self.destination));
+                self.subgoals
+                    .add_subgoal(Box::new(GoalFollowPath::new(path)));
+            }
+            None => {
+                miner.log(format!("Can't find the way to the {:?}", self.destination));
+                self.status = GoalStatus::Failed;
+            }
+        }
+    }
+
+    fn process(&mut self, miner: &mut Miner) -> GoalStatus {
+        self.activate_if_inactive(miner);
+        if self.is_active() {
+            self.status = self.subgoals.process_subgoals(miner);
+            if self.is_complete() {
+                miner.location = self.destination;
+            }
+        }
+        self.status
+    }
+
+    fn terminate(&mut self, miner: &mut Miner) {
+        self.subgoals.remove_all_subgoals(miner);
+    }
+
+    fn status(&self) -> GoalStatus {
+        self.status
+    }
+
+    fn children(&self) -> Vec<&dyn Goal<Miner>> {
+        self.subgoals.iter().collect()
+    }
+}
+
+/// Something the miner does once he's at the right place. Like the states of
+/// the FSM miner, every update spent on a task makes him thirstier.
+pub trait Task: Goal<Miner> + Default + 'static {
+    const LOCATION: Location;
+}
+
+/// Travels to where a task is done, then does it.
+pub struct Errand<G> {
+    subgoals: CompositeGoal<Miner>,
+    status: GoalStatus,
+    task: PhantomData<G>,
+}
+
+pub type GoalGetGold = Errand<DigForNugget>;
+pub type GoalDepositGold = Errand<DepositGold>;
+pub type GoalRest = Errand<SleepTilRested>;
+pub type GoalDrink = Errand<DrinkWhiskey>;
+
+impl<G: Task> Errand<G> {
+    pub fn new() -> Self {
+        Errand {
+            subgoals: CompositeGoal::new(),
+            status: GoalStatus::Inactive,
+            task: PhantomData,
+        }
+    }
+}
+
+impl<G: Task> Goal<Miner> for Errand<G> {
+    fn activate(&mut self, miner: &mut Miner) {
+        self.status = GoalStatus::Active;
+        self.subgoals.remove_all_subgoals(miner);
+        self.subgoals.add_subgoal(Box::new(G::default()));
+        self.subgoals
+            .add_subgoal(Box::new(GoalTravelTo::new(G::LOCATION)));
+    }
+
+    fn process(&mut self, miner: &mut Miner) -> GoalStatus {
+        self.activate_if_inactive(miner);
+        self.status = self.subgoals.process_subgoals(miner);
+        self.status
+    }
+
+    fn terminate(&mut self, miner: &mut Miner) {
+        self.subgoals.remove_all_subgoals(miner);
+    }
+
+    fn status(&self) -> GoalStatus {
+        self.status
+    }
+
+    fn name(&self) -> String {
+        match G::LOCATION {
+            Location::Goldmine => "GoalGetGold",
+            Location::Bank => "GoalDepositGold",
+            Location::Shack => "GoalRest",
+            Location::Saloon => "GoalDrink",
+        }
+        .to_string()
+    }
+
+    fn children(&self) -> Vec<&dyn Goal<Miner>> {
+        self.subgoals.iter().collect()
+    }
+}
+
+#[derive(Default)]
+pub struct DigForNugget {
+    status: Option<GoalStatus>,
+}
+
+impl Task for DigForNugget {
+    const LOCATION: Location = Location::Goldmine;
+}
+
+impl Goal<Miner> for DigForNugget {
+    fn activate(&mut self, _miner: &mut Miner) {
+        self.status = Some(GoalStatus::Active);
+    }
+
+    fn process(&mut self, miner: &mut Miner) -> GoalStatus {
+        self.activate_if_inactive(miner);
+        miner.increase_thirst();
+        miner.add_to_gold_carried(1);
+        miner.increase_fatigue();
+        miner.log("Pickin' up a nugget".to_string());
+        self.status = Some(GoalStatus::Completed);
+        self.status()
+    }
+
+    fn terminate(&mut self, miner: &mut Miner) {
+        if miner.pockets_full() {
+            miner.log("Ah'm leavin' the goldmine with mah pockets full o' sweet gold".to_string());
+        }
+    }
+
+    fn status(&self) -> GoalStatus {
+        self.status.unwrap_or(GoalStatus::Inactive)
+    }
+}
+
+#[derive(Default)]
+pub struct DepositGold {
+    status: Option<GoalStatus>,
+}
+
+impl Task for DepositGold {
+    const LOCATION: Location = Location::Bank;
+}
+
+impl Goal<Miner> for DepositGold {
+    fn activate(&mut self, _miner: &mut Miner) {
+        self.status = Some(GoalStatus::Active);
+    }
+
+    fn process(&mut self, miner: &mut Miner) -> GoalStatus {
+        self.activate_if_inactive(miner);
+        miner.increase_thirst();
+        miner.move_gold_to_bank();
+        miner.log(format!(
+            "Depositing gold. Total savings now: {}",
+            miner.wealth()
+        ));
+        self.status = Some(GoalStatus::Completed);
+        self.status()
+    }
+
+    fn terminate(&mut self, miner: &mut Miner) {
+        miner.log("Leavin' the bank".to_string());
+    }
+
+    fn status(&self) -> GoalStatus {
+        self.status.unwrap_or(GoalStatus::Inactive)
+    }
+}
+
+#[derive(Default)]
+pub struct SleepTilRested {
+    status: Option<GoalStatus>,
+}
+
+impl Task for SleepTilRested {
+    const LOCATION: Location = Location::Shack;
+}
+
+impl Goal<Miner> for SleepTilRested {
+    fn activate(&mut self, _miner: &mut Miner) {
+        self.status = Some(GoalStatus::Active);
+    }
+
+    fn process(&mut self, miner: &mut Miner) -> GoalStatus {
+        self.activate_if_inactive(miner);
+        miner.increase_thirst();
+        if miner.fatigue() > 0 {
+            miner.decrease_fatigue();
+            miner.log("ZZZZ... ".to_string());
+        } else {
+            miner.log("What a God darn fantastic nap! Time to find more gold".to_string());
+            self.status = Some(GoalStatus::Completed);
+        }
+        self.status()
+    }
+
+    fn terminate(&mut self, miner: &mut Miner) {
+        miner.log("Leaving the house".to_string());
+    }
+
+    fn status(&self) -> GoalStatus {
+        self.status.unwrap_or(GoalStatus::Inactive)
+    }
+}
+
+#[derive(Default)]
+pub struct DrinkWhiskey {
+    status: Option<GoalStatus>,
+}
+
+impl Task for DrinkWhiskey {
+    const LOCATION: Location = Location::Saloon;
+}
+
+impl Goal<Miner> for DrinkWhiskey {
+    fn activate(&mut self, _miner: &mut Miner) {
+        self.status = Some(GoalStatus::Active);
+    }
+
+    fn process(&mut self, miner: &mut Miner) -> GoalStatus {
+        self.activate_if_inactive(miner);
+        miner.buy_and_drink_whiskey();
+        miner.log("That's mighty fine sippin liquer".to_string());
+        self.status = Some(GoalStatus::Completed);
+        self.status()
+    }
+
+    fn terminate(&mut self, miner: &mut Miner) {
+        miner.log("Leaving the saloon, feelin' good".to_string());
+    }
+
+    fn status(&self) -> GoalStatus {
+        self.status.unwrap_or(GoalStatus::Inactive)
+    }
+}
@@ -0,0 +1,65 @@
+use game_ai::goals::describe::describe;
+use game_ai::goals::Goal;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
+
+pub trait Named<'a> {
+    fn name(&'a self) -> &'a str;
+}
+
+pub trait Log {
+    fn log<'a, N: Named<'a>>(&self, named: &'a N, msg: String);
+
+    /// Logs the goal hierarchy an agent is pursuing, as an indented tree.
+    fn log_goals<'a, N: Named<'a>, E, M>(&self, named: &'a N, goal: &dyn Goal<E, M>) {
+        self.log(named, format!("goals:\n{}", describe(goal).trim_end()));
+    }
+}
+
+/// Where [`ConsoleLog`] also writes each line, from `--transcript=FILE`.
+static TRANSCRIPT: OnceLock<Mutex<BufWriter<File>>> = OnceLock::new();
+
+/// Writes everything said to `path` as well, for runs to be compared line by
+/// line.
+pub fn set_transcript(path: &Path) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    if TRANSCRIPT.set(Mutex::new(file)).is_err() {
+        panic!("the transcript's already set");
+    }
+    Ok(())
+}
+
+/// Writes `line` to the transcript, if there is one.
+fn transcribe(line: &str) {
+    if let Some(transcript) = TRANSCRIPT.get() {
+        let mut transcript = transcript.lock().unwrap();
+        writeln!(transcript, "{}", line).expect("could not write the transcript");
+    }
+}
+
+/// Marks the end of a tick in the transcript, with a blank line.
+pub fn end_tick() {
+    transcribe("");
+}
+
+/// Finishes writing the transcript, if there is one.
+pub fn finish_transcript() {
+    if let Some(transcript) = TRANSCRIPT.get() {
+        transcript
+            .lock()
+            .unwrap()
+            .flush()
+            .expect("could not write the transcript");
+    }
+}
+
+pub struct ConsoleLog;
+
+impl Log for ConsoleLog {
+    fn log<'a, N: Named<'a>>(&self, named: &'a N, msg: String) {
+        transcribe(&format!("{}: {}", named.name(), msg));
+        println!("{}: {}", named.name(), msg);
+    }
+}
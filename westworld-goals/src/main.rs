@@ -0,0 +1,106 @@
+use game_ai::config::Config;
+use game_ai::entity::{self, BaseGameEntity};
+use game_ai::goals::Goal;
+use game_ai::regulator::TickRegulator;
+use std::env;
+use std::fs;
+use std::path::Path;
+use std::thread;
+
+mod evaluators;
+mod goals;
+mod log;
+mod miner;
+mod town;
+
+use miner::{Miner, MinerConfig, FRAMES_PER_UPDATE, GREETING_RANGE};
+
+static MINERS: &str = include_str!("../miners.ron");
+
+/// The value of `--name=VALUE`, if it was given.
+fn arg_value(name: &str) -> Option<String> {
+    let prefix = format!("{}=", name);
+    env::args()
+        .skip(1)
+        .find_map(|arg| arg.strip_prefix(&prefix).map(str::to_string))
+}
+
+fn main() {
+    let debug_goals = env::args().any(|arg| arg == "--debug-goals");
+    // flat out, without waiting out each tick
+    let fast = env::args().any(|arg| arg == "--fast");
+    // stops after this many ticks, or runs forever
+    let ticks = arg_value("--ticks").map_or(u64::MAX, |ticks| {
+        ticks
+            .parse()
+            .unwrap_or_else(|e| panic!("bad --ticks={}: {}", ticks, e))
+    });
+    if let Some(path) = arg_value("--transcript") {
+        log::set_transcript(Path::new(&path))
+            .unwrap_or_else(|e| panic!("could not write {}: {}", path, e));
+    }
+    let config = Config::from_args(env::args()).unwrap_or_else(|e| panic!("{}", e));
+    let miners: Vec<MinerConfig> = match env::args().skip(1).find(|arg| !arg.starts_with("--")) {
+        Some(path) => {
+            let file = fs::read_to_string(&path).expect("could not read miners file");
+            ron::from_str(&file).expect("could not parse miners file")
+        }
+        None => ron::from_str(MINERS).unwrap(),
+    };
+
+    // steering runs every frame, but the brains only think once per update,
+    // each on a different frame
+    let count = miners.len() as u32;
+    let mut miners: Vec<_> = miners
+        .into_iter()
+        .enumerate()
+        .map(|(i, miner)| {
+            let offset = i as u32 * FRAMES_PER_UPDATE / count.max(1);
+            (
+                Miner::new(miner.name).with_config(config.clone()),
+                evaluators::brain(miner.personality),
+                TickRegulator::new(FRAMES_PER_UPDATE).with_offset(offset),
+            )
+        })
+        .collect();
+
+    for _ in 0..ticks {
+        println!();
+
+        for _ in 0..FRAMES_PER_UPDATE {
+            for i in 0..miners.len() {
+                let (miner, brain, regulator) = &mut miners[i];
+                if regulator.is_ready() {
+                    brain.process(miner);
+                    if debug_goals {
+                        miner.log_goals(brain);
+                    }
+                    greet_neighbors(&mut miners, i);
+                }
+                miners[i].0.update_movement();
+            }
+
+            if !fast {
+                thread::sleep(config.tick_interval() / FRAMES_PER_UPDATE);
+            }
+        }
+
+        log::end_tick();
+    }
+    log::finish_transcript();
+}
+
+/// Lets a miner say howdy to whoever's drinking next to him.
+fn greet_neighbors<B, R>(miners: &mut [(Miner, B, R)], i: usize) {
+    let me = miners[i].0.base().clone();
+    entity::tag_neighbors(
+        &me,
+        miners.iter_mut().map(|(miner, _, _)| miner),
+        GREETING_RANGE,
+    );
+
+    let (before, rest) = miners.split_at_mut(i);
+    let (current, after) = rest.split_first_mut().unwrap();
+    let others = before.iter().chain(after.iter()).map(|(miner, _, _)| miner);
+    current.0.greet_neighbors(others);
+}
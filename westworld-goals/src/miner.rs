@@ -0,0 +1,178 @@
+use crate::log::{ConsoleLog, Log, Named};
+use crate::town;
+use game_ai::config::Config;
+use game_ai::entity::{BaseGameEntity, EntityBase};
+use game_ai::goals::{Goal, PathFollower, Personality};
+use game_ai::steering::Vehicle;
+use serde::Deserialize;
+
+pub static TIME_STEP: f64 = 0.8; // seconds of game time per goal update
+pub static FRAMES_PER_UPDATE: u32 = 8; // steering runs every frame, goals once per update
+pub static WALKING_SPEED: f64 = 10.0;
+pub static MAX_STEERING_FORCE: f64 = 50.0;
+pub static MINER_MASS: f64 = 0.2;
+pub static MINER_RADIUS: f64 = 1.0;
+pub static GREETING_RANGE: f64 = 5.0; // how close another miner must be to say howdy
+
+/// Seconds of game time per frame.
+pub fn frame_time() -> f64 {
+    TIME_STEP / f64::from(FRAMES_PER_UPDATE)
+}
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Location {
+    Goldmine,
+    Bank,
+    Shack,
+    Saloon,
+}
+
+/// A miner as described in `miners.ron`.
+#[derive(Debug, Deserialize)]
+pub struct MinerConfig {
+    pub name: String,
+    #[serde(default)]
+    pub personality: Personality,
+}
+
+pub struct Miner {
+    base: EntityBase,
+    pub name: String,
+    pub location: Location,
+    vehicle: Vehicle,
+    gold: i32,
+    bank: i32,
+    thirst: i32,
+    fatigue: i32,
+    greeted: bool,
+    config: Config,
+}
+
+impl<'a> Named<'a> for Miner {
+    fn name(&'a self) -> &'a str {
+        &self.name
+    }
+}
+
+impl BaseGameEntity for Miner {
+    fn base(&self) -> &EntityBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut EntityBase {
+        &mut self.base
+    }
+}
+
+impl PathFollower for Miner {
+    fn vehicle(&self) -> &Vehicle {
+        &self.vehicle
+    }
+
+    fn vehicle_mut(&mut self) -> &mut Vehicle {
+        &mut self.vehicle
+    }
+
+    fn time_step(&self) -> f64 {
+        TIME_STEP
+    }
+}
+
+impl Miner {
+    pub fn new(name: String) -> Self {
+        let mut vehicle = Vehicle::new(
+            town::position_of(Location::Shack),
+            WALKING_SPEED,
+            MAX_STEERING_FORCE,
+        );
+        vehicle.mass = MINER_MASS;
+
+        Miner {
+            base: EntityBase::new(0, vehicle.position, MINER_RADIUS),
+            name,
+            location: Location::Shack,
+            vehicle,
+            gold: 0,
+            bank: 0,
+            thirst: 0,
+            fatigue: 0,
+            greeted: false,
+            config: Config::default(),
+        }
+    }
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+    pub fn config(&self) -> &Config {
+        &self.config
+    }
+    /// Moves the miner one frame along whatever his goals are steering him
+    /// towards.
+    pub fn update_movement(&mut self) {
+        self.vehicle.update(frame_time());
+        self.base.position = self.vehicle.position;
+    }
+    pub fn add_to_gold_carried(&mut self, gold: i32) {
+        self.gold += gold;
+        if self.gold < 0 {
+            self.gold = 0;
+        }
+    }
+    pub fn gold_carried(&self) -> i32 {
+        self.gold
+    }
+    pub fn increase_fatigue(&mut self) {
+        self.fatigue += 1;
+    }
+    pub fn decrease_fatigue(&mut self) {
+        self.fatigue -= 1;
+    }
+    pub fn fatigue(&self) -> i32 {
+        self.fatigue
+    }
+    pub fn pockets_full(&self) -> bool {
+        self.gold >= self.config.max_nuggets
+    }
+    pub fn increase_thirst(&mut self) {
+        self.thirst += 1;
+    }
+    pub fn thirst(&self) -> i32 {
+        self.thirst
+    }
+    pub fn buy_and_drink_whiskey(&mut self) {
+        self.bank -= 2;
+        self.thirst = 0;
+    }
+    pub fn move_gold_to_bank(&mut self) {
+        self.bank += self.gold;
+        self.gold = 0;
+    }
+    pub fn wealth(&self) -> i32 {
+        self.bank
+    }
+    /// Says howdy to the miners drinking nearby, once per visit to the
+    /// saloon. The other miners must already be tagged as neighbours.
+    pub fn greet_neighbors<'a>(&mut self, others: impl IntoIterator<Item = &'a Miner>) {
+        if self.location != Location::Saloon {
+            self.greeted = false;
+            return;
+        }
+        if self.greeted {
+            return;
+        }
+
+        for other in others {
+            if other.is_tagged() && other.location == Location::Saloon {
+                self.log(format!("Howdy, {}!", other.name));
+                self.greeted = true;
+            }
+        }
+    }
+    pub fn log(&self, msg: String) {
+        ConsoleLog.log(self, msg);
+    }
+    pub fn log_goals(&self, goal: &dyn Goal<Miner>) {
+        ConsoleLog.log_goals(self, goal);
+    }
+}
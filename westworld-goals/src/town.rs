@@ -0,0 +1,45 @@
+use crate::miner::Location;
+use game_ai::graph::path::{edges_from_waypoints, PathEdge};
+use game_ai::graph::search::find_closest_item;
+use game_ai::graph::SparseGraph;
+use game_ai::Vector2D;
+use std::sync::OnceLock;
+
+static TOWN_MAP: &str = include_str!("../../game-ai/maps/town.ron");
+
+/// The roads between the town's locations, with each location's node tagged
+/// with its name.
+pub fn town() -> &'static SparseGraph<String> {
+    static TOWN: OnceLock<SparseGraph<String>> = OnceLock::new();
+    TOWN.get_or_init(|| SparseGraph::from_ron_str(TOWN_MAP).expect("bundled town map is valid"))
+}
+
+fn tag(location: Location) -> String {
+    format!("{:?}", location)
+}
+
+pub fn position_of(location: Location) -> Vector2D {
+    town()
+        .nodes_with(&tag(location))
+        .next()
+        .map(|node| node.position)
+        .expect("every location is on the town map")
+}
+
+/// The shortest route along the roads from `from` to a location.
+pub fn plan_path(from: Vector2D, to: Location) -> Option<Vec<PathEdge>> {
+    let graph = town();
+    let source = graph.closest_node(from)?;
+    let nodes = find_closest_item(graph, source, tag(to))?;
+
+    let mut waypoints = vec![from];
+    waypoints.extend(
+        nodes
+            .iter()
+            .filter_map(|&index| graph.node(index))
+            .map(|node| node.position),
+    );
+    waypoints.dedup();
+
+    Some(edges_from_waypoints(&waypoints))
+}
@@ -0,0 +1,75 @@
+//! Golden tests for the goal-driven miners: each run is stopped after so many
+//! ticks, with what the miners say written out through the log's transcript
+//! and compared against a transcript in `tests/golden`, so how their brains
+//! choose between goals can only change on purpose.
+//!
+//! After an intended change, rewrite the transcripts with
+//! `UPDATE_GOLDEN=1 cargo test -p westworld-goals --test golden` and review
+//! the diff.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.txt", name))
+}
+
+/// Runs the miners flat out with `args`, returning their transcript.
+fn transcript(name: &str, args: &[&str]) -> String {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.txt", name));
+    let status = Command::new(env!("CARGO_BIN_EXE_westworld-goals"))
+        .args(args)
+        .arg("--fast")
+        .arg(format!("--transcript={}", path.display()))
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "{} exited with {}", name, status);
+    fs::read_to_string(&path).unwrap()
+}
+
+fn assert_golden(name: &str, args: &[&str]) {
+    let transcript = transcript(name, args);
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, transcript).unwrap();
+        return;
+    }
+
+    let golden = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1)", path.display(), e));
+    if let Some((line, (said, expected))) = transcript
+        .lines()
+        .zip(golden.lines())
+        .enumerate()
+        .find(|(_, (said, expected))| said != expected)
+    {
+        panic!(
+            "{} strayed from its golden transcript at line {}:\n  said     {:?}\n  expected {:?}",
+            name,
+            line + 1,
+            said,
+            expected
+        );
+    }
+    assert_eq!(
+        transcript.lines().count(),
+        golden.lines().count(),
+        "{} said a different amount from its golden transcript",
+        name
+    );
+}
+
+#[test]
+fn bob_and_dave() {
+    assert_golden("bob_and_dave", &["--ticks=200"]);
+}
+
+#[test]
+fn their_goals() {
+    assert_golden("their_goals", &["--debug-goals", "--ticks=20"]);
+}
@@ -0,0 +1,66 @@
+//! Bob run by a state machine read from a file, with `--fsm=FILE`, rather
+//! than by the states in [`crate::miner`]. His gold, bank, thirst and
+//! fatigue are the machine's stats, and he says whatever its states and
+//! transitions say. `fsms/miner.ron` is the miner of the book.
+
+use crate::cli::Cli;
+use crate::log::{ConsoleLog, Log, Named};
+use game_ai::data_fsm::{DataFsm, MachineDef, MachineFileError, Stats};
+
+pub struct AuthoredMiner {
+    name: String,
+    fsm: DataFsm,
+    stats: Stats,
+}
+
+impl<'a> Named<'a> for AuthoredMiner {
+    fn name(&'a self) -> &'a str {
+        &self.name
+    }
+}
+
+impl AuthoredMiner {
+    pub fn new(name: String, fsm: DataFsm) -> Self {
+        AuthoredMiner {
+            name,
+            fsm,
+            stats: Stats::default(),
+        }
+    }
+
+    /// Bob, if `--fsm=FILE` says what he's to run.
+    pub fn from_cli(cli: &Cli) -> Result<Option<Self>, MachineFileError> {
+        match &cli.fsm {
+            Some(path) => {
+                let fsm = DataFsm::new(MachineDef::load(path)?)?;
+                Ok(Some(AuthoredMiner::new("Miner Bob".into(), fsm)))
+            }
+            None => Ok(None),
+        }
+    }
+
+    pub fn update(&mut self) {
+        let said: Vec<String> = self
+            .fsm
+            .update(&mut self.stats)
+            .into_iter()
+            .map(str::to_string)
+            .collect();
+        for msg in said {
+            ConsoleLog.log(self, msg);
+        }
+    }
+
+    /// Says how he ended up, once the simulation is over.
+    pub fn report(&self) {
+        ConsoleLog.log(
+            self,
+            format!(
+                "Callin' it a day in {} with {} gold in the bank and {} in mah pockets",
+                self.fsm.state(),
+                self.stats.get("bank"),
+                self.stats.get("gold")
+            ),
+        );
+    }
+}
@@ -0,0 +1,155 @@
+//! Keyboard controls for the example loop, read a line at a time from stdin
+//! on a background thread so the simulation never blocks waiting for input.
+//!
+//! - space (or just Enter) pauses and resumes
+//! - `n` steps one tick, pausing if running
+//! - `q` quits
+
+use crate::cli::Cli;
+use crate::shutdown::Shutdown;
+use game_ai::config::Config;
+use std::io::{stdin, BufRead};
+use std::sync::mpsc::{self, Receiver, RecvTimeoutError};
+use std::thread;
+use std::time::{Duration, Instant};
+
+pub static HELP: &str = "Controls: space to pause/resume, n to step, q to quit (then Enter)";
+
+const POLL: Duration = Duration::from_millis(100); // how often a wait checks for a shutdown
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Command {
+    TogglePause,
+    Step,
+    Quit,
+}
+
+impl Command {
+    pub fn parse(line: &str) -> Option<Command> {
+        match line.trim() {
+            "" => Some(Command::TogglePause),
+            "n" => Some(Command::Step),
+            "q" => Some(Command::Quit),
+            _ => None,
+        }
+    }
+}
+
+pub struct Controls {
+    lines: Receiver<String>,
+    paused: bool,
+    shutdown: Shutdown,
+}
+
+impl Controls {
+    /// Starts reading stdin in the background. Waits give up early once
+    /// `shutdown` is requested.
+    pub fn from_stdin(shutdown: Shutdown) -> Self {
+        let (sender, lines) = mpsc::channel();
+        thread::spawn(move || {
+            for line in stdin().lock().lines().map_while(Result::ok) {
+                if sender.send(line).is_err() {
+                    break;
+                }
+            }
+        });
+
+        Controls {
+            lines,
+            paused: false,
+            shutdown,
+        }
+    }
+
+    /// Waits out one tick of `interval`, or for as long as the user keeps the
+    /// simulation paused. Returns false once the user quits or a shutdown is
+    /// requested.
+    pub fn wait(&mut self, interval: Duration) -> bool {
+        let deadline = Instant::now() + interval;
+        loop {
+            if self.shutdown.requested() {
+                return false;
+            }
+            let remaining = deadline.saturating_duration_since(Instant::now());
+            if !self.paused && remaining.is_zero() {
+                return true;
+            }
+            let timeout = if self.paused {
+                POLL
+            } else {
+                remaining.min(POLL)
+            };
+            let line = match self.lines.recv_timeout(timeout) {
+                Ok(line) => Some(line),
+                Err(RecvTimeoutError::Timeout) => continue,
+                Err(RecvTimeoutError::Disconnected) => None,
+            };
+
+            match line.as_deref().map(Command::parse) {
+                Some(Some(Command::TogglePause)) => {
+                    self.paused = !self.paused;
+                    println!(
+                        "{}",
+                        if self.paused {
+                            "-- paused --"
+                        } else {
+                            "-- resumed --"
+                        }
+                    );
+                }
+                Some(Some(Command::Step)) => {
+                    self.paused = true;
+                    return true;
+                }
+                Some(Some(Command::Quit)) => return false,
+                Some(None) => println!("{}", HELP),
+                // stdin has closed, so nobody is left to unpause
+                None => {
+                    self.paused = false;
+                    thread::sleep(timeout);
+                }
+            }
+        }
+    }
+
+    /// Blocks until the user presses Enter, or a shutdown is requested.
+    pub fn wait_for_enter(&self) {
+        while !self.shutdown.requested() {
+            match self.lines.recv_timeout(POLL) {
+                Err(RecvTimeoutError::Timeout) => (),
+                _ => return,
+            }
+        }
+    }
+}
+
+/// How fast the simulation runs: `--tick-ms=N` between ticks (the config's
+/// `tick_interval_ms` by default), stopping after `--ticks=N` if given. `--fast` runs headless,
+/// with no delay between ticks and no keyboard controls.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Pace {
+    pub interval: Duration,
+    pub ticks: Option<u64>,
+    pub fast: bool,
+}
+
+impl Pace {
+    pub fn from_cli(config: &Config, cli: &Cli) -> Self {
+        let interval = if cli.fast {
+            Duration::ZERO
+        } else {
+            cli.tick_ms
+                .map_or_else(|| config.tick_interval(), Duration::from_millis)
+        };
+        Pace {
+            interval,
+            ticks: cli.ticks,
+            fast: cli.fast,
+        }
+    }
+
+    /// Whether to stop before running tick number `tick`, counting from 0.
+    pub fn finished(&self, tick: u64) -> bool {
+        self.ticks.is_some_and(|ticks| tick >= ticks)
+    }
+}
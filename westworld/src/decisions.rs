@@ -0,0 +1,143 @@
+//! A fuzzy alternative to the miner's crisp thresholds.
+//!
+//! Rather than switching state the moment thirst or fatigue crosses a fixed
+//! level, the miner scores how much he'd like to be mining, drinking,
+//! sleeping or banking from how thirsty, tired and rich he is, and does
+//! whichever scores highest.
+
+use game_ai::config::Config;
+use game_ai::fuzzy::{fairly, very, DefuzzifyMethod, FuzzyModule, FzSet};
+use game_ai::fuzzy_rules;
+
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum Activity {
+    Mine,
+    Bank,
+    Sleep,
+    Drink,
+}
+
+impl Activity {
+    fn desirability_variable(self) -> &'static str {
+        match self {
+            Activity::Mine => "MineDesirability",
+            Activity::Bank => "BankDesirability",
+            Activity::Sleep => "SleepDesirability",
+            Activity::Drink => "DrinkDesirability",
+        }
+    }
+}
+
+/// How a miner decides what to do next.
+pub enum Decisions {
+    /// The original fixed thresholds.
+    Crisp,
+    Fuzzy(FuzzyModule),
+}
+
+impl Decisions {
+    pub fn fuzzy(config: &Config) -> Self {
+        Decisions::Fuzzy(decision_module(config))
+    }
+}
+
+/// The levels of each need, as seen by the fuzzy decision step.
+pub struct Needs {
+    pub thirst: i32,
+    pub fatigue: i32,
+    pub gold: i32,
+    pub wealth: i32,
+}
+
+/// Scores every activity and returns the most desirable. Ties go to the
+/// activity listed first in [`Activity`].
+pub fn most_desirable(module: &mut FuzzyModule, needs: &Needs) -> Activity {
+    fuzzify(module, "Thirst", needs.thirst);
+    fuzzify(module, "Fatigue", needs.fatigue);
+    fuzzify(module, "Gold", needs.gold);
+    fuzzify(module, "Wealth", needs.wealth);
+
+    let mut best = (Activity::Mine, f64::NEG_INFINITY);
+    for &activity in &[
+        Activity::Mine,
+        Activity::Bank,
+        Activity::Sleep,
+        Activity::Drink,
+    ] {
+        let score = module.defuzzify(activity.desirability_variable(), DefuzzifyMethod::MaxAv);
+        if score > best.1 {
+            best = (activity, score);
+        }
+    }
+    best.0
+}
+
+// inputs outside a variable's sets would belong to none of them
+fn fuzzify(module: &mut FuzzyModule, name: &str, value: i32) {
+    let (min, max) = module.variable(name).unwrap().range();
+    module.fuzzify(name, f64::from(value).max(min).min(max));
+}
+
+fn decision_module(config: &Config) -> FuzzyModule {
+    let mut module = FuzzyModule::new();
+
+    let thirst = module.create_variable("Thirst");
+    let quenched = thirst.add_left_shoulder_set("Quenched", 0.0, 2.0, 5.0);
+    let thirsty = thirst.add_triangle_set("Thirsty", 2.0, 5.0, 8.0);
+    let parched = thirst.add_right_shoulder_set("Parched", 5.0, 8.0, 20.0);
+
+    let fatigue = module.create_variable("Fatigue");
+    let rested = fatigue.add_left_shoulder_set("Rested", 0.0, 2.0, 5.0);
+    let tired = fatigue.add_triangle_set("Tired", 2.0, 5.0, 8.0);
+    let exhausted = fatigue.add_right_shoulder_set("Exhausted", 5.0, 8.0, 20.0);
+
+    let max_nuggets = f64::from(config.max_nuggets);
+    let gold = module.create_variable("Gold");
+    let light = gold.add_left_shoulder_set("Light", 0.0, 1.0, max_nuggets);
+    let full = gold.add_right_shoulder_set("Full", 1.0, max_nuggets, max_nuggets * 2.0);
+
+    let wealth = module.create_variable("Wealth");
+    let poor = wealth.add_left_shoulder_set("Poor", 0.0, 0.0, 10.0);
+    let comfortable = wealth.add_right_shoulder_set("Comfortable", 0.0, 10.0, 100.0);
+
+    let mine = desirability(&mut module, Activity::Mine);
+    let bank = desirability(&mut module, Activity::Bank);
+    let sleep = desirability(&mut module, Activity::Sleep);
+    let drink = desirability(&mut module, Activity::Drink);
+
+    module.add_rules(fuzzy_rules! {
+        if (light & rested) then mine.very_desirable;
+        if (light & tired) then mine.desirable;
+        if (full | exhausted | parched) then mine.undesirable;
+
+        if very(full) then bank.very_desirable;
+        if (fairly(full) & comfortable) then bank.desirable;
+        if light then bank.undesirable;
+
+        if exhausted then sleep.very_desirable;
+        if (tired & comfortable) then sleep.desirable;
+        if (rested | (tired & poor)) then sleep.undesirable;
+
+        if parched then drink.very_desirable;
+        if thirsty then drink.desirable;
+        if quenched then drink.undesirable;
+    });
+
+    module
+}
+
+/// The sets of an activity's desirability.
+struct Desirability {
+    undesirable: FzSet,
+    desirable: FzSet,
+    very_desirable: FzSet,
+}
+
+fn desirability(module: &mut FuzzyModule, activity: Activity) -> Desirability {
+    let variable = module.create_variable(activity.desirability_variable());
+    Desirability {
+        undesirable: variable.add_left_shoulder_set("Undesirable", 0.0, 25.0, 50.0),
+        desirable: variable.add_triangle_set("Desirable", 25.0, 50.0, 75.0),
+        very_desirable: variable.add_right_shoulder_set("VeryDesirable", 50.0, 75.0, 100.0),
+    }
+}
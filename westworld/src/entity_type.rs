@@ -0,0 +1,5 @@
+/// The entity type tags of the characters in town.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum EntityType {
+    Miner,
+}
@@ -1,30 +1,130 @@
+use clap::Parser;
+use game_ai::config::Config;
 use game_state_machine::StateMachine;
-use std::io::{stdin, stdout, Read, Write};
-use std::thread;
-use std::time::Duration;
+use std::io::{stdout, Write};
 
+mod authored;
+mod cli;
+mod controls;
+mod decisions;
+mod entity_type;
 mod log;
 mod miner;
+mod shutdown;
+mod trace;
 
+use authored::AuthoredMiner;
+use cli::{Cli, DecisionMode};
+use controls::{Controls, Pace};
+use decisions::Decisions;
 use miner::{GoHomeAndSleepTilRested, Miner};
+use shutdown::Shutdown;
+use trace::traced;
+
+/// `--miners=N` Bobs deciding the way `--decisions` says, or N of each
+/// side by side.
+fn miners(cli: &Cli, config: &Config) -> Vec<Miner> {
+    let crisp = |i| Miner::new(numbered("Miner Bob", i)).with_config(config.clone());
+    let fuzzy = |i| {
+        Miner::new(numbered("Fuzzy Bob", i))
+            .with_config(config.clone())
+            .with_decisions(Decisions::fuzzy(config))
+    };
+    let mut miners = Vec::new();
+    for i in 0..cli.miners {
+        match cli.decisions {
+            DecisionMode::Crisp => miners.push(crisp(i)),
+            DecisionMode::Fuzzy => miners.push(fuzzy(i)),
+            DecisionMode::Both => miners.extend(vec![crisp(i), fuzzy(i)]),
+        }
+    }
+    miners
+}
+
+/// The first is just `name`, and the rest are numbered from 2.
+fn numbered(name: &str, i: usize) -> String {
+    match i {
+        0 => name.to_string(),
+        i => format!("{} {}", name, i + 1),
+    }
+}
 
 fn main() {
-    let mut sm = StateMachine::<Miner>::default();
-    let mut miner = Miner::new("Miner Bob".into());
+    let cli = Cli::parse();
+    log::set_format(cli.log_format);
+    if let Some(path) = &cli.transcript {
+        log::set_transcript(path)
+            .unwrap_or_else(|e| panic!("could not write {}: {}", path.display(), e));
+    }
+    // kept until the end, to finish the file
+    let _trace = trace::install(cli.trace.as_deref());
+    let config = cli.config().unwrap_or_else(|e| panic!("{}", e));
+    // `--fsm=FILE` runs Bob from a file instead
+    let mut authored = AuthoredMiner::from_cli(&cli).unwrap_or_else(|e| panic!("{}", e));
+    let coded = if authored.is_some() {
+        vec![]
+    } else {
+        miners(&cli, &config)
+    };
+    let mut agents: Vec<_> = coded
+        .into_iter()
+        .map(|mut miner| {
+            let mut sm = StateMachine::<Miner>::default();
+            sm.push(traced(GoHomeAndSleepTilRested), &mut miner);
+            (sm, miner)
+        })
+        .collect();
+
+    let shutdown = Shutdown::install();
+    let pace = Pace::from_cli(&config, &cli);
+    let mut controls = if pace.fast || cli.headless {
+        None
+    } else {
+        println!("{}", controls::HELP);
+        Some(Controls::from_stdin(shutdown.clone()))
+    };
+
+    let mut tick = 0;
+    let mut quit = false;
+    while (agents.iter().any(|(sm, _)| sm.is_running()) || authored.is_some())
+        && !pace.finished(tick)
+        && !quit
+        && !shutdown.requested()
+    {
+        for (sm, miner) in agents.iter_mut() {
+            sm.update(miner);
+        }
+        if let Some(miner) = authored.as_mut() {
+            miner.update();
+        }
+        log::end_tick();
+        tick += 1;
 
-    sm.push(Box::new(GoHomeAndSleepTilRested), &mut miner);
+        if let Some(controls) = controls.as_mut() {
+            quit = !controls.wait(pace.interval);
+        }
+    }
 
-    while sm.is_running() {
-        sm.update(&mut miner);
-        thread::sleep(Duration::from_millis(800));
+    // wind down whatever everyone is in the middle of
+    for (sm, miner) in agents.iter_mut() {
+        sm.stop(miner);
+        miner.report();
+    }
+    if let Some(miner) = &authored {
+        miner.report();
     }
+    log::finish_transcript();
 
-    pause();
+    if let Some(controls) = controls {
+        if !quit && !shutdown.requested() {
+            pause(&controls);
+        }
+    }
 }
 
-fn pause() {
+fn pause(controls: &Controls) {
     let mut stdout = stdout();
-    stdout.write(b"Press Enter to continue...").unwrap();
+    stdout.write_all(b"Press Enter to continue...").unwrap();
     stdout.flush().unwrap();
-    stdin().read(&mut [0]).unwrap();
+    controls.wait_for_enter();
 }
@@ -1,11 +1,12 @@
+use crate::decisions::{self, Activity, Decisions, Needs};
+use crate::entity_type::EntityType;
 use crate::log::{ConsoleLog, Log, Named};
+use crate::trace::traced;
+use game_ai::config::Config;
+use game_ai::entity::{BaseGameEntity, EntityBase};
+use game_ai::Vector2D;
 use game_state_machine::*;
 
-pub static COMFORT_LEVEL: i32 = 5; // the amount of gold a miner must have before he feels comfortable
-pub static MAX_NUGGETS: i32 = 3; // the amount of nuggets a miner can carry
-pub static THIRST_LEVEL: i32 = 5; // above this value a miner is thirsty
-pub static TIREDNESS_THRESHOLD: i32 = 5; // above this value a miner is sleepy
-
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Location {
     Goldmine,
@@ -15,12 +16,15 @@ pub enum Location {
 }
 
 pub struct Miner {
+    base: EntityBase,
     pub name: String,
     pub location: Location,
     gold: i32,
     bank: i32,
     thirst: i32,
     fatigue: i32,
+    decisions: Decisions,
+    config: Config,
 }
 
 impl<'a> Named<'a> for Miner {
@@ -29,15 +33,53 @@ impl<'a> Named<'a> for Miner {
     }
 }
 
+impl BaseGameEntity for Miner {
+    fn base(&self) -> &EntityBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut EntityBase {
+        &mut self.base
+    }
+}
+
 impl Miner {
     pub fn new(name: String) -> Self {
         Miner {
+            base: EntityBase::new(EntityType::Miner as i32, Vector2D::zero(), 0.0),
             name,
             location: Location::Shack,
             gold: 0,
             bank: 0,
             thirst: 0,
             fatigue: 0,
+            decisions: Decisions::Crisp,
+            config: Config::default(),
+        }
+    }
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
+    pub fn with_decisions(mut self, decisions: Decisions) -> Self {
+        self.decisions = decisions;
+        self
+    }
+    pub fn decides_fuzzily(&self) -> bool {
+        matches!(self.decisions, Decisions::Fuzzy(_))
+    }
+    /// The activity the miner would most like to be doing, or `None` if he
+    /// decides using the crisp thresholds instead.
+    pub fn fuzzy_choice(&mut self) -> Option<Activity> {
+        let needs = Needs {
+            thirst: self.thirst,
+            fatigue: self.fatigue,
+            gold: self.gold,
+            wealth: self.bank,
+        };
+        match &mut self.decisions {
+            Decisions::Crisp => None,
+            Decisions::Fuzzy(module) => Some(decisions::most_desirable(module, &needs)),
         }
     }
     pub fn add_to_gold_carried(&mut self, gold: i32) {
@@ -53,13 +95,13 @@ impl Miner {
         self.fatigue -= 1;
     }
     pub fn pockets_full(&self) -> bool {
-        self.gold >= MAX_NUGGETS
+        self.gold >= self.config.max_nuggets
     }
     pub fn increase_thirst(&mut self) {
         self.thirst += 1;
     }
     pub fn thirsty(&self) -> bool {
-        self.thirst > THIRST_LEVEL
+        self.thirst > self.config.thirst_level
     }
     pub fn buy_and_drink_whiskey(&mut self) {
         self.bank -= 2;
@@ -72,12 +114,40 @@ impl Miner {
     pub fn wealth(&self) -> i32 {
         self.bank
     }
+    pub fn comfortable(&self) -> bool {
+        self.bank >= self.config.comfort_level
+    }
     pub fn fatigued(&self) -> bool {
-        self.fatigue > TIREDNESS_THRESHOLD
+        self.fatigue > self.config.tiredness_threshold
     }
     pub fn log(&self, msg: String) {
         ConsoleLog.log(self, msg);
     }
+    /// Says how he ended up, once the simulation is over.
+    pub fn report(&self) {
+        self.log(format!(
+            "Callin' it a day with {} gold in the bank and {} in mah pockets",
+            self.bank, self.gold
+        ));
+    }
+}
+
+fn state_for(activity: Activity) -> Box<dyn State<Miner>> {
+    match activity {
+        Activity::Mine => traced(EnterMineAndDigForNugget),
+        Activity::Bank => traced(VisitBankAndDepositGold),
+        Activity::Sleep => traced(GoHomeAndSleepTilRested),
+        Activity::Drink => traced(QuenchThirst),
+    }
+}
+
+/// Switches to the state for `next`, unless that's the `current` one.
+fn switch_to(next: Activity, current: Activity) -> StateTransition<Miner> {
+    if next == current {
+        StateTransition::None
+    } else {
+        StateTransition::Switch(state_for(next))
+    }
 }
 
 pub struct EnterMineAndDigForNugget;
@@ -101,13 +171,13 @@ impl State<Miner> for EnterMineAndDigForNugget {
 
         miner.log(format!("Pickin' up a nugget"));
 
-        if miner.pockets_full() {
-            StateTransition::Switch(Box::new(VisitBankAndDepositGold))
-        } else if miner.thirsty() {
-            StateTransition::Switch(Box::new(QuenchThirst))
-        } else {
-            StateTransition::None
-        }
+        let next = match miner.fuzzy_choice() {
+            Some(activity) => activity,
+            None if miner.pockets_full() => Activity::Bank,
+            None if miner.thirsty() => Activity::Drink,
+            None => Activity::Mine,
+        };
+        switch_to(next, Activity::Mine)
     }
 
     fn on_stop(&mut self, miner: &mut Miner) {
@@ -139,14 +209,17 @@ impl State<Miner> for VisitBankAndDepositGold {
             miner.wealth()
         ));
 
-        if miner.wealth() >= COMFORT_LEVEL {
+        let next = match miner.fuzzy_choice() {
+            Some(activity) => activity,
+            None if miner.comfortable() => Activity::Sleep,
+            None => Activity::Mine,
+        };
+        if next == Activity::Sleep {
             miner.log(format!(
                 "WooHoo! Rich enough for now. Back home to mah li'lle lady"
             ));
-            StateTransition::Switch(Box::new(GoHomeAndSleepTilRested))
-        } else {
-            StateTransition::Switch(Box::new(EnterMineAndDigForNugget))
         }
+        switch_to(next, Activity::Bank)
     }
 
     fn on_stop(&mut self, miner: &mut Miner) {
@@ -166,11 +239,16 @@ impl State<Miner> for GoHomeAndSleepTilRested {
 
     fn update(&mut self, miner: &mut Miner) -> StateTransition<Miner> {
         miner.increase_thirst();
-        if !miner.fatigued() {
+        let next = match miner.fuzzy_choice() {
+            Some(activity) => activity,
+            None if miner.fatigued() => Activity::Sleep,
+            None => Activity::Mine,
+        };
+        if next != Activity::Sleep {
             miner.log(format!(
                 "What a God darn fantastic nap! Time to find more gold"
             ));
-            StateTransition::Switch(Box::new(EnterMineAndDigForNugget))
+            StateTransition::Switch(state_for(next))
         } else {
             miner.decrease_fatigue();
             miner.log(format!("ZZZZ... "));
@@ -195,10 +273,11 @@ impl State<Miner> for QuenchThirst {
 
     fn update(&mut self, miner: &mut Miner) -> StateTransition<Miner> {
         miner.increase_thirst();
-        if miner.thirsty() {
+        if miner.thirsty() || miner.decides_fuzzily() {
             miner.buy_and_drink_whiskey();
             miner.log(format!("That's mighty fine sippin liquer"));
-            StateTransition::Switch(Box::new(EnterMineAndDigForNugget))
+            let next = miner.fuzzy_choice().unwrap_or(Activity::Mine);
+            switch_to(next, Activity::Drink)
         } else {
             println!("ERROR!\nERROR!\nERROR!");
             StateTransition::Quit
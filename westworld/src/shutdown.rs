@@ -0,0 +1,26 @@
+//! Stopping cleanly on Ctrl-C, or SIGTERM on unix. The handler only raises a
+//! flag; the main loop finishes the tick it's on, then stops every state
+//! machine so the states still on the stack get their `on_stop`.
+
+use std::sync::atomic::{AtomicBool, Ordering};
+use std::sync::Arc;
+
+#[derive(Clone)]
+pub struct Shutdown(Arc<AtomicBool>);
+
+impl Shutdown {
+    /// Installs the signal handlers.
+    pub fn install() -> Self {
+        let flag = Arc::new(AtomicBool::new(false));
+        signal_hook::flag::register(signal_hook::consts::SIGINT, Arc::clone(&flag))
+            .expect("couldn't install the Ctrl-C handler");
+        #[cfg(unix)]
+        signal_hook::flag::register(signal_hook::consts::SIGTERM, Arc::clone(&flag))
+            .expect("couldn't install the SIGTERM handler");
+        Shutdown(flag)
+    }
+
+    pub fn requested(&self) -> bool {
+        self.0.load(Ordering::Relaxed)
+    }
+}
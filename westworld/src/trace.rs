@@ -0,0 +1,86 @@
+//! Each of an agent's states as a tracing span, open for as long as it's on
+//! the stack and entered whenever it runs, with its transitions as events;
+//! and `--trace=FILE`, writing them out as a Chrome trace.
+
+use crate::log::Named;
+use game_ai::trace::{self, FlushGuard, STATE};
+use game_state_machine::{State, StateTransition};
+use std::path::Path;
+use tracing::{debug, debug_span, Span};
+use tracing_subscriber::prelude::*;
+
+/// Installs the trace writing to `path`, if there's a path. Keep the guard
+/// until the run's over, or the file won't be finished.
+pub fn install(path: Option<&Path>) -> Option<FlushGuard> {
+    let (layer, guard) = trace::chrome_layer(path?);
+    tracing_subscriber::registry().with(layer).init();
+    Some(guard)
+}
+
+/// A state with a span named for it, opened when it starts and closed when it
+/// stops.
+pub struct Traced<T> {
+    name: &'static str,
+    state: Box<dyn State<T>>,
+    span: Span,
+}
+
+/// Boxes `state` to be traced, named for its type.
+pub fn traced<T, S>(state: S) -> Box<dyn State<T>>
+where
+    T: for<'a> Named<'a> + 'static,
+    S: State<T> + 'static,
+{
+    let name = std::any::type_name::<S>();
+    Box::new(Traced {
+        name: name.rsplit("::").next().unwrap_or(name),
+        state: Box::new(state),
+        span: Span::none(),
+    })
+}
+
+impl<T: for<'a> Named<'a>> State<T> for Traced<T> {
+    fn on_start(&mut self, data: &mut T) {
+        self.span = debug_span!(STATE, agent = data.name(), state = self.name);
+        let _running = self.span.enter();
+        debug!("started");
+        self.state.on_start(data);
+    }
+
+    fn on_stop(&mut self, data: &mut T) {
+        {
+            let _running = self.span.enter();
+            self.state.on_stop(data);
+            debug!("stopped");
+        }
+        self.span = Span::none();
+    }
+
+    fn on_pause(&mut self, data: &mut T) {
+        let _running = self.span.enter();
+        debug!("paused");
+        self.state.on_pause(data);
+    }
+
+    fn on_resume(&mut self, data: &mut T) {
+        let _running = self.span.enter();
+        debug!("resumed");
+        self.state.on_resume(data);
+    }
+
+    fn update(&mut self, data: &mut T) -> StateTransition<T> {
+        let _running = self.span.enter();
+        let transition = self.state.update(data);
+        let kind = match &transition {
+            StateTransition::None => None,
+            StateTransition::Pop => Some("pop"),
+            StateTransition::Push(_) => Some("push"),
+            StateTransition::Switch(_) => Some("switch"),
+            StateTransition::Quit => Some("quit"),
+        };
+        if let Some(kind) = kind {
+            debug!(transition = kind, "transition");
+        }
+        transition
+    }
+}
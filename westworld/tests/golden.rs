@@ -0,0 +1,81 @@
+//! Golden tests for Bob's day: each run is stopped after so many ticks, with
+//! what he says written out through the log's transcript and compared against
+//! a transcript in `tests/golden`, so the classic script, and the fuzzy and
+//! authored Bobs that follow it, can only change on purpose.
+//!
+//! After an intended change, rewrite the transcripts with
+//! `UPDATE_GOLDEN=1 cargo test -p westworld --test golden` and review the
+//! diff.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.txt", name))
+}
+
+/// Runs Bob flat out with `args`, returning its transcript.
+fn transcript(name: &str, args: &[&str]) -> String {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.txt", name));
+    let status = Command::new(env!("CARGO_BIN_EXE_westworld"))
+        .args(args)
+        .arg("--fast")
+        .arg(format!("--transcript={}", path.display()))
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "{} exited with {}", name, status);
+    fs::read_to_string(&path).unwrap()
+}
+
+fn assert_golden(name: &str, args: &[&str]) {
+    let transcript = transcript(name, args);
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, transcript).unwrap();
+        return;
+    }
+
+    let golden = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1)", path.display(), e));
+    if let Some((line, (said, expected))) = transcript
+        .lines()
+        .zip(golden.lines())
+        .enumerate()
+        .find(|(_, (said, expected))| said != expected)
+    {
+        panic!(
+            "{} strayed from its golden transcript at line {}:\n  said     {:?}\n  expected {:?}",
+            name,
+            line + 1,
+            said,
+            expected
+        );
+    }
+    assert_eq!(
+        transcript.lines().count(),
+        golden.lines().count(),
+        "{} said a different amount from its golden transcript",
+        name
+    );
+}
+
+#[test]
+fn crisp_bob() {
+    assert_golden("crisp_bob", &["--ticks=200"]);
+}
+
+#[test]
+fn fuzzy_bob() {
+    assert_golden("fuzzy_bob", &["--decisions=fuzzy", "--ticks=200"]);
+}
+
+#[test]
+fn authored_bob() {
+    let fsm = concat!(env!("CARGO_MANIFEST_DIR"), "/fsms/miner.ron");
+    assert_golden("authored_bob", &[&format!("--fsm={}", fsm), "--ticks=200"]);
+}
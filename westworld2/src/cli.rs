@@ -0,0 +1,65 @@
+//! The command line, with `--help` saying what's on it.
+
+use clap::{Parser, ValueEnum};
+use game_ai::config::{Config, ConfigError};
+use std::path::PathBuf;
+
+/// Westworld's miners and their wives, from the book's second chapter.
+#[derive(Parser, Clone, Debug, PartialEq)]
+#[command(version)]
+pub struct Cli {
+    /// How many miners there are.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub miners: usize,
+    /// How many miners' wives there are.
+    #[arg(long, value_name = "N", default_value_t = 1)]
+    pub partners: usize,
+    /// Seeds the run, a random seed without it.
+    #[arg(long, value_name = "N")]
+    pub seed: Option<u64>,
+    /// The tuning, which any fields left out of keep their defaults.
+    #[arg(long, value_name = "FILE")]
+    pub config: Option<PathBuf>,
+
+    /// Milliseconds between ticks, the config's tick_interval_ms by default.
+    #[arg(long, value_name = "MS")]
+    pub tick_ms: Option<u64>,
+    /// Stops after this many ticks.
+    #[arg(long, value_name = "N")]
+    pub ticks: Option<u64>,
+    /// Runs flat out, with no keyboard controls.
+    #[arg(long)]
+    pub fast: bool,
+    /// No keyboard controls, at the usual pace.
+    #[arg(long)]
+    pub headless: bool,
+    /// How what everyone says is written.
+    #[arg(long, value_enum, value_name = "FORMAT", default_value_t = LogFormat::Plain)]
+    pub log_format: LogFormat,
+    /// Writes everyone's states to FILE as a Chrome trace, to open in Perfetto.
+    #[arg(long, value_name = "FILE")]
+    pub trace: Option<PathBuf>,
+    /// Writes what everyone says to FILE too, plain with a blank line after
+    /// each tick, as the golden tests compare.
+    #[arg(long, value_name = "FILE")]
+    pub transcript: Option<PathBuf>,
+}
+
+impl Cli {
+    /// The `--config` file, or the defaults without one.
+    pub fn config(&self) -> Result<Config, ConfigError> {
+        match &self.config {
+            Some(path) => Config::load(path),
+            None => Ok(Config::default()),
+        }
+    }
+}
+
+#[derive(ValueEnum, Copy, Clone, Debug, Default, PartialEq)]
+pub enum LogFormat {
+    /// `Name: what they said`, a line each, and a blank line between ticks.
+    #[default]
+    Plain,
+    /// A JSON object a line, with the agent and the message.
+    Json,
+}
@@ -1,4 +1,8 @@
-use std::io::Write;
+use crate::cli::LogFormat;
+use std::fs::File;
+use std::io::{self, BufWriter, Write};
+use std::path::Path;
+use std::sync::{Mutex, OnceLock};
 
 pub trait Named<'a> {
     fn name(&'a self) -> &'a str;
@@ -8,10 +12,62 @@ pub trait Log {
     fn log<'a, N: Named<'a>>(&self, named: &'a N, msg: String);
 }
 
+/// How [`ConsoleLog`] writes each line, from `--log-format`.
+static FORMAT: OnceLock<LogFormat> = OnceLock::new();
+
+/// Sets how everything's written, before anyone's said anything.
+pub fn set_format(format: LogFormat) {
+    FORMAT.set(format).expect("the log format's already set");
+}
+
+/// Where [`ConsoleLog`] also writes each line, from `--transcript`.
+static TRANSCRIPT: OnceLock<Mutex<BufWriter<File>>> = OnceLock::new();
+
+/// Writes everything said to `path` as well, plain whatever the format, for
+/// runs to be compared line by line.
+pub fn set_transcript(path: &Path) -> io::Result<()> {
+    let file = BufWriter::new(File::create(path)?);
+    if TRANSCRIPT.set(Mutex::new(file)).is_err() {
+        panic!("the transcript's already set");
+    }
+    Ok(())
+}
+
+/// Writes `line` to the transcript, if there is one.
+fn transcribe(line: &str) {
+    if let Some(transcript) = TRANSCRIPT.get() {
+        let mut transcript = transcript.lock().unwrap();
+        writeln!(transcript, "{}", line).expect("could not write the transcript");
+    }
+}
+
+/// Marks the end of a tick in the transcript, with a blank line.
+pub fn end_tick() {
+    transcribe("");
+}
+
+/// Finishes writing the transcript, if there is one.
+pub fn finish_transcript() {
+    if let Some(transcript) = TRANSCRIPT.get() {
+        transcript
+            .lock()
+            .unwrap()
+            .flush()
+            .expect("could not write the transcript");
+    }
+}
+
 pub struct ConsoleLog;
 
 impl Log for ConsoleLog {
     fn log<'a, N: Named<'a>>(&self, named: &'a N, msg: String) {
-        println!("{}: {}", named.name(), msg);
+        transcribe(&format!("{}: {}", named.name(), msg));
+        match FORMAT.get().copied().unwrap_or_default() {
+            LogFormat::Plain => println!("{}: {}", named.name(), msg),
+            LogFormat::Json => println!(
+                "{}",
+                serde_json::json!({ "agent": named.name(), "message": msg })
+            ),
+        }
     }
 }
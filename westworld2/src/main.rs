@@ -1,45 +1,128 @@
+use clap::Parser;
 use game_state_machine::StateMachine;
-use std::io::{stdin, stdout, Read, Write};
-use std::thread;
-use std::time::Duration;
+use std::io::{stdout, Write};
 
+mod cli;
+mod controls;
+mod entity_type;
 mod location;
 mod log;
 mod miner;
 mod partner;
+mod rng;
+mod shutdown;
+mod trace;
 
+use cli::{Cli, LogFormat};
+use controls::{Controls, Pace};
 use miner::{GoHomeAndSleepTilRested, Miner};
 use partner::{DoHouseWork, Partner};
+use rand::SeedableRng;
+use rng::WorldRng;
+use shutdown::Shutdown;
+use trace::traced;
+
+static MINER_NAMES: &[&str] = &["Bob", "Jake", "Ezra", "Hank", "Cletus", "Amos"];
+static PARTNER_NAMES: &[&str] = &["Elsa", "Martha", "Clara", "Ida", "Mabel", "Ruth"];
+
+/// Names are used in order, then numbered once they run out.
+fn generated_name(names: &[&str], i: usize) -> String {
+    let name = names[i % names.len()];
+    match i / names.len() {
+        0 => name.to_string(),
+        n => format!("{} {}", name, n + 1),
+    }
+}
 
 fn main() {
-    let mut sm = StateMachine::<Miner>::default();
-    let mut miner = Miner::new("Miner Bob".into());
-    sm.push(Box::new(GoHomeAndSleepTilRested), &mut miner);
+    let cli = Cli::parse();
+    log::set_format(cli.log_format);
+    if let Some(path) = &cli.transcript {
+        log::set_transcript(path)
+            .unwrap_or_else(|e| panic!("could not write {}: {}", path.display(), e));
+    }
+    // kept until the end, to finish the file
+    let _trace = trace::install(cli.trace.as_deref());
+    let config = cli.config().unwrap_or_else(|e| panic!("{}", e));
+    // every partner gets her own generator, drawn from the one seed in turn
+    let mut rng = rng::from_cli(&cli);
+
+    let mut miners: Vec<_> = (0..cli.miners)
+        .map(|i| {
+            let mut miner = Miner::new(format!("Miner {}", generated_name(MINER_NAMES, i)))
+                .with_config(config.clone());
+            let mut sm = StateMachine::<Miner>::default();
+            sm.push(traced(GoHomeAndSleepTilRested), &mut miner);
+            (sm, miner)
+        })
+        .collect();
+
+    let mut partners: Vec<_> = (0..cli.partners)
+        .map(|i| {
+            let partner_rng = WorldRng::from_rng(&mut rng).unwrap();
+            let mut partner = Partner::new(generated_name(PARTNER_NAMES, i), partner_rng);
+            let mut sm = StateMachine::<Partner>::default();
+            sm.push(traced(DoHouseWork), &mut partner);
+            (sm, partner)
+        })
+        .collect();
 
-    let mut sm2 = StateMachine::<Partner>::default();
-    let mut partner = Partner::new("Elsa".into());
-    sm2.push(Box::new(DoHouseWork), &mut partner);
+    let shutdown = Shutdown::install();
+    let pace = Pace::from_cli(&config, &cli);
+    let mut controls = if pace.fast || cli.headless {
+        None
+    } else {
+        println!("{}", controls::HELP);
+        Some(Controls::from_stdin(shutdown.clone()))
+    };
 
-    while sm.is_running() || sm2.is_running() {
-        if sm.is_running() {
-            sm.update(&mut miner);
+    let mut tick = 0;
+    let mut quit = false;
+    while (miners.iter().any(|(sm, _)| sm.is_running())
+        || partners.iter().any(|(sm, _)| sm.is_running()))
+        && !pace.finished(tick)
+        && !quit
+        && !shutdown.requested()
+    {
+        for (sm, miner) in miners.iter_mut().filter(|(sm, _)| sm.is_running()) {
+            sm.update(miner);
         }
 
-        if sm2.is_running() {
-            sm2.update(&mut partner);
+        for (sm, partner) in partners.iter_mut().filter(|(sm, _)| sm.is_running()) {
+            sm.update(partner);
         }
 
-        println!("");
+        if cli.log_format == LogFormat::Plain {
+            println!();
+        }
+        log::end_tick();
+        tick += 1;
 
-        thread::sleep(Duration::from_millis(800));
+        if let Some(controls) = controls.as_mut() {
+            quit = !controls.wait(pace.interval);
+        }
     }
 
-    pause();
+    // wind down whatever everyone is in the middle of
+    for (sm, miner) in miners.iter_mut() {
+        sm.stop(miner);
+        miner.report();
+    }
+    for (sm, partner) in partners.iter_mut() {
+        sm.stop(partner);
+    }
+    log::finish_transcript();
+
+    if let Some(controls) = controls {
+        if !quit && !shutdown.requested() {
+            pause(&controls);
+        }
+    }
 }
 
-fn pause() {
+fn pause(controls: &Controls) {
     let mut stdout = stdout();
-    stdout.write(b"Press Enter to continue...").unwrap();
+    stdout.write_all(b"Press Enter to continue...").unwrap();
     stdout.flush().unwrap();
-    stdin().read(&mut [0]).unwrap();
+    controls.wait_for_enter();
 }
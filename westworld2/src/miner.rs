@@ -1,11 +1,11 @@
+use crate::entity_type::EntityType;
 use crate::log::{ConsoleLog, Log, Named};
+use crate::trace::traced;
+use game_ai::config::Config;
+use game_ai::entity::{BaseGameEntity, EntityBase};
+use game_ai::Vector2D;
 use game_state_machine::*;
 
-pub static COMFORT_LEVEL: i32 = 5; // the amount of gold a miner must have before he feels comfortable
-pub static MAX_NUGGETS: i32 = 3; // the amount of nuggets a miner can carry
-pub static THIRST_LEVEL: i32 = 5; // above this value a miner is thirsty
-pub static TIREDNESS_THRESHOLD: i32 = 5; // above this value a miner is sleepy
-
 #[derive(Copy, Clone, Debug, PartialEq)]
 pub enum Location {
     Goldmine,
@@ -15,12 +15,14 @@ pub enum Location {
 }
 
 pub struct Miner {
+    base: EntityBase,
     pub name: String,
     pub location: Location,
     gold: i32,
     bank: i32,
     thirst: i32,
     fatigue: i32,
+    config: Config,
 }
 
 impl<'a> Named<'a> for Miner {
@@ -29,17 +31,33 @@ impl<'a> Named<'a> for Miner {
     }
 }
 
+impl BaseGameEntity for Miner {
+    fn base(&self) -> &EntityBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut EntityBase {
+        &mut self.base
+    }
+}
+
 impl Miner {
     pub fn new(name: String) -> Self {
         Miner {
+            base: EntityBase::new(EntityType::Miner as i32, Vector2D::zero(), 0.0),
             name,
             location: Location::Shack,
             gold: 0,
             bank: 0,
             thirst: 0,
             fatigue: 0,
+            config: Config::default(),
         }
     }
+    pub fn with_config(mut self, config: Config) -> Self {
+        self.config = config;
+        self
+    }
     pub fn add_to_gold_carried(&mut self, gold: i32) {
         self.gold += gold;
         if self.gold < 0 {
@@ -53,13 +71,13 @@ impl Miner {
         self.fatigue -= 1;
     }
     pub fn pockets_full(&self) -> bool {
-        self.gold >= MAX_NUGGETS
+        self.gold >= self.config.max_nuggets
     }
     pub fn increase_thirst(&mut self) {
         self.thirst += 1;
     }
     pub fn thirsty(&self) -> bool {
-        self.thirst > THIRST_LEVEL
+        self.thirst > self.config.thirst_level
     }
     pub fn buy_and_drink_whiskey(&mut self) {
         self.bank -= 2;
@@ -72,12 +90,22 @@ impl Miner {
     pub fn wealth(&self) -> i32 {
         self.bank
     }
+    pub fn comfortable(&self) -> bool {
+        self.bank >= self.config.comfort_level
+    }
     pub fn fatigued(&self) -> bool {
-        self.fatigue > TIREDNESS_THRESHOLD
+        self.fatigue > self.config.tiredness_threshold
     }
     pub fn log(&self, msg: String) {
         ConsoleLog.log(self, msg);
     }
+    /// Says how he ended up, once the simulation is over.
+    pub fn report(&self) {
+        self.log(format!(
+            "Callin' it a day with {} gold in the bank and {} in mah pockets",
+            self.bank, self.gold
+        ));
+    }
 }
 
 pub struct EnterMineAndDigForNugget;
@@ -102,9 +130,9 @@ impl State<Miner> for EnterMineAndDigForNugget {
         miner.log(format!("Pickin' up a nugget"));
 
         if miner.pockets_full() {
-            StateTransition::Switch(Box::new(VisitBankAndDepositGold))
+            StateTransition::Switch(traced(VisitBankAndDepositGold))
         } else if miner.thirsty() {
-            StateTransition::Switch(Box::new(QuenchThirst))
+            StateTransition::Switch(traced(QuenchThirst))
         } else {
             StateTransition::None
         }
@@ -139,13 +167,13 @@ impl State<Miner> for VisitBankAndDepositGold {
             miner.wealth()
         ));
 
-        if miner.wealth() >= COMFORT_LEVEL {
+        if miner.comfortable() {
             miner.log(format!(
                 "WooHoo! Rich enough for now. Back home to mah li'lle lady"
             ));
-            StateTransition::Switch(Box::new(GoHomeAndSleepTilRested))
+            StateTransition::Switch(traced(GoHomeAndSleepTilRested))
         } else {
-            StateTransition::Switch(Box::new(EnterMineAndDigForNugget))
+            StateTransition::Switch(traced(EnterMineAndDigForNugget))
         }
     }
 
@@ -170,7 +198,7 @@ impl State<Miner> for GoHomeAndSleepTilRested {
             miner.log(format!(
                 "What a God darn fantastic nap! Time to find more gold"
             ));
-            StateTransition::Switch(Box::new(EnterMineAndDigForNugget))
+            StateTransition::Switch(traced(EnterMineAndDigForNugget))
         } else {
             miner.decrease_fatigue();
             miner.log(format!("ZZZZ... "));
@@ -198,7 +226,7 @@ impl State<Miner> for QuenchThirst {
         if miner.thirsty() {
             miner.buy_and_drink_whiskey();
             miner.log(format!("That's mighty fine sippin liquer"));
-            StateTransition::Switch(Box::new(EnterMineAndDigForNugget))
+            StateTransition::Switch(traced(EnterMineAndDigForNugget))
         } else {
             println!("ERROR!\nERROR!\nERROR!");
             StateTransition::Quit
@@ -1,9 +1,15 @@
+use crate::rng::WorldRng;
+use crate::trace::traced;
 use crate::{
+    entity_type::EntityType,
     location::Location,
     log::{ConsoleLog, Log, Named},
 };
+use game_ai::entity::{BaseGameEntity, EntityBase};
+use game_ai::Vector2D;
 use game_state_machine::*;
 use rand::distributions::{Distribution, Standard};
+use rand::Rng;
 
 enum PartnerChore {
     Mopping,
@@ -23,8 +29,10 @@ impl Distribution<PartnerChore> for Standard {
 }
 
 pub struct Partner {
+    base: EntityBase,
     pub name: String,
     location: Location,
+    rng: WorldRng,
 }
 
 impl<'a> Named<'a> for Partner {
@@ -33,11 +41,25 @@ impl<'a> Named<'a> for Partner {
     }
 }
 
+impl BaseGameEntity for Partner {
+    fn base(&self) -> &EntityBase {
+        &self.base
+    }
+
+    fn base_mut(&mut self) -> &mut EntityBase {
+        &mut self.base
+    }
+}
+
 impl Partner {
-    pub fn new(name: String) -> Self {
+    /// Her chores are chosen with `rng`; derive it from the run's seed to
+    /// replay her day exactly.
+    pub fn new(name: String, rng: WorldRng) -> Self {
         Partner {
+            base: EntityBase::new(EntityType::Partner as i32, Vector2D::zero(), 0.0),
             name,
             location: Location::Shack,
+            rng,
         }
     }
 
@@ -50,11 +72,11 @@ pub struct DoHouseWork;
 
 impl State<Partner> for DoHouseWork {
     fn update(&mut self, partner: &mut Partner) -> StateTransition<Partner> {
-        if rand::random::<f32>() < 0.1 {
-            return StateTransition::Push(Box::new(VisitBathroom));
+        if partner.rng.gen::<f32>() < 0.1 {
+            return StateTransition::Push(traced(VisitBathroom));
         }
 
-        match rand::random() {
+        match partner.rng.gen() {
             PartnerChore::Mopping => {
                 partner.log(format!("Moppin' the floor"));
             }
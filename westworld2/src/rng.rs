@@ -0,0 +1,19 @@
+use crate::cli::{Cli, LogFormat};
+use rand::SeedableRng;
+use rand_chacha::ChaCha8Rng;
+
+/// Where every random decision in the simulation comes from, so that a run
+/// can be replayed exactly from its seed.
+pub type WorldRng = ChaCha8Rng;
+
+/// Seeds the run from `--seed=N`, or picks a random seed without one. The
+/// seed is printed so any run can be replayed, on stderr if stdout's JSON.
+pub fn from_cli(cli: &Cli) -> WorldRng {
+    let seed = cli.seed.unwrap_or_else(rand::random);
+    let announcement = format!("Random seed {} (replay with --seed={})", seed, seed);
+    match cli.log_format {
+        LogFormat::Plain => println!("{}", announcement),
+        LogFormat::Json => eprintln!("{}", announcement),
+    }
+    WorldRng::seed_from_u64(seed)
+}
@@ -0,0 +1,78 @@
+//! Golden tests for the town: each run is seeded and stopped after so many
+//! ticks, with what everyone says written out through the log's transcript
+//! and compared against a transcript in `tests/golden`, so the Bob and Elsa
+//! script can only change on purpose.
+//!
+//! After an intended change, rewrite the transcripts with
+//! `UPDATE_GOLDEN=1 cargo test -p westworld2 --test golden` and review the
+//! diff.
+
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process::{Command, Stdio};
+
+fn golden_path(name: &str) -> PathBuf {
+    PathBuf::from(env!("CARGO_MANIFEST_DIR"))
+        .join("tests/golden")
+        .join(format!("{}.txt", name))
+}
+
+/// Runs the town flat out with `args`, returning its transcript.
+fn transcript(name: &str, args: &[&str]) -> String {
+    let path = PathBuf::from(env!("CARGO_TARGET_TMPDIR")).join(format!("{}.txt", name));
+    let status = Command::new(env!("CARGO_BIN_EXE_westworld2"))
+        .args(args)
+        .arg("--fast")
+        .arg(format!("--transcript={}", path.display()))
+        .stdout(Stdio::null())
+        .status()
+        .unwrap();
+    assert!(status.success(), "{} exited with {}", name, status);
+    fs::read_to_string(&path).unwrap()
+}
+
+fn assert_golden(name: &str, args: &[&str]) {
+    let transcript = transcript(name, args);
+    let path = golden_path(name);
+    if env::var_os("UPDATE_GOLDEN").is_some() {
+        fs::write(&path, transcript).unwrap();
+        return;
+    }
+
+    let golden = fs::read_to_string(&path)
+        .unwrap_or_else(|e| panic!("{}: {} (run with UPDATE_GOLDEN=1)", path.display(), e));
+    if let Some((line, (said, expected))) = transcript
+        .lines()
+        .zip(golden.lines())
+        .enumerate()
+        .find(|(_, (said, expected))| said != expected)
+    {
+        panic!(
+            "{} strayed from its golden transcript at line {}:\n  said     {:?}\n  expected {:?}",
+            name,
+            line + 1,
+            said,
+            expected
+        );
+    }
+    assert_eq!(
+        transcript.lines().count(),
+        golden.lines().count(),
+        "{} said a different amount from its golden transcript",
+        name
+    );
+}
+
+#[test]
+fn bob_and_elsa() {
+    assert_golden("bob_and_elsa", &["--seed=7", "--ticks=200"]);
+}
+
+#[test]
+fn a_crowded_town() {
+    assert_golden(
+        "a_crowded_town",
+        &["--miners=3", "--partners=2", "--seed=7", "--ticks=100"],
+    );
+}